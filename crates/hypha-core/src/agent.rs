@@ -5,6 +5,13 @@ pub enum Capability {
     Compute(u32),
     Storage(u64),
     Sensing(String),
+    /// Membership in the named network, presented as a join invitation.
+    Join(String),
+    /// Raising spikes up to this intensity.
+    Alert(u8),
+    /// Steering the mesh: publishing on topics an ACL reserves for
+    /// coordinators, such as mesh control.
+    Coordinator,
 }
 
 impl Capability {
@@ -13,17 +20,89 @@ impl Capability {
             (Self::Compute(available), Self::Compute(required)) => available >= required,
             (Self::Storage(available), Self::Storage(required)) => available >= required,
             (Self::Sensing(available), Self::Sensing(required)) => available == required,
+            (Self::Join(network), Self::Join(required)) => network == required,
+            (Self::Alert(available), Self::Alert(required)) => available >= required,
+            (Self::Coordinator, Self::Coordinator) => true,
             _ => false,
         }
     }
 }
 
+/// What a node is deployed to do. Set at startup, it picks role-specific
+/// defaults and is advertised in [`EnergyStatus`] so peers can weigh it when
+/// forming meshes and routing tasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeRole {
+    /// Reports readings; joins few topics and rarely relays.
+    Sensor,
+    /// Forwards traffic for others; keeps a wide mesh.
+    Relay,
+    /// Bids for and runs tasks.
+    Compute,
+    /// Steers the mesh and tracks the whole swarm.
+    Coordinator,
+}
+
+/// Where a node is in its lifecycle, as advertised in [`EnergyStatus`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeState {
+    /// Looking for its first peer.
+    #[default]
+    Bootstrapping,
+    /// Connected, but not yet in a mesh.
+    Joining,
+    Active,
+    /// In a mesh on a reduced energy rung.
+    Degraded,
+    /// Nearly empty: joined to spikes alone.
+    Hibernating,
+    /// Announced its departure ahead of running out of energy.
+    Departed,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnergyStatus {
     pub source_id: String,
     pub energy_score: f32,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub facts: Option<EnergyFacts>,
+    /// Gossip topics the sender is currently subscribed to. Empty when the
+    /// sender does not advertise its subscription policy.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub topics: Vec<String>,
+    /// Encoded delegations the sender presents to satisfy topic ACLs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub grants: Vec<String>,
+    /// Left out by nodes started without a role.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<NodeRole>,
+    /// Hardware attestation of the sender's device, for fleets that gate on
+    /// it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attestation: Option<Attestation>,
+    /// Zone the sender belongs to, in zone-aware meshes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
+    /// Left out by nodes that do not track their lifecycle.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<NodeState>,
+    /// Version of the sender's capability and sensor catalog.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub catalog_version: Option<u64>,
+    /// Sender's health score, as a percentage.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<u8>,
+}
+
+/// Evidence from a device's secure hardware, such as a TPM or secure-element
+/// quote. Its meaning is up to the operator's verifier.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attestation {
+    /// Evidence format, e.g. `tpm2-quote`.
+    pub format: String,
+    pub evidence: Vec<u8>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
@@ -44,6 +123,14 @@ impl EnergyStatus {
             source_id,
             energy_score,
             facts: None,
+            topics: Vec::new(),
+            grants: Vec::new(),
+            role: None,
+            attestation: None,
+            zone: None,
+            state: None,
+            catalog_version: None,
+            health: None,
         }
     }
 
@@ -51,6 +138,31 @@ impl EnergyStatus {
         self.facts = Some(facts);
         self
     }
+
+    pub fn with_topics(mut self, topics: Vec<String>) -> Self {
+        self.topics = topics;
+        self
+    }
+
+    pub fn with_grants(mut self, grants: Vec<String>) -> Self {
+        self.grants = grants;
+        self
+    }
+
+    pub fn with_role(mut self, role: NodeRole) -> Self {
+        self.role = Some(role);
+        self
+    }
+
+    pub fn with_attestation(mut self, attestation: Attestation) -> Self {
+        self.attestation = Some(attestation);
+        self
+    }
+
+    pub fn with_zone(mut self, zone: String) -> Self {
+        self.zone = Some(zone);
+        self
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -61,6 +173,29 @@ pub struct Task {
     pub reach_intensity: f32,
     pub source_id: String,
     pub auth_token: Option<String>,
+    /// The publisher allows results to travel unencrypted.
+    #[serde(default)]
+    pub public_result: bool,
+    /// Application the task belongs to on a multi-tenant node. `None` is the
+    /// node's own, untenanted scope.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// Hash of the code and input the task runs, for workers that memoize
+    /// results.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo_key: Option<String>,
+    /// Area the task targets, as a geohash prefix. Only nodes located inside
+    /// it bid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geohash: Option<String>,
+    /// Peer the task is addressed to. Addressed tasks skip the auction and
+    /// are delivered to that peer alone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Bytes of output the task is expected to leave on the worker. Nodes
+    /// with a storage quota bid only when it fits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_output_bytes: Option<u64>,
 }
 
 impl Task {
@@ -72,12 +207,42 @@ impl Task {
             reach_intensity: 1.0,
             source_id,
             auth_token: None,
+            public_result: false,
+            tenant: None,
+            memo_key: None,
+            geohash: None,
+            target: None,
+            expected_output_bytes: None,
         }
     }
     pub fn with_auth(mut self, token: String) -> Self {
         self.auth_token = Some(token);
         self
     }
+    pub fn with_public_result(mut self) -> Self {
+        self.public_result = true;
+        self
+    }
+    pub fn with_tenant(mut self, tenant: &str) -> Self {
+        self.tenant = Some(tenant.to_string());
+        self
+    }
+    pub fn with_memo_key(mut self, key: String) -> Self {
+        self.memo_key = Some(key);
+        self
+    }
+    pub fn with_geohash(mut self, area: &str) -> Self {
+        self.geohash = Some(area.to_string());
+        self
+    }
+    pub fn with_target(mut self, peer: &str) -> Self {
+        self.target = Some(peer.to_string());
+        self
+    }
+    pub fn with_expected_output_bytes(mut self, bytes: u64) -> Self {
+        self.expected_output_bytes = Some(bytes);
+        self
+    }
     pub fn diffuse(&self, conductivity: f32, neighbor_energy: f32, neighbor_pressure: f32) -> f32 {
         let pressure_factor = 1.0 - (neighbor_pressure.min(10.0) / 10.0);
         self.reach_intensity
@@ -94,11 +259,59 @@ pub struct Bid {
     pub bidder_id: String,
     pub energy_score: f32,
     pub cost_mah: f32,
+    /// How `cost_mah` was reached, when the bidder discounted it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<BidPrice>,
+}
+
+/// Breakdown of a discounted bid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BidPrice {
+    /// Estimated cost of the work before the discount.
+    pub base_mah: f32,
+    /// Fraction taken off `base_mah` because the bidder is harvesting more
+    /// energy than it uses, in `[0, 1]`.
+    pub surplus_discount: f32,
+}
+
+impl Bid {
+    /// What auctions rank bids by: the energy score, raised by any surplus
+    /// discount so that nodes with energy to spare right now win over nodes
+    /// that merely have a large battery.
+    pub fn merit(&self) -> f32 {
+        let discount = self
+            .price
+            .as_ref()
+            .map_or(0.0, |price| price.surplus_discount);
+        let discount = if discount.is_nan() {
+            0.0
+        } else {
+            discount.clamp(0.0, 1.0)
+        };
+        self.energy_score * (1.0 + discount)
+    }
+}
+
+/// Output of a task, addressed back to its publisher.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskResult {
+    pub task_id: String,
+    pub worker_id: String,
+    pub payload: ResultPayload,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "encoding", content = "data", rename_all = "snake_case")]
+pub enum ResultPayload {
+    /// Readable by anyone; only used when the task opted in.
+    Plain(Vec<u8>),
+    /// Sealed box to the publisher's key.
+    Sealed(Vec<u8>),
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Capability;
+    use super::{Bid, BidPrice, Capability};
 
     #[test]
     fn compute_capacity_satisfies_smaller_requirement() {
@@ -130,10 +343,38 @@ mod tests {
             .satisfies(&Capability::Sensing("temperature".to_string())));
     }
 
+    #[test]
+    fn alert_grant_covers_lower_intensities() {
+        assert!(Capability::Alert(240).satisfies(&Capability::Alert(220)));
+        assert!(!Capability::Alert(220).satisfies(&Capability::Alert(255)));
+    }
+
     #[test]
     fn different_capability_kinds_do_not_satisfy_each_other() {
         assert!(!Capability::Compute(100).satisfies(&Capability::Storage(100)));
         assert!(!Capability::Storage(100).satisfies(&Capability::Compute(100)));
         assert!(!Capability::Sensing("thermal".to_string()).satisfies(&Capability::Compute(1)));
     }
+
+    #[test]
+    fn surplus_discounts_raise_bid_merit() {
+        let mut bid = Bid {
+            task_id: "t".to_string(),
+            bidder_id: "p".to_string(),
+            energy_score: 0.6,
+            cost_mah: 25.0,
+            price: None,
+        };
+        assert_eq!(bid.merit(), 0.6);
+        bid.price = Some(BidPrice {
+            base_mah: 50.0,
+            surplus_discount: 0.5,
+        });
+        assert!((bid.merit() - 0.9).abs() < 1e-6);
+        bid.price = Some(BidPrice {
+            base_mah: 50.0,
+            surplus_discount: f32::NAN,
+        });
+        assert_eq!(bid.merit(), 0.6);
+    }
 }
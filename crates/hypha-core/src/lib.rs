@@ -4,6 +4,13 @@ pub mod agent;
 pub mod metabolism;
 pub mod sensor;
 
-pub use agent::{Bid, Capability, EnergyFacts, EnergyStatus, Task};
-pub use metabolism::{BatteryMetabolism, Metabolism, MockMetabolism, PowerMode};
-pub use sensor::{BasicSensor, VirtualSensor};
+pub use agent::{
+    Attestation, Bid, BidPrice, Capability, EnergyFacts, EnergyStatus, NodeRole, NodeState,
+    ResultPayload, Task, TaskResult,
+};
+pub use metabolism::{
+    BatteryMetabolism, HarvestingMetabolism, Metabolism, MockMetabolism, PowerMode,
+};
+pub use sensor::{
+    BasicSensor, FixedLocation, LocationSensor, ReadingSummary, SensorReading, VirtualSensor,
+};
@@ -6,6 +6,15 @@ pub trait Metabolism: Send + Sync + core::fmt::Debug {
     fn remaining(&self) -> f32;
     fn set_mode(&mut self, mode: PowerMode);
     fn is_mains_powered(&self) -> bool;
+    /// Charge left according to a hardware fuel gauge, if there is one.
+    fn measured_mah(&self) -> Option<f32> {
+        None
+    }
+    /// Fraction to take off the price of work right now, in `[0, 1]`.
+    /// Nodes harvesting more energy than they use bid cheaper.
+    fn surplus_discount(&self) -> f32 {
+        0.0
+    }
     #[cfg(feature = "std")]
     fn as_any(&mut self) -> &mut dyn std::any::Any;
 }
@@ -17,6 +26,20 @@ pub enum PowerMode {
     Critical,
 }
 
+impl PowerMode {
+    /// Classify an energy score into the bands used by mesh and subscription
+    /// policy: critical below 0.2, low battery below 0.5.
+    pub fn from_energy_score(score: f32) -> Self {
+        if score < 0.2 {
+            PowerMode::Critical
+        } else if score < 0.5 {
+            PowerMode::LowBattery
+        } else {
+            PowerMode::Normal
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BatteryMetabolism {
     pub voltage: f32,
@@ -82,6 +105,74 @@ impl Metabolism for BatteryMetabolism {
     }
 }
 
+/// A battery topped up by a harvester such as a solar panel. While the
+/// harvester delivers more than the node draws, energy spent on work would
+/// otherwise go to waste once the battery is full, so the node discounts its
+/// bids in proportion to the surplus.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HarvestingMetabolism {
+    pub battery: BatteryMetabolism,
+    /// Current from the harvester, in mA.
+    pub harvest_ma: f32,
+    /// The node's own average draw, in mA.
+    pub load_ma: f32,
+    /// Discount when all harvested current is surplus.
+    pub max_discount: f32,
+}
+
+impl Default for HarvestingMetabolism {
+    fn default() -> Self {
+        Self {
+            battery: BatteryMetabolism::default(),
+            harvest_ma: 0.0,
+            load_ma: 0.0,
+            max_discount: 0.5,
+        }
+    }
+}
+
+impl HarvestingMetabolism {
+    /// Run the harvester and the node's load for `hours`.
+    pub fn charge(&mut self, hours: f32) {
+        let net = (self.harvest_ma - self.load_ma) * hours;
+        if net < 0.0 {
+            self.battery.consume(-net);
+        } else {
+            self.battery.mah_remaining = (self.battery.mah_remaining + net).min(2500.0);
+            self.battery.voltage = 3.3 + (self.battery.mah_remaining / 2500.0) * 0.9;
+        }
+    }
+}
+
+impl Metabolism for HarvestingMetabolism {
+    fn energy_score(&self) -> f32 {
+        self.battery.energy_score()
+    }
+    fn consume(&mut self, cost: f32) -> bool {
+        self.battery.consume(cost)
+    }
+    fn remaining(&self) -> f32 {
+        self.battery.remaining()
+    }
+    fn set_mode(&mut self, mode: PowerMode) {
+        self.battery.set_mode(mode)
+    }
+    fn is_mains_powered(&self) -> bool {
+        self.battery.is_mains_powered()
+    }
+    fn surplus_discount(&self) -> f32 {
+        if self.harvest_ma <= 0.0 {
+            return 0.0;
+        }
+        let surplus = (self.harvest_ma - self.load_ma).max(0.0) / self.harvest_ma;
+        (self.max_discount * surplus).clamp(0.0, 1.0)
+    }
+    #[cfg(feature = "std")]
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MockMetabolism {
     pub energy: f32,
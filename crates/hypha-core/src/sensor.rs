@@ -1,9 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// One sensor value as it travels between nodes and bridges.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SensorReading {
+    pub source_id: String,
+    pub sensor: String,
+    pub value: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    /// Milliseconds since the Unix epoch, when the source knows it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_ms: Option<u64>,
+    /// Set when `value` is the last of several samples aggregated over a
+    /// window rather than a single raw sample.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<ReadingSummary>,
+}
+
+impl SensorReading {
+    pub fn new(source_id: String, sensor: String, value: f32) -> Self {
+        Self {
+            source_id,
+            sensor,
+            value,
+            unit: None,
+            timestamp_ms: None,
+            summary: None,
+        }
+    }
+}
+
+/// Statistics over the samples behind an aggregated reading.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ReadingSummary {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub count: u32,
+    pub window_ms: u64,
+}
+
 pub trait VirtualSensor: Send + Sync {
     fn name(&self) -> &str;
     fn read(&self) -> f32;
     fn update_from_mesh(&mut self, value: f32);
 }
 
+/// Where the node is, for spatial task routing. Mobile nodes back this with
+/// a GPS receiver; fixed installations with a [`FixedLocation`].
+pub trait LocationSensor: Send + Sync {
+    /// Latitude and longitude in degrees, or `None` without a fix.
+    fn location(&self) -> Option<(f64, f64)>;
+}
+
+/// A node that does not move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedLocation {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl LocationSensor for FixedLocation {
+    fn location(&self) -> Option<(f64, f64)> {
+        Some((self.lat, self.lon))
+    }
+}
+
 pub struct BasicSensor {
     pub name: String,
     pub last_value: f32,
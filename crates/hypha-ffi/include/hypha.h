@@ -0,0 +1,123 @@
+#ifndef HYPHA_H
+#define HYPHA_H
+
+/* Generated by cbindgen from crates/hypha-ffi. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define HYPHA_OK 0
+
+/**
+ * A required pointer argument was null.
+ */
+#define HYPHA_ERR_NULL -1
+
+/**
+ * An argument was not valid UTF-8, JSON, or a multiaddr.
+ */
+#define HYPHA_ERR_INVALID -2
+
+/**
+ * The output buffer is too small. The event stays pending.
+ */
+#define HYPHA_ERR_BUFFER_TOO_SMALL -3
+
+/**
+ * Opaque node handle.
+ */
+typedef struct HyphaNode HyphaNode;
+
+/**
+ * Called with a task's JSON encoding and the registered `user_data`.
+ */
+typedef void (*HyphaTaskCallback)(const char *task_json, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create a node stored at `storage_path` and start its network loop.
+ *
+ * `bootstrap` may be null when `bootstrap_len` is 0. Returns null on failure.
+ *
+ * # Safety
+ * String arguments must be valid NUL-terminated strings; `bootstrap` must
+ * point to `bootstrap_len` of them.
+ */
+HyphaNode *hypha_node_new(const char *storage_path,
+                          const char *listen_addr,
+                          const char *const *bootstrap,
+                          size_t bootstrap_len);
+
+/**
+ * Stop the node and release it. Null is ignored.
+ *
+ * # Safety
+ * `node` must come from `hypha_node_new` and not be used afterwards.
+ */
+void hypha_node_free(HyphaNode *node);
+
+/**
+ * Write the node's peer id into `buf`. Returns its length or an error code.
+ *
+ * # Safety
+ * `node` must be live; `buf` must hold `buf_len` bytes.
+ */
+int32_t hypha_node_peer_id(const HyphaNode *node, char *buf, size_t buf_len);
+
+/**
+ * Report the host-measured energy score (0.0–1.0).
+ *
+ * # Safety
+ * `node` must be live.
+ */
+int32_t hypha_node_set_energy(HyphaNode *node, float energy_score, bool is_mains);
+
+/**
+ * Report a full `EnergyStatus` as JSON. Its score and facts are advertised
+ * on the node's next heartbeat.
+ *
+ * # Safety
+ * `node` must be live; `status_json` must be a NUL-terminated string.
+ */
+int32_t hypha_node_publish_status(HyphaNode *node, const char *status_json);
+
+/**
+ * Queue a task, given as JSON, for the node's next heartbeat.
+ *
+ * # Safety
+ * `node` must be live; `task_json` must be a NUL-terminated string.
+ */
+int32_t hypha_node_publish_task(HyphaNode *node, const char *task_json);
+
+/**
+ * Register `callback` for inbound tasks, or clear it with null.
+ *
+ * The callback runs inside `hypha_node_poll_event` on the polling thread.
+ *
+ * # Safety
+ * `node` must be live; `user_data` must stay valid while registered.
+ */
+int32_t hypha_node_set_task_callback(HyphaNode *node, HyphaTaskCallback callback, void *user_data);
+
+/**
+ * Take the next event as JSON (tagged by `"kind"`) into `buf`.
+ *
+ * Returns the length written, 0 when no event is waiting, or an error code.
+ * On `HYPHA_ERR_BUFFER_TOO_SMALL` the event is kept for the next call.
+ *
+ * # Safety
+ * `node` must be live; `buf` must hold `buf_len` bytes.
+ */
+int32_t hypha_node_poll_event(HyphaNode *node, char *buf, size_t buf_len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* HYPHA_H */
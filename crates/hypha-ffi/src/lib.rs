@@ -0,0 +1,393 @@
+//! Stable C API for embedding a hypha node in non-Rust firmware hosts.
+//!
+//! The host owns power measurement: it reports an energy score (or a whole
+//! `EnergyStatus`) and the node advertises it on its next heartbeat. Events
+//! are pulled with `hypha_node_poll_event`, which also invokes the registered
+//! task callback for inbound tasks, so callbacks always run on the caller's
+//! thread. A `HyphaNode` is not thread-safe; use it from one thread at a time.
+//!
+//! The header lives in `include/hypha.h` and is generated with cbindgen.
+
+use hypha::embed::NodeHandle;
+use hypha::events::NodeEvent;
+use hypha::{EnergyStatus, Metabolism, PowerMode, Task};
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::TryRecvError};
+
+pub const HYPHA_OK: i32 = 0;
+/// A required pointer argument was null.
+pub const HYPHA_ERR_NULL: i32 = -1;
+/// An argument was not valid UTF-8, JSON, or a multiaddr.
+pub const HYPHA_ERR_INVALID: i32 = -2;
+/// The output buffer is too small. The event stays pending.
+pub const HYPHA_ERR_BUFFER_TOO_SMALL: i32 = -3;
+
+/// Called with a task's JSON encoding and the registered `user_data`.
+pub type HyphaTaskCallback = extern "C" fn(task_json: *const c_char, user_data: *mut c_void);
+
+/// Energy reported by the host rather than modelled by the node.
+#[derive(Debug, Clone)]
+pub struct ReportedMetabolism {
+    pub energy: f32,
+    pub is_mains: bool,
+    pub mah_remaining: f32,
+}
+
+impl Default for ReportedMetabolism {
+    fn default() -> Self {
+        Self {
+            energy: 1.0,
+            is_mains: false,
+            mah_remaining: 0.0,
+        }
+    }
+}
+
+impl Metabolism for ReportedMetabolism {
+    fn energy_score(&self) -> f32 {
+        if self.is_mains {
+            1.0
+        } else {
+            self.energy
+        }
+    }
+    fn consume(&mut self, _cost: f32) -> bool {
+        // The host does its own accounting; only refuse work when drained.
+        self.is_mains || self.energy > 0.0
+    }
+    fn remaining(&self) -> f32 {
+        self.mah_remaining
+    }
+    fn set_mode(&mut self, mode: PowerMode) {
+        self.energy = match mode {
+            PowerMode::Normal => 0.8,
+            PowerMode::LowBattery => 0.35,
+            PowerMode::Critical => 0.1,
+        };
+    }
+    fn is_mains_powered(&self) -> bool {
+        self.is_mains
+    }
+    fn measured_mah(&self) -> Option<f32> {
+        // Zero until the host reports a gauge reading.
+        (self.mah_remaining > 0.0).then_some(self.mah_remaining)
+    }
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
+/// Opaque node handle.
+pub struct HyphaNode {
+    handle: NodeHandle,
+    energy: Arc<Mutex<ReportedMetabolism>>,
+    events: broadcast::Receiver<NodeEvent>,
+    pending: Option<CString>,
+    task_callback: Option<(HyphaTaskCallback, *mut c_void)>,
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char) -> Result<&'a str, i32> {
+    if ptr.is_null() {
+        return Err(HYPHA_ERR_NULL);
+    }
+    CStr::from_ptr(ptr).to_str().map_err(|_| HYPHA_ERR_INVALID)
+}
+
+fn to_c_json<T: serde::Serialize>(value: &T) -> Option<CString> {
+    serde_json::to_string(value)
+        .ok()
+        .and_then(|json| CString::new(json).ok())
+}
+
+/// Copy `value` (with a trailing NUL) into `buf`. Returns the length written,
+/// excluding the NUL.
+unsafe fn write_out(value: &CStr, buf: *mut c_char, buf_len: usize) -> i32 {
+    if buf.is_null() {
+        return HYPHA_ERR_NULL;
+    }
+    let bytes = value.to_bytes_with_nul();
+    if bytes.len() > buf_len {
+        return HYPHA_ERR_BUFFER_TOO_SMALL;
+    }
+    std::ptr::copy_nonoverlapping(bytes.as_ptr().cast(), buf, bytes.len());
+    (bytes.len() - 1) as i32
+}
+
+/// Create a node stored at `storage_path` and start its network loop.
+///
+/// `bootstrap` may be null when `bootstrap_len` is 0. Returns null on failure.
+///
+/// # Safety
+/// String arguments must be valid NUL-terminated strings; `bootstrap` must
+/// point to `bootstrap_len` of them.
+#[no_mangle]
+pub unsafe extern "C" fn hypha_node_new(
+    storage_path: *const c_char,
+    listen_addr: *const c_char,
+    bootstrap: *const *const c_char,
+    bootstrap_len: usize,
+) -> *mut HyphaNode {
+    let Ok(path) = str_arg(storage_path) else {
+        return std::ptr::null_mut();
+    };
+    let Some(listen) = str_arg(listen_addr).ok().and_then(|s| s.parse().ok()) else {
+        return std::ptr::null_mut();
+    };
+    let mut peers = Vec::with_capacity(bootstrap_len);
+    if bootstrap_len > 0 {
+        if bootstrap.is_null() {
+            return std::ptr::null_mut();
+        }
+        for i in 0..bootstrap_len {
+            let Some(addr) = str_arg(*bootstrap.add(i)).ok().and_then(|s| s.parse().ok()) else {
+                return std::ptr::null_mut();
+            };
+            peers.push(addr);
+        }
+    }
+
+    let energy = Arc::new(Mutex::new(ReportedMetabolism::default()));
+    let Ok(handle) =
+        NodeHandle::spawn_with_metabolism(Path::new(path), energy.clone(), listen, peers)
+    else {
+        return std::ptr::null_mut();
+    };
+    let Some(events) = handle.subscribe() else {
+        return std::ptr::null_mut();
+    };
+
+    Box::into_raw(Box::new(HyphaNode {
+        handle,
+        energy,
+        events,
+        pending: None,
+        task_callback: None,
+    }))
+}
+
+/// Stop the node and release it. Null is ignored.
+///
+/// # Safety
+/// `node` must come from `hypha_node_new` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn hypha_node_free(node: *mut HyphaNode) {
+    if !node.is_null() {
+        drop(Box::from_raw(node));
+    }
+}
+
+/// Write the node's peer id into `buf`. Returns its length or an error code.
+///
+/// # Safety
+/// `node` must be live; `buf` must hold `buf_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn hypha_node_peer_id(
+    node: *const HyphaNode,
+    buf: *mut c_char,
+    buf_len: usize,
+) -> i32 {
+    let Some(node) = node.as_ref() else {
+        return HYPHA_ERR_NULL;
+    };
+    let Ok(peer_id) = CString::new(node.handle.peer_id()) else {
+        return HYPHA_ERR_INVALID;
+    };
+    write_out(&peer_id, buf, buf_len)
+}
+
+/// Report the host-measured energy score (0.0–1.0).
+///
+/// # Safety
+/// `node` must be live.
+#[no_mangle]
+pub unsafe extern "C" fn hypha_node_set_energy(
+    node: *mut HyphaNode,
+    energy_score: f32,
+    is_mains: bool,
+) -> i32 {
+    let Some(node) = node.as_mut() else {
+        return HYPHA_ERR_NULL;
+    };
+    if !energy_score.is_finite() {
+        return HYPHA_ERR_INVALID;
+    }
+    let mut energy = node.energy.lock().unwrap();
+    energy.energy = energy_score.clamp(0.0, 1.0);
+    energy.is_mains = is_mains;
+    HYPHA_OK
+}
+
+/// Report a full `EnergyStatus` as JSON. Its score and facts are advertised
+/// on the node's next heartbeat.
+///
+/// # Safety
+/// `node` must be live; `status_json` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn hypha_node_publish_status(
+    node: *mut HyphaNode,
+    status_json: *const c_char,
+) -> i32 {
+    let Some(node) = node.as_mut() else {
+        return HYPHA_ERR_NULL;
+    };
+    let json = match str_arg(status_json) {
+        Ok(json) => json,
+        Err(code) => return code,
+    };
+    let Ok(status) = serde_json::from_str::<EnergyStatus>(json) else {
+        return HYPHA_ERR_INVALID;
+    };
+    let mut energy = node.energy.lock().unwrap();
+    energy.energy = status.energy_score.clamp(0.0, 1.0);
+    if let Some(facts) = status.facts {
+        energy.is_mains = facts.is_mains.unwrap_or(energy.is_mains);
+        energy.mah_remaining = facts.mah_remaining.unwrap_or(energy.mah_remaining);
+    }
+    HYPHA_OK
+}
+
+/// Queue a task, given as JSON, for the node's next heartbeat.
+///
+/// # Safety
+/// `node` must be live; `task_json` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn hypha_node_publish_task(
+    node: *mut HyphaNode,
+    task_json: *const c_char,
+) -> i32 {
+    let Some(node) = node.as_mut() else {
+        return HYPHA_ERR_NULL;
+    };
+    let json = match str_arg(task_json) {
+        Ok(json) => json,
+        Err(code) => return code,
+    };
+    let Ok(task) = serde_json::from_str::<Task>(json) else {
+        return HYPHA_ERR_INVALID;
+    };
+    node.handle.publish_task(task);
+    HYPHA_OK
+}
+
+/// Register `callback` for inbound tasks, or clear it with null.
+///
+/// The callback runs inside `hypha_node_poll_event` on the polling thread.
+///
+/// # Safety
+/// `node` must be live; `user_data` must stay valid while registered.
+#[no_mangle]
+pub unsafe extern "C" fn hypha_node_set_task_callback(
+    node: *mut HyphaNode,
+    callback: Option<HyphaTaskCallback>,
+    user_data: *mut c_void,
+) -> i32 {
+    let Some(node) = node.as_mut() else {
+        return HYPHA_ERR_NULL;
+    };
+    node.task_callback = callback.map(|cb| (cb, user_data));
+    HYPHA_OK
+}
+
+/// Take the next event as JSON (tagged by `"kind"`) into `buf`.
+///
+/// Returns the length written, 0 when no event is waiting, or an error code.
+/// On `HYPHA_ERR_BUFFER_TOO_SMALL` the event is kept for the next call.
+///
+/// # Safety
+/// `node` must be live; `buf` must hold `buf_len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn hypha_node_poll_event(
+    node: *mut HyphaNode,
+    buf: *mut c_char,
+    buf_len: usize,
+) -> i32 {
+    let Some(node) = node.as_mut() else {
+        return HYPHA_ERR_NULL;
+    };
+    if node.pending.is_none() {
+        let event = loop {
+            match node.events.try_recv() {
+                Ok(event) => break event,
+                Err(TryRecvError::Lagged(_)) => continue,
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => return 0,
+            }
+        };
+        let Some(json) = to_c_json(&event) else {
+            return HYPHA_ERR_INVALID;
+        };
+        if let (NodeEvent::Task(task), Some((callback, user_data))) = (&event, node.task_callback) {
+            if let Some(task_json) = to_c_json(task) {
+                callback(task_json.as_ptr(), user_data);
+            }
+        }
+        node.pending = Some(json);
+    }
+
+    let written = write_out(node.pending.as_ref().unwrap(), buf, buf_len);
+    if written >= 0 {
+        node.pending = None;
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    #[test]
+    fn node_lifecycle_over_the_c_api() {
+        let tmp = tempdir().unwrap();
+        let path = c(tmp.path().to_str().unwrap());
+        let listen = c("/ip4/127.0.0.1/tcp/0");
+
+        unsafe {
+            let node = hypha_node_new(path.as_ptr(), listen.as_ptr(), std::ptr::null(), 0);
+            assert!(!node.is_null());
+
+            let mut buf = [0 as c_char; 128];
+            assert!(hypha_node_peer_id(node, buf.as_mut_ptr(), buf.len()) > 0);
+            assert_eq!(
+                hypha_node_peer_id(node, buf.as_mut_ptr(), 4),
+                HYPHA_ERR_BUFFER_TOO_SMALL
+            );
+
+            assert_eq!(hypha_node_set_energy(node, 0.3, false), HYPHA_OK);
+            assert_eq!((*node).handle.power_mode(), PowerMode::LowBattery);
+
+            let status = c(r#"{"source_id":"fw","energy_score":0.9}"#);
+            assert_eq!(hypha_node_publish_status(node, status.as_ptr()), HYPHA_OK);
+            assert!(((*node).handle.energy_score() - 0.9).abs() < f32::EPSILON);
+
+            let bad = c("{not json");
+            assert_eq!(
+                hypha_node_publish_task(node, bad.as_ptr()),
+                HYPHA_ERR_INVALID
+            );
+
+            assert_eq!(hypha_node_poll_event(node, buf.as_mut_ptr(), buf.len()), 0);
+
+            hypha_node_free(node);
+        }
+    }
+
+    #[test]
+    fn null_arguments_are_rejected() {
+        unsafe {
+            assert!(
+                hypha_node_new(std::ptr::null(), std::ptr::null(), std::ptr::null(), 0).is_null()
+            );
+            assert_eq!(
+                hypha_node_set_energy(std::ptr::null_mut(), 0.5, false),
+                HYPHA_ERR_NULL
+            );
+            hypha_node_free(std::ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,10 @@
+//! Python bindings for driving hypha nodes from notebooks.
+//!
+//! The `python` feature builds a pyo3 extension module named `hypha` over
+//! [`hypha::embed::NodeHandle`]; build it with `maturin develop`. Without the
+//! feature this crate only re-exports the handle.
+
+pub use hypha::embed::{BoxError, NodeHandle};
+
+#[cfg(feature = "python")]
+mod python;
@@ -0,0 +1,134 @@
+//! pyo3 wrappers around [`NodeHandle`].
+//!
+//! ```python
+//! import asyncio, hypha
+//!
+//! node = hypha.SporeNode("/tmp/spore-a")
+//! node.set("room", "lab")
+//! node.publish_task('{"id": "t1", "required_capability": {"Compute": 10}, '
+//!                   '"priority": 1, "reach_intensity": 1.0, "source_id": "nb"}')
+//!
+//! async def watch():
+//!     async for event in node.events():
+//!         print(event)  # JSON, tagged by "kind"
+//! ```
+
+use hypha::embed::NodeHandle;
+use hypha::events::NodeEvent;
+use hypha::Task;
+use pyo3::exceptions::{PyRuntimeError, PyStopAsyncIteration, PyValueError};
+use pyo3::prelude::*;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+#[pyclass(name = "SporeNode")]
+struct PySporeNode {
+    handle: NodeHandle,
+}
+
+#[pymethods]
+impl PySporeNode {
+    #[new]
+    #[pyo3(signature = (path, listen = "/ip4/0.0.0.0/tcp/0", bootstrap = Vec::new()))]
+    fn new(path: PathBuf, listen: &str, bootstrap: Vec<String>) -> PyResult<Self> {
+        let listen = listen
+            .parse()
+            .map_err(|e| PyValueError::new_err(format!("listen: {e}")))?;
+        let bootstrap = bootstrap
+            .iter()
+            .map(|addr| addr.parse())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| PyValueError::new_err(format!("bootstrap: {e}")))?;
+        let handle = NodeHandle::spawn(&path, listen, bootstrap)
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        Ok(Self { handle })
+    }
+
+    #[getter]
+    fn peer_id(&self) -> String {
+        self.handle.peer_id().to_string()
+    }
+
+    #[getter]
+    fn listen_addr(&self) -> Option<String> {
+        self.handle.listen_addr().map(ToString::to_string)
+    }
+
+    fn energy_score(&self) -> f32 {
+        self.handle.energy_score()
+    }
+
+    /// `"Normal"`, `"LowBattery"` or `"Critical"`.
+    fn power_mode(&self) -> String {
+        format!("{:?}", self.handle.power_mode())
+    }
+
+    /// Queue a task, given as its JSON encoding, for the next heartbeat.
+    fn publish_task(&self, task_json: &str) -> PyResult<()> {
+        let task: Task =
+            serde_json::from_str(task_json).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        self.handle.publish_task(task);
+        Ok(())
+    }
+
+    fn get(&self, key: &str) -> Option<String> {
+        self.handle.get(key)
+    }
+
+    fn set(&self, key: &str, value: &str) {
+        self.handle.set(key, value);
+    }
+
+    /// Async iterator of node events as JSON strings.
+    fn events(&self) -> PyResult<EventStream> {
+        let rx = self
+            .handle
+            .subscribe()
+            .ok_or_else(|| PyRuntimeError::new_err("node is stopped"))?;
+        Ok(EventStream {
+            rx: Arc::new(tokio::sync::Mutex::new(rx)),
+        })
+    }
+
+    fn stop(&mut self, py: Python<'_>) {
+        py.allow_threads(|| self.handle.stop());
+    }
+}
+
+#[pyclass]
+struct EventStream {
+    rx: Arc<tokio::sync::Mutex<broadcast::Receiver<NodeEvent>>>,
+}
+
+#[pymethods]
+impl EventStream {
+    fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __anext__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let rx = self.rx.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let mut rx = rx.lock().await;
+            loop {
+                match rx.recv().await {
+                    Ok(event) => {
+                        return serde_json::to_string(&event)
+                            .map_err(|e| PyValueError::new_err(e.to_string()));
+                    }
+                    // A slow notebook skips events rather than stalling the node.
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return Err(PyStopAsyncIteration::new_err(())),
+                }
+            }
+        })
+    }
+}
+
+#[pymodule]
+fn hypha(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PySporeNode>()?;
+    m.add_class::<EventStream>()?;
+    Ok(())
+}
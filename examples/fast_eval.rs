@@ -144,6 +144,11 @@ fn run_scenario(
         },
         consistency: ConsistencyMetrics::default(),
         fault_events: vec![],
+        warmup: Default::default(),
+        cooldown: Default::default(),
+        devices: vec![],
+        nodes: vec![],
+        auth: None,
     }
 }
 
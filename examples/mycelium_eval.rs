@@ -49,6 +49,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             reach_intensity: 1.0,
             source_id: "test-source".to_string(),
             auth_token: None,
+            public_result: false,
+            tenant: None,
+            memo_key: None,
+            geohash: None,
+            target: None,
+            expected_output_bytes: None,
         };
 
         let mut successful_bids = 0;
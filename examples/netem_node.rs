@@ -12,6 +12,7 @@
 //! This is deliberately minimal and not a general CLI.
 
 use hypha::mycelium::NetProfile;
+use hypha::util::RetryPolicy;
 use hypha::{EnergyStatus, SporeNode};
 use libp2p::futures::StreamExt;
 use libp2p::{gossipsub, swarm::SwarmEvent, Multiaddr, PeerId};
@@ -229,6 +230,14 @@ async fn main() -> Result<(), Box<dyn Error>> {
                 source_id: "publisher".to_string(),
                 energy_score: 0.9,
                 facts: None,
+                topics: Vec::new(),
+                grants: Vec::new(),
+                role: None,
+                attestation: None,
+                zone: None,
+                state: None,
+                catalog_version: None,
+                health: None,
             };
             let bytes = serde_json::to_vec(&status)?;
 
@@ -244,14 +253,21 @@ async fn main() -> Result<(), Box<dyn Error>> {
             }
 
             // Publish retries handle the common case: NoPeersSubscribedToTopic.
-            let mut last_err: Option<gossipsub::PublishError> = None;
             let publish_retries = env_u64("HYPHA_NETEM_PUB_PUBLISH_RETRIES", 10);
             let retry_sleep_ms = env_u64("HYPHA_NETEM_PUB_RETRY_SLEEP_MS", 100);
             let burst = env_u64("HYPHA_NETEM_PUB_BURST", 1);
             let burst_interval_ms = env_u64("HYPHA_NETEM_PUB_BURST_INTERVAL_MS", 200);
             let flush_ms = env_u64("HYPHA_NETEM_PUB_FLUSH_MS", 800);
+            let mut backoff = RetryPolicy {
+                max_attempts: publish_retries as u32,
+                base_delay: Duration::from_millis(retry_sleep_ms),
+                max_delay: Duration::from_secs(1),
+                jitter: 0.2,
+                min_energy: 0.0,
+            }
+            .backoff();
 
-            for _ in 0..publish_retries {
+            loop {
                 match mycelium
                     .swarm
                     .behaviour_mut()
@@ -284,14 +300,19 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         }
                         return Ok(());
                     }
-                    Err(e) => {
-                        last_err = Some(e);
-                        tokio::time::sleep(Duration::from_millis(retry_sleep_ms)).await;
-                    }
+                    Err(e) => match backoff.next_delay(node.energy_score()) {
+                        Some(delay) => tokio::time::sleep(delay).await,
+                        None => {
+                            return Err(format!(
+                                "publish failed after {} attempts: {:?}",
+                                backoff.attempts(),
+                                e
+                            )
+                            .into())
+                        }
+                    },
                 }
             }
-
-            Err(format!("publish failed after retries: {:?}", last_err).into())
         }
         Mode::Relay => {
             if args.len() < 6 {
@@ -358,8 +379,15 @@ async fn main() -> Result<(), Box<dyn Error>> {
                         gossipsub::Event::Message { message, .. },
                     )) if message.topic == mycelium.status_topic.hash() => {
                         // Application-level relay: re-publish once we see a status message.
-                        let mut last_err: Option<gossipsub::PublishError> = None;
-                        for _ in 0..10 {
+                        let mut backoff = RetryPolicy {
+                            max_attempts: 10,
+                            base_delay: Duration::from_millis(50),
+                            max_delay: Duration::from_millis(400),
+                            jitter: 0.2,
+                            min_energy: 0.0,
+                        }
+                        .backoff();
+                        loop {
                             match mycelium
                                 .swarm
                                 .behaviour_mut()
@@ -371,15 +399,14 @@ async fn main() -> Result<(), Box<dyn Error>> {
                                     println!("RELAYED_MS {}", dt.as_millis());
                                     break;
                                 }
-                                Err(e) => {
-                                    last_err = Some(e);
-                                    tokio::time::sleep(Duration::from_millis(50)).await;
-                                }
+                                Err(e) => match backoff.next_delay(node.energy_score()) {
+                                    Some(delay) => tokio::time::sleep(delay).await,
+                                    None => {
+                                        return Err(format!("relay publish failed: {:?}", e).into())
+                                    }
+                                },
                             }
                         }
-                        if last_err.is_some() {
-                            return Err(format!("relay publish failed: {:?}", last_err).into());
-                        }
                     }
                     _ => {}
                 }
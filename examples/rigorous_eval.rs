@@ -5,17 +5,94 @@
 //! - Energy consumption per delivery
 //! - Fault injection (degradation, partition)
 //! - Convergence metrics
-
-use hypha::eval::{EvalRun, EvalScenario, FaultType, MetricsCollector};
-use hypha::{Capability, SporeNode};
+//! - Authorization: unauthorized tasks that win bids, and what verifying
+//!   tokens costs
+
+use ed25519_dalek::SigningKey;
+use hypha::auth::{peer_id_of, unix_now, Delegation};
+use hypha::eval::{
+    load_scenarios, AirtimeBudget, AuthWorkload, DeviceClass, DeviceStats, EvalRun, EvalScenario,
+    FaultType, LinkModel, MetricsCollector, NodeMetrics, TokenFault, Uptime,
+};
+use hypha::{Capability, SporeNode, Task};
 use rand::{rng, Rng};
 use serde_json::json;
 use std::fs::File;
 use std::io::Write;
-use std::time::Duration;
+use std::path::Path;
+use std::time::{Duration, Instant};
 use tempfile::tempdir;
 
+/// Per-node radio state for constrained link models and device classes.
+struct Radios {
+    link: LinkModel,
+    budgets: Vec<Option<AirtimeBudget>>,
+    /// Relays skipped because the sender's duty-cycle budget was spent.
+    blocked: u64,
+    /// Class of each node; empty for a homogeneous swarm.
+    devices: Vec<DeviceClass>,
+    /// Where each node starts in its class's uptime cycle.
+    offsets: Vec<Duration>,
+}
+
+impl Radios {
+    fn new(link: &LinkModel, node_count: usize, devices: Vec<DeviceClass>) -> Self {
+        let mut rng = rng();
+        let offsets = devices
+            .iter()
+            .map(|class| match class.profile().uptime {
+                Uptime::Always => Duration::ZERO,
+                Uptime::Periodic { period, .. } => period.mul_f64(rng.random::<f64>()),
+            })
+            .collect();
+        Self {
+            link: link.clone(),
+            budgets: (0..node_count).map(|_| link.airtime_budget()).collect(),
+            blocked: 0,
+            devices,
+            offsets,
+        }
+    }
+
+    fn is_up(&self, node_idx: usize, now: Duration) -> bool {
+        self.devices
+            .get(node_idx)
+            .is_none_or(|class| class.profile().uptime.is_up(now, self.offsets[node_idx]))
+    }
+
+    fn rx_mah(&self, node_idx: usize) -> f32 {
+        self.devices
+            .get(node_idx)
+            .map_or(0.1, |class| class.profile().rx_mah)
+    }
+
+    fn tx_mah(&self, node_idx: usize) -> f32 {
+        self.devices
+            .get(node_idx)
+            .map_or(0.5, |class| class.profile().tx_mah)
+    }
+
+    fn transfer_time(&self, node_idx: usize, bytes: usize) -> Duration {
+        self.devices
+            .get(node_idx)
+            .map_or(Duration::ZERO, |class| class.profile().transfer_time(bytes))
+    }
+
+    /// Charge one broadcast by `node_idx` at simulated time `now`.
+    fn transmit(&mut self, node_idx: usize, now: Duration, bytes: usize) -> bool {
+        let airtime = self.link.airtime(bytes);
+        let allowed = self.budgets[node_idx]
+            .as_mut()
+            .is_none_or(|budget| budget.try_transmit(now, airtime));
+        if !allowed {
+            self.blocked += 1;
+        }
+        allowed
+    }
+}
+
 /// Simulates message propagation through the network using peer-to-peer relaying.
+/// `sent_at` is the simulated publish time, used for duty-cycle accounting.
 /// Returns (delivery_count, latencies_us)
 fn simulate_propagation(
     nodes: &[SporeNode],
@@ -23,6 +100,8 @@ fn simulate_propagation(
     payload: &[u8],
     drop_probability: f32,
     publisher_count: usize,
+    radios: &mut Radios,
+    sent_at: Duration,
 ) -> (u64, Vec<u64>) {
     let mut rng = rng();
     let mut delivered_nodes = std::collections::HashSet::new();
@@ -46,6 +125,11 @@ fn simulate_propagation(
     for _hop in 0..12 {
         let mut next_wave = Vec::new();
         for (node_idx, current_latency) in current_wave {
+            // A radio broadcast reaches every neighbor but spends airtime once.
+            let now = sent_at + Duration::from_micros(current_latency);
+            if !radios.is_up(node_idx, now) || !radios.transmit(node_idx, now, payload.len()) {
+                continue;
+            }
             // Pick D=8 neighbors for higher reach in stress (D=6 is standard)
             neighbor_indices.shuffle(&mut rng);
             let sample_size = 8.min(nodes.len());
@@ -57,8 +141,8 @@ fn simulate_propagation(
 
                 let neighbor = &nodes[neighbor_idx];
 
-                // Skip exhausted nodes
-                if neighbor.is_exhausted() {
+                // Skip exhausted and sleeping nodes
+                if neighbor.is_exhausted() || !radios.is_up(neighbor_idx, now) {
                     continue;
                 }
 
@@ -70,11 +154,13 @@ fn simulate_propagation(
                 // Success!
                 if neighbor.simulate_receive(message_id, payload).is_ok() {
                     delivered_nodes.insert(neighbor_idx);
-                    let hop_latency = 15_000 + rng.random_range(0..5_000);
+                    let hop = radios.link.hop_latency(payload.len())
+                        + radios.transfer_time(node_idx, payload.len());
+                    let hop_latency = hop.as_micros() as u64 + rng.random_range(0..5_000);
                     let total_latency = current_latency + hop_latency;
                     latencies.push(total_latency);
 
-                    neighbor.consume_energy(0.1);
+                    neighbor.consume_energy(radios.rx_mah(neighbor_idx));
 
                     // Relay based on Pulse-Gated strategy (simulated phase > 0.7)
                     let energy = neighbor.energy_score();
@@ -100,11 +186,88 @@ fn simulate_propagation(
 }
 
 /// Run a single evaluation scenario
+/// Offer `workload`'s tasks to the nodes in turn. Authorized tasks carry a
+/// token an operator key granted the node; the rest carry their injected
+/// fault. Each task is first offered without a token to the node with
+/// checks off, which tells whether the node could take it at all and how
+/// long a bid takes without verification.
+fn offer_tasks(
+    nodes: &mut [SporeNode],
+    workload: &AuthWorkload,
+    collector: &mut MetricsCollector,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let operator = SigningKey::from_bytes(&rng().random());
+    let impostor = SigningKey::from_bytes(&rng().random());
+    let operator_id = peer_id_of(&operator).to_string();
+    for node in nodes.iter_mut() {
+        node.trusted_issuers.push(operator_id.clone());
+    }
+    let required = Capability::Compute(50);
+    let hour = Duration::from_secs(3600);
+    let now = unix_now();
+
+    for i in 0..workload.task_count {
+        let node_idx = i % nodes.len();
+        let fault = workload.fault_for(i);
+        let audience = match fault {
+            Some(TokenFault::Misdirected) => nodes[(node_idx + 1) % nodes.len()].peer_id,
+            _ => nodes[node_idx].peer_id,
+        };
+        let mint = |key: &SigningKey, capability: Capability, issued_at: u64| {
+            Delegation::mint(key, &audience, capability, issued_at, hour, None)
+        };
+        let token = match fault {
+            None | Some(TokenFault::Misdirected) => Some(mint(&operator, required.clone(), now)?),
+            Some(TokenFault::Missing) => None,
+            Some(TokenFault::Forged) => Some(mint(&impostor, required.clone(), now)?),
+            Some(TokenFault::Expired) => Some(mint(&operator, required.clone(), now - 7200)?),
+            Some(TokenFault::Overreaching) => Some(mint(&operator, Capability::Compute(10), now)?),
+        };
+        let task = Task::new(
+            format!("auth-task-{i}"),
+            required.clone(),
+            1,
+            "eval-publisher".into(),
+        );
+        let node = &mut nodes[node_idx];
+
+        node.require_token = false;
+        let started = Instant::now();
+        let capable = node
+            .process_task_bundle_best_bid(&task, &mut Vec::new())
+            .is_some();
+        let unchecked = started.elapsed();
+        if !capable {
+            continue;
+        }
+
+        node.require_token = workload.secure;
+        let task = Task {
+            auth_token: token.map(|t| t.encode()),
+            ..task
+        };
+        let started = Instant::now();
+        let accepted = node
+            .process_task_bundle_best_bid(&task, &mut Vec::new())
+            .is_some();
+        let checked = started.elapsed();
+        collector.record_authorization(fault, accepted);
+        if task.auth_token.is_some() {
+            let overhead = checked.saturating_sub(unchecked);
+            collector.record_verify_overhead(overhead, workload.cpu_ma);
+            node.consume_energy(workload.cpu_ma * overhead.as_secs_f32() / 3600.0);
+        }
+    }
+    Ok(())
+}
+
 fn run_scenario(scenario: &EvalScenario) -> Result<EvalRun, Box<dyn std::error::Error>> {
     let tmp = tempdir()?;
     let mut collector = MetricsCollector::new();
     let mut nodes = Vec::new();
     let mut rng = rng();
+    let devices = scenario.devices();
+    let mut radios = Radios::new(&scenario.link, scenario.node_count, devices.clone());
 
     // Create nodes
     let low_energy_count =
@@ -115,6 +278,22 @@ fn run_scenario(scenario: &EvalScenario) -> Result<EvalRun, Box<dyn std::error::
         std::fs::create_dir(&path)?;
         let mut node = SporeNode::new(&path)?;
 
+        if let Some(class) = devices.get(i) {
+            let profile = class.profile();
+            if let Some(batt) = node
+                .metabolism
+                .lock()
+                .unwrap()
+                .as_any()
+                .downcast_mut::<hypha::BatteryMetabolism>()
+            {
+                batt.voltage = profile.voltage;
+                batt.mah_remaining = profile.mah;
+                batt.is_mains = profile.is_mains;
+            }
+            node.device_class = class.name().to_string();
+        }
+
         // Configure low-energy nodes
         if i < low_energy_count {
             let mut meta = node.metabolism.lock().unwrap();
@@ -130,7 +309,9 @@ fn run_scenario(scenario: &EvalScenario) -> Result<EvalRun, Box<dyn std::error::
     }
 
     // Track initial energy
-    let initial_energy: f32 = nodes.iter().map(|n| n.mah_remaining()).sum();
+    let initial_mah: Vec<f32> = nodes.iter().map(|n| n.mah_remaining()).collect();
+    let initial_scores: Vec<f32> = nodes.iter().map(|n| n.energy_score()).collect();
+    let initial_energy: f32 = initial_mah.iter().sum();
 
     // Process fault schedule
     let mut current_drop_prob = 0.0f32;
@@ -163,12 +344,20 @@ fn run_scenario(scenario: &EvalScenario) -> Result<EvalRun, Box<dyn std::error::
         }
     }
 
-    // Simulate message publishing
-    let message_count = (scenario.duration.as_secs_f32() * scenario.message_rate_per_sec) as usize;
+    // Simulate message publishing through warmup, the measured window and
+    // cooldown; only the measured window makes the headline numbers.
+    let message_count =
+        (scenario.total_duration().as_secs_f32() * scenario.message_rate_per_sec) as usize;
     let payload = vec![0u8; scenario.message_size_bytes];
 
     for msg_idx in 0..message_count {
         let msg_id = format!("{}-{}", scenario.name, msg_idx);
+        let sent_at = Duration::from_secs_f32(msg_idx as f32 / scenario.message_rate_per_sec);
+        let phase = scenario.phase_at(sent_at);
+        if phase != collector.phase() {
+            let remaining: f32 = nodes.iter().map(|n| n.mah_remaining()).sum();
+            collector.enter_phase(phase, initial_energy - remaining);
+        }
         collector.record_publish(nodes.len());
 
         // Simulate propagation
@@ -184,6 +373,8 @@ fn run_scenario(scenario: &EvalScenario) -> Result<EvalRun, Box<dyn std::error::
             &payload,
             effective_drop,
             scenario.publisher_count,
+            &mut radios,
+            sent_at,
         );
 
         for lat_us in latencies {
@@ -193,10 +384,18 @@ fn run_scenario(scenario: &EvalScenario) -> Result<EvalRun, Box<dyn std::error::
         // Publishers consume extra energy
         let publisher_idx = msg_idx % scenario.publisher_count;
         if publisher_idx < nodes.len() {
-            nodes[publisher_idx].consume_energy(0.5); // 0.5 mAh per publish
+            nodes[publisher_idx].consume_energy(radios.tx_mah(publisher_idx)); // 0.5 mAh per publish by default
         }
     }
 
+    if radios.blocked > 0 {
+        println!("  {} relays held back by duty cycle", radios.blocked);
+    }
+
+    if let Some(workload) = &scenario.authorization {
+        offer_tasks(&mut nodes, workload, &mut collector)?;
+    }
+
     // Record final energy state
     let energy_scores: Vec<f32> = nodes.iter().map(|n| n.energy_score()).collect();
     collector.record_energy_snapshot(energy_scores);
@@ -207,6 +406,38 @@ fn run_scenario(scenario: &EvalScenario) -> Result<EvalRun, Box<dyn std::error::
     let divergence: usize = message_counts.iter().map(|&c| max_count - c).sum();
     collector.record_consistency(divergence);
 
+    for class in DeviceClass::ALL {
+        let members: Vec<usize> = (0..devices.len())
+            .filter(|&i| devices[i] == class)
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+        collector.record_device(DeviceStats {
+            class,
+            nodes: members.len(),
+            deliveries: members
+                .iter()
+                .map(|&i| nodes[i].message_count() as u64)
+                .sum(),
+            mah_consumed: members
+                .iter()
+                .map(|&i| initial_mah[i] - nodes[i].mah_remaining())
+                .sum(),
+            nodes_exhausted: members.iter().filter(|&&i| nodes[i].is_exhausted()).count(),
+        });
+    }
+
+    for (i, node) in nodes.iter().enumerate() {
+        collector.record_node(NodeMetrics {
+            node: format!("node_{i}"),
+            deliveries: node.message_count() as u64,
+            mah_consumed: initial_mah[i] - node.mah_remaining(),
+            initial_energy: initial_scores[i],
+            mesh_size: node.mesh.lock().unwrap().mesh_peers.len(),
+        });
+    }
+
     // Calculate total energy consumed
     let final_energy: f32 = nodes.iter().map(|n| n.mah_remaining()).sum();
     let mah_consumed = initial_energy - final_energy;
@@ -219,6 +450,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Hypha Rigorous Evaluation Suite");
     println!("================================\n");
 
+    // With a directory argument, run the scenario files in it instead.
+    if let Some(dir) = std::env::args().nth(1) {
+        for scenario in load_scenarios(Path::new(&dir))? {
+            println!("Running: {}...", scenario.name);
+            let run = run_scenario(&scenario)?;
+            println!(
+                "  delivery={:.1}%, p99={:?}, exhausted={}",
+                run.delivery.delivery_rate() * 100.0,
+                run.delivery.p99(),
+                run.energy.nodes_exhausted
+            );
+        }
+        return Ok(());
+    }
+
     let mut all_runs: Vec<EvalRun> = Vec::new();
 
     // 1. Baseline (no faults)
@@ -379,6 +625,72 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     );
     all_runs.push(run);
 
+    // 7. LoRa-class links: airtime latency and 1% duty cycle
+    println!("\nRunning: LoRa link scenarios...");
+    for spreading_factor in [7, 9, 12] {
+        let scenario = EvalScenario::lora_field(20, spreading_factor);
+        let run = run_scenario(&scenario)?;
+        println!(
+            "  SF{}: delivery={:.1}%, p99={:?}",
+            spreading_factor,
+            run.delivery.delivery_rate() * 100.0,
+            run.delivery.p99()
+        );
+        all_runs.push(run);
+    }
+
+    // 8. Heterogeneous fleet: gateways, phones, solar sensors, beacons
+    println!("\nRunning: Mixed device fleet...");
+    let scenario = EvalScenario {
+        node_count: 40,
+        publisher_count: 4,
+        message_rate_per_sec: 5.0,
+        duration: Duration::from_secs(2),
+        ..EvalScenario::mixed_fleet(40)
+    };
+    let run = run_scenario(&scenario)?;
+    println!(
+        "  Fleet: delivery={:.1}%, p99={:?}",
+        run.delivery.delivery_rate() * 100.0,
+        run.delivery.p99()
+    );
+    for device in &run.devices {
+        println!(
+            "    {:?}: nodes={}, deliveries={}, mAh={:.1}, exhausted={}",
+            device.class,
+            device.nodes,
+            device.deliveries,
+            device.mah_consumed,
+            device.nodes_exhausted
+        );
+    }
+    all_runs.push(run);
+
+    // 9. Authorization: unauthorized tasks offered to open and secure nodes
+    println!("\nRunning: Authorization scenarios...");
+    for secure in [false, true] {
+        for pct in [10.0, 50.0] {
+            let scenario = EvalScenario {
+                message_rate_per_sec: 5.0,
+                duration: Duration::from_secs(2),
+                ..EvalScenario::authorization(20, pct, secure)
+            };
+            let run = run_scenario(&scenario)?;
+            if let Some(auth) = &run.auth {
+                println!(
+                    "  {}: slipped={}/{}, refused authorized={:.1}%, p99 verify={:?}, verify mAh={:.5}",
+                    run.scenario,
+                    auth.unauthorized_accepted(),
+                    auth.unauthorized_offered,
+                    auth.false_rejection_rate() * 100.0,
+                    auth.overhead_percentile(99.0),
+                    auth.verify_mah
+                );
+            }
+            all_runs.push(run);
+        }
+    }
+
     // Generate summary report
     println!("\n================================");
     println!("EVALUATION SUMMARY");
@@ -404,11 +716,29 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     "nodes_exhausted": run.energy.nodes_exhausted,
                     "gini_coefficient": format!("{:.3}", run.energy.energy_gini()),
                 },
+                "fairness": {
+                    "jain_index": run.fairness_index().map(|f| format!("{f:.3}")),
+                    "low_energy_starvation": run.starvation_ratio(0.5).map(|r| format!("{r:.3}")),
+                    "worst_nodes": run
+                        .worst_nodes(3)
+                        .iter()
+                        .map(|n| json!({"node": n.node, "deliveries": n.deliveries}))
+                        .collect::<Vec<_>>(),
+                },
                 "consistency": {
                     "converged": run.consistency.converged(),
                     "max_divergence": run.consistency.max_divergence,
                 },
                 "fault_events": run.fault_events.len(),
+                "auth": run.auth.as_ref().map(|auth| json!({
+                    "unauthorized_offered": auth.unauthorized_offered,
+                    "unauthorized_accepted": auth.unauthorized_accepted(),
+                    "slipped": auth.slipped,
+                    "false_rejection_rate": format!("{:.3}", auth.false_rejection_rate()),
+                    "verify_p50_us": auth.overhead_percentile(50.0).map(|d| d.as_micros()),
+                    "verify_p99_us": auth.overhead_percentile(99.0).map(|d| d.as_micros()),
+                    "verify_mah": format!("{:.5}", auth.verify_mah),
+                })),
             })
         })
         .collect();
@@ -488,5 +818,32 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    // Authorization: a bad token must never win a bid, and in secure mode
+    // neither may a missing one.
+    for run in &all_runs {
+        let Some(auth) = &run.auth else {
+            continue;
+        };
+        let bad_tokens: u64 = auth
+            .slipped
+            .iter()
+            .filter(|(fault, _)| **fault != TokenFault::Missing)
+            .map(|(_, count)| count)
+            .sum();
+        if bad_tokens > 0 {
+            println!(
+                "\nWARNING: {}: {} tasks with bad tokens won bids",
+                run.scenario, bad_tokens
+            );
+        }
+        if run.scenario.ends_with("_secure") && auth.unauthorized_accepted() > 0 {
+            println!(
+                "\nWARNING: {}: {} unauthorized tasks won bids in secure mode",
+                run.scenario,
+                auth.unauthorized_accepted()
+            );
+        }
+    }
+
     Ok(())
 }
@@ -109,6 +109,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     bidder_id: format!("node-{}", idx),
                     energy_score: score * intensity,
                     cost_mah: 50.0,
+                    price: None,
                 };
                 bids.push(bid);
             }
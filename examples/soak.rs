@@ -0,0 +1,174 @@
+//! Long-running soak test for leaks and drift.
+//!
+//! Runs a small real swarm on loopback for `minutes` (default 10; use hours
+//! for a real soak) with every node publishing a task every few seconds. Once
+//! a minute it samples process RSS, message cache and known-peer counts, fjall
+//! disk usage and delivery latency, and exits non-zero if any of them kept
+//! growing after warmup.
+//!
+//! ```bash
+//! cargo run --release --example soak -- 180 5   # minutes, nodes
+//! ```
+
+use hypha::eval::{dir_size, process_rss_bytes, SoakMonitor, SoakSample};
+use hypha::events::NodeEvent;
+use hypha::{Capability, NodeLink, SporeNode, Task};
+use libp2p::futures::future::join_all;
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tempfile::tempdir;
+
+const BASE_PORT: u16 = 47_100;
+const SAMPLE_EVERY: Duration = Duration::from_secs(60);
+const PUBLISH_EVERY: Duration = Duration::from_secs(5);
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn sample(
+    started: tokio::time::Instant,
+    links: &[NodeLink],
+    paths: &[PathBuf],
+    latencies: &Mutex<Vec<u64>>,
+) -> SoakSample {
+    let (mut message_cache, mut known_peers) = (0, 0);
+    for link in links {
+        let mesh = link.mesh.lock().unwrap();
+        message_cache += mesh.message_cache.len();
+        known_peers += mesh.known_peers.len();
+    }
+    let mut window = std::mem::take(&mut *latencies.lock().unwrap());
+    window.sort_unstable();
+    SoakSample {
+        elapsed: started.elapsed(),
+        rss_bytes: process_rss_bytes(),
+        message_cache,
+        known_peers,
+        disk_bytes: paths.iter().map(|p| dir_size(p)).sum(),
+        latency_p50_us: window.get(window.len() / 2).map(|ms| ms * 1000),
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let minutes: u64 = args.next().map(|a| a.parse()).transpose()?.unwrap_or(10);
+    let node_count: usize = args.next().map(|a| a.parse()).transpose()?.unwrap_or(5);
+    let run_for = Duration::from_secs(minutes * 60);
+    println!("Soaking {node_count} nodes for {minutes} minutes...");
+
+    let tmp = tempdir()?;
+    let mut nodes: Vec<SporeNode> = Vec::new();
+    let mut myceliums = Vec::new();
+    let mut paths = Vec::new();
+    for i in 0..node_count {
+        let path = tmp.path().join(format!("node_{i}"));
+        std::fs::create_dir(&path)?;
+        let mut node = SporeNode::new(&path)?;
+        node.add_capability(Capability::Compute(100));
+        let mut mycelium = node.build_mycelium()?;
+        mycelium.listen_on(format!("/ip4/127.0.0.1/tcp/{}", BASE_PORT + i as u16).parse()?)?;
+        if let Some(first) = nodes.first() {
+            mycelium.add_bootstrap(
+                format!("/ip4/127.0.0.1/tcp/{BASE_PORT}/p2p/{}", first.peer_id).parse()?,
+            );
+        }
+        nodes.push(node);
+        myceliums.push(mycelium);
+        paths.push(path);
+    }
+    let links: Vec<NodeLink> = nodes.iter().map(SporeNode::link).collect();
+
+    // Publish-to-receive latency, from the timestamp in each task id.
+    let latencies = Arc::new(Mutex::new(Vec::new()));
+    for link in &links {
+        let mut events = link.subscribe();
+        let latencies = latencies.clone();
+        tokio::spawn(async move {
+            while let Ok(event) = events.recv().await {
+                let NodeEvent::Task(task) = event else {
+                    continue;
+                };
+                let sent = task.id.strip_prefix("soak-").and_then(|rest| {
+                    let (_, ms) = rest.split_once('-')?;
+                    ms.parse::<u64>().ok()
+                });
+                if let Some(sent) = sent {
+                    latencies
+                        .lock()
+                        .unwrap()
+                        .push(unix_millis().saturating_sub(sent));
+                }
+            }
+        });
+    }
+
+    let started = tokio::time::Instant::now();
+    let deadline = started + run_for;
+    let publisher = async {
+        let mut tick = tokio::time::interval(PUBLISH_EVERY);
+        while tokio::time::Instant::now() < deadline {
+            tick.tick().await;
+            for (i, link) in links.iter().enumerate() {
+                link.publish_task(Task::new(
+                    format!("soak-{i}-{}", unix_millis()),
+                    Capability::Compute(1),
+                    1,
+                    "soak".to_string(),
+                ));
+            }
+        }
+    };
+    let sampler = async {
+        let mut monitor = SoakMonitor::default();
+        let mut tick = tokio::time::interval(SAMPLE_EVERY);
+        tick.tick().await;
+        while tokio::time::Instant::now() < deadline {
+            tick.tick().await;
+            let s = sample(started, &links, &paths, &latencies);
+            println!(
+                "{:>5}s rss={:?} cache={} peers={} disk={} p50_us={:?}",
+                s.elapsed.as_secs(),
+                s.rss_bytes,
+                s.message_cache,
+                s.known_peers,
+                s.disk_bytes,
+                s.latency_p50_us
+            );
+            monitor.record(s);
+        }
+        monitor
+    };
+    let swarm = join_all(nodes.iter_mut().zip(myceliums).map(|(node, mycelium)| {
+        let heartbeat = node.heartbeat_interval();
+        node.run_for(mycelium, run_for, heartbeat, 0.05, true, None)
+    }));
+
+    let (results, (), monitor) = tokio::join!(swarm, publisher, sampler);
+    for result in results {
+        result?;
+    }
+
+    let violations = monitor.violations();
+    if violations.is_empty() {
+        println!(
+            "No unbounded growth across {} samples.",
+            monitor.samples().len()
+        );
+        return Ok(());
+    }
+    for v in &violations {
+        println!(
+            "GROWING {:?}: early={:.0} middle={:.0} late={:.0}",
+            v.metric, v.early, v.middle, v.late
+        );
+    }
+    Err(format!("{} metric(s) grew without bound", violations.len()).into())
+}
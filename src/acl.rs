@@ -0,0 +1,141 @@
+//! Capability-based topic ACLs.
+//!
+//! A [`TopicAcl`] reserves sensitive topics for holders of a capability,
+//! e.g. only `Capability::Coordinator` may publish mesh control. Peers prove
+//! what they hold by listing delegations in their status adverts; the grants
+//! that verify for a capability some rule requires are remembered until they
+//! expire or the peer's next advert replaces them. Messages on a reserved
+//! topic from an author without a matching grant are dropped and the author
+//! penalized. The node holds itself to the same rules: it does not publish
+//! on a reserved topic unless one of its own presented grants covers it.
+//!
+//! With no rules every topic stays open.
+
+use crate::auth::{Delegation, DelegationLimits};
+use crate::core::Capability;
+use crate::mycelium::TopicKind;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TopicAcl {
+    /// The capability each reserved topic requires of its publishers.
+    pub rules: HashMap<TopicKind, Capability>,
+    /// Verified grants by peer id, with when each expires.
+    grants: HashMap<String, Vec<(Capability, u64)>>,
+}
+
+impl TopicAcl {
+    pub fn require(mut self, kind: TopicKind, capability: Capability) -> Self {
+        self.rules.insert(kind, capability);
+        self
+    }
+
+    /// Replace `peer`'s grants with those among `tokens` that verify, now,
+    /// for a capability some rule requires. Returns how many were kept.
+    pub fn present(
+        &mut self,
+        peer: &str,
+        tokens: &[String],
+        roots: &[String],
+        limits: &DelegationLimits,
+        now: u64,
+    ) -> usize {
+        let required: Vec<&Capability> = self.rules.values().collect();
+        let verified: Vec<(Capability, u64)> = tokens
+            .iter()
+            .filter_map(|token| Delegation::decode(token).ok())
+            .flat_map(|grant| {
+                required
+                    .iter()
+                    .filter(|cap| grant.verify(peer, cap, roots, limits, now).is_ok())
+                    .map(|cap| ((*cap).clone(), grant.expires_at))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        let kept = verified.len();
+        if verified.is_empty() {
+            self.grants.remove(peer);
+        } else {
+            self.grants.insert(peer.to_string(), verified);
+        }
+        kept
+    }
+
+    /// Whether `peer` may publish on `kind`. Topics outside the known kinds
+    /// are not covered by the ACL.
+    pub fn permits(&self, peer: &str, kind: Option<TopicKind>, now: u64) -> bool {
+        let Some(required) = kind.and_then(|kind| self.rules.get(&kind)) else {
+            return true;
+        };
+        self.grants.get(peer).is_some_and(|grants| {
+            grants
+                .iter()
+                .any(|(cap, expires_at)| cap.satisfies(required) && now < *expires_at)
+        })
+    }
+
+    /// Reserved topics for which `holds` is false.
+    pub fn forbidden(&self, holds: impl Fn(&Capability) -> bool) -> HashSet<TopicKind> {
+        self.rules
+            .iter()
+            .filter(|(_, cap)| !holds(cap))
+            .map(|(kind, _)| *kind)
+            .collect()
+    }
+
+    pub fn forget(&mut self, peer: &str) {
+        self.grants.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{peer_id_of, unix_now};
+    use ed25519_dalek::SigningKey;
+    use std::time::Duration;
+
+    #[test]
+    fn only_granted_coordinators_publish_control() {
+        let root = SigningKey::from_bytes(&[1; 32]);
+        let coordinator = peer_id_of(&SigningKey::from_bytes(&[2; 32]));
+        let stranger = peer_id_of(&SigningKey::from_bytes(&[3; 32])).to_string();
+        let roots = vec![peer_id_of(&root).to_string()];
+        let now = unix_now();
+        let mut acl = TopicAcl::default().require(TopicKind::Control, Capability::Coordinator);
+        let tokens = [Delegation::mint(
+            &root,
+            &coordinator,
+            Capability::Coordinator,
+            now,
+            Duration::from_secs(60),
+            None,
+        )
+        .unwrap()
+        .encode()];
+        let coordinator = coordinator.to_string();
+        let limits = DelegationLimits::default();
+
+        // A token minted for someone else proves nothing for the presenter.
+        assert_eq!(acl.present(&stranger, &tokens, &roots, &limits, now), 0);
+        assert_eq!(acl.present(&coordinator, &tokens, &[], &limits, now), 0);
+        assert_eq!(acl.present(&coordinator, &tokens, &roots, &limits, now), 1);
+
+        assert!(acl.permits(&coordinator, Some(TopicKind::Control), now));
+        assert!(!acl.permits(&coordinator, Some(TopicKind::Control), now + 60));
+        assert!(!acl.permits(&stranger, Some(TopicKind::Control), now));
+        assert!(acl.permits(&stranger, Some(TopicKind::Status), now));
+        assert!(acl.permits(&stranger, None, now));
+
+        assert_eq!(
+            acl.forbidden(|_| false),
+            HashSet::from([TopicKind::Control])
+        );
+        assert!(acl
+            .forbidden(|cap| *cap == Capability::Coordinator)
+            .is_empty());
+
+        acl.forget(&coordinator);
+        assert!(!acl.permits(&coordinator, Some(TopicKind::Control), now));
+    }
+}
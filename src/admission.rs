@@ -0,0 +1,421 @@
+//! Admission control for peers joining the swarm.
+//!
+//! Under an open policy any peer that connects takes part. Otherwise a newly
+//! connected peer is pending until it sends a [`JoinRequest`] over the
+//! [`JOIN_PROTOCOL`] request-response protocol whose [`Credential`] satisfies
+//! the node's [`AdmissionPolicy`]:
+//!
+//! - an invitation: a delegation of `Capability::Join(network)` to the
+//!   joining peer, rooted at one of the policy's issuers; or
+//! - proof of work: a nonce for which SHA-256 over the network name, the
+//!   peer id and the nonce has the required number of leading zero bits.
+//!   Binding the peer id means every Sybil identity pays for its own nonce.
+//!
+//! A pending peer is kept out of `TopicMesh::known_peers`, and messages it
+//! relays on restricted topics are dropped and counted against it. Peers
+//! that fail the check, or send nothing within the handshake timeout, are
+//! disconnected and blacklisted in gossipsub.
+//!
+//! Whatever the policy, a peer whose long-term record has sunk past
+//! `ReputationConfig::shun_above` is refused as soon as it connects.
+
+use crate::auth::{unix_now, AuthError, Delegation, DelegationLimits};
+use crate::core::Capability;
+use crate::mycelium::TopicKind;
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+pub const JOIN_PROTOCOL: &str = "/hypha/join/1.0.0";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum AdmissionPolicy {
+    #[default]
+    Open,
+    /// Require a `Capability::Join` delegation rooted at one of `issuers`.
+    Invitation { issuers: Vec<String> },
+    /// Require a proof-of-work nonce with `difficulty` leading zero bits.
+    ProofOfWork { difficulty: u8 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Credential {
+    /// An encoded `Delegation`.
+    Invitation {
+        token: String,
+    },
+    Work {
+        nonce: u64,
+    },
+}
+
+impl Credential {
+    /// Solve the proof-of-work puzzle for `peer` joining `network`.
+    pub fn work(network: &str, peer: &PeerId, difficulty: u8) -> Self {
+        let nonce = (0..)
+            .find(|nonce| work_bits(network, peer, *nonce) >= u32::from(difficulty))
+            .expect("a nonce exists below u64::MAX");
+        Credential::Work { nonce }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JoinRequest {
+    pub credential: Option<Credential>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JoinResponse {
+    Admitted,
+    Refused { reason: String },
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum AdmissionError {
+    #[error("no credential presented")]
+    MissingCredential,
+    #[error("credential does not fit the admission policy")]
+    WrongCredential,
+    #[error("invitation rejected: {0}")]
+    Invitation(#[from] AuthError),
+    #[error("proof of work has fewer than {0} leading zero bits")]
+    InsufficientWork(u8),
+    #[error("no join request within {0:?}")]
+    Timeout(Duration),
+    #[error("peer has a record of misbehavior (standing {0:.1})")]
+    Reputation(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdmissionConfig {
+    pub policy: AdmissionPolicy,
+    /// Network name bound into invitations and proofs of work.
+    pub network: String,
+    /// Topics an unadmitted peer may not publish on.
+    pub restricted: Vec<TopicKind>,
+    pub handshake_timeout: Duration,
+    /// What this node presents when it joins others.
+    pub credential: Option<Credential>,
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            policy: AdmissionPolicy::Open,
+            network: "hypha".to_string(),
+            // Spikes and departures stay open: both are small, and a peer
+            // should be able to warn the swarm before it is admitted.
+            restricted: vec![
+                TopicKind::Status,
+                TopicKind::Control,
+                TopicKind::Task,
+                TopicKind::SharedState,
+                TopicKind::Sensor,
+                TopicKind::Quorum,
+            ],
+            handshake_timeout: Duration::from_secs(10),
+            credential: None,
+        }
+    }
+}
+
+/// Which connected peers have been admitted.
+#[derive(Debug, Default)]
+pub struct Admission {
+    pub config: AdmissionConfig,
+    admitted: HashSet<PeerId>,
+    /// Connected peers awaiting a join request, with when they connected.
+    pending: HashMap<PeerId, Instant>,
+}
+
+impl Admission {
+    pub fn new(config: AdmissionConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.config.policy == AdmissionPolicy::Open
+    }
+
+    /// Note a new connection. Returns whether the peer is already admitted;
+    /// if not, it is pending until `check` or `expired`.
+    pub fn connected(&mut self, peer: PeerId, now: Instant) -> bool {
+        if self.is_admitted(&peer) {
+            return true;
+        }
+        self.pending.entry(peer).or_insert(now);
+        false
+    }
+
+    pub fn disconnected(&mut self, peer: &PeerId) {
+        self.admitted.remove(peer);
+        self.pending.remove(peer);
+    }
+
+    pub fn is_admitted(&self, peer: &PeerId) -> bool {
+        self.is_open() || self.admitted.contains(peer)
+    }
+
+    /// Whether messages relayed by `peer` on `kind` are processed. Topics
+    /// outside the known kinds count as restricted.
+    pub fn may_publish(&self, peer: &PeerId, kind: Option<TopicKind>) -> bool {
+        self.is_admitted(peer) || kind.is_some_and(|kind| !self.config.restricted.contains(&kind))
+    }
+
+    /// Validate `peer`'s join request and admit it on success. Either way
+    /// the peer stops pending. A failed request revokes an earlier admission,
+    /// so the peer is refused and disconnected like any other. Invitations
+    /// are held to the node's delegation `limits`.
+    pub fn check(
+        &mut self,
+        peer: PeerId,
+        request: &JoinRequest,
+        limits: &DelegationLimits,
+    ) -> Result<(), AdmissionError> {
+        self.pending.remove(&peer);
+        if let Err(e) = self.validate(&peer, request.credential.as_ref(), limits) {
+            self.admitted.remove(&peer);
+            return Err(e);
+        }
+        self.admitted.insert(peer);
+        Ok(())
+    }
+
+    fn validate(
+        &self,
+        peer: &PeerId,
+        credential: Option<&Credential>,
+        limits: &DelegationLimits,
+    ) -> Result<(), AdmissionError> {
+        let network = &self.config.network;
+        match (&self.config.policy, credential) {
+            (AdmissionPolicy::Open, _) => Ok(()),
+            (_, None) => Err(AdmissionError::MissingCredential),
+            (AdmissionPolicy::Invitation { issuers }, Some(Credential::Invitation { token })) => {
+                Delegation::decode(token)?.verify(
+                    &peer.to_string(),
+                    &Capability::Join(network.clone()),
+                    issuers,
+                    limits,
+                    unix_now(),
+                )?;
+                Ok(())
+            }
+            (AdmissionPolicy::ProofOfWork { difficulty }, Some(Credential::Work { nonce })) => {
+                if work_bits(network, peer, *nonce) >= u32::from(*difficulty) {
+                    Ok(())
+                } else {
+                    Err(AdmissionError::InsufficientWork(*difficulty))
+                }
+            }
+            _ => Err(AdmissionError::WrongCredential),
+        }
+    }
+
+    /// Pending peers that have waited past the handshake timeout. They are
+    /// no longer pending.
+    pub fn expired(&mut self, now: Instant) -> Vec<PeerId> {
+        let timeout = self.config.handshake_timeout;
+        let expired: Vec<PeerId> = self
+            .pending
+            .iter()
+            .filter(|(_, since)| now.saturating_duration_since(**since) >= timeout)
+            .map(|(peer, _)| *peer)
+            .collect();
+        for peer in &expired {
+            self.pending.remove(peer);
+        }
+        expired
+    }
+}
+
+/// Leading zero bits of the proof-of-work hash for `nonce`.
+pub fn work_bits(network: &str, peer: &PeerId, nonce: u64) -> u32 {
+    let mut hasher = Sha256::new();
+    hasher.update(network.as_bytes());
+    hasher.update(peer.to_bytes());
+    hasher.update(nonce.to_be_bytes());
+    let mut bits = 0;
+    for byte in hasher.finalize() {
+        bits += byte.leading_zeros();
+        if byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::peer_id_of;
+    use ed25519_dalek::SigningKey;
+
+    fn gated(policy: AdmissionPolicy) -> Admission {
+        Admission::new(AdmissionConfig {
+            policy,
+            ..AdmissionConfig::default()
+        })
+    }
+
+    #[test]
+    fn proof_of_work_is_bound_to_the_peer() {
+        let joiner = peer_id_of(&SigningKey::from_bytes(&[1; 32]));
+        let sybil = peer_id_of(&SigningKey::from_bytes(&[2; 32]));
+        let mut admission = gated(AdmissionPolicy::ProofOfWork { difficulty: 8 });
+        let now = Instant::now();
+        assert!(!admission.connected(joiner, now));
+        assert!(!admission.may_publish(&joiner, Some(TopicKind::Task)));
+        assert!(admission.may_publish(&joiner, Some(TopicKind::Spike)));
+        assert!(!admission.may_publish(&joiner, None));
+
+        let request = JoinRequest {
+            credential: Some(Credential::work("hypha", &joiner, 8)),
+        };
+        assert_eq!(
+            admission.check(sybil, &request, &DelegationLimits::default()),
+            Err(AdmissionError::InsufficientWork(8))
+        );
+        assert_eq!(
+            admission.check(joiner, &request, &DelegationLimits::default()),
+            Ok(())
+        );
+        assert!(admission.may_publish(&joiner, Some(TopicKind::Task)));
+        assert!(admission.connected(joiner, now));
+
+        admission.disconnected(&joiner);
+        assert!(!admission.is_admitted(&joiner));
+    }
+
+    #[test]
+    fn a_bad_request_revokes_an_earlier_admission() {
+        let joiner = peer_id_of(&SigningKey::from_bytes(&[1; 32]));
+        let mut admission = gated(AdmissionPolicy::ProofOfWork { difficulty: 8 });
+        admission.connected(joiner, Instant::now());
+        let request = JoinRequest {
+            credential: Some(Credential::work("hypha", &joiner, 8)),
+        };
+        assert_eq!(
+            admission.check(joiner, &request, &DelegationLimits::default()),
+            Ok(())
+        );
+
+        assert_eq!(
+            admission.check(
+                joiner,
+                &JoinRequest { credential: None },
+                &DelegationLimits::default()
+            ),
+            Err(AdmissionError::MissingCredential)
+        );
+        assert!(!admission.is_admitted(&joiner));
+        assert!(!admission.may_publish(&joiner, Some(TopicKind::Task)));
+    }
+
+    #[test]
+    fn invitations_must_come_from_a_trusted_issuer() {
+        let (root, stranger, joiner) = (
+            SigningKey::from_bytes(&[1; 32]),
+            SigningKey::from_bytes(&[2; 32]),
+            SigningKey::from_bytes(&[3; 32]),
+        );
+        let joiner_id = peer_id_of(&joiner);
+        let invite = |issuer: &SigningKey, network: &str| JoinRequest {
+            credential: Some(Credential::Invitation {
+                token: Delegation::mint(
+                    issuer,
+                    &joiner_id,
+                    Capability::Join(network.to_string()),
+                    unix_now(),
+                    Duration::from_secs(3600),
+                    None,
+                )
+                .unwrap()
+                .encode(),
+            }),
+        };
+        let mut admission = gated(AdmissionPolicy::Invitation {
+            issuers: vec![peer_id_of(&root).to_string()],
+        });
+
+        assert!(matches!(
+            admission.check(
+                joiner_id,
+                &invite(&stranger, "hypha"),
+                &DelegationLimits::default()
+            ),
+            Err(AdmissionError::Invitation(AuthError::UntrustedRoot(_)))
+        ));
+        assert!(matches!(
+            admission.check(
+                joiner_id,
+                &invite(&root, "other"),
+                &DelegationLimits::default()
+            ),
+            Err(AdmissionError::Invitation(_))
+        ));
+        assert_eq!(
+            admission.check(
+                joiner_id,
+                &JoinRequest { credential: None },
+                &DelegationLimits::default()
+            ),
+            Err(AdmissionError::MissingCredential)
+        );
+        assert_eq!(
+            admission.check(
+                joiner_id,
+                &JoinRequest {
+                    credential: Some(Credential::Work { nonce: 0 })
+                },
+                &DelegationLimits::default()
+            ),
+            Err(AdmissionError::WrongCredential)
+        );
+        assert_eq!(
+            admission.check(
+                joiner_id,
+                &invite(&root, "hypha"),
+                &DelegationLimits::default()
+            ),
+            Ok(())
+        );
+
+        // The node's limits apply: an hour-long invitation is too long for a
+        // node that allows a minute.
+        let strict = DelegationLimits {
+            max_ttl: Duration::from_secs(60),
+            ..DelegationLimits::default()
+        };
+        assert!(matches!(
+            admission.check(joiner_id, &invite(&root, "hypha"), &strict),
+            Err(AdmissionError::Invitation(AuthError::TtlTooLong { .. }))
+        ));
+    }
+
+    #[test]
+    fn silent_peers_expire() {
+        let peer = peer_id_of(&SigningKey::from_bytes(&[1; 32]));
+        let mut admission = gated(AdmissionPolicy::ProofOfWork { difficulty: 1 });
+        let start = Instant::now();
+        admission.connected(peer, start);
+        assert!(admission.expired(start + Duration::from_secs(1)).is_empty());
+        assert_eq!(
+            admission.expired(start + Duration::from_secs(10)),
+            vec![peer]
+        );
+        assert!(admission
+            .expired(start + Duration::from_secs(20))
+            .is_empty());
+
+        let mut open = Admission::default();
+        assert!(open.connected(peer, start));
+    }
+}
@@ -0,0 +1,207 @@
+//! Sliding-window sensor aggregation before gossip.
+//!
+//! Publishing every sample would drown the mesh, so readings are collected
+//! per (source, sensor) series and summarized over a window: the published
+//! [`SensorReading`] carries the latest value plus a [`ReadingSummary`] with
+//! min/max/mean/count. How often each series is reported depends on the
+//! node's power mode; in critical mode nothing is reported by default.
+
+use crate::core::{PowerMode, ReadingSummary, SensorReading};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+/// Minimum time between reports of one series, per power mode. `None`
+/// suppresses reports in that mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportingCadence {
+    pub normal: Option<Duration>,
+    pub low_battery: Option<Duration>,
+    pub critical: Option<Duration>,
+}
+
+impl Default for ReportingCadence {
+    fn default() -> Self {
+        Self {
+            normal: Some(Duration::from_secs(10)),
+            low_battery: Some(Duration::from_secs(60)),
+            critical: None,
+        }
+    }
+}
+
+impl ReportingCadence {
+    pub fn interval(&self, mode: &PowerMode) -> Option<Duration> {
+        match mode {
+            PowerMode::Normal => self.normal,
+            PowerMode::LowBattery => self.low_battery,
+            PowerMode::Critical => self.critical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregationConfig {
+    /// Samples older than this are dropped from the summary.
+    pub window: Duration,
+    pub cadence: ReportingCadence,
+    /// Cap on buffered samples per series; the oldest are dropped first.
+    pub max_samples: usize,
+}
+
+impl Default for AggregationConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            cadence: ReportingCadence::default(),
+            max_samples: 1024,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Series {
+    samples: VecDeque<(Instant, f32)>,
+    latest: SensorReading,
+    last_report: Option<Instant>,
+    /// Samples arrived since the last report.
+    fresh: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct SensorAggregator {
+    pub config: AggregationConfig,
+    series: HashMap<(String, String), Series>,
+}
+
+impl SensorAggregator {
+    pub fn new(config: AggregationConfig) -> Self {
+        Self {
+            config,
+            series: HashMap::new(),
+        }
+    }
+
+    /// Buffer one raw sample. Non-finite values are dropped.
+    pub fn record(&mut self, reading: SensorReading, now: Instant) {
+        if !reading.value.is_finite() {
+            return;
+        }
+        let key = (reading.source_id.clone(), reading.sensor.clone());
+        let series = self.series.entry(key).or_insert_with(|| Series {
+            samples: VecDeque::new(),
+            latest: reading.clone(),
+            last_report: None,
+            fresh: false,
+        });
+        if series.samples.len() >= self.config.max_samples.max(1) {
+            series.samples.pop_front();
+        }
+        series.samples.push_back((now, reading.value));
+        series.latest = reading;
+        series.fresh = true;
+    }
+
+    /// Aggregated readings for every series with new samples whose report
+    /// interval in `mode` has elapsed.
+    pub fn due(&mut self, mode: &PowerMode, now: Instant) -> Vec<SensorReading> {
+        let window = self.config.window;
+        for series in self.series.values_mut() {
+            while series
+                .samples
+                .front()
+                .is_some_and(|(at, _)| now.saturating_duration_since(*at) > window)
+            {
+                series.samples.pop_front();
+            }
+        }
+        self.series.retain(|_, s| !s.samples.is_empty());
+
+        let Some(interval) = self.config.cadence.interval(mode) else {
+            return Vec::new();
+        };
+        let mut due = Vec::new();
+        for series in self.series.values_mut() {
+            let waited = series
+                .last_report
+                .is_none_or(|at| now.saturating_duration_since(at) >= interval);
+            if !series.fresh || !waited {
+                continue;
+            }
+            let values = series.samples.iter().map(|(_, v)| *v);
+            let (min, max, sum) = values.fold(
+                (f32::INFINITY, f32::NEG_INFINITY, 0.0f64),
+                |(min, max, sum), v| (min.min(v), max.max(v), sum + f64::from(v)),
+            );
+            let count = series.samples.len();
+            let mut reading = series.latest.clone();
+            reading.summary = Some(ReadingSummary {
+                min,
+                max,
+                mean: (sum / count as f64) as f32,
+                count: count as u32,
+                window_ms: window.as_millis() as u64,
+            });
+            due.push(reading);
+            series.last_report = Some(now);
+            series.fresh = false;
+        }
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp(value: f32) -> SensorReading {
+        SensorReading::new("n1".to_string(), "temp".to_string(), value)
+    }
+
+    #[test]
+    fn reports_summary_once_per_cadence() {
+        let mut agg = SensorAggregator::default();
+        let t0 = Instant::now();
+        for (i, v) in [20.0, 24.0, 22.0].into_iter().enumerate() {
+            agg.record(temp(v), t0 + Duration::from_secs(i as u64));
+        }
+
+        let due = agg.due(&PowerMode::Normal, t0 + Duration::from_secs(2));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].value, 22.0);
+        let summary = due[0].summary.unwrap();
+        assert_eq!((summary.min, summary.max, summary.mean), (20.0, 24.0, 22.0));
+        assert_eq!(summary.count, 3);
+
+        agg.record(temp(30.0), t0 + Duration::from_secs(5));
+        assert!(agg
+            .due(&PowerMode::Normal, t0 + Duration::from_secs(5))
+            .is_empty());
+        assert_eq!(
+            agg.due(&PowerMode::Normal, t0 + Duration::from_secs(12))
+                .len(),
+            1
+        );
+        // Nothing new since that report.
+        assert!(agg
+            .due(&PowerMode::Normal, t0 + Duration::from_secs(30))
+            .is_empty());
+    }
+
+    #[test]
+    fn window_drops_old_samples_and_critical_mode_is_silent() {
+        let mut agg = SensorAggregator::new(AggregationConfig {
+            window: Duration::from_secs(10),
+            ..AggregationConfig::default()
+        });
+        let t0 = Instant::now();
+        agg.record(temp(100.0), t0);
+        agg.record(temp(1.0), t0 + Duration::from_secs(15));
+
+        assert!(agg
+            .due(&PowerMode::Critical, t0 + Duration::from_secs(15))
+            .is_empty());
+        let due = agg.due(&PowerMode::LowBattery, t0 + Duration::from_secs(15));
+        assert_eq!(due[0].summary.unwrap().max, 1.0);
+        assert_eq!(due[0].summary.unwrap().count, 1);
+    }
+}
@@ -0,0 +1,314 @@
+//! Online anomaly detection on peer behavior.
+//!
+//! For each peer the detector keeps an exponentially weighted mean and
+//! variance of three per-heartbeat metrics: message rate, duplicate ratio and
+//! malformed-message rate. When a fresh interval lands more than
+//! `z_threshold` deviations above the peer's own baseline, the peer is
+//! reported as an [`Anomaly`]; the run loop penalizes its mesh score for a
+//! while and emits an event. Anomalous intervals are kept out of the baseline
+//! so a misbehaving peer cannot teach the detector its new normal.
+//!
+//! Before each tick the run loop also hands the interval's raw offenses to
+//! the peer's long-term [`Reputation`](crate::reputation::Reputation).
+
+use crate::reputation::Offenses;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+/// Message ids remembered for duplicate detection.
+const RECENT_IDS: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BehaviorMetric {
+    MessageRate,
+    DuplicateRatio,
+    MalformedRate,
+}
+
+impl BehaviorMetric {
+    const ALL: [BehaviorMetric; 3] = [
+        BehaviorMetric::MessageRate,
+        BehaviorMetric::DuplicateRatio,
+        BehaviorMetric::MalformedRate,
+    ];
+
+    /// Smallest deviation used for z-scores, so a perfectly steady baseline
+    /// does not turn noise into an alarm.
+    fn min_deviation(self) -> f64 {
+        match self {
+            BehaviorMetric::MessageRate => 1.0,
+            BehaviorMetric::DuplicateRatio => 0.05,
+            BehaviorMetric::MalformedRate => 0.2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnomalyConfig {
+    /// EWMA smoothing factor in (0, 1].
+    pub alpha: f64,
+    pub z_threshold: f64,
+    /// Intervals of history needed before a peer can be flagged.
+    pub warmup: u32,
+    /// Score deduction applied to a flagged peer.
+    pub penalty: f32,
+    pub penalty_for: Duration,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            alpha: 0.2,
+            z_threshold: 4.0,
+            warmup: 5,
+            penalty: 0.5,
+            penalty_for: Duration::from_secs(300),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Anomaly {
+    pub peer: String,
+    pub metric: BehaviorMetric,
+    pub value: f64,
+    pub z_score: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Ewma {
+    mean: f64,
+    var: f64,
+}
+
+impl Ewma {
+    fn z_score(&self, x: f64, min_deviation: f64) -> f64 {
+        (x - self.mean) / self.var.sqrt().max(min_deviation)
+    }
+
+    fn update(&mut self, x: f64, alpha: f64) {
+        let diff = x - self.mean;
+        self.mean += alpha * diff;
+        self.var = (1.0 - alpha) * (self.var + alpha * diff * diff);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Interval {
+    messages: u32,
+    duplicates: u32,
+    malformed: u32,
+    unsolicited: u32,
+}
+
+#[derive(Debug, Default)]
+struct Baseline {
+    stats: [Ewma; 3],
+    intervals: u32,
+}
+
+#[derive(Debug, Default)]
+pub struct AnomalyDetector {
+    pub config: AnomalyConfig,
+    current: HashMap<String, Interval>,
+    baselines: HashMap<String, Baseline>,
+    recent_ids: HashSet<String>,
+    recent_order: VecDeque<String>,
+}
+
+impl AnomalyDetector {
+    pub fn new(config: AnomalyConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Returns whether `msg_id` was a duplicate.
+    pub fn record_message(&mut self, peer: &str, msg_id: &str) -> bool {
+        let interval = self.current.entry(peer.to_string()).or_default();
+        interval.messages += 1;
+        if !self.recent_ids.insert(msg_id.to_string()) {
+            interval.duplicates += 1;
+            return true;
+        }
+        self.recent_order.push_back(msg_id.to_string());
+        if self.recent_order.len() > RECENT_IDS {
+            if let Some(old) = self.recent_order.pop_front() {
+                self.recent_ids.remove(&old);
+            }
+        }
+        false
+    }
+
+    pub fn record_malformed(&mut self, peer: &str) {
+        self.current.entry(peer.to_string()).or_default().malformed += 1;
+    }
+
+    /// A message `peer` should not have sent, such as one relayed before it
+    /// was admitted. It counts toward the malformed rate.
+    pub fn record_unsolicited(&mut self, peer: &str) {
+        let interval = self.current.entry(peer.to_string()).or_default();
+        interval.malformed += 1;
+        interval.unsolicited += 1;
+    }
+
+    /// Offenses in the interval so far, by peer. Call before `tick`.
+    pub fn offenses(&self) -> Vec<(String, Offenses)> {
+        self.current
+            .iter()
+            .map(|(peer, i)| {
+                let offenses = Offenses {
+                    duplicates: f64::from(i.duplicates),
+                    malformed: f64::from(i.malformed - i.unsolicited),
+                    unsolicited: f64::from(i.unsolicited),
+                };
+                (peer.clone(), offenses)
+            })
+            .filter(|(_, offenses)| !offenses.is_empty())
+            .collect()
+    }
+
+    /// Drop all state for a departed peer.
+    pub fn forget(&mut self, peer: &str) {
+        self.current.remove(peer);
+        self.baselines.remove(peer);
+    }
+
+    /// Close the interval that lasted `elapsed` and return peers whose
+    /// behavior in it was anomalous.
+    pub fn tick(&mut self, elapsed: Duration) -> Vec<Anomaly> {
+        let secs = elapsed.as_secs_f64().max(1e-3);
+        let current = std::mem::take(&mut self.current);
+        let peers: HashSet<String> = current
+            .keys()
+            .chain(self.baselines.keys())
+            .cloned()
+            .collect();
+
+        let mut anomalies = Vec::new();
+        for peer in peers {
+            let interval = current.get(&peer);
+            let observed = interval.map_or([0.0; 3], |i| {
+                [
+                    f64::from(i.messages) / secs,
+                    if i.messages == 0 {
+                        0.0
+                    } else {
+                        f64::from(i.duplicates) / f64::from(i.messages)
+                    },
+                    f64::from(i.malformed) / secs,
+                ]
+            });
+            let baseline = self.baselines.entry(peer.clone()).or_default();
+
+            let mut flagged = false;
+            if baseline.intervals >= self.config.warmup {
+                for (index, metric) in BehaviorMetric::ALL.into_iter().enumerate() {
+                    let z_score =
+                        baseline.stats[index].z_score(observed[index], metric.min_deviation());
+                    if z_score > self.config.z_threshold {
+                        flagged = true;
+                        anomalies.push(Anomaly {
+                            peer: peer.clone(),
+                            metric,
+                            value: observed[index],
+                            z_score,
+                        });
+                    }
+                }
+            }
+            if !flagged {
+                for (stat, x) in baseline.stats.iter_mut().zip(observed) {
+                    stat.update(x, self.config.alpha);
+                }
+                baseline.intervals = baseline.intervals.saturating_add(1);
+            }
+        }
+        anomalies
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    /// Ten quiet intervals of five fresh messages per peer.
+    fn steady(detector: &mut AnomalyDetector, peers: &[&str], next_id: &mut u32) {
+        for _ in 0..10 {
+            for peer in peers {
+                for _ in 0..5 {
+                    detector.record_message(peer, &next_id.to_string());
+                    *next_id += 1;
+                }
+            }
+            assert!(detector.tick(SECOND).is_empty());
+        }
+    }
+
+    #[test]
+    fn flood_after_warmup_is_flagged() {
+        let mut detector = AnomalyDetector::default();
+        let mut id = 0;
+        steady(&mut detector, &["p1"], &mut id);
+
+        for _ in 0..200 {
+            detector.record_message("p1", &id.to_string());
+            id += 1;
+        }
+        let anomalies = detector.tick(SECOND);
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].metric, BehaviorMetric::MessageRate);
+        // The flood did not shift the baseline.
+        for _ in 0..200 {
+            detector.record_message("p1", &id.to_string());
+            id += 1;
+        }
+        assert_eq!(detector.tick(SECOND).len(), 1);
+    }
+
+    #[test]
+    fn duplicates_and_malformed_are_tracked_separately() {
+        let mut detector = AnomalyDetector::default();
+        let mut id = 0;
+        steady(&mut detector, &["p1", "p2"], &mut id);
+
+        for _ in 0..5 {
+            detector.record_message("p1", "replayed");
+            detector.record_message("p2", &id.to_string());
+            detector.record_malformed("p2");
+            id += 1;
+        }
+        let mut offenses = detector.offenses();
+        offenses.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(offenses[0].1.duplicates, 4.0);
+        assert_eq!(offenses[1].1.malformed, 5.0);
+
+        let mut metrics: Vec<_> = detector
+            .tick(SECOND)
+            .into_iter()
+            .map(|a| (a.peer, a.metric))
+            .collect();
+        metrics.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            metrics,
+            vec![
+                ("p1".to_string(), BehaviorMetric::DuplicateRatio),
+                ("p2".to_string(), BehaviorMetric::MalformedRate),
+            ]
+        );
+    }
+
+    #[test]
+    fn new_peers_are_not_flagged_during_warmup() {
+        let mut detector = AnomalyDetector::default();
+        for i in 0..500 {
+            detector.record_message("p1", &i.to_string());
+        }
+        assert!(detector.tick(SECOND).is_empty());
+    }
+}
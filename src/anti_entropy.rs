@@ -0,0 +1,121 @@
+//! Adaptive anti-entropy scheduling for the shared CRDT state.
+//!
+//! A fixed chance of broadcasting `SyncStep1` each heartbeat wastes bandwidth
+//! once every replica has converged, and heals too slowly after a partition.
+//! [`AntiEntropy`] instead tracks evidence of divergence: an applied update
+//! that changed local state, or a peer state vector that differs from ours.
+//! Any such evidence raises the sync probability to its ceiling; every quiet
+//! heartbeat after that halves it, down to a floor.
+
+use rand::Rng;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AntiEntropyConfig {
+    /// Probability on a quiet, converged node.
+    pub min_probability: f64,
+    /// Probability right after divergence was observed.
+    pub max_probability: f64,
+    /// Factor applied to the probability on each quiet heartbeat.
+    pub backoff: f64,
+}
+
+impl Default for AntiEntropyConfig {
+    fn default() -> Self {
+        Self {
+            min_probability: 0.01,
+            max_probability: 0.5,
+            backoff: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AntiEntropy {
+    pub config: AntiEntropyConfig,
+    probability: f64,
+    diverged: bool,
+}
+
+impl Default for AntiEntropy {
+    fn default() -> Self {
+        Self::new(AntiEntropyConfig::default())
+    }
+}
+
+impl AntiEntropy {
+    /// Starts at the ceiling, so a fresh node pulls state promptly.
+    pub fn new(config: AntiEntropyConfig) -> Self {
+        Self {
+            probability: config.max_probability,
+            config,
+            diverged: false,
+        }
+    }
+
+    /// Chance of syncing on the next heartbeat, before that heartbeat's
+    /// adjustment.
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+
+    /// Note that local state changed through a remote update, or a peer
+    /// reported a state vector different from ours.
+    pub fn record_divergence(&mut self) {
+        self.diverged = true;
+    }
+
+    /// Close one heartbeat: adjust the probability from what was observed
+    /// since the last call and decide whether to broadcast `SyncStep1`.
+    pub fn tick(&mut self, rng: &mut impl Rng) -> bool {
+        let AntiEntropyConfig {
+            min_probability,
+            max_probability,
+            backoff,
+        } = self.config;
+        self.probability = if std::mem::take(&mut self.diverged) {
+            max_probability
+        } else {
+            (self.probability * backoff).max(min_probability)
+        };
+        let probability = self.probability.clamp(0.0, 1.0);
+        rng.random_bool(probability)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_when_quiet_and_recovers_on_divergence() {
+        let mut rng = rand::rng();
+        let mut ae = AntiEntropy::default();
+        let mut last = ae.probability();
+        for _ in 0..3 {
+            ae.tick(&mut rng);
+            assert!(ae.probability() < last);
+            last = ae.probability();
+        }
+        for _ in 0..20 {
+            ae.tick(&mut rng);
+        }
+        assert_eq!(ae.probability(), ae.config.min_probability);
+
+        ae.record_divergence();
+        ae.tick(&mut rng);
+        assert_eq!(ae.probability(), ae.config.max_probability);
+    }
+
+    #[test]
+    fn sync_rate_tracks_probability() {
+        let mut rng = rand::rng();
+        let mut ae = AntiEntropy::new(AntiEntropyConfig {
+            min_probability: 0.0,
+            max_probability: 1.0,
+            backoff: 0.0,
+        });
+        ae.record_divergence();
+        assert!(ae.tick(&mut rng));
+        assert!((0..100).all(|_| !ae.tick(&mut rng)));
+    }
+}
@@ -0,0 +1,168 @@
+//! Device attestation.
+//!
+//! A node may attach an [`Attestation`] (a TPM or secure-element quote, say)
+//! to its status adverts. Judging the evidence is fleet-specific, so the
+//! operator plugs in an [`AttestationVerifier`]. The [`AttestationGate`]
+//! runs it on each authenticated advert and remembers which peers passed;
+//! topics it reserves then only accept messages from attested authors, and
+//! tasks are only awarded to attested winners when it requires so.
+//!
+//! A gate that reserves nothing admits every peer, attested or not. The
+//! status topic is never reserved, since the adverts on it carry the
+//! attestations.
+
+use crate::core::Attestation;
+use crate::mycelium::TopicKind;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AttestationError {
+    #[error("peer presented no attestation")]
+    Missing,
+    #[error("no verifier configured")]
+    NoVerifier,
+    #[error("unsupported attestation format `{0}`")]
+    UnsupportedFormat(String),
+    #[error("attestation rejected: {0}")]
+    Rejected(String),
+}
+
+/// Checks a peer's attestation evidence.
+pub trait AttestationVerifier: Debug + Send + Sync {
+    /// Accept `attestation` as proof that `peer` runs on trusted hardware.
+    fn verify(&self, peer: &str, attestation: &Attestation) -> Result<(), AttestationError>;
+}
+
+#[derive(Debug, Default)]
+pub struct AttestationGate {
+    verifier: Option<Arc<dyn AttestationVerifier>>,
+    /// Topics only attested peers may publish on.
+    pub topics: HashSet<TopicKind>,
+    /// Whether tasks may only be awarded to attested peers.
+    pub awards: bool,
+    attested: HashSet<String>,
+}
+
+impl AttestationGate {
+    pub fn new(verifier: Arc<dyn AttestationVerifier>) -> Self {
+        Self {
+            verifier: Some(verifier),
+            ..Self::default()
+        }
+    }
+
+    pub fn require_for_topic(mut self, kind: TopicKind) -> Self {
+        self.topics.insert(kind);
+        self
+    }
+
+    pub fn require_for_awards(mut self) -> Self {
+        self.awards = true;
+        self
+    }
+
+    /// Judge the attestation in `peer`'s latest advert. A missing or failing
+    /// one revokes any earlier pass.
+    pub fn present(
+        &mut self,
+        peer: &str,
+        attestation: Option<&Attestation>,
+    ) -> Result<(), AttestationError> {
+        let verified = attestation
+            .ok_or(AttestationError::Missing)
+            .and_then(|attestation| {
+                self.verifier
+                    .as_ref()
+                    .ok_or(AttestationError::NoVerifier)?
+                    .verify(peer, attestation)
+            });
+        if verified.is_ok() {
+            self.attested.insert(peer.to_string());
+        } else {
+            self.attested.remove(peer);
+        }
+        verified
+    }
+
+    pub fn is_attested(&self, peer: &str) -> bool {
+        self.attested.contains(peer)
+    }
+
+    /// Whether `peer` may publish on `kind`.
+    pub fn permits(&self, peer: &str, kind: Option<TopicKind>) -> bool {
+        let reserved =
+            kind.is_some_and(|kind| kind != TopicKind::Status && self.topics.contains(&kind));
+        !reserved || self.is_attested(peer)
+    }
+
+    /// Whether a task may be awarded to `peer`.
+    pub fn may_award(&self, peer: &str) -> bool {
+        !self.awards || self.is_attested(peer)
+    }
+
+    pub fn forget(&mut self, peer: &str) {
+        self.attested.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Accepts quotes that spell out the peer id.
+    #[derive(Debug)]
+    struct EchoVerifier;
+
+    impl AttestationVerifier for EchoVerifier {
+        fn verify(&self, peer: &str, attestation: &Attestation) -> Result<(), AttestationError> {
+            if attestation.format != "echo" {
+                return Err(AttestationError::UnsupportedFormat(
+                    attestation.format.clone(),
+                ));
+            }
+            if attestation.evidence != peer.as_bytes() {
+                return Err(AttestationError::Rejected(
+                    "quote names another peer".into(),
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    fn echo(peer: &str) -> Attestation {
+        Attestation {
+            format: "echo".to_string(),
+            evidence: peer.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn reserved_topics_and_awards_need_a_verified_attestation() {
+        let mut gate = AttestationGate::new(Arc::new(EchoVerifier))
+            .require_for_topic(TopicKind::Control)
+            .require_for_topic(TopicKind::Status)
+            .require_for_awards();
+        assert!(gate.present("a", Some(&echo("a"))).is_ok());
+        assert_eq!(
+            gate.present("b", Some(&echo("a"))),
+            Err(AttestationError::Rejected(
+                "quote names another peer".into()
+            ))
+        );
+        assert_eq!(gate.present("c", None), Err(AttestationError::Missing));
+
+        assert!(gate.permits("a", Some(TopicKind::Control)));
+        assert!(!gate.permits("b", Some(TopicKind::Control)));
+        assert!(gate.permits("c", Some(TopicKind::Status)));
+        assert!(gate.may_award("a") && !gate.may_award("c"));
+
+        // A later advert without the quote revokes the pass.
+        assert!(gate.present("a", None).is_err());
+        assert!(!gate.may_award("a"));
+
+        let open = AttestationGate::default();
+        assert!(open.permits("b", Some(TopicKind::Control)) && open.may_award("b"));
+    }
+}
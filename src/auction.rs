@@ -0,0 +1,359 @@
+//! The task auction as an explicit state machine.
+//!
+//! An auction runs through [`Phase`]s: the task is announced, bids come in,
+//! the auctioneer closes bidding and awards the best bid, the winner
+//! confirms and works, and the first result completes the task. Bidding may
+//! also run dry, be cancelled or outlive its deadline, which expires the
+//! auction. A winner that declines or lets its lease lapse sends the auction
+//! back to bidding, at most `max_reopens` times.
+//!
+//! [`AuctionStateMachine::step`] is pure: it takes the machine and an
+//! [`AuctionEvent`] and returns the next machine and what the auctioneer
+//! should do about it, or a [`Rejected`] leaving the machine as it was.
+//! Which phase an event may move an auction to is listed in [`TRANSITIONS`];
+//! `step` refuses anything not in the table, so the table is the protocol
+//! and the guards only pick among its rows. That makes the auction easy to
+//! model-test with arbitrary event sequences; see `tests/auction_model.rs`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use EventKind as E;
+use Phase as P;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Phase {
+    Announced,
+    Bidding,
+    Awarded,
+    Executing,
+    Completed,
+    Expired,
+}
+
+impl Phase {
+    /// Whether no event moves the auction any further.
+    pub fn is_terminal(self) -> bool {
+        matches!(self, Phase::Completed | Phase::Expired)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuctionEvent {
+    /// A bid, or a better one from a bidder already in.
+    Bid {
+        bidder: String,
+        score: f32,
+    },
+    /// The auctioneer stops taking bids and awards the best.
+    Close,
+    /// The winner confirms it is working: its first renewal, or any later.
+    Accept {
+        worker: String,
+    },
+    /// The winner will not take the task.
+    Decline {
+        worker: String,
+    },
+    /// The winner's lease lapsed without a renewal.
+    Lapse,
+    Result {
+        worker: String,
+    },
+    Cancel,
+    Deadline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Bid,
+    Close,
+    Accept,
+    Decline,
+    Lapse,
+    Result,
+    Cancel,
+    Deadline,
+}
+
+impl AuctionEvent {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            AuctionEvent::Bid { .. } => EventKind::Bid,
+            AuctionEvent::Close => EventKind::Close,
+            AuctionEvent::Accept { .. } => EventKind::Accept,
+            AuctionEvent::Decline { .. } => EventKind::Decline,
+            AuctionEvent::Lapse => EventKind::Lapse,
+            AuctionEvent::Result { .. } => EventKind::Result,
+            AuctionEvent::Cancel => EventKind::Cancel,
+            AuctionEvent::Deadline => EventKind::Deadline,
+        }
+    }
+}
+
+/// Every legal move as (from, event, to). Where a row pair shares a from
+/// and an event, a guard picks one: closing with no bids expires the
+/// auction, and a decline or lapse past `max_reopens` abandons it.
+pub const TRANSITIONS: &[(Phase, EventKind, Phase)] = &[
+    (P::Announced, E::Bid, P::Bidding),
+    (P::Announced, E::Close, P::Expired),
+    (P::Announced, E::Cancel, P::Expired),
+    (P::Announced, E::Deadline, P::Expired),
+    (P::Bidding, E::Bid, P::Bidding),
+    (P::Bidding, E::Close, P::Awarded),
+    (P::Bidding, E::Close, P::Expired),
+    // A late result from an earlier winner still settles the task.
+    (P::Bidding, E::Result, P::Completed),
+    (P::Bidding, E::Cancel, P::Expired),
+    (P::Bidding, E::Deadline, P::Expired),
+    (P::Awarded, E::Accept, P::Executing),
+    (P::Awarded, E::Decline, P::Bidding),
+    (P::Awarded, E::Decline, P::Expired),
+    (P::Awarded, E::Lapse, P::Bidding),
+    (P::Awarded, E::Lapse, P::Expired),
+    (P::Awarded, E::Result, P::Completed),
+    (P::Awarded, E::Cancel, P::Expired),
+    (P::Awarded, E::Deadline, P::Expired),
+    (P::Executing, E::Accept, P::Executing),
+    (P::Executing, E::Decline, P::Bidding),
+    (P::Executing, E::Decline, P::Expired),
+    (P::Executing, E::Lapse, P::Bidding),
+    (P::Executing, E::Lapse, P::Expired),
+    (P::Executing, E::Result, P::Completed),
+    (P::Executing, E::Cancel, P::Expired),
+    (P::Executing, E::Deadline, P::Expired),
+];
+
+/// Whether the table has a row from `from` on `event` to `to`.
+pub fn permits(from: Phase, event: EventKind, to: Phase) -> bool {
+    TRANSITIONS.contains(&(from, event, to))
+}
+
+/// What the auctioneer does after a step.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    /// Announce the award to `winner`.
+    Award { winner: String },
+    /// Publish the task again for fresh bids.
+    Reopen,
+    /// Take `worker`'s result as the task's.
+    Settle { worker: String },
+    /// Give up on the task.
+    Abandon,
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Rejected {
+    #[error("{event:?} is not allowed in {phase:?}")]
+    Illegal { phase: Phase, event: EventKind },
+    #[error("{worker} does not hold the award")]
+    NotWinner { worker: String },
+    #[error("bid from {bidder} has no usable score")]
+    InvalidBid { bidder: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuctionStateMachine {
+    pub phase: Phase,
+    /// Live bids by bidder. A winner that declines or lapses loses its bid.
+    pub bids: BTreeMap<String, f32>,
+    /// Holder of the current award, in `Awarded` and `Executing`.
+    pub winner: Option<String>,
+    /// Everyone awarded so far, whose results still settle the task.
+    pub awarded: BTreeSet<String>,
+    /// Who settled the task, once `Completed`.
+    pub settled_by: Option<String>,
+    pub reopens: u32,
+    pub max_reopens: u32,
+}
+
+impl AuctionStateMachine {
+    pub fn new(max_reopens: u32) -> Self {
+        Self {
+            phase: Phase::Announced,
+            bids: BTreeMap::new(),
+            winner: None,
+            awarded: BTreeSet::new(),
+            settled_by: None,
+            reopens: 0,
+            max_reopens,
+        }
+    }
+
+    /// The best live bid: highest score, then lowest bidder id.
+    pub fn leader(&self) -> Option<(&str, f32)> {
+        self.bids
+            .iter()
+            .max_by(|(a, x), (b, y)| x.total_cmp(y).then_with(|| b.cmp(a)))
+            .map(|(bidder, score)| (bidder.as_str(), *score))
+    }
+
+    /// The machine after `event`, and what to do about it.
+    pub fn step(&self, event: &AuctionEvent) -> Result<(Self, Option<Effect>), Rejected> {
+        let kind = event.kind();
+        let illegal = Rejected::Illegal {
+            phase: self.phase,
+            event: kind,
+        };
+        if !TRANSITIONS
+            .iter()
+            .any(|(from, on, _)| *from == self.phase && *on == kind)
+        {
+            return Err(illegal);
+        }
+        let mut next = self.clone();
+        let effect = match event {
+            AuctionEvent::Bid { bidder, score } => {
+                if !score.is_finite() {
+                    return Err(Rejected::InvalidBid {
+                        bidder: bidder.clone(),
+                    });
+                }
+                let best = next.bids.entry(bidder.clone()).or_insert(*score);
+                *best = best.max(*score);
+                next.phase = Phase::Bidding;
+                None
+            }
+            AuctionEvent::Close => match self.leader() {
+                Some((winner, _)) => {
+                    next.phase = Phase::Awarded;
+                    next.winner = Some(winner.to_string());
+                    next.awarded.insert(winner.to_string());
+                    Some(Effect::Award {
+                        winner: winner.to_string(),
+                    })
+                }
+                None => {
+                    next.phase = Phase::Expired;
+                    Some(Effect::Abandon)
+                }
+            },
+            AuctionEvent::Accept { worker } => {
+                self.check_winner(worker)?;
+                next.phase = Phase::Executing;
+                None
+            }
+            AuctionEvent::Decline { worker } => {
+                self.check_winner(worker)?;
+                Some(next.reopen())
+            }
+            AuctionEvent::Lapse => Some(next.reopen()),
+            AuctionEvent::Result { worker } => {
+                if !self.awarded.contains(worker) {
+                    return Err(Rejected::NotWinner {
+                        worker: worker.clone(),
+                    });
+                }
+                next.phase = Phase::Completed;
+                next.winner = None;
+                next.settled_by = Some(worker.clone());
+                Some(Effect::Settle {
+                    worker: worker.clone(),
+                })
+            }
+            AuctionEvent::Cancel | AuctionEvent::Deadline => {
+                next.phase = Phase::Expired;
+                next.winner = None;
+                Some(Effect::Abandon)
+            }
+        };
+        if !permits(self.phase, kind, next.phase) {
+            return Err(illegal);
+        }
+        Ok((next, effect))
+    }
+
+    fn check_winner(&self, worker: &str) -> Result<(), Rejected> {
+        if self.winner.as_deref() == Some(worker) {
+            Ok(())
+        } else {
+            Err(Rejected::NotWinner {
+                worker: worker.to_string(),
+            })
+        }
+    }
+
+    /// Drop the current winner and its bid and go back to bidding, or give
+    /// up once the task has been re-opened `max_reopens` times.
+    fn reopen(&mut self) -> Effect {
+        if let Some(winner) = self.winner.take() {
+            self.bids.remove(&winner);
+        }
+        if self.reopens >= self.max_reopens {
+            self.phase = Phase::Expired;
+            return Effect::Abandon;
+        }
+        self.reopens += 1;
+        self.phase = Phase::Bidding;
+        Effect::Reopen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bid(bidder: &str, score: f32) -> AuctionEvent {
+        AuctionEvent::Bid {
+            bidder: bidder.into(),
+            score,
+        }
+    }
+
+    fn run(machine: AuctionStateMachine, events: &[AuctionEvent]) -> AuctionStateMachine {
+        events.iter().fold(machine, |machine, event| {
+            machine.step(event).expect("legal step").0
+        })
+    }
+
+    #[test]
+    fn best_bid_wins_and_a_decline_passes_the_task_on() {
+        let machine = run(
+            AuctionStateMachine::new(1),
+            &[bid("a", 0.4), bid("b", 0.9), bid("c", 0.9)],
+        );
+        assert_eq!(machine.leader(), Some(("b", 0.9)));
+        let (machine, effect) = machine.step(&AuctionEvent::Close).unwrap();
+        assert_eq!(effect, Some(Effect::Award { winner: "b".into() }));
+
+        let accept_a = AuctionEvent::Accept { worker: "a".into() };
+        assert!(matches!(
+            machine.step(&accept_a),
+            Err(Rejected::NotWinner { .. })
+        ));
+        let decline = AuctionEvent::Decline { worker: "b".into() };
+        let (machine, effect) = machine.step(&decline).unwrap();
+        assert_eq!(
+            (machine.phase, effect),
+            (Phase::Bidding, Some(Effect::Reopen))
+        );
+
+        let machine = run(machine, &[AuctionEvent::Close, AuctionEvent::Lapse]);
+        assert_eq!(machine.phase, Phase::Expired);
+        assert_eq!(machine.awarded.len(), 2);
+        assert!(machine.step(&bid("d", 1.0)).is_err());
+    }
+
+    #[test]
+    fn cancelled_auctions_take_no_further_events() {
+        let machine = run(
+            AuctionStateMachine::new(3),
+            &[bid("a", 0.5), AuctionEvent::Close, AuctionEvent::Cancel],
+        );
+        assert_eq!(machine.phase, Phase::Expired);
+        let accept = AuctionEvent::Accept { worker: "a".into() };
+        assert_eq!(
+            machine.step(&accept),
+            Err(Rejected::Illegal {
+                phase: Phase::Expired,
+                event: EventKind::Accept,
+            })
+        );
+        // Closing with nobody bidding expires the auction too.
+        let (empty, effect) = AuctionStateMachine::new(3)
+            .step(&AuctionEvent::Close)
+            .unwrap();
+        assert_eq!(
+            (empty.phase, effect),
+            (Phase::Expired, Some(Effect::Abandon))
+        );
+    }
+}
@@ -0,0 +1,250 @@
+//! Tamper-evident log of authorization decisions.
+//!
+//! Every token check made by `SporeNode::validate_ucan` appends an
+//! [`AuditEntry`] to its own fjall keyspace. Each entry carries the SHA-256 of
+//! its predecessor and of its own contents, so editing, dropping or reordering
+//! stored entries breaks [`AuditLog::verify`]. Tokens are recorded by digest;
+//! operators who keep tokens elsewhere can match them up.
+
+use crate::core::Capability;
+use fjall::{Database, Keyspace, KeyspaceCreateOptions};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+
+const KEY_PREFIX: &str = "audit_";
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuditError {
+    #[error("audit storage error: {0}")]
+    Storage(#[from] fjall::Error),
+    #[error("audit entry {seq} is unreadable: {source}")]
+    Decode { seq: u64, source: serde_json::Error },
+    #[error("audit chain broken at entry {seq}")]
+    Tampered { seq: u64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    Accepted,
+    Rejected,
+}
+
+/// What was checked, before it is sequenced and chained.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: u64,
+    /// Peer that presented the token, when known (the task publisher).
+    pub requester: Option<String>,
+    pub task_id: Option<String>,
+    /// Tenant of the task checked. Left out of untenanted records so their
+    /// hashes are unchanged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    pub required: Capability,
+    /// Hex SHA-256 of the presented token.
+    pub token_sha256: String,
+    pub decision: Decision,
+    /// Why the token was rejected.
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    #[serde(flatten)]
+    pub record: AuditRecord,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+impl AuditEntry {
+    fn compute_hash(seq: u64, record: &AuditRecord, prev_hash: &str) -> String {
+        let body = serde_json::to_vec(&(seq, record)).expect("audit record serializes");
+        let mut hasher = Sha256::new();
+        hasher.update(prev_hash.as_bytes());
+        hasher.update(&body);
+        to_hex(&hasher.finalize())
+    }
+}
+
+pub struct AuditLog {
+    keyspace: Keyspace,
+    /// Sequence number and hash of the last entry.
+    head: Mutex<(u64, String)>,
+}
+
+impl AuditLog {
+    /// Open the log in `storage`, resuming after its last entry.
+    pub fn open(storage: &Database) -> Result<Self, AuditError> {
+        let keyspace = storage.keyspace("hypha_audit", KeyspaceCreateOptions::default)?;
+        let log = Self {
+            keyspace,
+            head: Mutex::new((0, GENESIS_HASH.to_string())),
+        };
+        // Keys sort in sequence order, so the head is the last one.
+        if let Some(last) = log.keyspace.prefix(KEY_PREFIX).next_back() {
+            let (key, value) = last.into_inner()?;
+            let seq = entry_seq(&key).unwrap_or_default();
+            let last: AuditEntry = serde_json::from_slice(&value)
+                .map_err(|source| AuditError::Decode { seq, source })?;
+            *log.head.lock().unwrap() = (last.seq, last.hash);
+        }
+        Ok(log)
+    }
+
+    /// Append a decision and return the stored entry.
+    pub fn record(&self, record: AuditRecord) -> Result<AuditEntry, AuditError> {
+        let mut head = self.head.lock().unwrap();
+        let seq = head.0 + 1;
+        let hash = AuditEntry::compute_hash(seq, &record, &head.1);
+        let entry = AuditEntry {
+            seq,
+            record,
+            prev_hash: head.1.clone(),
+            hash,
+        };
+        let value = serde_json::to_vec(&entry).expect("audit entry serializes");
+        self.keyspace.insert(entry_key(seq), value)?;
+        *head = (seq, entry.hash.clone());
+        Ok(entry)
+    }
+
+    pub fn len(&self) -> u64 {
+        self.head.lock().unwrap().0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// All entries in append order.
+    pub fn entries(&self) -> Result<Vec<AuditEntry>, AuditError> {
+        let keys: Vec<_> = self
+            .keyspace
+            .prefix(KEY_PREFIX)
+            .map(|item| item.key())
+            .collect::<Result<_, _>>()?;
+        let mut entries = Vec::with_capacity(keys.len());
+        for key in keys {
+            let Some(value) = self.keyspace.get(&key)? else {
+                continue;
+            };
+            let seq = entries.len() as u64 + 1;
+            let entry = serde_json::from_slice(&value)
+                .map_err(|source| AuditError::Decode { seq, source })?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Entries recorded for `tenant`'s tasks, so one tenant's history can be
+    /// handed over without the others'. `None` selects untenanted entries.
+    pub fn entries_for_tenant(&self, tenant: Option<&str>) -> Result<Vec<AuditEntry>, AuditError> {
+        let mut entries = self.entries()?;
+        entries.retain(|entry| entry.record.tenant.as_deref() == tenant);
+        Ok(entries)
+    }
+
+    /// Re-hash the stored chain. Returns the number of entries checked.
+    pub fn verify(&self) -> Result<u64, AuditError> {
+        let mut prev_hash = GENESIS_HASH.to_string();
+        let mut count = 0;
+        for entry in self.entries()? {
+            count += 1;
+            let expected = AuditEntry::compute_hash(count, &entry.record, &prev_hash);
+            if entry.seq != count || entry.prev_hash != prev_hash || entry.hash != expected {
+                return Err(AuditError::Tampered { seq: count });
+            }
+            prev_hash = entry.hash;
+        }
+        Ok(count)
+    }
+
+    /// Verified entries as JSON lines, for shipping to an external store.
+    pub fn export_json_lines(&self) -> Result<String, AuditError> {
+        self.verify()?;
+        let mut out = String::new();
+        for entry in self.entries()? {
+            out.push_str(&serde_json::to_string(&entry).expect("audit entry serializes"));
+            out.push('\n');
+        }
+        Ok(out)
+    }
+}
+
+/// Zero-padded so the keyspace's byte order is append order.
+fn entry_key(seq: u64) -> String {
+    format!("{KEY_PREFIX}{seq:020}")
+}
+
+fn entry_seq(key: &[u8]) -> Option<u64> {
+    std::str::from_utf8(key)
+        .ok()?
+        .strip_prefix(KEY_PREFIX)?
+        .parse()
+        .ok()
+}
+
+pub fn token_digest(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub(crate) fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(decision: Decision) -> AuditRecord {
+        AuditRecord {
+            timestamp: 1,
+            requester: Some("peer-a".to_string()),
+            task_id: Some("t1".to_string()),
+            tenant: None,
+            required: Capability::Compute(1),
+            token_sha256: token_digest("token"),
+            decision,
+            reason: None,
+        }
+    }
+
+    #[test]
+    fn chain_survives_reopen_and_detects_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let storage = Database::builder(dir.path()).open().unwrap();
+            let log = AuditLog::open(&storage).unwrap();
+            log.record(record(Decision::Accepted)).unwrap();
+            log.record(record(Decision::Rejected)).unwrap();
+        }
+
+        let storage = Database::builder(dir.path()).open().unwrap();
+        let log = AuditLog::open(&storage).unwrap();
+        let third = log.record(record(Decision::Accepted)).unwrap();
+        assert_eq!(third.seq, 3);
+        assert_eq!(log.verify().unwrap(), 3);
+        assert_eq!(log.export_json_lines().unwrap().lines().count(), 3);
+
+        let mut second = log.entries().unwrap().remove(1);
+        second.record.decision = Decision::Accepted;
+        log.keyspace
+            .insert(entry_key(2), serde_json::to_vec(&second).unwrap())
+            .unwrap();
+        assert!(matches!(log.verify(), Err(AuditError::Tampered { seq: 2 })));
+    }
+}
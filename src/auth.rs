@@ -0,0 +1,574 @@
+//! Capability delegation chains.
+//!
+//! A [`Delegation`] grants one [`Capability`] from an issuer to an audience
+//! for a bounded window. Minted links name both by `did:key`; verification
+//! also accepts peer ids, resolving either form through [`crate::did`]. It may carry a `proof`: the
+//! delegation that granted the issuer the capability in the first place. A
+//! gateway holding `Capability::Sensing("temp")` can therefore hand a worker
+//! a narrower, shorter-lived token without involving the root.
+//!
+//! Every link is signed with its issuer's ed25519 key. Verification walks the
+//! chain back to a trusted root and checks that each step only attenuates:
+//! the capability must be satisfied by the parent's, and the window must sit
+//! inside the parent's. A link scoped to a tenant keeps every link below it
+//! in that tenant, so one application's grants never authorize another's
+//! tasks on a shared node. Tokens are JSON so they fit `Task::auth_token`
+//! unchanged; the semantics follow UCAN but the encoding is hypha's own.
+
+use crate::core::Capability;
+use crate::did::{self, same_principal};
+use ed25519_dalek::{Signer, SigningKey};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Limits applied when verifying a chain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DelegationLimits {
+    /// Links from the presented token back to the root, inclusive.
+    pub max_chain_len: usize,
+    /// Longest window any single link may grant.
+    pub max_ttl: Duration,
+}
+
+impl Default for DelegationLimits {
+    fn default() -> Self {
+        Self {
+            max_chain_len: 4,
+            max_ttl: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum AuthError {
+    #[error("token is not a delegation: {0}")]
+    Malformed(String),
+    #[error("issuer `{0}` is not an ed25519 DID or peer id")]
+    UnknownIssuer(String),
+    #[error("signature by `{0}` does not verify")]
+    BadSignature(String),
+    #[error("token is for `{actual}`, not `{expected}`")]
+    WrongAudience { expected: String, actual: String },
+    #[error("delegated {granted:?} does not cover {required:?}")]
+    InsufficientCapability {
+        granted: Capability,
+        required: Capability,
+    },
+    #[error("link from `{issuer}` is not backed by a delegation to it")]
+    BrokenChain { issuer: String },
+    #[error("link from `{issuer}` widens its proof")]
+    NotAttenuated { issuer: String },
+    #[error("chain is longer than {max} links")]
+    ChainTooLong { max: usize },
+    #[error("link from `{issuer}` is outside its validity window")]
+    Expired { issuer: String },
+    #[error("link from `{issuer}` grants longer than the allowed {max:?}")]
+    TtlTooLong { issuer: String, max: Duration },
+    #[error("chain is rooted at untrusted issuer `{0}`")]
+    UntrustedRoot(String),
+    #[error("tenant `{0}` is not hosted on this node")]
+    UnknownTenant(String),
+    #[error("token is scoped to tenant {actual:?}, not {expected:?}")]
+    WrongTenant {
+        expected: Option<String>,
+        actual: Option<String>,
+    },
+}
+
+/// One signed link of a delegation chain. Times are Unix seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delegation {
+    pub issuer: String,
+    pub audience: String,
+    pub capability: Capability,
+    pub not_before: u64,
+    pub expires_at: u64,
+    /// Tenant the grant is confined to. `None` for node-wide grants.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant: Option<String>,
+    /// The delegation the issuer holds for this capability. `None` for a
+    /// root grant.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<Box<Delegation>>,
+    pub signature: Vec<u8>,
+}
+
+impl Delegation {
+    /// Mint a delegation signed with `signing_key`.
+    ///
+    /// With a `proof`, the new link is attenuated from it: the proof must be
+    /// addressed to this key, must cover `capability`, and caps the expiry.
+    /// The link inherits the proof's tenant.
+    pub fn mint(
+        signing_key: &SigningKey,
+        audience: &PeerId,
+        capability: Capability,
+        now: u64,
+        ttl: Duration,
+        proof: Option<Delegation>,
+    ) -> Result<Self, AuthError> {
+        let tenant = proof.as_ref().and_then(|p| p.tenant.clone());
+        Self::mint_scoped(signing_key, audience, capability, tenant, now, ttl, proof)
+    }
+
+    /// Like `mint`, confined to `tenant`. A tenant-scoped proof must name
+    /// the same tenant.
+    pub fn mint_for_tenant(
+        signing_key: &SigningKey,
+        audience: &PeerId,
+        capability: Capability,
+        tenant: &str,
+        now: u64,
+        ttl: Duration,
+        proof: Option<Delegation>,
+    ) -> Result<Self, AuthError> {
+        let tenant = Some(tenant.to_string());
+        Self::mint_scoped(signing_key, audience, capability, tenant, now, ttl, proof)
+    }
+
+    fn mint_scoped(
+        signing_key: &SigningKey,
+        audience: &PeerId,
+        capability: Capability,
+        tenant: Option<String>,
+        now: u64,
+        ttl: Duration,
+        proof: Option<Delegation>,
+    ) -> Result<Self, AuthError> {
+        let issuer = did::did_of(signing_key);
+        let mut expires_at = now.saturating_add(ttl.as_secs());
+        if let Some(parent) = &proof {
+            if !same_principal(&parent.audience, &issuer) {
+                return Err(AuthError::BrokenChain { issuer });
+            }
+            if !parent.capability.satisfies(&capability) || !parent.scope_covers(&tenant) {
+                return Err(AuthError::NotAttenuated { issuer });
+            }
+            expires_at = expires_at.min(parent.expires_at);
+        }
+
+        let mut delegation = Self {
+            issuer,
+            audience: did::did_of_peer(audience).unwrap_or_else(|| audience.to_string()),
+            capability,
+            not_before: now,
+            expires_at,
+            tenant,
+            proof: proof.map(Box::new),
+            signature: Vec::new(),
+        };
+        delegation.signature = signing_key
+            .sign(&delegation.signing_bytes())
+            .to_bytes()
+            .to_vec();
+        Ok(delegation)
+    }
+
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("delegation serializes")
+    }
+
+    pub fn decode(token: &str) -> Result<Self, AuthError> {
+        serde_json::from_str(token).map_err(|e| AuthError::Malformed(e.to_string()))
+    }
+
+    /// Chain length including this link.
+    pub fn chain_len(&self) -> usize {
+        1 + self.proof.as_ref().map_or(0, |p| p.chain_len())
+    }
+
+    /// Check that this token lets `audience` use `required` at `now`.
+    ///
+    /// Fails unless every link is signed by its issuer, addressed to the next
+    /// link's issuer, attenuates its proof, and is currently valid, and the
+    /// root issuer is one of `trusted_roots`.
+    pub fn verify(
+        &self,
+        audience: &str,
+        required: &Capability,
+        trusted_roots: &[String],
+        limits: &DelegationLimits,
+        now: u64,
+    ) -> Result<(), AuthError> {
+        if !same_principal(&self.audience, audience) {
+            return Err(AuthError::WrongAudience {
+                expected: audience.to_string(),
+                actual: self.audience.clone(),
+            });
+        }
+        if !self.capability.satisfies(required) {
+            return Err(AuthError::InsufficientCapability {
+                granted: self.capability.clone(),
+                required: required.clone(),
+            });
+        }
+        if self.chain_len() > limits.max_chain_len {
+            return Err(AuthError::ChainTooLong {
+                max: limits.max_chain_len,
+            });
+        }
+
+        let mut link = self;
+        loop {
+            link.verify_link(limits, now)?;
+            let Some(parent) = link.proof.as_deref() else {
+                break;
+            };
+            let issuer = || link.issuer.clone();
+            if !same_principal(&parent.audience, &link.issuer) {
+                return Err(AuthError::BrokenChain { issuer: issuer() });
+            }
+            if !parent.capability.satisfies(&link.capability)
+                || !parent.scope_covers(&link.tenant)
+                || link.not_before < parent.not_before
+                || link.expires_at > parent.expires_at
+            {
+                return Err(AuthError::NotAttenuated { issuer: issuer() });
+            }
+            link = parent;
+        }
+
+        if !trusted_roots
+            .iter()
+            .any(|root| same_principal(root, &link.issuer))
+        {
+            return Err(AuthError::UntrustedRoot(link.issuer.clone()));
+        }
+        Ok(())
+    }
+
+    /// Check that the token is scoped to exactly `tenant`: tenant tasks need
+    /// a grant for their tenant, and node-wide tasks a node-wide grant.
+    pub fn verify_tenant(&self, tenant: Option<&str>) -> Result<(), AuthError> {
+        if self.tenant.as_deref() == tenant {
+            return Ok(());
+        }
+        Err(AuthError::WrongTenant {
+            expected: tenant.map(str::to_string),
+            actual: self.tenant.clone(),
+        })
+    }
+
+    /// Whether a child link scoped to `tenant` stays within this link's
+    /// tenant. Node-wide links may be narrowed to any tenant.
+    fn scope_covers(&self, tenant: &Option<String>) -> bool {
+        self.tenant.is_none() || self.tenant == *tenant
+    }
+
+    fn verify_link(&self, limits: &DelegationLimits, now: u64) -> Result<(), AuthError> {
+        let key = issuer_key(&self.issuer)?;
+        if !key.verify(&self.signing_bytes(), &self.signature) {
+            return Err(AuthError::BadSignature(self.issuer.clone()));
+        }
+        if now < self.not_before || now >= self.expires_at {
+            return Err(AuthError::Expired {
+                issuer: self.issuer.clone(),
+            });
+        }
+        if self.expires_at - self.not_before > limits.max_ttl.as_secs() {
+            return Err(AuthError::TtlTooLong {
+                issuer: self.issuer.clone(),
+                max: limits.max_ttl,
+            });
+        }
+        Ok(())
+    }
+
+    /// Bytes covered by the signature. The proof is bound through its
+    /// signature, which already covers the rest of the chain. The tenant is
+    /// appended only when set, so node-wide tokens keep their old encoding.
+    fn signing_bytes(&self) -> Vec<u8> {
+        let proof_signature = self.proof.as_ref().map(|p| p.signature.as_slice());
+        let fields = (
+            &self.issuer,
+            &self.audience,
+            &self.capability,
+            self.not_before,
+            self.expires_at,
+            proof_signature,
+        );
+        match &self.tenant {
+            None => serde_json::to_vec(&fields),
+            Some(tenant) => serde_json::to_vec(&(fields, tenant)),
+        }
+        .expect("delegation fields serialize")
+    }
+}
+
+/// Current time in Unix seconds, as used for delegation windows.
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+pub fn peer_id_of(signing_key: &SigningKey) -> PeerId {
+    let keypair = libp2p::identity::Keypair::ed25519_from_bytes(signing_key.to_bytes())
+        .expect("32-byte ed25519 secret");
+    keypair.public().to_peer_id()
+}
+
+/// Recover the public key inlined in an issuer's peer id or `did:key`.
+fn issuer_key(issuer: &str) -> Result<libp2p::identity::PublicKey, AuthError> {
+    let key = if issuer.starts_with(did::DID_KEY_PREFIX) {
+        did::public_key_of_did(issuer).ok()
+    } else {
+        public_key_of(issuer)
+    };
+    key.ok_or_else(|| AuthError::UnknownIssuer(issuer.to_string()))
+}
+
+/// Recover the public key inlined in a peer id. Only keys small enough for an
+/// identity multihash (ed25519, secp256k1) are recoverable.
+pub fn public_key_of(peer_id: &str) -> Option<libp2p::identity::PublicKey> {
+    let peer_id: PeerId = peer_id.parse().ok()?;
+    let multihash = peer_id.as_ref();
+    // Identity multihash: the digest is the protobuf-encoded key itself.
+    if multihash.code() != 0 {
+        return None;
+    }
+    libp2p::identity::PublicKey::try_decode_protobuf(multihash.digest()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(3600);
+    const NOW: u64 = 1_700_000_000;
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn temp() -> Capability {
+        Capability::Sensing("temp".to_string())
+    }
+
+    #[test]
+    fn gateway_can_attenuate_root_grant_to_worker() {
+        let (root, gateway, worker) = (key(1), key(2), key(3));
+        let to_gateway = Delegation::mint(
+            &root,
+            &peer_id_of(&gateway),
+            Capability::Compute(100),
+            NOW,
+            HOUR * 8,
+            None,
+        )
+        .unwrap();
+        let to_worker = Delegation::mint(
+            &gateway,
+            &peer_id_of(&worker),
+            Capability::Compute(10),
+            NOW,
+            HOUR * 24,
+            Some(to_gateway),
+        )
+        .unwrap();
+        assert_eq!(to_worker.expires_at, NOW + 8 * 3600);
+        assert_eq!(to_worker.issuer, did::did_of(&gateway));
+
+        let token = Delegation::decode(&to_worker.encode()).unwrap();
+        let roots = [peer_id_of(&root).to_string()];
+        let worker_id = peer_id_of(&worker).to_string();
+        let limits = DelegationLimits::default();
+        assert_eq!(
+            token.verify(
+                &worker_id,
+                &Capability::Compute(5),
+                &roots,
+                &limits,
+                NOW + 60
+            ),
+            Ok(())
+        );
+        assert!(matches!(
+            token.verify(
+                &worker_id,
+                &Capability::Compute(50),
+                &roots,
+                &limits,
+                NOW + 60
+            ),
+            Err(AuthError::InsufficientCapability { .. })
+        ));
+        assert!(matches!(
+            token.verify(
+                &worker_id,
+                &Capability::Compute(5),
+                &roots,
+                &limits,
+                NOW + 9 * 3600
+            ),
+            Err(AuthError::Expired { .. })
+        ));
+        assert!(matches!(
+            token.verify(&worker_id, &Capability::Compute(5), &[], &limits, NOW + 60),
+            Err(AuthError::UntrustedRoot(_))
+        ));
+    }
+
+    #[test]
+    fn widening_or_forged_links_are_rejected() {
+        let (root, gateway, worker) = (key(1), key(2), key(3));
+        let to_gateway =
+            Delegation::mint(&root, &peer_id_of(&gateway), temp(), NOW, HOUR, None).unwrap();
+        assert_eq!(
+            Delegation::mint(
+                &gateway,
+                &peer_id_of(&worker),
+                Capability::Sensing("humidity".to_string()),
+                NOW,
+                HOUR,
+                Some(to_gateway.clone()),
+            ),
+            Err(AuthError::NotAttenuated {
+                issuer: did::did_of(&gateway)
+            })
+        );
+
+        let mut forged = Delegation::mint(
+            &gateway,
+            &peer_id_of(&worker),
+            temp(),
+            NOW,
+            HOUR,
+            Some(to_gateway),
+        )
+        .unwrap();
+        forged.capability = Capability::Sensing("humidity".to_string());
+        forged.proof.as_mut().unwrap().capability = forged.capability.clone();
+        let roots = [peer_id_of(&root).to_string()];
+        assert!(matches!(
+            forged.verify(
+                &peer_id_of(&worker).to_string(),
+                &forged.capability.clone(),
+                &roots,
+                &DelegationLimits::default(),
+                NOW,
+            ),
+            Err(AuthError::BadSignature(_))
+        ));
+    }
+
+    #[test]
+    fn chain_length_and_ttl_are_bounded() {
+        let keys: Vec<SigningKey> = (1..=4).map(key).collect();
+        let mut token =
+            Delegation::mint(&keys[0], &peer_id_of(&keys[1]), temp(), NOW, HOUR, None).unwrap();
+        for pair in keys[1..].windows(2) {
+            token = Delegation::mint(
+                &pair[0],
+                &peer_id_of(&pair[1]),
+                temp(),
+                NOW,
+                HOUR,
+                Some(token),
+            )
+            .unwrap();
+        }
+        let roots = [peer_id_of(&keys[0]).to_string()];
+        let holder = peer_id_of(&keys[3]).to_string();
+        let tight = DelegationLimits {
+            max_chain_len: 2,
+            max_ttl: HOUR,
+        };
+        assert_eq!(
+            token.verify(&holder, &temp(), &roots, &tight, NOW),
+            Err(AuthError::ChainTooLong { max: 2 })
+        );
+        let short_ttl = DelegationLimits {
+            max_chain_len: 4,
+            max_ttl: Duration::from_secs(60),
+        };
+        assert!(matches!(
+            token.verify(&holder, &temp(), &roots, &short_ttl, NOW),
+            Err(AuthError::TtlTooLong { .. })
+        ));
+    }
+
+    #[test]
+    fn tenant_scope_only_narrows() {
+        let (root, gateway, worker) = (key(1), key(2), key(3));
+        let node_wide =
+            Delegation::mint(&root, &peer_id_of(&gateway), temp(), NOW, HOUR, None).unwrap();
+        let to_gateway = Delegation::mint_for_tenant(
+            &root,
+            &peer_id_of(&gateway),
+            temp(),
+            "farm",
+            NOW,
+            HOUR,
+            None,
+        )
+        .unwrap();
+        let to_worker = Delegation::mint(
+            &gateway,
+            &peer_id_of(&worker),
+            temp(),
+            NOW,
+            HOUR,
+            Some(to_gateway.clone()),
+        )
+        .unwrap();
+        assert_eq!(to_worker.tenant.as_deref(), Some("farm"));
+        assert_eq!(to_worker.verify_tenant(Some("farm")), Ok(()));
+        assert!(matches!(
+            to_worker.verify_tenant(None),
+            Err(AuthError::WrongTenant { .. })
+        ));
+        assert_eq!(
+            Delegation::mint_for_tenant(
+                &gateway,
+                &peer_id_of(&worker),
+                temp(),
+                "city",
+                NOW,
+                HOUR,
+                Some(to_gateway),
+            ),
+            Err(AuthError::NotAttenuated {
+                issuer: did::did_of(&gateway)
+            })
+        );
+
+        // Stripping the tenant breaks the signature rather than widening.
+        let mut widened = to_worker;
+        widened.tenant = None;
+        let roots = [peer_id_of(&root).to_string()];
+        assert!(matches!(
+            widened.verify(
+                &peer_id_of(&worker).to_string(),
+                &temp(),
+                &roots,
+                &DelegationLimits::default(),
+                NOW,
+            ),
+            Err(AuthError::BadSignature(_))
+        ));
+
+        // Node-wide grants may be narrowed to a tenant.
+        let scoped = Delegation::mint_for_tenant(
+            &gateway,
+            &peer_id_of(&worker),
+            temp(),
+            "farm",
+            NOW,
+            HOUR,
+            Some(node_wide),
+        )
+        .unwrap();
+        assert_eq!(
+            scoped.verify(
+                &peer_id_of(&worker).to_string(),
+                &temp(),
+                &roots,
+                &DelegationLimits::default(),
+                NOW,
+            ),
+            Ok(())
+        );
+    }
+}
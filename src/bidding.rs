@@ -0,0 +1,130 @@
+//! Quorum sensing for task auctions.
+//!
+//! A node hearing a task counts the competing bids it already knows of.
+//! Once `min_healthy_bids` are in, a node whose energy score is below the
+//! silence threshold for its power mode keeps quiet and leaves the work to
+//! healthier peers, with probability `silence_probability`. No node bids
+//! below `min_energy`. The defaults reproduce the original rule: at 3 bids,
+//! nodes under 0.8 always stay silent, and nodes under 0.2 never bid. Deployments tune these through
+//! `HyphaConfig::quorum`; evaluations can sweep them.
+
+use crate::core::PowerMode;
+
+/// An energy score threshold for each [`PowerMode`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModeThresholds {
+    pub normal: f32,
+    pub low_battery: f32,
+    pub critical: f32,
+}
+
+impl ModeThresholds {
+    pub fn uniform(threshold: f32) -> Self {
+        Self {
+            normal: threshold,
+            low_battery: threshold,
+            critical: threshold,
+        }
+    }
+
+    pub fn get(&self, mode: &PowerMode) -> f32 {
+        match mode {
+            PowerMode::Normal => self.normal,
+            PowerMode::LowBattery => self.low_battery,
+            PowerMode::Critical => self.critical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuorumConfig {
+    /// Known competing bids at which low-energy nodes go quiet.
+    pub min_healthy_bids: usize,
+    /// Nodes below this score, in the power mode their score falls in,
+    /// count as low-energy.
+    pub silence_below: ModeThresholds,
+    /// Chance a low-energy node stays quiet once the quorum is reached.
+    pub silence_probability: f32,
+    /// No bids below this score, whatever the bid count. The degradation
+    /// ladder's bid gate still applies on top.
+    pub min_energy: f32,
+}
+
+impl Default for QuorumConfig {
+    fn default() -> Self {
+        Self {
+            min_healthy_bids: 3,
+            silence_below: ModeThresholds::uniform(0.8),
+            silence_probability: 1.0,
+            min_energy: 0.2,
+        }
+    }
+}
+
+impl QuorumConfig {
+    /// Whether a node at `energy` stays out of an auction that already has
+    /// `known_bids`. `roll` is uniform in `[0, 1)`.
+    pub fn stays_silent(&self, energy: f32, known_bids: usize, roll: f32) -> bool {
+        if energy < self.min_energy {
+            return true;
+        }
+        let mode = PowerMode::from_energy_score(energy);
+        known_bids >= self.min_healthy_bids
+            && energy < self.silence_below.get(&mode)
+            && roll < self.silence_probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn silence_follows_bid_count_mode_thresholds_and_probability() {
+        let config = QuorumConfig::default();
+        assert!(!config.stays_silent(0.3, 2, 0.0));
+        assert!(config.stays_silent(0.3, 3, 0.99));
+        assert!(!config.stays_silent(0.9, 10, 0.0));
+
+        let config = QuorumConfig {
+            min_healthy_bids: 1,
+            silence_below: ModeThresholds {
+                normal: 0.6,
+                low_battery: 1.0,
+                critical: 1.0,
+            },
+            silence_probability: 0.5,
+            min_energy: 0.1,
+        };
+        assert!(config.stays_silent(0.05, 0, 0.99));
+        assert!(!config.stays_silent(0.7, 1, 0.0));
+        assert!(config.stays_silent(0.55, 1, 0.4));
+        assert!(!config.stays_silent(0.55, 1, 0.6));
+        assert!(config.stays_silent(0.3, 1, 0.0));
+    }
+
+    #[test]
+    fn sweeping_the_quorum_size_changes_how_many_nodes_bid() {
+        // Ten nodes hear a task in order of falling energy.
+        let energies: Vec<f32> = (0..10).map(|i| 0.95 - i as f32 * 0.1).collect();
+        let bidders = |config: &QuorumConfig| {
+            let mut bids = 0;
+            for &energy in &energies {
+                if !config.stays_silent(energy, bids, 0.5) {
+                    bids += 1;
+                }
+            }
+            bids
+        };
+        let counts: Vec<usize> = [1, 3, 5]
+            .into_iter()
+            .map(|min_healthy_bids| {
+                bidders(&QuorumConfig {
+                    min_healthy_bids,
+                    ..QuorumConfig::default()
+                })
+            })
+            .collect();
+        assert_eq!(counts, [2, 3, 5]);
+    }
+}
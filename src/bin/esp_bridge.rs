@@ -3,20 +3,97 @@
 //! The node's metabolism is updated from the device; the node joins the mesh
 //! and advertises that energy. One process = one ESP-backed spore.
 //!
+//! With `--ota-key`, the node also takes firmware releases for
+//! `--device-class` from the mesh. A verified image is offered to the ESP by
+//! writing its signed manifest down the line; the ESP then pulls the image
+//! with `hypha_ota` chunk requests, which the bridge answers.
+//!
 //! Usage:
 //!   cargo run --bin esp_bridge -- [--port /dev/cu.usbmodem1101]
 //!   cargo run --bin esp_bridge -- --stdin   # read JSON lines from stdin (test without device)
+//!   cargo run --bin esp_bridge -- --ota-key <hex ed25519 key> [--device-class esp32c6]
 //!   (ESP sends newline-delimited JSON: {"source_id":"esp-1","energy_score":0.85})
 
+use hypha::ota::{FirmwareRelease, Installer};
 use hypha::{EnergyStatus, MockMetabolism, SporeNode};
-use std::io::{BufRead, BufReader};
-use std::sync::Arc;
+use hypha_ota::protocol;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tempfile::tempdir;
 use tracing::info;
 
 const DEFAULT_PORT: &str = "/dev/cu.usbmodem1101";
 const BAUD: u32 = 115200;
+const DEFAULT_DEVICE_CLASS: &str = "esp32c6";
+
+/// Firmware waiting to be pulled by the ESP.
+struct Firmware {
+    manifest: String,
+    image: Vec<u8>,
+    announced: bool,
+}
+
+/// Installs firmware by queueing it for the ESP, which verifies the manifest
+/// itself and flashes the image it pulls.
+#[derive(Clone, Default)]
+struct SerialInstaller {
+    pending: Arc<Mutex<Option<Firmware>>>,
+}
+
+impl Installer for SerialInstaller {
+    fn install(
+        &self,
+        release: &FirmwareRelease,
+        image: &[u8],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // The ESP reads one manifest per line.
+        let manifest = serde_json::from_str::<serde_json::Value>(&release.manifest)?.to_string();
+        *self.pending.lock().map_err(|_| "firmware slot poisoned")? = Some(Firmware {
+            manifest,
+            image: image.to_vec(),
+            announced: false,
+        });
+        info!(version = %release.version, "Firmware queued for the ESP");
+        Ok(())
+    }
+}
+
+/// Write the manifest of newly queued firmware to the ESP.
+fn announce_firmware(
+    firmware: &Mutex<Option<Firmware>>,
+    out: &mut impl Write,
+) -> std::io::Result<()> {
+    let mut pending = firmware.lock().unwrap();
+    if let Some(firmware) = pending.as_mut().filter(|firmware| !firmware.announced) {
+        writeln!(out, "{}", firmware.manifest)?;
+        out.flush()?;
+        firmware.announced = true;
+    }
+    Ok(())
+}
+
+/// Answer a chunk request from the ESP, or take the line as an energy update.
+fn handle_line(
+    line: &str,
+    metabolism: &std::sync::Mutex<MockMetabolism>,
+    firmware: &Mutex<Option<Firmware>>,
+    out: &mut impl Write,
+) -> std::io::Result<()> {
+    let Some(index) = protocol::parse_chunk_request(line.trim().as_bytes()) else {
+        apply_energy_line(line, metabolism);
+        return Ok(());
+    };
+    let pending = firmware.lock().unwrap();
+    if let Some(chunk) = pending
+        .as_ref()
+        .and_then(|firmware| protocol::image_chunk(&firmware.image, index))
+    {
+        writeln!(out, "{}", protocol::build_chunk_response(index, chunk))?;
+        out.flush()?;
+    }
+    Ok(())
+}
 
 fn apply_energy_line(line: &str, metabolism: &std::sync::Mutex<MockMetabolism>) {
     let s = line.trim();
@@ -35,7 +112,11 @@ fn apply_energy_line(line: &str, metabolism: &std::sync::Mutex<MockMetabolism>)
     }
 }
 
-fn serial_reader(port_path: String, metabolism: Arc<std::sync::Mutex<MockMetabolism>>) {
+fn serial_reader(
+    port_path: String,
+    metabolism: Arc<std::sync::Mutex<MockMetabolism>>,
+    firmware: Arc<Mutex<Option<Firmware>>>,
+) {
     loop {
         let Ok(port) = serialport::new(port_path.clone(), BAUD)
             .timeout(Duration::from_millis(500))
@@ -44,31 +125,64 @@ fn serial_reader(port_path: String, metabolism: Arc<std::sync::Mutex<MockMetabol
             std::thread::sleep(Duration::from_secs(2));
             continue;
         };
+        let Ok(mut writer) = port.try_clone() else {
+            std::thread::sleep(Duration::from_secs(2));
+            continue;
+        };
         info!(path = %port_path, "Serial port opened");
         let mut reader = BufReader::new(port);
         let mut line = String::new();
         loop {
-            line.clear();
+            if announce_firmware(&firmware, &mut writer).is_err() {
+                break;
+            }
+            // A timeout keeps any partial line for the next read.
             match reader.read_line(&mut line) {
                 Ok(0) => break,
-                Ok(_) => apply_energy_line(&line, &metabolism),
+                Ok(_) => {
+                    if handle_line(&line, &metabolism, &firmware, &mut writer).is_err() {
+                        break;
+                    }
+                    line.clear();
+                }
+                Err(e) if e.kind() == ErrorKind::TimedOut => continue,
                 Err(_) => break,
             }
         }
+        // Offer queued firmware again once the port is back.
+        if let Some(firmware) = firmware.lock().unwrap().as_mut() {
+            firmware.announced = false;
+        }
         std::thread::sleep(Duration::from_secs(2));
     }
 }
 
-fn stdin_reader(metabolism: Arc<std::sync::Mutex<MockMetabolism>>) {
+fn stdin_reader(
+    metabolism: Arc<std::sync::Mutex<MockMetabolism>>,
+    firmware: Arc<Mutex<Option<Firmware>>>,
+) {
     let mut reader = BufReader::new(std::io::stdin());
+    let mut stdout = std::io::stdout();
     let mut line = String::new();
     loop {
         line.clear();
         if reader.read_line(&mut line).map(|n| n == 0).unwrap_or(true) {
             break;
         }
-        apply_energy_line(&line, &metabolism);
+        let _ = announce_firmware(&firmware, &mut stdout);
+        let _ = handle_line(&line, &metabolism, &firmware, &mut stdout);
+    }
+}
+
+fn parse_key(hex: &str) -> Option<[u8; 32]> {
+    if hex.len() != 64 {
+        return None;
+    }
+    let mut key = [0u8; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
     }
+    Some(key)
 }
 
 #[tokio::main]
@@ -83,20 +197,40 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .and_then(|i| args.get(i + 1))
         .cloned()
         .unwrap_or_else(|| DEFAULT_PORT.to_string());
+    let arg = |name: &str| {
+        args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+            .cloned()
+    };
+    let ota_key = match arg("--ota-key") {
+        Some(hex) => Some(parse_key(hex.trim()).ok_or("--ota-key takes a 32-byte hex key")?),
+        None => None,
+    };
+    let device_class = arg("--device-class").unwrap_or_else(|| DEFAULT_DEVICE_CLASS.to_string());
 
     let tmp = tempdir()?;
     let metabolism = Arc::new(std::sync::Mutex::new(MockMetabolism::new(0.5, false)));
     let metabolism_clone = metabolism.clone();
+    let installer = SerialInstaller::default();
+    let firmware = installer.pending.clone();
 
     if use_stdin {
-        std::thread::spawn(move || stdin_reader(metabolism_clone));
+        std::thread::spawn(move || stdin_reader(metabolism_clone, firmware));
         info!("Reading EnergyStatus JSON lines from stdin.");
     } else {
-        std::thread::spawn(move || serial_reader(port, metabolism_clone));
+        std::thread::spawn(move || serial_reader(port, metabolism_clone, firmware));
     }
 
     let mut node = SporeNode::new_with_metabolism(tmp.path(), metabolism)?;
     node.add_capability(hypha::Capability::Sensing("esp".to_string()));
+    if let Some(key) = ota_key {
+        node.device_class = device_class;
+        node.ota.enabled = true;
+        node.ota.firmware_key = Some(key);
+        node.set_installer(Box::new(installer));
+        info!(device_class = %node.device_class, "Firmware updates enabled");
+    }
 
     info!("Spore node started; metabolism driven by ESP. Ctrl+C to stop.");
     node.start().await?;
@@ -0,0 +1,567 @@
+//! CoAP observation endpoint for constrained clients.
+//!
+//! Exposes a node's energy score, the latest sensor readings, mesh stats and
+//! fleet health as observable CoAP resources (RFC 7252, RFC 7641), so devices that cannot
+//! join the libp2p swarm can still follow node state. Battery level is also
+//! served at the LwM2M Device object path `/3/0/9` as an integer percentage.
+//!
+//! [`CoapEndpoint`] is the sans-IO core: feed it datagrams, send what it
+//! returns. The UDP server (`serve`) needs the `coap` feature.
+
+use crate::core::{PowerMode, SensorReading};
+use crate::health::FleetSummary;
+use crate::mesh::MeshStats;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+
+pub const CONTENT_FORMAT_TEXT: u16 = 0;
+pub const CONTENT_FORMAT_LINK: u16 = 40;
+pub const CONTENT_FORMAT_JSON: u16 = 50;
+
+const OPTION_OBSERVE: u16 = 6;
+const OPTION_URI_PATH: u16 = 11;
+const OPTION_CONTENT_FORMAT: u16 = 12;
+
+const CODE_GET: u8 = 0x01;
+const CODE_CONTENT: u8 = 0x45; // 2.05
+const CODE_NOT_FOUND: u8 = 0x84; // 4.04
+const CODE_METHOD_NOT_ALLOWED: u8 = 0x85; // 4.05
+
+/// Observers kept per endpoint; further registrations are served once.
+pub const MAX_OBSERVERS: usize = 32;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum CoapError {
+    #[error("datagram too short")]
+    Truncated,
+    #[error("unsupported CoAP version {0}")]
+    Version(u8),
+    #[error("malformed option encoding")]
+    BadOption,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    Confirmable,
+    NonConfirmable,
+    Acknowledgement,
+    Reset,
+}
+
+impl MessageType {
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0x03 {
+            0 => MessageType::Confirmable,
+            1 => MessageType::NonConfirmable,
+            2 => MessageType::Acknowledgement,
+            _ => MessageType::Reset,
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            MessageType::Confirmable => 0,
+            MessageType::NonConfirmable => 1,
+            MessageType::Acknowledgement => 2,
+            MessageType::Reset => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub mtype: MessageType,
+    pub code: u8,
+    pub message_id: u16,
+    pub token: Vec<u8>,
+    /// Options sorted by number, as they appear on the wire.
+    pub options: Vec<(u16, Vec<u8>)>,
+    pub payload: Vec<u8>,
+}
+
+impl Message {
+    pub fn decode(bytes: &[u8]) -> Result<Self, CoapError> {
+        if bytes.len() < 4 {
+            return Err(CoapError::Truncated);
+        }
+        let version = bytes[0] >> 6;
+        if version != 1 {
+            return Err(CoapError::Version(version));
+        }
+        let mtype = MessageType::from_bits(bytes[0] >> 4);
+        let token_len = (bytes[0] & 0x0f) as usize;
+        if token_len > 8 || bytes.len() < 4 + token_len {
+            return Err(CoapError::Truncated);
+        }
+        let code = bytes[1];
+        let message_id = u16::from_be_bytes([bytes[2], bytes[3]]);
+        let token = bytes[4..4 + token_len].to_vec();
+
+        let mut options = Vec::new();
+        let mut number = 0u16;
+        let mut i = 4 + token_len;
+        while i < bytes.len() {
+            if bytes[i] == 0xff {
+                i += 1;
+                if i == bytes.len() {
+                    // A payload marker must be followed by a payload.
+                    return Err(CoapError::BadOption);
+                }
+                break;
+            }
+            let header = bytes[i];
+            i += 1;
+            let delta = read_option_nibble(header >> 4, bytes, &mut i)?;
+            let len = read_option_nibble(header & 0x0f, bytes, &mut i)? as usize;
+            number = number.checked_add(delta).ok_or(CoapError::BadOption)?;
+            let value = bytes.get(i..i + len).ok_or(CoapError::Truncated)?;
+            options.push((number, value.to_vec()));
+            i += len;
+        }
+        let payload = bytes.get(i..).unwrap_or_default().to_vec();
+
+        Ok(Self {
+            mtype,
+            code,
+            message_id,
+            token,
+            options,
+            payload,
+        })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![
+            (1 << 6) | (self.mtype.bits() << 4) | self.token.len() as u8,
+            self.code,
+        ];
+        out.extend_from_slice(&self.message_id.to_be_bytes());
+        out.extend_from_slice(&self.token);
+
+        let mut options = self.options.clone();
+        options.sort_by_key(|(number, _)| *number);
+        let mut previous = 0u16;
+        for (number, value) in &options {
+            let (delta_nibble, delta_ext) = option_nibble(number - previous);
+            let (len_nibble, len_ext) = option_nibble(value.len() as u16);
+            out.push((delta_nibble << 4) | len_nibble);
+            out.extend_from_slice(&delta_ext);
+            out.extend_from_slice(&len_ext);
+            out.extend_from_slice(value);
+            previous = *number;
+        }
+        if !self.payload.is_empty() {
+            out.push(0xff);
+            out.extend_from_slice(&self.payload);
+        }
+        out
+    }
+
+    /// Uri-Path options joined with `/`, without a leading slash.
+    pub fn uri_path(&self) -> String {
+        self.options
+            .iter()
+            .filter(|(number, _)| *number == OPTION_URI_PATH)
+            .map(|(_, value)| String::from_utf8_lossy(value).into_owned())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    pub fn observe(&self) -> Option<u32> {
+        self.options
+            .iter()
+            .find(|(number, _)| *number == OPTION_OBSERVE)
+            .map(|(_, value)| value.iter().fold(0u32, |acc, b| (acc << 8) | *b as u32))
+    }
+}
+
+fn read_option_nibble(nibble: u8, bytes: &[u8], i: &mut usize) -> Result<u16, CoapError> {
+    match nibble {
+        0..=12 => Ok(nibble as u16),
+        13 => {
+            let ext = *bytes.get(*i).ok_or(CoapError::Truncated)?;
+            *i += 1;
+            Ok(ext as u16 + 13)
+        }
+        14 => {
+            let ext = bytes.get(*i..*i + 2).ok_or(CoapError::Truncated)?;
+            *i += 2;
+            u16::from_be_bytes([ext[0], ext[1]])
+                .checked_add(269)
+                .ok_or(CoapError::BadOption)
+        }
+        _ => Err(CoapError::BadOption),
+    }
+}
+
+fn option_nibble(value: u16) -> (u8, Vec<u8>) {
+    match value {
+        0..=12 => (value as u8, Vec::new()),
+        13..=268 => (13, vec![(value - 13) as u8]),
+        _ => (14, (value - 269).to_be_bytes().to_vec()),
+    }
+}
+
+/// Minimal big-endian encoding used by uint options.
+fn uint_option(value: u32) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let first = bytes.iter().position(|b| *b != 0).unwrap_or(4);
+    bytes[first..].to_vec()
+}
+
+/// Latest node state served by the endpoint.
+#[derive(Debug, Clone, Default)]
+pub struct CoapResources {
+    pub energy_score: f32,
+    pub mesh: Option<MeshStats>,
+    /// Latest reading per `source_id/sensor`.
+    pub readings: BTreeMap<String, SensorReading>,
+    pub health: Option<FleetSummary>,
+}
+
+impl CoapResources {
+    pub fn record_reading(&mut self, reading: SensorReading) {
+        let key = format!("{}/{}", reading.source_id, reading.sensor);
+        self.readings.insert(key, reading);
+    }
+
+    /// Content format and body for `path`, or `None` if there is no resource.
+    pub fn render(&self, path: &str) -> Option<(u16, Vec<u8>)> {
+        let json = |value: serde_json::Value| Some((CONTENT_FORMAT_JSON, value.to_string().into()));
+        match path {
+            ".well-known/core" => Some((
+                CONTENT_FORMAT_LINK,
+                b"</energy>;obs,</sensors>;obs,</mesh>;obs,</health>;obs,</3/0/9>;obs".to_vec(),
+            )),
+            "energy" => json(serde_json::json!({
+                "energy_score": self.energy_score,
+                "power_mode": format!("{:?}", PowerMode::from_energy_score(self.energy_score)),
+            })),
+            "sensors" => json(serde_json::json!(self
+                .readings
+                .values()
+                .collect::<Vec<_>>())),
+            "mesh" => json(serde_json::to_value(self.mesh.as_ref()?).ok()?),
+            "health" => json(serde_json::to_value(self.health.as_ref()?).ok()?),
+            // LwM2M Device object, Battery Level resource (0-100 %).
+            "3/0/9" => Some((
+                CONTENT_FORMAT_TEXT,
+                ((self.energy_score.clamp(0.0, 1.0) * 100.0).round() as u8)
+                    .to_string()
+                    .into_bytes(),
+            )),
+            _ => {
+                let key = path.strip_prefix("sensors/")?;
+                json(serde_json::to_value(self.readings.get(key)?).ok()?)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Observer {
+    addr: SocketAddr,
+    token: Vec<u8>,
+    path: String,
+    last: Vec<u8>,
+}
+
+/// Sans-IO CoAP endpoint: request handling plus observe notifications.
+#[derive(Debug, Default)]
+pub struct CoapEndpoint {
+    pub resources: CoapResources,
+    observers: Vec<Observer>,
+    next_message_id: u16,
+    observe_seq: u32,
+}
+
+impl CoapEndpoint {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observer_count(&self) -> usize {
+        self.observers.len()
+    }
+
+    /// Handle one datagram from `from`. Returns the reply to send, if any.
+    pub fn handle(&mut self, from: SocketAddr, datagram: &[u8]) -> Option<Vec<u8>> {
+        let request = Message::decode(datagram).ok()?;
+        match request.mtype {
+            // A reset answers one of our notifications: the client is gone.
+            MessageType::Reset => {
+                self.observers.retain(|o| o.addr != from);
+                return None;
+            }
+            MessageType::Acknowledgement => return None,
+            _ => {}
+        }
+
+        let mtype = if request.mtype == MessageType::Confirmable {
+            MessageType::Acknowledgement
+        } else {
+            MessageType::NonConfirmable
+        };
+        let mut response = Message {
+            mtype,
+            code: CODE_NOT_FOUND,
+            message_id: if mtype == MessageType::Acknowledgement {
+                request.message_id
+            } else {
+                self.message_id()
+            },
+            token: request.token.clone(),
+            options: Vec::new(),
+            payload: Vec::new(),
+        };
+
+        if request.code != CODE_GET {
+            response.code = CODE_METHOD_NOT_ALLOWED;
+            return Some(response.encode());
+        }
+
+        let path = request.uri_path();
+        let Some((format, body)) = self.resources.render(&path) else {
+            return Some(response.encode());
+        };
+
+        self.observers
+            .retain(|o| !(o.addr == from && o.token == request.token));
+        match request.observe() {
+            Some(0) if self.observers.len() < MAX_OBSERVERS => {
+                self.observers.push(Observer {
+                    addr: from,
+                    token: request.token.clone(),
+                    path,
+                    last: body.clone(),
+                });
+                response
+                    .options
+                    .push((OPTION_OBSERVE, uint_option(self.observe_seq)));
+            }
+            _ => {}
+        }
+
+        response.code = CODE_CONTENT;
+        response
+            .options
+            .push((OPTION_CONTENT_FORMAT, uint_option(format as u32)));
+        response.payload = body;
+        Some(response.encode())
+    }
+
+    /// Notifications for observers whose resource changed since last sent.
+    pub fn notifications(&mut self) -> Vec<(SocketAddr, Vec<u8>)> {
+        let mut out = Vec::new();
+        let mut changed = Vec::new();
+        for (index, observer) in self.observers.iter().enumerate() {
+            if let Some((format, body)) = self.resources.render(&observer.path) {
+                if body != observer.last {
+                    changed.push((index, format, body));
+                }
+            }
+        }
+        if changed.is_empty() {
+            return out;
+        }
+        self.observe_seq = (self.observe_seq + 1) & 0x00ff_ffff;
+        for (index, format, body) in changed {
+            let message_id = self.message_id();
+            let observer = &mut self.observers[index];
+            let notification = Message {
+                mtype: MessageType::NonConfirmable,
+                code: CODE_CONTENT,
+                message_id,
+                token: observer.token.clone(),
+                options: vec![
+                    (OPTION_OBSERVE, uint_option(self.observe_seq)),
+                    (OPTION_CONTENT_FORMAT, uint_option(format as u32)),
+                ],
+                payload: body.clone(),
+            };
+            observer.last = body;
+            out.push((observer.addr, notification.encode()));
+        }
+        out
+    }
+
+    fn message_id(&mut self) -> u16 {
+        self.next_message_id = self.next_message_id.wrapping_add(1);
+        self.next_message_id
+    }
+}
+
+/// Serve `link`'s node state over CoAP on `bind`, refreshing every `refresh`.
+#[cfg(feature = "coap")]
+pub async fn serve(
+    bind: SocketAddr,
+    link: crate::NodeLink,
+    refresh: std::time::Duration,
+) -> std::io::Result<()> {
+    use crate::events::NodeEvent;
+    use tokio::sync::broadcast::error::RecvError;
+
+    let socket = tokio::net::UdpSocket::bind(bind).await?;
+    let mut endpoint = CoapEndpoint::new();
+    let mut events = link.subscribe();
+    let mut tick = tokio::time::interval(refresh);
+    let mut buf = [0u8; 1152];
+
+    loop {
+        tokio::select! {
+            received = socket.recv_from(&mut buf) => {
+                let (len, from) = received?;
+                if let Some(reply) = endpoint.handle(from, &buf[..len]) {
+                    socket.send_to(&reply, from).await?;
+                }
+            }
+            event = events.recv() => match event {
+                Ok(NodeEvent::Reading(reading)) => endpoint.resources.record_reading(reading),
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            _ = tick.tick() => {
+                endpoint.resources.energy_score = link.metabolism.lock().unwrap().energy_score();
+                endpoint.resources.mesh = Some(link.mesh_snapshot.borrow().stats.clone());
+                let health = link.fleet_health.lock().unwrap().summary(std::time::Instant::now());
+                endpoint.resources.health = Some(health);
+                for (addr, notification) in endpoint.notifications() {
+                    socket.send_to(&notification, addr).await?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(path: &str, token: &[u8], observe: bool) -> Vec<u8> {
+        let mut options: Vec<(u16, Vec<u8>)> = path
+            .split('/')
+            .map(|segment| (OPTION_URI_PATH, segment.as_bytes().to_vec()))
+            .collect();
+        if observe {
+            options.insert(0, (OPTION_OBSERVE, Vec::new()));
+        }
+        Message {
+            mtype: MessageType::Confirmable,
+            code: CODE_GET,
+            message_id: 7,
+            token: token.to_vec(),
+            options,
+            payload: Vec::new(),
+        }
+        .encode()
+    }
+
+    fn client() -> SocketAddr {
+        "127.0.0.1:5683".parse().unwrap()
+    }
+
+    #[test]
+    fn message_roundtrip_with_extended_option_lengths() {
+        let message = Message {
+            mtype: MessageType::NonConfirmable,
+            code: CODE_CONTENT,
+            message_id: 0xbeef,
+            token: vec![1, 2, 3],
+            options: vec![(OPTION_URI_PATH, vec![b'x'; 300]), (300, vec![])],
+            payload: b"hello".to_vec(),
+        };
+
+        assert_eq!(Message::decode(&message.encode()).unwrap(), message);
+    }
+
+    #[test]
+    fn get_returns_battery_level_at_lwm2m_path() {
+        let mut endpoint = CoapEndpoint::new();
+        endpoint.resources.energy_score = 0.42;
+
+        let reply = endpoint
+            .handle(client(), &get("3/0/9", b"t", false))
+            .unwrap();
+        let reply = Message::decode(&reply).unwrap();
+
+        assert_eq!(reply.mtype, MessageType::Acknowledgement);
+        assert_eq!(reply.message_id, 7);
+        assert_eq!(reply.code, CODE_CONTENT);
+        assert_eq!(reply.payload, b"42");
+    }
+
+    #[test]
+    fn unknown_path_is_not_found() {
+        let mut endpoint = CoapEndpoint::new();
+        let reply = endpoint.handle(client(), &get("nope", b"", false)).unwrap();
+        assert_eq!(Message::decode(&reply).unwrap().code, CODE_NOT_FOUND);
+    }
+
+    #[test]
+    fn health_is_served_once_summarised() {
+        let mut endpoint = CoapEndpoint::new();
+        let reply = endpoint
+            .handle(client(), &get("health", b"", false))
+            .unwrap();
+        assert_eq!(Message::decode(&reply).unwrap().code, CODE_NOT_FOUND);
+
+        endpoint.resources.health = Some(FleetSummary {
+            local: Some(80),
+            nodes: 1,
+            mean: 0.8,
+            ..FleetSummary::default()
+        });
+        let reply = endpoint
+            .handle(client(), &get("health", b"", false))
+            .unwrap();
+        let reply = Message::decode(&reply).unwrap();
+        assert_eq!(reply.code, CODE_CONTENT);
+        let body: serde_json::Value = serde_json::from_slice(&reply.payload).unwrap();
+        assert_eq!(body["local"], 80);
+        assert_eq!(body["histogram"].as_array().unwrap().len(), 10);
+    }
+
+    #[test]
+    fn observers_are_notified_only_on_change() {
+        let mut endpoint = CoapEndpoint::new();
+        endpoint.resources.energy_score = 0.9;
+        endpoint
+            .handle(client(), &get("energy", b"ob", true))
+            .unwrap();
+        assert_eq!(endpoint.observer_count(), 1);
+
+        assert!(endpoint.notifications().is_empty());
+
+        endpoint.resources.energy_score = 0.1;
+        let notifications = endpoint.notifications();
+        assert_eq!(notifications.len(), 1);
+        let note = Message::decode(&notifications[0].1).unwrap();
+        assert_eq!(note.token, b"ob");
+        assert_eq!(note.observe(), Some(1));
+        assert!(String::from_utf8_lossy(&note.payload).contains("Critical"));
+
+        // A reset from the client cancels the observation.
+        let reset = Message {
+            mtype: MessageType::Reset,
+            code: 0,
+            message_id: note.message_id,
+            token: Vec::new(),
+            options: Vec::new(),
+            payload: Vec::new(),
+        };
+        assert!(endpoint.handle(client(), &reset.encode()).is_none());
+        assert_eq!(endpoint.observer_count(), 0);
+    }
+
+    #[test]
+    fn sensors_are_keyed_by_source_and_name() {
+        let mut resources = CoapResources::default();
+        resources.record_reading(SensorReading::new("n1".into(), "temp".into(), 20.0));
+        resources.record_reading(SensorReading::new("n1".into(), "temp".into(), 21.0));
+
+        let (_, body) = resources.render("sensors/n1/temp").unwrap();
+        let reading: SensorReading = serde_json::from_slice(&body).unwrap();
+        assert_eq!(reading.value, 21.0);
+        assert_eq!(resources.readings.len(), 1);
+    }
+}
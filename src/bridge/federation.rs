@@ -0,0 +1,485 @@
+//! Federation between two hypha swarms.
+//!
+//! Two swarms running under different topic namespaces share nothing by
+//! default. A federation bridge joins both, with one `Mycelium` in each, and
+//! carries only the traffic its [`FederationRule`]s select: each rule takes
+//! one topic from one swarm, optionally maps it to another topic kind and
+//! task capabilities to the names the other swarm uses, and caps the flow
+//! with its own rate limit. Forwarded messages are republished under the
+//! other swarm's namespace, signed by the bridge's peer there.
+//!
+//! Each forwarded envelope carries a [`FederationMark`] listing the swarms
+//! it has been in, origin first. A bridge never carries a message into a
+//! swarm it already visited, so two bridges between the same swarms, or a
+//! ring of them, cannot loop traffic back. Bare legacy payloads are wrapped
+//! in an envelope to carry the mark.
+//!
+//! [`Federation`] holds the rules and is plain code; [`run`] drives it over
+//! two swarms.
+
+use super::RateLimiter;
+use crate::core::{Capability, Task};
+use crate::mycelium::{Mycelium, MyceliumEvent, TopicKind};
+use crate::wire::{self, Envelope};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Most swarms a federated message may pass through.
+pub const MAX_FEDERATION_HOPS: usize = 8;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FederationError {
+    #[error("both swarms use the namespace {0:?}")]
+    SameNamespace(Option<String>),
+    #[error("invalid rule for {topic:?}: {reason}")]
+    InvalidRule {
+        topic: TopicKind,
+        reason: &'static str,
+    },
+    #[error("payload on `{topic}` is not an envelope: {reason}")]
+    Payload { topic: String, reason: String },
+    #[error("swarm error: {0}")]
+    Swarm(String),
+}
+
+/// One of the two federated swarms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Side {
+    A,
+    B,
+}
+
+impl Side {
+    pub fn other(self) -> Side {
+        match self {
+            Side::A => Side::B,
+            Side::B => Side::A,
+        }
+    }
+}
+
+/// The swarms a federated message has been in, origin first. Swarms are
+/// named by namespace; the empty string is the bare topic names.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FederationMark {
+    pub swarms: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FederationRule {
+    /// The swarm messages are taken from.
+    pub from: Side,
+    pub topic: TopicKind,
+    /// Topic in the other swarm; `None` keeps the same kind.
+    #[serde(default)]
+    pub to_topic: Option<TopicKind>,
+    /// For tasks: required capabilities to carry and what to call them in
+    /// the other swarm. When non-empty, tasks needing anything else stay.
+    #[serde(default)]
+    pub capabilities: Vec<(Capability, Capability)>,
+    /// Average messages per second forwarded by this rule. `None` is
+    /// unlimited.
+    #[serde(default)]
+    pub max_per_sec: Option<f32>,
+}
+
+impl FederationRule {
+    pub fn validate(&self) -> Result<(), FederationError> {
+        let invalid = |reason| FederationError::InvalidRule {
+            topic: self.topic,
+            reason,
+        };
+        let to_topic = self.to_topic.unwrap_or(self.topic);
+        if !self.capabilities.is_empty()
+            && (self.topic != TopicKind::Task || to_topic != TopicKind::Task)
+        {
+            return Err(invalid("capability mappings apply to tasks only"));
+        }
+        if matches!(self.max_per_sec, Some(rate) if rate.is_nan() || rate <= 0.0) {
+            return Err(invalid("max_per_sec must be positive"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FederationConfig {
+    /// Topic namespace of swarm A; `None` for the bare topic names.
+    pub a: Option<String>,
+    pub b: Option<String>,
+    pub rules: Vec<FederationRule>,
+}
+
+impl FederationConfig {
+    pub fn namespace(&self, side: Side) -> Option<&str> {
+        match side {
+            Side::A => self.a.as_deref(),
+            Side::B => self.b.as_deref(),
+        }
+    }
+
+    /// Gossip topic name of `kind` in the swarm on `side`.
+    pub fn topic_name(&self, side: Side, kind: TopicKind) -> String {
+        match self.namespace(side) {
+            Some(namespace) => kind.namespaced_name(namespace),
+            None => kind.base_name().to_string(),
+        }
+    }
+
+    fn swarm_name(&self, side: Side) -> String {
+        self.namespace(side).unwrap_or_default().to_string()
+    }
+}
+
+/// Routing state for one federation bridge: validated rules and their rate
+/// limiters.
+pub struct Federation {
+    config: FederationConfig,
+    limiters: Vec<Option<RateLimiter>>,
+}
+
+impl Federation {
+    pub fn new(config: FederationConfig) -> Result<Self, FederationError> {
+        if config.a == config.b {
+            return Err(FederationError::SameNamespace(config.a));
+        }
+        for rule in &config.rules {
+            rule.validate()?;
+        }
+        let limiters = config
+            .rules
+            .iter()
+            .map(|rule| {
+                rule.max_per_sec
+                    .map(|rate| RateLimiter::new(rate, rate.ceil() as u32))
+            })
+            .collect();
+        Ok(Self { config, limiters })
+    }
+
+    pub fn config(&self) -> &FederationConfig {
+        &self.config
+    }
+
+    /// Topic names to join in the swarm on `side`.
+    pub fn subscriptions(&self, side: Side) -> Vec<String> {
+        let mut topics: Vec<String> = self
+            .config
+            .rules
+            .iter()
+            .filter(|rule| rule.from == side)
+            .map(|rule| self.config.topic_name(side, rule.topic))
+            .collect();
+        topics.sort();
+        topics.dedup();
+        topics
+    }
+
+    /// Publishes (topic, payload) for the other swarm for a gossip message
+    /// received on `topic` in the swarm on `from`: one per matching rule
+    /// within its rate limit. Batch frames are split and each envelope
+    /// forwarded on its own, uncompressed.
+    pub fn forward(
+        &mut self,
+        from: Side,
+        topic: &str,
+        data: &[u8],
+        now: Instant,
+    ) -> Result<Vec<(String, Vec<u8>)>, FederationError> {
+        let Some(kind) = TopicKind::from_topic_name(topic)
+            .filter(|kind| self.config.topic_name(from, *kind) == topic)
+        else {
+            return Ok(Vec::new());
+        };
+        let payload_error = |reason: String| FederationError::Payload {
+            topic: topic.to_string(),
+            reason,
+        };
+        let items = wire::unbatch(data).map_err(|e| payload_error(e.to_string()))?;
+        let to = from.other();
+        let target = self.config.swarm_name(to);
+
+        let mut publishes = Vec::new();
+        for item in items {
+            let mut envelope = wire::decode::<serde_json::Value>(item)
+                .map_err(|e| payload_error(e.to_string()))?;
+            let mut mark = envelope.federation.take().unwrap_or_default();
+            if mark.swarms.is_empty() {
+                mark.swarms.push(self.config.swarm_name(from));
+            }
+            if mark.swarms.contains(&target) || mark.swarms.len() >= MAX_FEDERATION_HOPS {
+                continue;
+            }
+            mark.swarms.push(target.clone());
+
+            for index in 0..self.config.rules.len() {
+                let rule = &self.config.rules[index];
+                if rule.from != from || rule.topic != kind {
+                    continue;
+                }
+                let Some(body) = map_body(rule, &envelope.body) else {
+                    continue;
+                };
+                let to_topic = self.config.topic_name(to, rule.to_topic.unwrap_or(kind));
+                if !self.take_token(index, now) {
+                    continue;
+                }
+                let forwarded = Envelope {
+                    v: envelope.v.max(wire::ENVELOPE_VERSION),
+                    priority: envelope.priority,
+                    trace: envelope.trace.clone(),
+                    bridge: None,
+                    federation: Some(mark.clone()),
+                    // The source is not reachable from the other swarm.
+                    seq: None,
+                    body,
+                    extra: envelope.extra.clone(),
+                };
+                let bytes = forwarded
+                    .encode()
+                    .map_err(|e| payload_error(e.to_string()))?;
+                publishes.push((to_topic, bytes));
+            }
+        }
+        Ok(publishes)
+    }
+
+    fn take_token(&mut self, index: usize, now: Instant) -> bool {
+        self.limiters[index]
+            .as_mut()
+            .is_none_or(|limiter| limiter.allow(now))
+    }
+}
+
+/// `body` as `rule` forwards it, or `None` if the rule does not carry it.
+fn map_body(rule: &FederationRule, body: &serde_json::Value) -> Option<serde_json::Value> {
+    if rule.capabilities.is_empty() {
+        return Some(body.clone());
+    }
+    // Lease messages share the task topic; only tasks are mapped.
+    let mut task: Task = serde_json::from_value(body.clone()).ok()?;
+    let (_, mapped) = rule
+        .capabilities
+        .iter()
+        .find(|(from, _)| *from == task.required_capability)?;
+    task.required_capability = mapped.clone();
+    serde_json::to_value(task).ok()
+}
+
+/// Bridge `a` and `b` until either swarm's event stream ends. Both swarms
+/// must already be set up in the namespaces `federation` names and
+/// connected to their peers.
+pub async fn run(
+    mut federation: Federation,
+    mut a: Mycelium,
+    mut b: Mycelium,
+) -> Result<(), FederationError> {
+    use libp2p::futures::StreamExt;
+    use libp2p::gossipsub;
+    use libp2p::swarm::SwarmEvent;
+
+    for (side, mycelium) in [(Side::A, &mut a), (Side::B, &mut b)] {
+        for topic in federation.subscriptions(side) {
+            mycelium
+                .swarm
+                .behaviour_mut()
+                .gossipsub
+                .subscribe(&gossipsub::IdentTopic::new(topic))
+                .map_err(|e| FederationError::Swarm(e.to_string()))?;
+        }
+    }
+
+    loop {
+        let (from, event) = tokio::select! {
+            event = a.swarm.next() => (Side::A, event),
+            event = b.swarm.next() => (Side::B, event),
+        };
+        let Some(event) = event else {
+            return Ok(());
+        };
+        let SwarmEvent::Behaviour(MyceliumEvent::Gossipsub(gossipsub::Event::Message {
+            message,
+            ..
+        })) = event
+        else {
+            continue;
+        };
+        let publishes =
+            match federation.forward(from, message.topic.as_str(), &message.data, Instant::now()) {
+                Ok(publishes) => publishes,
+                Err(e) => {
+                    tracing::debug!(err = %e, "Not federating message");
+                    continue;
+                }
+            };
+        let target = match from {
+            Side::A => &mut b,
+            Side::B => &mut a,
+        };
+        for (topic, data) in publishes {
+            if let Err(e) = target
+                .swarm
+                .behaviour_mut()
+                .gossipsub
+                .publish(gossipsub::IdentTopic::new(topic.clone()), data)
+            {
+                tracing::debug!(%topic, err = %e, "Failed to federate message");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::SensorReading;
+    use crate::wire::Priority;
+    use std::time::Duration;
+
+    fn rule(from: Side, topic: TopicKind) -> FederationRule {
+        FederationRule {
+            from,
+            topic,
+            to_topic: None,
+            capabilities: Vec::new(),
+            max_per_sec: None,
+        }
+    }
+
+    fn federation(rules: Vec<FederationRule>) -> Federation {
+        Federation::new(FederationConfig {
+            a: Some("farm".to_string()),
+            b: Some("city".to_string()),
+            rules,
+        })
+        .unwrap()
+    }
+
+    fn reading() -> Vec<u8> {
+        let reading = SensorReading {
+            source_id: "s".to_string(),
+            sensor: "temp".to_string(),
+            value: 21.0,
+            unit: None,
+            timestamp_ms: None,
+            summary: None,
+        };
+        Envelope::new(Priority::Normal, reading).encode().unwrap()
+    }
+
+    #[test]
+    fn selected_topics_cross_with_namespaces_rewritten() {
+        let mut federation = federation(vec![rule(Side::A, TopicKind::Sensor)]);
+        let now = Instant::now();
+        assert_eq!(
+            federation.subscriptions(Side::A),
+            ["farm/hypha_sensor_readings"]
+        );
+        assert!(federation.subscriptions(Side::B).is_empty());
+
+        let out = federation
+            .forward(Side::A, "farm/hypha_sensor_readings", &reading(), now)
+            .unwrap();
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].0, "city/hypha_sensor_readings");
+        let envelope = wire::decode::<SensorReading>(&out[0].1).unwrap();
+        assert_eq!(envelope.body.value, 21.0);
+        assert_eq!(
+            envelope.federation.unwrap().swarms,
+            ["farm".to_string(), "city".to_string()]
+        );
+
+        // Not selected, or not from this swarm's namespace.
+        assert!(federation
+            .forward(Side::A, "farm/hypha_spikes", &reading(), now)
+            .unwrap()
+            .is_empty());
+        assert!(federation
+            .forward(Side::A, "hypha_sensor_readings", &reading(), now)
+            .unwrap()
+            .is_empty());
+        assert!(federation
+            .forward(Side::B, "city/hypha_sensor_readings", &reading(), now)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn marked_messages_never_return_to_a_visited_swarm() {
+        let mut there = federation(vec![rule(Side::A, TopicKind::Sensor)]);
+        let mut back = federation(vec![rule(Side::B, TopicKind::Sensor)]);
+        let now = Instant::now();
+        let (topic, data) = there
+            .forward(Side::A, "farm/hypha_sensor_readings", &reading(), now)
+            .unwrap()
+            .remove(0);
+        assert!(back
+            .forward(Side::B, &topic, &data, now)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn task_capabilities_are_mapped_and_flow_is_rate_limited() {
+        let mut tasks = rule(Side::B, TopicKind::Task);
+        tasks.capabilities = vec![(
+            Capability::Sensing("temperature".to_string()),
+            Capability::Sensing("temp".to_string()),
+        )];
+        tasks.max_per_sec = Some(1.0);
+        let mut federation = federation(vec![tasks]);
+        let now = Instant::now();
+
+        let task = |cap| {
+            Envelope::new(
+                Priority::High,
+                Task::new("t".to_string(), cap, 1, "p".to_string()),
+            )
+            .encode()
+            .unwrap()
+        };
+        let wanted = task(Capability::Sensing("temperature".to_string()));
+        let out = federation
+            .forward(Side::B, "city/hypha_task_stream", &wanted, now)
+            .unwrap();
+        assert_eq!(out[0].0, "farm/hypha_task_stream");
+        let envelope = wire::decode::<Task>(&out[0].1).unwrap();
+        assert_eq!(
+            envelope.body.required_capability,
+            Capability::Sensing("temp".to_string())
+        );
+        assert_eq!(envelope.priority, Priority::High);
+
+        // Over the rate, then an unmapped capability.
+        assert!(federation
+            .forward(Side::B, "city/hypha_task_stream", &wanted, now)
+            .unwrap()
+            .is_empty());
+        let later = now + Duration::from_secs(1);
+        assert!(federation
+            .forward(
+                Side::B,
+                "city/hypha_task_stream",
+                &task(Capability::Compute(1)),
+                later
+            )
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn rules_and_namespaces_are_validated() {
+        let same = FederationConfig {
+            a: None,
+            b: None,
+            rules: Vec::new(),
+        };
+        assert!(matches!(
+            Federation::new(same),
+            Err(FederationError::SameNamespace(None))
+        ));
+        let mut sensor = rule(Side::A, TopicKind::Sensor);
+        sensor.capabilities = vec![(Capability::Compute(1), Capability::Compute(2))];
+        assert!(sensor.validate().is_err());
+    }
+}
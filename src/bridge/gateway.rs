@@ -0,0 +1,665 @@
+//! HTTP gateway for task submission from cloud services.
+//!
+//! Services that are not libp2p peers submit tasks as JSON over HTTP,
+//! authenticated by a bearer key. [`Gateway`] is the sans-IO core: it maps
+//! the key to a [`GatewayClient`], checks the requested capability against
+//! the client's grant, and turns the submission into a [`Task`] published by
+//! this node and carrying a delegation it minted for the named worker. It then
+//! follows every task it submitted and turns node events and results into
+//! [`TaskUpdate`]s, which the server streams back to the caller as
+//! newline-delimited JSON until the task completes.
+//!
+//! Task ids are minted by the gateway from random bits, never taken from the
+//! caller, and each task's updates go only to the connection that submitted
+//! it: a completed task's output is already decrypted, so a caller able to
+//! pick or guess another's task id could otherwise read its result. The server also serves
+//! the node's mesh metrics to Prometheus at `GET /metrics`, without a key.
+//!
+//! Only HTTP/1.1 is served; gRPC clients need a proxy in front. The server
+//! (`serve`) needs the `gateway` feature.
+
+use super::RateLimiter;
+use crate::auth::{peer_id_of, AuthError, Delegation};
+use crate::core::{Capability, Task, TaskResult};
+use crate::events::NodeEvent;
+use crate::results::open_result;
+use ed25519_dalek::SigningKey;
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Largest request head or body the gateway reads.
+pub const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GatewayError {
+    #[error("missing or unknown API key")]
+    Unauthorized,
+    #[error("client `{0}` is over its submission rate")]
+    RateLimited(String),
+    #[error("client grant {granted:?} does not cover {requested:?}")]
+    Forbidden {
+        granted: Capability,
+        requested: Capability,
+    },
+    #[error("malformed submission: {0}")]
+    Malformed(String),
+    #[error("no worker named and no default worker configured")]
+    NoWorker,
+    #[error("failed to mint task token: {0}")]
+    Auth(#[from] AuthError),
+}
+
+impl GatewayError {
+    /// HTTP status code and reason phrase for the error.
+    pub fn status(&self) -> (u16, &'static str) {
+        match self {
+            GatewayError::Unauthorized => (401, "Unauthorized"),
+            GatewayError::RateLimited(_) => (429, "Too Many Requests"),
+            GatewayError::Forbidden { .. } => (403, "Forbidden"),
+            GatewayError::Malformed(_) | GatewayError::NoWorker => (400, "Bad Request"),
+            GatewayError::Auth(_) => (500, "Internal Server Error"),
+        }
+    }
+}
+
+/// What one API key may submit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatewayClient {
+    pub name: String,
+    /// Broadest capability the client's tasks may require.
+    pub capability: Capability,
+    /// Tenant the client's tasks belong to. `None` submits node-wide tasks.
+    pub tenant: Option<String>,
+    pub max_per_sec: f32,
+    pub burst: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GatewayConfig {
+    /// Clients by API key.
+    pub clients: HashMap<String, GatewayClient>,
+    /// Lifetime of the token minted for each task.
+    pub token_ttl: Duration,
+    /// Worker addressed when a submission names none.
+    pub default_worker: Option<PeerId>,
+    /// This node's own grant, when it is not a trusted root itself. Minted
+    /// tokens are attenuated from it.
+    pub proof: Option<Delegation>,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            clients: HashMap::new(),
+            token_ttl: Duration::from_secs(3600),
+            default_worker: None,
+            proof: None,
+        }
+    }
+}
+
+/// Body of a task submission.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TaskSubmission {
+    pub capability: Capability,
+    #[serde(default)]
+    pub priority: u8,
+    #[serde(default)]
+    pub public_result: bool,
+    /// Peer id of the worker the token is addressed to.
+    #[serde(default)]
+    pub worker: Option<String>,
+}
+
+/// Progress of a submitted task, as streamed to its caller.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TaskUpdate {
+    Published {
+        task_id: String,
+    },
+    Awarded {
+        task_id: String,
+        winner: String,
+    },
+    /// The winner stopped renewing; the task was published again.
+    Lapsed {
+        task_id: String,
+        winner: String,
+    },
+    /// The winner turned the award down; the task was published again.
+    Declined {
+        task_id: String,
+        winner: String,
+        reason: String,
+    },
+    Completed {
+        task_id: String,
+        worker: String,
+        output: Vec<u8>,
+    },
+    /// A result arrived that this node could not open.
+    Failed {
+        task_id: String,
+        reason: String,
+    },
+}
+
+impl TaskUpdate {
+    pub fn task_id(&self) -> &str {
+        match self {
+            TaskUpdate::Published { task_id }
+            | TaskUpdate::Awarded { task_id, .. }
+            | TaskUpdate::Lapsed { task_id, .. }
+            | TaskUpdate::Declined { task_id, .. }
+            | TaskUpdate::Completed { task_id, .. }
+            | TaskUpdate::Failed { task_id, .. } => task_id,
+        }
+    }
+
+    /// Whether no further updates follow for the task.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TaskUpdate::Completed { .. } | TaskUpdate::Failed { .. }
+        )
+    }
+}
+
+/// Sans-IO gateway: submissions in, tasks and updates out.
+pub struct Gateway {
+    pub config: GatewayConfig,
+    signing_key: SigningKey,
+    peer_id: PeerId,
+    limiters: HashMap<String, RateLimiter>,
+    /// Client name per submitted task id still in progress.
+    submitted: HashMap<String, String>,
+}
+
+impl Gateway {
+    /// A gateway publishing as the node that owns `signing_key`.
+    pub fn new(config: GatewayConfig, signing_key: SigningKey) -> Self {
+        Self {
+            config,
+            peer_id: peer_id_of(&signing_key),
+            signing_key,
+            limiters: HashMap::new(),
+            submitted: HashMap::new(),
+        }
+    }
+
+    /// Tasks submitted and not yet completed.
+    pub fn in_progress(&self) -> usize {
+        self.submitted.len()
+    }
+
+    /// Check a submission with `bearer` key and build its task, signed for
+    /// the worker at Unix time `now`.
+    pub fn submit(
+        &mut self,
+        bearer: Option<&str>,
+        body: &[u8],
+        now: u64,
+        at: Instant,
+    ) -> Result<Task, GatewayError> {
+        let client = bearer
+            .and_then(|key| self.config.clients.get(key))
+            .ok_or(GatewayError::Unauthorized)?
+            .clone();
+        let limiter = self
+            .limiters
+            .entry(client.name.clone())
+            .or_insert_with(|| RateLimiter::new(client.max_per_sec, client.burst));
+        if !limiter.allow(at) {
+            return Err(GatewayError::RateLimited(client.name));
+        }
+        let submission: TaskSubmission =
+            serde_json::from_slice(body).map_err(|e| GatewayError::Malformed(e.to_string()))?;
+        if !client.capability.satisfies(&submission.capability) {
+            return Err(GatewayError::Forbidden {
+                granted: client.capability,
+                requested: submission.capability,
+            });
+        }
+        let worker = match &submission.worker {
+            Some(worker) => worker
+                .parse()
+                .map_err(|_| GatewayError::Malformed(format!("bad worker id `{worker}`")))?,
+            None => self.config.default_worker.ok_or(GatewayError::NoWorker)?,
+        };
+
+        let (key, ttl, proof) = (
+            &self.signing_key,
+            self.config.token_ttl,
+            self.config.proof.clone(),
+        );
+        let capability = submission.capability.clone();
+        let token = match &client.tenant {
+            Some(tenant) => {
+                Delegation::mint_for_tenant(key, &worker, capability, tenant, now, ttl, proof)?
+            }
+            None => Delegation::mint(key, &worker, capability, now, ttl, proof)?,
+        };
+
+        let id = loop {
+            let id = format!("gw-{:032x}", rand::random::<u128>());
+            if !self.submitted.contains_key(&id) {
+                break id;
+            }
+        };
+        let mut task = Task::new(
+            id,
+            submission.capability,
+            submission.priority,
+            self.peer_id.to_string(),
+        )
+        .with_auth(token.encode());
+        if submission.public_result {
+            task = task.with_public_result();
+        }
+        if let Some(tenant) = &client.tenant {
+            task = task.with_tenant(tenant);
+        }
+        self.submitted.insert(task.id.clone(), client.name);
+        Ok(task)
+    }
+
+    /// The update `event` means for a submitted task, if any.
+    pub fn on_event(&self, event: &NodeEvent) -> Option<TaskUpdate> {
+        let update = match event {
+            NodeEvent::Awarded { task, winner } => TaskUpdate::Awarded {
+                task_id: task.id.clone(),
+                winner: winner.clone(),
+            },
+            NodeEvent::LeaseLapsed { task_id, winner } => TaskUpdate::Lapsed {
+                task_id: task_id.clone(),
+                winner: winner.clone(),
+            },
+            NodeEvent::AwardDeclined {
+                task_id,
+                winner,
+                reason,
+            } => TaskUpdate::Declined {
+                task_id: task_id.clone(),
+                winner: winner.clone(),
+                reason: reason.clone(),
+            },
+            _ => return None,
+        };
+        self.submitted
+            .contains_key(update.task_id())
+            .then_some(update)
+    }
+
+    /// Open a result for a submitted task. The task is then done.
+    pub fn on_result(&mut self, result: &TaskResult) -> Option<TaskUpdate> {
+        self.submitted.remove(&result.task_id)?;
+        let task_id = result.task_id.clone();
+        Some(match open_result(&self.signing_key, result) {
+            Ok(output) => TaskUpdate::Completed {
+                task_id,
+                worker: result.worker_id.clone(),
+                output,
+            },
+            Err(e) => TaskUpdate::Failed {
+                task_id,
+                reason: e.to_string(),
+            },
+        })
+    }
+}
+
+/// A parsed HTTP/1.1 request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Header names lowercased.
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// Parse a complete request from `bytes`. `Ok(None)` means more bytes
+    /// are needed.
+    pub fn parse(bytes: &[u8]) -> Result<Option<Self>, GatewayError> {
+        let malformed = |reason: &str| GatewayError::Malformed(reason.to_string());
+        let Some(head_end) = bytes.windows(4).position(|w| w == b"\r\n\r\n") else {
+            return if bytes.len() > MAX_REQUEST_BYTES {
+                Err(malformed("request head too large"))
+            } else {
+                Ok(None)
+            };
+        };
+        let head =
+            std::str::from_utf8(&bytes[..head_end]).map_err(|_| malformed("non-UTF-8 head"))?;
+        let mut lines = head.split("\r\n");
+        let mut request_line = lines.next().unwrap_or_default().split(' ');
+        let (Some(method), Some(path)) = (request_line.next(), request_line.next()) else {
+            return Err(malformed("bad request line"));
+        };
+        let headers: HashMap<String, String> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+            .collect();
+        let length = match headers.get("content-length") {
+            Some(value) => value
+                .parse::<usize>()
+                .map_err(|_| malformed("bad content-length"))?,
+            None => 0,
+        };
+        if length > MAX_REQUEST_BYTES {
+            return Err(malformed("body too large"));
+        }
+        let body_start = head_end + 4;
+        let Some(body) = bytes.get(body_start..body_start + length) else {
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers,
+            body: body.to_vec(),
+        }))
+    }
+
+    pub fn bearer(&self) -> Option<&str> {
+        self.headers.get("authorization")?.strip_prefix("Bearer ")
+    }
+}
+
+/// Accept task submissions on `bind` and publish them through `link`.
+///
+/// `POST /tasks` answers with a stream of [`TaskUpdate`] lines that ends
+/// once the task completes. `GET /metrics` answers with the mesh metrics
+/// from the node's latest mesh snapshot. Results reach the gateway through `results`;
+/// the embedder forwards whatever result transport it uses.
+#[cfg(feature = "gateway")]
+pub async fn serve(
+    bind: std::net::SocketAddr,
+    link: crate::NodeLink,
+    gateway: Gateway,
+    mut results: tokio::sync::mpsc::Receiver<TaskResult>,
+) -> std::io::Result<()> {
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::broadcast::error::RecvError;
+
+    let listener = tokio::net::TcpListener::bind(bind).await?;
+    let gateway = Arc::new(Mutex::new(gateway));
+    let routes: Routes = Arc::default();
+    let mut events = link.subscribe();
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                tokio::spawn(handle_connection(
+                    stream,
+                    link.clone(),
+                    gateway.clone(),
+                    routes.clone(),
+                ));
+            }
+            event = events.recv() => match event {
+                Ok(event) => {
+                    if let Some(update) = gateway.lock().unwrap().on_event(&event) {
+                        route(&routes, update);
+                    }
+                }
+                Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => return Ok(()),
+            },
+            Some(result) = results.recv() => {
+                if let Some(update) = gateway.lock().unwrap().on_result(&result) {
+                    route(&routes, update);
+                }
+            }
+        }
+    }
+}
+
+/// The connection streaming each submitted task's updates, by task id.
+#[cfg(feature = "gateway")]
+type Routes = std::sync::Arc<
+    std::sync::Mutex<HashMap<String, tokio::sync::mpsc::UnboundedSender<TaskUpdate>>>,
+>;
+
+/// Hand `update` to the connection that submitted its task, forgetting the
+/// route once the task is done or the connection has gone.
+#[cfg(feature = "gateway")]
+fn route(routes: &Routes, update: TaskUpdate) {
+    let mut routes = routes.lock().unwrap();
+    let task_id = update.task_id().to_string();
+    let last = update.is_final();
+    let delivered = routes
+        .get(&task_id)
+        .is_some_and(|connection| connection.send(update).is_ok());
+    if last || !delivered {
+        routes.remove(&task_id);
+    }
+}
+
+#[cfg(feature = "gateway")]
+async fn handle_connection(
+    mut stream: tokio::net::TcpStream,
+    link: crate::NodeLink,
+    gateway: std::sync::Arc<std::sync::Mutex<Gateway>>,
+    routes: Routes,
+) -> std::io::Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let request = loop {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        buf.extend_from_slice(&chunk[..read]);
+        match Request::parse(&buf) {
+            Ok(Some(request)) => break Ok(request),
+            Ok(None) => {}
+            Err(e) => break Err(e),
+        }
+    };
+
+    if request
+        .as_ref()
+        .is_ok_and(|request| request.method == "GET" && request.path == "/metrics")
+    {
+        let body = crate::prometheus::render_mesh(&link.mesh_snapshot.borrow().stats);
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            crate::prometheus::CONTENT_TYPE,
+            body.len()
+        );
+        return stream.write_all(response.as_bytes()).await;
+    }
+    let (sender, mut updates) = tokio::sync::mpsc::unbounded_channel();
+    let submitted = request.and_then(|request| {
+        if request.method != "POST" || request.path != "/tasks" {
+            return Err(GatewayError::Malformed(format!(
+                "no route for {} {}",
+                request.method, request.path
+            )));
+        }
+        let task = gateway.lock().unwrap().submit(
+            request.bearer(),
+            &request.body,
+            crate::auth::unix_now(),
+            Instant::now(),
+        )?;
+        // Routed before publishing, so no update can arrive unrouted.
+        routes.lock().unwrap().insert(task.id.clone(), sender);
+        Ok(task)
+    });
+    let task = match submitted {
+        Ok(task) => task,
+        Err(e) => {
+            let (code, reason) = e.status();
+            let body = serde_json::json!({ "error": e.to_string() }).to_string();
+            let response = format!(
+                "HTTP/1.1 {code} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            return stream.write_all(response.as_bytes()).await;
+        }
+    };
+
+    tracing::info!(task_id = %task.id, "Gateway accepted task");
+    let task_id = task.id.clone();
+    link.publish_task(task);
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+    let mut update = TaskUpdate::Published { task_id };
+    loop {
+        let mut line = serde_json::to_vec(&update).expect("task update serializes");
+        line.push(b'\n');
+        stream.write_all(&line).await?;
+        if update.is_final() {
+            return Ok(());
+        }
+        update = match updates.recv().await {
+            Some(next) => next,
+            None => return Ok(()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::{unix_now, DelegationLimits};
+    use crate::results::seal_result;
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn gateway(worker: PeerId) -> Gateway {
+        let client = GatewayClient {
+            name: "billing".to_string(),
+            capability: Capability::Compute(10),
+            tenant: None,
+            max_per_sec: 1.0,
+            burst: 2,
+        };
+        let config = GatewayConfig {
+            clients: HashMap::from([("secret".to_string(), client)]),
+            default_worker: Some(worker),
+            ..GatewayConfig::default()
+        };
+        Gateway::new(config, key(1))
+    }
+
+    #[test]
+    fn submissions_become_tasks_the_worker_can_verify() {
+        let worker = key(2);
+        let worker_id = peer_id_of(&worker);
+        let mut gateway = gateway(worker_id);
+        let now = unix_now();
+        let at = Instant::now();
+        let body = br#"{"capability":{"Compute":5},"priority":3}"#;
+
+        assert!(matches!(
+            gateway.submit(Some("wrong"), body, now, at),
+            Err(GatewayError::Unauthorized)
+        ));
+        let too_big = br#"{"capability":{"Compute":50}}"#;
+        assert_eq!(
+            gateway
+                .submit(Some("secret"), too_big, now, at)
+                .unwrap_err()
+                .status()
+                .0,
+            403
+        );
+
+        let task = gateway.submit(Some("secret"), body, now, at).unwrap();
+        assert!(task.id.starts_with("gw-"));
+        assert_eq!(task.source_id, peer_id_of(&key(1)).to_string());
+        let token = Delegation::decode(task.auth_token.as_deref().unwrap()).unwrap();
+        let gateway_id = peer_id_of(&key(1)).to_string();
+        token
+            .verify(
+                &worker_id.to_string(),
+                &task.required_capability,
+                &[gateway_id],
+                &DelegationLimits::default(),
+                now,
+            )
+            .unwrap();
+
+        // The burst of two is spent.
+        assert!(matches!(
+            gateway.submit(Some("secret"), body, now, at),
+            Err(GatewayError::RateLimited(_))
+        ));
+
+        // Ids are the gateway's own: one a caller names is ignored.
+        let named = br#"{"id":"job-1","capability":{"Compute":5}}"#;
+        let later = at + Duration::from_secs(1);
+        let other = gateway.submit(Some("secret"), named, now, later).unwrap();
+        assert!(other.id != task.id && other.id != "job-1");
+
+        let awarded = NodeEvent::Awarded {
+            task: task.clone(),
+            winner: worker_id.to_string(),
+        };
+        assert!(matches!(
+            gateway.on_event(&awarded),
+            Some(TaskUpdate::Awarded { .. })
+        ));
+        let result = seal_result(&task, &worker_id.to_string(), b"42".to_vec()).unwrap();
+        let done = gateway.on_result(&result).unwrap();
+        assert!(done.is_final());
+        assert_eq!(
+            done,
+            TaskUpdate::Completed {
+                task_id: task.id.clone(),
+                worker: worker_id.to_string(),
+                output: b"42".to_vec(),
+            }
+        );
+        assert_eq!(gateway.in_progress(), 1);
+        assert!(gateway.on_event(&awarded).is_none());
+    }
+
+    #[cfg(feature = "gateway")]
+    #[test]
+    fn updates_reach_only_the_submitting_connection() {
+        let routes: Routes = Default::default();
+        let (mine, mut mine_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (theirs, mut theirs_rx) = tokio::sync::mpsc::unbounded_channel();
+        routes.lock().unwrap().insert("gw-a".to_string(), mine);
+        routes.lock().unwrap().insert("gw-b".to_string(), theirs);
+
+        let done = TaskUpdate::Completed {
+            task_id: "gw-a".to_string(),
+            worker: "w".to_string(),
+            output: b"secret".to_vec(),
+        };
+        route(&routes, done.clone());
+        assert_eq!(mine_rx.try_recv().unwrap(), done);
+        assert!(theirs_rx.try_recv().is_err());
+        assert!(!routes.lock().unwrap().contains_key("gw-a"));
+    }
+
+    #[test]
+    fn requests_parse_once_the_body_arrives() {
+        let raw =
+            b"POST /tasks HTTP/1.1\r\nAuthorization: Bearer secret\r\nContent-Length: 4\r\n\r\n{}";
+        assert_eq!(Request::parse(raw).unwrap(), None);
+        let mut full = raw.to_vec();
+        full.extend_from_slice(b"\r\n");
+        let request = Request::parse(&full).unwrap().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.bearer(), Some("secret"));
+        assert_eq!(request.body, b"{}\r\n");
+    }
+}
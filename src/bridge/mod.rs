@@ -0,0 +1,189 @@
+//! Bridges between hypha gossip and other protocols.
+//!
+//! Each bridge keeps its payload translation and routing rules as plain,
+//! testable code. Network clients sit behind cargo features so a default
+//! build does not pull in every protocol stack.
+
+pub mod coap;
+pub mod federation;
+pub mod gateway;
+pub mod mqtt;
+pub mod zenoh;
+
+use crate::core::{SensorReading, Task};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Which way a bridge mapping forwards messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    /// External messages are injected into the mesh.
+    IntoMesh,
+    /// Mesh messages are published externally.
+    FromMesh,
+    Both,
+}
+
+impl Direction {
+    pub fn inbound(self) -> bool {
+        matches!(self, Direction::IntoMesh | Direction::Both)
+    }
+
+    pub fn outbound(self) -> bool {
+        matches!(self, Direction::FromMesh | Direction::Both)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PayloadKind {
+    SensorReading,
+    Task,
+}
+
+/// A message crossing a bridge in either direction.
+#[derive(Debug, Clone)]
+pub enum Bridged {
+    Reading(SensorReading),
+    Task(Task),
+}
+
+impl Bridged {
+    pub fn kind(&self) -> PayloadKind {
+        match self {
+            Bridged::Reading(_) => PayloadKind::SensorReading,
+            Bridged::Task(_) => PayloadKind::Task,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PartialReading {
+    value: f32,
+    #[serde(default)]
+    sensor: Option<String>,
+    #[serde(default)]
+    source_id: Option<String>,
+    #[serde(default)]
+    unit: Option<String>,
+    #[serde(default)]
+    timestamp_ms: Option<u64>,
+}
+
+/// Decode an external JSON payload published under `name` (a topic or key).
+///
+/// Readings may be a full `SensorReading`, an object with at least `value`,
+/// or a bare number. Missing names come from `name`: the last level is the
+/// sensor and the level before it the source, e.g. `site/pump-3/temp`
+/// becomes sensor `temp` from source `<origin>:pump-3`.
+pub(crate) fn decode_payload(
+    kind: PayloadKind,
+    origin: &str,
+    name: &str,
+    payload: &[u8],
+) -> Result<Bridged, String> {
+    match kind {
+        PayloadKind::Task => serde_json::from_slice(payload)
+            .map(Bridged::Task)
+            .map_err(|e| e.to_string()),
+        PayloadKind::SensorReading => {
+            let partial = match serde_json::from_slice::<PartialReading>(payload) {
+                Ok(partial) => partial,
+                Err(e) => {
+                    let value =
+                        serde_json::from_slice::<f32>(payload).map_err(|_| e.to_string())?;
+                    PartialReading {
+                        value,
+                        sensor: None,
+                        source_id: None,
+                        unit: None,
+                        timestamp_ms: None,
+                    }
+                }
+            };
+            let mut levels = name.rsplit('/');
+            let sensor_level = levels.next().unwrap_or(name);
+            let source_level = levels.next().unwrap_or(sensor_level);
+            Ok(Bridged::Reading(SensorReading {
+                source_id: partial
+                    .source_id
+                    .unwrap_or_else(|| format!("{origin}:{source_level}")),
+                sensor: partial.sensor.unwrap_or_else(|| sensor_level.to_string()),
+                value: partial.value,
+                unit: partial.unit,
+                timestamp_ms: partial.timestamp_ms,
+                summary: None,
+            }))
+        }
+    }
+}
+
+pub(crate) fn encode_payload(message: &Bridged) -> Option<Vec<u8>> {
+    match message {
+        Bridged::Reading(reading) => serde_json::to_vec(reading).ok(),
+        Bridged::Task(task) => serde_json::to_vec(task).ok(),
+    }
+}
+
+/// Substitute `{source_id}` and, for readings, `{sensor}` in `template`.
+pub(crate) fn expand_template(template: &str, message: &Bridged) -> String {
+    match message {
+        Bridged::Reading(reading) => template
+            .replace("{source_id}", &reading.source_id)
+            .replace("{sensor}", &reading.sensor),
+        Bridged::Task(task) => template.replace("{source_id}", &task.source_id),
+    }
+}
+
+/// Token bucket capping how many messages a bridge mapping forwards.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    per_sec: f32,
+    burst: f32,
+    tokens: f32,
+    last: Option<Instant>,
+}
+
+impl RateLimiter {
+    /// Allow `per_sec` messages per second on average and up to `burst` at once.
+    pub fn new(per_sec: f32, burst: u32) -> Self {
+        let burst = burst.max(1) as f32;
+        Self {
+            per_sec: per_sec.max(0.0),
+            burst,
+            tokens: burst,
+            last: None,
+        }
+    }
+
+    /// Take one token if available.
+    pub fn allow(&mut self, now: Instant) -> bool {
+        if let Some(last) = self.last {
+            let elapsed = now.saturating_duration_since(last);
+            self.tokens = (self.tokens + elapsed.as_secs_f32() * self.per_sec).min(self.burst);
+        }
+        self.last = Some(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn rate_limiter_refills_over_time() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new(2.0, 2);
+
+        assert!(limiter.allow(start));
+        assert!(limiter.allow(start));
+        assert!(!limiter.allow(start));
+        assert!(limiter.allow(start + Duration::from_millis(500)));
+    }
+}
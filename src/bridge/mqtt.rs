@@ -0,0 +1,365 @@
+//! MQTT bridge for fleets that already speak MQTT.
+//!
+//! Each [`MqttMapping`] ties an MQTT topic (a filter for inbound mappings) to
+//! one kind of hypha payload, with its own direction, QoS and rate limit.
+//! Inbound JSON becomes a [`SensorReading`] or [`Task`] and is queued on the
+//! node through a `NodeLink`; readings and tasks seen on the mesh are
+//! published back to MQTT by outbound mappings.
+//!
+//! The translation rules below are always built. The broker client
+//! (`run`) needs the `mqtt` feature.
+
+use super::{
+    decode_payload, encode_payload, expand_template, Bridged, Direction, PayloadKind, RateLimiter,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MqttBridgeError {
+    #[error("invalid mapping for `{topic}`: {reason}")]
+    InvalidMapping { topic: String, reason: &'static str },
+    #[error("payload on `{topic}` is not a valid {kind:?}: {reason}")]
+    Payload {
+        topic: String,
+        kind: PayloadKind,
+        reason: String,
+    },
+    #[error("MQTT client error: {0}")]
+    Client(String),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Qos {
+    AtMostOnce,
+    #[default]
+    AtLeastOnce,
+    ExactlyOnce,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MqttMapping {
+    /// Topic filter (`+`/`#` allowed) for inbound mappings; topic template
+    /// for outbound ones, where `{source_id}` and `{sensor}` are substituted
+    /// from the reading.
+    pub mqtt_topic: String,
+    pub kind: PayloadKind,
+    pub direction: Direction,
+    #[serde(default)]
+    pub qos: Qos,
+    /// Average messages per second forwarded by this mapping. `None` is
+    /// unlimited.
+    #[serde(default)]
+    pub max_per_sec: Option<f32>,
+}
+
+impl MqttMapping {
+    pub fn validate(&self) -> Result<(), MqttBridgeError> {
+        let invalid = |reason| MqttBridgeError::InvalidMapping {
+            topic: self.mqtt_topic.clone(),
+            reason,
+        };
+        if self.mqtt_topic.is_empty() {
+            return Err(invalid("empty topic"));
+        }
+        let has_wildcard = self.mqtt_topic.contains(['+', '#']);
+        if self.direction.outbound() && has_wildcard {
+            return Err(invalid("outbound topics cannot contain wildcards"));
+        }
+        if let Some(pos) = self.mqtt_topic.find('#') {
+            if pos != self.mqtt_topic.len() - 1 {
+                return Err(invalid("`#` must be the last level"));
+            }
+        }
+        if matches!(self.max_per_sec, Some(rate) if rate.is_nan() || rate <= 0.0) {
+            return Err(invalid("max_per_sec must be positive"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MqttBridgeConfig {
+    pub broker_host: String,
+    pub broker_port: u16,
+    pub client_id: String,
+    pub mappings: Vec<MqttMapping>,
+}
+
+/// Routing state for one bridge: validated mappings and their rate limiters.
+pub struct MqttBridge {
+    mappings: Vec<MqttMapping>,
+    limiters: Vec<Option<RateLimiter>>,
+}
+
+impl MqttBridge {
+    pub fn new(mappings: Vec<MqttMapping>) -> Result<Self, MqttBridgeError> {
+        for mapping in &mappings {
+            mapping.validate()?;
+        }
+        let limiters = mappings
+            .iter()
+            .map(|m| {
+                m.max_per_sec
+                    .map(|rate| RateLimiter::new(rate, rate.ceil() as u32))
+            })
+            .collect();
+        Ok(Self { mappings, limiters })
+    }
+
+    pub fn mappings(&self) -> &[MqttMapping] {
+        &self.mappings
+    }
+
+    /// Filters to subscribe to on the broker, with their QoS.
+    pub fn subscriptions(&self) -> Vec<(String, Qos)> {
+        self.mappings
+            .iter()
+            .filter(|m| m.direction.inbound())
+            .map(|m| (m.mqtt_topic.clone(), m.qos))
+            .collect()
+    }
+
+    /// Translate an MQTT publish into a mesh message.
+    ///
+    /// Uses the first inbound mapping whose filter matches. Returns `Ok(None)`
+    /// when nothing matches or the mapping's rate limit is exhausted.
+    pub fn inbound(
+        &mut self,
+        topic: &str,
+        payload: &[u8],
+        now: Instant,
+    ) -> Result<Option<Bridged>, MqttBridgeError> {
+        let Some(index) = self
+            .mappings
+            .iter()
+            .position(|m| m.direction.inbound() && topic_matches(&m.mqtt_topic, topic))
+        else {
+            return Ok(None);
+        };
+        let kind = self.mappings[index].kind;
+        let message = decode_payload(kind, "mqtt", topic, payload).map_err(|reason| {
+            MqttBridgeError::Payload {
+                topic: topic.to_string(),
+                kind,
+                reason,
+            }
+        })?;
+        if !self.take_token(index, now) {
+            return Ok(None);
+        }
+        Ok(Some(message))
+    }
+
+    /// MQTT publishes (topic, JSON payload, QoS) for a mesh message, one per
+    /// outbound mapping of the same kind that is within its rate limit.
+    pub fn outbound(&mut self, message: &Bridged, now: Instant) -> Vec<(String, Vec<u8>, Qos)> {
+        let Some(payload) = encode_payload(message) else {
+            return Vec::new();
+        };
+        let mut publishes = Vec::new();
+        for index in 0..self.mappings.len() {
+            let mapping = &self.mappings[index];
+            if !mapping.direction.outbound() || mapping.kind != message.kind() {
+                continue;
+            }
+            let topic = expand_template(&mapping.mqtt_topic, message);
+            let qos = mapping.qos;
+            if self.take_token(index, now) {
+                publishes.push((topic, payload.clone(), qos));
+            }
+        }
+        publishes
+    }
+
+    fn take_token(&mut self, index: usize, now: Instant) -> bool {
+        self.limiters[index]
+            .as_mut()
+            .is_none_or(|limiter| limiter.allow(now))
+    }
+}
+
+/// MQTT topic filter matching with `+` (one level) and `#` (rest).
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut levels = topic.split('/');
+    for part in filter.split('/') {
+        match part {
+            "#" => return true,
+            "+" => {
+                if levels.next().is_none() {
+                    return false;
+                }
+            }
+            literal => {
+                if levels.next() != Some(literal) {
+                    return false;
+                }
+            }
+        }
+    }
+    levels.next().is_none()
+}
+
+#[cfg(feature = "mqtt")]
+fn client_qos(qos: Qos) -> rumqttc::QoS {
+    match qos {
+        Qos::AtMostOnce => rumqttc::QoS::AtMostOnce,
+        Qos::AtLeastOnce => rumqttc::QoS::AtLeastOnce,
+        Qos::ExactlyOnce => rumqttc::QoS::ExactlyOnce,
+    }
+}
+
+/// Connect to the broker and bridge until the connection fails.
+///
+/// Inbound messages are queued on the node through `link`; readings and
+/// tasks the node sees on the mesh go out through outbound mappings.
+#[cfg(feature = "mqtt")]
+pub async fn run(config: MqttBridgeConfig, link: crate::NodeLink) -> Result<(), MqttBridgeError> {
+    use crate::events::NodeEvent;
+    use rumqttc::{AsyncClient, Event, MqttOptions, Packet};
+    use tokio::sync::broadcast::error::RecvError;
+
+    let mut bridge = MqttBridge::new(config.mappings)?;
+    let options = MqttOptions::new(config.client_id, config.broker_host, config.broker_port);
+    let (client, mut eventloop) = AsyncClient::new(options, 64);
+    for (filter, qos) in bridge.subscriptions() {
+        client
+            .subscribe(filter, client_qos(qos))
+            .await
+            .map_err(|e| MqttBridgeError::Client(e.to_string()))?;
+    }
+
+    let mut events = link.subscribe();
+    loop {
+        tokio::select! {
+            polled = eventloop.poll() => {
+                let event = polled.map_err(|e| MqttBridgeError::Client(e.to_string()))?;
+                if let Event::Incoming(Packet::Publish(publish)) = event {
+                    match bridge.inbound(&publish.topic, &publish.payload, Instant::now()) {
+                        Ok(Some(Bridged::Reading(reading))) => link.publish_reading(reading),
+                        Ok(Some(Bridged::Task(task))) => link.publish_task(task),
+                        Ok(None) => {}
+                        Err(e) => tracing::warn!(err = %e, "Dropping MQTT message"),
+                    }
+                }
+            }
+            event = events.recv() => {
+                let message = match event {
+                    Ok(NodeEvent::Reading(reading)) => Bridged::Reading(reading),
+                    Ok(NodeEvent::Task(task)) => Bridged::Task(task),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return Ok(()),
+                };
+                for (topic, payload, qos) in bridge.outbound(&message, Instant::now()) {
+                    client
+                        .publish(topic, client_qos(qos), false, payload)
+                        .await
+                        .map_err(|e| MqttBridgeError::Client(e.to_string()))?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Capability, SensorReading, Task};
+    use std::time::Duration;
+
+    fn mapping(topic: &str, kind: PayloadKind, direction: Direction) -> MqttMapping {
+        MqttMapping {
+            mqtt_topic: topic.to_string(),
+            kind,
+            direction,
+            qos: Qos::AtLeastOnce,
+            max_per_sec: None,
+        }
+    }
+
+    #[test]
+    fn topic_filters_follow_mqtt_wildcards() {
+        assert!(topic_matches("site/+/temp", "site/pump-3/temp"));
+        assert!(!topic_matches("site/+/temp", "site/pump-3/a/temp"));
+        assert!(topic_matches("site/#", "site/pump-3/a/temp"));
+        assert!(!topic_matches("site/pump", "site/pump/temp"));
+    }
+
+    #[test]
+    fn bare_numbers_take_names_from_the_topic() {
+        let mut bridge = MqttBridge::new(vec![mapping(
+            "site/+/temp",
+            PayloadKind::SensorReading,
+            Direction::IntoMesh,
+        )])
+        .unwrap();
+
+        let message = bridge
+            .inbound("site/pump-3/temp", b"21.5", Instant::now())
+            .unwrap();
+
+        let Some(Bridged::Reading(reading)) = message else {
+            panic!("expected a reading, got {message:?}");
+        };
+        assert_eq!(reading.source_id, "mqtt:pump-3");
+        assert_eq!(reading.sensor, "temp");
+        assert_eq!(reading.value, 21.5);
+    }
+
+    #[test]
+    fn malformed_task_is_an_error() {
+        let mut bridge = MqttBridge::new(vec![mapping(
+            "tasks/in",
+            PayloadKind::Task,
+            Direction::IntoMesh,
+        )])
+        .unwrap();
+
+        assert!(bridge
+            .inbound("tasks/in", b"{\"id\":1}", Instant::now())
+            .is_err());
+    }
+
+    #[test]
+    fn outbound_mappings_expand_templates_and_respect_rate_limits() {
+        let mut out = mapping(
+            "hypha/{source_id}/{sensor}",
+            PayloadKind::SensorReading,
+            Direction::FromMesh,
+        );
+        out.max_per_sec = Some(1.0);
+        let mut bridge = MqttBridge::new(vec![
+            out,
+            mapping("tasks/out", PayloadKind::Task, Direction::FromMesh),
+        ])
+        .unwrap();
+        let reading = Bridged::Reading(SensorReading::new("n1".into(), "temp".into(), 3.0));
+        let now = Instant::now();
+
+        let publishes = bridge.outbound(&reading, now);
+        assert_eq!(publishes.len(), 1);
+        assert_eq!(publishes[0].0, "hypha/n1/temp");
+        assert!(bridge.outbound(&reading, now).is_empty());
+        assert_eq!(
+            bridge
+                .outbound(&reading, now + Duration::from_secs(1))
+                .len(),
+            1
+        );
+
+        let task = Bridged::Task(Task::new(
+            "t".into(),
+            Capability::Compute(1),
+            1,
+            "n1".into(),
+        ));
+        assert_eq!(bridge.outbound(&task, now)[0].0, "tasks/out");
+    }
+
+    #[test]
+    fn outbound_wildcards_are_rejected() {
+        assert!(mapping("a/+", PayloadKind::Task, Direction::Both)
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! Zenoh adapter for robotics deployments.
+//!
+//! Lets a fleet that already coordinates over Zenoh (for example ROS 2 via
+//! `rmw_zenoh`) use hypha's energy-aware task auctioning without changing its
+//! middleware. Inbound mappings ingest samples under a key expression as
+//! readings or tasks; outbound mappings republish what the node sees on the
+//! mesh under a key template.
+//!
+//! Key-expression matching and payload translation are always built. The
+//! session loop (`run`) needs the `zenoh` feature.
+
+use super::{decode_payload, encode_payload, expand_template, Bridged, Direction, PayloadKind};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ZenohAdapterError {
+    #[error("invalid key expression `{key_expr}`: {reason}")]
+    InvalidMapping {
+        key_expr: String,
+        reason: &'static str,
+    },
+    #[error("sample on `{key}` is not a valid {kind:?}: {reason}")]
+    Payload {
+        key: String,
+        kind: PayloadKind,
+        reason: String,
+    },
+    #[error("Zenoh session error: {0}")]
+    Session(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ZenohMapping {
+    /// Key expression (`*` and `**` allowed) for inbound mappings; key
+    /// template for outbound ones, with `{source_id}` and `{sensor}`.
+    pub key_expr: String,
+    pub kind: PayloadKind,
+    pub direction: Direction,
+}
+
+impl ZenohMapping {
+    pub fn validate(&self) -> Result<(), ZenohAdapterError> {
+        let invalid = |reason| ZenohAdapterError::InvalidMapping {
+            key_expr: self.key_expr.clone(),
+            reason,
+        };
+        if self.key_expr.is_empty() || self.key_expr.split('/').any(str::is_empty) {
+            return Err(invalid("empty chunk"));
+        }
+        if self.key_expr.starts_with('/') || self.key_expr.ends_with('/') {
+            return Err(invalid("leading or trailing `/`"));
+        }
+        if self.direction.outbound() && self.key_expr.contains('*') {
+            return Err(invalid("outbound keys cannot contain wildcards"));
+        }
+        Ok(())
+    }
+}
+
+/// Routing rules for one Zenoh session.
+#[derive(Debug, Clone)]
+pub struct ZenohAdapter {
+    mappings: Vec<ZenohMapping>,
+}
+
+impl ZenohAdapter {
+    pub fn new(mappings: Vec<ZenohMapping>) -> Result<Self, ZenohAdapterError> {
+        for mapping in &mappings {
+            mapping.validate()?;
+        }
+        Ok(Self { mappings })
+    }
+
+    /// Key expressions to declare subscribers for.
+    pub fn subscriptions(&self) -> Vec<&str> {
+        self.mappings
+            .iter()
+            .filter(|m| m.direction.inbound())
+            .map(|m| m.key_expr.as_str())
+            .collect()
+    }
+
+    /// Translate a sample into a mesh message using the first matching
+    /// inbound mapping.
+    pub fn inbound(&self, key: &str, payload: &[u8]) -> Result<Option<Bridged>, ZenohAdapterError> {
+        let Some(mapping) = self
+            .mappings
+            .iter()
+            .find(|m| m.direction.inbound() && key_expr_matches(&m.key_expr, key))
+        else {
+            return Ok(None);
+        };
+        decode_payload(mapping.kind, "zenoh", key, payload)
+            .map(Some)
+            .map_err(|reason| ZenohAdapterError::Payload {
+                key: key.to_string(),
+                kind: mapping.kind,
+                reason,
+            })
+    }
+
+    /// Puts (key, JSON payload) for a mesh message.
+    pub fn outbound(&self, message: &Bridged) -> Vec<(String, Vec<u8>)> {
+        let Some(payload) = encode_payload(message) else {
+            return Vec::new();
+        };
+        self.mappings
+            .iter()
+            .filter(|m| m.direction.outbound() && m.kind == message.kind())
+            .map(|m| (expand_template(&m.key_expr, message), payload.clone()))
+            .collect()
+    }
+}
+
+/// Zenoh key-expression matching: `*` is exactly one chunk, `**` is zero or
+/// more chunks. Sub-chunk wildcards (`$*`) are not supported.
+pub fn key_expr_matches(expr: &str, key: &str) -> bool {
+    fn go(expr: &[&str], key: &[&str]) -> bool {
+        match expr.split_first() {
+            None => key.is_empty(),
+            Some((&"**", rest)) => (0..=key.len()).any(|skip| go(rest, &key[skip..])),
+            Some((&"*", rest)) => !key.is_empty() && go(rest, &key[1..]),
+            Some((chunk, rest)) => key.first() == Some(chunk) && go(rest, &key[1..]),
+        }
+    }
+    let expr: Vec<&str> = expr.split('/').collect();
+    let key: Vec<&str> = key.split('/').collect();
+    go(&expr, &key)
+}
+
+/// Open a Zenoh session with `config` and bridge until the node stops.
+#[cfg(feature = "zenoh")]
+pub async fn run(
+    adapter: ZenohAdapter,
+    config: ::zenoh::Config,
+    link: crate::NodeLink,
+) -> Result<(), ZenohAdapterError> {
+    use crate::events::NodeEvent;
+    use tokio::sync::broadcast::error::RecvError;
+
+    let session_err = |e: ::zenoh::Error| ZenohAdapterError::Session(e.to_string());
+    let session = ::zenoh::open(config).await.map_err(session_err)?;
+
+    // Each subscriber forwards samples into one channel so a single loop can
+    // also watch node events.
+    let (samples_tx, mut samples) = tokio::sync::mpsc::channel::<(String, Vec<u8>)>(256);
+    let mut subscribers = Vec::new();
+    for key_expr in adapter.subscriptions() {
+        let tx = samples_tx.clone();
+        let subscriber = session
+            .declare_subscriber(key_expr.to_string())
+            .callback(move |sample| {
+                let _ = tx.try_send((
+                    sample.key_expr().as_str().to_string(),
+                    sample.payload().to_bytes().into_owned(),
+                ));
+            })
+            .await
+            .map_err(session_err)?;
+        subscribers.push(subscriber);
+    }
+    drop(samples_tx);
+
+    let mut events = link.subscribe();
+    loop {
+        tokio::select! {
+            Some((key, payload)) = samples.recv() => {
+                match adapter.inbound(&key, &payload) {
+                    Ok(Some(Bridged::Reading(reading))) => link.publish_reading(reading),
+                    Ok(Some(Bridged::Task(task))) => link.publish_task(task),
+                    Ok(None) => {}
+                    Err(e) => tracing::warn!(err = %e, "Dropping Zenoh sample"),
+                }
+            }
+            event = events.recv() => {
+                let message = match event {
+                    Ok(NodeEvent::Reading(reading)) => Bridged::Reading(reading),
+                    Ok(NodeEvent::Task(task)) => Bridged::Task(task),
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return Ok(()),
+                };
+                for (key, payload) in adapter.outbound(&message) {
+                    session.put(key, payload).await.map_err(session_err)?;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::SensorReading;
+
+    #[test]
+    fn key_expressions_follow_zenoh_wildcards() {
+        assert!(key_expr_matches("robot/*/battery", "robot/r1/battery"));
+        assert!(!key_expr_matches("robot/*/battery", "robot/r1/arm/battery"));
+        assert!(key_expr_matches("robot/**/battery", "robot/r1/arm/battery"));
+        assert!(key_expr_matches("robot/**", "robot"));
+        assert!(!key_expr_matches("robot/r1", "robot/r2"));
+    }
+
+    #[test]
+    fn samples_become_readings_and_readings_become_puts() {
+        let adapter = ZenohAdapter::new(vec![
+            ZenohMapping {
+                key_expr: "rt/*/battery".to_string(),
+                kind: PayloadKind::SensorReading,
+                direction: Direction::IntoMesh,
+            },
+            ZenohMapping {
+                key_expr: "hypha/{source_id}/{sensor}".to_string(),
+                kind: PayloadKind::SensorReading,
+                direction: Direction::FromMesh,
+            },
+        ])
+        .unwrap();
+
+        let Some(Bridged::Reading(reading)) = adapter
+            .inbound("rt/r1/battery", br#"{"value": 0.7, "unit": "ratio"}"#)
+            .unwrap()
+        else {
+            panic!("expected a reading");
+        };
+        assert_eq!(reading.source_id, "zenoh:r1");
+        assert_eq!(reading.unit.as_deref(), Some("ratio"));
+
+        let puts = adapter.outbound(&Bridged::Reading(SensorReading::new(
+            "n1".into(),
+            "temp".into(),
+            1.0,
+        )));
+        assert_eq!(puts.len(), 1);
+        assert_eq!(puts[0].0, "hypha/n1/temp");
+    }
+
+    #[test]
+    fn outbound_wildcards_are_rejected() {
+        let mapping = ZenohMapping {
+            key_expr: "hypha/**".to_string(),
+            kind: PayloadKind::Task,
+            direction: Direction::Both,
+        };
+        assert!(mapping.validate().is_err());
+    }
+}
@@ -0,0 +1,227 @@
+//! Per-capability admission limits.
+//!
+//! Advertising `Capability::Compute(100)` says how big a task a node can
+//! take, not how many at once. [`CapacityLimits`] caps, per kind of
+//! capability, the tasks a node holds in flight and the units (compute or
+//! storage amounts) they declare in total. `SporeNode` checks the limits
+//! before bidding and again when an award arrives, since auctions run in
+//! parallel and several may be won at once; an award over the limit is
+//! declined on the lease topic so the auctioneer can re-assign it.
+//!
+//! Tasks declaring `expected_output_bytes` are also checked against the
+//! storage quota, if the node's store has one: their output must fit in the
+//! headroom left after the output expected of the tasks already in flight.
+//! Writes between the bid and the award can use that headroom up, so the
+//! award is checked again.
+//!
+//! No kind is limited by default.
+
+use crate::core::Capability;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapabilityKind {
+    Compute,
+    Storage,
+    Sensing,
+    Join,
+    Alert,
+    Coordinator,
+}
+
+impl CapabilityKind {
+    pub fn of(capability: &Capability) -> Self {
+        match capability {
+            Capability::Compute(_) => Self::Compute,
+            Capability::Storage(_) => Self::Storage,
+            Capability::Sensing(_) => Self::Sensing,
+            Capability::Join(_) => Self::Join,
+            Capability::Alert(_) => Self::Alert,
+            Capability::Coordinator => Self::Coordinator,
+        }
+    }
+}
+
+impl fmt::Display for CapabilityKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Compute => "compute",
+            Self::Storage => "storage",
+            Self::Sensing => "sensing",
+            Self::Join => "join",
+            Self::Alert => "alert",
+            Self::Coordinator => "coordinator",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Units a task requiring `capability` declares: the amount for compute and
+/// storage, one otherwise.
+pub fn units(capability: &Capability) -> u64 {
+    match capability {
+        Capability::Compute(units) => u64::from(*units),
+        Capability::Storage(bytes) => *bytes,
+        _ => 1,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapacityLimit {
+    /// Most tasks of this kind in flight at once.
+    #[serde(default)]
+    pub max_tasks: Option<usize>,
+    /// Most units, summed over the tasks of this kind in flight.
+    #[serde(default)]
+    pub max_units: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CapacityError {
+    #[error("{kind} already has {limit} tasks in flight")]
+    Tasks { kind: CapabilityKind, limit: usize },
+    #[error("{requested} more {kind} units would exceed {limit} ({in_flight} in flight)")]
+    Units {
+        kind: CapabilityKind,
+        in_flight: u64,
+        requested: u64,
+        limit: u64,
+    },
+    #[error("{requested} output bytes exceed the {headroom} left under the storage quota")]
+    Storage { requested: u64, headroom: u64 },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CapacityLimits {
+    limits: HashMap<CapabilityKind, CapacityLimit>,
+}
+
+impl CapacityLimits {
+    pub fn set(&mut self, kind: CapabilityKind, limit: CapacityLimit) {
+        self.limits.insert(kind, limit);
+    }
+
+    pub fn get(&self, kind: CapabilityKind) -> Option<&CapacityLimit> {
+        self.limits.get(&kind)
+    }
+
+    /// Whether one more task requiring `required` fits alongside the
+    /// requirements of the tasks already `in_flight`.
+    pub fn admit<'a>(
+        &self,
+        required: &Capability,
+        in_flight: impl IntoIterator<Item = &'a Capability>,
+    ) -> Result<(), CapacityError> {
+        let kind = CapabilityKind::of(required);
+        let Some(limit) = self.limits.get(&kind) else {
+            return Ok(());
+        };
+        let (tasks, in_flight) = in_flight
+            .into_iter()
+            .filter(|capability| CapabilityKind::of(capability) == kind)
+            .fold((0usize, 0u64), |(tasks, total), capability| {
+                (tasks + 1, total.saturating_add(units(capability)))
+            });
+        if let Some(max) = limit.max_tasks {
+            if tasks >= max {
+                return Err(CapacityError::Tasks { kind, limit: max });
+            }
+        }
+        let requested = units(required);
+        if let Some(max) = limit.max_units {
+            if in_flight.saturating_add(requested) > max {
+                return Err(CapacityError::Units {
+                    kind,
+                    in_flight,
+                    requested,
+                    limit: max,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `requested` bytes of output fit in `headroom` (`None` without a
+/// quota) once the output `reserved` by tasks in flight is set aside.
+pub fn admit_output(
+    requested: Option<u64>,
+    headroom: Option<u64>,
+    reserved: impl IntoIterator<Item = u64>,
+) -> Result<(), CapacityError> {
+    let (Some(requested), Some(headroom)) = (requested, headroom) else {
+        return Ok(());
+    };
+    let reserved = reserved.into_iter().fold(0u64, u64::saturating_add);
+    let headroom = headroom.saturating_sub(reserved);
+    if requested > headroom {
+        return Err(CapacityError::Storage {
+            requested,
+            headroom,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_count_only_tasks_of_the_same_kind() {
+        let mut limits = CapacityLimits::default();
+        limits.set(
+            CapabilityKind::Compute,
+            CapacityLimit {
+                max_tasks: Some(3),
+                max_units: Some(100),
+            },
+        );
+        let running = [
+            Capability::Compute(40),
+            Capability::Compute(40),
+            Capability::Storage(1 << 30),
+        ];
+
+        assert_eq!(limits.admit(&Capability::Compute(20), &running), Ok(()));
+        assert_eq!(
+            limits.admit(&Capability::Compute(21), &running),
+            Err(CapacityError::Units {
+                kind: CapabilityKind::Compute,
+                in_flight: 80,
+                requested: 21,
+                limit: 100,
+            })
+        );
+        let full = [0, 1, 2].map(|_| Capability::Compute(1));
+        assert_eq!(
+            limits.admit(&Capability::Compute(1), &full),
+            Err(CapacityError::Tasks {
+                kind: CapabilityKind::Compute,
+                limit: 3,
+            })
+        );
+        // Storage has no limit set.
+        assert_eq!(
+            limits.admit(&Capability::Storage(u64::MAX), &running),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn expected_output_must_fit_beside_what_is_in_flight() {
+        assert_eq!(admit_output(Some(u64::MAX), None, [1 << 40]), Ok(()));
+        assert_eq!(admit_output(None, Some(0), []), Ok(()));
+        assert_eq!(admit_output(Some(400), Some(1000), [300, 300]), Ok(()));
+        assert_eq!(
+            admit_output(Some(401), Some(1000), [300, 300]),
+            Err(CapacityError::Storage {
+                requested: 401,
+                headroom: 400,
+            })
+        );
+    }
+}
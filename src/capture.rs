@@ -0,0 +1,229 @@
+//! Capture and replay of inbound gossip.
+//!
+//! With `SporeNode::capture` set, every gossip message the run loop receives
+//! is written to a capture file before any check runs on it: topic, payload,
+//! receipt time, the peer that relayed it and the peer that signed it. The
+//! file is JSON lines, one [`CapturedMessage`] each, flushed per message so
+//! a crash keeps everything up to the crash.
+//!
+//! `SporeNode::replay` feeds a capture back into a node, normally a fresh
+//! one with no peers. Replayed messages go through the same path as live
+//! ones (admission, ACLs, traces, per-topic handlers) in capture order and
+//! ahead of any live traffic, so the node sees the exact sequence that
+//! triggered an incident. Timers keep wall-clock time: heartbeats and expiry
+//! during a replay follow the replay, not the recorded `at_ms`.
+
+use crate::audit::{from_hex, to_hex};
+use libp2p::gossipsub::{self, MessageId, TopicHash};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError {
+    #[error("capture file error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("capture line {line} is unreadable: {source}")]
+    Decode {
+        line: usize,
+        source: serde_json::Error,
+    },
+    #[error("captured message has a malformed {0}")]
+    Malformed(&'static str),
+}
+
+/// One inbound gossip message as received.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapturedMessage {
+    /// Unix time of receipt, in milliseconds.
+    pub at_ms: u64,
+    /// Topic hash as gossipsub reports it.
+    pub topic: String,
+    /// Peer that relayed the message to this node.
+    pub source: String,
+    /// Peer that published it, for signed messages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// Gossipsub message id, in hex.
+    pub message_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence_number: Option<u64>,
+    /// Payload as received, in hex.
+    pub data: String,
+}
+
+impl CapturedMessage {
+    pub fn new(at_ms: u64, source: &PeerId, id: &MessageId, message: &gossipsub::Message) -> Self {
+        Self {
+            at_ms,
+            topic: message.topic.to_string(),
+            source: source.to_string(),
+            author: message.source.map(|peer| peer.to_string()),
+            message_id: to_hex(&id.0),
+            sequence_number: message.sequence_number,
+            data: to_hex(&message.data),
+        }
+    }
+
+    pub fn payload(&self) -> Result<Vec<u8>, CaptureError> {
+        from_hex(&self.data).ok_or(CaptureError::Malformed("payload"))
+    }
+
+    /// The gossipsub event this message arrived as.
+    pub fn to_event(&self) -> Result<gossipsub::Event, CaptureError> {
+        let propagation_source = self
+            .source
+            .parse::<PeerId>()
+            .map_err(|_| CaptureError::Malformed("source"))?;
+        let author = match &self.author {
+            Some(author) => Some(
+                author
+                    .parse::<PeerId>()
+                    .map_err(|_| CaptureError::Malformed("author"))?,
+            ),
+            None => None,
+        };
+        let message_id = from_hex(&self.message_id).ok_or(CaptureError::Malformed("message id"))?;
+        Ok(gossipsub::Event::Message {
+            propagation_source,
+            message_id: MessageId(message_id),
+            message: gossipsub::Message {
+                source: author,
+                data: self.payload()?,
+                sequence_number: self.sequence_number,
+                topic: TopicHash::from_raw(self.topic.clone()),
+            },
+        })
+    }
+}
+
+/// Appends captured messages to a file.
+pub struct CaptureWriter {
+    out: BufWriter<File>,
+    written: u64,
+}
+
+impl CaptureWriter {
+    /// Opens `path` for appending, creating it if needed, so that restarts
+    /// extend one capture.
+    pub fn open(path: &Path) -> Result<Self, CaptureError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            out: BufWriter::new(file),
+            written: 0,
+        })
+    }
+
+    pub fn record(&mut self, message: &CapturedMessage) -> Result<(), CaptureError> {
+        let line = serde_json::to_string(message).map_err(std::io::Error::from)?;
+        writeln!(self.out, "{line}")?;
+        self.out.flush()?;
+        self.written += 1;
+        Ok(())
+    }
+
+    /// Messages recorded through this writer.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+}
+
+/// Every message in the capture at `path`, in capture order.
+pub fn read(path: &Path) -> Result<Vec<CapturedMessage>, CaptureError> {
+    parse(BufReader::new(File::open(path)?))
+}
+
+/// Messages from capture lines, skipping blank ones. A torn last line, as a
+/// crash mid-write leaves, is dropped; damage anywhere else is an error.
+pub fn parse(reader: impl BufRead) -> Result<Vec<CapturedMessage>, CaptureError> {
+    let lines = reader.lines().collect::<Result<Vec<_>, _>>()?;
+    let last = lines.len();
+    let mut messages = Vec::with_capacity(lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(message) => messages.push(message),
+            Err(e) if e.is_eof() && index + 1 == last => break,
+            Err(source) => {
+                return Err(CaptureError::Decode {
+                    line: index + 1,
+                    source,
+                })
+            }
+        }
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(data: &[u8]) -> (PeerId, MessageId, gossipsub::Message) {
+        let message = gossipsub::Message {
+            source: Some(PeerId::random()),
+            data: data.to_vec(),
+            sequence_number: Some(7),
+            topic: TopicHash::from_raw("hypha-status"),
+        };
+        (PeerId::random(), MessageId::new(b"id-1"), message)
+    }
+
+    #[test]
+    fn captured_messages_round_trip_to_the_same_event() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("capture.jsonl");
+        let (source, id, original) = message(b"{\"v\":1}\x00\xff");
+        let mut writer = CaptureWriter::open(&path).unwrap();
+        writer
+            .record(&CapturedMessage::new(10, &source, &id, &original))
+            .unwrap();
+        let mut unsigned = original.clone();
+        unsigned.source = None;
+        writer
+            .record(&CapturedMessage::new(11, &source, &id, &unsigned))
+            .unwrap();
+        assert_eq!(writer.written(), 2);
+        drop(writer);
+
+        let captured = read(&path).unwrap();
+        assert_eq!(captured.len(), 2);
+        assert_eq!(captured[1].author, None);
+        let gossipsub::Event::Message {
+            propagation_source,
+            message_id,
+            message,
+        } = captured[0].to_event().unwrap()
+        else {
+            panic!("expected a message event");
+        };
+        assert_eq!((propagation_source, message_id), (source, id));
+        assert_eq!(message, original);
+    }
+
+    #[test]
+    fn a_torn_last_line_is_dropped_but_damage_elsewhere_is_not() {
+        let (source, id, original) = message(b"payload");
+        let line =
+            serde_json::to_string(&CapturedMessage::new(1, &source, &id, &original)).unwrap();
+        let torn = format!("{line}\n\n{}", &line[..line.len() / 2]);
+        assert_eq!(parse(torn.as_bytes()).unwrap().len(), 1);
+
+        let damaged = format!("{}\n{line}\n", &line[..line.len() / 2]);
+        assert!(matches!(
+            parse(damaged.as_bytes()),
+            Err(CaptureError::Decode { line: 1, .. })
+        ));
+
+        let mut odd = CapturedMessage::new(1, &source, &id, &original);
+        odd.data.push('a');
+        assert!(matches!(
+            odd.to_event(),
+            Err(CaptureError::Malformed("payload"))
+        ));
+    }
+}
@@ -0,0 +1,301 @@
+//! Versioned capability and sensor catalogs.
+//!
+//! A node's [`Catalog`] lists the capabilities it offers and the sensors it
+//! reads. Rather than resend the whole list, the node bumps the catalog's
+//! version whenever the list changes and publishes a [`CatalogDelta`] on the
+//! status topic: the entries added and removed since the previous version.
+//! Its status adverts carry the current version.
+//!
+//! [`PeerCatalogs`] applies the deltas it hears. A delta that does not start
+//! at the version it holds for the owner, or an advert naming another
+//! version, means it missed an update; it then asks the owner for a
+//! [`CatalogSnapshot`] over the [`CATALOG_PROTOCOL`] request-response
+//! protocol, at most once per `retry` interval. A restarted node counts from
+//! version 0 again, and its first delta lists its whole catalog, so a delta
+//! from version 0 always replaces what was held.
+//!
+//! Nodes that know where they are list their geohash too, so the catalogs
+//! double as a directory of who is where: see [`PeerCatalogs::near`].
+
+use crate::core::Capability;
+use crate::geohash;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+pub const CATALOG_PROTOCOL: &str = "/hypha/catalog/1.0.0";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatalogEntry {
+    Capability(Capability),
+    /// A sensor, by name.
+    Sensor(String),
+    /// Where the node is, as a geohash.
+    Location(String),
+}
+
+/// What changed in `owner`'s catalog between versions `from` and `to`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogDelta {
+    pub owner: String,
+    pub from: u64,
+    pub to: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added: Vec<CatalogEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed: Vec<CatalogEntry>,
+}
+
+/// A whole catalog, as served to peers that fell behind.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CatalogSnapshot {
+    pub version: u64,
+    pub entries: Vec<CatalogEntry>,
+}
+
+impl CatalogSnapshot {
+    /// The owner's geohash, if it listed one.
+    pub fn location(&self) -> Option<&str> {
+        self.entries.iter().find_map(|entry| match entry {
+            CatalogEntry::Location(hash) => Some(hash.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// Asks the receiving node for a snapshot of its catalog.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CatalogRequest {}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CatalogError {
+    #[error("catalog gap: holding version {held}, delta starts at {from}")]
+    Gap { held: u64, from: u64 },
+}
+
+/// The local node's catalog. Version 0 is the empty catalog.
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    version: u64,
+    entries: Vec<CatalogEntry>,
+}
+
+impl Catalog {
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn entries(&self) -> &[CatalogEntry] {
+        &self.entries
+    }
+
+    /// Replace the catalog with `entries`. Returns the delta to publish, or
+    /// `None` if nothing changed.
+    pub fn set(&mut self, owner: &str, entries: Vec<CatalogEntry>) -> Option<CatalogDelta> {
+        let added: Vec<_> = entries
+            .iter()
+            .filter(|entry| !self.entries.contains(entry))
+            .cloned()
+            .collect();
+        let removed: Vec<_> = self
+            .entries
+            .iter()
+            .filter(|entry| !entries.contains(entry))
+            .cloned()
+            .collect();
+        if added.is_empty() && removed.is_empty() {
+            return None;
+        }
+        let from = self.version;
+        self.version += 1;
+        self.entries = entries;
+        Some(CatalogDelta {
+            owner: owner.to_string(),
+            from,
+            to: self.version,
+            added,
+            removed,
+        })
+    }
+
+    pub fn snapshot(&self) -> CatalogSnapshot {
+        CatalogSnapshot {
+            version: self.version,
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+/// Catalogs heard from peers.
+#[derive(Debug)]
+pub struct PeerCatalogs {
+    /// How long to wait for a snapshot before asking the same peer again.
+    pub retry: Duration,
+    catalogs: HashMap<String, CatalogSnapshot>,
+    requested: HashMap<String, Instant>,
+}
+
+impl Default for PeerCatalogs {
+    fn default() -> Self {
+        Self {
+            retry: Duration::from_secs(30),
+            catalogs: HashMap::new(),
+            requested: HashMap::new(),
+        }
+    }
+}
+
+impl PeerCatalogs {
+    pub fn get(&self, peer: &str) -> Option<&CatalogSnapshot> {
+        self.catalogs.get(peer)
+    }
+
+    /// The version held for `peer`; 0 if none.
+    pub fn version(&self, peer: &str) -> u64 {
+        self.catalogs.get(peer).map_or(0, |c| c.version)
+    }
+
+    /// Apply `delta` to its owner's catalog. Other deltas ending at or below
+    /// the held version are ignored.
+    pub fn apply(&mut self, delta: &CatalogDelta) -> Result<(), CatalogError> {
+        let catalog = self.catalogs.entry(delta.owner.clone()).or_default();
+        if delta.from == 0 {
+            *catalog = CatalogSnapshot {
+                version: delta.to,
+                entries: delta.added.clone(),
+            };
+            return Ok(());
+        }
+        if delta.to <= catalog.version {
+            return Ok(());
+        }
+        if delta.from != catalog.version {
+            return Err(CatalogError::Gap {
+                held: catalog.version,
+                from: delta.from,
+            });
+        }
+        catalog
+            .entries
+            .retain(|entry| !delta.removed.contains(entry));
+        catalog.entries.extend(delta.added.iter().cloned());
+        catalog.version = delta.to;
+        Ok(())
+    }
+
+    /// Replace `peer`'s catalog with `snapshot`, as served by the peer.
+    pub fn apply_snapshot(&mut self, peer: &str, snapshot: CatalogSnapshot) {
+        self.requested.remove(peer);
+        self.catalogs.insert(peer.to_string(), snapshot);
+    }
+
+    /// Whether to ask `peer` for a snapshot, given the version it advertises.
+    /// Records the request when it says yes.
+    pub fn needs_snapshot(&mut self, peer: &str, advertised: u64, now: Instant) -> bool {
+        if advertised == self.version(peer) {
+            return false;
+        }
+        if self
+            .requested
+            .get(peer)
+            .is_some_and(|at| now.duration_since(*at) < self.retry)
+        {
+            return false;
+        }
+        self.requested.insert(peer.to_string(), now);
+        true
+    }
+
+    /// Peers whose listed location lies in or around the geohash `area`,
+    /// sorted. A peer listing a coarser cell than `area` counts when its
+    /// cell contains the area.
+    pub fn near(&self, area: &str) -> Vec<&str> {
+        let mut peers: Vec<_> = self
+            .catalogs
+            .iter()
+            .filter(|(_, catalog)| {
+                catalog
+                    .location()
+                    .is_some_and(|here| geohash::overlaps(area, here))
+            })
+            .map(|(peer, _)| peer.as_str())
+            .collect();
+        peers.sort_unstable();
+        peers
+    }
+
+    pub fn forget(&mut self, peer: &str) {
+        self.catalogs.remove(peer);
+        self.requested.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deltas_apply_in_order_and_gaps_call_for_a_snapshot() {
+        let mut local = Catalog::default();
+        let mut peers = PeerCatalogs::default();
+        let sensor = CatalogEntry::Sensor("temp".to_string());
+        let compute = CatalogEntry::Capability(Capability::Compute(4));
+
+        let first = local
+            .set("a", vec![sensor.clone(), compute.clone()])
+            .unwrap();
+        assert_eq!((first.from, first.to), (0, 1));
+        assert!(local
+            .set("a", vec![compute.clone(), sensor.clone()])
+            .is_none());
+        peers.apply(&first).unwrap();
+
+        let second = local.set("a", vec![compute.clone()]).unwrap();
+        assert_eq!(second.removed, vec![sensor.clone()]);
+        let third = local.set("a", vec![compute.clone(), sensor]).unwrap();
+
+        // The second delta was missed.
+        let now = Instant::now();
+        assert_eq!(
+            peers.apply(&third),
+            Err(CatalogError::Gap { held: 1, from: 2 })
+        );
+        assert!(peers.needs_snapshot("a", local.version(), now));
+        assert!(!peers.needs_snapshot("a", local.version(), now));
+
+        peers.apply_snapshot("a", local.snapshot());
+        assert_eq!(peers.get("a"), Some(&local.snapshot()));
+        // A late delta changes nothing.
+        peers.apply(&second).unwrap();
+        assert_eq!(peers.version("a"), 3);
+        assert!(!peers.needs_snapshot("a", 3, now));
+
+        // After a restart the owner starts over from version 0.
+        let mut restarted = Catalog::default();
+        peers
+            .apply(&restarted.set("a", vec![compute.clone()]).unwrap())
+            .unwrap();
+        assert_eq!(peers.get("a").unwrap().entries, vec![compute]);
+        assert_eq!(peers.version("a"), 1);
+    }
+
+    #[test]
+    fn listed_locations_answer_who_is_near() {
+        let mut peers = PeerCatalogs::default();
+        for (owner, hash) in [("a", "u4pruy"), ("b", "u4prv0"), ("c", "ezs42j")] {
+            let mut catalog = Catalog::default();
+            let entries = vec![CatalogEntry::Location(hash.to_string())];
+            peers.apply(&catalog.set(owner, entries).unwrap()).unwrap();
+        }
+        let mut unlocated = Catalog::default();
+        let entries = vec![CatalogEntry::Sensor("temp".to_string())];
+        peers.apply(&unlocated.set("d", entries).unwrap()).unwrap();
+
+        assert_eq!(peers.get("a").unwrap().location(), Some("u4pruy"));
+        assert_eq!(peers.near("u4pr"), vec!["a", "b"]);
+        assert_eq!(peers.near("u4pruydqq"), vec!["a"]);
+        assert_eq!(peers.near("ezs"), vec!["c"]);
+        assert!(peers.near("zzz").is_empty());
+    }
+}
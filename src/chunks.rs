@@ -0,0 +1,90 @@
+//! Content-addressed chunk store.
+//!
+//! Payloads too large for one message travel the mesh as chunks named by
+//! their SHA-256, so any node holding a chunk can serve it and the receiver
+//! checks it without trusting the sender. [`ChunkStore`] keeps chunks in a
+//! fjall keyspace of their own, keyed by the hex hash. Firmware images are
+//! the first payloads split this way; see `crate::ota`.
+
+use crate::audit::to_hex;
+use fjall::{Database, Keyspace, KeyspaceCreateOptions};
+use sha2::{Digest, Sha256};
+
+/// Name of the chunk holding `data`.
+pub fn chunk_hash(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChunkError {
+    #[error("chunk storage error: {0}")]
+    Storage(#[from] fjall::Error),
+    #[error("chunk does not hash to {0}")]
+    HashMismatch(String),
+}
+
+pub struct ChunkStore {
+    keyspace: Keyspace,
+}
+
+impl ChunkStore {
+    pub fn open(storage: &Database) -> Result<Self, ChunkError> {
+        let keyspace = storage.keyspace("hypha_chunks", KeyspaceCreateOptions::default)?;
+        Ok(Self { keyspace })
+    }
+
+    /// Store `data` under its own hash, which is returned.
+    pub fn put(&self, data: &[u8]) -> Result<String, ChunkError> {
+        let hash = chunk_hash(data);
+        self.keyspace.insert(&hash, data)?;
+        Ok(hash)
+    }
+
+    /// Store `data`, received as the chunk named `hash`, if that is what it
+    /// hashes to.
+    pub fn insert(&self, hash: &str, data: &[u8]) -> Result<(), ChunkError> {
+        if chunk_hash(data) != hash {
+            return Err(ChunkError::HashMismatch(hash.to_string()));
+        }
+        self.keyspace.insert(hash, data)?;
+        Ok(())
+    }
+
+    pub fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, ChunkError> {
+        Ok(self.keyspace.get(hash)?.map(|value| value.to_vec()))
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.keyspace.get(hash).ok().flatten().is_some()
+    }
+
+    pub fn remove(&self, hash: &str) -> Result<(), ChunkError> {
+        self.keyspace.remove(hash)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_are_stored_under_their_hash_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Database::builder(dir.path()).open().unwrap();
+        let store = ChunkStore::open(&storage).unwrap();
+
+        let hash = store.put(b"firmware").unwrap();
+        assert_eq!(hash, chunk_hash(b"firmware"));
+        assert_eq!(store.get(&hash).unwrap().as_deref(), Some(&b"firmware"[..]));
+
+        let other = chunk_hash(b"other");
+        assert!(matches!(
+            store.insert(&other, b"forged"),
+            Err(ChunkError::HashMismatch(_))
+        ));
+        assert!(!store.contains(&other));
+        store.insert(&other, b"other").unwrap();
+        assert!(store.contains(&other));
+    }
+}
@@ -0,0 +1,171 @@
+//! Several logical spores in one process.
+//!
+//! Edge gateways often front more than one sensor cluster. `SporeCluster`
+//! keeps one `SporeNode` per cluster, each with its own identity and store,
+//! and drives all of them on the caller's tokio runtime. Each member runs its
+//! own swarm; members with a namespace use namespaced gossip topics so they
+//! only mesh with peers in the same namespace.
+
+use crate::mesh::MeshStats;
+use crate::SporeNode;
+use libp2p::futures::future::join_all;
+use libp2p::Multiaddr;
+use serde::Serialize;
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+
+pub struct ClusterMember {
+    pub node: SporeNode,
+    /// Topic namespace for this member. `None` joins the default topics.
+    pub namespace: Option<String>,
+}
+
+#[derive(Default)]
+pub struct SporeCluster {
+    pub members: Vec<ClusterMember>,
+    /// Bootstrap addresses handed to every member's swarm.
+    pub bootstrap: Vec<Multiaddr>,
+}
+
+/// One member's slice of the combined metrics view.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterNodeMetrics {
+    pub peer_id: String,
+    pub namespace: Option<String>,
+    pub energy_score: f32,
+    pub messages_delivered: u64,
+    pub mesh: MeshStats,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterMetrics {
+    pub nodes: Vec<ClusterNodeMetrics>,
+    pub total_delivered: u64,
+    pub mean_energy_score: f32,
+}
+
+impl SporeCluster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open one node per namespace under `root/<namespace>`.
+    pub fn open(root: &Path, namespaces: &[&str]) -> Result<Self, Box<dyn Error>> {
+        let mut cluster = Self::new();
+        for namespace in namespaces {
+            let path = root.join(namespace);
+            std::fs::create_dir_all(&path)?;
+            cluster.add_node(SporeNode::new(&path)?, Some(namespace.to_string()));
+        }
+        Ok(cluster)
+    }
+
+    pub fn add_node(&mut self, node: SporeNode, namespace: Option<String>) {
+        self.members.push(ClusterMember { node, namespace });
+    }
+
+    pub fn add_bootstrap(&mut self, addr: Multiaddr) {
+        if !self.bootstrap.contains(&addr) {
+            self.bootstrap.push(addr);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Run every member's networking loop concurrently for `run_for`.
+    ///
+    /// All members listen on `listen` (use port 0 for ephemeral ports) and
+    /// share the current task, so no extra runtime or threads are created.
+    pub async fn run_for(
+        &mut self,
+        listen: Multiaddr,
+        run_for: Duration,
+        heartbeat_every: Duration,
+        pulse_delta: f32,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut myceliums = Vec::with_capacity(self.members.len());
+        for member in &self.members {
+            let mut mycelium = member.node.build_mycelium()?;
+            if let Some(namespace) = &member.namespace {
+                mycelium.set_namespace(namespace);
+            }
+            for addr in &self.bootstrap {
+                mycelium.add_bootstrap(addr.clone());
+            }
+            mycelium.listen_on(listen.clone())?;
+            myceliums.push(mycelium);
+        }
+
+        let runs = self
+            .members
+            .iter_mut()
+            .zip(myceliums)
+            .map(|(member, mycelium)| {
+                member
+                    .node
+                    .run_for(mycelium, run_for, heartbeat_every, pulse_delta, false, None)
+            });
+        for result in join_all(runs).await {
+            result?;
+        }
+        Ok(())
+    }
+
+    /// Combined metrics across members.
+    pub fn metrics(&self) -> ClusterMetrics {
+        let nodes: Vec<ClusterNodeMetrics> = self
+            .members
+            .iter()
+            .map(|member| ClusterNodeMetrics {
+                peer_id: member.node.peer_id.to_string(),
+                namespace: member.namespace.clone(),
+                energy_score: member.node.energy_score(),
+                messages_delivered: member.node.metrics.lock().unwrap().messages_delivered(),
+                mesh: member.node.mesh.lock().unwrap().stats(),
+            })
+            .collect();
+
+        let total_delivered = nodes.iter().map(|n| n.messages_delivered).sum();
+        let mean_energy_score = if nodes.is_empty() {
+            0.0
+        } else {
+            nodes.iter().map(|n| n.energy_score).sum::<f32>() / nodes.len() as f32
+        };
+
+        ClusterMetrics {
+            nodes,
+            total_delivered,
+            mean_energy_score,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn open_creates_one_identity_per_namespace() {
+        let tmp = tempdir().unwrap();
+        let cluster = SporeCluster::open(tmp.path(), &["north", "south"]).unwrap();
+
+        assert_eq!(cluster.len(), 2);
+        assert_ne!(
+            cluster.members[0].node.peer_id,
+            cluster.members[1].node.peer_id
+        );
+
+        let metrics = cluster.metrics();
+        assert_eq!(metrics.nodes.len(), 2);
+        assert_eq!(metrics.nodes[0].namespace.as_deref(), Some("north"));
+        assert_eq!(metrics.total_delivered, 0);
+    }
+}
@@ -0,0 +1,155 @@
+//! Fuel-to-energy calibration from execution telemetry.
+//!
+//! Runtimes charge a fixed [`DEFAULT_MAH_PER_FUEL`] for the fuel a task
+//! burns, but what an instruction really costs depends on the hardware. Each
+//! [`ExecutionReport`] is kept per device class; once enough of them carry a
+//! measured mAh delta, an ordinary least-squares fit of mAh on fuel replaces
+//! the default rate for that class. Bids price work with the fitted model.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// Conversion used before a device class has been calibrated; matches the
+/// energy units the runtimes charge (1.0 per 100,000 fuel).
+pub const DEFAULT_MAH_PER_FUEL: f64 = 1.0 / 100_000.0;
+
+/// Reports kept per device class.
+const HISTORY: usize = 256;
+
+/// Measured reports needed before the fit is trusted.
+const MIN_MEASURED: usize = 8;
+
+/// What one execution cost.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExecutionReport {
+    pub wall_time: Duration,
+    pub fuel_used: u64,
+    /// Drop in the fuel gauge across the execution, when the hardware has one.
+    pub mah_delta: Option<f32>,
+}
+
+/// `mah = intercept + slope * fuel`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FuelModel {
+    pub slope: f64,
+    pub intercept: f64,
+}
+
+impl Default for FuelModel {
+    fn default() -> Self {
+        Self {
+            slope: DEFAULT_MAH_PER_FUEL,
+            intercept: 0.0,
+        }
+    }
+}
+
+impl FuelModel {
+    pub fn estimate_mah(&self, fuel: u64) -> f32 {
+        (self.intercept + self.slope * fuel as f64).max(0.0) as f32
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Calibration {
+    history: HashMap<String, VecDeque<ExecutionReport>>,
+}
+
+impl Calibration {
+    pub fn record(&mut self, class: &str, report: ExecutionReport) {
+        let history = self.history.entry(class.to_string()).or_default();
+        if history.len() == HISTORY {
+            history.pop_front();
+        }
+        history.push_back(report);
+    }
+
+    pub fn history(&self, class: &str) -> impl Iterator<Item = &ExecutionReport> {
+        self.history.get(class).into_iter().flatten()
+    }
+
+    /// The fitted model for `class`, or the default until enough measured
+    /// reports with distinct fuel counts exist.
+    pub fn model(&self, class: &str) -> FuelModel {
+        let points: Vec<(f64, f64)> = self
+            .history(class)
+            .filter_map(|r| r.mah_delta.map(|mah| (r.fuel_used as f64, f64::from(mah))))
+            .collect();
+        if points.len() < MIN_MEASURED {
+            return FuelModel::default();
+        }
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let sxx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+        let sxy: f64 = points
+            .iter()
+            .map(|(x, y)| (x - mean_x) * (y - mean_y))
+            .sum();
+        if sxx <= f64::EPSILON {
+            return FuelModel::default();
+        }
+        let slope = sxy / sxx;
+        FuelModel {
+            slope,
+            intercept: mean_y - slope * mean_x,
+        }
+    }
+
+    pub fn estimate_mah(&self, class: &str, fuel: u64) -> f32 {
+        self.model(class).estimate_mah(fuel)
+    }
+
+    /// Estimated cost of a typical execution on `class`: the model at the
+    /// mean fuel of recorded executions. `None` before any are recorded.
+    pub fn typical_mah(&self, class: &str) -> Option<f32> {
+        let (count, fuel) = self
+            .history(class)
+            .fold((0u64, 0u64), |(n, sum), r| (n + 1, sum + r.fuel_used));
+        (count > 0).then(|| self.estimate_mah(class, fuel / count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(fuel: u64, mah: Option<f32>) -> ExecutionReport {
+        ExecutionReport {
+            wall_time: Duration::from_millis(5),
+            fuel_used: fuel,
+            mah_delta: mah,
+        }
+    }
+
+    #[test]
+    fn fit_recovers_the_device_rate() {
+        let mut calibration = Calibration::default();
+        // A phone: 0.5 mAh fixed wake-up cost plus 2 mAh per 100k fuel.
+        for i in 1..=10u64 {
+            let fuel = i * 50_000;
+            let mah = 0.5 + 2.0 * fuel as f32 / 100_000.0;
+            calibration.record("phone", report(fuel, Some(mah)));
+        }
+        let model = calibration.model("phone");
+        assert!((model.slope * 100_000.0 - 2.0).abs() < 1e-3, "{model:?}");
+        assert!((model.intercept - 0.5).abs() < 1e-3, "{model:?}");
+        assert!((calibration.estimate_mah("phone", 200_000) - 4.5).abs() < 1e-3);
+
+        // Other classes keep the default until they have their own data.
+        assert_eq!(calibration.model("gateway"), FuelModel::default());
+        assert_eq!(calibration.typical_mah("gateway"), None);
+    }
+
+    #[test]
+    fn unmeasured_or_degenerate_history_keeps_the_default() {
+        let mut calibration = Calibration::default();
+        for _ in 0..20 {
+            calibration.record("sensor", report(100_000, None));
+            calibration.record("beacon", report(100_000, Some(3.0)));
+        }
+        assert_eq!(calibration.model("sensor"), FuelModel::default());
+        assert_eq!(calibration.model("beacon"), FuelModel::default());
+        assert_eq!(calibration.typical_mah("sensor"), Some(1.0));
+    }
+}
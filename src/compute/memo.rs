@@ -0,0 +1,187 @@
+//! Memoized task results.
+//!
+//! Running the same WASM module on the same input always yields the same
+//! output, so a worker keeps each output it computed in a fjall keyspace,
+//! keyed by [`memo_key`]: the SHA-256 of the module's and the input's own
+//! digests. Entries expire after `ttl` and remember the capability the task
+//! required, so a cached answer is only served to tasks that require no more
+//! than the one it was computed for. A publisher that sets `Task::memo_key`
+//! lets workers holding the answer bid it at near-zero cost.
+
+use super::Execution;
+use crate::audit::to_hex;
+use crate::core::Capability;
+use fjall::{Database, Keyspace, KeyspaceCreateOptions};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+const KEY_PREFIX: &str = "memo_";
+
+/// Bid cost of a task whose result is already cached.
+pub const CACHED_COST_MAH: f32 = 0.01;
+
+#[derive(Debug, thiserror::Error)]
+pub enum MemoError {
+    #[error("result cache storage error: {0}")]
+    Storage(#[from] fjall::Error),
+    #[error("cached result {key} is unreadable: {source}")]
+    Decode {
+        key: String,
+        source: serde_json::Error,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedResult {
+    pub output: Vec<u8>,
+    /// Capability the task that produced it required.
+    pub capability: Capability,
+    /// Unix time in seconds.
+    pub stored_at: u64,
+}
+
+/// Output of a memoized execution.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Memoized {
+    /// Served from the cache without running anything.
+    Hit(Vec<u8>),
+    Computed(Execution),
+}
+
+impl Memoized {
+    pub fn output(&self) -> &[u8] {
+        match self {
+            Memoized::Hit(output) => output,
+            Memoized::Computed(execution) => &execution.output,
+        }
+    }
+}
+
+/// Cache key for running `payload` on `input`.
+pub fn memo_key(payload: &[u8], input: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(Sha256::digest(payload));
+    hasher.update(Sha256::digest(input));
+    to_hex(&hasher.finalize())
+}
+
+pub struct ResultCache {
+    keyspace: Keyspace,
+    pub ttl: Duration,
+}
+
+impl ResultCache {
+    pub fn open(storage: &Database, ttl: Duration) -> Result<Self, MemoError> {
+        let keyspace = storage.keyspace("hypha_results", KeyspaceCreateOptions::default)?;
+        Ok(Self { keyspace, ttl })
+    }
+
+    pub fn insert(
+        &self,
+        key: &str,
+        capability: &Capability,
+        output: Vec<u8>,
+        now: u64,
+    ) -> Result<(), MemoError> {
+        let entry = CachedResult {
+            output,
+            capability: capability.clone(),
+            stored_at: now,
+        };
+        let value = serde_json::to_vec(&entry).expect("cached result serializes");
+        self.keyspace.insert(entry_key(key), value)?;
+        Ok(())
+    }
+
+    /// The cached output for `key`, if it is fresh at Unix time `now` and was
+    /// computed for a capability that covers `required`.
+    pub fn get(
+        &self,
+        key: &str,
+        required: &Capability,
+        now: u64,
+    ) -> Result<Option<Vec<u8>>, MemoError> {
+        Ok(self
+            .entry(key)?
+            .filter(|entry| self.fresh(entry, now) && entry.capability.satisfies(required))
+            .map(|entry| entry.output))
+    }
+
+    pub fn contains(&self, key: &str, required: &Capability, now: u64) -> bool {
+        self.get(key, required, now).ok().flatten().is_some()
+    }
+
+    /// Drop expired entries. Returns how many were removed.
+    pub fn prune(&self, now: u64) -> Result<usize, MemoError> {
+        let keys: Vec<_> = self
+            .keyspace
+            .prefix(KEY_PREFIX)
+            .map(|item| item.key())
+            .collect::<Result<_, _>>()?;
+        let mut removed = 0;
+        for key in keys {
+            let fresh = match self.keyspace.get(&key)? {
+                Some(value) => serde_json::from_slice::<CachedResult>(&value)
+                    .is_ok_and(|entry| self.fresh(&entry, now)),
+                None => true,
+            };
+            if !fresh {
+                self.keyspace.remove(key)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    fn entry(&self, key: &str) -> Result<Option<CachedResult>, MemoError> {
+        let Some(value) = self.keyspace.get(entry_key(key))? else {
+            return Ok(None);
+        };
+        serde_json::from_slice(&value)
+            .map(Some)
+            .map_err(|source| MemoError::Decode {
+                key: key.to_string(),
+                source,
+            })
+    }
+
+    fn fresh(&self, entry: &CachedResult, now: u64) -> bool {
+        now.saturating_sub(entry.stored_at) < self.ttl.as_secs()
+    }
+}
+
+fn entry_key(key: &str) -> String {
+    format!("{KEY_PREFIX}{key}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cached_results_respect_ttl_and_capability() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Database::builder(dir.path()).open().unwrap();
+        let cache = ResultCache::open(&storage, Duration::from_secs(60)).unwrap();
+        let key = memo_key(b"\0asm", b"input");
+        assert_ne!(key, memo_key(b"\0asm", b"other input"));
+
+        cache
+            .insert(&key, &Capability::Compute(10), b"42".to_vec(), 1_000)
+            .unwrap();
+        assert_eq!(
+            cache.get(&key, &Capability::Compute(5), 1_030).unwrap(),
+            Some(b"42".to_vec())
+        );
+        assert!(!cache.contains(&key, &Capability::Compute(50), 1_030));
+        assert!(!cache.contains(&key, &Capability::Compute(5), 1_060));
+
+        assert_eq!(cache.prune(1_030).unwrap(), 0);
+        assert_eq!(cache.prune(1_060).unwrap(), 1);
+        assert_eq!(
+            cache.get(&key, &Capability::Compute(5), 1_000).unwrap(),
+            None
+        );
+    }
+}
@@ -1,6 +1,9 @@
+use crate::compute::calibration::ExecutionReport;
+use crate::compute::registry::ArtifactError;
 use crate::core::Metabolism;
 use async_trait::async_trait;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 /// Error type for compute failures
 #[derive(Debug, thiserror::Error)]
@@ -11,6 +14,53 @@ pub enum ComputeError {
     Exhausted,
     #[error("Task validation failed: {0}")]
     Validation(String),
+    #[error("Sandbox limit exceeded: {0}")]
+    LimitExceeded(SandboxLimit),
+    #[error("Payload refused: {0}")]
+    Unapproved(#[from] ArtifactError),
+}
+
+/// The sandbox limit an execution ran into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum SandboxLimit {
+    #[error("linear memory above {0} bytes")]
+    Memory(usize),
+    #[error("table above {0} elements")]
+    TableElements(usize),
+    #[error("wall clock above {0:?}")]
+    WallClock(Duration),
+    #[error("more than {0} instructions")]
+    Instructions(u64),
+}
+
+/// Per-execution caps enforced by the sandbox, on top of the energy budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    /// Size of any one linear memory.
+    pub max_memory_bytes: usize,
+    /// Elements in any one table.
+    pub max_table_elements: usize,
+    pub wall_clock: Duration,
+    /// Approximated by fuel: one unit per instruction.
+    pub max_instructions: u64,
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        Self {
+            max_memory_bytes: 16 << 20,
+            max_table_elements: 10_000,
+            wall_clock: Duration::from_secs(5),
+            max_instructions: 10_000_000,
+        }
+    }
+}
+
+/// A successful execution and what it cost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Execution {
+    pub output: Vec<u8>,
+    pub report: ExecutionReport,
 }
 
 /// Abstract Interface for a Compute Runtime
@@ -25,13 +75,18 @@ pub trait ComputeRuntime: Send + Sync {
     /// * `input`: Input data for the task
     /// * `metabolism`: Access to resource accounting
     /// * `budget`: Max resource cost allowed
+    /// * `limits`: Sandbox caps; tripping one fails with `LimitExceeded`
     async fn execute(
         &self,
         payload: &[u8],
         input: &[u8],
         metabolism: Arc<Mutex<dyn Metabolism>>,
         budget: f32,
-    ) -> Result<Vec<u8>, ComputeError>;
+        limits: &ExecutionLimits,
+    ) -> Result<Execution, ComputeError>;
 }
 
+pub mod calibration;
+pub mod memo;
+pub mod registry;
 pub mod wasm;
@@ -0,0 +1,230 @@
+//! Operator-approved task code.
+//!
+//! New task code reaches the fleet through the shared-state document: an
+//! operator approves a WASM module by writing a signed [`ArtifactApproval`]
+//! for its SHA-256 under [`artifact_key`], and the CRDT and anti-entropy
+//! carry it to every node. Withdrawing code is another signed record for
+//! the same hash with `approved` cleared. Approvals of different modules
+//! live under different keys, so concurrent rollouts merge.
+//!
+//! With [`ArtifactPolicy::enforce`] set, `SporeNode::execute_task` refuses
+//! any payload whose hash has no approval signed by one of the policy's
+//! operators. Enforcement is off by default. Firmware images always need an
+//! approval; see `crate::ota`.
+
+use crate::audit::to_hex;
+use crate::auth::{peer_id_of, public_key_of};
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Prefix of approval keys in the shared key/value map.
+pub const ARTIFACT_PREFIX: &str = "artifact/";
+
+const DOMAIN: &[u8] = b"hypha-artifact-v1:";
+
+/// Hex SHA-256 of a payload, as approvals name it.
+pub fn artifact_hash(payload: &[u8]) -> String {
+    to_hex(&Sha256::digest(payload))
+}
+
+/// Key of the approval for the payload hashing to `hash`.
+pub fn artifact_key(hash: &str) -> String {
+    format!("{ARTIFACT_PREFIX}{hash}")
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ArtifactError {
+    #[error("malformed artifact approval: {0}")]
+    Malformed(String),
+    #[error("`{0}` is not an artifact operator")]
+    UnknownOperator(String),
+    #[error("artifact approval signature by `{0}` does not verify")]
+    BadSignature(String),
+    #[error("approval is for {approval}, not {payload}")]
+    HashMismatch { approval: String, payload: String },
+    #[error("payload {0} is not in the artifact registry")]
+    NotApproved(String),
+    #[error("payload {0} was withdrawn from the artifact registry")]
+    Withdrawn(String),
+}
+
+/// An operator's decision on one module.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactApproval {
+    /// Hex SHA-256 of the module.
+    pub hash: String,
+    /// Human-readable name, e.g. `filter-v3`.
+    pub name: String,
+    /// `false` withdraws an earlier approval.
+    pub approved: bool,
+    /// Unix seconds.
+    pub issued_at: u64,
+    /// Peer id of the signing operator key.
+    pub operator: String,
+    pub signature: Vec<u8>,
+}
+
+impl ArtifactApproval {
+    pub fn sign(
+        operator: &SigningKey,
+        hash: &str,
+        name: &str,
+        approved: bool,
+        issued_at: u64,
+    ) -> Self {
+        let mut signed = Self {
+            hash: hash.to_string(),
+            name: name.to_string(),
+            approved,
+            issued_at,
+            operator: peer_id_of(operator).to_string(),
+            signature: Vec::new(),
+        };
+        signed.signature = operator.sign(&signed.signing_bytes()).to_bytes().to_vec();
+        signed
+    }
+
+    /// Check that one of `operators` signed the approval.
+    pub fn verify(&self, operators: &[String]) -> Result<(), ArtifactError> {
+        if !operators.contains(&self.operator) {
+            return Err(ArtifactError::UnknownOperator(self.operator.clone()));
+        }
+        if public_key_of(&self.operator)
+            .is_some_and(|key| key.verify(&self.signing_bytes(), &self.signature))
+        {
+            Ok(())
+        } else {
+            Err(ArtifactError::BadSignature(self.operator.clone()))
+        }
+    }
+
+    /// The document stored under [`artifact_key`].
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("approval serializes")
+    }
+
+    pub fn decode(document: &str) -> Result<Self, ArtifactError> {
+        serde_json::from_str(document).map_err(|e| ArtifactError::Malformed(e.to_string()))
+    }
+
+    fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = DOMAIN.to_vec();
+        bytes.extend(
+            serde_json::to_vec(&(
+                &self.hash,
+                &self.name,
+                self.approved,
+                self.issued_at,
+                &self.operator,
+            ))
+            .expect("approval serializes"),
+        );
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArtifactPolicy {
+    /// Off by default: any payload runs.
+    pub enforce: bool,
+    /// Peer ids of the keys allowed to approve code.
+    pub operators: Vec<String>,
+}
+
+impl ArtifactPolicy {
+    /// Whether `payload` may run, reading approvals through `lookup` from
+    /// the shared key/value map.
+    pub fn check(
+        &self,
+        payload: &[u8],
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), ArtifactError> {
+        if !self.enforce {
+            return Ok(());
+        }
+        self.approved(&artifact_hash(payload), lookup)
+    }
+
+    /// Whether the payload hashing to `hash` has a current approval by one
+    /// of the operators, whether or not the policy is enforced.
+    pub fn approved(
+        &self,
+        hash: &str,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), ArtifactError> {
+        let document = lookup(&artifact_key(hash))
+            .ok_or_else(|| ArtifactError::NotApproved(hash.to_string()))?;
+        let approval = ArtifactApproval::decode(&document)?;
+        approval.verify(&self.operators)?;
+        if approval.hash != hash {
+            return Err(ArtifactError::HashMismatch {
+                approval: approval.hash,
+                payload: hash.to_string(),
+            });
+        }
+        if !approval.approved {
+            return Err(ArtifactError::Withdrawn(hash.to_string()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn only_signed_current_approvals_let_code_run() {
+        let operator = SigningKey::from_bytes(&[7; 32]);
+        let intruder = SigningKey::from_bytes(&[8; 32]);
+        let module = b"\0asm filter-v3";
+        let hash = artifact_hash(module);
+        let mut registry = HashMap::new();
+        let policy = ArtifactPolicy {
+            enforce: true,
+            operators: vec![peer_id_of(&operator).to_string()],
+        };
+        let check = |registry: &HashMap<String, String>| {
+            policy.check(module, |key| registry.get(key).cloned())
+        };
+
+        assert!(ArtifactPolicy::default().check(module, |_| None).is_ok());
+        assert_eq!(
+            check(&registry),
+            Err(ArtifactError::NotApproved(hash.clone()))
+        );
+
+        let forged = ArtifactApproval::sign(&intruder, &hash, "filter", true, 1);
+        registry.insert(artifact_key(&hash), forged.encode());
+        assert!(matches!(
+            check(&registry),
+            Err(ArtifactError::UnknownOperator(_))
+        ));
+
+        let mut tampered = ArtifactApproval::sign(&operator, &hash, "filter", false, 1);
+        tampered.approved = true;
+        registry.insert(artifact_key(&hash), tampered.encode());
+        assert!(matches!(
+            check(&registry),
+            Err(ArtifactError::BadSignature(_))
+        ));
+
+        let other = artifact_hash(b"\0asm other");
+        let misfiled = ArtifactApproval::sign(&operator, &other, "other", true, 1);
+        registry.insert(artifact_key(&hash), misfiled.encode());
+        assert!(matches!(
+            check(&registry),
+            Err(ArtifactError::HashMismatch { .. })
+        ));
+
+        let approval = ArtifactApproval::sign(&operator, &hash, "filter", true, 1);
+        registry.insert(artifact_key(&hash), approval.encode());
+        assert_eq!(check(&registry), Ok(()));
+
+        let withdrawal = ArtifactApproval::sign(&operator, &hash, "filter", false, 2);
+        registry.insert(artifact_key(&hash), withdrawal.encode());
+        assert_eq!(check(&registry), Err(ArtifactError::Withdrawn(hash)));
+    }
+}
@@ -1,8 +1,14 @@
-use crate::compute::{ComputeError, ComputeRuntime};
+use crate::compute::calibration::ExecutionReport;
+use crate::compute::{ComputeError, ComputeRuntime, Execution, ExecutionLimits, SandboxLimit};
 use crate::core::Metabolism;
 use async_trait::async_trait;
 use std::sync::{Arc, Mutex};
-use wasmtime::{Config, Engine, Linker, Module, Store};
+use std::time::Instant;
+use wasmtime::{Config, Engine, Linker, Module, ResourceLimiter, Store, Trap};
+
+/// Fuel between yields to the executor, so the wall-clock limit can fire
+/// while a guest is spinning.
+const YIELD_INTERVAL: u64 = 10_000;
 
 pub struct WasmTimeRuntime {
     engine: Engine,
@@ -19,6 +25,45 @@ impl WasmTimeRuntime {
     }
 }
 
+/// Refuses growth past the execution limits and remembers which one tripped.
+struct Limiter {
+    limits: ExecutionLimits,
+    tripped: Option<SandboxLimit>,
+}
+
+impl Limiter {
+    fn trip(&mut self, limit: SandboxLimit) -> anyhow::Result<bool> {
+        self.tripped = Some(limit);
+        Err(limit.into())
+    }
+}
+
+impl ResourceLimiter for Limiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        if desired > self.limits.max_memory_bytes {
+            return self.trip(SandboxLimit::Memory(self.limits.max_memory_bytes));
+        }
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        if desired > self.limits.max_table_elements {
+            return self.trip(SandboxLimit::TableElements(self.limits.max_table_elements));
+        }
+        Ok(true)
+    }
+}
+
 #[async_trait]
 impl ComputeRuntime for WasmTimeRuntime {
     fn name(&self) -> &str {
@@ -31,35 +76,57 @@ impl ComputeRuntime for WasmTimeRuntime {
         _input: &[u8],
         metabolism: Arc<Mutex<dyn Metabolism>>,
         budget: f32,
-    ) -> Result<Vec<u8>, ComputeError> {
+        limits: &ExecutionLimits,
+    ) -> Result<Execution, ComputeError> {
         // 1. Compile Module
         let module = Module::from_binary(&self.engine, payload)
             .map_err(|e| ComputeError::Wasm(e.to_string()))?;
 
-        // 2. Setup Store & Fuel
-        struct State {}
-        let mut store = Store::new(&self.engine, State {});
+        // 2. Setup Store, Limits & Fuel
+        struct State {
+            limiter: Limiter,
+        }
+        let mut store = Store::new(
+            &self.engine,
+            State {
+                limiter: Limiter {
+                    limits: limits.clone(),
+                    tripped: None,
+                },
+            },
+        );
+        store.limiter(|state| &mut state.limiter);
 
-        // Map 1.0 energy -> 100,000 fuel units (example ratio)
-        let fuel_limit = (budget * 100_000.0) as u64;
+        // Map 1.0 energy -> 100,000 fuel units (example ratio). Fuel also
+        // stands in for the instruction count, so the lower cap applies.
+        let budget_fuel = (budget * 100_000.0) as u64;
+        let fuel_limit = budget_fuel.min(limits.max_instructions);
         store
             .set_fuel(fuel_limit)
             .map_err(|e| ComputeError::Wasm(e.to_string()))?;
-
-        // 3. Instantiate
-        let linker = Linker::new(&self.engine);
-        let instance = linker
-            .instantiate_async(&mut store, &module)
-            .await
+        store
+            .fuel_async_yield_interval(Some(YIELD_INTERVAL))
             .map_err(|e| ComputeError::Wasm(e.to_string()))?;
 
-        // 4. Invoke "run" export
-        let run = instance
-            .get_typed_func::<(), ()>(&mut store, "run")
-            .map_err(|e| ComputeError::Wasm(format!("Missing 'run' export: {}", e)))?;
+        // 3. Instantiate, then invoke the "run" export, within the wall clock
+        let linker = Linker::new(&self.engine);
+        let gauge_before = metabolism.lock().unwrap().measured_mah();
+        let started = Instant::now();
+        let run = async {
+            let instance = linker.instantiate_async(&mut store, &module).await?;
+            let run = instance
+                .get_typed_func::<(), ()>(&mut store, "run")
+                .map_err(|e| anyhow::anyhow!("Missing 'run' export: {}", e))?;
+            run.call_async(&mut store, ()).await
+        };
+        let Ok(outcome) = tokio::time::timeout(limits.wall_clock, run).await else {
+            return Err(ComputeError::LimitExceeded(SandboxLimit::WallClock(
+                limits.wall_clock,
+            )));
+        };
 
-        // 5. Execute
-        match run.call_async(&mut store, ()).await {
+        // 4. Settle
+        match outcome {
             Ok(_) => {
                 // Calculate consumed energy
                 // get_fuel returns remaining fuel.
@@ -69,16 +136,37 @@ impl ComputeRuntime for WasmTimeRuntime {
 
                 // Deduct from metabolism
                 let mut meta = metabolism.lock().unwrap();
+                let mah_delta = gauge_before
+                    .zip(meta.measured_mah())
+                    .map(|(before, after)| before - after);
                 if !meta.consume(cost) {
                     return Err(ComputeError::Exhausted);
                 }
 
-                Ok(vec![]) // Output capturing TBD
-            }
-            Err(e) => {
-                // If it's OOG, it traps.
-                Err(ComputeError::Wasm(e.to_string()))
+                Ok(Execution {
+                    output: vec![], // Output capturing TBD
+                    report: ExecutionReport {
+                        wall_time: started.elapsed(),
+                        fuel_used: consumed,
+                        mah_delta,
+                    },
+                })
             }
+            Err(e) => Err(match store.data().limiter.tripped {
+                Some(limit) => ComputeError::LimitExceeded(limit),
+                // Fuel is capped by both the budget and the instruction limit;
+                // name whichever was lower.
+                None if matches!(e.downcast_ref::<Trap>(), Some(Trap::OutOfFuel)) => {
+                    if fuel_limit < budget_fuel {
+                        ComputeError::LimitExceeded(SandboxLimit::Instructions(
+                            limits.max_instructions,
+                        ))
+                    } else {
+                        ComputeError::Exhausted
+                    }
+                }
+                None => ComputeError::Wasm(e.to_string()),
+            }),
         }
     }
 }
@@ -86,9 +174,10 @@ impl ComputeRuntime for WasmTimeRuntime {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::compute::ComputeError;
+    use crate::compute::{ComputeError, ExecutionLimits, SandboxLimit};
     use crate::core::{BatteryMetabolism, Metabolism};
     use std::sync::{Arc, Mutex};
+    use std::time::Duration;
 
     #[tokio::test]
     async fn test_wasm_execution_consumes_fuel() {
@@ -116,9 +205,19 @@ mod tests {
 
         // 4. Execute with budget
         // 1.0 budget = 100,000 units. 1000 loops should be cheap.
-        let result = runtime.execute(&wasm_bytes, &[], meta.clone(), 1.0).await;
+        let result = runtime
+            .execute(
+                &wasm_bytes,
+                &[],
+                meta.clone(),
+                1.0,
+                &ExecutionLimits::default(),
+            )
+            .await;
 
-        assert!(result.is_ok(), "Execution failed: {:?}", result.err());
+        let execution = result.expect("Execution failed");
+        assert!(execution.report.fuel_used > 1000);
+        assert_eq!(execution.report.mah_delta, None);
 
         // 5. Verify energy consumption
         let remaining = meta.lock().unwrap().energy_score();
@@ -151,7 +250,13 @@ mod tests {
 
         // Tiny budget (0.0001 = 10 fuel)
         let result = runtime
-            .execute(&wasm_bytes, &[], meta.clone(), 0.0001)
+            .execute(
+                &wasm_bytes,
+                &[],
+                meta.clone(),
+                0.0001,
+                &ExecutionLimits::default(),
+            )
             .await;
 
         // Should fail due to OOG (Out Of Gas) / Wasm runtime error
@@ -162,4 +267,79 @@ mod tests {
             // assert!(msg.contains("fuel"));
         }
     }
+
+    async fn run_with(wat: &str, limits: ExecutionLimits) -> Result<Execution, ComputeError> {
+        let runtime = WasmTimeRuntime::new().unwrap();
+        let meta = Arc::new(Mutex::new(BatteryMetabolism::default()));
+        let wasm_bytes = wat::parse_str(wat).unwrap();
+        runtime
+            .execute(&wasm_bytes, &[], meta, 1_000_000.0, &limits)
+            .await
+    }
+
+    #[tokio::test]
+    async fn memory_and_table_growth_are_capped() {
+        let grow_memory = r#"
+            (module
+                (memory 1)
+                (func (export "run")
+                    (drop (memory.grow (i32.const 1000)))
+                )
+            )
+        "#;
+        let limits = ExecutionLimits {
+            max_memory_bytes: 1 << 20,
+            ..ExecutionLimits::default()
+        };
+        assert!(matches!(
+            run_with(grow_memory, limits).await,
+            Err(ComputeError::LimitExceeded(SandboxLimit::Memory(_)))
+        ));
+
+        let grow_table = r#"
+            (module
+                (table 1 funcref)
+                (func (export "run")
+                    (drop (table.grow (ref.null func) (i32.const 100000)))
+                )
+            )
+        "#;
+        assert!(matches!(
+            run_with(grow_table, ExecutionLimits::default()).await,
+            Err(ComputeError::LimitExceeded(SandboxLimit::TableElements(
+                10_000
+            )))
+        ));
+    }
+
+    #[tokio::test]
+    async fn spinning_guest_trips_instructions_or_wall_clock() {
+        let spin = r#"
+            (module
+                (func (export "run")
+                    (loop $l (br $l))
+                )
+            )
+        "#;
+        let few_instructions = ExecutionLimits {
+            max_instructions: 50_000,
+            ..ExecutionLimits::default()
+        };
+        assert!(matches!(
+            run_with(spin, few_instructions).await,
+            Err(ComputeError::LimitExceeded(SandboxLimit::Instructions(
+                50_000
+            )))
+        ));
+
+        let short_clock = ExecutionLimits {
+            max_instructions: u64::MAX,
+            wall_clock: Duration::from_millis(50),
+            ..ExecutionLimits::default()
+        };
+        assert!(matches!(
+            run_with(spin, short_clock).await,
+            Err(ComputeError::LimitExceeded(SandboxLimit::WallClock(_)))
+        ));
+    }
 }
@@ -0,0 +1,138 @@
+//! Node configuration that can change while the node runs.
+//!
+//! [`HyphaConfig`] gathers the knobs an operator tunes after deployment.
+//! `SporeNode::apply_config` diffs a new config against the node's live
+//! state and applies only the [`ConfigSection`]s that differ; a running node
+//! takes updates through `NodeLink::apply_config` and applies them on its
+//! next heartbeat. Most sections take effect in place. A namespace change
+//! has to leave every topic and rejoin under the new names, so it restarts
+//! the subscriptions.
+
+use crate::auth::DelegationLimits;
+use crate::bidding::QuorumConfig;
+use crate::degradation::DegradationLadder;
+use crate::mycelium::SubscriptionPolicy;
+use crate::shard::ShardPlan;
+use crate::wire::SendPolicy;
+use libp2p::Multiaddr;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HyphaConfig {
+    /// Energy bands, relay gates and the full-energy mesh parameters.
+    pub degradation: DegradationLadder,
+    pub subscriptions: SubscriptionPolicy,
+    /// Limits on outgoing gossip in low-power modes.
+    pub send_policy: SendPolicy,
+    pub bootstrap: Vec<Multiaddr>,
+    pub rendezvous_points: Vec<Multiaddr>,
+    /// Topic namespace; `None` uses the bare topic names.
+    pub namespace: Option<String>,
+    pub trusted_issuers: Vec<String>,
+    pub delegation_limits: DelegationLimits,
+    /// When to stay out of task auctions.
+    pub quorum: QuorumConfig,
+    /// How the task topic is split; changing it reshards the node.
+    pub shards: ShardPlan,
+}
+
+/// A part of [`HyphaConfig`] that can change independently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ConfigSection {
+    Mesh,
+    EnergyBands,
+    Relay,
+    Subscriptions,
+    SendPolicy,
+    Bootstrap,
+    Rendezvous,
+    /// Restarts the subscriptions under the new topic names.
+    Namespace,
+    TrustedIssuers,
+    DelegationLimits,
+    Quorum,
+    /// Joins the new plan's task shards, keeping the old ones while they
+    /// drain.
+    Shards,
+}
+
+impl HyphaConfig {
+    /// Sections that differ between `self` and `new`.
+    pub fn diff(&self, new: &HyphaConfig) -> Vec<ConfigSection> {
+        let (old_ladder, new_ladder) = (&self.degradation, &new.degradation);
+        let relay =
+            |l: &DegradationLadder| (l.relay_all_above, l.relay_max_pressure, l.relay_min_phase);
+        [
+            (old_ladder.mesh != new_ladder.mesh, ConfigSection::Mesh),
+            (
+                old_ladder.bands != new_ladder.bands,
+                ConfigSection::EnergyBands,
+            ),
+            (relay(old_ladder) != relay(new_ladder), ConfigSection::Relay),
+            (
+                self.subscriptions != new.subscriptions,
+                ConfigSection::Subscriptions,
+            ),
+            (
+                self.send_policy != new.send_policy,
+                ConfigSection::SendPolicy,
+            ),
+            (self.bootstrap != new.bootstrap, ConfigSection::Bootstrap),
+            (
+                self.rendezvous_points != new.rendezvous_points,
+                ConfigSection::Rendezvous,
+            ),
+            (self.namespace != new.namespace, ConfigSection::Namespace),
+            (
+                self.trusted_issuers != new.trusted_issuers,
+                ConfigSection::TrustedIssuers,
+            ),
+            (
+                self.delegation_limits != new.delegation_limits,
+                ConfigSection::DelegationLimits,
+            ),
+            (self.quorum != new.quorum, ConfigSection::Quorum),
+            (self.shards != new.shards, ConfigSection::Shards),
+        ]
+        .into_iter()
+        .filter_map(|(changed, section)| changed.then_some(section))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mycelium::TopicKind;
+    use crate::shard::ShardKey;
+    use crate::wire::Priority;
+
+    #[test]
+    fn diff_lists_only_changed_sections() {
+        let old = HyphaConfig::default();
+        assert!(old.diff(&old.clone()).is_empty());
+
+        let mut new = old.clone();
+        new.degradation.mesh.d_high = 16;
+        new.degradation.relay_min_phase = 0.5;
+        new.subscriptions
+            .low_battery
+            .retain(|kind| *kind != TopicKind::Sensor);
+        new.send_policy.low_battery_min = Priority::High;
+        new.namespace = Some("lab".to_string());
+        new.quorum.min_healthy_bids = 5;
+        new.shards = ShardPlan::new(8, ShardKey::Capability);
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                ConfigSection::Mesh,
+                ConfigSection::Relay,
+                ConfigSection::Subscriptions,
+                ConfigSection::SendPolicy,
+                ConfigSection::Namespace,
+                ConfigSection::Quorum,
+                ConfigSection::Shards,
+            ]
+        );
+    }
+}
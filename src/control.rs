@@ -0,0 +1,142 @@
+//! Signed mesh control messages.
+//!
+//! GRAFT and PRUNE travel on the control topic, which every peer may publish
+//! on, so a bare `(target, control)` pair lets anyone prune a victim out of a
+//! neighbor's mesh. Each [`SignedControl`] names its sender and carries that
+//! sender's signature over the target and the control. Control is link-local:
+//! a receiver acts only on controls signed by the peer that delivered them.
+//!
+//! A control that arrives through another peer than its claimed sender is
+//! dropped quietly, signed or not: gossipsub relays control traffic like any
+//! other, and the relay cannot be told from the forger. Only a control whose
+//! claimed sender delivered it and whose signature fails gets its deliverer
+//! penalized.
+
+use crate::auth::{peer_id_of, public_key_of};
+use crate::mesh::MeshControl;
+use ed25519_dalek::{Signer, SigningKey};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+
+const DOMAIN: &[u8] = b"hypha-control-v1:";
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ControlError {
+    #[error("control signature by `{0}` does not verify")]
+    BadSignature(String),
+    #[error("control signed by `{sender}` was delivered by `{source_peer}`")]
+    Relayed { sender: String, source_peer: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedControl {
+    pub sender: String,
+    /// The peer the control is addressed to.
+    pub target: String,
+    pub control: MeshControl,
+    pub signature: Vec<u8>,
+}
+
+impl SignedControl {
+    pub fn sign(signing_key: &SigningKey, target: String, control: MeshControl) -> Self {
+        let sender = peer_id_of(signing_key).to_string();
+        let signature = signing_key
+            .sign(&signing_bytes(&sender, &target, &control))
+            .to_bytes()
+            .to_vec();
+        Self {
+            sender,
+            target,
+            control,
+            signature,
+        }
+    }
+
+    /// Check that `source` (the propagation source) is the sender, then the
+    /// signature. A relayed control is reported as such before its signature
+    /// is looked at, so a forgery passed on by an honest relay is never
+    /// blamed on the relay.
+    pub fn verify(&self, source: &PeerId) -> Result<(), ControlError> {
+        if self.sender != source.to_string() {
+            return Err(ControlError::Relayed {
+                sender: self.sender.clone(),
+                source_peer: source.to_string(),
+            });
+        }
+        let bytes = signing_bytes(&self.sender, &self.target, &self.control);
+        if !public_key_of(&self.sender).is_some_and(|key| key.verify(&bytes, &self.signature)) {
+            return Err(ControlError::BadSignature(self.sender.clone()));
+        }
+        Ok(())
+    }
+}
+
+fn signing_bytes(sender: &str, target: &str, control: &MeshControl) -> Vec<u8> {
+    let mut bytes = DOMAIN.to_vec();
+    bytes.extend(serde_json::to_vec(&(sender, target, control)).expect("mesh control serializes"));
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn prune() -> MeshControl {
+        MeshControl::Prune {
+            topic: "hypha_status".to_string(),
+            backoff: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn only_the_signing_neighbor_is_believed() {
+        let sender = SigningKey::from_bytes(&[1; 32]);
+        let relay = peer_id_of(&SigningKey::from_bytes(&[2; 32]));
+        let sender_id = peer_id_of(&sender);
+        let signed = SignedControl::sign(&sender, "victim".to_string(), prune());
+
+        assert_eq!(signed.verify(&sender_id), Ok(()));
+        assert!(matches!(
+            signed.verify(&relay),
+            Err(ControlError::Relayed { .. })
+        ));
+
+        let mut retargeted = signed.clone();
+        retargeted.target = "other".to_string();
+        assert!(matches!(
+            retargeted.verify(&sender_id),
+            Err(ControlError::BadSignature(_))
+        ));
+
+        // Claiming someone else's identity does not help a forger.
+        let mut spoofed = SignedControl::sign(
+            &SigningKey::from_bytes(&[3; 32]),
+            "victim".to_string(),
+            prune(),
+        );
+        spoofed.sender = sender_id.to_string();
+        assert!(matches!(
+            spoofed.verify(&sender_id),
+            Err(ControlError::BadSignature(_))
+        ));
+    }
+
+    #[test]
+    fn a_forgery_passed_on_by_a_relay_is_not_blamed_on_it() {
+        let sender = SigningKey::from_bytes(&[1; 32]);
+        let relay = peer_id_of(&SigningKey::from_bytes(&[2; 32]));
+        let mut forged = SignedControl::sign(&sender, "victim".to_string(), prune());
+        forged.signature = vec![0; 64];
+
+        // The run loop penalizes the deliverer only on `BadSignature`.
+        assert!(matches!(
+            forged.verify(&relay),
+            Err(ControlError::Relayed { .. })
+        ));
+        assert!(matches!(
+            forged.verify(&peer_id_of(&sender)),
+            Err(ControlError::BadSignature(_))
+        ));
+    }
+}
@@ -1,41 +1,115 @@
+use super::{NodeRole, PowerMode};
 use rand::rng;
-use rand::seq::IndexedRandom;
+use rand::seq::IteratorRandom;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 /// Prototype spike intensity that affects local mesh pressure.
 pub const PRESSURE_SPIKE_THRESHOLD: u8 = 200;
 
+/// Energy score assumed for a connected peer that has not advertised status yet.
+pub const UNKNOWN_ENERGY_SCORE: f32 = 0.5;
+
+/// Backoff applied when a mesh peer's last connection closes.
+pub const DISCONNECT_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Longest downtime after which a restored node still reclaims its previous
+/// mesh slots. Older state only seeds peer scores.
+pub const MAX_WARM_START_AGE: Duration = Duration::from_secs(10 * 60);
+
+/// How long a restored mesh peer may stay disconnected before it is pruned.
+pub const WARM_START_GRACE: Duration = Duration::from_secs(30);
+
+/// Round-trip time at or above which a peer gets no latency credit when
+/// ranking forward targets.
+pub const SLOW_RTT: Duration = Duration::from_millis(500);
+
+/// Span of the windowed delivery counters in [`MeshStats`].
+pub const DELIVERY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Granularity of the windowed delivery counters.
+const DELIVERY_BUCKET: Duration = Duration::from_secs(10);
+
+/// Encoded size of an IHAVE besides its topic and ids, as counted against
+/// `MeshConfig::max_ihave_bytes`.
+const IHAVE_OVERHEAD: usize = 32;
+
 /// Mesh configuration parameters for local graft/prune behavior.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct MeshConfig {
     pub d: usize,
     pub d_low: usize,
     pub d_high: usize,
     pub d_lazy: usize,
+    /// Most message ids in one IHAVE.
+    pub max_ihave_ids: usize,
+    /// Cap on the encoded size of all IHAVEs sent in one heartbeat.
+    pub max_ihave_bytes: usize,
     pub heartbeat_interval: Duration,
+    /// Weights the mesh's `PeerScorer` applies.
+    pub score_weights: ScoreWeights,
     pub opportunistic_graft_threshold: f32,
     pub graft_threshold: f32,
     pub prune_threshold: f32,
+    /// Least share of `d` the mesh keeps filled with peers this node dialed,
+    /// so inbound connections alone cannot take the mesh over.
+    pub outbound_fraction: f32,
+    /// Forward-target ranking for latency-sensitive topics.
+    pub latency_forwarding: ForwardWeights,
+    /// Forward-target ranking for bulk topics.
+    pub bulk_forwarding: ForwardWeights,
+    /// Most peers `TopicMesh::known_peers` holds. Past it the least useful
+    /// peers outside the mesh are evicted: lowest score first, then longest
+    /// unseen. `None`, the default, tracks every peer seen.
+    pub max_known_peers: Option<usize>,
+    /// Connected peers that every own critical message goes to first,
+    /// chosen to differ in introducer, address prefix and zone so that no
+    /// single party can eclipse the node. 0, the default, turns this off.
+    pub diverse_first_hops: usize,
 }
 
 impl MeshConfig {
+    /// Mesh degree for `energy_score`, banded as `PowerMode::from_energy_score`.
+    /// A node's run loop takes its band from `DegradationLadder` instead.
     pub fn adaptive(energy_score: f32) -> Self {
+        Self::for_mode(&PowerMode::from_energy_score(energy_score))
+    }
+
+    /// Smaller meshes in low-power modes mean fewer forwards per message.
+    pub fn for_mode(mode: &PowerMode) -> Self {
         let mut config = Self::default();
-        if energy_score < 0.2 {
-            config.d = 2;
-            config.d_low = 1;
-            config.d_high = 4;
-            config.d_lazy = 2;
-        } else if energy_score < 0.5 {
-            config.d = 4;
-            config.d_low = 2;
-            config.d_high = 8;
-            config.d_lazy = 4;
+        match mode {
+            PowerMode::Normal => {}
+            PowerMode::LowBattery => {
+                config.d = 4;
+                config.d_low = 2;
+                config.d_high = 8;
+                config.d_lazy = 4;
+            }
+            PowerMode::Critical => {
+                config.d = 2;
+                config.d_low = 1;
+                config.d_high = 4;
+                config.d_lazy = 2;
+            }
         }
         config
     }
+
+    /// Lazy-push budget scaled to `headroom`, the share of bandwidth and
+    /// energy to spare in `0.0..=1.0`. At zero the node sends no IHAVEs.
+    pub fn scale_gossip(mut self, headroom: f32) -> Self {
+        let headroom = headroom.clamp(0.0, 1.0);
+        let scale = |n: usize| (n as f32 * headroom).ceil() as usize;
+        self.d_lazy = scale(self.d_lazy);
+        self.max_ihave_ids = scale(self.max_ihave_ids);
+        self.max_ihave_bytes = scale(self.max_ihave_bytes);
+        self
+    }
 }
 
 impl Default for MeshConfig {
@@ -45,10 +119,145 @@ impl Default for MeshConfig {
             d_low: 4,
             d_high: 12,
             d_lazy: 6,
+            max_ihave_ids: 10,
+            max_ihave_bytes: 4096,
             heartbeat_interval: Duration::from_secs(1),
+            score_weights: ScoreWeights::default(),
             opportunistic_graft_threshold: 0.3,
             graft_threshold: 0.1,
             prune_threshold: 0.05,
+            outbound_fraction: 0.3,
+            latency_forwarding: ForwardWeights::latency(),
+            bulk_forwarding: ForwardWeights::bulk(),
+            max_known_peers: None,
+            diverse_first_hops: 0,
+        }
+    }
+}
+
+/// Weights of the default peer score. They need not sum to one; the
+/// graft and prune thresholds are set against the defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreWeights {
+    pub energy: f32,
+    pub activity: f32,
+    pub conductivity: f32,
+    /// Weight of low local pressure.
+    pub pressure: f32,
+    /// Scales the role bias: relays up, sensors down.
+    pub role: f32,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            energy: 0.3,
+            activity: 0.2,
+            conductivity: 0.3,
+            pressure: 0.2,
+            role: 1.0,
+        }
+    }
+}
+
+/// What a topic's traffic cares about when forward targets are ranked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrafficClass {
+    /// Spikes, control and the like: the first hop should be a fast one.
+    Latency,
+    /// Readings and state sync: throughput over speed.
+    Bulk,
+}
+
+/// Weights of the forward-target ranking, applied once any candidate has an
+/// RTT measurement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForwardWeights {
+    /// Weight of a short round trip, relative to [`SLOW_RTT`].
+    pub rtt: f32,
+    /// Weight of low peer pressure.
+    pub pressure: f32,
+    pub conductivity: f32,
+}
+
+impl ForwardWeights {
+    /// Fast, unloaded peers first.
+    pub fn latency() -> Self {
+        Self {
+            rtt: 1.0,
+            pressure: 0.5,
+            conductivity: 0.0,
+        }
+    }
+
+    /// Well-used paths first, whatever their RTT.
+    pub fn bulk() -> Self {
+        Self {
+            rtt: 0.0,
+            pressure: 0.2,
+            conductivity: 1.0,
+        }
+    }
+}
+
+/// Scores peers for mesh maintenance. A deployment can install its own with
+/// `TopicMesh::set_scorer`, e.g. to weigh in RTT or zone affinity it tracks
+/// itself; the default is [`WeightedScore`].
+pub trait PeerScorer: fmt::Debug + Send + Sync {
+    fn score(&self, peer: &MeshPeer, weights: &ScoreWeights) -> f32;
+}
+
+/// `MeshPeer::weighted_score`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WeightedScore;
+
+impl PeerScorer for WeightedScore {
+    fn score(&self, peer: &MeshPeer, weights: &ScoreWeights) -> f32 {
+        peer.weighted_score(weights)
+    }
+}
+
+/// What a peer may share with others, for telling apart first hops an
+/// attacker could control together. Unknown facts count as shared.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PeerOrigin {
+    /// The peer that led this node to it, such as a rendezvous point.
+    pub introducer: Option<String>,
+    /// See [`ip_prefix`].
+    pub ip_prefix: Option<String>,
+    pub zone: Option<String>,
+}
+
+impl PeerOrigin {
+    fn facts(&self) -> [Option<&str>; 3] {
+        [
+            self.introducer.as_deref(),
+            self.ip_prefix.as_deref(),
+            self.zone.as_deref(),
+        ]
+    }
+}
+
+/// One thing learned about where a peer came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OriginFact {
+    Introducer(String),
+    /// An address the peer was reached at.
+    Address(IpAddr),
+    Zone(Option<String>),
+}
+
+/// The network an address sits in, as far as one operator usually holds:
+/// the /24 of an IPv4 address, the /48 of an IPv6 one.
+pub fn ip_prefix(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            format!("{a}.{b}.{c}.0/24")
+        }
+        IpAddr::V6(ip) => {
+            let [a, b, c, ..] = ip.segments();
+            format!("{a:x}:{b:x}:{c:x}::/48")
         }
     }
 }
@@ -63,6 +272,21 @@ pub struct MeshPeer {
     pub message_count: u64,
     pub last_seen: Instant,
     pub in_mesh: bool,
+    /// Whether at least one swarm connection to this peer is open.
+    pub connected: bool,
+    /// Whether this node dialed one of the open connections.
+    pub outbound: bool,
+    /// Temporary deduction from `score`, e.g. after anomalous behavior.
+    pub penalty: f32,
+    pub penalty_until: Option<Instant>,
+    /// Lasting deduction from `score` for misbehavior over past days, as
+    /// kept by the node's reputation record.
+    pub reputation: f32,
+    /// Role the peer advertises, if any.
+    pub role: Option<NodeRole>,
+    /// Smoothed round-trip time, once measured.
+    pub rtt: Option<Duration>,
+    pub origin: PeerOrigin,
 }
 
 impl MeshPeer {
@@ -75,20 +299,62 @@ impl MeshPeer {
             message_count: 0,
             last_seen: Instant::now(),
             in_mesh: false,
+            connected: false,
+            outbound: false,
+            penalty: 0.0,
+            penalty_until: None,
+            reputation: 0.0,
+            role: None,
+            rtt: None,
+            origin: PeerOrigin::default(),
         }
     }
 
+    /// Score under the default weights. The mesh itself scores through its
+    /// `PeerScorer` with `MeshConfig::score_weights`.
     pub fn score(&self) -> f32 {
+        self.weighted_score(&ScoreWeights::default())
+    }
+
+    /// Rank as a forward target under `weights`. A peer without an RTT
+    /// measurement counts as halfway to [`SLOW_RTT`].
+    pub fn forward_score(&self, weights: &ForwardWeights) -> f32 {
+        let rtt_score = self.rtt.map_or(0.5, |rtt| {
+            1.0 - (rtt.as_secs_f32() / SLOW_RTT.as_secs_f32()).min(1.0)
+        });
+        let pressure_score = 1.0 - (self.pressure.min(10.0) / 10.0);
+        let normalized_conductivity = self.conductivity.min(5.0) / 5.0;
+        rtt_score * weights.rtt
+            + pressure_score * weights.pressure
+            + normalized_conductivity * weights.conductivity
+    }
+
+    pub fn weighted_score(&self, weights: &ScoreWeights) -> f32 {
         let activity_score = (self.message_count as f32 / 100.0).min(1.0);
         // This weighted score is a local mesh-maintenance heuristic. It is not
         // a trust score or an adversarial GossipSub peer score.
         let normalized_conductivity = self.conductivity.min(5.0) / 5.0;
         let pressure_score = 1.0 - (self.pressure.min(10.0) / 10.0);
 
-        self.energy_score * 0.3
-            + activity_score * 0.2
-            + normalized_conductivity * 0.3
-            + pressure_score * 0.2
+        let penalty = match self.penalty_until {
+            Some(until) if Instant::now() < until => self.penalty,
+            _ => 0.0,
+        };
+        // Relays are there to carry traffic; sensors would rather not.
+        let role_bias = match self.role {
+            Some(NodeRole::Relay) => 0.2,
+            Some(NodeRole::Coordinator) => 0.1,
+            Some(NodeRole::Sensor) => -0.1,
+            Some(NodeRole::Compute) | None => 0.0,
+        };
+
+        self.energy_score * weights.energy
+            + activity_score * weights.activity
+            + normalized_conductivity * weights.conductivity
+            + pressure_score * weights.pressure
+            + role_bias * weights.role
+            - penalty
+            - self.reputation
     }
 }
 
@@ -110,6 +376,28 @@ pub enum MeshControl {
     },
 }
 
+/// Score history of one peer, as persisted across restarts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersistedPeer {
+    pub id: String,
+    pub energy_score: f32,
+    pub conductivity: f32,
+    pub message_count: u64,
+    pub penalty: f32,
+    /// Penalty time left when the state was saved.
+    pub penalty_left: Option<Duration>,
+}
+
+/// Mesh membership, backoffs and peer scores saved on shutdown so a restarted
+/// node can rejoin where it left off. Deadlines are stored as time remaining,
+/// since `Instant`s do not survive a restart.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PersistedMesh {
+    pub mesh_peers: Vec<String>,
+    pub peers: Vec<PersistedPeer>,
+    pub backoff: Vec<(String, Duration)>,
+}
+
 #[derive(Debug)]
 pub struct TopicMesh {
     pub topic: String,
@@ -119,8 +407,23 @@ pub struct TopicMesh {
     pub mesh_peers: HashSet<String>,
     pub known_peers: HashMap<String, MeshPeer>,
     pub message_cache: HashSet<String>,
+    /// Cached messages to announce ahead of the rest in the next IHAVEs.
+    urgent: HashSet<String>,
     pub duplicate_count: u64,
+    /// Deliveries and IHAVE outcomes over the last [`DELIVERY_WINDOW`].
+    pub delivery_window: DeliveryWindow,
+    /// Peers evicted to stay within `MeshConfig::max_known_peers`.
+    pub evicted_count: u64,
     pub backoff: HashMap<String, Instant>,
+    /// The last diverse first hops chosen.
+    first_hops: Vec<String>,
+    /// How often each peer has been chosen as a first hop.
+    first_hop_uses: HashMap<String, u64>,
+    /// Restored mesh peers and when they are pruned if still disconnected.
+    warm: HashMap<String, Instant>,
+    /// Restored mesh peers to announce with a graft on the next heartbeat.
+    pending_grafts: Vec<String>,
+    scorer: Arc<dyn PeerScorer>,
 }
 
 impl TopicMesh {
@@ -133,8 +436,110 @@ impl TopicMesh {
             mesh_peers: HashSet::new(),
             known_peers: HashMap::new(),
             message_cache: HashSet::new(),
+            urgent: HashSet::new(),
             duplicate_count: 0,
+            delivery_window: DeliveryWindow::default(),
+            evicted_count: 0,
             backoff: HashMap::new(),
+            first_hops: Vec::new(),
+            first_hop_uses: HashMap::new(),
+            warm: HashMap::new(),
+            pending_grafts: Vec::new(),
+            scorer: Arc::new(WeightedScore),
+        }
+    }
+
+    /// Score peers with `scorer` from now on.
+    pub fn set_scorer(&mut self, scorer: Arc<dyn PeerScorer>) {
+        self.scorer = scorer;
+    }
+
+    /// `peer`'s score as this mesh sees it.
+    pub fn peer_score(&self, peer: &MeshPeer) -> f32 {
+        self.scorer.score(peer, &self.config.score_weights)
+    }
+
+    /// The `limit` best-scored known peers with their scores, best first.
+    pub fn ranked_peers(&self, limit: usize) -> Vec<(String, f32)> {
+        let mut ranked: Vec<(String, f32)> = self
+            .known_peers
+            .values()
+            .map(|peer| (peer.id.clone(), self.peer_score(peer)))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(limit);
+        ranked
+    }
+
+    /// Capture membership, backoffs and scores for `restore`.
+    pub fn persist(&self) -> PersistedMesh {
+        let now = Instant::now();
+        let left = |until: Instant| until.checked_duration_since(now).filter(|d| !d.is_zero());
+        let mut mesh_peers: Vec<String> = self.mesh_peers.iter().cloned().collect();
+        mesh_peers.sort();
+        PersistedMesh {
+            mesh_peers,
+            peers: self
+                .known_peers
+                .values()
+                .map(|peer| PersistedPeer {
+                    id: peer.id.clone(),
+                    energy_score: peer.energy_score,
+                    conductivity: peer.conductivity,
+                    message_count: peer.message_count,
+                    penalty: peer.penalty,
+                    penalty_left: peer.penalty_until.and_then(left),
+                })
+                .collect(),
+            backoff: self
+                .backoff
+                .iter()
+                .filter_map(|(id, until)| Some((id.clone(), left(*until)?)))
+                .collect(),
+        }
+    }
+
+    /// Load state saved `downtime` ago.
+    ///
+    /// Backoffs and penalties lose the downtime and conductivity decays as if
+    /// the missed heartbeats had run. Peers come back disconnected. After a
+    /// short restart (up to [`MAX_WARM_START_AGE`]) previous mesh members
+    /// rejoin the mesh at once and are re-grafted on the next heartbeat; any
+    /// that have not reconnected within [`WARM_START_GRACE`] are pruned.
+    pub fn restore(&mut self, state: PersistedMesh, downtime: Duration) {
+        let now = Instant::now();
+        let missed =
+            downtime.as_secs_f32() / self.config.heartbeat_interval.as_secs_f32().max(1e-3);
+        let decay = 0.95f32.powf(missed.min(1_000.0));
+        for saved in state.peers {
+            let mut peer = MeshPeer::new(saved.id.clone(), saved.energy_score);
+            peer.conductivity = (saved.conductivity * decay).max(saved.conductivity.min(0.5));
+            peer.message_count = saved.message_count;
+            if let Some(left) = saved.penalty_left.and_then(|d| d.checked_sub(downtime)) {
+                peer.penalty = saved.penalty;
+                peer.penalty_until = Some(now + left);
+            }
+            self.known_peers.entry(saved.id).or_insert(peer);
+        }
+        self.evict_known_peers(None);
+        for (id, left) in state.backoff {
+            if let Some(left) = left.checked_sub(downtime).filter(|d| !d.is_zero()) {
+                self.backoff.insert(id, now + left);
+            }
+        }
+        if downtime > MAX_WARM_START_AGE {
+            return;
+        }
+        for id in state.mesh_peers {
+            if self.backoff.contains_key(&id) || !self.known_peers.contains_key(&id) {
+                continue;
+            }
+            if let Some(peer) = self.known_peers.get_mut(&id) {
+                peer.in_mesh = true;
+            }
+            self.mesh_peers.insert(id.clone());
+            self.warm.insert(id.clone(), now + WARM_START_GRACE);
+            self.pending_grafts.push(id);
         }
     }
 
@@ -173,10 +578,89 @@ impl TopicMesh {
         }
     }
 
+    /// Fold an RTT sample for a known peer into its smoothed RTT.
+    pub fn record_rtt(&mut self, id: &str, rtt: Duration) {
+        if let Some(peer) = self.known_peers.get_mut(id) {
+            peer.rtt = Some(
+                peer.rtt
+                    .map_or(rtt, |smoothed| smoothed.mul_f32(0.8) + rtt.mul_f32(0.2)),
+            );
+        }
+    }
+
     pub fn add_peer(&mut self, id: String, energy_score: f32) {
         self.known_peers
             .entry(id.clone())
-            .or_insert_with(|| MeshPeer::new(id, energy_score));
+            .or_insert_with(|| MeshPeer::new(id.clone(), energy_score));
+        self.evict_known_peers(Some(&id));
+    }
+
+    /// Connection hook: track a newly connected peer with an unknown score.
+    ///
+    /// Existing peers keep their score history.
+    pub fn peer_connected(&mut self, id: &str) {
+        let peer = self
+            .known_peers
+            .entry(id.to_string())
+            .or_insert_with(|| MeshPeer::new(id.to_string(), UNKNOWN_ENERGY_SCORE));
+        peer.connected = true;
+        peer.last_seen = Instant::now();
+        self.warm.remove(id);
+        self.evict_known_peers(Some(id));
+    }
+
+    /// Connection hook: as `peer_connected`, for a connection this node
+    /// dialed.
+    pub fn peer_dialed(&mut self, id: &str) {
+        self.peer_connected(id);
+        if let Some(peer) = self.known_peers.get_mut(id) {
+            peer.outbound = true;
+        }
+    }
+
+    /// Activity hook: refresh `last_seen` for a known peer.
+    pub fn mark_seen(&mut self, id: &str) {
+        if let Some(peer) = self.known_peers.get_mut(id) {
+            peer.last_seen = Instant::now();
+        }
+    }
+
+    /// Connection hook: the last connection to `id` closed.
+    ///
+    /// The peer is pruned from the mesh immediately and backed off, so the next
+    /// heartbeat does not try to graft it again.
+    pub fn peer_disconnected(&mut self, id: &str) {
+        if let Some(peer) = self.known_peers.get_mut(id) {
+            peer.connected = false;
+            peer.outbound = false;
+        }
+        self.handle_prune(id, DISCONNECT_BACKOFF);
+    }
+
+    /// Deduct `penalty` from a known peer's score for `duration`. Repeat
+    /// penalties keep the larger deduction and the later expiry.
+    pub fn penalize_peer(&mut self, id: &str, penalty: f32, duration: Duration) {
+        if let Some(peer) = self.known_peers.get_mut(id) {
+            let now = Instant::now();
+            let until = now + duration;
+            match peer.penalty_until {
+                Some(active) if now < active => {
+                    peer.penalty = peer.penalty.max(penalty);
+                    peer.penalty_until = Some(active.max(until));
+                }
+                _ => {
+                    peer.penalty = penalty;
+                    peer.penalty_until = Some(until);
+                }
+            }
+        }
+    }
+
+    /// Set the lasting reputation deduction of a known peer.
+    pub fn set_peer_reputation(&mut self, id: &str, deduction: f32) {
+        if let Some(peer) = self.known_peers.get_mut(id) {
+            peer.reputation = deduction;
+        }
     }
 
     pub fn update_peer_score(&mut self, id: &str, energy_score: f32) {
@@ -186,6 +670,60 @@ impl TopicMesh {
             .or_insert_with(|| MeshPeer::new(id.to_string(), energy_score));
         peer.energy_score = energy_score;
         peer.last_seen = Instant::now();
+        self.evict_known_peers(Some(id));
+    }
+
+    /// Evict peers while `known_peers` is over `MeshConfig::max_known_peers`:
+    /// lowest score first, then longest unseen. Mesh members and `keep`, the
+    /// peer just added, stay.
+    fn evict_known_peers(&mut self, keep: Option<&str>) {
+        let Some(max) = self.config.max_known_peers else {
+            return;
+        };
+        let excess = self.known_peers.len().saturating_sub(max);
+        if excess == 0 {
+            return;
+        }
+        let mut candidates: Vec<(f32, Instant, &String)> = self
+            .known_peers
+            .values()
+            .filter(|peer| Some(peer.id.as_str()) != keep && !self.mesh_peers.contains(&peer.id))
+            .map(|peer| (self.peer_score(peer), peer.last_seen, &peer.id))
+            .collect();
+        candidates.sort_by(|a, b| {
+            a.0.total_cmp(&b.0)
+                .then(a.1.cmp(&b.1))
+                .then_with(|| a.2.cmp(b.2))
+        });
+        let evicted: Vec<String> = candidates
+            .into_iter()
+            .take(excess)
+            .map(|(_, _, id)| id.clone())
+            .collect();
+        for id in evicted {
+            self.known_peers.remove(&id);
+            self.warm.remove(&id);
+            self.evicted_count += 1;
+        }
+    }
+
+    /// Note where a known peer came from. Unknown peers are not added.
+    pub fn record_origin(&mut self, id: &str, fact: OriginFact) {
+        if let Some(peer) = self.known_peers.get_mut(id) {
+            match fact {
+                OriginFact::Introducer(introducer) => peer.origin.introducer = Some(introducer),
+                OriginFact::Address(ip) => peer.origin.ip_prefix = Some(ip_prefix(ip)),
+                OriginFact::Zone(zone) => peer.origin.zone = zone,
+            }
+        }
+    }
+
+    /// Note the role `id` advertises. Unknown peers are not added; their
+    /// first status or connection does that.
+    pub fn update_peer_role(&mut self, id: &str, role: Option<NodeRole>) {
+        if let Some(peer) = self.known_peers.get_mut(id) {
+            peer.role = role;
+        }
     }
 
     pub fn record_message(&mut self, peer_id: &str, msg_id: &str) {
@@ -196,10 +734,21 @@ impl TopicMesh {
             peer.conductivity = (peer.conductivity + 0.1 * pressure_grad).min(10.0);
         }
 
+        let counts = self.delivery_window.at(Instant::now());
         if self.message_cache.contains(msg_id) {
             self.duplicate_count += 1;
+            counts.duplicates += 1;
         } else {
             self.message_cache.insert(msg_id.to_string());
+            counts.first_deliveries += 1;
+        }
+    }
+
+    /// Announce cached `msg_id` before other messages in the next IHAVE
+    /// gossip, e.g. because it carries a high send priority.
+    pub fn mark_urgent(&mut self, msg_id: &str) {
+        if self.message_cache.contains(msg_id) {
+            self.urgent.insert(msg_id.to_string());
         }
     }
 
@@ -208,7 +757,7 @@ impl TopicMesh {
             .mesh_peers
             .iter()
             .filter_map(|id| self.known_peers.get(id))
-            .map(|p| p.score())
+            .map(|p| self.peer_score(p))
             .collect();
 
         if scores.is_empty() {
@@ -224,6 +773,59 @@ impl TopicMesh {
         }
     }
 
+    /// How many mesh members must be outbound.
+    fn outbound_quota(&self) -> usize {
+        let quota = (self.config.d as f32 * self.config.outbound_fraction.clamp(0.0, 1.0)).ceil();
+        (quota as usize).min(self.config.d_low)
+    }
+
+    fn is_outbound(&self, id: &str) -> bool {
+        self.known_peers.get(id).is_some_and(|peer| peer.outbound)
+    }
+
+    fn mesh_outbound(&self) -> usize {
+        self.mesh_peers
+            .iter()
+            .filter(|id| self.is_outbound(id))
+            .count()
+    }
+
+    /// Mesh members with their current scores.
+    fn scored_mesh_peers(&self) -> Vec<(String, f32)> {
+        self.mesh_peers
+            .iter()
+            .filter_map(|id| {
+                self.known_peers
+                    .get(id)
+                    .map(|p| (id.clone(), self.peer_score(p)))
+            })
+            .collect()
+    }
+
+    /// The `k` best-scoring peers outside the mesh and not backed off, best
+    /// first. Linear in the number of known peers.
+    fn top_candidates(&self, k: usize) -> Vec<(String, f32)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut scored: Vec<(&String, f32)> = self
+            .known_peers
+            .iter()
+            .filter(|(id, _)| !self.mesh_peers.contains(*id) && !self.backoff.contains_key(*id))
+            .map(|(id, peer)| (id, self.peer_score(peer)))
+            .collect();
+        let by_score_desc = |a: &(&String, f32), b: &(&String, f32)| b.1.total_cmp(&a.1);
+        if scored.len() > k {
+            scored.select_nth_unstable_by(k - 1, by_score_desc);
+            scored.truncate(k);
+        }
+        scored.sort_by(by_score_desc);
+        scored
+            .into_iter()
+            .map(|(id, score)| (id.clone(), score))
+            .collect()
+    }
+
     pub fn heartbeat(&mut self) -> Vec<(String, MeshControl)> {
         let mut controls = Vec::new();
         let mut rng = rng();
@@ -234,15 +836,37 @@ impl TopicMesh {
 
         let now = Instant::now();
         self.backoff.retain(|_, expiry| *expiry > now);
+        // The cap may have shrunk with the config.
+        self.evict_known_peers(None);
+
+        for id in std::mem::take(&mut self.pending_grafts) {
+            if self.mesh_peers.contains(&id) {
+                controls.push((
+                    id,
+                    MeshControl::Graft {
+                        topic: self.topic.clone(),
+                    },
+                ));
+            }
+        }
+        let stale_warm: HashSet<String> = self
+            .warm
+            .iter()
+            .filter(|(_, deadline)| **deadline <= now)
+            .map(|(id, _)| id.clone())
+            .collect();
+        self.warm.retain(|id, _| !stale_warm.contains(id));
 
         let to_prune: Vec<String> = self
             .mesh_peers
             .iter()
             .filter(|id| {
-                self.known_peers
-                    .get(*id)
-                    .map(|p| p.score() < self.config.prune_threshold)
-                    .unwrap_or(true)
+                stale_warm.contains(*id)
+                    || self
+                        .known_peers
+                        .get(*id)
+                        .map(|p| self.peer_score(p) < self.config.prune_threshold)
+                        .unwrap_or(true)
             })
             .cloned()
             .collect();
@@ -262,14 +886,27 @@ impl TopicMesh {
             self.backoff.insert(id, now + Duration::from_secs(60));
         }
 
-        while self.mesh_peers.len() > self.config.d_high {
-            let lowest = self
-                .mesh_peers
-                .iter()
-                .filter_map(|id| self.known_peers.get(id).map(|p| (id.clone(), p.score())))
-                .min_by(|a, b| a.1.total_cmp(&b.1));
-
-            if let Some((id, _)) = lowest {
+        let quota = self.outbound_quota();
+        if self.mesh_peers.len() > self.config.d_high {
+            let mut scored = self.scored_mesh_peers();
+            scored.sort_by(|a, b| a.1.total_cmp(&b.1));
+            let excess = self.mesh_peers.len() - self.config.d_high;
+            // The weakest go first, except outbound peers the quota needs.
+            let mut outbound = self.mesh_outbound();
+            let mut victims = Vec::with_capacity(excess);
+            for (id, _) in scored {
+                if victims.len() == excess {
+                    break;
+                }
+                if self.is_outbound(&id) {
+                    if outbound <= quota {
+                        continue;
+                    }
+                    outbound -= 1;
+                }
+                victims.push(id);
+            }
+            for id in victims {
                 self.mesh_peers.remove(&id);
                 if let Some(peer) = self.known_peers.get_mut(&id) {
                     peer.in_mesh = false;
@@ -282,59 +919,43 @@ impl TopicMesh {
                     },
                 ));
                 self.backoff.insert(id, now + Duration::from_secs(60));
-            } else {
-                break;
             }
         }
 
-        while self.mesh_peers.len() < self.config.d_low {
-            let candidate = self
-                .known_peers
-                .iter()
-                .filter(|(id, peer)| {
-                    !self.mesh_peers.contains(*id)
-                        && !self.backoff.contains_key(*id)
-                        && peer.score() >= self.config.graft_threshold
-                })
-                .max_by(|a, b| a.1.score().total_cmp(&b.1.score()));
+        // Grafting, opportunistic grafting and the swap below each take the
+        // best remaining candidates, so only the top few are ever needed.
+        let wanted = self.config.d_low.saturating_sub(self.mesh_peers.len()) + 3;
+        let mut candidates = self.top_candidates(wanted).into_iter().peekable();
 
-            if let Some((id, _)) = candidate {
-                let id = id.clone();
-                self.mesh_peers.insert(id.clone());
-                if let Some(peer) = self.known_peers.get_mut(&id) {
-                    peer.in_mesh = true;
-                }
-                controls.push((
-                    id,
-                    MeshControl::Graft {
-                        topic: self.topic.clone(),
-                    },
-                ));
-            } else {
+        while self.mesh_peers.len() < self.config.d_low {
+            let Some((id, _)) =
+                candidates.next_if(|(_, score)| *score >= self.config.graft_threshold)
+            else {
                 break;
+            };
+            self.mesh_peers.insert(id.clone());
+            if let Some(peer) = self.known_peers.get_mut(&id) {
+                peer.in_mesh = true;
             }
+            controls.push((
+                id,
+                MeshControl::Graft {
+                    topic: self.topic.clone(),
+                },
+            ));
         }
 
         let median = self.mesh_median_score();
         if median < self.config.opportunistic_graft_threshold
             && self.mesh_peers.len() < self.config.d_high
         {
-            let mut candidates: Vec<_> = self
-                .known_peers
-                .iter()
-                .filter(|(id, peer)| {
-                    !self.mesh_peers.contains(*id)
-                        && !self.backoff.contains_key(*id)
-                        && peer.score() > median
-                })
-                .map(|(id, peer)| (id.clone(), peer.score()))
-                .collect();
-            candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
-
-            for (id, _) in candidates.into_iter().take(2) {
+            for _ in 0..2 {
                 if self.mesh_peers.len() >= self.config.d_high {
                     break;
                 }
+                let Some((id, _)) = candidates.next_if(|(_, score)| *score > median) else {
+                    break;
+                };
                 self.mesh_peers.insert(id.clone());
                 if let Some(peer) = self.known_peers.get_mut(&id) {
                     peer.in_mesh = true;
@@ -349,25 +970,17 @@ impl TopicMesh {
         }
 
         if self.mesh_peers.len() >= self.config.d_low {
+            let spare_outbound = self.mesh_outbound() > quota;
             let weakest = self
-                .mesh_peers
-                .iter()
-                .filter_map(|id| self.known_peers.get(id).map(|p| (id.clone(), p.score())))
+                .scored_mesh_peers()
+                .into_iter()
+                .filter(|(id, _)| spare_outbound || !self.is_outbound(id))
                 .min_by(|a, b| a.1.total_cmp(&b.1));
 
             if let Some((weak_id, weak_score)) = weakest {
-                let best_candidate = self
-                    .known_peers
-                    .iter()
-                    .filter(|(id, peer)| {
-                        !self.mesh_peers.contains(*id)
-                            && !self.backoff.contains_key(*id)
-                            && peer.score() > weak_score + 0.1
-                    })
-                    .max_by(|a, b| a.1.score().total_cmp(&b.1.score()));
-
-                if let Some((best_id, _)) = best_candidate {
-                    let best_id = best_id.clone();
+                if let Some((best_id, _)) =
+                    candidates.next_if(|(_, score)| *score > weak_score + 0.1)
+                {
                     self.mesh_peers.remove(&weak_id);
                     if let Some(peer) = self.known_peers.get_mut(&weak_id) {
                         peer.in_mesh = false;
@@ -396,30 +1009,79 @@ impl TopicMesh {
             }
         }
 
-        let non_mesh: Vec<_> = self
+        // Top up outbound peers the quota still lacks, even past `d_high`.
+        let missing = quota.saturating_sub(self.mesh_outbound());
+        if missing > 0 {
+            let mut dialed: Vec<(String, f32)> = self
+                .known_peers
+                .iter()
+                .filter(|(id, peer)| {
+                    peer.outbound
+                        && !self.mesh_peers.contains(*id)
+                        && !self.backoff.contains_key(*id)
+                })
+                .map(|(id, peer)| (id.clone(), self.peer_score(peer)))
+                .filter(|(_, score)| *score >= self.config.graft_threshold)
+                .collect();
+            dialed.sort_by(|a, b| b.1.total_cmp(&a.1));
+            for (id, _) in dialed.into_iter().take(missing) {
+                self.mesh_peers.insert(id.clone());
+                if let Some(peer) = self.known_peers.get_mut(&id) {
+                    peer.in_mesh = true;
+                }
+                controls.push((
+                    id,
+                    MeshControl::Graft {
+                        topic: self.topic.clone(),
+                    },
+                ));
+            }
+        }
+
+        let ihave_targets: Vec<String> = self
             .known_peers
             .keys()
             .filter(|id| !self.mesh_peers.contains(*id))
-            .cloned()
-            .collect();
-
-        let ihave_targets: Vec<_> = non_mesh
-            .choose_multiple(&mut rng, self.config.d_lazy.min(non_mesh.len()))
+            .choose_multiple(&mut rng, self.config.d_lazy)
+            .into_iter()
             .cloned()
             .collect();
 
         if !self.message_cache.is_empty() && !ihave_targets.is_empty() {
-            let recent_msgs: Vec<_> = self.message_cache.iter().take(10).cloned().collect();
+            // Urgent messages lead, so a tight byte budget still carries them.
+            let max_ids = self.config.max_ihave_ids;
+            let mut ids: Vec<String> = self.urgent.iter().take(max_ids).cloned().collect();
+            ids.extend(
+                self.message_cache
+                    .iter()
+                    .filter(|id| !self.urgent.contains(*id))
+                    .take(max_ids - ids.len())
+                    .cloned(),
+            );
 
+            let mut budget = self.config.max_ihave_bytes;
+            let mut announced = 0;
             for target in ihave_targets {
+                let fit = (0..=ids.len())
+                    .rev()
+                    .find(|&n| ihave_size(&self.topic, &ids[..n]) <= budget)
+                    .unwrap_or(0);
+                if fit == 0 {
+                    break;
+                }
+                budget -= ihave_size(&self.topic, &ids[..fit]);
+                announced = announced.max(fit);
                 controls.push((
                     target,
                     MeshControl::IHave {
                         topic: self.topic.clone(),
-                        message_ids: recent_msgs.clone(),
+                        message_ids: ids[..fit].to_vec(),
                     },
                 ));
             }
+            for id in &ids[..announced] {
+                self.urgent.remove(id);
+            }
         }
 
         controls
@@ -430,7 +1092,7 @@ impl TopicMesh {
             return false;
         }
         if let Some(peer) = self.known_peers.get(peer_id) {
-            if peer.score() >= self.config.graft_threshold
+            if self.peer_score(peer) >= self.config.graft_threshold
                 && self.mesh_peers.len() < self.config.d_high
             {
                 self.mesh_peers.insert(peer_id.to_string());
@@ -478,10 +1140,12 @@ impl TopicMesh {
                 None
             }
             MeshControl::IHave { message_ids, .. } => {
-                let missing: Vec<_> = message_ids
+                let (missing, held): (Vec<_>, Vec<_>) = message_ids
                     .into_iter()
-                    .filter(|id| !self.message_cache.contains(id))
-                    .collect();
+                    .partition(|id| !self.message_cache.contains(id));
+                let counts = self.delivery_window.at(Instant::now());
+                counts.ihave_hits += held.len() as u64;
+                counts.ihave_misses += missing.len() as u64;
 
                 if !missing.is_empty() {
                     Some(MeshControl::IWant {
@@ -499,7 +1163,7 @@ impl TopicMesh {
         if is_own_message {
             self.known_peers
                 .iter()
-                .filter(|(_, peer)| peer.score() >= self.config.graft_threshold)
+                .filter(|(_, peer)| self.peer_score(peer) >= self.config.graft_threshold)
                 .map(|(id, _)| id.clone())
                 .collect()
         } else {
@@ -507,35 +1171,313 @@ impl TopicMesh {
         }
     }
 
+    /// `get_forward_targets`, best first for `class` once RTT data exists.
+    /// Relayed messages then only go to the best `d` of the mesh; own
+    /// messages still reach every candidate.
+    pub fn forward_targets(&self, is_own_message: bool, class: TrafficClass) -> Vec<String> {
+        let mut targets = self.get_forward_targets(is_own_message);
+        let measured = targets
+            .iter()
+            .any(|id| self.known_peers.get(id).is_some_and(|p| p.rtt.is_some()));
+        if !measured {
+            return targets;
+        }
+        let weights = match class {
+            TrafficClass::Latency => &self.config.latency_forwarding,
+            TrafficClass::Bulk => &self.config.bulk_forwarding,
+        };
+        let rank = |id: &String| {
+            self.known_peers
+                .get(id)
+                .map_or(f32::NEG_INFINITY, |p| p.forward_score(weights))
+        };
+        targets.sort_by(|a, b| rank(b).total_cmp(&rank(a)).then_with(|| a.cmp(b)));
+        if !is_own_message {
+            targets.truncate(self.config.d.max(1));
+        }
+        targets
+    }
+
+    /// Choose up to `MeshConfig::diverse_first_hops` connected peers for an
+    /// own critical message. Each pick adds the most introducers, address
+    /// prefixes and zones not yet covered; ties go to the peer chosen least
+    /// often so far, so the same neighbours are not always used, then to the
+    /// best score. Peers serving a penalty are skipped.
+    pub fn select_first_hops(&mut self) -> Vec<String> {
+        let k = self.config.diverse_first_hops;
+        let now = Instant::now();
+        let known = &self.known_peers;
+        self.first_hop_uses.retain(|id, _| known.contains_key(id));
+        let mut candidates: Vec<(&MeshPeer, f32)> = known
+            .values()
+            .filter(|peer| peer.connected && peer.penalty_until.is_none_or(|until| until <= now))
+            .map(|peer| (peer, self.peer_score(peer)))
+            .collect();
+        let mut covered: [HashSet<&str>; 3] = Default::default();
+        let mut chosen = Vec::new();
+        while chosen.len() < k && !candidates.is_empty() {
+            let novelty = |peer: &MeshPeer| {
+                peer.origin
+                    .facts()
+                    .iter()
+                    .zip(&covered)
+                    .filter(|(fact, seen)| fact.is_some_and(|fact| !seen.contains(fact)))
+                    .count()
+            };
+            let uses = |peer: &MeshPeer| self.first_hop_uses.get(&peer.id).copied().unwrap_or(0);
+            let (best, _) = candidates
+                .iter()
+                .enumerate()
+                .max_by(|(_, (a, a_score)), (_, (b, b_score))| {
+                    novelty(a)
+                        .cmp(&novelty(b))
+                        .then(uses(b).cmp(&uses(a)))
+                        .then(a_score.total_cmp(b_score))
+                        .then_with(|| b.id.cmp(&a.id))
+                })
+                .expect("candidates is not empty");
+            let (peer, _) = candidates.swap_remove(best);
+            for (fact, seen) in peer.origin.facts().into_iter().zip(&mut covered) {
+                seen.extend(fact);
+            }
+            chosen.push(peer.id.clone());
+        }
+        for id in &chosen {
+            *self.first_hop_uses.entry(id.clone()).or_default() += 1;
+        }
+        self.first_hops = chosen.clone();
+        chosen
+    }
+
+    /// Targets for an own critical message: the diverse first hops, which
+    /// get it whatever their score, then the usual flood targets.
+    pub fn critical_targets(&mut self, class: TrafficClass) -> Vec<String> {
+        let mut targets = self.select_first_hops();
+        for id in self.forward_targets(true, class) {
+            if !targets.contains(&id) {
+                targets.push(id);
+            }
+        }
+        targets
+    }
+
     pub fn stats(&self) -> MeshStats {
         let scores: Vec<f32> = self
             .mesh_peers
             .iter()
             .filter_map(|id| self.known_peers.get(id))
-            .map(|p| p.score())
+            .map(|p| self.peer_score(p))
             .collect();
 
+        let outbound_peers = self.mesh_outbound();
+        let deliveries = self.delivery_window.counts(Instant::now());
         MeshStats {
             mesh_size: self.mesh_peers.len(),
+            inbound_peers: self.mesh_peers.len() - outbound_peers,
+            outbound_peers,
             known_peers: self.known_peers.len(),
             median_score: self.mesh_median_score(),
             min_score: scores.iter().cloned().fold(f32::INFINITY, f32::min),
             max_score: scores.iter().cloned().fold(f32::NEG_INFINITY, f32::max),
             messages_cached: self.message_cache.len(),
             duplicate_count: self.duplicate_count,
+            deliveries,
+            gossip_efficiency: deliveries.efficiency(),
+            evicted_count: self.evicted_count,
             backoff_count: self.backoff.len(),
+            first_hops: self.first_hops.clone(),
+        }
+    }
+
+    /// Everything the mesh knows, in serializable form.
+    pub fn diagnostics(&self) -> MeshDiagnostics {
+        let now = Instant::now();
+        let left = |until: Instant| until.checked_duration_since(now).filter(|d| !d.is_zero());
+        let sorted = |ids: &mut dyn Iterator<Item = &String>| {
+            let mut ids: Vec<String> = ids.cloned().collect();
+            ids.sort();
+            ids
+        };
+        let mut peers: Vec<PeerDiagnostics> = self
+            .known_peers
+            .values()
+            .map(|peer| PeerDiagnostics {
+                id: peer.id.clone(),
+                score: self.peer_score(peer),
+                energy_score: peer.energy_score,
+                conductivity: peer.conductivity,
+                pressure: peer.pressure,
+                message_count: peer.message_count,
+                since_seen: now.saturating_duration_since(peer.last_seen),
+                in_mesh: peer.in_mesh,
+                connected: peer.connected,
+                outbound: peer.outbound,
+                penalty: peer.penalty,
+                penalty_left: peer.penalty_until.and_then(left),
+                reputation: peer.reputation,
+                role: peer.role,
+                rtt: peer.rtt,
+            })
+            .collect();
+        peers.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut backoff: Vec<(String, Duration)> = self
+            .backoff
+            .iter()
+            .filter_map(|(id, until)| Some((id.clone(), left(*until)?)))
+            .collect();
+        backoff.sort();
+        MeshDiagnostics {
+            topic: self.topic.clone(),
+            local_pressure: self.local_pressure,
+            pulse_phase: self.pulse_phase,
+            stats: self.stats(),
+            mesh_peers: sorted(&mut self.mesh_peers.iter()),
+            peers,
+            backoff,
+            warm: sorted(&mut self.warm.keys()),
+            urgent: self.urgent.len(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Approximate encoded size of an IHAVE carrying `ids` for `topic`.
+fn ihave_size(topic: &str, ids: &[String]) -> usize {
+    // Each id is quoted and comma-separated.
+    IHAVE_OVERHEAD + topic.len() + ids.iter().map(|id| id.len() + 3).sum::<usize>()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct MeshStats {
     pub mesh_size: usize,
+    /// Mesh members on connections the peer dialed.
+    pub inbound_peers: usize,
+    /// Mesh members on connections this node dialed.
+    pub outbound_peers: usize,
     pub known_peers: usize,
     pub median_score: f32,
     pub min_score: f32,
     pub max_score: f32,
     pub messages_cached: usize,
+    /// Duplicates since the node started; `deliveries` has recent ones.
     pub duplicate_count: u64,
+    /// Counts over the last [`DELIVERY_WINDOW`].
+    #[serde(default)]
+    pub deliveries: DeliveryCounts,
+    /// Useful deliveries over all received in `deliveries`, if any arrived.
+    #[serde(default)]
+    pub gossip_efficiency: Option<f32>,
+    /// Peers evicted so far to stay within `MeshConfig::max_known_peers`.
+    #[serde(default)]
+    pub evicted_count: u64,
     pub backoff_count: usize,
+    /// The diverse first hops last chosen for an own critical message.
+    #[serde(default)]
+    pub first_hops: Vec<String>,
+}
+
+/// Gossip deliveries and IHAVE outcomes over a window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryCounts {
+    /// Messages seen for the first time.
+    pub first_deliveries: u64,
+    pub duplicates: u64,
+    /// Ids in received IHAVEs that were already cached.
+    pub ihave_hits: u64,
+    /// Ids in received IHAVEs that were missing, and so asked for with IWANT.
+    pub ihave_misses: u64,
+}
+
+impl DeliveryCounts {
+    /// First deliveries over all deliveries, or `None` before any.
+    pub fn efficiency(&self) -> Option<f32> {
+        let total = self.first_deliveries + self.duplicates;
+        (total > 0).then(|| self.first_deliveries as f32 / total as f32)
+    }
+
+    fn add(&mut self, other: &DeliveryCounts) {
+        self.first_deliveries += other.first_deliveries;
+        self.duplicates += other.duplicates;
+        self.ihave_hits += other.ihave_hits;
+        self.ihave_misses += other.ihave_misses;
+    }
+}
+
+/// [`DeliveryCounts`] kept in short buckets, so the last
+/// [`DELIVERY_WINDOW`] can be summed without storing every event.
+#[derive(Debug, Clone, Default)]
+pub struct DeliveryWindow {
+    buckets: VecDeque<(Instant, DeliveryCounts)>,
+}
+
+impl DeliveryWindow {
+    /// The counts to add events at `now` to. Buckets past the window are
+    /// dropped.
+    pub fn at(&mut self, now: Instant) -> &mut DeliveryCounts {
+        while self
+            .buckets
+            .front()
+            .is_some_and(|(start, _)| now.saturating_duration_since(*start) >= DELIVERY_WINDOW)
+        {
+            self.buckets.pop_front();
+        }
+        let open = self
+            .buckets
+            .back()
+            .is_some_and(|(start, _)| now.saturating_duration_since(*start) < DELIVERY_BUCKET);
+        if !open {
+            self.buckets.push_back((now, DeliveryCounts::default()));
+        }
+        &mut self.buckets.back_mut().expect("bucket just ensured").1
+    }
+
+    /// Counts of the buckets opened within the window before `now`.
+    pub fn counts(&self, now: Instant) -> DeliveryCounts {
+        let mut total = DeliveryCounts::default();
+        for (_, counts) in self
+            .buckets
+            .iter()
+            .filter(|(start, _)| now.saturating_duration_since(*start) < DELIVERY_WINDOW)
+        {
+            total.add(counts);
+        }
+        total
+    }
+}
+
+/// One known peer as seen at a point in time, for diagnostics. Deadlines
+/// and timestamps become durations relative to that point.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerDiagnostics {
+    pub id: String,
+    pub score: f32,
+    pub energy_score: f32,
+    pub conductivity: f32,
+    pub pressure: f32,
+    pub message_count: u64,
+    pub since_seen: Duration,
+    pub in_mesh: bool,
+    pub connected: bool,
+    pub outbound: bool,
+    pub penalty: f32,
+    pub penalty_left: Option<Duration>,
+    pub reputation: f32,
+    pub role: Option<NodeRole>,
+    pub rtt: Option<Duration>,
+}
+
+/// Serializable view of a whole [`TopicMesh`], for support bundles. Peers
+/// and ids are sorted so two dumps diff cleanly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeshDiagnostics {
+    pub topic: String,
+    pub local_pressure: f32,
+    pub pulse_phase: f32,
+    pub stats: MeshStats,
+    pub mesh_peers: Vec<String>,
+    pub peers: Vec<PeerDiagnostics>,
+    /// Backoffs still running, with the time left.
+    pub backoff: Vec<(String, Duration)>,
+    /// Restored peers still waiting to reconnect.
+    pub warm: Vec<String>,
+    pub urgent: usize,
 }
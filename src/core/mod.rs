@@ -1,7 +1,13 @@
 pub mod mesh;
 
 pub use hypha_core::{
-    BasicSensor, BatteryMetabolism, Bid, Capability, EnergyFacts, EnergyStatus, Metabolism,
-    MockMetabolism, PowerMode, Task, VirtualSensor,
+    Attestation, BasicSensor, BatteryMetabolism, Bid, BidPrice, Capability, EnergyFacts,
+    EnergyStatus, FixedLocation, HarvestingMetabolism, LocationSensor, Metabolism, MockMetabolism,
+    NodeRole, NodeState, PowerMode, ReadingSummary, ResultPayload, SensorReading, Task, TaskResult,
+    VirtualSensor,
+};
+pub use mesh::{
+    MeshConfig, MeshControl, MeshPeer, MeshStats, PersistedMesh, PersistedPeer, TopicMesh,
+    DISCONNECT_BACKOFF, MAX_WARM_START_AGE, PRESSURE_SPIKE_THRESHOLD, UNKNOWN_ENERGY_SCORE,
+    WARM_START_GRACE,
 };
-pub use mesh::{MeshConfig, MeshControl, MeshPeer, MeshStats, TopicMesh, PRESSURE_SPIKE_THRESHOLD};
@@ -0,0 +1,221 @@
+//! Damping for pressure-driven heartbeat acceleration.
+//!
+//! Under local pressure the degradation ladder shortens the heartbeat up to
+//! 4x. When a whole swarm sees the same load, every node speeds up at once;
+//! the extra status adverts and mesh control raise everyone's pressure
+//! further, and the swarm feeds a storm. A [`HeartbeatDamper`] breaks the
+//! loop in three ways:
+//!
+//! - It folds each heartbeat's inbound message rate and duplicate ratio into
+//!   moving averages. While either is past its burst threshold the network
+//!   is already saturated, and the heartbeat does not accelerate at all.
+//! - Otherwise it gives back a random share, up to `jitter`, of each
+//!   acceleration, so that neighbours seeing the same pressure drift apart
+//!   instead of beating in step.
+//! - Status adverts and mesh control that the heartbeat originates draw on a
+//!   token bucket. Its rate is the swarm-wide `max_control_per_sec` shared
+//!   among the known peers, so the aggregate stays bounded however many
+//!   nodes speed up.
+
+use crate::bridge::RateLimiter;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DampingConfig {
+    /// Off by default: pressure accelerates the heartbeat undamped.
+    pub enabled: bool,
+    /// Weight of each heartbeat in the moving averages.
+    pub smoothing: f32,
+    /// Inbound messages per second at which the network counts as bursting.
+    pub burst_rate: f32,
+    /// Share of inbound messages that are duplicates at which the network
+    /// counts as bursting.
+    pub burst_duplicate_ratio: f32,
+    /// Largest share of an acceleration given back at random.
+    pub jitter: f32,
+    /// Control messages per second the whole swarm may originate.
+    pub max_control_per_sec: f32,
+    /// Each node's share never drops below this.
+    pub min_control_per_sec: f32,
+}
+
+impl Default for DampingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            smoothing: 0.3,
+            burst_rate: 200.0,
+            burst_duplicate_ratio: 0.5,
+            jitter: 0.5,
+            max_control_per_sec: 100.0,
+            min_control_per_sec: 0.5,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct HeartbeatDamper {
+    pub config: DampingConfig,
+    messages: u32,
+    duplicates: u32,
+    last: Option<Instant>,
+    rate: f32,
+    duplicate_ratio: f32,
+    bursting: bool,
+    control: Option<(f32, RateLimiter)>,
+}
+
+impl Default for HeartbeatDamper {
+    fn default() -> Self {
+        Self::new(DampingConfig::default())
+    }
+}
+
+impl HeartbeatDamper {
+    pub fn new(config: DampingConfig) -> Self {
+        Self {
+            config,
+            messages: 0,
+            duplicates: 0,
+            last: None,
+            rate: 0.0,
+            duplicate_ratio: 0.0,
+            bursting: false,
+            control: None,
+        }
+    }
+
+    /// Count an inbound gossip message.
+    pub fn record_message(&mut self, duplicate: bool) {
+        self.messages = self.messages.saturating_add(1);
+        if duplicate {
+            self.duplicates = self.duplicates.saturating_add(1);
+        }
+    }
+
+    /// Fold in the messages counted since the last heartbeat. Returns the
+    /// new burst state when it changed.
+    pub fn observe(&mut self, now: Instant) -> Option<bool> {
+        let messages = std::mem::take(&mut self.messages);
+        let duplicates = std::mem::take(&mut self.duplicates);
+        let last = self.last.replace(now)?;
+        let elapsed = now.saturating_duration_since(last).as_secs_f32();
+        if elapsed <= 0.0 {
+            return None;
+        }
+        let alpha = self.config.smoothing.clamp(0.0, 1.0);
+        self.rate += alpha * (messages as f32 / elapsed - self.rate);
+        if messages > 0 {
+            let ratio = duplicates as f32 / messages as f32;
+            self.duplicate_ratio += alpha * (ratio - self.duplicate_ratio);
+        }
+        let bursting = self.rate >= self.config.burst_rate
+            || self.duplicate_ratio >= self.config.burst_duplicate_ratio;
+        (bursting != self.bursting).then(|| {
+            self.bursting = bursting;
+            bursting
+        })
+    }
+
+    /// Whether the network currently counts as bursting.
+    pub fn bursting(&self) -> bool {
+        self.config.enabled && self.bursting
+    }
+
+    /// Average inbound messages per second and duplicate ratio so far.
+    pub fn averages(&self) -> (f32, f32) {
+        (self.rate, self.duplicate_ratio)
+    }
+
+    /// The heartbeat period to use given the rung's `base` period and the
+    /// `accelerated` one pressure asks for. `roll` is uniform in `[0, 1)`.
+    pub fn period(&self, base: Duration, accelerated: Duration, roll: f32) -> Duration {
+        if !self.config.enabled || accelerated >= base {
+            return accelerated;
+        }
+        if self.bursting {
+            return base;
+        }
+        let share = (self.config.jitter * roll).clamp(0.0, 1.0);
+        accelerated + (base - accelerated).mul_f32(share)
+    }
+
+    /// Take a token for one control message, with `peers` other nodes
+    /// sharing the swarm-wide budget.
+    pub fn allow_control(&mut self, peers: usize, now: Instant) -> bool {
+        if !self.config.enabled {
+            return true;
+        }
+        let rate = (self.config.max_control_per_sec / (peers + 1) as f32)
+            .max(self.config.min_control_per_sec);
+        match &mut self.control {
+            Some((current, limiter)) if *current == rate => limiter.allow(now),
+            _ => {
+                let mut limiter = RateLimiter::new(rate, rate.ceil() as u32);
+                let allowed = limiter.allow(now);
+                self.control = Some((rate, limiter));
+                allowed
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn damper() -> HeartbeatDamper {
+        HeartbeatDamper::new(DampingConfig {
+            enabled: true,
+            smoothing: 1.0,
+            ..DampingConfig::default()
+        })
+    }
+
+    #[test]
+    fn bursts_hold_the_base_period_and_jitter_spreads_the_rest() {
+        let mut damper = damper();
+        let base = Duration::from_secs(1);
+        let fast = Duration::from_millis(250);
+        let t0 = Instant::now();
+        assert_eq!(damper.observe(t0), None);
+
+        assert_eq!(damper.period(base, fast, 0.0), fast);
+        assert_eq!(damper.period(base, fast, 1.0), Duration::from_millis(625));
+        assert_eq!(damper.period(base, base, 0.5), base);
+
+        // Mostly duplicates: the network is saturated.
+        for i in 0..10 {
+            damper.record_message(i % 4 != 0);
+        }
+        assert_eq!(damper.observe(t0 + base), Some(true));
+        assert!(damper.bursting());
+        assert_eq!(damper.period(base, fast, 0.0), base);
+
+        // A flood of fresh messages also counts.
+        for _ in 0..300 {
+            damper.record_message(false);
+        }
+        assert_eq!(damper.observe(t0 + base * 2), None);
+        assert_eq!(damper.observe(t0 + base * 3), Some(false));
+        assert_eq!(damper.period(base, fast, 0.0), fast);
+
+        let off = HeartbeatDamper::default();
+        assert_eq!(off.period(base, fast, 1.0), fast);
+    }
+
+    #[test]
+    fn control_budget_is_shared_among_peers() {
+        let mut damper = damper();
+        let now = Instant::now();
+        let sent = |damper: &mut HeartbeatDamper, peers| {
+            (0..100)
+                .filter(|_| damper.allow_control(peers, now))
+                .count()
+        };
+        assert_eq!(sent(&mut damper, 9), 10);
+        assert_eq!(sent(&mut damper, 999), 1);
+        assert_eq!(sent(&mut damper, 999), 0);
+        assert_eq!(sent(&mut HeartbeatDamper::default(), 999), 100);
+    }
+}
@@ -0,0 +1,114 @@
+//! Deferred verification of status chatter.
+//!
+//! Checking the delegations and attestation in every status advert costs a
+//! signature verification or more per message, which adds up during a
+//! gossip storm. With a [`VerifyQueue`] enabled, authenticated adverts wait
+//! in a bounded queue and are verified in batches at the node's pulse peaks;
+//! a batch shrinks with the node's energy. When the queue is full the oldest
+//! advert is dropped unverified: its sender advertises again soon. Spikes,
+//! mesh control and every other topic are still verified on arrival.
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyQueueConfig {
+    /// Off by default: every advert is verified on arrival.
+    pub enabled: bool,
+    /// Most items waiting at once.
+    pub capacity: usize,
+    /// Items verified per pulse peak at full energy.
+    pub batch: usize,
+}
+
+impl Default for VerifyQueueConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capacity: 128,
+            batch: 32,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct VerifyQueue<T> {
+    pub config: VerifyQueueConfig,
+    pending: VecDeque<T>,
+    /// Items dropped unverified because the queue was full.
+    pub dropped: u64,
+}
+
+impl<T> Default for VerifyQueue<T> {
+    fn default() -> Self {
+        Self::new(VerifyQueueConfig::default())
+    }
+}
+
+impl<T> VerifyQueue<T> {
+    pub fn new(config: VerifyQueueConfig) -> Self {
+        Self {
+            config,
+            pending: VecDeque::new(),
+            dropped: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    /// Queue `item`, returning the oldest item if the queue overflowed.
+    pub fn push(&mut self, item: T) -> Option<T> {
+        self.pending.push_back(item);
+        if self.pending.len() <= self.config.capacity {
+            return None;
+        }
+        self.dropped += 1;
+        self.pending.pop_front()
+    }
+
+    /// The next items to verify at energy score `energy`, oldest first: the
+    /// configured batch scaled by energy, but at least one.
+    pub fn take_batch(&mut self, energy: f32) -> Vec<T> {
+        let size = (self.config.batch as f32 * energy.clamp(0.0, 1.0)).ceil() as usize;
+        let size = size.max(1).min(self.pending.len());
+        self.pending.drain(..size).collect()
+    }
+
+    /// Keep only the items `keep` accepts, e.g. to drop a departed peer's.
+    pub fn retain(&mut self, keep: impl FnMut(&T) -> bool) {
+        self.pending.retain(keep);
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overflow_drops_the_oldest_and_batches_shrink_with_energy() {
+        let mut queue = VerifyQueue::new(VerifyQueueConfig {
+            enabled: true,
+            capacity: 4,
+            batch: 4,
+        });
+        for i in 0..4 {
+            assert_eq!(queue.push(i), None);
+        }
+        assert_eq!(queue.push(4), Some(0));
+        assert_eq!(queue.dropped, 1);
+
+        assert_eq!(queue.take_batch(0.5), vec![1, 2]);
+        assert_eq!(queue.take_batch(0.0), vec![3]);
+        queue.retain(|&i| i != 4);
+        assert!(queue.take_batch(1.0).is_empty());
+    }
+}
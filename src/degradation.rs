@@ -0,0 +1,213 @@
+//! One energy policy for every subsystem.
+//!
+//! A [`DegradationLadder`] splits the energy score into bands, each mapped to
+//! a [`Rung`]: the set of features a node keeps at that level. The run loop
+//! reads the rung for its mesh degree, subscriptions, send policy, relaying,
+//! task bidding, heartbeat and sensor cadence, so lowering a threshold here
+//! moves all of them together. The power-mode keyed policies
+//! (`SubscriptionPolicy`, `SendPolicy`, `ReportingCadence`) still decide the
+//! details within a mode; the ladder decides which mode applies.
+//!
+//! The default bands match `PowerMode::from_energy_score`: normal from 0.5,
+//! low battery from 0.2.
+
+use crate::core::PowerMode;
+use crate::mesh::MeshConfig;
+use crate::mycelium::{SubscriptionPolicy, TopicKind};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rung {
+    /// Everything on, including pulse-gated relaying.
+    Full,
+    /// Full participation except relaying others' messages.
+    NoRelay,
+    /// Low-battery mode: bulky streams dropped, low-priority sends held back.
+    StatusOnly,
+    /// Critical mode: only high-priority sends such as spikes, no bidding and
+    /// no sensor reports.
+    SpikeOnly,
+    /// Nearly empty: joined to spikes alone and waking rarely.
+    Hibernate,
+}
+
+impl Rung {
+    pub fn power_mode(self) -> PowerMode {
+        match self {
+            Rung::Full | Rung::NoRelay => PowerMode::Normal,
+            Rung::StatusOnly => PowerMode::LowBattery,
+            Rung::SpikeOnly | Rung::Hibernate => PowerMode::Critical,
+        }
+    }
+
+    pub fn relays(self) -> bool {
+        self == Rung::Full
+    }
+
+    pub fn bids(self) -> bool {
+        self <= Rung::StatusOnly
+    }
+
+    /// Heartbeat period before pressure acceleration.
+    pub fn heartbeat(self) -> Duration {
+        match self {
+            Rung::Full | Rung::NoRelay => Duration::from_secs(1),
+            Rung::StatusOnly => Duration::from_secs(10),
+            Rung::SpikeOnly => Duration::from_secs(60),
+            Rung::Hibernate => Duration::from_secs(300),
+        }
+    }
+
+    /// Whether local pressure may shorten the heartbeat.
+    pub fn accelerates(self) -> bool {
+        self <= Rung::NoRelay
+    }
+}
+
+/// A rung and the lowest energy score it applies from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Band {
+    pub floor: f32,
+    pub rung: Rung,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DegradationLadder {
+    /// Mesh parameters at full energy. Lower-power modes cap the degrees at
+    /// `MeshConfig::for_mode`'s smaller values.
+    pub mesh: MeshConfig,
+    /// Highest floor first. A score takes the first band it reaches; scores
+    /// under every floor take the last band.
+    pub bands: Vec<Band>,
+    /// Above this score (mains-class energy) relaying skips the pressure and
+    /// pulse gates.
+    pub relay_all_above: f32,
+    /// Relays are held back at or above this local pressure.
+    pub relay_max_pressure: f32,
+    /// Relays go out only past this pulse phase, so neighbours take turns.
+    pub relay_min_phase: f32,
+}
+
+impl Default for DegradationLadder {
+    fn default() -> Self {
+        let band = |floor, rung| Band { floor, rung };
+        Self {
+            mesh: MeshConfig::default(),
+            bands: vec![
+                band(0.6, Rung::Full),
+                band(0.5, Rung::NoRelay),
+                band(0.2, Rung::StatusOnly),
+                band(0.01, Rung::SpikeOnly),
+                band(0.0, Rung::Hibernate),
+            ],
+            relay_all_above: 0.9,
+            relay_max_pressure: 7.0,
+            relay_min_phase: 0.7,
+        }
+    }
+}
+
+impl DegradationLadder {
+    pub fn rung(&self, energy: f32) -> Rung {
+        self.bands
+            .iter()
+            .find(|band| energy >= band.floor)
+            .or(self.bands.last())
+            .map_or(Rung::Full, |band| band.rung)
+    }
+
+    pub fn power_mode(&self, energy: f32) -> PowerMode {
+        self.rung(energy).power_mode()
+    }
+
+    pub fn mesh_config(&self, energy: f32) -> MeshConfig {
+        let mode = self.power_mode(energy);
+        if mode == PowerMode::Normal {
+            return self.mesh.clone();
+        }
+        let degrees = MeshConfig::for_mode(&mode);
+        MeshConfig {
+            d: degrees.d.min(self.mesh.d),
+            d_low: degrees.d_low.min(self.mesh.d_low),
+            d_high: degrees.d_high.min(self.mesh.d_high),
+            d_lazy: degrees.d_lazy.min(self.mesh.d_lazy),
+            ..self.mesh.clone()
+        }
+    }
+
+    /// Topics to join at `energy`: the policy's set for the power mode, cut
+    /// down to spikes when hibernating.
+    pub fn topics(&self, energy: f32, policy: &SubscriptionPolicy) -> Vec<TopicKind> {
+        match self.rung(energy) {
+            Rung::Hibernate => vec![TopicKind::Spike],
+            rung => policy.topics_for(&rung.power_mode()),
+        }
+    }
+
+    /// Whether to relay a received message.
+    pub fn should_relay(&self, energy: f32, pressure: f32, pulse_phase: f32) -> bool {
+        if energy > self.relay_all_above {
+            return true;
+        }
+        self.rung(energy).relays()
+            && pressure < self.relay_max_pressure
+            && pulse_phase > self.relay_min_phase
+    }
+
+    pub fn may_bid(&self, energy: f32) -> bool {
+        self.rung(energy).bids()
+    }
+
+    /// Heartbeat period at `energy` under local `pressure`: high pressure
+    /// shortens it up to 4x on rungs that allow it.
+    pub fn heartbeat(&self, energy: f32, pressure: f32) -> Duration {
+        let rung = self.rung(energy);
+        let base = rung.heartbeat();
+        if rung.accelerates() && pressure > 5.0 {
+            base.div_f32((pressure / 5.0).min(4.0))
+        } else {
+            base
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bands_agree_with_power_modes() {
+        let ladder = DegradationLadder::default();
+        for energy in [1.0, 0.75, 0.55, 0.5, 0.35, 0.2, 0.1, 0.012, 0.0] {
+            assert_eq!(
+                ladder.power_mode(energy),
+                PowerMode::from_energy_score(energy),
+                "{energy}"
+            );
+        }
+        assert_eq!(ladder.rung(0.7), Rung::Full);
+        assert_eq!(ladder.rung(0.55), Rung::NoRelay);
+        assert_eq!(ladder.rung(0.005), Rung::Hibernate);
+        assert_eq!(ladder.rung(-1.0), Rung::Hibernate);
+    }
+
+    #[test]
+    fn features_shut_off_in_ladder_order() {
+        let ladder = DegradationLadder::default();
+        assert!(ladder.should_relay(0.95, 9.0, 0.0));
+        assert!(ladder.should_relay(0.7, 1.0, 0.8));
+        assert!(!ladder.should_relay(0.7, 1.0, 0.5));
+        assert!(!ladder.should_relay(0.55, 1.0, 0.8));
+
+        assert!(ladder.may_bid(0.2));
+        assert!(!ladder.may_bid(0.19));
+
+        assert_eq!(ladder.heartbeat(0.7, 10.0), Duration::from_millis(500));
+        assert_eq!(ladder.heartbeat(0.3, 10.0), Duration::from_secs(10));
+        assert_eq!(ladder.heartbeat(0.0, 0.0), Duration::from_secs(300));
+
+        let policy = SubscriptionPolicy::default();
+        assert_eq!(ladder.topics(0.0, &policy), vec![TopicKind::Spike]);
+        assert!(ladder.topics(0.1, &policy).contains(&TopicKind::Status));
+    }
+}
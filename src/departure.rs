@@ -0,0 +1,149 @@
+//! Graceful departure ahead of energy exhaustion.
+//!
+//! A battery node that is about to die should say so while it still can.
+//! [`DepartureMonitor`] forecasts time-to-empty from the recent drain of the
+//! energy score and, once the forecast drops under `warn_within`, hands the
+//! run loop a single [`Departing`] announcement to gossip. Receivers prune the
+//! peer from their mesh, re-publish any of its in-flight tasks they
+//! originally published so the auction runs again, and start a shared-state
+//! sync so whatever only the departing peer holds is pulled before it goes.
+
+use crate::core::Task;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepartureConfig {
+    /// Announce once the forecast time-to-empty falls below this.
+    pub warn_within: Duration,
+    /// EWMA smoothing factor for the drain rate, in (0, 1].
+    pub alpha: f64,
+    /// Drain observations needed before a forecast is trusted.
+    pub min_samples: u32,
+}
+
+impl Default for DepartureConfig {
+    fn default() -> Self {
+        Self {
+            warn_within: Duration::from_secs(5 * 60),
+            alpha: 0.3,
+            min_samples: 3,
+        }
+    }
+}
+
+/// Gossiped on the departure topic by a node about to run out of energy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Departing {
+    pub peer: String,
+    /// Forecast seconds until the node exhausts.
+    pub eta_secs: u64,
+    /// Tasks the node accepted and has not finished.
+    pub in_flight: Vec<Task>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DepartureMonitor {
+    pub config: DepartureConfig,
+    last: Option<(Instant, f32)>,
+    /// Smoothed drain in energy-score units per second.
+    drain_per_sec: f64,
+    samples: u32,
+    announced: bool,
+}
+
+impl DepartureMonitor {
+    pub fn new(config: DepartureConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Forecast time until the energy score reaches zero, if the node is
+    /// draining and enough history has been seen.
+    pub fn time_to_empty(&self) -> Option<Duration> {
+        let (_, energy) = self.last?;
+        if self.samples < self.config.min_samples || self.drain_per_sec <= 0.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(
+            f64::from(energy.max(0.0)) / self.drain_per_sec,
+        ))
+    }
+
+    /// Whether a departure was announced and has not been re-armed since.
+    pub fn is_departing(&self) -> bool {
+        self.announced
+    }
+
+    /// Feed one energy reading. Returns the forecast the first time it falls
+    /// under `warn_within`; mains power or a recovering battery re-arms it.
+    pub fn observe(&mut self, energy: f32, is_mains: bool, now: Instant) -> Option<Duration> {
+        if let Some((at, previous)) = self.last {
+            let secs = now.saturating_duration_since(at).as_secs_f64();
+            if secs > 0.0 {
+                let rate = f64::from(previous - energy) / secs;
+                self.drain_per_sec = if self.samples == 0 {
+                    rate
+                } else {
+                    self.drain_per_sec + self.config.alpha * (rate - self.drain_per_sec)
+                };
+                self.samples = self.samples.saturating_add(1);
+            }
+        }
+        self.last = Some((now, energy));
+
+        let eta = self.time_to_empty().filter(|_| !is_mains);
+        match eta {
+            Some(eta) if eta < self.config.warn_within => {
+                (!std::mem::replace(&mut self.announced, true)).then_some(eta)
+            }
+            _ => {
+                self.announced = false;
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn announces_once_when_forecast_drops_below_threshold() {
+        let mut monitor = DepartureMonitor::default();
+        let t0 = Instant::now();
+        // 1% per minute from 20%: ~20 minutes left, no warning yet.
+        for i in 0..5 {
+            let energy = 0.20 - 0.01 * i as f32;
+            assert_eq!(monitor.observe(energy, false, t0 + MINUTE * i), None);
+        }
+        let eta = monitor.time_to_empty().unwrap();
+        assert!(eta > MINUTE * 10, "{eta:?}");
+
+        let mut announcements = Vec::new();
+        for i in 5..19 {
+            let energy = 0.20 - 0.01 * i as f32;
+            announcements.extend(monitor.observe(energy, false, t0 + MINUTE * i));
+        }
+        assert_eq!(announcements.len(), 1);
+        assert!(announcements[0] < monitor.config.warn_within);
+    }
+
+    #[test]
+    fn steady_or_mains_nodes_never_announce() {
+        let t0 = Instant::now();
+        let mut steady = DepartureMonitor::default();
+        let mut mains = DepartureMonitor::default();
+        for i in 0..10 {
+            let at = t0 + MINUTE * i;
+            assert_eq!(steady.observe(0.05, false, at), None);
+            assert_eq!(mains.observe(0.10 - 0.01 * i as f32, true, at), None);
+        }
+        assert_eq!(steady.time_to_empty(), None);
+    }
+}
@@ -0,0 +1,148 @@
+//! `did:key` identities for nodes.
+//!
+//! A node's DID is its ed25519 public key in the `did:key` method: the key
+//! behind the ed25519 multicodec prefix, base58btc-encoded after a `z`
+//! multibase tag. An ed25519 peer id also inlines the key, so the two forms
+//! map onto each other without any lookup. [`resolve`] accepts either, which
+//! lets delegation chains name principals by DID while transport-level code
+//! keeps using peer ids.
+
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use libp2p::{identity, PeerId};
+
+pub const DID_KEY_PREFIX: &str = "did:key:";
+
+/// Multicodec varint for an ed25519 public key.
+const ED25519_PUB: [u8; 2] = [0xed, 0x01];
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DidError {
+    #[error("`{0}` is not a base58btc did:key")]
+    NotDidKey(String),
+    #[error("did:key is not valid base58: {0}")]
+    Encoding(String),
+    #[error("did:key does not hold an ed25519 key")]
+    UnsupportedKey,
+    #[error("`{0}` is neither a did:key nor an ed25519 peer id")]
+    UnknownPrincipal(String),
+}
+
+pub fn did_key(public: &VerifyingKey) -> String {
+    let mut bytes = ED25519_PUB.to_vec();
+    bytes.extend_from_slice(public.as_bytes());
+    format!("{DID_KEY_PREFIX}z{}", bs58::encode(bytes).into_string())
+}
+
+/// The DID of the node holding `signing_key`.
+pub fn did_of(signing_key: &SigningKey) -> String {
+    did_key(&signing_key.verifying_key())
+}
+
+/// The DID for an ed25519 peer id; `None` for other key types or hashed ids.
+pub fn did_of_peer(peer: &PeerId) -> Option<String> {
+    let public = crate::auth::public_key_of(&peer.to_string())?;
+    let bytes = public.try_into_ed25519().ok()?.to_bytes();
+    VerifyingKey::from_bytes(&bytes)
+        .ok()
+        .map(|key| did_key(&key))
+}
+
+pub fn public_key_of_did(did: &str) -> Result<identity::PublicKey, DidError> {
+    let encoded = did
+        .strip_prefix(DID_KEY_PREFIX)
+        .and_then(|rest| rest.strip_prefix('z'))
+        .ok_or_else(|| DidError::NotDidKey(did.to_string()))?;
+    let bytes = bs58::decode(encoded)
+        .into_vec()
+        .map_err(|e| DidError::Encoding(e.to_string()))?;
+    let key = bytes
+        .strip_prefix(&ED25519_PUB)
+        .ok_or(DidError::UnsupportedKey)?;
+    let key =
+        identity::ed25519::PublicKey::try_from_bytes(key).map_err(|_| DidError::UnsupportedKey)?;
+    Ok(identity::PublicKey::from(key))
+}
+
+pub fn peer_id_of_did(did: &str) -> Result<PeerId, DidError> {
+    Ok(public_key_of_did(did)?.to_peer_id())
+}
+
+/// Map a principal named either by DID or by peer id to its peer id.
+pub fn resolve(principal: &str) -> Result<PeerId, DidError> {
+    if principal.starts_with("did:") {
+        return peer_id_of_did(principal);
+    }
+    principal
+        .parse()
+        .map_err(|_| DidError::UnknownPrincipal(principal.to_string()))
+}
+
+/// Whether `a` and `b` name the same node, in either form.
+pub fn same_principal(a: &str, b: &str) -> bool {
+    a == b
+        || matches!(
+            (resolve(a), resolve(b)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+/// Identify agent string carrying the node's DID, so peers learn it during
+/// the identify exchange.
+pub fn agent_version(did: &str) -> String {
+    format!("hypha/{} {did}", env!("CARGO_PKG_VERSION"))
+}
+
+/// The DID advertised in a peer's identify agent string, if it matches the
+/// peer's own key.
+pub fn did_from_agent_version(peer: &PeerId, agent_version: &str) -> Option<String> {
+    let did = agent_version
+        .split_whitespace()
+        .find(|part| part.starts_with(DID_KEY_PREFIX))?;
+    (peer_id_of_did(did).ok()? == *peer).then(|| did.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::peer_id_of;
+
+    #[test]
+    fn did_and_peer_id_round_trip() {
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let did = did_of(&key);
+        let peer = peer_id_of(&key);
+
+        assert!(did.starts_with("did:key:z6Mk"), "{did}");
+        assert_eq!(peer_id_of_did(&did), Ok(peer));
+        assert_eq!(did_of_peer(&peer).as_deref(), Some(did.as_str()));
+        assert_eq!(resolve(&peer.to_string()), Ok(peer));
+        assert!(same_principal(&did, &peer.to_string()));
+        assert_eq!(
+            did_from_agent_version(&peer, &agent_version(&did)).as_deref(),
+            Some(did.as_str())
+        );
+        let other = peer_id_of(&SigningKey::from_bytes(&[8; 32]));
+        assert_eq!(did_from_agent_version(&other, &agent_version(&did)), None);
+    }
+
+    #[test]
+    fn malformed_dids_are_rejected() {
+        assert!(matches!(
+            resolve("did:web:example.org"),
+            Err(DidError::NotDidKey(_))
+        ));
+        assert!(matches!(
+            resolve("did:key:z0OIl"),
+            Err(DidError::Encoding(_))
+        ));
+        // A secp256k1 key (multicodec 0xe7 0x01).
+        let mut secp = vec![0xe7, 0x01];
+        secp.extend([2; 33]);
+        let did = format!("did:key:z{}", bs58::encode(secp).into_string());
+        assert_eq!(resolve(&did), Err(DidError::UnsupportedKey));
+        assert!(matches!(
+            resolve("not-a-peer"),
+            Err(DidError::UnknownPrincipal(_))
+        ));
+    }
+}
@@ -0,0 +1,293 @@
+//! Emergent role differentiation.
+//!
+//! Mesh links strengthen with use; [`RoleInference`] does the same for a
+//! node's role. On each heartbeat it folds the messages the node relayed
+//! since the last one and its energy score into moving averages. A
+//! mains-powered node that keeps relaying a lot steps up from its starting
+//! role to `Relay`, and after as long again to `Coordinator`; it steps back
+//! down once it loses mains power or goes quiet. A battery node whose
+//! energy stays low drops to `Sensor` and returns to its starting role once
+//! it recovers. Every threshold has a separate way back, and each step
+//! needs a streak of qualifying heartbeats, so roles do not flap.
+//!
+//! The starting role is whatever the node holds when inference first sees
+//! it, or any role an operator sets later with `SporeNode::set_role`.
+//! [`RoleInference::pin`] holds a role regardless of history. A
+//! self-promoted coordinator takes the coordinator defaults only; topic ACLs
+//! still require a `Capability::Coordinator` grant.
+
+use crate::core::NodeRole;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleInferenceConfig {
+    /// Off by default: roles change only when set.
+    pub enabled: bool,
+    /// Weight of each heartbeat in the moving averages.
+    pub smoothing: f32,
+    /// Average relays per heartbeat at which a mains node steps up.
+    pub promote_relays: f32,
+    /// Average relays per heartbeat below which a promoted node steps down.
+    pub release_relays: f32,
+    /// Qualifying heartbeats before each step up.
+    pub promote_after: u32,
+    /// Average energy below which a battery node drops to `Sensor`.
+    pub demote_below: f32,
+    /// Average energy at which a demoted node takes its role back.
+    pub recover_above: f32,
+    /// Qualifying heartbeats before a demotion or any step back.
+    pub demote_after: u32,
+}
+
+impl Default for RoleInferenceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            smoothing: 0.05,
+            promote_relays: 1.0,
+            release_relays: 0.25,
+            promote_after: 300,
+            demote_below: 0.3,
+            recover_above: 0.5,
+            demote_after: 120,
+        }
+    }
+}
+
+/// A role change inference calls for; `None` is the all-round node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoleChange {
+    pub from: Option<NodeRole>,
+    pub to: Option<NodeRole>,
+}
+
+#[derive(Debug, Default)]
+pub struct RoleInference {
+    pub config: RoleInferenceConfig,
+    pinned: Option<NodeRole>,
+    /// The role the node started from, before any change made here.
+    base: Option<NodeRole>,
+    /// The role last chosen here, while it differs from `base`.
+    adapted: Option<Option<NodeRole>>,
+    relayed: u32,
+    relay_rate: f32,
+    energy: Option<f32>,
+    busy: u32,
+    idle: u32,
+    low: u32,
+    recovered: u32,
+}
+
+/// Position on the promotion ladder.
+fn rank(role: Option<NodeRole>) -> u8 {
+    match role {
+        Some(NodeRole::Sensor) => 0,
+        None | Some(NodeRole::Compute) => 1,
+        Some(NodeRole::Relay) => 2,
+        Some(NodeRole::Coordinator) => 3,
+    }
+}
+
+impl RoleInference {
+    pub fn new(config: RoleInferenceConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Hold `role` whatever the node's history says.
+    pub fn pin(&mut self, role: NodeRole) {
+        self.pinned = Some(role);
+    }
+
+    /// Let history move the role again, starting from the pinned one.
+    pub fn unpin(&mut self) {
+        self.pinned = None;
+        self.adapted = None;
+    }
+
+    pub fn pinned(&self) -> Option<NodeRole> {
+        self.pinned
+    }
+
+    /// Count a message the node relayed for others.
+    pub fn record_relay(&mut self) {
+        self.relayed = self.relayed.saturating_add(1);
+    }
+
+    /// Average relays per heartbeat and average energy so far.
+    pub fn averages(&self) -> (f32, Option<f32>) {
+        (self.relay_rate, self.energy)
+    }
+
+    /// Fold in a heartbeat at `energy` for a node holding `current`.
+    /// Returns the role to move to, if any.
+    pub fn observe(
+        &mut self,
+        current: Option<NodeRole>,
+        energy: f32,
+        is_mains: bool,
+    ) -> Option<RoleChange> {
+        let relayed = std::mem::take(&mut self.relayed) as f32;
+        if let Some(pinned) = self.pinned {
+            return (current != Some(pinned)).then_some(RoleChange {
+                from: current,
+                to: Some(pinned),
+            });
+        }
+        if !self.config.enabled {
+            return None;
+        }
+
+        let alpha = self.config.smoothing.clamp(0.0, 1.0);
+        self.relay_rate += alpha * (relayed - self.relay_rate);
+        let average = self
+            .energy
+            .map_or(energy, |average| average + alpha * (energy - average));
+        self.energy = Some(average);
+        // An operator set another role since the last change made here.
+        if self.adapted.is_some_and(|adapted| adapted != current) {
+            self.adapted = None;
+        }
+        if self.adapted.is_none() {
+            self.base = current;
+        }
+
+        let streak = |count: &mut u32, holds: bool| {
+            *count = if holds { count.saturating_add(1) } else { 0 };
+            *count
+        };
+        let config = &self.config;
+        let busy = streak(
+            &mut self.busy,
+            is_mains && self.relay_rate >= config.promote_relays,
+        );
+        let idle = streak(
+            &mut self.idle,
+            !is_mains || self.relay_rate < config.release_relays,
+        );
+        let low = streak(&mut self.low, !is_mains && average < config.demote_below);
+        let recovered = streak(&mut self.recovered, average >= config.recover_above);
+
+        let promoted = self.adapted.is_some() && rank(current) > rank(self.base);
+        let demoted = self.adapted.is_some() && current == Some(NodeRole::Sensor);
+        let target = if low >= config.demote_after && current != Some(NodeRole::Sensor) {
+            Some(NodeRole::Sensor)
+        } else if demoted && recovered >= config.demote_after {
+            self.base
+        } else if busy >= config.promote_after && rank(current) < 3 {
+            Some(if rank(current) < 2 {
+                NodeRole::Relay
+            } else {
+                NodeRole::Coordinator
+            })
+        } else if promoted && idle >= config.demote_after {
+            match current {
+                Some(NodeRole::Coordinator) if rank(self.base) < 2 => Some(NodeRole::Relay),
+                _ => self.base,
+            }
+        } else {
+            return None;
+        };
+        if target == current {
+            return None;
+        }
+
+        self.busy = 0;
+        self.idle = 0;
+        self.low = 0;
+        self.recovered = 0;
+        self.adapted = (target != self.base).then_some(target);
+        Some(RoleChange {
+            from: current,
+            to: target,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RoleInferenceConfig {
+        RoleInferenceConfig {
+            enabled: true,
+            smoothing: 1.0,
+            promote_after: 3,
+            demote_after: 2,
+            ..RoleInferenceConfig::default()
+        }
+    }
+
+    /// Run heartbeats until the role changes, applying the change.
+    fn beats(
+        inference: &mut RoleInference,
+        role: &mut Option<NodeRole>,
+        count: u32,
+        relays: u32,
+        energy: f32,
+        is_mains: bool,
+    ) -> Option<u32> {
+        for beat in 1..=count {
+            for _ in 0..relays {
+                inference.record_relay();
+            }
+            if let Some(change) = inference.observe(*role, energy, is_mains) {
+                assert_eq!(change.from, *role);
+                *role = change.to;
+                return Some(beat);
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn busy_mains_nodes_climb_and_step_back_down() {
+        let mut inference = RoleInference::new(config());
+        let mut role = Some(NodeRole::Compute);
+
+        assert_eq!(beats(&mut inference, &mut role, 10, 2, 1.0, true), Some(3));
+        assert_eq!(role, Some(NodeRole::Relay));
+        assert_eq!(beats(&mut inference, &mut role, 10, 2, 1.0, true), Some(3));
+        assert_eq!(role, Some(NodeRole::Coordinator));
+        assert_eq!(beats(&mut inference, &mut role, 10, 2, 1.0, true), None);
+
+        // Unplugged, it steps back one rung at a time.
+        assert_eq!(beats(&mut inference, &mut role, 10, 2, 1.0, false), Some(2));
+        assert_eq!(role, Some(NodeRole::Relay));
+        assert_eq!(beats(&mut inference, &mut role, 10, 2, 1.0, false), Some(2));
+        assert_eq!(role, Some(NodeRole::Compute));
+        // Relaying on battery earns nothing.
+        assert_eq!(beats(&mut inference, &mut role, 10, 2, 1.0, false), None);
+    }
+
+    #[test]
+    fn low_energy_demotes_to_sensor_with_hysteresis() {
+        let mut inference = RoleInference::new(config());
+        let mut role = None;
+
+        assert_eq!(beats(&mut inference, &mut role, 10, 0, 0.2, false), Some(2));
+        assert_eq!(role, Some(NodeRole::Sensor));
+        // Between the two thresholds nothing moves.
+        assert_eq!(beats(&mut inference, &mut role, 10, 0, 0.4, false), None);
+        assert_eq!(beats(&mut inference, &mut role, 10, 0, 0.6, false), Some(2));
+        assert_eq!(role, None);
+    }
+
+    #[test]
+    fn pins_and_operator_roles_override_history() {
+        let mut inference = RoleInference::new(config());
+        let mut role = Some(NodeRole::Relay);
+        inference.pin(NodeRole::Sensor);
+        assert_eq!(beats(&mut inference, &mut role, 1, 5, 1.0, true), Some(1));
+        assert_eq!(role, Some(NodeRole::Sensor));
+        assert_eq!(beats(&mut inference, &mut role, 10, 5, 1.0, true), None);
+
+        inference.unpin();
+        assert_eq!(beats(&mut inference, &mut role, 10, 5, 1.0, true), Some(3));
+        assert_eq!(role, Some(NodeRole::Relay));
+        // The operator moves the node; that becomes its starting role.
+        role = Some(NodeRole::Compute);
+        assert_eq!(beats(&mut inference, &mut role, 10, 0, 1.0, true), None);
+    }
+}
@@ -0,0 +1,414 @@
+//! DNS-SD advertisement of a node's local HTTP service.
+//!
+//! Phones and laptops on the same LAN find nearby nodes by browsing for
+//! [`SERVICE`] over multicast DNS, then open the node's gateway or dashboard
+//! without knowing its address. [`Advertisement`] is the sans-IO core: it
+//! answers mDNS queries for the service, the node's instance and its host
+//! name with one response carrying the PTR, SRV, TXT and address records,
+//! and builds the unsolicited announcements and the goodbye sent on exit.
+//!
+//! `SporeNode::advertisement` describes the node, or returns `None` when
+//! `SporeNode::dns_sd` is off, as it should be for stealth deployments. The
+//! responder (`advertise`) needs the `dns-sd` feature, which pulls in
+//! `socket2` to share port 5353 with the host's own mDNS daemon.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+/// Service type nodes advertise under.
+pub const SERVICE: &str = "_hypha._tcp.local";
+
+/// Name browsers query to list every service type on the link.
+pub const SERVICE_ENUMERATION: &str = "_services._dns-sd._udp.local";
+
+/// The mDNS multicast group.
+pub const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+
+/// Where mDNS queries and responses go.
+pub const MDNS_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(MDNS_GROUP), 5353);
+
+/// Record lifetime in announcements and answers, as RFC 6762 recommends for
+/// records naming a host.
+pub const DEFAULT_TTL: u32 = 120;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+/// Set on records only this host answers for, so caches replace rather
+/// than add to them.
+const CACHE_FLUSH: u16 = 0x8000;
+const FLAGS_RESPONSE: u16 = 0x8400;
+/// Longest DNS label.
+const MAX_LABEL: usize = 63;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum DnsError {
+    #[error("truncated DNS message")]
+    Truncated,
+    #[error("malformed DNS name")]
+    BadName,
+}
+
+/// One question of an mDNS query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Question {
+    pub name: String,
+    pub qtype: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Advertisement {
+    /// Instance label, unique on the link; browsers show it to users.
+    pub instance: String,
+    /// Host label, without `.local`.
+    pub host: String,
+    pub port: u16,
+    /// Addresses the service is reached at. The responder fills in the
+    /// link's own address when this is empty.
+    pub addrs: Vec<IpAddr>,
+    /// Key/value pairs for the TXT record.
+    pub txt: Vec<(String, String)>,
+    pub ttl: u32,
+}
+
+impl Advertisement {
+    /// A node's service on `port`, named after the tail of its peer id.
+    pub fn new(peer_id: &str, port: u16) -> Self {
+        let tail = peer_id
+            .get(peer_id.len().saturating_sub(8)..)
+            .unwrap_or(peer_id);
+        let name = format!("hypha-{}", tail.to_ascii_lowercase());
+        Self {
+            instance: name.clone(),
+            host: name,
+            port,
+            addrs: Vec::new(),
+            txt: vec![("peer".to_string(), peer_id.to_string())],
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    pub fn with_txt(mut self, key: &str, value: &str) -> Self {
+        self.txt.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn instance_name(&self) -> String {
+        format!("{}.{SERVICE}", label(&self.instance))
+    }
+
+    pub fn host_name(&self) -> String {
+        format!("{}.local", label(&self.host))
+    }
+
+    /// The response to an mDNS query, or `None` if it asks about nothing
+    /// this node advertises. Responses are not queries, so they get none.
+    pub fn answer(&self, packet: &[u8]) -> Result<Option<Vec<u8>>, DnsError> {
+        if packet.len() < 12 {
+            return Err(DnsError::Truncated);
+        }
+        if packet[2] & 0x80 != 0 {
+            return Ok(None);
+        }
+        let known = [
+            SERVICE.to_string(),
+            SERVICE_ENUMERATION.to_string(),
+            self.instance_name(),
+            self.host_name(),
+        ];
+        let asks = parse_questions(packet)?.iter().any(|question| {
+            known
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(&question.name))
+        });
+        Ok(asks.then(|| self.response(self.ttl)))
+    }
+
+    /// The unsolicited response sent on start, announcing every record.
+    pub fn announcement(&self) -> Vec<u8> {
+        self.response(self.ttl)
+    }
+
+    /// The records with a zero TTL, telling caches to drop them.
+    pub fn goodbye(&self) -> Vec<u8> {
+        self.response(0)
+    }
+
+    fn response(&self, ttl: u32) -> Vec<u8> {
+        let instance = self.instance_name();
+        let host = self.host_name();
+        let mut records = Vec::new();
+
+        records.push(record(SERVICE, TYPE_PTR, CLASS_IN, ttl, &name(&instance)));
+        records.push(record(
+            SERVICE_ENUMERATION,
+            TYPE_PTR,
+            CLASS_IN,
+            ttl,
+            &name(SERVICE),
+        ));
+        let mut srv = Vec::new();
+        srv.extend_from_slice(&[0, 0, 0, 0]);
+        srv.extend_from_slice(&self.port.to_be_bytes());
+        srv.extend_from_slice(&name(&host));
+        records.push(record(
+            &instance,
+            TYPE_SRV,
+            CLASS_IN | CACHE_FLUSH,
+            ttl,
+            &srv,
+        ));
+        records.push(record(
+            &instance,
+            TYPE_TXT,
+            CLASS_IN | CACHE_FLUSH,
+            ttl,
+            &txt(&self.txt),
+        ));
+        for addr in &self.addrs {
+            let (rtype, data) = match addr {
+                IpAddr::V4(ip) => (TYPE_A, ip.octets().to_vec()),
+                IpAddr::V6(ip) => (TYPE_AAAA, ip.octets().to_vec()),
+            };
+            records.push(record(&host, rtype, CLASS_IN | CACHE_FLUSH, ttl, &data));
+        }
+
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&[0, 0]);
+        packet.extend_from_slice(&FLAGS_RESPONSE.to_be_bytes());
+        packet.extend_from_slice(&[0, 0]);
+        packet.extend_from_slice(&(records.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 0]);
+        for record in records {
+            packet.extend_from_slice(&record);
+        }
+        packet
+    }
+}
+
+/// `text` cut to a DNS label: dots, which would split it, become dashes.
+fn label(text: &str) -> String {
+    let mut label = text.replace('.', "-");
+    while label.len() > MAX_LABEL {
+        label.pop();
+    }
+    label
+}
+
+fn name(dotted: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    for part in dotted.split('.').filter(|part| !part.is_empty()) {
+        out.push(part.len() as u8);
+        out.extend_from_slice(part.as_bytes());
+    }
+    out.push(0);
+    out
+}
+
+fn record(owner: &str, rtype: u16, class: u16, ttl: u32, data: &[u8]) -> Vec<u8> {
+    let mut out = name(owner);
+    out.extend_from_slice(&rtype.to_be_bytes());
+    out.extend_from_slice(&class.to_be_bytes());
+    out.extend_from_slice(&ttl.to_be_bytes());
+    out.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+/// TXT strings of at most 255 bytes; an empty record holds one empty string.
+fn txt(pairs: &[(String, String)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (key, value) in pairs {
+        let mut entry = format!("{key}={value}").into_bytes();
+        entry.truncate(255);
+        out.push(entry.len() as u8);
+        out.extend_from_slice(&entry);
+    }
+    if out.is_empty() {
+        out.push(0);
+    }
+    out
+}
+
+/// The questions of a DNS message, with names as sent.
+pub fn parse_questions(packet: &[u8]) -> Result<Vec<Question>, DnsError> {
+    let count = u16::from_be_bytes([
+        *packet.get(4).ok_or(DnsError::Truncated)?,
+        *packet.get(5).ok_or(DnsError::Truncated)?,
+    ]);
+    let mut at = 12;
+    let mut questions = Vec::new();
+    for _ in 0..count {
+        let (name, end) = read_name(packet, at)?;
+        let fixed = packet.get(end..end + 4).ok_or(DnsError::Truncated)?;
+        questions.push(Question {
+            name,
+            qtype: u16::from_be_bytes([fixed[0], fixed[1]]),
+        });
+        at = end + 4;
+    }
+    Ok(questions)
+}
+
+/// The name at `at` and the offset just past it, following compression
+/// pointers.
+fn read_name(packet: &[u8], mut at: usize) -> Result<(String, usize), DnsError> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Each pointer must go backwards, which also rules out loops.
+    let mut limit = at;
+    loop {
+        let len = *packet.get(at).ok_or(DnsError::Truncated)? as usize;
+        match len {
+            0 => {
+                let end = end.unwrap_or(at + 1);
+                return Ok((labels.join("."), end));
+            }
+            len if len & 0xc0 == 0xc0 => {
+                let low = *packet.get(at + 1).ok_or(DnsError::Truncated)? as usize;
+                let target = ((len & 0x3f) << 8) | low;
+                if target >= limit {
+                    return Err(DnsError::BadName);
+                }
+                end.get_or_insert(at + 2);
+                limit = target;
+                at = target;
+            }
+            len if len <= MAX_LABEL => {
+                let bytes = packet
+                    .get(at + 1..at + 1 + len)
+                    .ok_or(DnsError::Truncated)?;
+                labels.push(String::from_utf8_lossy(bytes).into_owned());
+                at += 1 + len;
+            }
+            _ => return Err(DnsError::BadName),
+        }
+    }
+}
+
+/// Answer mDNS queries for `advertisement` until `link`'s node shuts down,
+/// then say goodbye. Announces twice on start, a second apart.
+#[cfg(feature = "dns-sd")]
+pub async fn advertise(
+    link: crate::NodeLink,
+    mut advertisement: Advertisement,
+) -> std::io::Result<()> {
+    use socket2::{Domain, Protocol, Socket, Type};
+    use tokio::sync::broadcast::error::RecvError;
+
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(unix)]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), MDNS_ADDR.port()).into())?;
+    socket.join_multicast_v4(&MDNS_GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_nonblocking(true)?;
+    let socket = tokio::net::UdpSocket::from_std(socket.into())?;
+
+    if advertisement.addrs.is_empty() {
+        // Connecting a UDP socket sends nothing but picks the interface
+        // that routes to the group.
+        let probe = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        probe.connect(MDNS_ADDR)?;
+        advertisement.addrs.push(probe.local_addr()?.ip());
+    }
+
+    let mut events = link.subscribe();
+    let mut announce = tokio::time::interval(std::time::Duration::from_secs(1));
+    let mut announced = 0;
+    let mut buf = [0u8; 9000];
+    loop {
+        tokio::select! {
+            received = socket.recv_from(&mut buf) => {
+                let (len, _) = received?;
+                match advertisement.answer(&buf[..len]) {
+                    Ok(Some(response)) => {
+                        socket.send_to(&response, MDNS_ADDR).await?;
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::debug!(err = %e, "Ignoring malformed mDNS packet"),
+                }
+            }
+            _ = announce.tick(), if announced < 2 => {
+                socket.send_to(&advertisement.announcement(), MDNS_ADDR).await?;
+                announced += 1;
+            }
+            event = events.recv() => match event {
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => {
+                    socket.send_to(&advertisement.goodbye(), MDNS_ADDR).await?;
+                    return Ok(());
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TYPE_ANY: u16 = 255;
+
+    fn query(names: &[&str]) -> Vec<u8> {
+        let mut packet = vec![0, 0, 0, 0];
+        packet.extend_from_slice(&(names.len() as u16).to_be_bytes());
+        packet.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        for owner in names {
+            packet.extend_from_slice(&name(owner));
+            packet.extend_from_slice(&TYPE_ANY.to_be_bytes());
+            packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        }
+        packet
+    }
+
+    fn advertisement() -> Advertisement {
+        let mut ad =
+            Advertisement::new("12D3KooWQYhTNQdmr3ArTeUHRYzFg94BKyTkoWBDWez9kSCVe2Xo", 8080)
+                .with_txt("path", "/");
+        ad.addrs.push(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)));
+        ad
+    }
+
+    #[test]
+    fn answers_browsers_with_every_record() {
+        let ad = advertisement();
+        assert_eq!(ad.instance_name(), "hypha-kscve2xo._hypha._tcp.local");
+
+        let response = ad.answer(&query(&["_HYPHA._tcp.local"])).unwrap().unwrap();
+        assert_eq!(&response[2..4], &FLAGS_RESPONSE.to_be_bytes());
+        // Service and enumeration PTRs, SRV, TXT and one A record.
+        assert_eq!(u16::from_be_bytes([response[6], response[7]]), 5);
+        let (owner, end) = read_name(&response, 12).unwrap();
+        assert_eq!(owner, SERVICE);
+        assert_eq!(&response[end..end + 2], &TYPE_PTR.to_be_bytes());
+        let windows = |needle: &[u8]| response.windows(needle.len()).any(|w| w == needle);
+        assert!(windows(&[0, 0, 0, 0, 0x1f, 0x90]));
+        assert!(windows(b"path=/"));
+        assert!(windows(&[192, 168, 1, 20]));
+
+        assert_eq!(ad.answer(&query(&["_http._tcp.local"])).unwrap(), None);
+        let mut from_responder = query(&[SERVICE]);
+        from_responder[2] = 0x84;
+        assert_eq!(ad.answer(&from_responder).unwrap(), None);
+        let goodbye = ad.goodbye();
+        assert_eq!(&goodbye[end + 4..end + 8], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn names_follow_pointers_but_not_loops() {
+        // "local" at 12, then "a" plus a pointer back to it at 19.
+        let mut packet = vec![0; 12];
+        packet.extend_from_slice(&name("local"));
+        packet.extend_from_slice(&[1, b'a', 0xc0, 12]);
+        assert_eq!(read_name(&packet, 19).unwrap(), ("a.local".to_string(), 23));
+
+        packet.extend_from_slice(&[0xc0, 23]);
+        assert_eq!(read_name(&packet, 23), Err(DnsError::BadName));
+        assert_eq!(read_name(&packet[..20], 19), Err(DnsError::Truncated));
+        assert_eq!(parse_questions(&[0; 4]), Err(DnsError::Truncated));
+    }
+}
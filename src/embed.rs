@@ -0,0 +1,244 @@
+//! Run a `SporeNode` on its own thread for non-async embedders.
+//!
+//! [`NodeHandle`] drives the node on a dedicated thread with its own tokio
+//! runtime and keeps the node's shared handles (metabolism, CRDT state, task
+//! queue, event channel) for callers on other threads. The Python bindings
+//! (`hypha-py`) and the C API (`hypha-ffi`) are thin layers over it.
+
+use crate::events::NodeEvent;
+use crate::mycelium::Mycelium;
+use crate::sync::SharedState;
+use crate::{BatteryMetabolism, Metabolism, PowerMode, SporeNode, Task};
+use libp2p::Multiaddr;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::{broadcast, oneshot};
+
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+/// How long `spawn` waits for the swarm to report a listen address.
+const LISTEN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A running node and the handles needed to talk to it from other threads.
+pub struct NodeHandle {
+    peer_id: String,
+    listen_addr: Option<Multiaddr>,
+    metabolism: Arc<Mutex<dyn Metabolism>>,
+    shared_state: Arc<Mutex<SharedState>>,
+    outgoing_tasks: Arc<Mutex<Vec<Task>>>,
+    events: Option<broadcast::Sender<NodeEvent>>,
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+struct Started {
+    peer_id: String,
+    metabolism: Arc<Mutex<dyn Metabolism>>,
+    shared_state: Arc<Mutex<SharedState>>,
+    outgoing_tasks: Arc<Mutex<Vec<Task>>>,
+    events: broadcast::Sender<NodeEvent>,
+}
+
+impl NodeHandle {
+    /// Open (or recover) the node stored at `path` and start its network loop.
+    pub fn spawn(
+        path: &Path,
+        listen: Multiaddr,
+        bootstrap: Vec<Multiaddr>,
+    ) -> Result<Self, BoxError> {
+        Self::spawn_with_metabolism(
+            path,
+            Arc::new(Mutex::new(BatteryMetabolism::default())),
+            listen,
+            bootstrap,
+        )
+    }
+
+    /// Like [`NodeHandle::spawn`], with a caller-owned metabolism. Hosts that
+    /// measure power themselves keep a clone and update it in place.
+    pub fn spawn_with_metabolism(
+        path: &Path,
+        metabolism: Arc<Mutex<dyn Metabolism>>,
+        listen: Multiaddr,
+        bootstrap: Vec<Multiaddr>,
+    ) -> Result<Self, BoxError> {
+        let path: PathBuf = path.to_path_buf();
+        let (started_tx, started_rx) = mpsc::channel::<Result<Started, String>>();
+        let (listen_tx, listen_rx) = oneshot::channel();
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+
+        let thread = std::thread::Builder::new()
+            .name("hypha-node".to_string())
+            .spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(runtime) => runtime,
+                    Err(e) => {
+                        let _ = started_tx.send(Err(e.to_string()));
+                        return;
+                    }
+                };
+                runtime.block_on(async move {
+                    let node = match SporeNode::new_with_metabolism(&path, metabolism) {
+                        Ok(node) => node,
+                        Err(e) => {
+                            let _ = started_tx.send(Err(e.to_string()));
+                            return;
+                        }
+                    };
+                    let mycelium = match prepare_mycelium(&node, listen, bootstrap) {
+                        Ok(mycelium) => mycelium,
+                        Err(e) => {
+                            let _ = started_tx.send(Err(e.to_string()));
+                            return;
+                        }
+                    };
+                    let _ = started_tx.send(Ok(Started {
+                        peer_id: node.peer_id.to_string(),
+                        metabolism: node.metabolism.clone(),
+                        shared_state: node.shared_state.clone(),
+                        outgoing_tasks: node.outgoing_tasks.clone(),
+                        events: node.events.clone(),
+                    }));
+
+                    let heartbeat = node.heartbeat_interval();
+                    tokio::select! {
+                        _ = node.run_supervised(
+                            mycelium,
+                            Duration::from_secs(u64::MAX / 4),
+                            heartbeat,
+                            0.05,
+                            true,
+                            Some(listen_tx),
+                        ) => {}
+                        _ = stop_rx => {}
+                    }
+                });
+            })?;
+
+        let started = started_rx
+            .recv()
+            .map_err(|_| "node thread exited before starting")??;
+        let listen_addr = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()?
+            .block_on(async { tokio::time::timeout(LISTEN_TIMEOUT, listen_rx).await })
+            .ok()
+            .and_then(Result::ok);
+
+        Ok(Self {
+            peer_id: started.peer_id,
+            listen_addr,
+            metabolism: started.metabolism,
+            shared_state: started.shared_state,
+            outgoing_tasks: started.outgoing_tasks,
+            events: Some(started.events),
+            stop: Some(stop_tx),
+            thread: Some(thread),
+        })
+    }
+
+    pub fn peer_id(&self) -> &str {
+        &self.peer_id
+    }
+
+    /// First address the swarm reported, if it reported one in time.
+    pub fn listen_addr(&self) -> Option<&Multiaddr> {
+        self.listen_addr.as_ref()
+    }
+
+    pub fn energy_score(&self) -> f32 {
+        self.metabolism.lock().unwrap().energy_score()
+    }
+
+    pub fn power_mode(&self) -> PowerMode {
+        PowerMode::from_energy_score(self.energy_score())
+    }
+
+    /// Queue `task` for the node's next heartbeat.
+    pub fn publish_task(&self, task: Task) {
+        self.outgoing_tasks.lock().unwrap().push(task);
+    }
+
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.shared_state.lock().unwrap().get(key)
+    }
+
+    pub fn set(&self, key: &str, value: &str) {
+        self.shared_state.lock().unwrap().set(key, value);
+    }
+
+    /// Subscribe to node events. Returns `None` after `stop`.
+    pub fn subscribe(&self) -> Option<broadcast::Receiver<NodeEvent>> {
+        self.events.as_ref().map(broadcast::Sender::subscribe)
+    }
+
+    /// Stop the network loop and wait for the node thread to exit.
+    ///
+    /// Existing subscribers see the event stream close.
+    pub fn stop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        self.events = None;
+    }
+}
+
+impl Drop for NodeHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn prepare_mycelium(
+    node: &SporeNode,
+    listen: Multiaddr,
+    bootstrap: Vec<Multiaddr>,
+) -> Result<Mycelium, Box<dyn Error>> {
+    let mut mycelium = node.build_mycelium()?;
+    for addr in bootstrap {
+        mycelium.add_bootstrap(addr);
+    }
+    mycelium.listen_on(listen)?;
+    Ok(mycelium)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn handle_exposes_state_and_stops_cleanly() {
+        let tmp = tempdir().unwrap();
+        let mut handle = NodeHandle::spawn(
+            tmp.path(),
+            "/ip4/127.0.0.1/tcp/0".parse().unwrap(),
+            Vec::new(),
+        )
+        .unwrap();
+
+        assert!(handle.listen_addr().is_some());
+        assert!(!handle.peer_id().is_empty());
+
+        handle.set("greeting", "hello");
+        assert_eq!(handle.get("greeting").as_deref(), Some("hello"));
+        assert_eq!(handle.get("missing"), None);
+
+        let mut events = handle.subscribe().unwrap();
+        handle.stop();
+        assert!(handle.subscribe().is_none());
+        assert!(matches!(
+            events.try_recv(),
+            Err(broadcast::error::TryRecvError::Closed)
+        ));
+    }
+}
@@ -0,0 +1,117 @@
+//! Emergency relaying after a danger spike.
+//!
+//! Handling a spike normally only raises local pressure, which if anything
+//! makes a node relay less. [`EmergencyRelay`] turns a spike into a bounded
+//! burst of forwarding instead: for `hold` after the last spike the node
+//! relays spikes and high-priority tasks whatever its pulse phase or
+//! pressure, then its willingness falls linearly to nothing over `decay`.
+//! A node under `min_energy` keeps to its normal relay policy throughout, so
+//! an emergency cannot drain a nearly empty battery.
+
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmergencyConfig {
+    /// How long after a spike the node relays unconditionally.
+    pub hold: Duration,
+    /// How long willingness takes to fall back to zero after `hold`.
+    pub decay: Duration,
+    /// Energy score below which emergencies are ignored.
+    pub min_energy: f32,
+    /// Tasks at or above this priority are relayed during an emergency.
+    pub min_task_priority: u8,
+}
+
+impl Default for EmergencyConfig {
+    fn default() -> Self {
+        Self {
+            hold: Duration::from_secs(30),
+            decay: Duration::from_secs(30),
+            min_energy: 0.2,
+            min_task_priority: 5,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct EmergencyRelay {
+    pub config: EmergencyConfig,
+    /// When the last spike's hold ends.
+    held_until: Option<Instant>,
+}
+
+impl EmergencyRelay {
+    pub fn new(config: EmergencyConfig) -> Self {
+        Self {
+            config,
+            held_until: None,
+        }
+    }
+
+    /// Enter, or extend, an emergency at `now`.
+    pub fn trigger(&mut self, now: Instant) {
+        let until = now + self.config.hold;
+        self.held_until = Some(self.held_until.map_or(until, |held| held.max(until)));
+    }
+
+    /// Relay willingness at `now`: 1 during the hold, falling to 0 by the end
+    /// of the decay.
+    pub fn willingness(&self, now: Instant) -> f32 {
+        let Some(held_until) = self.held_until else {
+            return 0.0;
+        };
+        let past = now.saturating_duration_since(held_until);
+        if now <= held_until {
+            1.0
+        } else if past >= self.config.decay {
+            0.0
+        } else {
+            1.0 - past.as_secs_f32() / self.config.decay.as_secs_f32()
+        }
+    }
+
+    pub fn is_active(&self, now: Instant) -> bool {
+        self.willingness(now) > 0.0
+    }
+
+    /// Whether to relay an emergency message at `energy`. `roll` is a
+    /// uniform draw in `0..1`; it only matters while willingness decays.
+    pub fn should_relay(&self, energy: f32, now: Instant, roll: f32) -> bool {
+        energy >= self.config.min_energy && roll < self.willingness(now)
+    }
+
+    pub fn covers_task(&self, priority: u8) -> bool {
+        priority >= self.config.min_task_priority
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn emergency_holds_then_decays_above_the_energy_floor() {
+        let mut emergency = EmergencyRelay::default();
+        let start = Instant::now();
+        assert!(!emergency.is_active(start));
+        assert!(!emergency.should_relay(1.0, start, 0.0));
+
+        emergency.trigger(start);
+        assert!(emergency.should_relay(0.5, start + Duration::from_secs(29), 0.99));
+        assert!(!emergency.should_relay(0.1, start, 0.0));
+
+        // Halfway through the decay, half the rolls relay.
+        let halfway = start + Duration::from_secs(45);
+        assert!((emergency.willingness(halfway) - 0.5).abs() < 1e-3);
+        assert!(emergency.should_relay(0.5, halfway, 0.4));
+        assert!(!emergency.should_relay(0.5, halfway, 0.6));
+        assert!(!emergency.is_active(start + Duration::from_secs(60)));
+
+        // A later spike extends the emergency; an earlier one cannot shorten it.
+        emergency.trigger(halfway);
+        emergency.trigger(start);
+        assert_eq!(emergency.willingness(start + Duration::from_secs(75)), 1.0);
+
+        assert!(emergency.covers_task(5) && !emergency.covers_task(1));
+    }
+}
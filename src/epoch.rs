@@ -0,0 +1,337 @@
+//! Network-wide configuration epochs.
+//!
+//! An operator rolls a parameter change out to the whole swarm by writing a
+//! [`ConfigEpoch`] under [`EPOCH_KEY`] in the shared-state document; the CRDT
+//! and anti-entropy carry it to every node. The epoch is signed by an
+//! operator key, and a node only takes epochs signed by one of its
+//! configured operators and newer than the one it runs.
+//!
+//! Applying the same change everywhere at once would shake every mesh at the
+//! same moment, so an [`EpochWatcher`] holds each new epoch back for a random
+//! delay of up to `max_jitter`. If the epoch then fails validation, or the
+//! node cannot apply it, the node rolls back to the parameters of the epoch
+//! it ran before and does not try that epoch again.
+//!
+//! An epoch may also carry a new [`ShardPlan`] for the task topic, which is
+//! how the swarm is resharded; see `crate::shard`.
+
+use crate::auth::{peer_id_of, public_key_of};
+use crate::degradation::DegradationLadder;
+use crate::shard::ShardPlan;
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+/// Key of the epoch document in the shared key/value map.
+pub const EPOCH_KEY: &str = "config_epoch";
+
+const DOMAIN: &[u8] = b"hypha-epoch-v1:";
+
+/// The parameters an epoch sets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EpochParams {
+    pub d: usize,
+    pub d_low: usize,
+    pub d_high: usize,
+    pub d_lazy: usize,
+    pub relay_all_above: f32,
+    pub relay_max_pressure: f32,
+    pub relay_min_phase: f32,
+    /// Task topic sharding to switch to; unchanged when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shards: Option<ShardPlan>,
+}
+
+impl EpochParams {
+    pub fn of(ladder: &DegradationLadder) -> Self {
+        Self {
+            d: ladder.mesh.d,
+            d_low: ladder.mesh.d_low,
+            d_high: ladder.mesh.d_high,
+            d_lazy: ladder.mesh.d_lazy,
+            relay_all_above: ladder.relay_all_above,
+            relay_max_pressure: ladder.relay_max_pressure,
+            relay_min_phase: ladder.relay_min_phase,
+            shards: None,
+        }
+    }
+
+    pub fn with_shards(mut self, plan: ShardPlan) -> Self {
+        self.shards = Some(plan);
+        self
+    }
+
+    pub fn apply_to(&self, ladder: &mut DegradationLadder) {
+        ladder.mesh.d = self.d;
+        ladder.mesh.d_low = self.d_low;
+        ladder.mesh.d_high = self.d_high;
+        ladder.mesh.d_lazy = self.d_lazy;
+        ladder.relay_all_above = self.relay_all_above;
+        ladder.relay_max_pressure = self.relay_max_pressure;
+        ladder.relay_min_phase = self.relay_min_phase;
+    }
+
+    pub fn validate(&self) -> Result<(), EpochError> {
+        if self.d_low == 0 || self.d_low > self.d || self.d > self.d_high {
+            return Err(EpochError::Invalid(format!(
+                "mesh degrees must satisfy 0 < d_low <= d <= d_high, got {} <= {} <= {}",
+                self.d_low, self.d, self.d_high
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.relay_all_above) {
+            return Err(EpochError::Invalid(format!(
+                "relay_all_above {} is not an energy score",
+                self.relay_all_above
+            )));
+        }
+        if !(0.0..=1.0).contains(&self.relay_min_phase) {
+            return Err(EpochError::Invalid(format!(
+                "relay_min_phase {} is not a pulse phase",
+                self.relay_min_phase
+            )));
+        }
+        if !(self.relay_max_pressure.is_finite() && self.relay_max_pressure >= 0.0) {
+            return Err(EpochError::Invalid(format!(
+                "relay_max_pressure {} is not a pressure",
+                self.relay_max_pressure
+            )));
+        }
+        if let Some(plan) = &self.shards {
+            plan.validate().map_err(EpochError::Invalid)?;
+        }
+        Ok(())
+    }
+}
+
+/// One operator-signed revision of the swarm's parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigEpoch {
+    pub epoch: u64,
+    pub params: EpochParams,
+    /// Peer id of the signing operator key.
+    pub operator: String,
+    pub signature: Vec<u8>,
+}
+
+impl ConfigEpoch {
+    pub fn sign(operator: &SigningKey, epoch: u64, params: EpochParams) -> Self {
+        let mut signed = Self {
+            epoch,
+            params,
+            operator: peer_id_of(operator).to_string(),
+            signature: Vec::new(),
+        };
+        signed.signature = operator.sign(&signed.signing_bytes()).to_bytes().to_vec();
+        signed
+    }
+
+    /// Check that one of `operators` signed the epoch.
+    pub fn verify(&self, operators: &[String]) -> Result<(), EpochError> {
+        if !operators.contains(&self.operator) {
+            return Err(EpochError::UnknownOperator(self.operator.clone()));
+        }
+        if public_key_of(&self.operator)
+            .is_some_and(|key| key.verify(&self.signing_bytes(), &self.signature))
+        {
+            Ok(())
+        } else {
+            Err(EpochError::BadSignature(self.operator.clone()))
+        }
+    }
+
+    /// The document stored under [`EPOCH_KEY`].
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("epoch serializes")
+    }
+
+    pub fn decode(document: &str) -> Result<Self, EpochError> {
+        serde_json::from_str(document).map_err(|e| EpochError::Malformed(e.to_string()))
+    }
+
+    fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = DOMAIN.to_vec();
+        bytes.extend(
+            serde_json::to_vec(&(self.epoch, &self.params, &self.operator))
+                .expect("epoch serializes"),
+        );
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum EpochError {
+    #[error("malformed config epoch: {0}")]
+    Malformed(String),
+    #[error("`{0}` is not a configured operator")]
+    UnknownOperator(String),
+    #[error("config epoch signature by `{0}` does not verify")]
+    BadSignature(String),
+    #[error("invalid epoch parameters: {0}")]
+    Invalid(String),
+    #[error("epoch could not be applied: {0}")]
+    Apply(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochConfig {
+    /// Peer ids of the keys allowed to sign epochs. With none, epochs are
+    /// ignored.
+    pub operators: Vec<String>,
+    /// Upper bound of the random delay before a new epoch is applied.
+    pub max_jitter: Duration,
+}
+
+impl Default for EpochConfig {
+    fn default() -> Self {
+        Self {
+            operators: Vec::new(),
+            max_jitter: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Tracks the epoch a node runs and the one it is about to apply.
+#[derive(Debug, Default)]
+pub struct EpochWatcher {
+    pub config: EpochConfig,
+    /// Epoch in force, 0 before the first.
+    current: u64,
+    /// A newer epoch and when to apply it.
+    pending: Option<(ConfigEpoch, Instant)>,
+    /// Epochs that failed to apply.
+    rejected: HashSet<u64>,
+}
+
+impl EpochWatcher {
+    pub fn new(config: EpochConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn current(&self) -> u64 {
+        self.current
+    }
+
+    /// Look at the shared epoch `document` and return the pending epoch once
+    /// its jitter has passed. A newer document replaces a pending epoch but
+    /// keeps its due time, so a quick run of epochs does not postpone all of
+    /// them. Documents that do not verify are ignored.
+    pub fn poll(&mut self, document: Option<&str>, now: Instant) -> Option<ConfigEpoch> {
+        if let Some(document) = document {
+            self.observe(document, now);
+        }
+        match &self.pending {
+            Some((_, due)) if now >= *due => self.pending.take().map(|(epoch, _)| epoch),
+            _ => None,
+        }
+    }
+
+    fn observe(&mut self, document: &str, now: Instant) {
+        let Ok(epoch) = ConfigEpoch::decode(document) else {
+            return;
+        };
+        let newest = self
+            .pending
+            .as_ref()
+            .map_or(self.current, |(pending, _)| pending.epoch);
+        if epoch.epoch <= newest || self.rejected.contains(&epoch.epoch) {
+            return;
+        }
+        if let Err(e) = epoch.verify(&self.config.operators) {
+            tracing::debug!(epoch = epoch.epoch, err = %e, "Ignoring config epoch");
+            return;
+        }
+        let due = match self.pending.take() {
+            Some((_, due)) => due,
+            None => now + self.config.max_jitter.mul_f64(rand::random::<f64>()),
+        };
+        self.pending = Some((epoch, due));
+    }
+
+    /// Note that `epoch` is in force.
+    pub fn applied(&mut self, epoch: u64) {
+        self.current = epoch;
+    }
+
+    /// Give up on `epoch`; the node stays on (or returns to) `current`.
+    pub fn reject(&mut self, epoch: u64) {
+        self.rejected.insert(epoch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shard::ShardKey;
+
+    fn operator() -> SigningKey {
+        SigningKey::from_bytes(&[7; 32])
+    }
+
+    fn watcher() -> EpochWatcher {
+        EpochWatcher::new(EpochConfig {
+            operators: vec![peer_id_of(&operator()).to_string()],
+            max_jitter: Duration::from_secs(10),
+        })
+    }
+
+    fn params(d: usize) -> EpochParams {
+        let mut params = EpochParams::of(&DegradationLadder::default());
+        params.d = d;
+        params.d_high = params.d_high.max(d);
+        params
+    }
+
+    #[test]
+    fn only_newer_operator_epochs_apply_after_jitter() {
+        let mut watcher = watcher();
+        let now = Instant::now();
+        let due = now + Duration::from_secs(10);
+        let stranger = ConfigEpoch::sign(&SigningKey::from_bytes(&[8; 32]), 1, params(8));
+        assert_eq!(watcher.poll(Some(&stranger.encode()), now), None);
+        let mut forged = ConfigEpoch::sign(&operator(), 2, params(8));
+        forged.params.d = 9;
+        assert_eq!(watcher.poll(Some(&forged.encode()), now), None);
+        assert_eq!(watcher.poll(Some("not json"), now), None);
+        assert_eq!(watcher.poll(None, due), None);
+
+        // A forged document does not burn the epoch number.
+        let epoch = ConfigEpoch::sign(&operator(), 2, params(8));
+        assert_eq!(watcher.poll(Some(&epoch.encode()), now), None);
+        assert_eq!(watcher.poll(None, due), Some(epoch.clone()));
+        watcher.applied(2);
+        assert_eq!(watcher.current(), 2);
+
+        // Re-reading the same or an older document changes nothing.
+        assert_eq!(watcher.poll(Some(&epoch.encode()), due), None);
+        let older = ConfigEpoch::sign(&operator(), 1, params(8));
+        assert_eq!(watcher.poll(Some(&older.encode()), due), None);
+        assert_eq!(watcher.poll(None, due + Duration::from_secs(10)), None);
+    }
+
+    #[test]
+    fn rejected_epochs_are_not_retried() {
+        let mut watcher = watcher();
+        let now = Instant::now();
+        let due = now + Duration::from_secs(10);
+        let mut bad = params(8);
+        bad.d_low = 0;
+        let epoch = ConfigEpoch::sign(&operator(), 1, bad);
+        assert!(matches!(
+            epoch.params.validate(),
+            Err(EpochError::Invalid(_))
+        ));
+        let too_many = params(8).with_shards(ShardPlan::new(100_000, ShardKey::Zone));
+        assert!(too_many.validate().is_err());
+
+        watcher.poll(Some(&epoch.encode()), now);
+        let epoch = watcher.poll(None, due).unwrap();
+        watcher.reject(epoch.epoch);
+        assert_eq!(watcher.current(), 0);
+        assert_eq!(watcher.poll(Some(&epoch.encode()), due), None);
+        assert_eq!(watcher.poll(None, due + Duration::from_secs(10)), None);
+    }
+}
@@ -6,8 +6,43 @@
 //! - Convergence Time: time until all nodes have consistent state
 //! - Energy Efficiency: mAh consumed per successful message delivery
 //! - Recovery Time: time to recover from fault injection
+//!
+//! Scenarios also pick a [`LinkModel`], so constrained radios (LoRa duty
+//! cycles, BLE connection intervals) can be simulated alongside ideal links,
+//! and a [`Topology`], so sparse deployments (a ring, a radio grid, a
+//! scale-free graph, clustered sites) can be simulated alongside a full mesh.
+//! A scenario's `device_mix` turns a homogeneous swarm into a fleet of
+//! [`DeviceClass`]es, each with its own battery, radio costs, bandwidth and
+//! uptime pattern.
+//!
+//! A scenario's `authorization` offers tasks to the swarm, a share of them
+//! with a missing or bad token, and the run's [`AuthMetrics`] count how many
+//! of those a node would have executed and what verifying tokens cost.
+//!
+//! [`simulate`] runs a scenario in memory from a seed, so its results can be
+//! pinned in a committed [`Baseline`] and compared on every test run.
+//!
+//! Scenarios, fault schedule included, can also be written as TOML or JSON
+//! files and read with [`EvalScenario::load`] or [`load_scenarios`]. Every
+//! field is optional and falls back to [`EvalScenario::default`]; durations
+//! are in seconds:
+//!
+//! ```toml
+//! node_count = 50
+//! duration = 30
+//! link = { LoRa = { spreading_factor = 9 } }
+//! topology = { Grid = { radio_range = 1.5 } }
+//!
+//! [[fault_schedule]]
+//! time = 10
+//! fault = { Degradation = { drop_probability = 0.3 } }
+//! ```
 
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 /// Collected during a single evaluation run
@@ -20,6 +55,92 @@ pub struct EvalRun {
     pub energy: EnergyMetrics,
     pub consistency: ConsistencyMetrics,
     pub fault_events: Vec<FaultEvent>,
+    /// Samples recorded during warmup and cooldown, kept out of `delivery`
+    /// and `energy`.
+    #[serde(default)]
+    pub warmup: PhaseMetrics,
+    #[serde(default)]
+    pub cooldown: PhaseMetrics,
+    /// Per-class results when the scenario mixes device classes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub devices: Vec<DeviceStats>,
+    /// One entry per node, when the driver records them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<NodeMetrics>,
+    /// Present when the scenario had an authorization workload.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthMetrics>,
+}
+
+/// How one node fared over a run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeMetrics {
+    pub node: String,
+    /// Messages the node received.
+    pub deliveries: u64,
+    pub mah_consumed: f32,
+    /// Energy score at the start of the run.
+    pub initial_energy: f32,
+    /// Mesh peers at the end of the run.
+    pub mesh_size: usize,
+}
+
+impl EvalRun {
+    /// The `k` nodes that received the fewest messages, fewest first.
+    pub fn worst_nodes(&self, k: usize) -> Vec<&NodeMetrics> {
+        let mut nodes: Vec<&NodeMetrics> = self.nodes.iter().collect();
+        nodes.sort_by_key(|node| node.deliveries);
+        nodes.truncate(k);
+        nodes
+    }
+
+    /// Jain's fairness index over per-node deliveries: 1.0 when every node
+    /// received the same, `1/n` when one node received everything. `None`
+    /// without per-node data or deliveries.
+    pub fn fairness_index(&self) -> Option<f64> {
+        let n = self.nodes.len() as f64;
+        let sum: f64 = self.nodes.iter().map(|node| node.deliveries as f64).sum();
+        let squares: f64 = self
+            .nodes
+            .iter()
+            .map(|node| (node.deliveries as f64).powi(2))
+            .sum();
+        (squares > 0.0).then(|| sum * sum / (n * squares))
+    }
+
+    /// Mean deliveries of nodes that started below `energy`, relative to
+    /// the rest. Well under 1.0 means low-energy nodes are starved. `None`
+    /// when either group is empty or the rest received nothing.
+    pub fn starvation_ratio(&self, energy: f32) -> Option<f64> {
+        let mean = |low: bool| {
+            let group: Vec<f64> = self
+                .nodes
+                .iter()
+                .filter(|node| (node.initial_energy < energy) == low)
+                .map(|node| node.deliveries as f64)
+                .collect();
+            (!group.is_empty()).then(|| group.iter().sum::<f64>() / group.len() as f64)
+        };
+        let (low, rest) = (mean(true)?, mean(false)?);
+        (rest > 0.0).then(|| low / rest)
+    }
+}
+
+/// Which part of a run a sample belongs to. Only `Measured` samples count
+/// towards the headline delivery rate, latency percentiles and energy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Phase {
+    Warmup,
+    #[default]
+    Measured,
+    Cooldown,
+}
+
+/// What happened during warmup or cooldown.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PhaseMetrics {
+    pub delivery: DeliveryMetrics,
+    pub mah_consumed: f32,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -31,6 +152,18 @@ pub struct DeliveryMetrics {
     pub expected_deliveries: u64,
     /// Latency samples in microseconds
     pub latencies_us: Vec<u64>,
+    /// Publishes held in the outbox by the energy-aware send policy
+    #[serde(default)]
+    pub publishes_queued: u64,
+    /// Publishes dropped by the send policy because the outbox was full
+    #[serde(default)]
+    pub publishes_dropped: u64,
+    /// Encoded envelope bytes before compression
+    #[serde(default)]
+    pub bytes_encoded: u64,
+    /// Bytes actually handed to gossipsub
+    #[serde(default)]
+    pub bytes_sent: u64,
 }
 
 impl DeliveryMetrics {
@@ -42,15 +175,17 @@ impl DeliveryMetrics {
         (self.messages_delivered as f64 / self.expected_deliveries as f64).min(1.0)
     }
 
+    /// Sent bytes per encoded byte; 1.0 when nothing was compressed.
+    pub fn compression_ratio(&self) -> f64 {
+        if self.bytes_encoded == 0 {
+            return 1.0;
+        }
+        self.bytes_sent as f64 / self.bytes_encoded as f64
+    }
+
     /// Compute percentile from latency samples
     pub fn percentile(&self, p: f64) -> Option<Duration> {
-        if self.latencies_us.is_empty() {
-            return None;
-        }
-        let mut sorted = self.latencies_us.clone();
-        sorted.sort_unstable();
-        let idx = ((p / 100.0) * (sorted.len() - 1) as f64).round() as usize;
-        Some(Duration::from_micros(sorted[idx]))
+        percentile_us(&self.latencies_us, p)
     }
 
     pub fn p50(&self) -> Option<Duration> {
@@ -175,6 +310,60 @@ impl ConsistencyMetrics {
     }
 }
 
+/// The `p`th percentile of microsecond samples.
+fn percentile_us(samples: &[u64], p: f64) -> Option<Duration> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+    let idx = ((p / 100.0) * (sorted.len() - 1) as f64).round() as usize;
+    Some(Duration::from_micros(sorted[idx]))
+}
+
+/// How task authorization held up under a scenario's authorization
+/// workload.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AuthMetrics {
+    /// Tasks offered with a valid token, and how many of those won a bid.
+    pub authorized_offered: u64,
+    pub authorized_accepted: u64,
+    pub unauthorized_offered: u64,
+    /// Unauthorized tasks a node bid for anyway, by what was wrong with
+    /// them. Empty when nodes require tokens.
+    pub slipped: BTreeMap<TokenFault, u64>,
+    /// Time token verification added to each bid on a task with a token.
+    pub overhead_us: Vec<u64>,
+    /// Energy spent verifying, from `overhead_us` at the workload's CPU draw.
+    pub verify_mah: f32,
+}
+
+impl AuthMetrics {
+    pub fn unauthorized_accepted(&self) -> u64 {
+        self.slipped.values().sum()
+    }
+
+    /// Share of unauthorized tasks that would have been executed.
+    pub fn slip_rate(&self) -> f64 {
+        if self.unauthorized_offered == 0 {
+            return 0.0;
+        }
+        self.unauthorized_accepted() as f64 / self.unauthorized_offered as f64
+    }
+
+    /// Share of authorized tasks refused.
+    pub fn false_rejection_rate(&self) -> f64 {
+        if self.authorized_offered == 0 {
+            return 0.0;
+        }
+        1.0 - self.authorized_accepted as f64 / self.authorized_offered as f64
+    }
+
+    pub fn overhead_percentile(&self, p: f64) -> Option<Duration> {
+        percentile_us(&self.overhead_us, p)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FaultType {
     /// Network partition between two groups
@@ -196,26 +385,529 @@ pub enum FaultType {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FaultEvent {
+    #[serde(with = "secs")]
     pub time: Duration,
     pub fault: FaultType,
 }
 
-/// Evaluation scenario configuration
+/// LoRa modulation and regulatory limits.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoRaParams {
+    /// Spreading factor, 7..=12.
+    pub spreading_factor: u8,
+    pub bandwidth_hz: u32,
+    /// Coding rate denominator offset: 1 means 4/5, 4 means 4/8.
+    pub coding_rate: u8,
+    pub preamble_symbols: u16,
+    /// Largest frame payload; bigger messages are fragmented.
+    pub max_payload: usize,
+    /// Fraction of `duty_window` a node may spend transmitting.
+    pub duty_cycle: f32,
+    #[serde(with = "secs")]
+    pub duty_window: Duration,
+}
+
+impl Default for LoRaParams {
+    /// SF7/125 kHz with the EU868 1% duty cycle.
+    fn default() -> Self {
+        Self {
+            spreading_factor: 7,
+            bandwidth_hz: 125_000,
+            coding_rate: 1,
+            preamble_symbols: 8,
+            max_payload: 222,
+            duty_cycle: 0.01,
+            duty_window: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl LoRaParams {
+    /// Semtech time-on-air for one frame with explicit header and CRC.
+    fn frame_airtime(&self, payload: usize) -> Duration {
+        let sf = f64::from(self.spreading_factor);
+        let symbol = 2f64.powf(sf) / f64::from(self.bandwidth_hz);
+        let low_data_rate = if symbol > 0.016 { 1.0 } else { 0.0 };
+        let preamble = (f64::from(self.preamble_symbols) + 4.25) * symbol;
+        let bits = 8.0 * payload as f64 - 4.0 * sf + 28.0 + 16.0;
+        let blocks = (bits / (4.0 * (sf - 2.0 * low_data_rate))).ceil().max(0.0);
+        let payload_symbols = 8.0 + blocks * f64::from(self.coding_rate + 4);
+        Duration::from_secs_f64(preamble + payload_symbols * symbol)
+    }
+}
+
+/// Bluetooth LE connection parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BleParams {
+    /// Application throughput after link-layer overhead.
+    pub throughput_bps: u32,
+    /// A node waits on average half an interval for its next connection event.
+    #[serde(with = "secs")]
+    pub connection_interval: Duration,
+    pub mtu: usize,
+}
+
+impl Default for BleParams {
+    fn default() -> Self {
+        Self {
+            throughput_bps: 250_000,
+            connection_interval: Duration::from_millis(30),
+            mtu: 244,
+        }
+    }
+}
+
+/// How one hop between simulated nodes behaves.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum LinkModel {
+    /// Fixed per-hop latency and no bandwidth or airtime limits.
+    #[default]
+    Ideal,
+    LoRa(LoRaParams),
+    Ble(BleParams),
+}
+
+impl LinkModel {
+    /// Time the sender's radio is busy transmitting `bytes`.
+    pub fn airtime(&self, bytes: usize) -> Duration {
+        match self {
+            LinkModel::Ideal => Duration::ZERO,
+            LinkModel::LoRa(lora) => {
+                let frame = lora.max_payload.max(1);
+                let full = bytes / frame;
+                let rest = bytes % frame;
+                let mut airtime = lora.frame_airtime(frame) * full as u32;
+                if rest > 0 || full == 0 {
+                    airtime += lora.frame_airtime(rest);
+                }
+                airtime
+            }
+            LinkModel::Ble(ble) => {
+                Duration::from_secs_f64(bytes as f64 * 8.0 / f64::from(ble.throughput_bps.max(1)))
+            }
+        }
+    }
+
+    /// Expected one-hop latency for a message of `bytes`, excluding queueing
+    /// behind the duty cycle.
+    pub fn hop_latency(&self, bytes: usize) -> Duration {
+        match self {
+            LinkModel::Ideal => Duration::from_millis(15),
+            LinkModel::LoRa(_) => self.airtime(bytes),
+            LinkModel::Ble(ble) => ble.connection_interval / 2 + self.airtime(bytes),
+        }
+    }
+
+    /// A fresh per-node airtime budget, if the link is duty-cycle limited.
+    pub fn airtime_budget(&self) -> Option<AirtimeBudget> {
+        match self {
+            LinkModel::LoRa(lora) => Some(AirtimeBudget::new(lora.duty_cycle, lora.duty_window)),
+            LinkModel::Ideal | LinkModel::Ble(_) => None,
+        }
+    }
+}
+
+/// Sliding-window duty-cycle accounting for one simulated radio.
 #[derive(Debug, Clone)]
+pub struct AirtimeBudget {
+    window: Duration,
+    allowance: Duration,
+    used: Duration,
+    /// (start of transmission, airtime), oldest first.
+    transmissions: VecDeque<(Duration, Duration)>,
+}
+
+impl AirtimeBudget {
+    pub fn new(duty_cycle: f32, window: Duration) -> Self {
+        Self {
+            window,
+            allowance: window.mul_f32(duty_cycle.clamp(0.0, 1.0)),
+            used: Duration::ZERO,
+            transmissions: VecDeque::new(),
+        }
+    }
+
+    /// Airtime left in the window ending at simulated time `now`.
+    pub fn remaining(&mut self, now: Duration) -> Duration {
+        while let Some(&(at, airtime)) = self.transmissions.front() {
+            if now.saturating_sub(at) < self.window {
+                break;
+            }
+            self.transmissions.pop_front();
+            self.used -= airtime;
+        }
+        self.allowance.saturating_sub(self.used)
+    }
+
+    /// Spend `airtime` at `now` if the budget allows it.
+    pub fn try_transmit(&mut self, now: Duration, airtime: Duration) -> bool {
+        if airtime > self.remaining(now) {
+            return false;
+        }
+        self.used += airtime;
+        self.transmissions.push_back((now, airtime));
+        true
+    }
+}
+
+/// Hardware a simulated node stands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeviceClass {
+    MainsGateway,
+    Phone,
+    SolarSensor,
+    CoinCellBeacon,
+}
+
+impl DeviceClass {
+    pub const ALL: [DeviceClass; 4] = [
+        DeviceClass::MainsGateway,
+        DeviceClass::Phone,
+        DeviceClass::SolarSensor,
+        DeviceClass::CoinCellBeacon,
+    ];
+
+    /// Name used as `SporeNode::device_class`, so calibration data from a
+    /// simulated fleet lands in the same buckets as a real one.
+    pub fn name(self) -> &'static str {
+        match self {
+            DeviceClass::MainsGateway => "gateway",
+            DeviceClass::Phone => "phone",
+            DeviceClass::SolarSensor => "solar_sensor",
+            DeviceClass::CoinCellBeacon => "beacon",
+        }
+    }
+
+    pub fn profile(self) -> DeviceProfile {
+        const DAY: Duration = Duration::from_secs(24 * 3600);
+        match self {
+            DeviceClass::MainsGateway => DeviceProfile {
+                is_mains: true,
+                mah: 2500.0,
+                voltage: 4.2,
+                rx_mah: 0.05,
+                tx_mah: 0.1,
+                bandwidth_bps: 10_000_000,
+                uptime: Uptime::Always,
+            },
+            // Off the network overnight.
+            DeviceClass::Phone => DeviceProfile {
+                is_mains: false,
+                mah: 2500.0,
+                voltage: 4.0,
+                rx_mah: 0.1,
+                tx_mah: 0.5,
+                bandwidth_bps: 1_000_000,
+                uptime: Uptime::Periodic {
+                    period: DAY,
+                    on: Duration::from_secs(16 * 3600),
+                },
+            },
+            // An 802.15.4 radio that sleeps while there is no sun.
+            DeviceClass::SolarSensor => DeviceProfile {
+                is_mains: false,
+                mah: 1000.0,
+                voltage: 3.8,
+                rx_mah: 0.05,
+                tx_mah: 0.2,
+                bandwidth_bps: 250_000,
+                uptime: Uptime::Periodic {
+                    period: DAY,
+                    on: Duration::from_secs(12 * 3600),
+                },
+            },
+            // A CR2032 waking for one second in ten.
+            DeviceClass::CoinCellBeacon => DeviceProfile {
+                is_mains: false,
+                mah: 220.0,
+                voltage: 3.6,
+                rx_mah: 0.02,
+                tx_mah: 0.05,
+                bandwidth_bps: 125_000,
+                uptime: Uptime::Periodic {
+                    period: Duration::from_secs(10),
+                    on: Duration::from_secs(1),
+                },
+            },
+        }
+    }
+}
+
+/// When a simulated device can send and receive.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Uptime {
+    Always,
+    /// Up for the first `on` of every `period`, shifted by a per-node offset
+    /// so a fleet does not wake in lockstep.
+    Periodic {
+        period: Duration,
+        on: Duration,
+    },
+}
+
+impl Uptime {
+    pub fn is_up(&self, at: Duration, offset: Duration) -> bool {
+        match *self {
+            Uptime::Always => true,
+            Uptime::Periodic { period, on } => {
+                let period = period.as_nanos().max(1);
+                (at + offset).as_nanos() % period < on.as_nanos()
+            }
+        }
+    }
+}
+
+/// Metabolism, radio and uptime parameters of one [`DeviceClass`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceProfile {
+    pub is_mains: bool,
+    /// Charge at the start of a run.
+    pub mah: f32,
+    pub voltage: f32,
+    /// Charge spent receiving one message.
+    pub rx_mah: f32,
+    /// Charge spent publishing or relaying one message.
+    pub tx_mah: f32,
+    pub bandwidth_bps: u64,
+    pub uptime: Uptime,
+}
+
+impl DeviceProfile {
+    /// Time to put `bytes` on this device's link, on top of the link model.
+    pub fn transfer_time(&self, bytes: usize) -> Duration {
+        Duration::from_secs_f64(bytes as f64 * 8.0 / self.bandwidth_bps.max(1) as f64)
+    }
+}
+
+/// Results for the nodes of one device class.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceStats {
+    pub class: DeviceClass,
+    pub nodes: usize,
+    /// Messages received by nodes of the class.
+    pub deliveries: u64,
+    pub mah_consumed: f32,
+    pub nodes_exhausted: usize,
+}
+
+/// How simulated nodes are wired together. A node only gossips to its
+/// neighbors, so every topology but `FullMesh` is sparse.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum Topology {
+    /// Every node neighbors every other.
+    #[default]
+    FullMesh,
+    /// Each node links to `neighbors` nodes on either side of a ring.
+    Ring { neighbors: usize },
+    /// Nodes sit on a square lattice one unit apart, linked when within
+    /// `radio_range` units: 1.0 reaches four neighbors, 1.5 eight.
+    Grid { radio_range: f32 },
+    /// Barabási–Albert preferential attachment: each node joins by linking
+    /// to `attachments` existing nodes, picked in proportion to their
+    /// degree, which grows a few well-connected hubs.
+    ScaleFree { attachments: usize },
+    /// `clusters` sites scattered over a unit square, with nodes spread up
+    /// to `spread` around their site and linked within `radio_range`.
+    /// Sites out of range of each other stay partitioned.
+    Geo {
+        clusters: usize,
+        spread: f32,
+        radio_range: f32,
+    },
+}
+
+impl Topology {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Topology::FullMesh => "full_mesh",
+            Topology::Ring { .. } => "ring",
+            Topology::Grid { .. } => "grid",
+            Topology::ScaleFree { .. } => "scale_free",
+            Topology::Geo { .. } => "geo",
+        }
+    }
+
+    /// Neighbor lists for `n` nodes, with any random placement drawn from
+    /// `rng`. `None` for a full mesh, which needs no list.
+    pub fn build(&self, n: usize, rng: &mut impl Rng) -> Option<Vec<Vec<usize>>> {
+        let mut graph = vec![Vec::new(); n];
+        match *self {
+            Topology::FullMesh => return None,
+            Topology::Ring { neighbors } => {
+                for a in 0..n {
+                    for k in 1..=neighbors.min(n / 2) {
+                        link(&mut graph, a, (a + k) % n);
+                    }
+                }
+            }
+            Topology::Grid { radio_range } => {
+                let width = (n as f64).sqrt().ceil().max(1.0) as usize;
+                let points: Vec<(f32, f32)> = (0..n)
+                    .map(|i| ((i % width) as f32, (i / width) as f32))
+                    .collect();
+                link_within(&mut graph, &points, radio_range);
+            }
+            Topology::ScaleFree { attachments } => {
+                let m = attachments.max(1);
+                // A small clique to attach to, then every link's two ends
+                // as the degree-weighted pool to draw from.
+                let seed = (m + 1).min(n);
+                for a in 0..seed {
+                    for b in a + 1..seed {
+                        link(&mut graph, a, b);
+                    }
+                }
+                let mut ends: Vec<usize> = (0..seed)
+                    .flat_map(|a| std::iter::repeat_n(a, graph[a].len()))
+                    .collect();
+                for joining in seed..n {
+                    let mut targets = Vec::with_capacity(m);
+                    while targets.len() < m {
+                        let target = ends[rng.random_range(0..ends.len())];
+                        if !targets.contains(&target) {
+                            targets.push(target);
+                        }
+                    }
+                    for target in targets {
+                        link(&mut graph, joining, target);
+                        ends.extend([joining, target]);
+                    }
+                }
+            }
+            Topology::Geo {
+                clusters,
+                spread,
+                radio_range,
+            } => {
+                let sites: Vec<(f32, f32)> = (0..clusters.max(1))
+                    .map(|_| (rng.random(), rng.random()))
+                    .collect();
+                let points: Vec<(f32, f32)> = (0..n)
+                    .map(|i| {
+                        let (x, y) = sites[i % sites.len()];
+                        let angle = rng.random::<f32>() * std::f32::consts::TAU;
+                        let r = spread * rng.random::<f32>().sqrt();
+                        (x + r * angle.cos(), y + r * angle.sin())
+                    })
+                    .collect();
+                link_within(&mut graph, &points, radio_range);
+            }
+        }
+        Some(graph)
+    }
+}
+
+fn link(graph: &mut [Vec<usize>], a: usize, b: usize) {
+    if a != b && !graph[a].contains(&b) {
+        graph[a].push(b);
+        graph[b].push(a);
+    }
+}
+
+/// Link every pair of `points` at most `range` apart.
+fn link_within(graph: &mut [Vec<usize>], points: &[(f32, f32)], range: f32) {
+    for a in 0..points.len() {
+        for b in a + 1..points.len() {
+            let (dx, dy) = (points[a].0 - points[b].0, points[a].1 - points[b].1);
+            if dx.hypot(dy) <= range {
+                link(graph, a, b);
+            }
+        }
+    }
+}
+
+/// What is wrong with an unauthorized task's token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum TokenFault {
+    /// No token at all.
+    Missing,
+    /// Rooted at an issuer the node does not trust.
+    Forged,
+    Expired,
+    /// Granted to another node.
+    Misdirected,
+    /// Grants less than the task requires.
+    Overreaching,
+}
+
+impl TokenFault {
+    pub const ALL: [Self; 5] = [
+        Self::Missing,
+        Self::Forged,
+        Self::Expired,
+        Self::Misdirected,
+        Self::Overreaching,
+    ];
+}
+
+/// Tasks offered to the swarm during a scenario, a share of them without a
+/// valid token.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthWorkload {
+    pub task_count: usize,
+    /// Percentage of tasks injected without a valid token.
+    pub unauthorized_percentage: f32,
+    /// Nodes refuse tasks with no token, not only tasks with a bad one.
+    pub secure: bool,
+    /// Current drawn while verifying, in mA, to price verification time.
+    pub cpu_ma: f32,
+}
+
+impl Default for AuthWorkload {
+    fn default() -> Self {
+        Self {
+            task_count: 200,
+            unauthorized_percentage: 20.0,
+            secure: true,
+            cpu_ma: 80.0,
+        }
+    }
+}
+
+impl AuthWorkload {
+    /// The fault injected into the `index`th task, if any. Faulty tasks are
+    /// spread evenly at `unauthorized_percentage` and cycle through every
+    /// kind of fault.
+    pub fn fault_for(&self, index: usize) -> Option<TokenFault> {
+        let share = (self.unauthorized_percentage as f64 / 100.0).clamp(0.0, 1.0);
+        let before = (index as f64 * share).floor() as usize;
+        let after = ((index + 1) as f64 * share).floor() as usize;
+        (after > before).then(|| TokenFault::ALL[before % TokenFault::ALL.len()])
+    }
+}
+
+/// Evaluation scenario configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct EvalScenario {
     pub name: String,
     pub node_count: usize,
     pub publisher_count: usize,
     pub message_rate_per_sec: f32,
     pub message_size_bytes: usize,
+    #[serde(with = "secs")]
     pub duration: Duration,
+    #[serde(with = "secs")]
     pub warmup: Duration,
+    #[serde(with = "secs")]
     pub cooldown: Duration,
     pub fault_schedule: Vec<FaultEvent>,
     /// Percentage of nodes starting with low energy
     pub low_energy_percentage: f32,
     /// Ratio of low-scoring peers included from the start.
     pub low_score_ratio: f32,
+    /// Radio between every pair of neighbors.
+    pub link: LinkModel,
+    /// Relative share of each device class. Empty keeps every node a
+    /// default battery node.
+    pub device_mix: Vec<(DeviceClass, f32)>,
+    /// Which nodes neighbor which.
+    pub topology: Topology,
+    /// Tasks to offer alongside the gossip load; `None` offers none.
+    pub authorization: Option<AuthWorkload>,
 }
 
 impl Default for EvalScenario {
@@ -232,6 +924,10 @@ impl Default for EvalScenario {
             fault_schedule: vec![],
             low_energy_percentage: 0.0,
             low_score_ratio: 0.0,
+            link: LinkModel::Ideal,
+            device_mix: Vec::new(),
+            topology: Topology::FullMesh,
+            authorization: None,
         }
     }
 }
@@ -301,6 +997,393 @@ impl EvalScenario {
             ..Default::default()
         }
     }
+
+    /// A realistic fleet: a few mains gateways, phones, and mostly solar
+    /// sensors and coin-cell beacons.
+    pub fn mixed_fleet(node_count: usize) -> Self {
+        Self {
+            name: "mixed_fleet".to_string(),
+            node_count,
+            publisher_count: (node_count / 10).max(1),
+            device_mix: vec![
+                (DeviceClass::MainsGateway, 0.05),
+                (DeviceClass::Phone, 0.25),
+                (DeviceClass::SolarSensor, 0.4),
+                (DeviceClass::CoinCellBeacon, 0.3),
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// The phase a sample taken `elapsed` into the run belongs to: the first
+    /// `warmup`, then `duration`, then `cooldown`.
+    pub fn phase_at(&self, elapsed: Duration) -> Phase {
+        if elapsed < self.warmup {
+            Phase::Warmup
+        } else if elapsed < self.warmup + self.duration {
+            Phase::Measured
+        } else {
+            Phase::Cooldown
+        }
+    }
+
+    /// Warmup, measured duration and cooldown together.
+    pub fn total_duration(&self) -> Duration {
+        self.warmup + self.duration + self.cooldown
+    }
+
+    /// The class of each node, spread evenly through the index range in
+    /// proportion to `device_mix`. Empty when the mix is.
+    pub fn devices(&self) -> Vec<DeviceClass> {
+        let total: f32 = self
+            .device_mix
+            .iter()
+            .map(|(_, share)| share.max(0.0))
+            .sum();
+        if total <= 0.0 {
+            return Vec::new();
+        }
+        let mut assigned = vec![0usize; self.device_mix.len()];
+        (0..self.node_count)
+            .map(|i| {
+                // The class furthest behind its share so far.
+                let deficit = |(j, (_, share)): (usize, &(DeviceClass, f32))| {
+                    share.max(0.0) / total * (i + 1) as f32 - assigned[j] as f32
+                };
+                let (j, (class, _)) = self
+                    .device_mix
+                    .iter()
+                    .enumerate()
+                    .max_by(|a, b| deficit(*a).total_cmp(&deficit(*b)))
+                    .expect("mix is not empty");
+                assigned[j] += 1;
+                *class
+            })
+            .collect()
+    }
+
+    /// Read a scenario from a `.toml` or `.json` file. Without a `name`, the
+    /// scenario is named after the file.
+    pub fn load(path: &Path) -> Result<Self, ScenarioError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ScenarioError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let parsed = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str::<toml::Table>(&text)
+                .and_then(|mut table| {
+                    table.entry("name").or_insert(stem.into());
+                    table.try_into()
+                })
+                .map_err(|e| e.to_string()),
+            Some("json") => serde_json::from_str::<serde_json::Value>(&text)
+                .and_then(|mut value| {
+                    if let Some(object) = value.as_object_mut() {
+                        object.entry("name").or_insert(stem.into());
+                    }
+                    serde_json::from_value(value)
+                })
+                .map_err(|e| e.to_string()),
+            _ => return Err(ScenarioError::Format(path.to_path_buf())),
+        };
+        parsed.map_err(|message| ScenarioError::Parse {
+            path: path.to_path_buf(),
+            message,
+        })
+    }
+
+    /// The scenarios pinned by the committed evaluation baseline.
+    pub fn regression_suite() -> Vec<Self> {
+        vec![
+            Self::baseline(100),
+            Self {
+                name: "percolation_30pct_dead".to_string(),
+                low_energy_percentage: 30.0,
+                ..Default::default()
+            },
+            Self::degradation_attack(0.2),
+            Self::partition_test(),
+            Self::mixed_fleet(40),
+            Self::lora_field(30, 9),
+        ]
+    }
+
+    /// `node_count` nodes wired as `topology`.
+    pub fn sparse(node_count: usize, topology: Topology) -> Self {
+        Self {
+            name: topology.name().to_string(),
+            topology,
+            ..Self::baseline(node_count)
+        }
+    }
+
+    /// One scenario per sparse topology, at sizes typical of field
+    /// deployments.
+    pub fn topology_sweep(node_count: usize) -> Vec<Self> {
+        [
+            Topology::Ring { neighbors: 2 },
+            Topology::Grid { radio_range: 1.5 },
+            Topology::ScaleFree { attachments: 2 },
+            Topology::Geo {
+                clusters: 4,
+                spread: 0.15,
+                radio_range: 0.1,
+            },
+        ]
+        .into_iter()
+        .map(|topology| Self::sparse(node_count, topology))
+        .collect()
+    }
+
+    /// Tasks offered with `unauthorized_percentage` of them lacking a valid
+    /// token, to nodes that require tokens when `secure`.
+    pub fn authorization(node_count: usize, unauthorized_percentage: f32, secure: bool) -> Self {
+        Self {
+            name: format!(
+                "auth_{}pct_{}",
+                unauthorized_percentage,
+                if secure { "secure" } else { "open" }
+            ),
+            node_count,
+            publisher_count: (node_count / 10).max(1),
+            authorization: Some(AuthWorkload {
+                unauthorized_percentage,
+                secure,
+                ..AuthWorkload::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Sparse LoRa field deployment: small payloads, low rate, 1% duty cycle.
+    pub fn lora_field(node_count: usize, spreading_factor: u8) -> Self {
+        Self {
+            name: format!("lora_sf{}", spreading_factor),
+            node_count,
+            publisher_count: (node_count / 10).max(1),
+            message_rate_per_sec: 0.1,
+            message_size_bytes: 64,
+            duration: Duration::from_secs(3600),
+            link: LinkModel::LoRa(LoRaParams {
+                spreading_factor,
+                ..LoRaParams::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// One periodic observation of a long-running swarm.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SoakSample {
+    pub elapsed: Duration,
+    /// Resident set size of the process, where the platform reports it.
+    pub rss_bytes: Option<u64>,
+    /// Sum of `TopicMesh::message_cache` sizes across nodes.
+    pub message_cache: usize,
+    /// Sum of `TopicMesh::known_peers` sizes across nodes.
+    pub known_peers: usize,
+    /// Bytes on disk under the nodes' storage directories.
+    pub disk_bytes: u64,
+    /// Median delivery latency since the previous sample.
+    pub latency_p50_us: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoakMetric {
+    RssBytes,
+    MessageCache,
+    KnownPeers,
+    DiskBytes,
+    LatencyP50,
+}
+
+impl SoakMetric {
+    pub const ALL: [SoakMetric; 5] = [
+        SoakMetric::RssBytes,
+        SoakMetric::MessageCache,
+        SoakMetric::KnownPeers,
+        SoakMetric::DiskBytes,
+        SoakMetric::LatencyP50,
+    ];
+
+    fn value(self, sample: &SoakSample) -> Option<f64> {
+        match self {
+            SoakMetric::RssBytes => sample.rss_bytes.map(|b| b as f64),
+            SoakMetric::MessageCache => Some(sample.message_cache as f64),
+            SoakMetric::KnownPeers => Some(sample.known_peers as f64),
+            SoakMetric::DiskBytes => Some(sample.disk_bytes as f64),
+            SoakMetric::LatencyP50 => sample.latency_p50_us.map(|us| us as f64),
+        }
+    }
+}
+
+/// Growth allowed between the first and last third of a soak, past warmup.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GrowthBound {
+    /// Fractional increase tolerated, e.g. 0.25 for 25%.
+    pub relative: f64,
+    /// Absolute increase always tolerated, so tiny baselines don't trip.
+    pub absolute: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoakConfig {
+    /// Fraction of the samples treated as warmup and ignored.
+    pub warmup_fraction: f64,
+    pub rss: GrowthBound,
+    pub message_cache: GrowthBound,
+    pub known_peers: GrowthBound,
+    pub disk: GrowthBound,
+    pub latency: GrowthBound,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            warmup_fraction: 0.25,
+            rss: GrowthBound {
+                relative: 0.25,
+                absolute: 16.0 * 1024.0 * 1024.0,
+            },
+            message_cache: GrowthBound {
+                relative: 0.5,
+                absolute: 1000.0,
+            },
+            known_peers: GrowthBound {
+                relative: 0.5,
+                absolute: 8.0,
+            },
+            disk: GrowthBound {
+                relative: 0.5,
+                absolute: 64.0 * 1024.0 * 1024.0,
+            },
+            latency: GrowthBound {
+                relative: 1.0,
+                absolute: 50_000.0,
+            },
+        }
+    }
+}
+
+impl SoakConfig {
+    fn bound(&self, metric: SoakMetric) -> GrowthBound {
+        match metric {
+            SoakMetric::RssBytes => self.rss,
+            SoakMetric::MessageCache => self.message_cache,
+            SoakMetric::KnownPeers => self.known_peers,
+            SoakMetric::DiskBytes => self.disk,
+            SoakMetric::LatencyP50 => self.latency,
+        }
+    }
+}
+
+/// A metric that kept growing through the soak.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SoakViolation {
+    pub metric: SoakMetric,
+    /// Mean over the first third of the post-warmup samples.
+    pub early: f64,
+    pub middle: f64,
+    /// Mean over the last third.
+    pub late: f64,
+}
+
+/// Collects [`SoakSample`]s and flags metrics that grow without bound.
+///
+/// A metric is flagged when its late mean exceeds the early mean by more than
+/// its [`GrowthBound`] and the middle third sits between the two, i.e. growth
+/// is sustained rather than a one-off step.
+#[derive(Debug, Default)]
+pub struct SoakMonitor {
+    pub config: SoakConfig,
+    samples: Vec<SoakSample>,
+}
+
+impl SoakMonitor {
+    pub fn new(config: SoakConfig) -> Self {
+        Self {
+            config,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, sample: SoakSample) {
+        self.samples.push(sample);
+    }
+
+    pub fn samples(&self) -> &[SoakSample] {
+        &self.samples
+    }
+
+    pub fn violations(&self) -> Vec<SoakViolation> {
+        let skip = (self.samples.len() as f64 * self.config.warmup_fraction) as usize;
+        let steady = &self.samples[skip.min(self.samples.len())..];
+        let mut violations = Vec::new();
+        for metric in SoakMetric::ALL {
+            let values: Vec<f64> = steady.iter().filter_map(|s| metric.value(s)).collect();
+            if values.len() < 6 {
+                continue;
+            }
+            let third = values.len() / 3;
+            let mean = |xs: &[f64]| xs.iter().sum::<f64>() / xs.len() as f64;
+            let early = mean(&values[..third]);
+            let middle = mean(&values[third..values.len() - third]);
+            let late = mean(&values[values.len() - third..]);
+            let bound = self.config.bound(metric);
+            let allowed = early * (1.0 + bound.relative) + bound.absolute;
+            if late > allowed && middle > early && middle < late {
+                violations.push(SoakViolation {
+                    metric,
+                    early,
+                    middle,
+                    late,
+                });
+            }
+        }
+        violations
+    }
+}
+
+/// Resident set size of this process. Linux only; `None` elsewhere.
+pub fn process_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Total size of the regular files under `path`.
+pub fn dir_size(path: &std::path::Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Per-tenant counters on a multi-tenant node.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TenantMetrics {
+    /// Tasks for the tenant seen on the task topic.
+    pub tasks_seen: u64,
+    /// Tenant tokens that verified.
+    pub tasks_authorized: u64,
+    /// Tenant tokens that did not, including tokens for another tenant.
+    pub tasks_rejected: u64,
+    /// Remote updates that changed the tenant's shared-state doc.
+    pub state_updates: u64,
 }
 
 /// Collector for metrics during evaluation
@@ -311,6 +1394,15 @@ pub struct MetricsCollector {
     energy_samples: Vec<(Duration, Vec<f32>)>,
     consistency_samples: Vec<(Duration, usize)>, // (time, divergence count)
     fault_events: Vec<FaultEvent>,
+    tenants: HashMap<String, TenantMetrics>,
+    devices: Vec<DeviceStats>,
+    nodes: Vec<NodeMetrics>,
+    phase: Phase,
+    warmup: PhaseMetrics,
+    cooldown: PhaseMetrics,
+    /// Swarm-wide consumption when `phase` began.
+    phase_start_mah: f32,
+    auth: Option<AuthMetrics>,
 }
 
 impl MetricsCollector {
@@ -321,19 +1413,115 @@ impl MetricsCollector {
         }
     }
 
+    /// Attribute what follows to `phase`. `spent_mah` is the swarm's total
+    /// consumption so far; what was spent since the last call is charged to
+    /// the phase that is ending.
+    pub fn enter_phase(&mut self, phase: Phase, spent_mah: f32) {
+        if phase == self.phase {
+            return;
+        }
+        let spent = spent_mah - self.phase_start_mah;
+        match self.phase {
+            Phase::Warmup => self.warmup.mah_consumed += spent,
+            Phase::Cooldown => self.cooldown.mah_consumed += spent,
+            Phase::Measured => {}
+        }
+        self.phase = phase;
+        self.phase_start_mah = spent_mah;
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    fn delivery_mut(&mut self) -> &mut DeliveryMetrics {
+        match self.phase {
+            Phase::Warmup => &mut self.warmup.delivery,
+            Phase::Measured => &mut self.delivery,
+            Phase::Cooldown => &mut self.cooldown.delivery,
+        }
+    }
+
     /// Set expected deliveries based on message count and node count
     pub fn set_expected_deliveries(&mut self, node_count: usize) {
-        self.delivery.expected_deliveries = self.delivery.messages_published * node_count as u64;
+        let delivery = self.delivery_mut();
+        delivery.expected_deliveries = delivery.messages_published * node_count as u64;
     }
 
     pub fn record_publish(&mut self, node_count: usize) {
-        self.delivery.messages_published += 1;
-        self.delivery.expected_deliveries += node_count as u64;
+        let delivery = self.delivery_mut();
+        delivery.messages_published += 1;
+        delivery.expected_deliveries += node_count as u64;
     }
 
     pub fn record_delivery(&mut self, latency: Duration) {
-        self.delivery.messages_delivered += 1;
-        self.delivery.latencies_us.push(latency.as_micros() as u64);
+        let delivery = self.delivery_mut();
+        delivery.messages_delivered += 1;
+        delivery.latencies_us.push(latency.as_micros() as u64);
+    }
+
+    pub fn messages_delivered(&self) -> u64 {
+        self.delivery.messages_delivered
+    }
+
+    pub fn record_publish_queued(&mut self) {
+        self.delivery_mut().publishes_queued += 1;
+    }
+
+    pub fn record_publish_dropped(&mut self) {
+        self.delivery_mut().publishes_dropped += 1;
+    }
+
+    pub fn record_publish_bytes(&mut self, encoded: usize, sent: usize) {
+        let delivery = self.delivery_mut();
+        delivery.bytes_encoded += encoded as u64;
+        delivery.bytes_sent += sent as u64;
+    }
+
+    pub fn compression_ratio(&self) -> f64 {
+        self.delivery.compression_ratio()
+    }
+
+    pub fn publishes_queued(&self) -> u64 {
+        self.delivery.publishes_queued
+    }
+
+    pub fn publishes_dropped(&self) -> u64 {
+        self.delivery.publishes_dropped
+    }
+
+    /// Counters for `tenant`, created on first use.
+    pub fn tenant_mut(&mut self, tenant: &str) -> &mut TenantMetrics {
+        self.tenants.entry(tenant.to_string()).or_default()
+    }
+
+    pub fn tenant(&self, tenant: &str) -> TenantMetrics {
+        self.tenants.get(tenant).cloned().unwrap_or_default()
+    }
+
+    /// Note whether an offered task, unauthorized by `fault` or authorized
+    /// when `None`, won a bid.
+    pub fn record_authorization(&mut self, fault: Option<TokenFault>, accepted: bool) {
+        let auth = self.auth.get_or_insert_with(AuthMetrics::default);
+        match fault {
+            None => {
+                auth.authorized_offered += 1;
+                auth.authorized_accepted += accepted as u64;
+            }
+            Some(fault) => {
+                auth.unauthorized_offered += 1;
+                if accepted {
+                    *auth.slipped.entry(fault).or_default() += 1;
+                }
+            }
+        }
+    }
+
+    /// Note `overhead` spent verifying a token, drawing `cpu_ma`.
+    pub fn record_verify_overhead(&mut self, overhead: Duration, cpu_ma: f32) {
+        let auth = self.auth.get_or_insert_with(AuthMetrics::default);
+        auth.overhead_us.push(overhead.as_micros() as u64);
+        auth.verify_mah += cpu_ma * overhead.as_secs_f32() / 3600.0;
     }
 
     pub fn record_energy_snapshot(&mut self, scores: Vec<f32>) {
@@ -346,6 +1534,14 @@ impl MetricsCollector {
         self.consistency_samples.push((elapsed, divergence_count));
     }
 
+    pub fn record_device(&mut self, stats: DeviceStats) {
+        self.devices.push(stats);
+    }
+
+    pub fn record_node(&mut self, metrics: NodeMetrics) {
+        self.nodes.push(metrics);
+    }
+
     pub fn record_fault(&mut self, fault: FaultType) {
         let elapsed = self.start_time.map(|s| s.elapsed()).unwrap_or_default();
         self.fault_events.push(FaultEvent {
@@ -354,7 +1550,12 @@ impl MetricsCollector {
         });
     }
 
-    pub fn finalize(self, scenario: &EvalScenario, mah_consumed: f32) -> EvalRun {
+    /// Close the run. `mah_consumed` is the whole run's consumption; the
+    /// headline energy metrics keep only what was not charged to warmup or
+    /// cooldown.
+    pub fn finalize(mut self, scenario: &EvalScenario, mah_consumed: f32) -> EvalRun {
+        self.enter_phase(Phase::Measured, mah_consumed);
+        let mah_consumed = mah_consumed - self.warmup.mah_consumed - self.cooldown.mah_consumed;
         let final_scores = self
             .energy_samples
             .last()
@@ -410,6 +1611,11 @@ impl MetricsCollector {
                 final_divergence_ln,
             },
             fault_events: self.fault_events,
+            warmup: self.warmup,
+            cooldown: self.cooldown,
+            devices: self.devices,
+            nodes: self.nodes,
+            auth: self.auth,
         }
     }
 }
@@ -480,6 +1686,398 @@ impl EvalSummary {
     }
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum ScenarioError {
+    #[error("cannot read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{0} is neither a .toml nor a .json scenario")]
+    Format(PathBuf),
+    #[error("invalid scenario {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+}
+
+/// Every `.toml` and `.json` scenario in `dir`, in file name order. Other
+/// files are skipped.
+pub fn load_scenarios(dir: &Path) -> Result<Vec<EvalScenario>, ScenarioError> {
+    let io = |source| ScenarioError::Io {
+        path: dir.to_path_buf(),
+        source,
+    };
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir).map_err(io)? {
+        let path = entry.map_err(io)?.path();
+        if matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("toml" | "json")
+        ) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    paths.iter().map(|path| EvalScenario::load(path)).collect()
+}
+
+/// Durations as (fractional) seconds in scenario files.
+mod secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Gossip fanout of a relaying node in [`simulate`].
+const SIM_FANOUT: usize = 6;
+
+/// Hops after which [`simulate`] stops propagating a message.
+const SIM_MAX_HOPS: usize = 12;
+
+/// Battery node used when a scenario has no `device_mix`.
+const SIM_DEFAULT_PROFILE: DeviceProfile = DeviceProfile {
+    is_mains: false,
+    mah: 2500.0,
+    voltage: 3.7,
+    rx_mah: 0.1,
+    tx_mah: 0.5,
+    bandwidth_bps: u64::MAX,
+    uptime: Uptime::Always,
+};
+
+struct SimNode {
+    profile: DeviceProfile,
+    /// Where the node starts in its uptime cycle.
+    offset: Duration,
+    mah: f32,
+    initial_mah: f32,
+    initial_energy: f32,
+    received: u64,
+    airtime: Option<AirtimeBudget>,
+    crashed: bool,
+    /// Which side of a partition the node is on.
+    side_b: bool,
+}
+
+impl SimNode {
+    fn energy(&self) -> f32 {
+        if self.profile.is_mains {
+            1.0
+        } else {
+            (self.mah / self.profile.mah).clamp(0.0, 1.0)
+        }
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.energy() < 0.05
+    }
+
+    fn is_up(&self, now: Duration) -> bool {
+        !self.crashed && !self.is_exhausted() && self.profile.uptime.is_up(now, self.offset)
+    }
+
+    fn spend(&mut self, mah: f32) {
+        if !self.profile.is_mains {
+            self.mah = (self.mah - mah).max(0.0);
+        }
+    }
+}
+
+fn sim_node_index(id: &str) -> Option<usize> {
+    id.strip_prefix("node_")?.parse().ok()
+}
+
+/// Run `scenario` in memory. Every random choice (uptime offsets, starting
+/// charge of low-energy nodes, peer selection, drops, relay decisions and
+/// jitter) comes from `seed`, so the same scenario and seed always give the
+/// same run.
+///
+/// Nodes gossip each message to [`SIM_FANOUT`] random neighbors per hop
+/// rather than over a maintained mesh. Under a full mesh every node is a
+/// neighbor and per-node `mesh_size` is 0; otherwise it is the node's degree
+/// in the scenario's [`Topology`]. Faults take effect at their scheduled
+/// time; node ids are `node_<index>`.
+pub fn simulate(scenario: &EvalScenario, seed: u64) -> EvalRun {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut collector = MetricsCollector::new();
+    let n = scenario.node_count;
+    let devices = scenario.devices();
+    let low_energy_count = (n as f32 * scenario.low_energy_percentage / 100.0) as usize;
+
+    let mut nodes: Vec<SimNode> = (0..n)
+        .map(|i| {
+            let profile = devices
+                .get(i)
+                .map_or(SIM_DEFAULT_PROFILE, |class| class.profile());
+            let offset = match profile.uptime {
+                Uptime::Always => Duration::ZERO,
+                Uptime::Periodic { period, .. } => period.mul_f64(rng.random::<f64>()),
+            };
+            let mah = if i < low_energy_count {
+                rng.random_range(5.0..50.0)
+            } else {
+                profile.mah
+            };
+            let mut node = SimNode {
+                profile,
+                offset,
+                mah,
+                initial_mah: mah,
+                initial_energy: 0.0,
+                received: 0,
+                airtime: scenario.link.airtime_budget(),
+                crashed: false,
+                side_b: false,
+            };
+            node.initial_energy = node.energy();
+            node
+        })
+        .collect();
+    let spent = |nodes: &[SimNode]| -> f32 { nodes.iter().map(|n| n.initial_mah - n.mah).sum() };
+    let mut graph = scenario.topology.build(n, &mut rng);
+
+    let mut faults: Vec<&FaultEvent> = scenario.fault_schedule.iter().collect();
+    faults.sort_by_key(|event| event.time);
+    let mut faults = faults.into_iter().peekable();
+    let mut drop_probability = 0.0f32;
+    let mut partitioned = false;
+
+    let rate = scenario.message_rate_per_sec.max(f32::EPSILON);
+    let message_count = (scenario.total_duration().as_secs_f32() * rate) as usize;
+    let size = scenario.message_size_bytes;
+    let publishers = scenario.publisher_count.clamp(1, n.max(1));
+    let mut order: Vec<usize> = (0..n).collect();
+
+    for msg_idx in 0..message_count {
+        let sent_at = Duration::from_secs_f32(msg_idx as f32 / rate);
+        while let Some(event) = faults.next_if(|event| event.time <= sent_at) {
+            match &event.fault {
+                FaultType::Partition { group_b, .. } => {
+                    partitioned = true;
+                    for node in &mut nodes {
+                        node.side_b = false;
+                    }
+                    for i in group_b.iter().filter_map(|id| sim_node_index(id)) {
+                        if let Some(node) = nodes.get_mut(i) {
+                            node.side_b = true;
+                        }
+                    }
+                }
+                FaultType::PartitionHeal => partitioned = false,
+                FaultType::NodeCrash { node_ids } | FaultType::NodeRecover { node_ids } => {
+                    let crashed = matches!(event.fault, FaultType::NodeCrash { .. });
+                    for i in node_ids.iter().filter_map(|id| sim_node_index(id)) {
+                        if let Some(node) = nodes.get_mut(i) {
+                            node.crashed = crashed;
+                        }
+                    }
+                }
+                FaultType::Degradation {
+                    drop_probability: p,
+                } => drop_probability = *p,
+                FaultType::SyncSpike { .. } => {}
+            }
+            collector.record_fault(event.fault.clone());
+        }
+        let phase = scenario.phase_at(sent_at);
+        if phase != collector.phase() {
+            collector.enter_phase(phase, spent(&nodes));
+        }
+        collector.record_publish(n.saturating_sub(1));
+
+        // Publishers are taken from the end, away from the low-energy nodes.
+        let origin = n - 1 - msg_idx % publishers;
+        let mut delivered = vec![false; n];
+        delivered[origin] = true;
+        let mut wave = vec![(origin, 0u64)];
+        for _hop in 0..SIM_MAX_HOPS {
+            let mut next_wave = Vec::new();
+            for (from, latency) in wave {
+                let now = sent_at + Duration::from_micros(latency);
+                let sender = &mut nodes[from];
+                if !sender.is_up(now) {
+                    continue;
+                }
+                let airtime = scenario.link.airtime(size);
+                if let Some(budget) = &mut sender.airtime {
+                    if !budget.try_transmit(now, airtime) {
+                        continue;
+                    }
+                }
+                sender.spend(sender.profile.tx_mah);
+                let hop = scenario.link.hop_latency(size) + sender.profile.transfer_time(size);
+                let sender_side = sender.side_b;
+
+                let neighbors = match &mut graph {
+                    Some(graph) => &mut graph[from],
+                    None => &mut order,
+                };
+                let fanout = SIM_FANOUT.min(neighbors.len());
+                for k in 0..fanout {
+                    let j = rng.random_range(k..neighbors.len());
+                    neighbors.swap(k, j);
+                }
+                for &to in &neighbors[..fanout] {
+                    let receiver = &mut nodes[to];
+                    if delivered[to]
+                        || !receiver.is_up(now)
+                        || (partitioned && receiver.side_b != sender_side)
+                        || rng.random::<f32>() < drop_probability
+                    {
+                        continue;
+                    }
+                    delivered[to] = true;
+                    receiver.received += 1;
+                    receiver.spend(receiver.profile.rx_mah);
+                    let total = latency + hop.as_micros() as u64 + rng.random_range(0..5_000);
+                    collector.record_delivery(Duration::from_micros(total));
+
+                    // Pulse-gated relay: full-energy nodes always relay,
+                    // middling ones at most pulse peaks.
+                    let energy = receiver.energy();
+                    if energy > 0.9 || (energy > 0.6 && rng.random::<f32>() < 0.7) {
+                        next_wave.push((to, total));
+                    }
+                }
+            }
+            wave = next_wave;
+            if wave.is_empty() {
+                break;
+            }
+        }
+    }
+
+    collector.record_energy_snapshot(nodes.iter().map(SimNode::energy).collect());
+    let most = nodes.iter().map(|node| node.received).max().unwrap_or(0);
+    let divergence: u64 = nodes.iter().map(|node| most - node.received).sum();
+    collector.record_consistency(divergence as usize);
+    for class in DeviceClass::ALL {
+        let members: Vec<&SimNode> = (0..devices.len())
+            .filter(|&i| devices[i] == class)
+            .map(|i| &nodes[i])
+            .collect();
+        if members.is_empty() {
+            continue;
+        }
+        collector.record_device(DeviceStats {
+            class,
+            nodes: members.len(),
+            deliveries: members.iter().map(|node| node.received).sum(),
+            mah_consumed: members.iter().map(|node| node.initial_mah - node.mah).sum(),
+            nodes_exhausted: members.iter().filter(|node| node.is_exhausted()).count(),
+        });
+    }
+    for (i, node) in nodes.iter().enumerate() {
+        collector.record_node(NodeMetrics {
+            node: format!("node_{i}"),
+            deliveries: node.received,
+            mah_consumed: node.initial_mah - node.mah,
+            initial_energy: node.initial_energy,
+            mesh_size: graph.as_ref().map_or(0, |graph| graph[i].len()),
+        });
+    }
+    let total = spent(&nodes);
+    collector.finalize(scenario, total)
+}
+
+/// Committed expectations for [`simulate`]: one summary per scenario and
+/// seed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    pub entries: Vec<BaselineEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub seed: u64,
+    pub summary: EvalSummary,
+}
+
+impl Baseline {
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(std::io::Error::other)
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        let mut json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        json.push('\n');
+        std::fs::write(path, json)
+    }
+
+    pub fn get(&self, scenario: &str, seed: u64) -> Option<&EvalSummary> {
+        self.entries
+            .iter()
+            .find(|entry| entry.seed == seed && entry.summary.scenario == scenario)
+            .map(|entry| &entry.summary)
+    }
+
+    /// Set the expectation for `summary`'s scenario under `seed`.
+    pub fn record(&mut self, seed: u64, summary: EvalSummary) {
+        self.entries
+            .retain(|entry| !(entry.seed == seed && entry.summary.scenario == summary.scenario));
+        self.entries.push(BaselineEntry { seed, summary });
+    }
+}
+
+/// How far a rerun may fall behind its baseline before it counts as a
+/// regression. Improvements always pass.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Absolute drop in delivery rate.
+    pub delivery_rate: f64,
+    /// Relative rise in p99 latency.
+    pub p99: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            delivery_rate: 0.01,
+            p99: 0.10,
+        }
+    }
+}
+
+impl Tolerance {
+    pub fn regressions(&self, baseline: &EvalSummary, current: &EvalSummary) -> Vec<Regression> {
+        let mut regressions = Vec::new();
+        if current.delivery_rate_mean < baseline.delivery_rate_mean - self.delivery_rate {
+            regressions.push(Regression {
+                scenario: current.scenario.clone(),
+                metric: "delivery rate",
+                baseline: baseline.delivery_rate_mean,
+                current: current.delivery_rate_mean,
+            });
+        }
+        if current.p99_latency_mean_us > baseline.p99_latency_mean_us * (1.0 + self.p99) {
+            regressions.push(Regression {
+                scenario: current.scenario.clone(),
+                metric: "p99 latency (us)",
+                baseline: baseline.p99_latency_mean_us,
+                current: current.p99_latency_mean_us,
+            });
+        }
+        regressions
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("{scenario}: {metric} regressed from {baseline:.4} to {current:.4}")]
+pub struct Regression {
+    pub scenario: String,
+    pub metric: &'static str,
+    pub baseline: f64,
+    pub current: f64,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -540,4 +2138,361 @@ mod tests {
         let percolation = EvalScenario::percolation_sweep();
         assert_eq!(percolation.len(), 10);
     }
+
+    #[test]
+    fn lora_airtime_matches_semtech_calculator() {
+        let sf7 = LinkModel::LoRa(LoRaParams::default());
+        let airtime = sf7.airtime(20).as_secs_f64() * 1000.0;
+        assert!((airtime - 56.576).abs() < 0.01, "airtime was {airtime}ms");
+
+        // SF12 enables low data rate optimization and is ~25x slower.
+        let sf12 = LinkModel::LoRa(LoRaParams {
+            spreading_factor: 12,
+            ..LoRaParams::default()
+        });
+        assert!(sf12.airtime(20) > sf7.airtime(20) * 20);
+        // Fragmented messages pay per-frame overhead.
+        assert!(sf7.airtime(444) > sf7.airtime(222) * 2 - Duration::from_millis(1));
+    }
+
+    #[test]
+    fn duty_cycle_budget_refills_as_the_window_slides() {
+        let mut budget = LinkModel::LoRa(LoRaParams::default())
+            .airtime_budget()
+            .unwrap();
+        let frame = Duration::from_secs(1);
+        let transmitted = (0..40)
+            .filter(|&i| budget.try_transmit(Duration::from_secs(i), frame))
+            .count();
+        assert_eq!(transmitted, 36);
+        assert!(!budget.try_transmit(Duration::from_secs(3599), frame));
+        assert!(budget.try_transmit(Duration::from_secs(3600), frame));
+
+        assert!(LinkModel::Ble(BleParams::default())
+            .airtime_budget()
+            .is_none());
+        let scenario = EvalScenario::lora_field(20, 9);
+        assert!(matches!(scenario.link, LinkModel::LoRa(ref p) if p.spreading_factor == 9));
+    }
+
+    #[test]
+    fn device_mix_is_spread_in_proportion() {
+        assert!(EvalScenario::baseline(10).devices().is_empty());
+
+        let fleet = EvalScenario::mixed_fleet(100);
+        let devices = fleet.devices();
+        assert_eq!(devices.len(), 100);
+        let count = |class| devices.iter().filter(|d| **d == class).count();
+        assert_eq!(count(DeviceClass::MainsGateway), 5);
+        assert_eq!(count(DeviceClass::Phone), 25);
+        assert_eq!(count(DeviceClass::SolarSensor), 40);
+        assert_eq!(count(DeviceClass::CoinCellBeacon), 30);
+        // Every class shows up among the first publishers' neighbours.
+        for class in DeviceClass::ALL {
+            assert!(devices[..20].contains(&class), "{class:?}");
+        }
+    }
+
+    #[test]
+    fn uptime_follows_the_class_pattern() {
+        let beacon = DeviceClass::CoinCellBeacon.profile().uptime;
+        let up = (0..100)
+            .filter(|s| beacon.is_up(Duration::from_secs(*s), Duration::ZERO))
+            .count();
+        assert_eq!(up, 10);
+        assert!(!beacon.is_up(Duration::ZERO, Duration::from_secs(5)));
+        assert!(DeviceClass::MainsGateway
+            .profile()
+            .uptime
+            .is_up(Duration::from_secs(12345), Duration::ZERO));
+
+        let sensor = DeviceClass::SolarSensor.profile();
+        assert!(sensor.transfer_time(2048) > DeviceClass::Phone.profile().transfer_time(2048));
+    }
+
+    #[test]
+    fn warmup_and_cooldown_stay_out_of_the_headline() {
+        let scenario = EvalScenario {
+            warmup: Duration::from_secs(10),
+            duration: Duration::from_secs(20),
+            cooldown: Duration::from_secs(10),
+            ..EvalScenario::baseline(10)
+        };
+        assert_eq!(scenario.phase_at(Duration::from_secs(9)), Phase::Warmup);
+        assert_eq!(scenario.phase_at(Duration::from_secs(10)), Phase::Measured);
+        assert_eq!(scenario.phase_at(Duration::from_secs(30)), Phase::Cooldown);
+        assert_eq!(scenario.total_duration(), Duration::from_secs(40));
+
+        let mut collector = MetricsCollector::new();
+        // Slow, lossy start while the mesh forms.
+        collector.enter_phase(Phase::Warmup, 0.0);
+        collector.record_publish(10);
+        collector.record_delivery(Duration::from_secs(2));
+        collector.enter_phase(Phase::Measured, 5.0);
+        collector.record_publish(10);
+        for _ in 0..10 {
+            collector.record_delivery(Duration::from_millis(20));
+        }
+        collector.enter_phase(Phase::Cooldown, 7.0);
+        collector.record_publish(10);
+        let run = collector.finalize(&scenario, 8.0);
+
+        assert_eq!(run.delivery.delivery_rate(), 1.0);
+        assert_eq!(run.delivery.p99(), Some(Duration::from_millis(20)));
+        assert_eq!(run.energy.total_mah_consumed, 2.0);
+        assert_eq!(run.energy.mah_per_delivery, 0.2);
+        assert_eq!(run.warmup.delivery.messages_delivered, 1);
+        assert_eq!(run.warmup.mah_consumed, 5.0);
+        assert_eq!(run.cooldown.delivery.messages_published, 1);
+        assert_eq!(run.cooldown.mah_consumed, 1.0);
+    }
+
+    #[test]
+    fn per_node_metrics_expose_starved_nodes() {
+        let mut collector = MetricsCollector::new();
+        for (i, deliveries) in [10, 10, 9, 1].into_iter().enumerate() {
+            collector.record_node(NodeMetrics {
+                node: format!("node_{i}"),
+                deliveries,
+                mah_consumed: 1.0,
+                initial_energy: if i == 3 { 0.1 } else { 0.9 },
+                mesh_size: 6,
+            });
+        }
+        let run = collector.finalize(&EvalScenario::baseline(4), 4.0);
+
+        let worst: Vec<&str> = run.worst_nodes(2).iter().map(|n| n.node.as_str()).collect();
+        assert_eq!(worst, ["node_3", "node_2"]);
+        let fairness = run.fairness_index().unwrap();
+        assert!((0.25..0.9).contains(&fairness), "fairness was {fairness}");
+        let starvation = run.starvation_ratio(0.5).unwrap();
+        assert!((starvation - 1.0 / 29.0 * 3.0).abs() < 1e-9);
+        assert_eq!(run.starvation_ratio(0.0), None);
+
+        let empty = MetricsCollector::new().finalize(&EvalScenario::baseline(4), 0.0);
+        assert_eq!(empty.fairness_index(), None);
+    }
+
+    #[test]
+    fn unauthorized_tasks_are_spread_and_counted() {
+        let workload = AuthWorkload {
+            task_count: 100,
+            unauthorized_percentage: 20.0,
+            ..AuthWorkload::default()
+        };
+        let faults: Vec<TokenFault> = (0..100).filter_map(|i| workload.fault_for(i)).collect();
+        assert_eq!(faults.len(), 20);
+        for kind in TokenFault::ALL {
+            assert_eq!(faults.iter().filter(|f| **f == kind).count(), 4);
+        }
+        let none = AuthWorkload {
+            unauthorized_percentage: 0.0,
+            ..workload.clone()
+        };
+        assert!((0..100).all(|i| none.fault_for(i).is_none()));
+
+        let mut collector = MetricsCollector::new();
+        collector.record_authorization(None, true);
+        collector.record_authorization(None, false);
+        collector.record_authorization(Some(TokenFault::Missing), true);
+        collector.record_authorization(Some(TokenFault::Forged), false);
+        collector.record_verify_overhead(Duration::from_millis(36), 100.0);
+        let scenario = EvalScenario::authorization(4, 50.0, false);
+        let auth = collector.finalize(&scenario, 0.0).auth.unwrap();
+        assert_eq!(auth.slip_rate(), 0.5);
+        assert_eq!(auth.false_rejection_rate(), 0.5);
+        assert_eq!(auth.slipped, BTreeMap::from([(TokenFault::Missing, 1)]));
+        assert_eq!(
+            auth.overhead_percentile(50.0),
+            Some(Duration::from_millis(36))
+        );
+        assert!((auth.verify_mah - 0.001).abs() < 1e-6);
+        assert!(MetricsCollector::new()
+            .finalize(&scenario, 0.0)
+            .auth
+            .is_none());
+    }
+
+    #[test]
+    fn simulation_is_reproducible_and_gated() {
+        let scenario = EvalScenario {
+            duration: Duration::from_secs(5),
+            warmup: Duration::from_secs(1),
+            cooldown: Duration::from_secs(1),
+            ..EvalScenario::baseline(30)
+        };
+        let run = simulate(&scenario, 7);
+        let again = simulate(&scenario, 7);
+        assert_eq!(run.delivery.latencies_us, again.delivery.latencies_us);
+        assert_eq!(run.nodes, again.nodes);
+        assert!(run.delivery.delivery_rate() > 0.5);
+
+        let expected = EvalSummary::from_runs(&[run]).unwrap();
+        let mut baseline = Baseline::default();
+        baseline.record(7, expected.clone());
+        baseline.record(7, expected.clone());
+        assert_eq!(baseline.entries.len(), 1);
+        let pinned = baseline.get("baseline", 7).unwrap();
+        let tolerance = Tolerance::default();
+        assert!(tolerance.regressions(pinned, &expected).is_empty());
+
+        let worse = EvalSummary {
+            delivery_rate_mean: expected.delivery_rate_mean - 0.05,
+            p99_latency_mean_us: expected.p99_latency_mean_us * 1.5,
+            ..expected.clone()
+        };
+        let metrics: Vec<&str> = tolerance
+            .regressions(pinned, &worse)
+            .iter()
+            .map(|r| r.metric)
+            .collect();
+        assert_eq!(metrics, ["delivery rate", "p99 latency (us)"]);
+        assert!(baseline.get("baseline", 8).is_none());
+    }
+
+    #[test]
+    fn scenarios_load_from_toml_and_json() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("b_lossy_lora.toml"),
+            r#"
+node_count = 20
+duration = 2.5
+link = { LoRa = { spreading_factor = 10 } }
+device_mix = [["Phone", 1.0]]
+
+[[fault_schedule]]
+time = 1
+fault = { Degradation = { drop_probability = 0.3 } }
+
+[[fault_schedule]]
+time = 2
+fault = "PartitionHeal"
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("a.json"),
+            r#"{"name": "tiny", "node_count": 3, "warmup": 0}"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.md"), "ignored").unwrap();
+
+        let scenarios = load_scenarios(dir.path()).unwrap();
+        let names: Vec<&str> = scenarios.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["tiny", "b_lossy_lora"]);
+        assert_eq!(scenarios[0].node_count, 3);
+        assert_eq!(scenarios[0].warmup, Duration::ZERO);
+        assert_eq!(scenarios[0].duration, EvalScenario::default().duration);
+
+        let lora = &scenarios[1];
+        assert_eq!(lora.duration, Duration::from_millis(2500));
+        assert!(matches!(
+            &lora.link,
+            LinkModel::LoRa(params) if params.spreading_factor == 10
+                && params.duty_window == LoRaParams::default().duty_window
+        ));
+        assert_eq!(lora.device_mix, [(DeviceClass::Phone, 1.0)]);
+        assert!(matches!(
+            lora.fault_schedule.as_slice(),
+            [
+                FaultEvent { time: t1, fault: FaultType::Degradation { drop_probability } },
+                FaultEvent { time: t2, fault: FaultType::PartitionHeal },
+            ] if *t1 == Duration::from_secs(1) && *t2 == Duration::from_secs(2)
+                && *drop_probability == 0.3
+        ));
+
+        // Round trip through JSON.
+        let json = serde_json::to_string(lora).unwrap();
+        let back: EvalScenario = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.duration, lora.duration);
+
+        std::fs::write(dir.path().join("c.json"), r#"{"node_count": "many"}"#).unwrap();
+        assert!(matches!(
+            load_scenarios(dir.path()),
+            Err(ScenarioError::Parse { .. })
+        ));
+        assert!(matches!(
+            EvalScenario::load(&dir.path().join("notes.md")),
+            Err(ScenarioError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn topologies_have_their_expected_shape() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let degrees = |graph: &[Vec<usize>]| graph.iter().map(Vec::len).collect::<Vec<_>>();
+
+        let ring = Topology::Ring { neighbors: 2 }.build(10, &mut rng).unwrap();
+        assert!(degrees(&ring).iter().all(|&d| d == 4));
+
+        // A 3x3 lattice: corners, edges and the center.
+        let grid = Topology::Grid { radio_range: 1.0 }
+            .build(9, &mut rng)
+            .unwrap();
+        assert_eq!(degrees(&grid), [2, 3, 2, 3, 4, 3, 2, 3, 2]);
+
+        let scale_free = Topology::ScaleFree { attachments: 2 }
+            .build(200, &mut rng)
+            .unwrap();
+        let links: usize = degrees(&scale_free).iter().sum::<usize>() / 2;
+        assert_eq!(links, 3 + 2 * 197);
+        assert!(degrees(&scale_free).iter().all(|&d| d >= 2));
+        assert!(*degrees(&scale_free).iter().max().unwrap() > 20);
+
+        let geo = Topology::Geo {
+            clusters: 2,
+            spread: 0.05,
+            radio_range: 0.2,
+        };
+        assert_eq!(
+            geo.build(20, &mut StdRng::seed_from_u64(5)),
+            geo.build(20, &mut StdRng::seed_from_u64(5))
+        );
+        assert_eq!(Topology::FullMesh.build(20, &mut rng), None);
+    }
+
+    #[test]
+    fn sparse_topologies_limit_how_far_gossip_spreads() {
+        let short = |topology| EvalScenario {
+            duration: Duration::from_secs(2),
+            warmup: Duration::ZERO,
+            cooldown: Duration::ZERO,
+            ..EvalScenario::sparse(100, topology)
+        };
+        let full = simulate(&short(Topology::FullMesh), 1);
+        // Each hop reaches at most two new nodes along a bare ring.
+        let ring = simulate(&short(Topology::Ring { neighbors: 1 }), 1);
+        assert!(ring.delivery.delivery_rate() <= 24.0 / 99.0);
+        assert!(full.delivery.delivery_rate() > ring.delivery.delivery_rate());
+        assert!(ring.nodes.iter().all(|node| node.mesh_size == 2));
+    }
+
+    fn soak(values: impl Fn(usize) -> usize) -> SoakMonitor {
+        let mut monitor = SoakMonitor::default();
+        for i in 0..40 {
+            monitor.record(SoakSample {
+                elapsed: Duration::from_secs(i as u64 * 60),
+                message_cache: values(i),
+                known_peers: 5,
+                ..Default::default()
+            });
+        }
+        monitor
+    }
+
+    #[test]
+    fn soak_flags_sustained_growth_only() {
+        let leaking = soak(|i| 5_000 + i * 500);
+        let violations = leaking.violations();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].metric, SoakMetric::MessageCache);
+
+        // Fills quickly during warmup, then plateaus.
+        let bounded = soak(|i| 5_000 + i.min(8) * 500);
+        assert!(bounded.violations().is_empty());
+        // Sawtooth from periodic pruning.
+        let pruned = soak(|i| 5_000 + (i % 5) * 2_000);
+        assert!(pruned.violations().is_empty());
+    }
 }
@@ -0,0 +1,107 @@
+//! Application-facing events observed by `SporeNode::run_for`.
+//!
+//! Embedders (language bindings, protocol bridges) subscribe with
+//! `SporeNode::subscribe_events` instead of reaching into the swarm. The
+//! channel is a broadcast: slow subscribers lag and skip events rather than
+//! stalling the network loop.
+
+use crate::anomaly::Anomaly;
+use crate::config::ConfigSection;
+use crate::core::{EnergyStatus, NodeRole, NodeState, SensorReading, Task};
+use crate::departure::Departing;
+use crate::mycelium::Spike;
+use crate::quorum::QuorumCert;
+use crate::slo::SloViolation;
+use crate::trace::Trace;
+use serde::Serialize;
+
+/// Buffered events per subscriber before the oldest are skipped.
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NodeEvent {
+    /// A neighbor's energy advertisement.
+    Status(EnergyStatus),
+    /// A task seen on the task topic.
+    Task(Task),
+    /// A mesh pressure spike.
+    Spike(Spike),
+    /// A sensor reading seen on the sensor topic.
+    Reading(SensorReading),
+    /// Shared state changed after applying a remote update; `tenant` names
+    /// the tenant whose doc changed, if not the node's own.
+    StateUpdated {
+        source: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tenant: Option<String>,
+    },
+    /// Enough super-peers approved a critical action.
+    Certified(QuorumCert),
+    /// A local rule fired on a sensor sample.
+    RuleFired { rule: String, value: f32 },
+    /// A peer's behavior broke from its baseline; its score is penalized.
+    AnomalyDetected(Anomaly),
+    /// A traced message arrived; `trace` ends with this node's hop.
+    Traced { topic: String, trace: Trace },
+    /// A task's publisher awarded it to `winner` under a lease, or this node
+    /// took a task addressed to it.
+    Awarded { task: Task, winner: String },
+    /// `winner` stopped renewing its award; the task was published again.
+    LeaseLapsed { task_id: String, winner: String },
+    /// An award was declined: by `winner` for a task this node published,
+    /// which was published again, or by this node itself. Refused addressed
+    /// tasks are reported the same way, and not published again.
+    AwardDeclined {
+        task_id: String,
+        winner: String,
+        reason: String,
+    },
+    /// A peer announced it is about to run out of energy and was pruned.
+    Departing(Departing),
+    /// A delivery SLO has been failing for its sustain period.
+    SloViolated(SloViolation),
+    /// `apply_config` changed these sections of the live config.
+    ConfigApplied { changed: Vec<ConfigSection> },
+    /// An operator's config epoch took effect.
+    EpochApplied { epoch: u64 },
+    /// A config epoch failed to validate or apply; the node stays on
+    /// `current`.
+    EpochRejected {
+        epoch: u64,
+        reason: String,
+        current: u64,
+    },
+    /// A peer passed the admission check and joined the mesh.
+    PeerAdmitted { peer: String },
+    /// A peer failed the admission check, or never attempted it, and was
+    /// disconnected.
+    PeerRefused { peer: String, reason: String },
+    /// The run loop stalled and was restarted on a fresh swarm. `recent`
+    /// lists the last event kinds it handled, oldest first.
+    NodeRestarted {
+        restarts: u32,
+        stalled_ms: u64,
+        recent: Vec<String>,
+    },
+    /// The node moved to another lifecycle state.
+    StateChanged { from: NodeState, to: NodeState },
+    /// A peer's capability and sensor catalog reached `version`, from a
+    /// delta or a snapshot.
+    CatalogUpdated { peer: String, version: u64 },
+    /// Role inference moved the node to another role; `None` is the
+    /// all-round node.
+    RoleChanged {
+        from: Option<NodeRole>,
+        to: Option<NodeRole>,
+    },
+    /// Sequenced messages from `source` went missing and could not be
+    /// recovered.
+    MessagesLost { source: String, count: u64 },
+    /// The firmware release for this node's device class was fetched,
+    /// verified and handed to the installer.
+    FirmwareInstalled { version: String },
+    /// A fetched firmware release could not be installed; it is not tried
+    /// again.
+    FirmwareFailed { version: String, reason: String },
+}
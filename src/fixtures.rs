@@ -0,0 +1,111 @@
+//! Canonical wire samples for compatibility tests.
+//!
+//! [`all`] returns one [`Fixture`] per gossiped type: a fixed sample value
+//! encoded in the current envelope, plus a decoder that reads any version of
+//! it. `tests/wire_compat.rs` compares the encodings with the files committed
+//! under `tests/fixtures/wire/v<ENVELOPE_VERSION>/` and decodes every older
+//! version's files with today's decoders. A change that alters an encoding
+//! therefore fails until `ENVELOPE_VERSION` is bumped and the new version's
+//! files are written with `HYPHA_BLESS=1 cargo test --test wire_compat`;
+//! the old files stay, so old peers keep being understood.
+//!
+//! Mesh control travels as a [`SignedControl`], so that is what its fixture
+//! holds.
+
+use crate::control::SignedControl;
+use crate::core::{Bid, Capability, EnergyStatus, MeshControl, NodeRole, Task};
+use crate::spike::Spike;
+use crate::sync::SyncMessage;
+use crate::wire::{self, Envelope, Priority};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::Duration;
+
+pub struct Fixture {
+    /// File stem under each version directory.
+    pub name: &'static str,
+    /// The sample in the current envelope.
+    pub encoded: Vec<u8>,
+    /// Decodes an encoding of this type, returning its envelope version.
+    pub decode: fn(&[u8]) -> serde_json::Result<u8>,
+}
+
+fn fixture<T: Serialize + DeserializeOwned>(
+    name: &'static str,
+    priority: Priority,
+    body: T,
+) -> Fixture {
+    Fixture {
+        name,
+        encoded: Envelope::new(priority, body)
+            .encode()
+            .expect("fixture serializes"),
+        decode: |bytes| wire::decode::<T>(bytes).map(|envelope| envelope.v),
+    }
+}
+
+/// Every wire type's fixture.
+pub fn all() -> Vec<Fixture> {
+    vec![
+        fixture(
+            "task",
+            Priority::Normal,
+            Task::new(
+                "fixture-task".to_string(),
+                Capability::Compute(100),
+                5,
+                "fixture-source".to_string(),
+            )
+            .with_auth("token".to_string()),
+        ),
+        fixture(
+            "bid",
+            Priority::Normal,
+            Bid {
+                task_id: "fixture-task".to_string(),
+                bidder_id: "fixture-worker".to_string(),
+                energy_score: 0.75,
+                cost_mah: 12.5,
+                price: None,
+            },
+        ),
+        fixture(
+            "energy_status",
+            Priority::Low,
+            EnergyStatus::new("fixture-node".to_string(), 0.5)
+                .with_topics(vec!["hypha-status".to_string()])
+                .with_role(NodeRole::Relay),
+        ),
+        fixture(
+            "mesh_control",
+            Priority::High,
+            SignedControl {
+                sender: "fixture-a".to_string(),
+                target: "fixture-b".to_string(),
+                control: MeshControl::Prune {
+                    topic: "hypha-task".to_string(),
+                    backoff: Duration::from_secs(60),
+                },
+                signature: vec![1, 2, 3],
+            },
+        ),
+        fixture(
+            "sync_message",
+            Priority::Normal,
+            SyncMessage::Update(vec![1, 2, 3]),
+        ),
+        fixture(
+            "spike",
+            Priority::Critical,
+            Spike {
+                source: "fixture-node".to_string(),
+                intensity: 220,
+                pattern_id: 1,
+                proof: None,
+                issued_at: None,
+                seq: None,
+                signature: vec![4, 5, 6],
+            },
+        ),
+    ]
+}
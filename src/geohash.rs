@@ -0,0 +1,90 @@
+//! Geohashes for spatial task routing.
+//!
+//! A geohash names a cell of the globe in base 32; each character narrows
+//! the cell, so every prefix of a node's geohash is an area containing it.
+//! A task carrying a geohash goes only to nodes inside that area, and nodes
+//! with a `LocationSensor` put their own geohash in their catalog, which
+//! lets `PeerCatalogs::near` answer "who is near X".
+
+/// Characters of the geohash alphabet, in value order.
+const ALPHABET: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Longest geohash worth computing: cells are a few centimetres across.
+pub const MAX_PRECISION: usize = 12;
+
+/// Characters of a node's geohash put in its catalog; about a 1.2 km cell.
+pub const DEFAULT_PRECISION: usize = 6;
+
+/// The `precision`-character geohash of a position in degrees, or `None`
+/// if the position is off the globe.
+pub fn encode(lat: f64, lon: f64, precision: usize) -> Option<String> {
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return None;
+    }
+    let (mut lat_range, mut lon_range) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut hash = String::with_capacity(precision);
+    let mut bits = 0u8;
+    let mut value = 0usize;
+    // Bits alternate between longitude and latitude, longitude first.
+    let mut even = true;
+    while hash.len() < precision.min(MAX_PRECISION) {
+        let (range, coordinate): (&mut (f64, f64), f64) = if even {
+            (&mut lon_range, lon)
+        } else {
+            (&mut lat_range, lat)
+        };
+        let mid = (range.0 + range.1) / 2.0;
+        value <<= 1;
+        if coordinate >= mid {
+            value |= 1;
+            range.0 = mid;
+        } else {
+            range.1 = mid;
+        }
+        even = !even;
+        bits += 1;
+        if bits == 5 {
+            hash.push(ALPHABET[value] as char);
+            bits = 0;
+            value = 0;
+        }
+    }
+    Some(hash)
+}
+
+/// Whether the cell `location` lies inside `area`. The empty area covers
+/// the whole globe.
+pub fn covers(area: &str, location: &str) -> bool {
+    location
+        .get(..area.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(area))
+}
+
+/// Whether two cells share ground: one lies inside the other.
+pub fn overlaps(a: &str, b: &str) -> bool {
+    covers(a, b) || covers(b, a)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_known_cells_and_matches_prefixes() {
+        assert_eq!(encode(42.6, -5.6, 5).as_deref(), Some("ezs42"));
+        assert_eq!(
+            encode(57.64911, 10.40744, 11).as_deref(),
+            Some("u4pruydqqvj")
+        );
+        assert_eq!(encode(0.0, 0.0, 40).unwrap().len(), MAX_PRECISION);
+        assert_eq!(encode(91.0, 0.0, 5), None);
+
+        assert!(covers("u4pr", "u4pruydqqvj"));
+        assert!(covers("U4PR", "u4pruy"));
+        assert!(covers("", "ezs42"));
+        assert!(!covers("u4pruy", "u4pr"));
+        assert!(!covers("u4ps", "u4pruy"));
+        assert!(overlaps("u4pruy", "u4pr"));
+        assert!(!overlaps("u4ps", "u4pr"));
+    }
+}
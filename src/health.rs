@@ -0,0 +1,221 @@
+//! Node health scores and the fleet health histogram.
+//!
+//! A node's health folds what an operator checks first into one number in
+//! `0.0..=1.0`: energy, how full its mesh is against the target degree, how
+//! much of its send queue is held back, and, when a `host_disk_free` sensor
+//! is registered, how full its disk is. Each status advert carries the
+//! score as a whole percentage, so every node, super-peers included, keeps a
+//! [`FleetHealth`] of its peers' latest scores without extra traffic. The
+//! CoAP `health` resource serves its [`FleetSummary`].
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Histogram buckets, each ten percentage points wide.
+pub const BUCKETS: usize = 10;
+
+/// Most peers a [`FleetHealth`] tracks.
+pub const MAX_TRACKED_PEERS: usize = 4096;
+
+/// Scores below this count a node as unhealthy.
+pub const UNHEALTHY_BELOW: f32 = 0.3;
+
+/// What a health score is made of. Every input is a fraction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthInputs {
+    pub energy: f32,
+    /// Mesh size over the target degree.
+    pub mesh_fill: f32,
+    /// Held-back messages over the send queue's capacity.
+    pub outbox_fill: f32,
+    /// Used share of the node's disk, when known.
+    pub storage_used: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthWeights {
+    pub energy: f32,
+    pub mesh: f32,
+    pub outbox: f32,
+    pub storage: f32,
+}
+
+impl Default for HealthWeights {
+    fn default() -> Self {
+        Self {
+            energy: 0.4,
+            mesh: 0.3,
+            outbox: 0.2,
+            storage: 0.1,
+        }
+    }
+}
+
+impl HealthWeights {
+    /// Weighted mean of the inputs, each clamped to `0.0..=1.0`; an unknown
+    /// storage share leaves its weight out.
+    pub fn score(&self, inputs: &HealthInputs) -> f32 {
+        let clamp = |value: f32| {
+            if value.is_finite() {
+                value.clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        };
+        let mut parts = vec![
+            (self.energy, clamp(inputs.energy)),
+            (self.mesh, clamp(inputs.mesh_fill)),
+            (self.outbox, 1.0 - clamp(inputs.outbox_fill)),
+        ];
+        if let Some(used) = inputs.storage_used {
+            parts.push((self.storage, 1.0 - clamp(used)));
+        }
+        let total: f32 = parts.iter().map(|(weight, _)| weight).sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        parts
+            .iter()
+            .map(|(weight, value)| weight * value)
+            .sum::<f32>()
+            / total
+    }
+}
+
+/// A score as the whole percentage status adverts carry.
+pub fn percent(score: f32) -> u8 {
+    (score.clamp(0.0, 1.0) * 100.0).round() as u8
+}
+
+/// Fleet health at a glance.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FleetSummary {
+    /// This node's own score, in percent.
+    pub local: Option<u8>,
+    /// Nodes counted, this one included.
+    pub nodes: usize,
+    pub mean: f32,
+    pub unhealthy: usize,
+    /// Nodes per ten-point band, lowest band first; 100 falls in the last.
+    pub histogram: [usize; BUCKETS],
+}
+
+/// Latest health scores heard from peers.
+#[derive(Debug, Clone)]
+pub struct FleetHealth {
+    /// Scores older than this no longer count.
+    pub max_age: Duration,
+    pub local: Option<u8>,
+    peers: HashMap<String, (u8, Instant)>,
+}
+
+impl Default for FleetHealth {
+    fn default() -> Self {
+        Self {
+            max_age: Duration::from_secs(300),
+            local: None,
+            peers: HashMap::new(),
+        }
+    }
+}
+
+impl FleetHealth {
+    pub fn record(&mut self, peer: &str, percent: u8, now: Instant) {
+        if !self.peers.contains_key(peer) && self.peers.len() >= MAX_TRACKED_PEERS {
+            self.expire(now);
+            if self.peers.len() >= MAX_TRACKED_PEERS {
+                let oldest = self
+                    .peers
+                    .iter()
+                    .min_by_key(|(_, (_, at))| *at)
+                    .map(|(peer, _)| peer.clone());
+                if let Some(oldest) = oldest {
+                    self.peers.remove(&oldest);
+                }
+            }
+        }
+        self.peers.insert(peer.to_string(), (percent.min(100), now));
+    }
+
+    /// Drop scores older than `max_age`.
+    pub fn expire(&mut self, now: Instant) {
+        let max_age = self.max_age;
+        self.peers
+            .retain(|_, (_, at)| now.saturating_duration_since(*at) < max_age);
+    }
+
+    pub fn summary(&self, now: Instant) -> FleetSummary {
+        let scores: Vec<u8> = self
+            .peers
+            .values()
+            .filter(|(_, at)| now.saturating_duration_since(*at) < self.max_age)
+            .map(|(percent, _)| *percent)
+            .chain(self.local)
+            .collect();
+        let mut summary = FleetSummary {
+            local: self.local,
+            nodes: scores.len(),
+            ..FleetSummary::default()
+        };
+        for percent in &scores {
+            let bucket = (*percent as usize * BUCKETS / 100).min(BUCKETS - 1);
+            summary.histogram[bucket] += 1;
+            if (*percent as f32) < UNHEALTHY_BELOW * 100.0 {
+                summary.unhealthy += 1;
+            }
+        }
+        if !scores.is_empty() {
+            let sum: u32 = scores.iter().map(|percent| *percent as u32).sum();
+            summary.mean = sum as f32 / scores.len() as f32 / 100.0;
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scores_weigh_every_input_and_skip_unknown_storage() {
+        let weights = HealthWeights::default();
+        let healthy = HealthInputs {
+            energy: 1.0,
+            mesh_fill: 1.5,
+            outbox_fill: 0.0,
+            storage_used: None,
+        };
+        assert_eq!(weights.score(&healthy), 1.0);
+        let struggling = HealthInputs {
+            energy: 0.5,
+            mesh_fill: 0.0,
+            outbox_fill: 1.0,
+            storage_used: Some(1.0),
+        };
+        assert!((weights.score(&struggling) - 0.2).abs() < 1e-6);
+        assert_eq!(percent(weights.score(&struggling)), 20);
+    }
+
+    #[test]
+    fn the_histogram_counts_fresh_scores_and_this_node() {
+        let t0 = Instant::now();
+        let mut fleet = FleetHealth {
+            local: Some(100),
+            ..FleetHealth::default()
+        };
+        fleet.record("a", 5, t0);
+        fleet.record("b", 55, t0 + Duration::from_secs(200));
+        fleet.record("c", 59, t0 + Duration::from_secs(200));
+
+        let summary = fleet.summary(t0 + Duration::from_secs(400));
+        assert_eq!(summary.nodes, 3);
+        assert_eq!(summary.histogram, [0, 0, 0, 0, 0, 2, 0, 0, 0, 1]);
+        assert_eq!(summary.unhealthy, 0);
+        assert!((summary.mean - 0.713_333).abs() < 1e-4);
+
+        let summary = fleet.summary(t0);
+        assert_eq!(summary.histogram[0], 1);
+        assert_eq!(summary.unhealthy, 1);
+    }
+}
@@ -0,0 +1,221 @@
+//! Hibernation snapshots for deep sleep and planned shutdowns.
+//!
+//! The warm start `run_for` leaves behind covers the mesh only; a node waking
+//! from it still has to find its peers again, starts with an empty shared
+//! document and has lost whatever the send policy was holding back. Before a
+//! deep sleep, `SporeNode::hibernate` writes one compressed [`Snapshot`]
+//! under [`HIBERNATION_KEY`] instead: mesh membership and scores, the
+//! addresses peers were last dialed at, the shared document and the held
+//! publishes.
+//!
+//! `SporeNode::new` takes the snapshot (it is read once) in place of the
+//! warm-start mesh state. The next run loop redials the previous mesh peers
+//! at once, puts the held publishes back in the outbox, and asks for missed
+//! updates as soon as one of those peers is back rather than waiting for
+//! anti-entropy to come round.
+
+use crate::mesh::PersistedMesh;
+use crate::wire::OutboxEntry;
+use libp2p::{Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Storage key of the snapshot `SporeNode::hibernate` writes.
+pub const HIBERNATION_KEY: &str = "hibernation";
+
+/// Largest snapshot, inflated, that `Snapshot::decode` accepts.
+pub const MAX_SNAPSHOT_BYTES: usize = 64 << 20;
+
+/// Most peers a [`PeerStore`] remembers addresses for.
+pub const MAX_STORED_PEERS: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum SnapshotError {
+    #[error("truncated or corrupt hibernation snapshot")]
+    Corrupt,
+    #[error("malformed hibernation snapshot: {0}")]
+    Malformed(String),
+}
+
+/// Where peers were last reached, for redialing them after a wake. Only
+/// addresses this node dialed are kept; the far end of an inbound
+/// connection is usually an ephemeral port.
+#[derive(Debug, Clone, Default)]
+pub struct PeerStore {
+    addresses: HashMap<String, (u64, Multiaddr)>,
+    clock: u64,
+}
+
+impl PeerStore {
+    pub fn record(&mut self, peer: &str, addr: Multiaddr) {
+        self.clock += 1;
+        self.addresses.insert(peer.to_string(), (self.clock, addr));
+        if self.addresses.len() > MAX_STORED_PEERS {
+            let oldest = self
+                .addresses
+                .iter()
+                .min_by_key(|(_, (at, _))| *at)
+                .map(|(peer, _)| peer.clone());
+            if let Some(oldest) = oldest {
+                self.addresses.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn get(&self, peer: &str) -> Option<&Multiaddr> {
+        self.addresses.get(peer).map(|(_, addr)| addr)
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    /// Peers and addresses, least recently recorded first.
+    fn entries(&self) -> Vec<(String, String)> {
+        let mut entries: Vec<_> = self.addresses.iter().collect();
+        entries.sort_by_key(|(_, (at, _))| *at);
+        entries
+            .into_iter()
+            .map(|(peer, (_, addr))| (peer.clone(), addr.to_string()))
+            .collect()
+    }
+}
+
+/// Everything a node needs to be useful straight after waking.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Unix milliseconds.
+    pub saved_at: u64,
+    pub mesh: PersistedMesh,
+    /// Peer ids and the addresses they were last dialed at.
+    pub addresses: Vec<(String, String)>,
+    /// The shared document, encoded as a single update.
+    pub state: Vec<u8>,
+    /// Publishes the send policy was still holding back.
+    pub outbox: Vec<OutboxEntry>,
+}
+
+impl Snapshot {
+    pub fn new(
+        saved_at: u64,
+        mesh: PersistedMesh,
+        peers: &PeerStore,
+        state: Vec<u8>,
+        outbox: Vec<OutboxEntry>,
+    ) -> Self {
+        Self {
+            saved_at,
+            mesh,
+            addresses: peers.entries(),
+            state,
+            outbox,
+        }
+    }
+
+    /// JSON, LZ4-compressed with the inflated size prepended.
+    pub fn encode(&self) -> Vec<u8> {
+        lz4_flex::compress_prepend_size(&serde_json::to_vec(self).expect("snapshot serializes"))
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        let size = bytes
+            .first_chunk::<4>()
+            .map(|size| u32::from_le_bytes(*size) as usize)
+            .ok_or(SnapshotError::Corrupt)?;
+        if size > MAX_SNAPSHOT_BYTES {
+            return Err(SnapshotError::Corrupt);
+        }
+        let json =
+            lz4_flex::decompress_size_prepended(bytes).map_err(|_| SnapshotError::Corrupt)?;
+        serde_json::from_slice(&json).map_err(|e| SnapshotError::Malformed(e.to_string()))
+    }
+
+    /// The stored addresses, skipping any that no longer parse.
+    pub fn peer_store(&self) -> PeerStore {
+        let mut store = PeerStore::default();
+        for (peer, addr) in &self.addresses {
+            if let Ok(addr) = addr.parse() {
+                store.record(peer, addr);
+            }
+        }
+        store
+    }
+
+    /// What the next run loop does with the snapshot.
+    pub fn wake(self) -> Wake {
+        let store = self.peer_store();
+        let dial = self
+            .mesh
+            .mesh_peers
+            .iter()
+            .filter_map(|peer| Some((peer.parse().ok()?, store.get(peer)?.clone())))
+            .collect();
+        Wake {
+            peers: self.mesh.mesh_peers.into_iter().collect(),
+            dial,
+            outbox: self.outbox,
+        }
+    }
+}
+
+/// Work left for the first run loop after a wake.
+#[derive(Debug, Clone, Default)]
+pub struct Wake {
+    /// The previous mesh peers; the first of them back triggers a sync.
+    pub peers: HashSet<String>,
+    /// Previous mesh peers with a known address, to dial at once.
+    pub dial: Vec<(PeerId, Multiaddr)>,
+    pub outbox: Vec<OutboxEntry>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::Priority;
+
+    #[test]
+    fn snapshots_round_trip_and_plan_the_wake() {
+        let member = PeerId::random().to_string();
+        let stranger = PeerId::random().to_string();
+        let mut peers = PeerStore::default();
+        peers.record(&member, "/ip4/10.0.0.1/tcp/4001".parse().unwrap());
+        peers.record(&stranger, "/ip4/10.0.0.2/tcp/4001".parse().unwrap());
+        let mesh = PersistedMesh {
+            mesh_peers: vec![member.clone(), "not-a-peer-id".to_string()],
+            ..PersistedMesh::default()
+        };
+        let held = OutboxEntry {
+            topic: "hypha_sensor_readings".to_string(),
+            priority: Priority::Low,
+            bytes: b"{}".to_vec(),
+        };
+        let snapshot = Snapshot::new(7, mesh, &peers, vec![1, 2, 3], vec![held.clone()]);
+
+        let restored = Snapshot::decode(&snapshot.encode()).unwrap();
+        assert_eq!(restored, snapshot);
+        assert_eq!(Snapshot::decode(b"garbage"), Err(SnapshotError::Corrupt));
+        assert_eq!(restored.peer_store().len(), 2);
+
+        let wake = restored.wake();
+        assert_eq!(wake.peers.len(), 2);
+        assert_eq!(wake.dial.len(), 1);
+        assert_eq!(wake.dial[0].0.to_string(), member);
+        assert_eq!(wake.outbox, vec![held]);
+    }
+
+    #[test]
+    fn peer_store_forgets_the_least_recent_past_its_cap() {
+        let mut peers = PeerStore::default();
+        let addr: Multiaddr = "/ip4/10.0.0.1/tcp/4001".parse().unwrap();
+        for i in 0..=MAX_STORED_PEERS {
+            peers.record(&format!("peer-{i}"), addr.clone());
+        }
+        assert_eq!(peers.len(), MAX_STORED_PEERS);
+        assert!(peers.get("peer-0").is_none());
+        assert!(peers.get(&format!("peer-{MAX_STORED_PEERS}")).is_some());
+    }
+}
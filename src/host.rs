@@ -0,0 +1,218 @@
+//! Built-in sensors for device health.
+//!
+//! A [`HostSensor`] reads the node's own host: CPU load, SoC temperature or
+//! free disk space. `SporeNode::add_host_sensors` registers them like any
+//! other `VirtualSensor`, so their readings flow through rules, aggregation
+//! and the sensor topic without custom code. Reading the host needs the
+//! `host-metrics` feature, which pulls in `sysinfo`.
+//!
+//! Values are plain fractions and degrees so that rules read naturally:
+//! `host_cpu` is the share of CPU time in use over the last read, in
+//! `0.0..=1.0`; `host_temp_c` is the hottest CPU or SoC component in °C;
+//! `host_disk_free` is the free share of the disk holding a given path.
+
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HostMetric {
+    CpuLoad,
+    Temperature,
+    DiskFree,
+}
+
+impl HostMetric {
+    pub const ALL: [HostMetric; 3] = [
+        HostMetric::CpuLoad,
+        HostMetric::Temperature,
+        HostMetric::DiskFree,
+    ];
+
+    /// Sensor name the readings carry.
+    pub fn name(self) -> &'static str {
+        match self {
+            HostMetric::CpuLoad => "host_cpu",
+            HostMetric::Temperature => "host_temp_c",
+            HostMetric::DiskFree => "host_disk_free",
+        }
+    }
+}
+
+/// Component labels that name the CPU or SoC on common platforms.
+const SOC_LABELS: &[&str] = &["cpu", "soc", "package", "core", "tctl", "k10temp"];
+
+/// The SoC temperature among labelled component readings: the hottest one
+/// whose label names the CPU or SoC, else the hottest of any.
+pub fn soc_temperature<'a>(readings: impl IntoIterator<Item = (&'a str, f32)>) -> Option<f32> {
+    let mut soc = None::<f32>;
+    let mut any = None::<f32>;
+    for (label, celsius) in readings {
+        if !celsius.is_finite() {
+            continue;
+        }
+        let label = label.to_ascii_lowercase();
+        if SOC_LABELS.iter().any(|soc_label| label.contains(soc_label)) {
+            soc = Some(soc.map_or(celsius, |hottest| hottest.max(celsius)));
+        }
+        any = Some(any.map_or(celsius, |hottest| hottest.max(celsius)));
+    }
+    soc.or(any)
+}
+
+/// Free share of the disk holding `path`, from `(mount point, available,
+/// total)` entries. The longest mount point containing `path` wins.
+pub fn free_share<'a>(
+    path: &Path,
+    disks: impl IntoIterator<Item = (&'a Path, u64, u64)>,
+) -> Option<f32> {
+    disks
+        .into_iter()
+        .filter(|(mount, _, total)| *total > 0 && path.starts_with(mount))
+        .max_by_key(|(mount, _, _)| mount.as_os_str().len())
+        .map(|(_, available, total)| (available as f64 / total as f64) as f32)
+}
+
+#[cfg(feature = "host-metrics")]
+pub use probe::HostSensor;
+
+#[cfg(feature = "host-metrics")]
+mod probe {
+    use super::{free_share, soc_temperature, HostMetric};
+    use crate::core::VirtualSensor;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
+    use sysinfo::{Components, Disks, System};
+
+    enum Probe {
+        Cpu(Box<System>),
+        Components(Components),
+        Disks(Disks, PathBuf),
+    }
+
+    /// One host metric as a `VirtualSensor`. A read that finds nothing
+    /// repeats the last value.
+    pub struct HostSensor {
+        metric: HostMetric,
+        probe: Mutex<(Probe, f32)>,
+    }
+
+    impl HostSensor {
+        /// CPU load. The first read has no interval to measure and gives 0.
+        pub fn cpu() -> Self {
+            Self::new(HostMetric::CpuLoad, Probe::Cpu(Box::new(System::new())))
+        }
+
+        /// SoC temperature, or `None` when the host reports no components
+        /// with a temperature.
+        pub fn temperature() -> Option<Self> {
+            let components = Components::new_with_refreshed_list();
+            soc_temperature(readings(&components))?;
+            Some(Self::new(
+                HostMetric::Temperature,
+                Probe::Components(components),
+            ))
+        }
+
+        /// Free share of the disk holding `path`.
+        pub fn disk_free(path: impl Into<PathBuf>) -> Self {
+            let probe = Probe::Disks(Disks::new_with_refreshed_list(), path.into());
+            Self::new(HostMetric::DiskFree, probe)
+        }
+
+        fn new(metric: HostMetric, probe: Probe) -> Self {
+            let sensor = Self {
+                metric,
+                probe: Mutex::new((probe, 0.0)),
+            };
+            // Primes the CPU interval and the last value.
+            sensor.read();
+            sensor
+        }
+
+        pub fn metric(&self) -> HostMetric {
+            self.metric
+        }
+    }
+
+    fn readings(components: &Components) -> impl Iterator<Item = (&str, f32)> {
+        components
+            .iter()
+            .filter_map(|component| Some((component.label(), component.temperature()?)))
+    }
+
+    impl VirtualSensor for HostSensor {
+        fn name(&self) -> &str {
+            self.metric.name()
+        }
+
+        fn read(&self) -> f32 {
+            let mut guard = self.probe.lock().unwrap();
+            let (probe, last) = &mut *guard;
+            let value = match probe {
+                Probe::Cpu(system) => {
+                    system.refresh_cpu_usage();
+                    Some((system.global_cpu_usage() / 100.0).clamp(0.0, 1.0))
+                }
+                Probe::Components(components) => {
+                    components.refresh(false);
+                    soc_temperature(readings(components))
+                }
+                Probe::Disks(disks, path) => {
+                    disks.refresh(false);
+                    free_share(
+                        path,
+                        disks.iter().map(|disk| {
+                            (
+                                disk.mount_point(),
+                                disk.available_space(),
+                                disk.total_space(),
+                            )
+                        }),
+                    )
+                }
+            };
+            if let Some(value) = value.filter(|value| value.is_finite()) {
+                *last = value;
+            }
+            *last
+        }
+
+        /// Host sensors measure this node only.
+        fn update_from_mesh(&mut self, _value: f32) {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn soc_components_win_over_other_hot_parts() {
+        let readings = [
+            ("nvme Composite", 61.0),
+            ("cpu_thermal", 48.5),
+            ("gpu", f32::NAN),
+        ];
+        assert_eq!(soc_temperature(readings), Some(48.5));
+        assert_eq!(
+            soc_temperature([("acpitz", 40.0), ("nvme", 52.0)]),
+            Some(52.0)
+        );
+        assert_eq!(soc_temperature([("gpu", f32::NAN)]), None);
+    }
+
+    #[test]
+    fn the_longest_mount_point_holding_the_path_counts() {
+        let disks = [
+            (Path::new("/"), 10, 100),
+            (Path::new("/var"), 30, 40),
+            (Path::new("/var/lib/hyphae"), 0, 0),
+        ];
+        assert_eq!(free_share(Path::new("/var/lib/hypha"), disks), Some(0.75));
+        assert_eq!(free_share(Path::new("/home"), disks), Some(0.1));
+        assert_eq!(free_share(Path::new("relative"), disks), None);
+        assert_eq!(
+            HostMetric::ALL.map(HostMetric::name),
+            ["host_cpu", "host_temp_c", "host_disk_free"]
+        );
+    }
+}
@@ -0,0 +1,453 @@
+//! Peer introductions: "who do you know?"
+//!
+//! A node booting into a sparse swarm may reach only one or two peers, and
+//! without a DHT it waits on rendezvous refreshes and gossip to find more.
+//! With introductions on, a node that knows fewer than `sparse_below` peers
+//! asks each new connection who it knows, over the [`INTRODUCTION_PROTOCOL`]
+//! request-response protocol. The answer, an [`Introduction`], samples the
+//! answering node's best-scored known peers.
+//!
+//! Each peer in it travels as a [`SignedPeer`]: addresses the peer signed
+//! for itself, so an introducer can pass records on but cannot forge where
+//! a real peer is reachable. A node collects records from the peers that
+//! ask it (a request carries the asker's own record) and from introductions
+//! it receives.
+//!
+//! Poisoned introductions are blunted on both sides. A node answers a peer
+//! at most once per `interval` and asks it at most as often. It hands out
+//! and accepts at most `sample` records, only fresh and verifying ones, and
+//! no more than `max_per_prefix` per address prefix, so a flood of sybils
+//! on one network cannot crowd out the rest. Introduced peers are recorded
+//! as introduced by the answering node, which the mesh's first-hop
+//! diversity already accounts for.
+
+use crate::auth::{peer_id_of, public_key_of};
+use crate::mesh::ip_prefix;
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+pub const INTRODUCTION_PROTOCOL: &str = "/hypha/introduce/1.0.0";
+
+/// Most peer records a node keeps to hand on.
+pub const MAX_RECORDS: usize = 256;
+
+/// Most addresses one record may list.
+pub const MAX_RECORD_ADDRS: usize = 8;
+
+/// Addresses a peer signed for itself. `issued_at` is Unix seconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignedPeer {
+    pub peer: String,
+    pub addrs: Vec<String>,
+    pub issued_at: u64,
+    pub signature: Vec<u8>,
+}
+
+impl SignedPeer {
+    /// The record of the node holding `signing_key`, reachable at `addrs`.
+    pub fn sign(signing_key: &SigningKey, addrs: Vec<String>, now: u64) -> Self {
+        let mut record = Self {
+            peer: peer_id_of(signing_key).to_string(),
+            addrs,
+            issued_at: now,
+            signature: Vec::new(),
+        };
+        record.signature = signing_key
+            .sign(&record.signing_bytes())
+            .to_bytes()
+            .to_vec();
+        record
+    }
+
+    /// Check that the record is signed by the peer it names and was issued
+    /// within `max_age` of `now`.
+    pub fn verify(&self, now: u64, max_age: Duration) -> Result<(), IntroductionError> {
+        if self.addrs.len() > MAX_RECORD_ADDRS {
+            return Err(IntroductionError::TooManyAddresses(self.peer.clone()));
+        }
+        let key = public_key_of(&self.peer)
+            .ok_or_else(|| IntroductionError::UnknownKey(self.peer.clone()))?;
+        if !key.verify(&self.signing_bytes(), &self.signature) {
+            return Err(IntroductionError::BadSignature(self.peer.clone()));
+        }
+        if self.issued_at > now || now - self.issued_at > max_age.as_secs() {
+            return Err(IntroductionError::Stale(self.peer.clone()));
+        }
+        Ok(())
+    }
+
+    /// Address prefixes of the record's IP addresses; see `ip_prefix`.
+    pub fn prefixes(&self) -> Vec<String> {
+        self.addrs
+            .iter()
+            .filter_map(|addr| address_ip(addr))
+            .map(ip_prefix)
+            .collect()
+    }
+
+    fn signing_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(&("hypha-peer", &self.peer, &self.addrs, self.issued_at))
+            .expect("peer record fields serialize")
+    }
+}
+
+/// The IP address in a multiaddr's text form, if it starts with one.
+fn address_ip(addr: &str) -> Option<IpAddr> {
+    let mut parts = addr.split('/').skip(1);
+    match (parts.next()?, parts.next()?) {
+        ("ip4" | "ip6", ip) => ip.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Asks the receiving node who it knows, with the asker's own record so
+/// that it can be introduced onward.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntroductionRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record: Option<SignedPeer>,
+}
+
+/// Records of peers the answering node knows, its own first.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Introduction {
+    pub records: Vec<SignedPeer>,
+    /// Why the node did not answer, when it did not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refused: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum IntroductionError {
+    #[error("peer record for `{0}` names no recoverable key")]
+    UnknownKey(String),
+    #[error("peer record for `{0}` is not signed by it")]
+    BadSignature(String),
+    #[error("peer record for `{0}` is stale or from the future")]
+    Stale(String),
+    #[error("peer record for `{0}` lists more than {MAX_RECORD_ADDRS} addresses")]
+    TooManyAddresses(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntroductionConfig {
+    /// Off by default: the node neither asks nor answers.
+    pub enabled: bool,
+    /// Ask new connections who they know while connected to fewer peers.
+    pub sparse_below: usize,
+    /// Most records handed out, and accepted, per introduction.
+    pub sample: usize,
+    /// Most records per address prefix in one introduction.
+    pub max_per_prefix: usize,
+    /// Least time between two answers to one peer, and between two
+    /// questions to it.
+    pub interval: Duration,
+    /// Oldest record handed out or accepted.
+    pub max_age: Duration,
+}
+
+impl Default for IntroductionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sparse_below: 6,
+            sample: 8,
+            max_per_prefix: 2,
+            interval: Duration::from_secs(60),
+            max_age: Duration::from_secs(3600),
+        }
+    }
+}
+
+/// What an introduction yielded.
+#[derive(Debug, Default, PartialEq)]
+pub struct Accepted {
+    /// Records of peers worth dialing.
+    pub dial: Vec<SignedPeer>,
+    /// Records that failed to verify.
+    pub invalid: usize,
+}
+
+/// Records collected for handing on, and when each peer was last asked
+/// and answered.
+#[derive(Debug, Default)]
+pub struct Introductions {
+    pub config: IntroductionConfig,
+    records: HashMap<String, SignedPeer>,
+    asked: HashMap<String, Instant>,
+    answered: HashMap<String, Instant>,
+}
+
+impl Introductions {
+    pub fn new(config: IntroductionConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// The record held for `peer`.
+    pub fn record(&self, peer: &str) -> Option<&SignedPeer> {
+        self.records.get(peer)
+    }
+
+    /// Keep `record` to hand on, if it verifies and is newer than the one
+    /// held. The oldest record makes room once `MAX_RECORDS` are held.
+    pub fn learn(&mut self, record: SignedPeer, now: u64) -> Result<(), IntroductionError> {
+        record.verify(now, self.config.max_age)?;
+        if self
+            .records
+            .get(&record.peer)
+            .is_some_and(|held| held.issued_at >= record.issued_at)
+        {
+            return Ok(());
+        }
+        if !self.records.contains_key(&record.peer) && self.records.len() >= MAX_RECORDS {
+            let oldest = self
+                .records
+                .values()
+                .min_by_key(|held| held.issued_at)
+                .map(|held| held.peer.clone());
+            if let Some(oldest) = oldest {
+                self.records.remove(&oldest);
+            }
+        }
+        self.records.insert(record.peer.clone(), record);
+        Ok(())
+    }
+
+    /// Whether to ask `peer`, just connected, who it knows, given `connected`
+    /// peers. Counts as asking.
+    pub fn should_ask(&mut self, peer: &str, connected: usize, now: Instant) -> bool {
+        if !self.config.enabled || connected >= self.config.sparse_below {
+            return false;
+        }
+        let interval = self.config.interval;
+        self.asked
+            .retain(|_, at| now.saturating_duration_since(*at) < interval);
+        if self.asked.contains_key(peer) {
+            return false;
+        }
+        self.asked.insert(peer.to_string(), now);
+        true
+    }
+
+    /// Answer `requester`: `me` followed by the records of the best of
+    /// `ranked` (peer ids with scores, best first), spread over address
+    /// prefixes.
+    pub fn answer(
+        &mut self,
+        requester: &str,
+        me: SignedPeer,
+        ranked: &[(String, f32)],
+        now: Instant,
+        unix: u64,
+    ) -> Introduction {
+        if !self.config.enabled {
+            return Introduction::refused("introductions are off");
+        }
+        let interval = self.config.interval;
+        self.answered
+            .retain(|_, at| now.saturating_duration_since(*at) < interval);
+        if self.answered.contains_key(requester) {
+            return Introduction::refused("asked again too soon");
+        }
+        self.answered.insert(requester.to_string(), now);
+
+        let mut prefixes = PrefixCount::new(self.config.max_per_prefix);
+        let mut records = vec![me];
+        for (peer, _) in ranked {
+            if records.len() > self.config.sample {
+                break;
+            }
+            if peer == requester {
+                continue;
+            }
+            let Some(record) = self.records.get(peer) else {
+                continue;
+            };
+            if record.verify(unix, self.config.max_age).is_ok() && prefixes.admit(record) {
+                records.push(record.clone());
+            }
+        }
+        Introduction {
+            records,
+            refused: None,
+        }
+    }
+
+    /// The records in `introduction` worth dialing: the first `sample` that
+    /// verify, are not `me` and not `known`, at most `max_per_prefix` per
+    /// address prefix. The verified ones are learned for handing on.
+    pub fn accept(
+        &mut self,
+        introduction: Introduction,
+        me: &str,
+        known: impl Fn(&str) -> bool,
+        unix: u64,
+    ) -> Accepted {
+        let mut accepted = Accepted::default();
+        let mut prefixes = PrefixCount::new(self.config.max_per_prefix);
+        let sample = self.config.sample + 1;
+        for record in introduction.records.into_iter().take(sample) {
+            if let Err(e) = self.learn(record.clone(), unix) {
+                tracing::debug!(err = %e, "Ignoring introduced peer");
+                accepted.invalid += 1;
+                continue;
+            }
+            if record.peer == me || known(&record.peer) || !prefixes.admit(&record) {
+                continue;
+            }
+            accepted.dial.push(record);
+        }
+        accepted
+    }
+}
+
+impl Introduction {
+    fn refused(reason: &str) -> Self {
+        Self {
+            records: Vec::new(),
+            refused: Some(reason.to_string()),
+        }
+    }
+}
+
+/// Records seen per address prefix, up to a cap.
+struct PrefixCount {
+    max: usize,
+    seen: HashMap<String, usize>,
+}
+
+impl PrefixCount {
+    fn new(max: usize) -> Self {
+        Self {
+            max,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Count `record` if none of its prefixes is at the cap yet.
+    fn admit(&mut self, record: &SignedPeer) -> bool {
+        let prefixes = record.prefixes();
+        if prefixes
+            .iter()
+            .any(|prefix| self.seen.get(prefix).copied().unwrap_or(0) >= self.max)
+        {
+            return false;
+        }
+        for prefix in prefixes {
+            *self.seen.entry(prefix).or_default() += 1;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    fn record(seed: u8, addr: &str) -> SignedPeer {
+        SignedPeer::sign(&key(seed), vec![addr.to_string()], NOW)
+    }
+
+    fn enabled() -> Introductions {
+        Introductions::new(IntroductionConfig {
+            enabled: true,
+            ..IntroductionConfig::default()
+        })
+    }
+
+    #[test]
+    fn records_verify_only_as_signed_and_while_fresh() {
+        let good = record(1, "/ip4/10.0.0.1/tcp/4001");
+        assert_eq!(good.verify(NOW + 60, Duration::from_secs(3600)), Ok(()));
+        assert!(matches!(
+            good.verify(NOW + 7200, Duration::from_secs(3600)),
+            Err(IntroductionError::Stale(_))
+        ));
+
+        let mut moved = good.clone();
+        moved.addrs = vec!["/ip4/203.0.113.9/tcp/4001".to_string()];
+        assert!(matches!(
+            moved.verify(NOW, Duration::from_secs(3600)),
+            Err(IntroductionError::BadSignature(_))
+        ));
+        assert_eq!(
+            good.prefixes(),
+            vec![ip_prefix("10.0.0.1".parse().unwrap())]
+        );
+    }
+
+    #[test]
+    fn answers_are_rate_limited_and_spread_over_prefixes() {
+        let mut intros = enabled();
+        let t0 = Instant::now();
+        let mut ranked = Vec::new();
+        // Five sybils on one network outrank two peers elsewhere.
+        for seed in 1..=5 {
+            let sybil = record(seed, &format!("/ip4/192.0.2.{seed}/tcp/4001"));
+            ranked.push((sybil.peer.clone(), 1.0));
+            intros.learn(sybil, NOW).unwrap();
+        }
+        for (seed, addr) in [
+            (6, "/ip4/198.51.100.7/tcp/4001"),
+            (7, "/ip6/2001:db8::7/tcp/4001"),
+        ] {
+            let peer = record(seed, addr);
+            ranked.push((peer.peer.clone(), 0.5));
+            intros.learn(peer, NOW).unwrap();
+        }
+
+        let me = record(9, "/ip4/203.0.113.1/tcp/4001");
+        let answer = intros.answer("asker", me.clone(), &ranked, t0, NOW);
+        assert_eq!(answer.records[0], me);
+        assert_eq!(answer.records.len(), 1 + 2 + 2);
+
+        let again = intros.answer("asker", me.clone(), &ranked, t0, NOW);
+        assert!(again.refused.is_some() && again.records.is_empty());
+        let later = t0 + intros.config.interval;
+        assert!(intros
+            .answer("asker", me, &ranked, later, NOW)
+            .refused
+            .is_none());
+    }
+
+    #[test]
+    fn accepted_introductions_skip_forgeries_known_peers_and_crowds() {
+        let mut intros = enabled();
+        let me = record(9, "/ip4/203.0.113.1/tcp/4001");
+        let known = record(1, "/ip4/198.51.100.1/tcp/4001");
+        let mut forged = record(2, "/ip4/198.51.100.2/tcp/4001");
+        forged.addrs = vec!["/ip4/192.0.2.66/tcp/4001".to_string()];
+        let mut records = vec![me.clone(), known.clone(), forged];
+        for seed in 3..=6 {
+            records.push(record(seed, &format!("/ip4/192.0.2.{seed}/tcp/4001")));
+        }
+
+        let accepted = intros.accept(
+            Introduction {
+                records,
+                refused: None,
+            },
+            &me.peer,
+            |peer| peer == known.peer,
+            NOW,
+        );
+        assert_eq!(accepted.invalid, 1);
+        assert_eq!(accepted.dial.len(), 2);
+        assert!(intros.record(&known.peer).is_some());
+        assert!(!intros.should_ask("peer", intros.config.sparse_below, Instant::now()));
+        assert!(intros.should_ask("peer", 0, Instant::now()));
+        assert!(!intros.should_ask("peer", 0, Instant::now()));
+    }
+}
@@ -0,0 +1,339 @@
+//! Award leases for tasks.
+//!
+//! Awarding a task to a winner that then goes silent would leave the task
+//! stuck, so an award is a lease. The auctioneer (the task's publisher)
+//! gossips a [`LeaseMessage::Award`] on the task topic; the winner adds the
+//! task to its in-flight set and publishes a [`LeaseMessage::Renew`] every
+//! `renew_every` until it finishes. If no renewal reaches the auctioneer
+//! within `lease`, the auction is re-opened by publishing the task again.
+//!
+//! A winner that cannot take the task after all, such as one already at its
+//! capacity limits, answers with a [`LeaseMessage::Decline`] and the
+//! auctioneer re-opens the auction at once instead of waiting for the lease
+//! to lapse. Older auctioneers do not read declines and fall back on the
+//! lapse.
+//!
+//! Renewals can be lost, so the lease spans several renewal periods, and a
+//! task is settled by its first result whoever sends it: a late result from
+//! the original winner still completes a re-opened task, and any result
+//! after the first is reported as a duplicate.
+
+use crate::core::Task;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeaseConfig {
+    /// An award lapses when no renewal arrives for this long.
+    pub lease: Duration,
+    /// How often a winner renews the tasks it holds.
+    pub renew_every: Duration,
+    /// Re-openings per task before the auctioneer gives up on it.
+    pub max_reopens: u32,
+}
+
+impl Default for LeaseConfig {
+    fn default() -> Self {
+        Self {
+            lease: Duration::from_secs(30),
+            renew_every: Duration::from_secs(10),
+            max_reopens: 3,
+        }
+    }
+}
+
+/// Gossiped on the task topic alongside tasks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LeaseMessage {
+    /// Sent by the task's publisher.
+    Award {
+        task: Task,
+        winner: String,
+        lease_secs: u64,
+    },
+    /// Sent by the winner while it works on the task.
+    Renew { task_id: String, winner: String },
+    /// Sent by the winner when it will not take the task.
+    Decline {
+        task_id: String,
+        winner: String,
+        reason: String,
+    },
+}
+
+/// What a result means to the auctioneer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Settlement {
+    /// The first result for the task; use it.
+    First,
+    /// The task is already settled; drop it.
+    Duplicate,
+}
+
+#[derive(Debug, Clone)]
+struct Lease {
+    task: Task,
+    winner: String,
+    renewed: Instant,
+    reopens: u32,
+}
+
+/// Both sides of the lease protocol for one node.
+#[derive(Debug, Default)]
+pub struct LeaseBook {
+    pub config: LeaseConfig,
+    /// Awards this node made, by task id.
+    granted: HashMap<String, Lease>,
+    /// Re-openings so far of tasks whose lease lapsed, kept across re-awards.
+    reopens: HashMap<String, u32>,
+    /// Awards this node won, with when it last renewed them.
+    held: HashMap<String, Instant>,
+    settled: HashSet<String>,
+    outgoing: Vec<LeaseMessage>,
+}
+
+impl LeaseBook {
+    pub fn new(config: LeaseConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Award `task` to `winner` and queue the announcement.
+    pub fn award(&mut self, task: Task, winner: String, now: Instant) {
+        let reopens = self.reopens.get(&task.id).copied().unwrap_or(0);
+        self.outgoing.push(LeaseMessage::Award {
+            task: task.clone(),
+            winner: winner.clone(),
+            lease_secs: self.config.lease.as_secs(),
+        });
+        self.granted.insert(
+            task.id.clone(),
+            Lease {
+                task,
+                winner,
+                renewed: now,
+                reopens,
+            },
+        );
+    }
+
+    /// Note a renewal gossiped by `winner`. Renewals for leases this node
+    /// did not grant, or from anyone but the current winner, are ignored.
+    pub fn renewed(&mut self, task_id: &str, winner: &str, now: Instant) -> bool {
+        match self.granted.get_mut(task_id) {
+            Some(lease) if lease.winner == winner => {
+                lease.renewed = now;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Start holding an award this node won.
+    pub fn won(&mut self, task_id: &str, now: Instant) {
+        self.held.insert(task_id.to_string(), now);
+    }
+
+    /// Queue a decline for an award this node won but will not take.
+    pub fn decline(&mut self, task_id: &str, me: &str, reason: String) {
+        self.held.remove(task_id);
+        self.outgoing.push(LeaseMessage::Decline {
+            task_id: task_id.to_string(),
+            winner: me.to_string(),
+            reason,
+        });
+    }
+
+    /// Note a decline gossiped by `winner` and return the task to publish
+    /// again. Re-openings count against `max_reopens` as lapses do; declines
+    /// for leases this node did not grant, or from anyone but the current
+    /// winner, are ignored.
+    pub fn declined(&mut self, task_id: &str, winner: &str) -> Option<Task> {
+        if self.granted.get(task_id)?.winner != winner {
+            return None;
+        }
+        let lease = self.granted.remove(task_id)?;
+        if lease.reopens >= self.config.max_reopens {
+            tracing::warn!(%task_id, %winner, "Abandoning task after repeated re-openings");
+            self.reopens.remove(task_id);
+            return None;
+        }
+        self.reopens.insert(task_id.to_string(), lease.reopens + 1);
+        Some(lease.task)
+    }
+
+    /// Renewals due for held awards still in `in_flight`. Held awards that
+    /// have left it are finished and dropped.
+    pub fn due_renewals(
+        &mut self,
+        me: &str,
+        in_flight: &HashSet<String>,
+        now: Instant,
+    ) -> Vec<LeaseMessage> {
+        self.held.retain(|task_id, _| in_flight.contains(task_id));
+        let renew_every = self.config.renew_every;
+        self.held
+            .iter_mut()
+            .filter(|(_, last)| now.saturating_duration_since(**last) >= renew_every)
+            .map(|(task_id, last)| {
+                *last = now;
+                LeaseMessage::Renew {
+                    task_id: task_id.clone(),
+                    winner: me.to_string(),
+                }
+            })
+            .collect()
+    }
+
+    /// Tasks whose lease lapsed, to publish again. A task that has been
+    /// re-opened `max_reopens` times is abandoned instead.
+    pub fn lapsed(&mut self, now: Instant) -> Vec<(Task, String)> {
+        let lease = self.config.lease;
+        let lapsed: Vec<String> = self
+            .granted
+            .iter()
+            .filter(|(_, l)| now.saturating_duration_since(l.renewed) >= lease)
+            .map(|(task_id, _)| task_id.clone())
+            .collect();
+        let mut reopened = Vec::new();
+        for task_id in lapsed {
+            let Some(lease) = self.granted.remove(&task_id) else {
+                continue;
+            };
+            if lease.reopens >= self.config.max_reopens {
+                tracing::warn!(
+                    %task_id,
+                    winner = %lease.winner,
+                    "Abandoning task after repeated lapsed leases"
+                );
+                self.reopens.remove(&task_id);
+                continue;
+            }
+            self.reopens.insert(task_id, lease.reopens + 1);
+            reopened.push((lease.task, lease.winner));
+        }
+        reopened
+    }
+
+    /// Record a result for `task_id`. Only the first settles the task and
+    /// closes its lease.
+    pub fn settle(&mut self, task_id: &str) -> Settlement {
+        if !self.settled.insert(task_id.to_string()) {
+            return Settlement::Duplicate;
+        }
+        self.granted.remove(task_id);
+        self.reopens.remove(task_id);
+        Settlement::First
+    }
+
+    pub fn take_outgoing(&mut self) -> Vec<LeaseMessage> {
+        std::mem::take(&mut self.outgoing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Capability;
+
+    const SEC: Duration = Duration::from_secs(1);
+
+    fn task() -> Task {
+        Task::new("t1".into(), Capability::Compute(1), 1, "me".into())
+    }
+
+    #[test]
+    fn silent_winner_reopens_the_auction() {
+        let mut book = LeaseBook::default();
+        let t0 = Instant::now();
+        book.award(task(), "w1".into(), t0);
+        assert!(matches!(
+            book.take_outgoing().as_slice(),
+            [LeaseMessage::Award { winner, .. }] if winner == "w1"
+        ));
+
+        // A renewal from someone else does not extend the lease.
+        assert!(!book.renewed("t1", "w2", t0 + SEC * 20));
+        assert!(book.renewed("t1", "w1", t0 + SEC * 20));
+        assert!(book.lapsed(t0 + SEC * 45).is_empty());
+
+        let reopened = book.lapsed(t0 + SEC * 50);
+        assert_eq!(reopened.len(), 1);
+        assert_eq!(reopened[0].1, "w1");
+        assert!(book.lapsed(t0 + SEC * 100).is_empty());
+
+        // The re-auction awards someone else, but the old winner answers
+        // first; its result settles the task and the new lease closes.
+        book.award(task(), "w2".into(), t0 + SEC * 60);
+        assert_eq!(book.settle("t1"), Settlement::First);
+        assert_eq!(book.settle("t1"), Settlement::Duplicate);
+        assert!(book.lapsed(t0 + SEC * 200).is_empty());
+    }
+
+    #[test]
+    fn reopens_are_bounded() {
+        let mut book = LeaseBook::new(LeaseConfig {
+            max_reopens: 1,
+            ..LeaseConfig::default()
+        });
+        let t0 = Instant::now();
+        book.award(task(), "w1".into(), t0);
+        assert_eq!(book.lapsed(t0 + SEC * 30).len(), 1);
+        book.award(task(), "w2".into(), t0 + SEC * 30);
+        assert!(book.lapsed(t0 + SEC * 60).is_empty());
+    }
+
+    #[test]
+    fn a_declined_award_reopens_at_once() {
+        let mut book = LeaseBook::default();
+        let t0 = Instant::now();
+        book.award(task(), "w1".into(), t0);
+        book.take_outgoing();
+
+        assert!(book.declined("t1", "w2").is_none());
+        assert_eq!(
+            book.declined("t1", "w1").map(|task| task.id),
+            Some("t1".into())
+        );
+        assert!(book.declined("t1", "w1").is_none());
+        assert!(book.lapsed(t0 + SEC * 100).is_empty());
+
+        let mut winner = LeaseBook::default();
+        winner.won("t1", t0);
+        winner.decline("t1", "w1", "at capacity".into());
+        assert!(matches!(
+            winner.take_outgoing().as_slice(),
+            [LeaseMessage::Decline { task_id, winner, .. }] if task_id == "t1" && winner == "w1"
+        ));
+        let in_flight = HashSet::from(["t1".to_string()]);
+        assert!(winner
+            .due_renewals("w1", &in_flight, t0 + SEC * 60)
+            .is_empty());
+    }
+
+    #[test]
+    fn winner_renews_until_the_task_leaves_in_flight() {
+        let mut book = LeaseBook::default();
+        let t0 = Instant::now();
+        book.won("t1", t0);
+        let mut in_flight = HashSet::from(["t1".to_string()]);
+
+        assert!(book.due_renewals("w1", &in_flight, t0 + SEC * 5).is_empty());
+        assert!(matches!(
+            book.due_renewals("w1", &in_flight, t0 + SEC * 10).as_slice(),
+            [LeaseMessage::Renew { task_id, .. }] if task_id == "t1"
+        ));
+        assert!(book
+            .due_renewals("w1", &in_flight, t0 + SEC * 15)
+            .is_empty());
+
+        in_flight.clear();
+        assert!(book
+            .due_renewals("w1", &in_flight, t0 + SEC * 30)
+            .is_empty());
+    }
+}
@@ -1,30 +1,179 @@
+use bytes::Bytes;
 use ed25519_dalek::SigningKey;
 use fjall::{Database, Keyspace, KeyspaceCreateOptions};
-use libp2p::{futures::StreamExt, gossipsub, swarm::SwarmEvent, Multiaddr, PeerId};
-use rand::{rng, Rng};
+use libp2p::{
+    futures::StreamExt, gossipsub, multiaddr::Protocol, request_response, swarm::SwarmEvent,
+    Multiaddr, PeerId,
+};
+use rand::rng;
 use rand_core::OsRng;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tracing::info;
 
+pub mod acl;
+pub mod admission;
+pub mod aggregate;
+pub mod anomaly;
+pub mod anti_entropy;
+pub mod attestation;
+pub mod auction;
+pub mod audit;
+pub mod auth;
+pub mod bidding;
+pub mod bridge;
 pub mod capabilities;
+pub mod capacity;
+pub mod capture;
+pub mod catalog;
+pub mod chunks;
+pub mod cluster;
 pub mod compute;
+pub mod config;
+pub mod control;
 pub mod core;
+pub mod damping;
+pub mod deferred;
+pub mod degradation;
+pub mod departure;
+pub mod did;
+pub mod differentiation;
+pub mod dnssd;
+pub mod embed;
+pub mod emergency;
+pub mod epoch;
 pub mod eval;
+pub mod events;
+pub mod fixtures;
+pub mod geohash;
+pub mod health;
+pub mod hibernate;
+pub mod host;
+pub mod introduction;
+pub mod lease;
+pub mod lifecycle;
+pub mod mailbox;
 pub mod mesh;
+pub mod mesh_actor;
+pub mod mesh_manager;
 pub mod mycelium;
+pub mod offload;
+pub mod ota;
+pub mod prometheus;
+pub mod provenance;
+pub mod quorum;
+pub mod reputation;
+pub mod results;
+pub mod role;
+pub mod rules;
+pub mod schema;
+pub mod sequence;
+pub mod shard;
+pub mod slo;
+pub mod spike;
+pub mod storage;
+pub mod support;
 pub mod sync;
+pub mod tenant;
+pub mod testing;
+pub mod topic;
+pub mod trace;
+pub mod util;
+pub mod watchdog;
+pub mod wire;
+pub mod zone;
 
 pub use crate::core::{
-    BasicSensor, BatteryMetabolism, Bid, Capability, EnergyFacts, EnergyStatus, Metabolism,
-    MockMetabolism, PowerMode, Task, VirtualSensor,
+    Attestation, BasicSensor, BatteryMetabolism, Bid, BidPrice, Capability, EnergyFacts,
+    EnergyStatus, FixedLocation, HarvestingMetabolism, LocationSensor, Metabolism, MockMetabolism,
+    NodeRole, NodeState, PowerMode, ResultPayload, SensorReading, Task, TaskResult, VirtualSensor,
 };
 
+use crate::acl::TopicAcl;
+use crate::admission::{Admission, AdmissionError, JoinRequest, JoinResponse};
+use crate::aggregate::SensorAggregator;
+use crate::anomaly::AnomalyDetector;
+use crate::anti_entropy::AntiEntropy;
+use crate::attestation::AttestationGate;
+use crate::audit::{token_digest, AuditLog, AuditRecord, Decision};
+use crate::auth::{unix_now, AuthError, Delegation, DelegationLimits};
+use crate::bidding::QuorumConfig;
+use crate::capacity::{admit_output, CapacityError, CapacityLimits};
+use crate::capture::{CaptureError, CaptureWriter, CapturedMessage};
+use crate::catalog::{
+    Catalog, CatalogDelta, CatalogEntry, CatalogRequest, CatalogSnapshot, PeerCatalogs,
+};
+use crate::chunks::{ChunkError, ChunkStore};
+use crate::compute::calibration::{Calibration, ExecutionReport};
+use crate::compute::memo::{memo_key, Memoized, ResultCache, CACHED_COST_MAH};
+use crate::compute::registry::{artifact_key, ArtifactApproval, ArtifactPolicy};
+use crate::compute::{ComputeError, ComputeRuntime, ExecutionLimits};
+use crate::config::{ConfigSection, HyphaConfig};
+use crate::control::{ControlError, SignedControl};
+use crate::damping::HeartbeatDamper;
+use crate::deferred::VerifyQueue;
+use crate::degradation::DegradationLadder;
+use crate::departure::{Departing, DepartureMonitor};
+use crate::differentiation::RoleInference;
+use crate::emergency::EmergencyRelay;
+use crate::epoch::{ConfigEpoch, EpochError, EpochWatcher, EPOCH_KEY};
 use crate::eval::MetricsCollector;
-use crate::mesh::{MeshConfig, MeshControl, TopicMesh};
-use crate::mycelium::{Mycelium, MyceliumEvent, NetProfile, Spike};
+use crate::events::{NodeEvent, EVENT_CHANNEL_CAPACITY};
+use crate::health::{FleetHealth, HealthInputs, HealthWeights};
+use crate::hibernate::{PeerStore, Snapshot, Wake, HIBERNATION_KEY};
+use crate::introduction::{
+    Introduction, IntroductionRequest, Introductions, SignedPeer, MAX_RECORD_ADDRS,
+};
+use crate::lease::{LeaseBook, LeaseMessage, Settlement};
+use crate::lifecycle::{LifecycleSignals, NodeLifecycle};
+use crate::mailbox::{AssignmentError, Mailbox, MailboxAck, MailboxDelivery, Refusal};
+use crate::mesh::{MeshConfig, OriginFact, PersistedMesh, TopicMesh, PRESSURE_SPIKE_THRESHOLD};
+use crate::mesh_actor::{MeshHandle, MeshSnapshot};
+use crate::mycelium::{
+    Mycelium, MyceliumBehaviour, MyceliumEvent, NetOptions, NetProfile, SubscriptionPolicy,
+    TopicKind, BOOTSTRAP_REDIAL_INTERVAL,
+};
+use crate::offload::{Offload, DEFAULT_QUEUE};
+use crate::ota::{
+    release_key, ChunkRequest, ChunkResponse, Download, FirmwareRelease, Installer, OtaConfig,
+    OtaError, DEFAULT_CHUNK_SIZE,
+};
+use crate::provenance::{unix_millis, Provenance, ProvenanceLog};
+use crate::quorum::{
+    Approval, QuorumAction, QuorumCert, QuorumCollector, QuorumMessage, SignerSet,
+};
+use crate::reputation::{PeerRecord, Reputation};
+use crate::results::ResultError;
+use crate::role::RoleDefaults;
+use crate::rules::{RuleAction, RuleEngine};
+use crate::sequence::{
+    Arrival, GapTracker, ResendRequest, ResendResponse, Resent, SequenceConfig, Sequencer,
+};
+use crate::shard::TaskShards;
+use crate::slo::{SloMonitor, SLO_ALERT_PATTERN};
+use crate::spike::{SpikeError, SpikeGuard};
+use crate::storage::{StorageManager, DEFAULT_CACHE_CAPACITY};
+use crate::support::SupportBundle;
 use crate::sync::{SharedState, SyncMessage};
+use crate::tenant::{Tenant, TenantSync};
+use crate::watchdog::{WatchdogConfig, Watermark, LIVENESS_TICK};
+use crate::wire::Priority;
+use crate::zone::{Forward, ZoneRouter};
+
+/// Storage key for the mesh state saved when `run_for` returns.
+const MESH_STATE_KEY: &str = "mesh_state";
+
+/// Storage key for peers' long-term reputation records.
+const REPUTATION_KEY: &str = "peer_reputation";
+
+/// Storage key for where this node's message sequence continues.
+const SEQUENCE_KEY: &str = "sequence_next";
+
+/// How long a computed result is served from the cache.
+const RESULT_CACHE_TTL: Duration = Duration::from_secs(24 * 3600);
 
 pub struct SporeNode {
     pub peer_id: PeerId,
@@ -32,12 +181,238 @@ pub struct SporeNode {
     pub metabolism: Arc<Mutex<dyn Metabolism>>,
     pub storage: Database,
     pub db: Keyspace,
+    /// `db` behind a hot in-memory cache; message reads and writes go here.
+    pub messages: Arc<StorageManager>,
     pub signing_key: SigningKey,
     pub capabilities: Vec<Capability>,
+    /// Set at startup with `set_role` and advertised in status adverts.
+    pub role: Option<NodeRole>,
+    /// Whether the node bids for tasks at all; a role may turn it off.
+    pub compute: bool,
+    /// Caps on tasks in flight per kind of capability; see
+    /// `crate::capacity`. Unlimited by default.
+    pub capacity: CapacityLimits,
+    /// Roles peers advertise in their own status adverts.
+    peer_roles: HashMap<String, NodeRole>,
+    /// Moves `role` with the node's relay and energy history, when enabled.
+    pub role_inference: RoleInference,
+    /// Hardware class whose fuel-to-mAh calibration prices this node's bids.
+    pub device_class: String,
+    /// Execution telemetry by device class; see `record_execution`.
+    pub calibration: Arc<Mutex<Calibration>>,
     pub sensors: Vec<Box<dyn VirtualSensor>>,
+    /// Where the node is. Tasks targeting an area are bid on only from
+    /// inside it, so a node without a location never bids on them.
+    pub location: Option<Box<dyn LocationSensor>>,
+    /// Characters of the node's geohash listed in its catalog.
+    pub location_precision: usize,
     pub mesh: Arc<Mutex<TopicMesh>>,
+    /// Mesh view published by the mesh actor while `run_for` is running.
+    pub mesh_snapshot: tokio::sync::watch::Sender<MeshSnapshot>,
     pub metrics: Arc<Mutex<MetricsCollector>>,
     pub shared_state: Arc<Mutex<SharedState>>,
+    /// Which gossip topics to join in each power mode.
+    pub subscription_policy: SubscriptionPolicy,
+    /// Tasks queued by `publish_task`, sent on the next heartbeat.
+    pub outgoing_tasks: Arc<Mutex<Vec<Task>>>,
+    /// Readings queued by `publish_reading`, aggregated before sending.
+    pub outgoing_readings: Arc<Mutex<Vec<SensorReading>>>,
+    /// Tasks this node accepted and has not finished, by id. Listed in the
+    /// departure announcement so peers can re-auction them.
+    pub in_flight: Arc<Mutex<HashMap<String, Task>>>,
+    /// Award leases granted and held by this node.
+    pub leases: Arc<Mutex<LeaseBook>>,
+    pub events: tokio::sync::broadcast::Sender<NodeEvent>,
+    /// Peer ids whose grants may root a delegation chain presented to this
+    /// node, in addition to the node itself.
+    pub trusted_issuers: Vec<String>,
+    pub delegation_limits: DelegationLimits,
+    /// Refuse tasks that carry no token. Off by default, while tokens are
+    /// still optional on the task topic.
+    pub require_token: bool,
+    /// Which connected peers may join the mesh and publish on restricted
+    /// topics.
+    pub admission: Admission,
+    /// Topics reserved for holders of a capability, checked on receive and
+    /// before this node's own publishes.
+    pub topic_acl: TopicAcl,
+    /// Delegations this node presents in its status adverts.
+    pub grants: Vec<Delegation>,
+    /// Applications hosted alongside the node's own scope, by tenant id.
+    pub tenants: HashMap<String, Tenant>,
+    /// Every token check, accepted or not.
+    pub audit: Arc<AuditLog>,
+    /// Where each stored `msg_<id>` came from.
+    pub provenance: Arc<ProvenanceLog>,
+    /// Outputs of earlier executions, keyed by code and input hash.
+    pub result_cache: Arc<ResultCache>,
+    /// Whether task code must be approved in the artifact registry.
+    pub artifacts: ArtifactPolicy,
+    pub quorum: Arc<Mutex<QuorumCollector>>,
+    /// When to stay out of task auctions others are already bidding in.
+    pub quorum_sensing: QuorumConfig,
+    /// Approvals and certificates queued for the quorum topic.
+    pub outgoing_quorum: Arc<Mutex<Vec<QuorumMessage>>>,
+    /// Evaluated against `sensors` on every heartbeat.
+    pub rules: RuleEngine,
+    /// Summarizes local sensor samples and queued readings before gossip.
+    pub aggregator: SensorAggregator,
+    /// Flags peers whose message patterns break from their own baseline.
+    pub anomaly: AnomalyDetector,
+    /// Misbehavior per peer over days, kept across restarts.
+    pub reputation: Reputation,
+    /// Signs outgoing spikes and enforces per-source spike quotas.
+    pub spikes: SpikeGuard,
+    /// Decides each heartbeat whether to broadcast a shared-state SyncStep1.
+    pub anti_entropy: AntiEntropy,
+    /// Energy bands deciding which features stay on.
+    pub degradation: DegradationLadder,
+    /// Relays spikes and urgent tasks for a while after a danger spike.
+    pub emergency: EmergencyRelay,
+    /// Announces departure once energy is forecast to run out soon.
+    pub departure: DepartureMonitor,
+    /// Delivery SLOs judged on every heartbeat. Their topics are traced.
+    pub slo: SloMonitor,
+    /// Config queued through `NodeLink::apply_config`, applied on the next
+    /// heartbeat.
+    pub pending_config: Arc<Mutex<Option<HyphaConfig>>>,
+    /// This node's own hardware attestation, attached to its status adverts.
+    pub attestation: Option<Attestation>,
+    /// Verified peer attestations, and the topics and awards that need one.
+    pub attestations: Arc<Mutex<AttestationGate>>,
+    /// This node's zone and its zone's elected bridge.
+    pub zones: ZoneRouter,
+    /// How tasks are spread over task topic shards, and the plan replaced
+    /// by the last reshard while it drains.
+    pub task_shards: TaskShards,
+    /// Authenticated status adverts waiting for their grants and attestation
+    /// to be verified at a pulse peak, when enabled.
+    pub status_checks: VerifyQueue<EnergyStatus>,
+    /// Lifecycle state, moved on each heartbeat and advertised in status.
+    pub lifecycle: NodeLifecycle,
+    /// This node's capabilities and sensors, published as versioned deltas.
+    pub catalog: Catalog,
+    /// Catalogs heard from peers.
+    pub peer_catalogs: PeerCatalogs,
+    /// Tasks held for known peers while they are away, when enabled.
+    pub mailbox: Mailbox,
+    /// Peer samples asked of and handed to new connections during sparse
+    /// boots, when enabled; see `crate::introduction`.
+    pub introductions: Introductions,
+    /// Per-source sequence numbers on application topics, when enabled;
+    /// see `crate::sequence`.
+    pub sequencing: SequenceConfig,
+    /// Stamps this node's sequenced publishes and answers resend requests.
+    pub sequencer: Arc<Mutex<Sequencer>>,
+    gaps: GapTracker,
+    /// Damps pressure-driven heartbeat acceleration, when enabled.
+    pub damping: HeartbeatDamper,
+    /// Addresses peers were last dialed at, kept in hibernation snapshots.
+    pub peer_store: PeerStore,
+    /// Set after waking from a hibernation snapshot until the node is back
+    /// in touch with its previous mesh.
+    waking: Option<Wake>,
+    /// Records inbound gossip for replay; see `crate::capture`. Off by
+    /// default.
+    pub capture: Option<CaptureWriter>,
+    /// Captured messages the next run loop handles before live traffic.
+    replaying: VecDeque<gossipsub::Event>,
+    /// Whether `advertisement` describes the node for DNS-SD. Turn off for
+    /// stealth deployments.
+    pub dns_sd: bool,
+    /// How the health score in status adverts weighs its inputs.
+    pub health_weights: HealthWeights,
+    /// Peers' health scores from their status adverts, and this node's.
+    pub fleet_health: Arc<Mutex<FleetHealth>>,
+    /// Firmware updates over the mesh; see `crate::ota`. Off by default.
+    pub ota: OtaConfig,
+    /// Content-addressed chunks this node holds and serves to peers.
+    pub chunks: Arc<ChunkStore>,
+    installer: Option<Box<dyn Installer>>,
+    /// The firmware release being fetched.
+    download: Option<Download>,
+    /// Hash of the last release that failed to install.
+    firmware_failed: Option<String>,
+    /// Operator-signed config epochs read from `shared_state`.
+    pub epochs: EpochWatcher,
+    /// Stamped by the run loop on every pass; watched by `run_supervised`.
+    pub watermark: Arc<Watermark>,
+    pub watchdog: WatchdogConfig,
+    /// Set once the run loop holding this node is abandoned, so that it
+    /// stops writing to logs it shares with its successor.
+    retired: Arc<AtomicBool>,
+}
+
+/// Cloneable handles for feeding a running node from other tasks, such as
+/// protocol bridges, while `run_for` holds the node.
+#[derive(Clone)]
+pub struct NodeLink {
+    pub outgoing_tasks: Arc<Mutex<Vec<Task>>>,
+    pub outgoing_readings: Arc<Mutex<Vec<SensorReading>>>,
+    pub in_flight: Arc<Mutex<HashMap<String, Task>>>,
+    pub leases: Arc<Mutex<LeaseBook>>,
+    pub events: tokio::sync::broadcast::Sender<NodeEvent>,
+    pub metabolism: Arc<Mutex<dyn Metabolism>>,
+    pub mesh: Arc<Mutex<TopicMesh>>,
+    /// Read this rather than locking `mesh` while the node is running.
+    pub mesh_snapshot: tokio::sync::watch::Receiver<MeshSnapshot>,
+    pub pending_config: Arc<Mutex<Option<HyphaConfig>>>,
+    pub attestations: Arc<Mutex<AttestationGate>>,
+    pub lifecycle: tokio::sync::watch::Receiver<NodeState>,
+    pub fleet_health: Arc<Mutex<FleetHealth>>,
+}
+
+impl NodeLink {
+    pub fn publish_task(&self, task: Task) {
+        self.outgoing_tasks.lock().unwrap().push(task);
+    }
+
+    pub fn publish_reading(&self, reading: SensorReading) {
+        self.outgoing_readings.lock().unwrap().push(reading);
+    }
+
+    pub fn accept_task(&self, task: Task) {
+        self.in_flight.lock().unwrap().insert(task.id.clone(), task);
+    }
+
+    pub fn finish_task(&self, task_id: &str) -> Option<Task> {
+        self.in_flight.lock().unwrap().remove(task_id)
+    }
+
+    pub fn award_task(&self, task: Task, winner: &PeerId) -> bool {
+        if !self
+            .attestations
+            .lock()
+            .unwrap()
+            .may_award(&winner.to_string())
+        {
+            return false;
+        }
+        self.leases
+            .lock()
+            .unwrap()
+            .award(task, winner.to_string(), std::time::Instant::now());
+        true
+    }
+
+    pub fn settle_result(&self, result: &TaskResult) -> Settlement {
+        self.leases.lock().unwrap().settle(&result.task_id)
+    }
+
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<NodeEvent> {
+        self.events.subscribe()
+    }
+
+    /// Hand the running node a new config. It is diffed and applied on the
+    /// next heartbeat, replacing any config still waiting.
+    pub fn apply_config(&self, config: HyphaConfig) {
+        *self.pending_config.lock().unwrap() = Some(config);
+    }
+
+    /// The node's lifecycle state as of its last heartbeat.
+    pub fn state(&self) -> NodeState {
+        *self.lifecycle.borrow()
+    }
 }
 
 impl SporeNode {
@@ -77,57 +452,639 @@ impl SporeNode {
             "hypha".to_string(),
             MeshConfig::default(),
         )));
+        // A hibernation snapshot is read once and stands in for the mesh
+        // state saved with it.
+        let snapshot = match db.get(HIBERNATION_KEY)? {
+            Some(bytes) => {
+                db.remove(HIBERNATION_KEY)?;
+                match Snapshot::decode(&bytes) {
+                    Ok(snapshot) => Some(snapshot),
+                    Err(e) => {
+                        tracing::warn!(err = %e, "Ignoring unreadable hibernation snapshot");
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+        // Warm start: rejoin the mesh this node left, aged by the downtime.
+        let saved_mesh = match &snapshot {
+            Some(snapshot) => Some((snapshot.saved_at, snapshot.mesh.clone())),
+            None => match db.get(MESH_STATE_KEY)? {
+                Some(bytes) => match serde_json::from_slice::<(u64, PersistedMesh)>(&bytes) {
+                    Ok(saved) => Some(saved),
+                    Err(e) => {
+                        tracing::warn!(err = %e, "Ignoring unreadable mesh state");
+                        None
+                    }
+                },
+                None => None,
+            },
+        };
+        if let Some((saved_at, state)) = saved_mesh {
+            let downtime = Duration::from_millis(trace::now_ms().saturating_sub(saved_at));
+            mesh.lock().unwrap().restore(state, downtime);
+        }
+        let mut reputation = Reputation::default();
+        if let Some(bytes) = db.get(REPUTATION_KEY)? {
+            match serde_json::from_slice::<HashMap<String, PeerRecord>>(&bytes) {
+                Ok(records) => reputation.restore(records),
+                Err(e) => tracing::warn!(err = %e, "Ignoring unreadable reputation records"),
+            }
+        }
+        // Starting over at 0 would reuse numbers peers have already seen
+        // and drop as replays, so an unreadable sequence is an error.
+        let resume = match db.get(SEQUENCE_KEY)? {
+            Some(bytes) => serde_json::from_slice::<u64>(&bytes)
+                .map_err(|e| format!("unreadable message sequence: {e}"))?,
+            None => 0,
+        };
+        let sequence_db = db.clone();
+        let sequencer =
+            Sequencer::new(&peer_id.to_string(), resume).with_persist(move |reserved| {
+                let bytes = serde_json::to_vec(&reserved).expect("u64 serializes");
+                if let Err(e) = sequence_db.insert(SEQUENCE_KEY, bytes) {
+                    tracing::warn!(err = %e, "Failed to reserve message sequence numbers");
+                }
+            });
         let metrics = Arc::new(Mutex::new(MetricsCollector::new()));
         let shared_state = Arc::new(Mutex::new(SharedState::new("hypha_global_state")));
+        let peer_store = snapshot
+            .as_ref()
+            .map(Snapshot::peer_store)
+            .unwrap_or_default();
+        let waking = snapshot.map(|snapshot| {
+            if let Err(e) = shared_state.lock().unwrap().apply_update(&snapshot.state) {
+                tracing::warn!(err = %e, "Ignoring unreadable shared state in snapshot");
+            }
+            snapshot.wake()
+        });
+        let audit = Arc::new(AuditLog::open(&storage)?);
+        let provenance = Arc::new(ProvenanceLog::open(&storage)?);
+        let result_cache = Arc::new(ResultCache::open(&storage, RESULT_CACHE_TTL)?);
+        let chunks = Arc::new(ChunkStore::open(&storage)?);
 
         Ok(Self {
             peer_id,
             power_mode: PowerMode::Normal,
             metabolism,
             storage,
+            messages: Arc::new(StorageManager::new(db.clone(), DEFAULT_CACHE_CAPACITY)),
             db,
             signing_key,
             capabilities: Vec::new(),
+            role: None,
+            compute: true,
+            capacity: CapacityLimits::default(),
+            peer_roles: HashMap::new(),
+            role_inference: RoleInference::default(),
+            device_class: "generic".to_string(),
+            calibration: Arc::new(Mutex::new(Calibration::default())),
             sensors: Vec::new(),
+            location: None,
+            location_precision: geohash::DEFAULT_PRECISION,
             mesh,
+            mesh_snapshot: tokio::sync::watch::channel(MeshSnapshot::default()).0,
             metrics,
             shared_state,
+            subscription_policy: SubscriptionPolicy::default(),
+            outgoing_tasks: Arc::new(Mutex::new(Vec::new())),
+            outgoing_readings: Arc::new(Mutex::new(Vec::new())),
+            in_flight: Arc::new(Mutex::new(HashMap::new())),
+            leases: Arc::new(Mutex::new(LeaseBook::default())),
+            events: tokio::sync::broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            trusted_issuers: Vec::new(),
+            delegation_limits: DelegationLimits::default(),
+            require_token: false,
+            tenants: HashMap::new(),
+            admission: Admission::default(),
+            topic_acl: TopicAcl::default(),
+            grants: Vec::new(),
+            audit,
+            provenance,
+            result_cache,
+            artifacts: ArtifactPolicy::default(),
+            quorum: Arc::new(Mutex::new(QuorumCollector::default())),
+            quorum_sensing: QuorumConfig::default(),
+            outgoing_quorum: Arc::new(Mutex::new(Vec::new())),
+            rules: RuleEngine::default(),
+            aggregator: SensorAggregator::default(),
+            anomaly: AnomalyDetector::default(),
+            reputation,
+            spikes: SpikeGuard::default(),
+            anti_entropy: AntiEntropy::default(),
+            degradation: DegradationLadder::default(),
+            emergency: EmergencyRelay::default(),
+            departure: DepartureMonitor::default(),
+            slo: SloMonitor::default(),
+            pending_config: Arc::new(Mutex::new(None)),
+            attestation: None,
+            attestations: Arc::new(Mutex::new(AttestationGate::default())),
+            zones: ZoneRouter::default(),
+            task_shards: TaskShards::default(),
+            status_checks: VerifyQueue::default(),
+            lifecycle: NodeLifecycle::default(),
+            catalog: Catalog::default(),
+            peer_catalogs: PeerCatalogs::default(),
+            mailbox: Mailbox::default(),
+            introductions: Introductions::default(),
+            sequencing: SequenceConfig::default(),
+            sequencer: Arc::new(Mutex::new(sequencer)),
+            gaps: GapTracker::default(),
+            damping: HeartbeatDamper::default(),
+            peer_store,
+            waking,
+            capture: None,
+            replaying: VecDeque::new(),
+            dns_sd: true,
+            health_weights: HealthWeights::default(),
+            fleet_health: Arc::new(Mutex::new(FleetHealth::default())),
+            ota: OtaConfig::default(),
+            chunks,
+            installer: None,
+            download: None,
+            firmware_failed: None,
+            epochs: EpochWatcher::default(),
+            watermark: Arc::new(Watermark::default()),
+            watchdog: WatchdogConfig::default(),
+            retired: Arc::new(AtomicBool::new(false)),
         })
     }
 
+    /// Builds the node that takes over once this one's run loop is
+    /// abandoned. It shares every handle `link` gives out, the storage and
+    /// the logs kept on it, and this node's configuration; what only the
+    /// abandoned loop held (sensors and in-memory trackers such as anomaly
+    /// baselines) starts afresh. Nothing is built until the closure is
+    /// called, so take it before handing the node to a loop.
+    fn successor(&self) -> impl FnOnce() -> Self + Send + 'static {
+        let peer_id = self.peer_id;
+        let power_mode = self.power_mode.clone();
+        let metabolism = self.metabolism.clone();
+        let storage = self.storage.clone();
+        let db = self.db.clone();
+        let messages = self.messages.clone();
+        let signing_key = self.signing_key.clone();
+        let capabilities = self.capabilities.clone();
+        let role = self.role;
+        let compute = self.compute;
+        let capacity = self.capacity.clone();
+        let role_inference = self.role_inference.config.clone();
+        let device_class = self.device_class.clone();
+        let calibration = self.calibration.clone();
+        let location_precision = self.location_precision;
+        let mesh = self.mesh.clone();
+        let mesh_snapshot = self.mesh_snapshot.clone();
+        let metrics = self.metrics.clone();
+        let shared_state = self.shared_state.clone();
+        let subscription_policy = self.subscription_policy.clone();
+        let outgoing_tasks = self.outgoing_tasks.clone();
+        let outgoing_readings = self.outgoing_readings.clone();
+        let in_flight = self.in_flight.clone();
+        let leases = self.leases.clone();
+        let events = self.events.clone();
+        let trusted_issuers = self.trusted_issuers.clone();
+        let delegation_limits = self.delegation_limits;
+        let require_token = self.require_token;
+        let admission = self.admission.config.clone();
+        let topic_acl = self.topic_acl.clone();
+        let grants = self.grants.clone();
+        let tenants = self
+            .tenants
+            .iter()
+            .map(|(id, tenant)| {
+                let tenant = Tenant {
+                    capabilities: tenant.capabilities.clone(),
+                    trusted_issuers: tenant.trusted_issuers.clone(),
+                    shared_state: tenant.shared_state.clone(),
+                };
+                (id.clone(), tenant)
+            })
+            .collect::<HashMap<_, _>>();
+        let audit = self.audit.clone();
+        let provenance = self.provenance.clone();
+        let result_cache = self.result_cache.clone();
+        let artifacts = self.artifacts.clone();
+        let quorum = self.quorum.clone();
+        let quorum_sensing = self.quorum_sensing.clone();
+        let outgoing_quorum = self.outgoing_quorum.clone();
+        let rules = self.rules.reset();
+        let aggregator = self.aggregator.config.clone();
+        let anomaly = self.anomaly.config.clone();
+        let reputation = (self.reputation.config.clone(), self.db.clone());
+        let spikes = self.spikes.config.clone();
+        let anti_entropy = self.anti_entropy.config.clone();
+        let degradation = self.degradation.clone();
+        let emergency = self.emergency.config.clone();
+        let departure = self.departure.config.clone();
+        let slo = self.slo.slos().cloned().collect::<Vec<_>>();
+        let pending_config = self.pending_config.clone();
+        let attestation = self.attestation.clone();
+        let attestations = self.attestations.clone();
+        let zones = (self.zones.zone.clone(), self.zones.lease);
+        let task_shards = (self.task_shards.plan, self.task_shards.drain);
+        let lifecycle = self.lifecycle.clone();
+        let mailbox = self.mailbox.config.clone();
+        let introductions = self.introductions.config.clone();
+        let sequencing = self.sequencing.clone();
+        let sequencer = self.sequencer.clone();
+        let damping = self.damping.config.clone();
+        let peer_store = self.peer_store.clone();
+        let dns_sd = self.dns_sd;
+        let health_weights = self.health_weights;
+        let fleet_health = self.fleet_health.clone();
+        let ota = self.ota.clone();
+        let chunks = self.chunks.clone();
+        let epochs = self.epochs.config.clone();
+        let watermark = self.watermark.clone();
+        let watchdog = self.watchdog.clone();
+        move || Self {
+            peer_id,
+            power_mode,
+            metabolism,
+            storage,
+            db,
+            messages,
+            signing_key,
+            capabilities,
+            role,
+            compute,
+            capacity,
+            peer_roles: HashMap::new(),
+            role_inference: RoleInference::new(role_inference),
+            device_class,
+            calibration,
+            sensors: Vec::new(),
+            location: None,
+            location_precision,
+            mesh,
+            mesh_snapshot,
+            metrics,
+            shared_state,
+            subscription_policy,
+            outgoing_tasks,
+            outgoing_readings,
+            in_flight,
+            leases,
+            events,
+            trusted_issuers,
+            delegation_limits,
+            require_token,
+            admission: Admission::new(admission),
+            topic_acl,
+            grants,
+            tenants,
+            audit,
+            provenance,
+            result_cache,
+            artifacts,
+            quorum,
+            quorum_sensing,
+            outgoing_quorum,
+            rules,
+            aggregator: SensorAggregator::new(aggregator),
+            anomaly: AnomalyDetector::new(anomaly),
+            reputation: {
+                // Read back what the abandoned loop last saved.
+                let (config, db) = reputation;
+                let mut reputation = Reputation::new(config);
+                let saved = db.get(REPUTATION_KEY).ok().flatten();
+                match saved
+                    .map(|bytes| serde_json::from_slice::<HashMap<String, PeerRecord>>(&bytes))
+                {
+                    Some(Ok(records)) => reputation.restore(records),
+                    Some(Err(e)) => {
+                        tracing::warn!(err = %e, "Ignoring unreadable reputation records")
+                    }
+                    None => {}
+                }
+                reputation
+            },
+            spikes: SpikeGuard::new(spikes),
+            anti_entropy: AntiEntropy::new(anti_entropy),
+            degradation,
+            emergency: EmergencyRelay::new(emergency),
+            departure: DepartureMonitor::new(departure),
+            slo: {
+                let mut monitor = SloMonitor::default();
+                for slo in slo {
+                    monitor.add(slo);
+                }
+                monitor
+            },
+            pending_config,
+            attestation,
+            attestations,
+            zones: {
+                let (zone, lease) = zones;
+                let mut zones = ZoneRouter::default();
+                zones.zone = zone;
+                zones.lease = lease;
+                zones
+            },
+            task_shards: {
+                let (plan, drain) = task_shards;
+                let mut task_shards = TaskShards::default();
+                task_shards.plan = plan;
+                task_shards.drain = drain;
+                task_shards
+            },
+            status_checks: VerifyQueue::default(),
+            lifecycle,
+            catalog: Catalog::default(),
+            peer_catalogs: PeerCatalogs::default(),
+            mailbox: Mailbox::new(mailbox),
+            introductions: Introductions::new(introductions),
+            sequencing,
+            sequencer,
+            gaps: GapTracker::default(),
+            damping: HeartbeatDamper::new(damping),
+            peer_store,
+            waking: None,
+            capture: None,
+            replaying: VecDeque::new(),
+            dns_sd,
+            health_weights,
+            fleet_health,
+            ota,
+            chunks,
+            installer: None,
+            download: None,
+            firmware_failed: None,
+            epochs: EpochWatcher::new(epochs),
+            watermark,
+            watchdog,
+            retired: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
     pub fn add_sensor(&mut self, sensor: Box<dyn VirtualSensor>) {
         info!(peer_id = %self.peer_id, sensor = %sensor.name(), "Added virtual sensor");
         self.sensors.push(sensor);
     }
 
+    pub fn set_location_sensor(&mut self, sensor: Box<dyn LocationSensor>) {
+        info!(peer_id = %self.peer_id, "Added location sensor");
+        self.location = Some(sensor);
+    }
+
+    /// This node's `precision`-character geohash, when it has a fix.
+    pub fn geohash(&self, precision: usize) -> Option<String> {
+        let (lat, lon) = self.location.as_ref()?.location()?;
+        geohash::encode(lat, lon, precision)
+    }
+
+    /// Register the built-in host sensors: CPU load, SoC temperature (when
+    /// the host reports one) and the free share of the disk holding
+    /// `disk_path`. Returns the names of the sensors added.
+    #[cfg(feature = "host-metrics")]
+    pub fn add_host_sensors(&mut self, disk_path: &std::path::Path) -> Vec<&'static str> {
+        let mut sensors = vec![host::HostSensor::cpu()];
+        sensors.extend(host::HostSensor::temperature());
+        sensors.push(host::HostSensor::disk_free(disk_path));
+        sensors
+            .into_iter()
+            .map(|sensor| {
+                let name = sensor.metric().name();
+                self.add_sensor(Box::new(sensor));
+                name
+            })
+            .collect()
+    }
+
     pub fn add_capability(&mut self, cap: Capability) {
         info!(peer_id = %self.peer_id, ?cap, "Registered capability");
         self.capabilities.push(cap);
     }
 
+    /// Take `role` and its defaults for subscriptions, mesh degree, relaying
+    /// and task bidding. Call before `run_for`; later changes go through
+    /// `apply_config`.
+    pub fn set_role(&mut self, role: NodeRole) {
+        self.take_role(Some(role));
+    }
+
+    /// Take `role`'s defaults, or the all-round ones for `None`.
+    fn take_role(&mut self, role: Option<NodeRole>) {
+        let defaults = RoleDefaults::of(role.unwrap_or(NodeRole::Compute));
+        self.subscription_policy = defaults.subscriptions;
+        self.degradation.mesh = defaults.mesh;
+        self.degradation.relay_all_above = defaults.relay_all_above;
+        self.degradation.relay_max_pressure = defaults.relay_max_pressure;
+        self.degradation.relay_min_phase = defaults.relay_min_phase;
+        self.compute = defaults.compute;
+        self.role = role;
+    }
+
+    /// The role `peer` last advertised.
+    pub fn peer_role(&self, peer: &str) -> Option<NodeRole> {
+        self.peer_roles.get(peer).copied()
+    }
+
+    /// Host `tenant` under `id`, replacing any tenant already there.
+    pub fn add_tenant(&mut self, id: &str, tenant: Tenant) {
+        info!(peer_id = %self.peer_id, tenant = %id, "Hosting tenant");
+        self.tenants.insert(id.to_string(), tenant);
+    }
+
+    /// The shared-state doc for `tenant`, or the node's own for `None`.
+    pub fn shared_state_of(&self, tenant: Option<&str>) -> Option<Arc<Mutex<SharedState>>> {
+        match tenant {
+            None => Some(self.shared_state.clone()),
+            Some(id) => self.tenants.get(id).map(|t| t.shared_state.clone()),
+        }
+    }
+
+    /// The task shard `task` is published on, if the task topic is sharded.
+    fn task_shard(&self, task: &Task) -> Option<u32> {
+        self.task_shards
+            .plan
+            .shard_for(task, self.zones.zone.as_deref())
+    }
+
+    /// The task shards this node's capabilities, tenants and zone call for.
+    fn wanted_shards(&self, now: std::time::Instant) -> BTreeSet<u32> {
+        let capabilities: Vec<&Capability> = self
+            .capabilities
+            .iter()
+            .chain(
+                self.tenants
+                    .values()
+                    .flat_map(|tenant| &tenant.capabilities),
+            )
+            .collect();
+        let tenants: Vec<&str> = self.tenants.keys().map(String::as_str).collect();
+        self.task_shards
+            .wanted(&capabilities, &tenants, self.zones.zone.as_deref(), now)
+    }
+
     fn has_capability(&self, required: &Capability) -> bool {
         self.capabilities
             .iter()
             .any(|capability| capability.satisfies(required))
     }
 
+    /// Whether `task` fits the capacity limits alongside the other tasks in
+    /// flight, and its expected output fits the storage quota beside theirs.
+    fn admit(&self, task: &Task) -> Result<(), CapacityError> {
+        let in_flight = self.in_flight.lock().unwrap();
+        let others = || in_flight.values().filter(|other| other.id != task.id);
+        self.capacity.admit(
+            &task.required_capability,
+            others().map(|other| &other.required_capability),
+        )?;
+        admit_output(
+            task.expected_output_bytes,
+            self.messages.headroom(),
+            others().filter_map(|other| other.expected_output_bytes),
+        )
+    }
+
+    /// Whether the node, or the tenant `task` belongs to, can do `task`.
+    fn capable(&self, task: &Task) -> bool {
+        match &task.tenant {
+            None => self.has_capability(&task.required_capability),
+            Some(id) => self
+                .tenants
+                .get(id)
+                .is_some_and(|tenant| tenant.has_capability(&task.required_capability)),
+        }
+    }
+
     fn local_bid_for_task(&self, task: &Task, energy_score: f32) -> Option<Bid> {
-        if energy_score < 0.2 || task.reach_intensity < 0.1 {
+        if !self.compute || !self.degradation.may_bid(energy_score) || task.reach_intensity < 0.1 {
             return None;
         }
-
-        if !self.has_capability(&task.required_capability) {
+        // Addressed tasks are not auctioned.
+        if task.target.is_some() || !self.capable(task) {
+            return None;
+        }
+        if let Err(e) = self.admit(task) {
+            tracing::debug!(task_id = %task.id, reason = %e, "Not bidding at capacity");
             return None;
         }
+        if let Some(area) = &task.geohash {
+            let inside = self
+                .geohash(area.len())
+                .is_some_and(|here| geohash::covers(area, &here));
+            if !inside {
+                return None;
+            }
+        }
 
+        // A cached answer costs next to nothing, so the auction prefers us.
+        let cached = task.memo_key.as_deref().is_some_and(|key| {
+            self.result_cache
+                .contains(key, &task.required_capability, unix_now())
+        });
+        let base_mah = if cached {
+            CACHED_COST_MAH
+        } else {
+            self.calibration
+                .lock()
+                .unwrap()
+                .typical_mah(&self.device_class)
+                .unwrap_or(50.0)
+        };
+        // Harvested surplus would go to waste; selling it cheap steers work
+        // toward nodes that are energy-rich right now.
+        let discount = self.metabolism.lock().unwrap().surplus_discount();
+        let price = (discount > 0.0).then(|| BidPrice {
+            base_mah,
+            surplus_discount: discount.min(1.0),
+        });
+        let cost_mah = price
+            .as_ref()
+            .map_or(base_mah, |price| base_mah * (1.0 - price.surplus_discount));
         Some(Bid {
             task_id: task.id.clone(),
             bidder_id: self.peer_id.to_string(),
             energy_score: energy_score * task.reach_intensity,
-            cost_mah: 50.0,
+            cost_mah,
+            price,
         })
     }
 
+    /// Take `task`, addressed to this node, without an auction. It needs a
+    /// token granting its capability, the capability itself and room under
+    /// the capacity limits; it is then in flight until `finish_task`.
+    pub fn take_assignment(&self, task: &Task) -> Result<(), AssignmentError> {
+        if task.target.as_deref() != Some(self.peer_id.to_string().as_str()) {
+            return Err(AssignmentError::NotAddressed);
+        }
+        let token = task
+            .auth_token
+            .as_deref()
+            .ok_or(AssignmentError::MissingToken)?;
+        if !self.authorize(
+            token,
+            &task.required_capability,
+            task.tenant.as_deref(),
+            Some(task),
+        ) {
+            return Err(AssignmentError::Unauthorized);
+        }
+        if !self.capable(task) {
+            return Err(AssignmentError::Incapable);
+        }
+        self.admit(task)?;
+        self.in_flight
+            .lock()
+            .unwrap()
+            .insert(task.id.clone(), task.clone());
+        Ok(())
+    }
+
+    /// Run `payload` on `input` for `task`, or serve the output cached from
+    /// an earlier identical run. Fresh executions are recorded for
+    /// calibration and cached. Payloads the artifact policy does not
+    /// approve are refused.
+    pub async fn execute_task(
+        &self,
+        runtime: &dyn ComputeRuntime,
+        task: &Task,
+        payload: &[u8],
+        input: &[u8],
+        budget: f32,
+        limits: &ExecutionLimits,
+    ) -> Result<Memoized, ComputeError> {
+        {
+            let shared_state = self.shared_state.lock().unwrap();
+            self.artifacts.check(payload, |key| shared_state.get(key))?;
+        }
+        let key = memo_key(payload, input);
+        let now = unix_now();
+        match self.result_cache.get(&key, &task.required_capability, now) {
+            Ok(Some(output)) => return Ok(Memoized::Hit(output)),
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!(task_id = %task.id, err = %e, "Ignoring unreadable cached result")
+            }
+        }
+        let execution = runtime
+            .execute(payload, input, self.metabolism.clone(), budget, limits)
+            .await?;
+        self.record_execution(execution.report);
+        if let Err(e) = self.result_cache.insert(
+            &key,
+            &task.required_capability,
+            execution.output.clone(),
+            now,
+        ) {
+            tracing::warn!(task_id = %task.id, err = %e, "Failed to cache result");
+        }
+        Ok(Memoized::Computed(execution))
+    }
+
+    /// Feed an execution's telemetry into this node's device-class
+    /// calibration, so later bids are priced from measured cost.
+    pub fn record_execution(&self, report: ExecutionReport) {
+        self.calibration
+            .lock()
+            .unwrap()
+            .record(&self.device_class, report);
+    }
+
     pub fn set_power_mode(&mut self, mode: PowerMode) {
         self.metabolism.lock().unwrap().set_mode(mode.clone());
         self.power_mode = mode;
@@ -138,15 +1095,18 @@ impl SporeNode {
         self.metabolism.lock().unwrap().energy_score()
     }
 
-    /// Local quorum-count bidding heuristic.
+    /// Local quorum-count bidding heuristic, tuned by `quorum_sensing`.
     ///
     /// The caller supplies only a count of known competing bids. This is an
     /// advisory local silence rule, not a distributed auction protocol.
     pub fn evaluate_task_with_quorum(&self, task: &Task, known_bids: usize) -> Option<Bid> {
         let score = self.energy_score();
 
-        // If 3+ healthy nodes are already bidding, lower-energy nodes stay silent.
-        if known_bids >= 3 && score < 0.8 {
+        // Once enough healthy nodes are bidding, lower-energy nodes stay silent.
+        if self
+            .quorum_sensing
+            .stays_silent(score, known_bids, rand::random())
+        {
             return None;
         }
 
@@ -159,28 +1119,15 @@ impl SporeNode {
     }
 
     pub fn heartbeat_interval(&self) -> Duration {
-        let score = self.energy_score();
-        let pressure = {
-            let mesh = self.mesh.lock().unwrap();
-            mesh.local_pressure
-        };
-
-        let base_ms = if score < 0.2 {
-            60_000 // 1 minute
-        } else if score < 0.5 {
-            10_000 // 10 seconds
-        } else {
-            1_000 // 1 second
-        };
+        let pressure = self.mesh.lock().unwrap().local_pressure;
+        self.heartbeat_interval_at(pressure)
+    }
 
-        // High local pressure accelerates heartbeat up to 4x, provided we have
-        // enough energy.
-        if score > 0.4 && pressure > 5.0 {
-            let factor = (pressure / 5.0).min(4.0);
-            Duration::from_millis((base_ms as f32 / factor) as u64)
-        } else {
-            Duration::from_millis(base_ms)
-        }
+    fn heartbeat_interval_at(&self, pressure: f32) -> Duration {
+        let energy = self.energy_score();
+        let base = self.degradation.rung(energy).heartbeat();
+        let accelerated = self.degradation.heartbeat(energy, pressure);
+        self.damping.period(base, accelerated, rand::random())
     }
 
     /// Consume energy for an operation. Returns false if exhausted.
@@ -218,29 +1165,183 @@ impl SporeNode {
     /// Simulate receiving a message (for evaluation without full network)
     pub fn simulate_receive(&self, msg_id: &str, payload: &[u8]) -> Result<(), Box<dyn Error>> {
         let key = format!("msg_{}", msg_id);
-        self.db.insert(key, payload)?;
+        self.messages.insert(&key, payload)?;
         Ok(())
     }
 
-    /// Prototype UCAN token check for a task.
-    pub fn validate_ucan(&self, token: &str, _required_cap: &Capability) -> bool {
-        // In a real implementation:
-        // 1. Parse token using `ucan` crate APIs
-        // 2. Validate signature against issuer DID
-        // 3. Check capabilities against required_cap
-        //
-        // For prototype: THIS IS NOT SECURITY. It's a placeholder to keep
-        // the call sites honest about where auth checks belong.
-        if token.is_empty() {
-            return false;
+    /// A stored message's payload, from the cache when it is hot.
+    pub fn message(&self, msg_id: &str) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+        Ok(self.messages.get(&format!("msg_{}", msg_id))?)
+    }
+
+    /// Verify the grants and attestation in `p`, an advert its sender
+    /// signed.
+    fn verify_status(&mut self, p: &EnergyStatus) {
+        self.topic_acl.present(
+            &p.source_id,
+            &p.grants,
+            &self.acl_roots(),
+            &self.delegation_limits,
+            unix_now(),
+        );
+        if let Err(e) = self
+            .attestations
+            .lock()
+            .unwrap()
+            .present(&p.source_id, p.attestation.as_ref())
+        {
+            tracing::debug!(peer_id = %p.source_id, err = %e, "Peer is not attested");
+        }
+    }
+
+    /// Issuers that may root a grant presented against `topic_acl`: the
+    /// trusted issuers and the node itself.
+    fn acl_roots(&self) -> Vec<String> {
+        let mut roots = self.trusted_issuers.clone();
+        roots.push(self.peer_id.to_string());
+        roots
+    }
+
+    /// Reserved topics none of this node's own grants lets it publish on.
+    fn forbidden_topics(&self) -> HashSet<TopicKind> {
+        let me = self.peer_id.to_string();
+        let roots = self.acl_roots();
+        let now = unix_now();
+        self.topic_acl.forbidden(|cap| {
+            self.grants.iter().any(|grant| {
+                grant
+                    .verify(&me, cap, &roots, &self.delegation_limits, now)
+                    .is_ok()
+            })
+        })
+    }
+
+    /// Check that a task's token delegates `required_cap` to this node.
+    ///
+    /// The token must be a [`Delegation`] chain addressed to this node and
+    /// rooted at the node itself or one of `trusted_issuers`. The outcome is
+    /// appended to the audit log.
+    pub fn validate_ucan(&self, token: &str, required_cap: &Capability) -> bool {
+        self.authorize(token, required_cap, None, None)
+    }
+
+    /// Like `validate_ucan` for a hosted tenant: the token must be scoped to
+    /// `tenant` and rooted at the node or one of the tenant's issuers.
+    pub fn validate_tenant_ucan(
+        &self,
+        token: &str,
+        required_cap: &Capability,
+        tenant: &str,
+    ) -> bool {
+        self.authorize(token, required_cap, Some(tenant), None)
+    }
+
+    fn authorize(
+        &self,
+        token: &str,
+        required_cap: &Capability,
+        tenant: Option<&str>,
+        task: Option<&Task>,
+    ) -> bool {
+        let roots = match tenant {
+            None => Ok(&self.trusted_issuers),
+            Some(id) => self
+                .tenants
+                .get(id)
+                .map(|t| &t.trusted_issuers)
+                .ok_or_else(|| AuthError::UnknownTenant(id.to_string())),
+        };
+        let verified = if token.is_empty() {
+            Err(AuthError::Malformed("empty token".to_string()))
+        } else {
+            roots.and_then(|roots| {
+                let delegation = Delegation::decode(token)?;
+                delegation.verify_tenant(tenant)?;
+                let mut roots = roots.clone();
+                roots.push(self.peer_id.to_string());
+                delegation.verify(
+                    &self.peer_id.to_string(),
+                    required_cap,
+                    &roots,
+                    &self.delegation_limits,
+                    unix_now(),
+                )
+            })
+        };
+
+        if let Some(id) = tenant.filter(|id| self.tenants.contains_key(*id)) {
+            let mut metrics = self.metrics.lock().unwrap();
+            let counters = metrics.tenant_mut(id);
+            if verified.is_ok() {
+                counters.tasks_authorized += 1;
+            } else {
+                counters.tasks_rejected += 1;
+            }
         }
 
-        // Mock validation: "auth-valid" token is always valid.
-        if token.contains("auth-valid") {
-            return true;
+        let record = AuditRecord {
+            timestamp: unix_now(),
+            requester: task.map(|t| t.source_id.clone()),
+            task_id: task.map(|t| t.id.clone()),
+            tenant: tenant.map(str::to_string),
+            required: required_cap.clone(),
+            token_sha256: token_digest(token),
+            decision: if verified.is_ok() {
+                Decision::Accepted
+            } else {
+                Decision::Rejected
+            },
+            reason: verified.as_ref().err().map(ToString::to_string),
+        };
+        if self.retired.load(Ordering::Relaxed) {
+            tracing::debug!("Not auditing from an abandoned run loop");
+        } else if let Err(e) = self.audit.record(record) {
+            tracing::warn!(err = %e, "Failed to append audit entry");
+        }
+
+        match verified {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::debug!(err = %e, "Delegation rejected");
+                false
+            }
         }
+    }
+
+    /// This node's identity as a `did:key`, the issuer name on its
+    /// delegations.
+    pub fn did(&self) -> String {
+        did::did_of(&self.signing_key)
+    }
+
+    /// Grant `capability` to `audience` for `ttl`, signed with this node's
+    /// key. Pass the delegation this node holds as `proof` to attenuate it.
+    pub fn delegate(
+        &self,
+        audience: &PeerId,
+        capability: Capability,
+        ttl: Duration,
+        proof: Option<Delegation>,
+    ) -> Result<Delegation, AuthError> {
+        Delegation::mint(
+            &self.signing_key,
+            audience,
+            capability,
+            unix_now(),
+            ttl,
+            proof,
+        )
+    }
+
+    /// Package `output` of `task` for its publisher, sealed unless the task
+    /// asked for a public result.
+    pub fn seal_result(&self, task: &Task, output: Vec<u8>) -> Result<TaskResult, ResultError> {
+        results::seal_result(task, &self.peer_id.to_string(), output)
+    }
 
-        false
+    /// Read a result of a task this node published.
+    pub fn open_result(&self, result: &TaskResult) -> Result<Vec<u8>, ResultError> {
+        results::open_result(&self.signing_key, result)
     }
 
     /// Local best-bid bidding heuristic.
@@ -256,15 +1357,23 @@ impl SporeNode {
         let score = self.energy_score();
         let my_id = self.peer_id.to_string();
 
-        // Prototype UCAN gate. This is not a complete authorization boundary.
-        if let Some(token) = &task.auth_token {
-            if !self.validate_ucan(token, &task.required_capability) {
+        // Tokens are optional unless `require_token` is set, and then a
+        // missing one is checked, and audited, as an empty token. A present
+        // token must verify.
+        let token = task
+            .auth_token
+            .as_deref()
+            .or(self.require_token.then_some(""));
+        if let Some(token) = token {
+            if !self.authorize(
+                token,
+                &task.required_capability,
+                task.tenant.as_deref(),
+                Some(task),
+            ) {
                 tracing::warn!(task_id = %task.id, "Rejected task due to invalid UCAN");
                 return None;
             }
-        } else {
-            // Reject unauthenticated tasks in secure mode
-            // For now, we allow them for backward compatibility/testing
         }
 
         let bid = self.local_bid_for_task(task, score)?;
@@ -274,11 +1383,11 @@ impl SporeNode {
         // not block a local finite bid.
         let best_bid = known_bids
             .iter()
-            .filter(|b| b.task_id == task.id && b.energy_score.is_finite())
-            .max_by(|a, b| a.energy_score.total_cmp(&b.energy_score));
+            .filter(|b| b.task_id == task.id && b.merit().is_finite())
+            .max_by(|a, b| a.merit().total_cmp(&b.merit()));
 
         if let Some(best) = best_bid {
-            if bid.energy_score < best.energy_score {
+            if bid.merit() < best.merit() {
                 return None;
             }
         }
@@ -306,6 +1415,19 @@ impl SporeNode {
     pub fn build_mycelium_with_profile(
         &self,
         profile: NetProfile,
+    ) -> Result<Mycelium, Box<dyn Error>> {
+        let options = NetOptions {
+            profile,
+            ..NetOptions::default()
+        };
+        self.build_mycelium_with_options(options)
+    }
+
+    /// Like `build_mycelium_with_profile`, with control over optional
+    /// services such as rendezvous server mode.
+    pub fn build_mycelium_with_options(
+        &self,
+        options: NetOptions,
     ) -> Result<Mycelium, Box<dyn Error>> {
         let keypair = libp2p::identity::Keypair::ed25519_from_bytes(self.signing_key.to_bytes())?;
         let expected_peer_id = PeerId::from_public_key(&keypair.public());
@@ -313,7 +1435,340 @@ impl SporeNode {
             expected_peer_id, self.peer_id,
             "persisted peer_id must match swarm identity"
         );
-        Mycelium::new_with_profile(keypair, self.mesh.clone(), self.metrics.clone(), profile)
+        Mycelium::new_with_options(keypair, self.mesh.clone(), self.metrics.clone(), options)
+    }
+
+    /// Queue a task for publication on the next heartbeat of `run_for`. A
+    /// task with a `target` is delivered to that peer instead, through the
+    /// mailbox protocol, once it is connected.
+    pub fn publish_task(&self, task: Task) {
+        self.outgoing_tasks.lock().unwrap().push(task);
+    }
+
+    /// Queue a sensor reading. It is summarized with other samples of the
+    /// same series and published at the power mode's reporting cadence.
+    pub fn publish_reading(&self, reading: SensorReading) {
+        self.outgoing_readings.lock().unwrap().push(reading);
+    }
+
+    /// Record that this node is working on `task` until `finish_task`.
+    pub fn accept_task(&self, task: Task) {
+        self.in_flight.lock().unwrap().insert(task.id.clone(), task);
+    }
+
+    pub fn finish_task(&self, task_id: &str) -> Option<Task> {
+        self.in_flight.lock().unwrap().remove(task_id)
+    }
+
+    /// Award `task`, which this node published, to `winner` under a lease.
+    /// The award is gossiped on the next heartbeat, and the task is published
+    /// again if the winner stops renewing. Returns false, awarding nothing,
+    /// when `attestations` requires an attested winner and `winner` is not.
+    pub fn award_task(&self, task: Task, winner: &PeerId) -> bool {
+        if !self
+            .attestations
+            .lock()
+            .unwrap()
+            .may_award(&winner.to_string())
+        {
+            return false;
+        }
+        self.leases
+            .lock()
+            .unwrap()
+            .award(task, winner.to_string(), std::time::Instant::now());
+        true
+    }
+
+    /// Record a result for a task this node awarded. Only the first result
+    /// per task is `Settlement::First`, whichever winner sent it.
+    pub fn settle_result(&self, result: &TaskResult) -> Settlement {
+        self.leases.lock().unwrap().settle(&result.task_id)
+    }
+
+    /// Super-peers whose approvals certify critical actions. Votes collected
+    /// under a previous set are discarded.
+    pub fn set_quorum_signers(&self, set: SignerSet) {
+        *self.quorum.lock().unwrap() = QuorumCollector::new(set);
+    }
+
+    /// Sign `action` as one of the super-peers and queue the vote. If it
+    /// completes a quorum locally, the certificate is gossiped as well.
+    pub fn approve_action(&self, action: QuorumAction) -> Approval {
+        let approval = Approval::sign(&self.signing_key, action);
+        let mut outgoing = self.outgoing_quorum.lock().unwrap();
+        outgoing.push(QuorumMessage::Approval(approval.clone()));
+        match self
+            .quorum
+            .lock()
+            .unwrap()
+            .add_approval(approval.clone(), unix_now())
+        {
+            Ok(Some(cert)) => {
+                outgoing.push(QuorumMessage::Cert(cert.clone()));
+                let _ = self.events.send(NodeEvent::Certified(cert));
+            }
+            Ok(None) => {}
+            Err(e) => tracing::debug!(err = %e, "Own approval not counted locally"),
+        }
+        approval
+    }
+
+    /// The certificate that authorizes `action` on `scope` right now, if any.
+    /// Actuators should check this rather than react to spikes.
+    pub fn certified_action(&self, action: &str, scope: &str) -> Option<QuorumCert> {
+        self.quorum
+            .lock()
+            .unwrap()
+            .certificate(action, scope, unix_now())
+            .cloned()
+    }
+
+    /// How to advertise this node's HTTP service on `port` to the LAN over
+    /// DNS-SD; see `crate::dnssd`. `None` when `dns_sd` is off.
+    pub fn advertisement(&self, port: u16) -> Option<dnssd::Advertisement> {
+        if !self.dns_sd {
+            return None;
+        }
+        let mut advertisement = dnssd::Advertisement::new(&self.peer_id.to_string(), port);
+        if let Some(role) = self.role.and_then(|role| serde_json::to_value(role).ok()) {
+            advertisement = advertisement.with_txt("role", role.as_str().unwrap_or_default());
+        }
+        Some(advertisement)
+    }
+
+    /// Handles for feeding this node while `run_for` is borrowed elsewhere.
+    pub fn link(&self) -> NodeLink {
+        NodeLink {
+            outgoing_tasks: self.outgoing_tasks.clone(),
+            outgoing_readings: self.outgoing_readings.clone(),
+            in_flight: self.in_flight.clone(),
+            leases: self.leases.clone(),
+            events: self.events.clone(),
+            metabolism: self.metabolism.clone(),
+            mesh: self.mesh.clone(),
+            mesh_snapshot: self.mesh_snapshot.subscribe(),
+            pending_config: self.pending_config.clone(),
+            attestations: self.attestations.clone(),
+            lifecycle: self.lifecycle.subscribe(),
+            fleet_health: self.fleet_health.clone(),
+        }
+    }
+
+    /// The live configuration of this node and `mycelium`.
+    pub fn config(&self, mycelium: &Mycelium) -> HyphaConfig {
+        HyphaConfig {
+            degradation: self.degradation.clone(),
+            subscriptions: self.subscription_policy.clone(),
+            send_policy: mycelium.send_policy.clone(),
+            bootstrap: mycelium.bootstrap.clone(),
+            rendezvous_points: mycelium.rendezvous_points.clone(),
+            namespace: mycelium.namespace.clone(),
+            trusted_issuers: self.trusted_issuers.clone(),
+            delegation_limits: self.delegation_limits,
+            quorum: self.quorum_sensing.clone(),
+            shards: self.task_shards.plan,
+        }
+    }
+
+    /// Collect a [`SupportBundle`] describing this node and `mycelium` for a
+    /// bug report.
+    pub fn support_bundle(&self, mycelium: &Mycelium) -> SupportBundle {
+        SupportBundle {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            envelope_version: wire::ENVELOPE_VERSION,
+            generated_at: unix_now(),
+            peer_id: self.peer_id.to_string(),
+            state: self.lifecycle.state(),
+            config: format!("{:#?}", self.config(mycelium)),
+            listen_addrs: mycelium
+                .listen_addrs
+                .iter()
+                .map(Multiaddr::to_string)
+                .collect(),
+            topics: mycelium.subscribed_topic_names(),
+            mesh: self.mesh.lock().unwrap().diagnostics(),
+            recent_events: self
+                .watermark
+                .recent()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            message_cache: self.messages.stats(),
+        }
+    }
+
+    /// Apply `new` in place, touching only the sections that differ from the
+    /// live config, and emit `NodeEvent::ConfigApplied` listing them.
+    ///
+    /// Mesh, energy-band and relay changes take effect on the next heartbeat;
+    /// new bootstrap and rendezvous entries are dialed at once. A namespace
+    /// change leaves every topic and rejoins under the new names.
+    pub fn apply_config(
+        &mut self,
+        mycelium: &mut Mycelium,
+        new: HyphaConfig,
+    ) -> Result<Vec<ConfigSection>, Box<dyn Error>> {
+        let changed = self.config(mycelium).diff(&new);
+        if changed.is_empty() {
+            return Ok(changed);
+        }
+        let HyphaConfig {
+            degradation,
+            subscriptions,
+            send_policy,
+            bootstrap,
+            rendezvous_points,
+            namespace,
+            trusted_issuers,
+            delegation_limits,
+            quorum,
+            shards,
+        } = new;
+        self.degradation = degradation;
+        self.subscription_policy = subscriptions;
+        self.trusted_issuers = trusted_issuers;
+        self.delegation_limits = delegation_limits;
+        self.quorum_sensing = quorum;
+        mycelium.send_policy = send_policy;
+
+        if changed.contains(&ConfigSection::Bootstrap) {
+            mycelium.bootstrap.clear();
+            for addr in bootstrap {
+                mycelium.add_bootstrap(addr);
+            }
+            mycelium.redial_bootstrap();
+        }
+        if changed.contains(&ConfigSection::Rendezvous) {
+            mycelium.rendezvous_points.clear();
+            for addr in rendezvous_points {
+                if !mycelium.add_rendezvous_point(addr.clone()) {
+                    tracing::warn!(%addr, "Rendezvous point without a peer id ignored");
+                }
+            }
+            mycelium.refresh_rendezvous();
+        }
+        if changed.contains(&ConfigSection::Namespace) {
+            mycelium.move_to_namespace(namespace.as_deref());
+        }
+        if changed.contains(&ConfigSection::Shards) {
+            // The heartbeat joins the new shards.
+            self.task_shards.reshard(shards, std::time::Instant::now());
+        }
+        let resubscribe = [
+            ConfigSection::Namespace,
+            ConfigSection::Subscriptions,
+            ConfigSection::EnergyBands,
+        ];
+        if changed.iter().any(|section| resubscribe.contains(section)) {
+            let topics = self
+                .degradation
+                .topics(self.energy_score(), &self.subscription_policy);
+            mycelium.apply_topics(&topics)?;
+        }
+
+        info!(peer_id = %self.peer_id, ?changed, "Configuration applied");
+        let _ = self.events.send(NodeEvent::ConfigApplied {
+            changed: changed.clone(),
+        });
+        Ok(changed)
+    }
+
+    /// Write `epoch` to the shared-state document for every node to apply.
+    /// Only nodes that list its operator in `epochs.config.operators` take it.
+    pub fn roll_out_epoch(&self, epoch: &ConfigEpoch) {
+        self.shared_state
+            .lock()
+            .unwrap()
+            .set(EPOCH_KEY, &epoch.encode());
+    }
+
+    /// Write `approval` to the artifact registry in the shared-state
+    /// document. Nodes enforcing the registry take it if they list its
+    /// operator in `artifacts.operators`.
+    pub fn publish_artifact_approval(&self, approval: &ArtifactApproval) {
+        self.shared_state
+            .lock()
+            .unwrap()
+            .set(&artifact_key(&approval.hash), &approval.encode());
+    }
+
+    /// Release `image`, signed by the `hypha_ota` `manifest`, to every node
+    /// of `device_class`: its chunks go into this node's chunk store and the
+    /// release into the shared-state document. Nodes install it once an
+    /// operator's approval of its hash is in the artifact registry too.
+    pub fn release_firmware(
+        &self,
+        device_class: &str,
+        manifest: &str,
+        image: &[u8],
+    ) -> Result<FirmwareRelease, OtaError> {
+        let release = FirmwareRelease::new(device_class, manifest, image, DEFAULT_CHUNK_SIZE)?;
+        for chunk in image.chunks(DEFAULT_CHUNK_SIZE) {
+            self.chunks
+                .put(chunk)
+                .map_err(|e| OtaError::Storage(e.to_string()))?;
+        }
+        self.shared_state
+            .lock()
+            .unwrap()
+            .set(&release_key(device_class), &release.encode());
+        Ok(release)
+    }
+
+    /// Hand verified firmware images for this node's device class to
+    /// `installer`.
+    pub fn set_installer(&mut self, installer: Box<dyn Installer>) {
+        self.installer = Some(installer);
+    }
+
+    /// Apply a config epoch the watcher released. If it does not validate
+    /// or cannot be applied, the config in force before it is restored.
+    fn apply_epoch(
+        &mut self,
+        mycelium: &mut Mycelium,
+        epoch: ConfigEpoch,
+    ) -> Result<(), Box<dyn Error>> {
+        let previous = self.config(mycelium);
+        let mut next = previous.clone();
+        epoch.params.apply_to(&mut next.degradation);
+        if let Some(plan) = epoch.params.shards {
+            next.shards = plan;
+        }
+        let outcome = epoch.params.validate().and_then(|()| {
+            self.apply_config(mycelium, next)
+                .map_err(|e| EpochError::Apply(e.to_string()))
+        });
+        match outcome {
+            Ok(_) => {
+                info!(peer_id = %self.peer_id, epoch = epoch.epoch, "Config epoch applied");
+                self.epochs.applied(epoch.epoch);
+                let _ = self
+                    .events
+                    .send(NodeEvent::EpochApplied { epoch: epoch.epoch });
+            }
+            Err(e) => {
+                tracing::warn!(
+                    peer_id = %self.peer_id,
+                    epoch = epoch.epoch,
+                    err = %e,
+                    "Config epoch rejected; rolling back"
+                );
+                self.epochs.reject(epoch.epoch);
+                self.apply_config(mycelium, previous)?;
+                let _ = self.events.send(NodeEvent::EpochRejected {
+                    epoch: epoch.epoch,
+                    reason: e.to_string(),
+                    current: self.epochs.current(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Subscribe to events observed by the networking loop.
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<NodeEvent> {
+        self.events.subscribe()
     }
 
     /// Trigger a local prototype mesh pressure spike.
@@ -322,11 +1777,7 @@ impl SporeNode {
     /// wake protocol.
     pub fn trigger_sync_spike(&self, intensity: u8) -> Result<(), Box<dyn Error>> {
         info!(peer_id = %self.peer_id, %intensity, "Triggering mesh pressure spike");
-        let spike = Spike {
-            source: self.peer_id.to_string(),
-            intensity,
-            pattern_id: 0,
-        };
+        let spike = self.spikes.sign(&self.signing_key, intensity, 0);
         let mut mesh = self.mesh.lock().unwrap();
         mesh.handle_spike(&spike.source, spike.intensity);
         Ok(())
@@ -338,27 +1789,388 @@ impl SporeNode {
     /// Callers can optionally provide a one-shot to learn the first listen address.
     pub async fn run_for(
         &mut self,
-        mut mycelium: Mycelium,
+        mycelium: Mycelium,
         run_for: Duration,
         heartbeat_every: Duration,
         pulse_delta: f32,
         dynamic_heartbeat: bool,
-        mut on_listen: Option<tokio::sync::oneshot::Sender<Multiaddr>>,
+        on_listen: Option<tokio::sync::oneshot::Sender<Multiaddr>>,
     ) -> Result<Mycelium, Box<dyn Error>> {
-        mycelium.subscribe_all()?;
-        info!(peer_id = %self.peer_id, "Hypha Spore active");
-
-        let deadline = tokio::time::Instant::now() + run_for;
-        let mut heartbeat = tokio::time::interval(heartbeat_every);
-        let mut listen_sent = false;
-
-        loop {
+        let (mesh, actor) = MeshHandle::spawn(self.mesh.clone(), self.mesh_snapshot.clone());
+        let (offload, mut completions) = Offload::spawn(DEFAULT_QUEUE)?;
+        let mut result = self
+            .run_loop(
+                mycelium,
+                &mesh,
+                &offload,
+                &mut completions,
+                run_for,
+                heartbeat_every,
+                pulse_delta,
+                dynamic_heartbeat,
+                on_listen,
+            )
+            .await;
+        // Let the actor apply what is still queued before callers inspect
+        // `self.mesh`.
+        drop(mesh);
+        let _ = actor.await;
+        // Likewise let queued writes and merges land, and act on what they
+        // report.
+        let _ = tokio::task::spawn_blocking(move || offload.finish()).await;
+        if let Ok(mycelium) = &mut result {
+            let mode = self.degradation.power_mode(self.energy_score());
+            while let Ok(done) = completions.try_recv() {
+                if let Err(e) = self.complete(mycelium, done, &mode) {
+                    tracing::warn!(err = %e, "Failed to act on offloaded work");
+                }
+            }
+        }
+        if let Err(e) = self.save_mesh_state() {
+            tracing::warn!(err = %e, "Failed to save mesh state");
+        }
+        if let Err(e) = self.save_reputation() {
+            tracing::warn!(err = %e, "Failed to save reputation records");
+        }
+        if let Err(e) = self.save_sequence() {
+            tracing::warn!(err = %e, "Failed to save message sequence");
+        }
+        if let Err(e) = self.result_cache.prune(unix_now()) {
+            tracing::warn!(err = %e, "Failed to prune cached results");
+        }
+        result
+    }
+
+    /// Like `run_for`, but restarts the swarm if the run loop stalls.
+    ///
+    /// The loop runs on its own thread while a watchdog task checks its
+    /// watermark. Once it is older than `self.watchdog.stall_after`, the loop
+    /// is cancelled, a fresh swarm is built with the same options, listen
+    /// addresses, bootstrap and rendezvous points, namespace and send policy,
+    /// and the loop resumes for what remains of `run_for`. Node state (mesh,
+    /// shared state, queues, quorum) carries over untouched.
+    ///
+    /// A loop blocked inside a synchronous call cannot be cancelled. If it
+    /// has not handed the node back within another `stall_after`, its thread
+    /// is abandoned with the node retired, and a successor (see `successor`)
+    /// carries on, keeping everything `link` shares but losing what only the
+    /// abandoned loop held. A deadlock on a lock the successor needs too
+    /// stalls it in turn, until `max_restarts` runs out.
+    ///
+    /// Each restart emits `NodeEvent::NodeRestarted`; after `max_restarts`
+    /// the call fails. On success the node is handed back with the swarm.
+    pub async fn run_supervised(
+        self,
+        mut mycelium: Mycelium,
+        run_for: Duration,
+        heartbeat_every: Duration,
+        pulse_delta: f32,
+        dynamic_heartbeat: bool,
+        mut on_listen: Option<tokio::sync::oneshot::Sender<Multiaddr>>,
+    ) -> Result<(Self, Mycelium), Box<dyn Error>> {
+        let deadline = tokio::time::Instant::now() + run_for;
+        let mut restarts = 0;
+        let mut node = self;
+        loop {
+            let config = node.config(&mycelium);
+            let options = mycelium.options.clone();
+            let listen_addrs = mycelium.listen_addrs.clone();
+            let retry_policy = mycelium.retry_policy.clone();
+            let compression = mycelium.compression.clone();
+            let batch_policy = mycelium.batcher.policy.clone();
+
+            let stalled = Arc::new(tokio::sync::Notify::new());
+            node.watermark.beat("start");
+            let monitor = tokio::spawn(watchdog::monitor(
+                node.watermark.clone(),
+                node.watchdog.clone(),
+                stalled.clone(),
+            ));
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            let successor = node.successor();
+            let retired = node.retired.clone();
+            let peer_id = node.peer_id;
+            let watermark = node.watermark.clone();
+            let watchdog = node.watchdog.clone();
+            let on_listen = on_listen.take();
+            let (cancel_tx, cancel_rx) = tokio::sync::oneshot::channel::<()>();
+            let (done_tx, mut done_rx) = tokio::sync::oneshot::channel();
+            let (exited_tx, exited_rx) = std::sync::mpsc::channel();
+            let mut stop = LoopStop {
+                cancel: Some(cancel_tx),
+                exited: Some(exited_rx),
+                grace: watchdog.stall_after,
+            };
+            let runtime = tokio::runtime::Handle::current();
+            std::thread::Builder::new()
+                .name("hypha-run-loop".to_string())
+                .spawn(move || {
+                    let outcome = runtime.block_on(async {
+                        tokio::select! {
+                            // A cancelled loop must not be polled again: its
+                            // runtime may be shutting down.
+                            biased;
+                            _ = cancel_rx => None,
+                            result = node.run_for(
+                                mycelium,
+                                remaining,
+                                heartbeat_every,
+                                pulse_delta,
+                                dynamic_heartbeat,
+                                on_listen,
+                            ) => Some(result.map_err(|e| e.to_string())),
+                        }
+                    });
+                    let _ = done_tx.send((node, outcome));
+                    let _ = exited_tx.send(());
+                })?;
+
+            let finished = tokio::select! {
+                done = &mut done_rx => Some(done),
+                _ = stalled.notified() => None,
+            };
+            monitor.abort();
+            let done = match finished {
+                Some(done) => done.ok(),
+                None => {
+                    stop.cancel();
+                    tokio::time::timeout(watchdog.stall_after, done_rx)
+                        .await
+                        .ok()
+                        .and_then(Result::ok)
+                }
+            };
+            // The loop has either handed the node back or is left behind.
+            stop.exited = None;
+            let handed_back = match done {
+                Some((node, Some(result))) => {
+                    return result.map(|mycelium| (node, mycelium)).map_err(Into::into);
+                }
+                Some((node, None)) => Some(node),
+                None => {
+                    retired.store(true, Ordering::Relaxed);
+                    tracing::error!(%peer_id, "Abandoning a run loop that would not stop");
+                    None
+                }
+            };
+            let abandoned = handed_back.is_none();
+
+            restarts += 1;
+            let stalled_ms = watermark.since_last().as_millis() as u64;
+            let recent: Vec<String> = watermark.recent().into_iter().map(str::to_string).collect();
+            if restarts > watchdog.max_restarts {
+                return Err(format!("run loop stalled {restarts} times; giving up").into());
+            }
+            tracing::warn!(%peer_id, restarts, stalled_ms, "Restarting stalled swarm");
+            node = handed_back.unwrap_or_else(successor);
+
+            mycelium = node.build_mycelium_with_options(options)?;
+            mycelium.send_policy = config.send_policy;
+            mycelium.retry_policy = retry_policy;
+            mycelium.compression = compression;
+            mycelium.batcher.policy = batch_policy;
+            if let Some(namespace) = &config.namespace {
+                mycelium.set_namespace(namespace);
+            }
+            // Dialed by the first bootstrap tick of the new loop.
+            for addr in config.bootstrap {
+                mycelium.add_bootstrap(addr);
+            }
+            for addr in config.rendezvous_points {
+                mycelium.add_rendezvous_point(addr);
+            }
+            for addr in listen_addrs {
+                match mycelium.listen_on(addr.clone()) {
+                    Ok(()) => {}
+                    // The abandoned swarm may still hold a fixed port.
+                    Err(e) if abandoned => {
+                        tracing::warn!(%addr, err = %e, "Could not listen again after abandoning the run loop");
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            let _ = node.events.send(NodeEvent::NodeRestarted {
+                restarts,
+                stalled_ms,
+                recent,
+            });
+        }
+    }
+
+    /// Save mesh membership, backoffs and peer scores so that the next
+    /// `SporeNode::new` on this storage can warm-start. `run_for` calls this
+    /// on return.
+    pub fn save_mesh_state(&self) -> Result<(), Box<dyn Error>> {
+        let state = self.mesh.lock().unwrap().persist();
+        let bytes = serde_json::to_vec(&(trace::now_ms(), state))?;
+        self.db.insert(MESH_STATE_KEY, bytes)?;
+        Ok(())
+    }
+
+    /// Save peers' reputation records, dropping those that have decayed
+    /// away. `run_for` calls this on return and whenever a peer offends.
+    /// A retired node leaves them to its successor.
+    pub fn save_reputation(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.retired.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        self.reputation.prune(unix_now());
+        let bytes = serde_json::to_vec(self.reputation.records())?;
+        self.db.insert(REPUTATION_KEY, bytes)?;
+        Ok(())
+    }
+
+    /// `save_reputation`, with the write left to `offload`.
+    fn save_reputation_on(
+        &mut self,
+        offload: &Offload<Offloaded>,
+    ) -> Result<(), serde_json::Error> {
+        self.reputation.prune(unix_now());
+        let bytes = serde_json::to_vec(self.reputation.records())?;
+        let db = self.db.clone();
+        offload.write(move || {
+            if let Err(e) = db.insert(REPUTATION_KEY, bytes) {
+                tracing::warn!(err = %e, "Failed to save reputation records");
+            }
+        });
+        Ok(())
+    }
+
+    /// Save where this node's message sequence continues, so that a clean
+    /// restart leaves no gap. `run_for` calls this on return.
+    pub fn save_sequence(&self) -> Result<(), Box<dyn Error>> {
+        let next = self.sequencer.lock().unwrap().next();
+        self.db.insert(SEQUENCE_KEY, serde_json::to_vec(&next)?)?;
+        Ok(())
+    }
+
+    /// Write a hibernation snapshot ahead of a deep sleep or planned
+    /// shutdown; see `crate::hibernate`. Publishes held back in `mycelium`'s
+    /// outbox move into the snapshot. Returns the snapshot's size in bytes.
+    pub fn hibernate(&mut self, mycelium: &mut Mycelium) -> Result<usize, Box<dyn Error>> {
+        let snapshot = Snapshot::new(
+            trace::now_ms(),
+            self.mesh.lock().unwrap().persist(),
+            &self.peer_store,
+            self.shared_state.lock().unwrap().encode_state(),
+            mycelium.outbox.take_all(),
+        );
+        let bytes = snapshot.encode();
+        let size = bytes.len();
+        self.db.insert(HIBERNATION_KEY, bytes)?;
+        self.save_reputation()?;
+        self.save_sequence()?;
+        info!(
+            peer_id = %self.peer_id,
+            bytes = size,
+            mesh_peers = snapshot.mesh.mesh_peers.len(),
+            held = snapshot.outbox.len(),
+            "Hibernating"
+        );
+        Ok(size)
+    }
+
+    /// Start recording inbound gossip to `path`, appending to any capture
+    /// already there.
+    pub fn capture_to(&mut self, path: &std::path::Path) -> Result<(), CaptureError> {
+        self.capture = Some(CaptureWriter::open(path)?);
+        Ok(())
+    }
+
+    /// Queue captured messages for the next run loop, which handles them in
+    /// order before any live traffic. Nothing is queued if one is malformed.
+    pub fn queue_replay(&mut self, messages: &[CapturedMessage]) -> Result<usize, CaptureError> {
+        let events = messages
+            .iter()
+            .map(CapturedMessage::to_event)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.replaying.extend(events);
+        Ok(messages.len())
+    }
+
+    /// Replay the capture at `path` into this node, then keep running for
+    /// `settle` so that work the messages started (bids, syncs, relays)
+    /// plays out. Meant for a fresh node on a swarm with no peers, which
+    /// then sees exactly the captured traffic.
+    pub async fn replay(
+        &mut self,
+        mycelium: Mycelium,
+        path: &std::path::Path,
+        settle: Duration,
+    ) -> Result<Mycelium, Box<dyn Error>> {
+        let queued = self.queue_replay(&capture::read(path)?)?;
+        info!(peer_id = %self.peer_id, messages = queued, "Replaying captured gossip");
+        let heartbeat = self.heartbeat_interval();
+        self.run_for(mycelium, settle, heartbeat, 0.05, false, None)
+            .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn run_loop(
+        &mut self,
+        mut mycelium: Mycelium,
+        mesh: &MeshHandle,
+        offload: &Offload<Offloaded>,
+        completions: &mut tokio::sync::mpsc::UnboundedReceiver<Offloaded>,
+        run_for: Duration,
+        heartbeat_every: Duration,
+        pulse_delta: f32,
+        dynamic_heartbeat: bool,
+        mut on_listen: Option<tokio::sync::oneshot::Sender<Multiaddr>>,
+    ) -> Result<Mycelium, Box<dyn Error>> {
+        let energy = self.energy_score();
+        mycelium.apply_topics(&self.degradation.topics(energy, &self.subscription_policy))?;
+        mycelium.traced.extend(self.slo.topics());
+        self.sequencer.lock().unwrap().config = self.sequencing.clone();
+        if self.sequencing.enabled {
+            mycelium.sequencer = Some(self.sequencer.clone());
+        }
+        // Just woken: put held publishes back and redial the previous mesh
+        // rather than waiting for discovery.
+        if let Some(wake) = &mut self.waking {
+            for entry in std::mem::take(&mut wake.outbox) {
+                mycelium.outbox.push(entry);
+            }
+            for (peer, addr) in std::mem::take(&mut wake.dial) {
+                if let Err(e) = mycelium.dial_peer(peer, addr) {
+                    tracing::debug!(%peer, err = %e, "Could not redial previous mesh peer");
+                }
+            }
+            if wake.peers.is_empty() {
+                self.waking = None;
+            }
+        }
+        info!(peer_id = %self.peer_id, "Hypha Spore active");
+
+        let deadline = tokio::time::Instant::now() + run_for;
+        let mut heartbeat = tokio::time::interval(heartbeat_every);
+        let mut bootstrap_redial = tokio::time::interval(BOOTSTRAP_REDIAL_INTERVAL);
+        let mut liveness = tokio::time::interval(LIVENESS_TICK);
+        let mut listen_sent = false;
+        let mut last_anomaly_tick = tokio::time::Instant::now();
+        let mut replaying = std::mem::take(&mut self.replaying);
+
+        loop {
             if tokio::time::Instant::now() >= deadline {
                 return Ok(mycelium);
             }
 
             tokio::select! {
+                _ = liveness.tick() => {
+                    self.watermark.beat("tick");
+                }
+                Some(done) = completions.recv() => {
+                    self.watermark.beat("offload");
+                    let mode = self.degradation.power_mode(self.energy_score());
+                    self.complete(&mut mycelium, done, &mode)?;
+                }
+                _ = bootstrap_redial.tick() => {
+                    self.watermark.beat("bootstrap_redial");
+                    mycelium.redial_bootstrap();
+                    mycelium.refresh_rendezvous();
+                }
                 _ = heartbeat.tick() => {
+                    self.watermark.beat("heartbeat");
                     // 1. Energy Status Advertisement
                     let (energy, is_mains, mah_remaining) = {
                         let metabolism = self.metabolism.lock().unwrap();
@@ -368,73 +2180,278 @@ impl SporeNode {
                             metabolism.remaining(),
                         )
                     };
-                    let p = EnergyStatus::new(self.peer_id.to_string(), energy).with_facts(
-                        EnergyFacts {
+                    if let Some(change) = self.role_inference.observe(self.role, energy, is_mains) {
+                        info!(peer_id = %self.peer_id, from = ?change.from, to = ?change.to, "Role changed");
+                        self.take_role(change.to);
+                        let _ = self.events.send(NodeEvent::RoleChanged { from: change.from, to: change.to });
+                    }
+                    // Follow the degradation ladder as the energy band moves in
+                    // either direction.
+                    let rung = self.degradation.rung(energy);
+                    let mode = rung.power_mode();
+                    let topics = self.degradation.topics(energy, &self.subscription_policy);
+                    if mycelium.apply_topics(&topics)? {
+                        info!(peer_id = %self.peer_id, ?rung, "Subscriptions updated for energy band");
+                    }
+                    mycelium.forbidden = self.forbidden_topics();
+                    let pending_config = self.pending_config.lock().unwrap().take();
+                    if let Some(config) = pending_config {
+                        self.apply_config(&mut mycelium, config)?;
+                    }
+                    let document = self.shared_state.lock().unwrap().get(EPOCH_KEY);
+                    if let Some(epoch) = self.epochs.poll(document.as_deref(), std::time::Instant::now()) {
+                        self.apply_epoch(&mut mycelium, epoch)?;
+                    }
+                    let shards = self.wanted_shards(std::time::Instant::now());
+                    if mycelium.join_task_shards(&shards)? {
+                        info!(peer_id = %self.peer_id, ?shards, "Task shards updated");
+                    }
+                    let timeout = self.admission.config.handshake_timeout;
+                    for peer in self.admission.expired(std::time::Instant::now()) {
+                        self.refuse_peer(&mut mycelium, peer, &AdmissionError::Timeout(timeout));
+                        let _ = mycelium.swarm.disconnect_peer_id(peer);
+                    }
+                    mycelium.flush_outbox(&mode);
+                    mycelium.retry_publishes(energy);
+
+                    // Until the node is in a mesh, tasks wait in the queue.
+                    let tasks = if self.lifecycle.publishes_work() {
+                        std::mem::take(&mut *self.outgoing_tasks.lock().unwrap())
+                    } else {
+                        Vec::new()
+                    };
+                    for task in tasks {
+                        if let Some(target) = task.target.clone() {
+                            self.mailbox.address(&target, task, std::time::Instant::now());
+                            continue;
+                        }
+                        mycelium.publish_to_shard(topic::TASKS, self.task_shard(&task), Priority::High, &task, &mode)?;
+                        self.hold_for_absent(&task, energy);
+                    }
+                    let connected: Vec<PeerId> = mycelium.swarm.connected_peers().copied().collect();
+                    for peer in connected {
+                        let mail = self.mailbox.due(&peer.to_string(), std::time::Instant::now());
+                        if !mail.is_empty() {
+                            mycelium.deliver_mail(&peer, mail);
+                        }
+                    }
+                    self.publish_leases(&mut mycelium, &mode)?;
+                    self.poll_firmware(&mut mycelium, energy, is_mains);
+                    let votes = std::mem::take(&mut *self.outgoing_quorum.lock().unwrap());
+                    for vote in votes {
+                        mycelium.publish(topic::QUORUM, Priority::High, &vote, &mode)?;
+                    }
+                    self.quorum.lock().unwrap().prune(unix_now());
+
+                    if let Some(eta) = self.departure.observe(energy, is_mains, std::time::Instant::now()) {
+                        let departing = Departing {
+                            peer: self.peer_id.to_string(),
+                            eta_secs: eta.as_secs(),
+                            in_flight: self.in_flight.lock().unwrap().values().cloned().collect(),
+                        };
+                        tracing::warn!(
+                            peer_id = %self.peer_id,
+                            eta_secs = departing.eta_secs,
+                            "Energy nearly exhausted; announcing departure"
+                        );
+                        mycelium.publish(topic::DEPARTURES, Priority::Critical, &departing, &mode)?;
+                    }
+                    let signals = LifecycleSignals {
+                        connected_peers: mycelium.swarm.connected_peers().count(),
+                        mesh_peers: mesh.snapshot().stats.mesh_size,
+                        rung,
+                        departing: self.departure.is_departing(),
+                    };
+                    if let Some((from, to)) = self.lifecycle.update(&signals, std::time::Instant::now()) {
+                        info!(peer_id = %self.peer_id, ?from, ?to, "Lifecycle state changed");
+                        let _ = self.events.send(NodeEvent::StateChanged { from, to });
+                    }
+
+                    for violation in self.slo.evaluate(std::time::Instant::now()) {
+                        tracing::warn!(
+                            slo = %violation.name,
+                            compliance = violation.compliance,
+                            target = violation.target,
+                            "Delivery SLO violated"
+                        );
+                        // Diagnostic only: at the threshold, not above it, so
+                        // receivers do not count it as mesh pressure.
+                        let spike = self.spikes.sign(
+                            &self.signing_key,
+                            PRESSURE_SPIKE_THRESHOLD,
+                            SLO_ALERT_PATTERN,
+                        );
+                        mycelium.publish(topic::SPIKES, Priority::High, &spike, &mode)?;
+                        let _ = self.events.send(NodeEvent::SloViolated(violation));
+                    }
+
+                    // The interval's offenses go on each peer's lasting record first.
+                    let offenders = self.anomaly.offenses();
+                    let now = unix_now();
+                    for (peer, offenses) in &offenders {
+                        self.reputation.record(peer, *offenses, now);
+                        mesh.set_peer_reputation(peer, self.reputation.penalty(peer, now));
+                    }
+                    if !offenders.is_empty() {
+                        if let Err(e) = self.save_reputation_on(offload) {
+                            tracing::warn!(err = %e, "Failed to save reputation records");
+                        }
+                    }
+
+                    let anomalies = self.anomaly.tick(last_anomaly_tick.elapsed());
+                    last_anomaly_tick = tokio::time::Instant::now();
+                    for anomaly in anomalies {
+                        tracing::warn!(
+                            peer_id = %anomaly.peer,
+                            metric = ?anomaly.metric,
+                            z = anomaly.z_score,
+                            "Anomalous peer behavior"
+                        );
+                        mesh.penalize_peer(
+                            &anomaly.peer,
+                            self.anomaly.config.penalty,
+                            self.anomaly.config.penalty_for,
+                        );
+                        let _ = self.events.send(NodeEvent::AnomalyDetected(anomaly));
+                    }
+
+                    let sampled_at = std::time::Instant::now();
+                    let mut firings = Vec::new();
+                    let mut disk_free = None;
+                    for sensor in &self.sensors {
+                        let value = sensor.read();
+                        if sensor.name() == host::HostMetric::DiskFree.name() {
+                            disk_free = Some(value);
+                        }
+                        firings.extend(self.rules.observe(sensor.name(), value, sampled_at));
+                        self.aggregator.record(
+                            SensorReading::new(self.peer_id.to_string(), sensor.name().to_string(), value),
+                            sampled_at,
+                        );
+                    }
+                    let readings = std::mem::take(&mut *self.outgoing_readings.lock().unwrap());
+                    for reading in readings {
+                        self.aggregator.record(reading, sampled_at);
+                    }
+                    for reading in self.aggregator.due(&mode, sampled_at) {
+                        mycelium.publish(topic::SENSOR, Priority::Normal, &reading, &mode)?;
+                    }
+                    for firing in firings {
+                        info!(peer_id = %self.peer_id, rule = %firing.rule, value = firing.value, "Rule fired");
+                        match firing.action {
+                            RuleAction::Task { id, capability, priority } => {
+                                let task = Task::new(
+                                    format!("{id}-{}", unix_now()),
+                                    capability,
+                                    priority,
+                                    self.peer_id.to_string(),
+                                );
+                                mycelium.publish_to_shard(topic::TASKS, self.task_shard(&task), Priority::High, &task, &mode)?;
+                            }
+                            RuleAction::Spike { intensity, pattern_id } => {
+                                let spike = self.spikes.sign(&self.signing_key, intensity, pattern_id);
+                                mesh.handle_spike(&spike.source, intensity);
+                                mycelium.publish(topic::SPIKES, Priority::High, &spike, &mode)?;
+                            }
+                        }
+                        let _ = self.events.send(NodeEvent::RuleFired {
+                            rule: firing.rule,
+                            value: firing.value,
+                        });
+                    }
+
+                    let mut p = EnergyStatus::new(self.peer_id.to_string(), energy)
+                        .with_facts(EnergyFacts {
                             state_of_charge: Some(energy.clamp(0.0, 1.0)),
                             is_mains: Some(is_mains),
                             mah_remaining: Some(mah_remaining),
                             projected_drain_mah_per_hour: None,
-                        },
-                    );
+                        })
+                        .with_topics(mycelium.subscribed_topic_names())
+                        .with_grants(self.grants.iter().map(Delegation::encode).collect());
+                    p.role = self.role;
+                    p.attestation = self.attestation.clone();
+                    p.zone = self.zones.zone.clone();
+                    p.state = Some(self.lifecycle.state());
+                    let health = self.health_weights.score(&HealthInputs {
+                        energy,
+                        mesh_fill: mesh.snapshot().stats.mesh_size as f32 / self.degradation.mesh_config(energy).d.max(1) as f32,
+                        outbox_fill: 1.0 - mycelium.link_headroom(),
+                        storage_used: disk_free.map(|free| 1.0 - free),
+                    });
+                    p.health = Some(health::percent(health));
+                    self.fleet_health.lock().unwrap().local = p.health;
+                    self.zones.observe(&self.peer_id.to_string(), p.zone.as_deref(), energy, std::time::Instant::now());
 
-                    let phase = {
-                        let mut mesh = self.mesh.lock().unwrap();
-                        mesh.tick_pulse(pulse_delta);
-                        mesh.pulse_phase
-                    };
+                    let phase = mesh.tick_pulse(pulse_delta).await.unwrap_or_default();
+                    let peers = mesh.snapshot().stats.known_peers;
 
                     // Pulse-Gating: Only publish status/heartbeats at pulse peak
                     if phase > 0.8 {
-                        let _ = mycelium.swarm.behaviour_mut().gossipsub.publish(
-                            mycelium.status_topic.clone(),
-                            serde_json::to_vec(&p)?,
-                        );
+                        if self.lifecycle.advertises() && self.damping.allow_control(peers, std::time::Instant::now()) {
+                            // A changed catalog goes out ahead of the advert
+                            // that names its version.
+                            if let Some(delta) = self.catalog.set(&self.peer_id.to_string(), self.catalog_entries()) {
+                                mycelium.publish(topic::CATALOG, Priority::Low, &delta, &mode)?;
+                            }
+                            p.catalog_version = Some(self.catalog.version());
+                            mycelium.publish(topic::STATUS, Priority::Low, &p, &mode)?;
+                        }
 
                     // 2. Mesh Heartbeat & Adaptation
-                    let (controls, _stats) = {
-                        let mut mesh = self.mesh.lock().unwrap();
-
-                        // Adaptive Mesh Configuration: re-calculate based on current energy
-                        mesh.config = MeshConfig::adaptive(energy);
-
-                        let c = mesh.heartbeat();
-                        (c, mesh.stats())
-                    };
+                    // Adaptive Mesh Configuration: re-calculate based on current energy,
+                    // with IHAVE gossip cut back further on a congested link.
+                    let mesh_config = self.degradation.mesh_config(energy).scale_gossip(energy.min(mycelium.link_headroom()));
+                    let controls = mesh.heartbeat(mesh_config).await;
 
                         for (target_peer, ctrl) in controls {
-                            let _ = mycelium.swarm.behaviour_mut().gossipsub.publish(
-                                mycelium.control_topic.clone(),
-                                serde_json::to_vec(&(target_peer, ctrl))?,
-                            );
+                            if !self.damping.allow_control(peers, std::time::Instant::now()) {
+                                tracing::debug!(peer = %target_peer, "Control budget spent; holding mesh control");
+                                continue;
+                            }
+                            mycelium.publish(
+                                topic::CONTROL,
+                                Priority::High,
+                                &SignedControl::sign(&self.signing_key, target_peer, ctrl),
+                                &mode,
+                            )?;
+                        }
+                        // Small publishes held since the last peak go out
+                        // together, one message per topic.
+                        mycelium.flush_batches();
+                        for p in self.status_checks.take_batch(energy) {
+                            self.verify_status(&p);
                         }
                     }
 
                     // Update pressure based on local stats
-                    {
-                        let mut mesh = self.mesh.lock().unwrap();
-                        let backlog = mesh.message_cache.len() as f32; // Proxy for pressure
-                        mesh.set_pressure(backlog * 0.1);
-                    }
+                    mesh.refresh_pressure();
 
-                    // Adjust local heartbeat dynamically
+                    // Adjust local heartbeat dynamically, damped while the
+                    // network is bursting.
+                    if let Some(bursting) = self.damping.observe(std::time::Instant::now()).filter(|_| self.damping.config.enabled) {
+                        let (rate, duplicates) = self.damping.averages();
+                        info!(peer_id = %self.peer_id, bursting, rate, duplicates, "Network burst state changed");
+                    }
                     if dynamic_heartbeat {
-                        heartbeat = tokio::time::interval(self.heartbeat_interval());
-                    }
-
-                    // 3. Shared State Anti-Entropy (Probabilistic)
-                    // Every few heartbeats, broadcast a SyncStep1 to pull missing updates.
-                    if rng().random_bool(0.1) {
-                        let state = self.shared_state.lock().unwrap();
-                        let sync_msg = state.create_sync_step_1();
-                        if let Ok(bytes) = serde_json::to_vec(&sync_msg) {
-                            let _ = mycelium.swarm.behaviour_mut().gossipsub.publish(
-                                mycelium.shared_state_topic.clone(),
-                                bytes,
-                            );
-                        }
+                        let pressure = mesh.snapshot().local_pressure;
+                        heartbeat = tokio::time::interval(self.heartbeat_interval_at(pressure));
+                    }
+
+                    // 3. Shared State Anti-Entropy (Adaptive)
+                    // Broadcast a SyncStep1 to pull missing updates, more often while
+                    // replicas are diverging and rarely once they have converged.
+                    if self.anti_entropy.tick(&mut rng()) {
+                        self.publish_sync_requests(&mut mycelium, &mode)?;
+                    }
+
+                    // 4. Sequence gaps: ask sources to resend, report losses.
+                    if self.sequencing.enabled {
+                        self.request_missing(&mut mycelium);
                     }
                 }
-                event = mycelium.swarm.select_next_some() => {
+                event = next_event(&mut mycelium.swarm, &mut replaying) => {
+                    self.watermark.beat(swarm_event_kind(&event));
                     if !listen_sent {
                         if let SwarmEvent::NewListenAddr { address, .. } = &event {
                             if let Some(tx) = on_listen.take() {
@@ -443,168 +2460,1370 @@ impl SporeNode {
                             listen_sent = true;
                         }
                     }
+                    // Keep mesh peer lifecycles in step with the swarm's connections.
+                    match &event {
+                        SwarmEvent::ConnectionEstablished { peer_id, num_established, endpoint, .. } => {
+                            let peer = peer_id.to_string();
+                            let now = unix_now();
+                            if self.reputation.shuns(&peer, now) {
+                                let standing = self.reputation.standing(&peer, now);
+                                self.refuse_peer(&mut mycelium, *peer_id, &AdmissionError::Reputation(standing));
+                                let _ = mycelium.swarm.disconnect_peer_id(*peer_id);
+                                continue;
+                            }
+                            // Gated peers join the mesh once their join request checks out.
+                            if self.admission.connected(*peer_id, std::time::Instant::now()) {
+                                if endpoint.is_dialer() {
+                                    mesh.peer_dialed(&peer);
+                                } else {
+                                    mesh.peer_connected(&peer);
+                                }
+                                mesh.set_peer_reputation(&peer, self.reputation.penalty(&peer, now));
+                                // Where the peer came from, for picking diverse first hops.
+                                if let Some(ip) = remote_ip(endpoint.get_remote_address()) {
+                                    mesh.record_origin(&peer, OriginFact::Address(ip));
+                                }
+                                if let Some(point) = mycelium.take_introducer(peer_id) {
+                                    mesh.record_origin(&peer, OriginFact::Introducer(point.to_string()));
+                                }
+                            }
+                            if endpoint.is_dialer() {
+                                self.peer_store.record(&peer, endpoint.get_remote_address().clone());
+                            }
+                            // The first previous mesh peer back after a wake
+                            // is asked for what this node slept through.
+                            if self.waking.as_ref().is_some_and(|wake| wake.peers.contains(&peer)) {
+                                self.waking = None;
+                                info!(%peer, "Previous mesh peer back; requesting missed updates");
+                                self.anti_entropy.record_divergence();
+                                let mode = self.degradation.power_mode(self.energy_score());
+                                self.publish_sync_requests(&mut mycelium, &mode)?;
+                            }
+                            if num_established.get() == 1 && self.admission.config.credential.is_some() {
+                                mycelium.request_join(peer_id, self.admission.config.credential.clone());
+                            }
+                            mycelium.rendezvous_connected(peer_id);
+                            if num_established.get() == 1 {
+                                let mail = self.mailbox.connected(&peer, std::time::Instant::now());
+                                if !mail.is_empty() {
+                                    info!(%peer, tasks = mail.len(), "Delivering held tasks");
+                                    mycelium.deliver_mail(peer_id, mail);
+                                }
+                                let connected = mycelium.swarm.connected_peers().count();
+                                if self.introductions.should_ask(&peer, connected, std::time::Instant::now()) {
+                                    let record = self.own_peer_record(&mycelium);
+                                    mycelium.request_introduction(peer_id, Some(record));
+                                }
+                            }
+                        }
+                        SwarmEvent::ConnectionClosed {
+                            peer_id,
+                            num_established: 0,
+                            ..
+                        } => {
+                            self.admission.disconnected(peer_id);
+                            mesh.peer_disconnected(&peer_id.to_string());
+                            self.mailbox.disconnected(&peer_id.to_string(), std::time::Instant::now());
+                        }
+                        SwarmEvent::Behaviour(MyceliumEvent::Rendezvous(ev)) => {
+                            mycelium.handle_rendezvous_event(ev);
+                        }
+                        _ => {}
+                    }
+                    let event = match event {
+                        SwarmEvent::Behaviour(MyceliumEvent::Join(ev)) => {
+                            self.handle_join_event(&mut mycelium, mesh, ev);
+                            continue;
+                        }
+                        SwarmEvent::Behaviour(MyceliumEvent::Catalog(ev)) => {
+                            self.handle_catalog_event(&mut mycelium, ev);
+                            continue;
+                        }
+                        SwarmEvent::Behaviour(MyceliumEvent::Mailbox(ev)) => {
+                            self.handle_mailbox_event(&mut mycelium, ev);
+                            continue;
+                        }
+                        SwarmEvent::Behaviour(MyceliumEvent::Sequence(ev)) => {
+                            self.handle_sequence_event(&mut mycelium, ev);
+                            continue;
+                        }
+                        SwarmEvent::Behaviour(MyceliumEvent::Chunks(ev)) => {
+                            self.handle_chunk_event(&mut mycelium, ev);
+                            continue;
+                        }
+                        SwarmEvent::Behaviour(MyceliumEvent::Introduce(ev)) => {
+                            self.handle_introduction_event(&mut mycelium, mesh, ev);
+                            continue;
+                        }
+                        other => other,
+                    };
                     if let SwarmEvent::Behaviour(MyceliumEvent::Gossipsub(gossipsub::Event::Message {
                         propagation_source: source_peer_id,
                         message_id: id,
                         message,
                     })) = event {
+                        if let Some(capture) = &mut self.capture {
+                            let captured = CapturedMessage::new(trace::now_ms(), &source_peer_id, &id, &message);
+                            if let Err(e) = capture.record(&captured) {
+                                tracing::warn!(err = %e, "Stopping gossip capture");
+                                self.capture = None;
+                            }
+                        }
+                        // Take ownership of the payload once: decoders borrow
+                        // it, batch items are slices of it and the relay below
+                        // hands the same buffer back to gossipsub. Only the
+                        // relay is copy-free; the stored copy, the dedup cache
+                        // and decoded CRDT updates still own their bytes.
+                        let gossipsub::Message { data, topic, source: origin, .. } = message;
+                        if !self.admission.may_publish(&source_peer_id, mycelium.topic_kind(&topic)) {
+                            tracing::debug!(peer_id = %source_peer_id, %topic, "Dropping message relayed by unadmitted peer");
+                            self.anomaly.record_unsolicited(&source_peer_id.to_string());
+                            continue;
+                        }
+                        let author = origin.map(|peer| peer.to_string()).unwrap_or_default();
+                        if !self.topic_acl.permits(&author, mycelium.topic_kind(&topic), unix_now()) {
+                            tracing::warn!(peer_id = %author, %topic, "Dropping publish the topic ACL does not permit");
+                            mesh.penalize_peer(&author, self.anomaly.config.penalty, self.anomaly.config.penalty_for);
+                            continue;
+                        }
+                        if !self.attestations.lock().unwrap().permits(&author, mycelium.topic_kind(&topic)) {
+                            tracing::debug!(peer_id = %author, %topic, "Dropping publish from unattested peer");
+                            continue;
+                        }
+                        let data = Bytes::from(data);
+                        // Traced messages get our hop on receipt; a relay below
+                        // republishes with it included.
+                        let mut trace = trace::peek(&data);
+                        let mut looped = false;
+                        if let Some(trace) = trace.as_mut() {
+                            let now_ms = trace::now_ms();
+                            looped = !trace.record_hop(&self.peer_id.to_string(), now_ms);
+                            if let Some(kind) = mycelium.topic_kind(&topic) {
+                                let sent_ms = trace.hops.first().map_or(now_ms, |hop| hop.at_ms);
+                                self.slo.record_delivery(
+                                    kind,
+                                    Duration::from_millis(now_ms.saturating_sub(sent_ms)),
+                                    std::time::Instant::now(),
+                                );
+                            }
+                            if looped {
+                                tracing::warn!(%id, origin = %trace.origin, hops = trace.hop_count, "Traced message looped back");
+                            }
+                            let _ = self.events.send(NodeEvent::Traced {
+                                topic: topic.to_string(),
+                                trace: trace.clone(),
+                            });
+                        }
+                        mesh.mark_seen(&source_peer_id.to_string());
+                        let duplicate = self.anomaly.record_message(&source_peer_id.to_string(), &id.to_string());
+                        self.damping.record_message(duplicate);
                         let energy = self.energy_score();
+                        let mode = self.degradation.power_mode(energy);
                         self.metrics.lock().unwrap().record_delivery(Duration::from_millis(50));
 
-                        if message.topic == mycelium.status_topic.hash() {
-                            match serde_json::from_slice::<EnergyStatus>(&message.data) {
-                                Ok(p) => {
-                                    let mut mesh = self.mesh.lock().unwrap();
-                                    mesh.update_peer_score(&source_peer_id.to_string(), p.energy_score);
+                        // A batch frame carries several envelopes for one
+                        // known topic; each is handled as its own message.
+                        let items: Vec<Bytes> = match mycelium.topic_kind(&topic).map(|_| wire::unbatch(&data)) {
+                            Some(Ok(items)) => items.into_iter().map(|item| data.slice_ref(item)).collect(),
+                            Some(Err(e)) => {
+                                tracing::warn!(peer_id = %source_peer_id, err = %e, "Ignoring malformed batch");
+                                self.anomaly.record_malformed(&source_peer_id.to_string());
+                                continue;
+                            }
+                            None => vec![data],
+                        };
+                        for data in items {
+                            // Stamps count only from their own source; relayed copies
+                            // carry the relay's signature.
+                            let stamp = mycelium.topic_kind(&topic)
+                                .filter(|kind| self.sequencing.sequences(*kind))
+                                .and_then(|_| wire::peek_sequence(&data))
+                                .filter(|seq| seq.source == author);
+                            if let Some(seq) = stamp {
+                                if self.gaps.observe(&self.sequencing, &seq, std::time::Instant::now()) == Arrival::Duplicate {
+                                    tracing::debug!(%id, source = %seq.source, n = seq.n, "Dropping duplicate sequenced message");
+                                    continue;
+                                }
+                            }
+                            if mycelium.carries(topic::STATUS, &topic) {
+                                match topic::STATUS.decode(&data).map(|e| e.body) {
+                                    Ok(p) => {
+                                        mesh.update_peer_score(&source_peer_id.to_string(), p.energy_score);
+                                        if author == p.source_id {
+                                            if !self.status_checks.is_enabled() {
+                                                self.verify_status(&p);
+                                            } else if let Some(stale) = self.status_checks.push(p.clone()) {
+                                                tracing::debug!(peer_id = %stale.source_id, "Dropping unverified status; verification queue full");
+                                            }
+                                            mesh.update_peer_role(&p.source_id, p.role);
+                                            self.zones.observe(&p.source_id, p.zone.as_deref(), p.energy_score, std::time::Instant::now());
+                                            mesh.record_origin(&p.source_id, OriginFact::Zone(p.zone.clone()));
+                                            if let Some(health) = p.health {
+                                                self.fleet_health.lock().unwrap().record(&p.source_id, health, std::time::Instant::now());
+                                            }
+                                            if let (Some(version), Some(origin)) = (p.catalog_version, origin) {
+                                                if self.peer_catalogs.needs_snapshot(&p.source_id, version, std::time::Instant::now()) {
+                                                    mycelium.request_catalog(&origin);
+                                                }
+                                            }
+                                            match p.role {
+                                                Some(role) => {
+                                                    self.peer_roles.insert(p.source_id.clone(), role)
+                                                }
+                                                None => self.peer_roles.remove(&p.source_id),
+                                            };
+                                        }
 
-                                    if p.energy_score > energy + 0.3 {
-                                        info!(peer_id = %self.peer_id, "Sensing high-energy neighbor {}, moving to passive sync", p.source_id);
+                                        if p.energy_score > energy + 0.3 {
+                                            info!(peer_id = %self.peer_id, "Sensing high-energy neighbor {}, moving to passive sync", p.source_id);
+                                        }
+                                        let _ = self.events.send(NodeEvent::Status(p));
                                     }
+                                    Err(e) => match topic::CATALOG.decode(&data) {
+                                        Ok(envelope) => self.handle_catalog_delta(&mut mycelium, envelope.body, origin),
+                                        Err(_) => {
+                                            // Treat malformed status as untrusted input (DoS otherwise).
+                                            tracing::warn!(
+                                                peer_id = %source_peer_id,
+                                                err = %e,
+                                                "Ignoring malformed EnergyStatus"
+                                            );
+                                            self.anomaly.record_malformed(&source_peer_id.to_string());
+                                        }
+                                    },
                                 }
-                                Err(e) => {
-                                    // Treat malformed status as untrusted input (DoS otherwise).
-                                    tracing::warn!(
-                                        peer_id = %source_peer_id,
-                                        err = %e,
-                                        "Ignoring malformed EnergyStatus"
-                                    );
-                                }
-                            }
-                        } else if message.topic == mycelium.control_topic.hash() {
-                            match serde_json::from_slice::<(String, MeshControl)>(&message.data) {
-                                Ok((target_id, ctrl)) => {
-                                    if target_id == self.peer_id.to_string() {
-                                        let mut mesh = self.mesh.lock().unwrap();
-                                        if let Some(response) =
-                                            mesh.handle_control(&source_peer_id.to_string(), ctrl)
-                                        {
-                                            let _ = mycelium.swarm.behaviour_mut().gossipsub.publish(
-                                                mycelium.control_topic.clone(),
-                                                serde_json::to_vec(&(source_peer_id.to_string(), response))?,
+                            } else if mycelium.carries(topic::CONTROL, &topic) {
+                                match topic::CONTROL.decode(&data).map(|e| e.body) {
+                                    Ok(signed) => match signed.verify(&source_peer_id) {
+                                        Ok(()) if signed.target == self.peer_id.to_string() => {
+                                            let response = mesh
+                                                .handle_control(&signed.sender, signed.control)
+                                                .await;
+                                            if let Some(response) = response {
+                                                mycelium.publish(
+                                                    topic::CONTROL,
+                                                    Priority::High,
+                                                    &SignedControl::sign(
+                                                        &self.signing_key,
+                                                        signed.sender,
+                                                        response,
+                                                    ),
+                                                    &mode,
+                                                )?;
+                                            }
+                                        }
+                                        // Only a sender that delivered its own forgery is
+                                        // penalized; a relay cannot vouch for what it passes on.
+                                        Ok(()) | Err(ControlError::Relayed { .. }) => {}
+                                        Err(e @ ControlError::BadSignature(_)) => {
+                                            tracing::warn!(
+                                                peer_id = %source_peer_id,
+                                                err = %e,
+                                                "Dropping forged MeshControl message"
+                                            );
+                                            mesh.penalize_peer(
+                                                &source_peer_id.to_string(),
+                                                self.anomaly.config.penalty,
+                                                self.anomaly.config.penalty_for,
                                             );
                                         }
+                                    },
+                                    Err(e) => {
+                                        tracing::warn!(
+                                            peer_id = %source_peer_id,
+                                            err = %e,
+                                            "Ignoring malformed MeshControl message"
+                                        );
+                                        self.anomaly.record_malformed(&source_peer_id.to_string());
                                     }
                                 }
-                                Err(e) => {
-                                    tracing::warn!(
-                                        peer_id = %source_peer_id,
-                                        err = %e,
-                                        "Ignoring malformed MeshControl message"
-                                    );
-                                }
-                            }
-                        } else if message.topic == mycelium.task_topic.hash() {
-                            match serde_json::from_slice::<Task>(&message.data) {
-                                Ok(task) => {
-                                    info!(%id, task_id = %task.id, "Task detected in network");
+                            } else if mycelium.carries(topic::TASKS, &topic) {
+                                match topic::TASKS.decode(&data).map(|e| e.body) {
+                                    Ok(task) => {
+                                        info!(%id, task_id = %task.id, "Task detected in network");
+                                        self.mailbox.seen(&task.id);
+                                        self.hold_for_absent(&task, energy);
+                                        // Only hosted tenants get counters; ids are sender-chosen.
+                                        if let Some(tenant) = task.tenant.as_deref().filter(|t| self.tenants.contains_key(*t)) {
+                                            self.metrics.lock().unwrap().tenant_mut(tenant).tasks_seen += 1;
+                                        }
+                                        if self.emergency.covers_task(task.priority)
+                                            && self.emergency.should_relay(energy, std::time::Instant::now(), rand::random())
+                                            && self.lifecycle.relays()
+                                            && !looped
+                                        {
+                                            let _ = mycelium.swarm.behaviour_mut().gossipsub.publish(topic.clone(), data.clone());
+                                            info!(%id, task_id = %task.id, "Emergency relay of task");
+                                        }
+                                        let _ = self.events.send(NodeEvent::Task(task));
+                                    }
+                                    Err(e) => match topic::LEASES.decode(&data) {
+                                        Ok(envelope) => self.handle_lease(envelope.body, origin, &source_peer_id),
+                                        Err(_) => {
+                                            tracing::warn!(
+                                                peer_id = %source_peer_id,
+                                                err = %e,
+                                                "Ignoring malformed Task"
+                                            );
+                                            self.anomaly.record_malformed(&source_peer_id.to_string());
+                                        }
+                                    },
                                 }
-                                Err(e) => {
+                            } else if mycelium.carries(topic::SPIKES, &topic) {
+                                // Prototype pressure telemetry. Not an alert bus.
+                                if let Ok(spike) = topic::SPIKES.decode(&data).map(|e| e.body) {
+                                    let mut roots = self.trusted_issuers.clone();
+                                    roots.push(self.peer_id.to_string());
+                                    if let Err(e) = self.spikes.admit(
+                                        &spike,
+                                        &roots,
+                                        &self.delegation_limits,
+                                        std::time::Instant::now(),
+                                    ) {
+                                        tracing::debug!(
+                                            peer_id = %source_peer_id,
+                                            source = %spike.source,
+                                            err = %e,
+                                            "Dropping spike"
+                                        );
+                                        if matches!(e, SpikeError::BadSignature(_)) {
+                                            self.anomaly.record_malformed(&source_peer_id.to_string());
+                                        }
+                                        continue;
+                                    }
+                                    if spike.affects_mesh_pressure() {
+                                        info!(
+                                            peer_id = %self.peer_id,
+                                            source = %spike.source,
+                                            intensity = spike.intensity,
+                                            "Received mesh pressure spike"
+                                        );
+                                        mesh.handle_spike(&spike.source, spike.intensity);
+                                        self.emergency.trigger(std::time::Instant::now());
+                                    }
+                                    if self.emergency.should_relay(energy, std::time::Instant::now(), rand::random()) && self.lifecycle.relays() && !looped {
+                                        let _ = mycelium.swarm.behaviour_mut().gossipsub.publish(topic.clone(), data.clone());
+                                        info!(%id, source = %spike.source, "Emergency relay of spike");
+                                    }
+                                    let _ = self.events.send(NodeEvent::Spike(spike));
+                                } else {
                                     tracing::warn!(
                                         peer_id = %source_peer_id,
-                                        err = %e,
-                                        "Ignoring malformed Task"
-                                    );
-                                }
-                            }
-                        } else if message.topic == mycelium.spike_topic.hash() {
-                            // Prototype pressure telemetry. Not an alert bus.
-                            if let Ok(spike) = serde_json::from_slice::<Spike>(&message.data) {
-                                if spike.affects_mesh_pressure() {
-                                    info!(
-                                        peer_id = %self.peer_id,
-                                        source = %spike.source,
-                                        intensity = spike.intensity,
-                                        "Received mesh pressure spike"
+                                        "Ignoring malformed Spike"
                                     );
-                                    let mut mesh = self.mesh.lock().unwrap();
-                                    mesh.handle_spike(&spike.source, spike.intensity);
+                                    self.anomaly.record_malformed(&source_peer_id.to_string());
                                 }
-                            } else {
-                                tracing::warn!(
-                                    peer_id = %source_peer_id,
-                                    "Ignoring malformed Spike"
-                                );
-                            }
-                        } else if message.topic == mycelium.shared_state_topic.hash() {
-                            // CRDT Sync
-                            match serde_json::from_slice::<SyncMessage>(&message.data) {
-                                Ok(SyncMessage::Update(bytes)) => {
-                                    let state = self.shared_state.lock().unwrap();
-                                    if let Err(e) = state.apply_update(&bytes) {
-                                        tracing::warn!("Failed to apply CRDT update: {}", e);
-                                    } else {
-                                        tracing::info!("Applied CRDT update from {}", source_peer_id);
+                            } else if mycelium.carries(topic::SENSOR, &topic) {
+                                match topic::SENSOR.decode(&data).map(|e| e.body) {
+                                    Ok(reading) => {
+                                        let _ = self.events.send(NodeEvent::Reading(reading));
+                                    }
+                                    Err(e) => {
+                                        tracing::warn!(
+                                            peer_id = %source_peer_id,
+                                            err = %e,
+                                            "Ignoring malformed SensorReading"
+                                        );
+                                        self.anomaly.record_malformed(&source_peer_id.to_string());
                                     }
                                 }
-                                Ok(SyncMessage::SyncStep1(sv_bytes)) => {
-                                    let state = self.shared_state.lock().unwrap();
-                                    if let Ok(reply) = state.handle_sync_step_1(&sv_bytes) {
-                                        let _ = mycelium.swarm.behaviour_mut().gossipsub.publish(
-                                            mycelium.shared_state_topic.clone(),
-                                            serde_json::to_vec(&reply).unwrap(),
+                            } else if mycelium.carries(topic::QUORUM, &topic) {
+                                match topic::QUORUM.decode(&data).map(|e| e.body) {
+                                    Ok(vote) => {
+                                        let handled = self.quorum.lock().unwrap().handle(vote, unix_now());
+                                        match handled {
+                                            Ok(Some(cert)) => {
+                                                info!(peer_id = %self.peer_id, action = %cert.action.action, scope = %cert.action.scope, "Quorum reached");
+                                                let _ = self.events.send(NodeEvent::Certified(cert));
+                                            }
+                                            Ok(None) => {}
+                                            Err(e) => tracing::debug!(peer_id = %source_peer_id, err = %e, "Ignoring quorum vote"),
+                                        }
+                                    }
+                                    Err(e) => {
+                                        tracing::warn!(
+                                            peer_id = %source_peer_id,
+                                            err = %e,
+                                            "Ignoring malformed QuorumMessage"
                                         );
+                                        self.anomaly.record_malformed(&source_peer_id.to_string());
                                     }
                                 }
-                                Ok(SyncMessage::SyncStep2(update_bytes)) => {
-                                    let state = self.shared_state.lock().unwrap();
-                                    if let Err(e) = state.handle_sync_step_2(&update_bytes) {
-                                        tracing::warn!("Failed to apply sync step 2: {}", e);
+                            } else if mycelium.carries(topic::DEPARTURES, &topic) {
+                                match topic::DEPARTURES.decode(&data).map(|e| e.body) {
+                                    // Only the departing node may announce itself.
+                                    Ok(departing) if origin.is_some_and(|o| o.to_string() == departing.peer) => {
+                                        info!(peer_id = %departing.peer, eta_secs = departing.eta_secs, "Peer departing");
+                                        mesh.peer_disconnected(&departing.peer);
+                                        self.anomaly.forget(&departing.peer);
+                                        self.peer_roles.remove(&departing.peer);
+                                        self.attestations.lock().unwrap().forget(&departing.peer);
+                                        self.zones.forget(&departing.peer);
+                                        self.status_checks.retain(|p| p.source_id != departing.peer);
+                                        self.peer_catalogs.forget(&departing.peer);
+
+                                        // Re-auction what we published and it had taken on.
+                                        let me = self.peer_id.to_string();
+                                        self.outgoing_tasks.lock().unwrap().extend(
+                                            departing.in_flight.iter().filter(|t| t.source_id == me).cloned(),
+                                        );
+
+                                        // Pull any state only it holds while it can still answer.
+                                        self.anti_entropy.record_divergence();
+                                        self.publish_sync_requests(&mut mycelium, &mode)?;
+                                        let _ = self.events.send(NodeEvent::Departing(departing));
+                                    }
+                                    Ok(departing) => {
+                                        tracing::warn!(
+                                            peer_id = %source_peer_id,
+                                            claimed = %departing.peer,
+                                            "Ignoring departure announced on behalf of another peer"
+                                        );
+                                        self.anomaly.record_malformed(&source_peer_id.to_string());
+                                    }
+                                    Err(e) => {
+                                        tracing::warn!(
+                                            peer_id = %source_peer_id,
+                                            err = %e,
+                                            "Ignoring malformed Departing"
+                                        );
+                                        self.anomaly.record_malformed(&source_peer_id.to_string());
                                     }
                                 }
-                                Err(e) => {
-                                    tracing::warn!("Malformed sync message: {}", e);
+                            } else if mycelium.carries(topic::SHARED_STATE, &topic) {
+                                // CRDT Sync, for a tenant's doc or the node's own
+                                let decoded = match topic::TENANT_SYNC.decode(&data) {
+                                    Ok(envelope) => Ok((Some(envelope.body.tenant), envelope.body.message)),
+                                    Err(_) => topic::SHARED_STATE.decode(&data).map(|e| (None, e.body)),
+                                };
+                                match decoded {
+                                    Ok((tenant, message)) => {
+                                        self.handle_sync(offload, tenant, message, &source_peer_id);
+                                    }
+                                    Err(e) => {
+                                        tracing::warn!("Malformed sync message: {}", e);
+                                        self.anomaly.record_malformed(&source_peer_id.to_string());
+                                    }
                                 }
-                            }
-                        } else {
-                            let key = format!("msg_{}", id);
-                            let _ = self.db.insert(key, &message.data);
-
-                            let mut mesh = self.mesh.lock().unwrap();
-                            mesh.record_message(&source_peer_id.to_string(), &id.to_string());
-
-                            // Emergent Relaying: high-energy nodes relay messages to deepen reach
-                            let energy = self.energy_score();
-                            let (pressure, pulse_phase) = {
-                                let mesh = self.mesh.lock().unwrap();
-                                (mesh.local_pressure, mesh.pulse_phase)
-                            };
-
-                            // Relaying strategy:
-                            // 1. High energy (>0.6)
-                            // 2. Low pressure (<7.0)
-                            // 3. Pulse-gated (peak) OR high-energy mains power
-                            let should_relay = if energy > 0.9 {
-                                true // Mains power relays everything
                             } else {
-                                energy > 0.6 && pressure < 7.0 && pulse_phase > 0.7
-                            };
+                                let key = format!("msg_{}", id);
+                                let payload = data.clone();
+                                let size = data.len();
 
-                            if should_relay {
-                                let _ = mycelium.swarm.behaviour_mut().gossipsub.publish(
-                                    message.topic.clone(),
-                                    message.data.clone(),
-                                );
-                                info!(%id, "Emergent relay triggered");
+                                mesh.record_message(&source_peer_id.to_string(), &id.to_string());
+                                if wire::peek_priority(&data).is_some_and(|p| p >= Priority::High) {
+                                    mesh.mark_urgent(&id.to_string());
+                                }
+
+                                // Emergent Relaying: high-energy nodes relay messages to deepen reach
+                                let energy = self.energy_score();
+                                let MeshSnapshot { local_pressure: pressure, pulse_phase, .. } = mesh.snapshot();
+
+                                // Mains-class nodes relay everything; others only on
+                                // the full rung, at low pressure and at their pulse peak.
+                                let should_relay = self.degradation.should_relay(energy, pressure, pulse_phase);
+
+                                // Across zones only the zone's bridge relays, whatever
+                                // its relay policy says.
+                                let route = if looped {
+                                    Forward::Hold
+                                } else {
+                                    let tag = zone::peek(&data);
+                                    self.zones.route(&self.peer_id.to_string(), &id.to_string(), &author, tag.as_ref(), std::time::Instant::now())
+                                };
+                                let relayed = self.lifecycle.relays() && match route {
+                                    Forward::Relay => should_relay,
+                                    Forward::Bridge(_) => true,
+                                    Forward::Hold => false,
+                                };
+                                if relayed {
+                                    let data = match trace.clone().and_then(|t| trace::restamp(&data, t)) {
+                                        Some(stamped) => Bytes::from(stamped),
+                                        None => data,
+                                    };
+                                    let data = match route {
+                                        Forward::Bridge(tag) => zone::restamp(&data, tag).map_or(data, Bytes::from),
+                                        _ => data,
+                                    };
+                                    let _ = mycelium.swarm.behaviour_mut().gossipsub.publish(topic.clone(), data);
+                                    self.role_inference.record_relay();
+                                    info!(%id, "Emergent relay triggered");
+                                }
+
+                                let from = source_peer_id.to_string();
+                                let record = Provenance {
+                                    id: id.to_string(),
+                                    topic: topic.to_string(),
+                                    author: (!author.is_empty() && author != from).then(|| author.clone()),
+                                    from,
+                                    received_at: unix_millis(),
+                                    size,
+                                    relayed,
+                                };
+                                let (messages, provenance) = (self.messages.clone(), self.provenance.clone());
+                                offload.write(move || {
+                                    let _ = messages.insert(&key, &payload[..]);
+                                    if let Err(e) = provenance.record(&record) {
+                                        tracing::warn!(id = %record.id, err = %e, "Failed to record message provenance");
+                                    }
+                                });
+                                info!(%source_peer_id, %id, "Message persisted");
                             }
+                        }
+                    }
+                }
+            }
+        }
+    }
 
-                            info!(%source_peer_id, %id, "Message persisted");
+    /// Answer join requests and note the answers to ours.
+    fn handle_join_event(
+        &mut self,
+        mycelium: &mut Mycelium,
+        mesh: &MeshHandle,
+        event: request_response::Event<JoinRequest, JoinResponse>,
+    ) {
+        match event {
+            request_response::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+                ..
+            } => {
+                let response = match self
+                    .admission
+                    .check(peer, &request, &self.delegation_limits)
+                {
+                    Ok(()) => {
+                        info!(%peer, "Peer admitted");
+                        let gossipsub = &mut mycelium.swarm.behaviour_mut().gossipsub;
+                        gossipsub.remove_blacklisted_peer(&peer);
+                        mesh.peer_connected(&peer.to_string());
+                        mesh.set_peer_reputation(
+                            &peer.to_string(),
+                            self.reputation.penalty(&peer.to_string(), unix_now()),
+                        );
+                        let _ = self.events.send(NodeEvent::PeerAdmitted {
+                            peer: peer.to_string(),
+                        });
+                        JoinResponse::Admitted
+                    }
+                    Err(e) => {
+                        self.refuse_peer(mycelium, peer, &e);
+                        JoinResponse::Refused {
+                            reason: e.to_string(),
                         }
                     }
+                };
+                let join = &mut mycelium.swarm.behaviour_mut().join;
+                let _ = join.send_response(channel, response);
+            }
+            request_response::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Response {
+                        response: JoinResponse::Refused { reason },
+                        ..
+                    },
+                ..
+            } => {
+                tracing::warn!(%peer, %reason, "Admission refused by peer");
+            }
+            // A refusal has gone out, or could not; either way the peer is done.
+            request_response::Event::ResponseSent { peer, .. }
+            | request_response::Event::InboundFailure { peer, .. }
+                if !self.admission.is_admitted(&peer) =>
+            {
+                let _ = mycelium.swarm.disconnect_peer_id(peer);
+            }
+            _ => {}
+        }
+    }
+
+    /// The capabilities and sensors this node offers, in catalog form.
+    fn catalog_entries(&self) -> Vec<CatalogEntry> {
+        self.capabilities
+            .iter()
+            .cloned()
+            .map(CatalogEntry::Capability)
+            .chain(
+                self.sensors
+                    .iter()
+                    .map(|sensor| CatalogEntry::Sensor(sensor.name().to_string())),
+            )
+            .chain(
+                self.geohash(self.location_precision)
+                    .map(CatalogEntry::Location),
+            )
+            .collect()
+    }
+
+    /// Apply a peer's catalog delta, asking the peer for a snapshot if it
+    /// shows that an earlier delta was missed. Only the owner may publish
+    /// its deltas.
+    fn handle_catalog_delta(
+        &mut self,
+        mycelium: &mut Mycelium,
+        delta: CatalogDelta,
+        origin: Option<PeerId>,
+    ) {
+        let Some(origin) = origin.filter(|o| o.to_string() == delta.owner) else {
+            tracing::debug!(owner = %delta.owner, "Dropping catalog delta not published by its owner");
+            return;
+        };
+        match self.peer_catalogs.apply(&delta) {
+            Ok(()) => {
+                let version = self.peer_catalogs.version(&delta.owner);
+                let _ = self.events.send(NodeEvent::CatalogUpdated {
+                    peer: delta.owner,
+                    version,
+                });
+            }
+            Err(e) => {
+                tracing::debug!(peer = %origin, err = %e, "Requesting catalog snapshot");
+                if self.peer_catalogs.needs_snapshot(
+                    &delta.owner,
+                    delta.to,
+                    std::time::Instant::now(),
+                ) {
+                    mycelium.request_catalog(&origin);
+                }
+            }
+        }
+    }
+
+    fn handle_catalog_event(
+        &mut self,
+        mycelium: &mut Mycelium,
+        event: request_response::Event<CatalogRequest, CatalogSnapshot>,
+    ) {
+        match event {
+            request_response::Event::Message {
+                message: request_response::Message::Request { channel, .. },
+                ..
+            } => {
+                let catalog = &mut mycelium.swarm.behaviour_mut().catalog;
+                let _ = catalog.send_response(channel, self.catalog.snapshot());
+            }
+            request_response::Event::Message {
+                peer,
+                message: request_response::Message::Response { response, .. },
+                ..
+            } => {
+                let version = response.version;
+                self.peer_catalogs
+                    .apply_snapshot(&peer.to_string(), response);
+                let _ = self.events.send(NodeEvent::CatalogUpdated {
+                    peer: peer.to_string(),
+                    version,
+                });
+            }
+            request_response::Event::OutboundFailure { peer, error, .. } => {
+                tracing::debug!(%peer, err = %error, "Catalog snapshot request failed");
+            }
+            _ => {}
+        }
+    }
+
+    /// This node's addresses, signed for handing out in introductions.
+    /// External addresses when the swarm has confirmed any, else the
+    /// specific addresses it listens on.
+    fn own_peer_record(&self, mycelium: &Mycelium) -> SignedPeer {
+        let mut addrs: Vec<String> = mycelium
+            .swarm
+            .external_addresses()
+            .map(|addr| addr.to_string())
+            .collect();
+        if addrs.is_empty() {
+            addrs = mycelium
+                .swarm
+                .listeners()
+                .filter(|addr| {
+                    !addr.iter().any(|p| match p {
+                        Protocol::Ip4(ip) => ip.is_unspecified(),
+                        Protocol::Ip6(ip) => ip.is_unspecified(),
+                        _ => false,
+                    })
+                })
+                .map(|addr| addr.to_string())
+                .collect();
+        }
+        addrs.truncate(MAX_RECORD_ADDRS);
+        SignedPeer::sign(&self.signing_key, addrs, unix_now())
+    }
+
+    fn handle_introduction_event(
+        &mut self,
+        mycelium: &mut Mycelium,
+        mesh: &MeshHandle,
+        event: request_response::Event<IntroductionRequest, Introduction>,
+    ) {
+        match event {
+            request_response::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+                ..
+            } => {
+                let asker = peer.to_string();
+                // Only the asker's own record; anything else would let it
+                // plant addresses for peers it does not control.
+                if let Some(record) = request.record.filter(|record| record.peer == asker) {
+                    if let Err(e) = self.introductions.learn(record, unix_now()) {
+                        tracing::debug!(%peer, err = %e, "Ignoring asker's peer record");
+                        self.anomaly.record_malformed(&asker);
+                    }
+                }
+                let me = self.own_peer_record(mycelium);
+                let ranked = mesh.snapshot().ranked;
+                let answer = self.introductions.answer(
+                    &asker,
+                    me,
+                    &ranked,
+                    std::time::Instant::now(),
+                    unix_now(),
+                );
+                let introduce = &mut mycelium.swarm.behaviour_mut().introduce;
+                let _ = introduce.send_response(channel, answer);
+            }
+            request_response::Event::Message {
+                peer,
+                message: request_response::Message::Response { response, .. },
+                ..
+            } => {
+                if let Some(reason) = &response.refused {
+                    tracing::debug!(%peer, %reason, "Introduction refused");
+                    return;
+                }
+                let me = mycelium.swarm.local_peer_id().to_string();
+                let connected: HashSet<String> = mycelium
+                    .swarm
+                    .connected_peers()
+                    .map(|p| p.to_string())
+                    .collect();
+                let accepted =
+                    self.introductions
+                        .accept(response, &me, |p| connected.contains(p), unix_now());
+                if accepted.invalid > 0 {
+                    self.anomaly.record_malformed(&peer.to_string());
+                }
+                let dialed: usize = accepted
+                    .dial
+                    .iter()
+                    .map(|record| mycelium.dial_introduced(peer, record))
+                    .sum();
+                if dialed > 0 {
+                    info!(%peer, dialed, "Dialing introduced peers");
+                }
+            }
+            request_response::Event::OutboundFailure { peer, error, .. } => {
+                tracing::debug!(%peer, err = %error, "Introduction request failed");
+            }
+            _ => {}
+        }
+    }
+
+    /// Keep `task` for absent peers that could take it, if the mailbox is
+    /// on and energy allows. Peers whose catalog is unknown get it anyway;
+    /// for a task targeting an area, so do peers that list no location.
+    fn hold_for_absent(&mut self, task: &Task, energy: f32) {
+        if !self.mailbox.holds(energy) || task.target.is_some() {
+            return;
+        }
+        let catalogs = &self.peer_catalogs;
+        let held = self
+            .mailbox
+            .hold(task, std::time::Instant::now(), |peer, task| {
+                catalogs.get(peer).is_none_or(|catalog| {
+                    let capable = catalog.entries.iter().any(|entry| match entry {
+                        CatalogEntry::Capability(cap) => cap.satisfies(&task.required_capability),
+                        CatalogEntry::Sensor(name) => matches!(
+                            &task.required_capability,
+                            Capability::Sensing(sensor) if sensor == name
+                        ),
+                        CatalogEntry::Location(_) => false,
+                    });
+                    let near = match (&task.geohash, catalog.location()) {
+                        (Some(area), Some(here)) => geohash::overlaps(area, here),
+                        _ => true,
+                    };
+                    capable && near
+                })
+            });
+        if held > 0 {
+            tracing::debug!(task_id = %task.id, peers = held, "Holding task for absent peers");
+        }
+    }
+
+    fn handle_mailbox_event(
+        &mut self,
+        mycelium: &mut Mycelium,
+        event: request_response::Event<MailboxDelivery, MailboxAck>,
+    ) {
+        match event {
+            request_response::Event::Message {
+                peer,
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+                ..
+            } => {
+                // Held tasks pass the gates task gossip does. Without a
+                // response the holder keeps them and retries later.
+                if !self.admission.may_publish(&peer, Some(TopicKind::Task)) {
+                    tracing::debug!(%peer, "Dropping held tasks from unadmitted peer");
+                    self.anomaly.record_unsolicited(&peer.to_string());
+                    return;
+                }
+                let tasks = self.mailbox.accept(request);
+                info!(%peer, tasks = tasks.len(), "Received held tasks");
+                let mut ack = MailboxAck::default();
+                for task in tasks {
+                    let author = &task.source_id;
+                    let permitted =
+                        self.topic_acl
+                            .permits(author, Some(TopicKind::Task), unix_now())
+                            && self
+                                .attestations
+                                .lock()
+                                .unwrap()
+                                .permits(author, Some(TopicKind::Task));
+                    if !permitted {
+                        tracing::warn!(%peer, %author, task_id = %task.id, "Dropping held task its author may not publish");
+                        ack.refused.push(Refusal {
+                            task_id: task.id,
+                            reason: "author may not publish tasks".to_string(),
+                        });
+                        continue;
+                    }
+                    if task.target.is_none() {
+                        ack.accepted += 1;
+                        let _ = self.events.send(NodeEvent::Task(task));
+                        continue;
+                    }
+                    let winner = self.peer_id.to_string();
+                    match self.take_assignment(&task) {
+                        Ok(()) => {
+                            info!(%peer, task_id = %task.id, "Took addressed task");
+                            ack.accepted += 1;
+                            let _ = self.events.send(NodeEvent::Awarded { task, winner });
+                        }
+                        Err(e) => {
+                            info!(%peer, task_id = %task.id, reason = %e, "Refusing addressed task");
+                            let _ = self.events.send(NodeEvent::AwardDeclined {
+                                task_id: task.id.clone(),
+                                winner,
+                                reason: e.to_string(),
+                            });
+                            ack.refused.push(Refusal {
+                                task_id: task.id,
+                                reason: e.to_string(),
+                            });
+                        }
+                    }
+                }
+                let mailbox = &mut mycelium.swarm.behaviour_mut().mailbox;
+                let _ = mailbox.send_response(channel, ack);
+            }
+            request_response::Event::Message {
+                peer,
+                message: request_response::Message::Response { response, .. },
+                ..
+            } => {
+                self.mailbox.delivered(&peer.to_string());
+                for refusal in response.refused {
+                    tracing::warn!(
+                        %peer,
+                        task_id = %refusal.task_id,
+                        reason = %refusal.reason,
+                        "Addressed task refused"
+                    );
+                    let _ = self.events.send(NodeEvent::AwardDeclined {
+                        task_id: refusal.task_id,
+                        winner: peer.to_string(),
+                        reason: refusal.reason,
+                    });
+                }
+            }
+            request_response::Event::OutboundFailure { peer, error, .. } => {
+                tracing::debug!(%peer, err = %error, "Held task delivery failed");
+                self.mailbox.failed(&peer.to_string());
+            }
+            _ => {}
+        }
+    }
+
+    /// Take up a new firmware release for this node's device class, and
+    /// ask peers for the chunks it still lacks while energy allows.
+    fn poll_firmware(&mut self, mycelium: &mut Mycelium, energy: f32, is_mains: bool) {
+        if !self.ota.enabled {
+            return;
+        }
+        let document = self
+            .shared_state
+            .lock()
+            .unwrap()
+            .get(&release_key(&self.device_class));
+        if let Some(document) = document {
+            match FirmwareRelease::decode(&document) {
+                Ok(release) => self.consider_release(release),
+                Err(e) => tracing::debug!(err = %e, "Ignoring unreadable firmware release"),
+            }
+        }
+        let Some(download) = self.download.as_mut() else {
+            return;
+        };
+        if download.is_complete() {
+            self.install_firmware();
+            return;
+        }
+        if !self.ota.may_fetch(energy, is_mains) {
+            return;
+        }
+        let peers: Vec<PeerId> = mycelium.swarm.connected_peers().copied().collect();
+        let requests = download.requests(
+            &peers,
+            self.ota.parallel,
+            self.ota.request_timeout,
+            std::time::Instant::now(),
+        );
+        for (peer, hash) in requests {
+            mycelium.request_chunk(&peer, hash);
+        }
+    }
+
+    fn consider_release(&mut self, release: FirmwareRelease) {
+        let known = self.ota.current_version.as_deref() == Some(release.version.as_str())
+            || self.firmware_failed.as_deref() == Some(release.hash.as_str())
+            || self
+                .download
+                .as_ref()
+                .is_some_and(|download| download.release.hash == release.hash);
+        if known {
+            return;
+        }
+        let admitted = {
+            let shared_state = self.shared_state.lock().unwrap();
+            self.ota
+                .admit(&release, &self.artifacts, |key| shared_state.get(key))
+        };
+        if let Err(e) = admitted {
+            tracing::debug!(version = %release.version, err = %e, "Ignoring firmware release");
+            return;
+        }
+        let chunks = &self.chunks;
+        let download = Download::new(release, |hash| chunks.contains(hash));
+        info!(
+            version = %download.release.version,
+            missing = download.remaining(),
+            "Fetching firmware release"
+        );
+        self.download = Some(download);
+    }
+
+    fn install_firmware(&mut self) {
+        let Some(download) = self.download.take() else {
+            return;
+        };
+        let release = download.release;
+        let chunks = &self.chunks;
+        let installed = release
+            .assemble(|hash| chunks.get(hash).ok().flatten())
+            .and_then(|image| match &self.installer {
+                Some(installer) => installer
+                    .install(&release, &image)
+                    .map_err(|e| OtaError::Install(e.to_string())),
+                None => Err(OtaError::NoInstaller),
+            });
+        match installed {
+            Ok(()) => {
+                info!(version = %release.version, "Firmware installed");
+                self.ota.current_version = Some(release.version.clone());
+                let _ = self.events.send(NodeEvent::FirmwareInstalled {
+                    version: release.version,
+                });
+            }
+            Err(e) => {
+                tracing::warn!(version = %release.version, err = %e, "Firmware install failed");
+                self.firmware_failed = Some(release.hash);
+                let _ = self.events.send(NodeEvent::FirmwareFailed {
+                    version: release.version,
+                    reason: e.to_string(),
+                });
+            }
+        }
+    }
+
+    /// Serve chunks this node holds, and store those it asked for.
+    fn handle_chunk_event(
+        &mut self,
+        mycelium: &mut Mycelium,
+        event: request_response::Event<ChunkRequest, ChunkResponse>,
+    ) {
+        match event {
+            request_response::Event::Message {
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+                ..
+            } => {
+                let data = self.chunks.get(&request.hash).ok().flatten();
+                let response = ChunkResponse::new(&request.hash, data.as_deref());
+                let chunks = &mut mycelium.swarm.behaviour_mut().chunks;
+                let _ = chunks.send_response(channel, response);
+            }
+            request_response::Event::Message {
+                peer,
+                message: request_response::Message::Response { response, .. },
+                ..
+            } => {
+                let Some(download) = self.download.as_mut() else {
+                    return;
+                };
+                let stored = match response.bytes() {
+                    Some(data) => self.chunks.insert(&response.hash, &data),
+                    None if response.data.is_none() => {
+                        // The peer does not hold it; the next round asks another.
+                        download.failed(&response.hash);
+                        return;
+                    }
+                    None => Err(ChunkError::HashMismatch(response.hash.clone())),
+                };
+                match stored {
+                    Ok(()) => {
+                        download.received(&response.hash);
+                    }
+                    Err(ChunkError::HashMismatch(_)) => {
+                        tracing::warn!(%peer, hash = %response.hash, "Dropping corrupt chunk");
+                        self.anomaly.record_malformed(&peer.to_string());
+                        download.failed(&response.hash);
+                    }
+                    Err(e) => {
+                        tracing::warn!(hash = %response.hash, err = %e, "Failed to store chunk");
+                        download.failed(&response.hash);
+                    }
                 }
+                if download.is_complete() {
+                    self.install_firmware();
+                }
+            }
+            request_response::Event::OutboundFailure { peer, error, .. } => {
+                tracing::debug!(%peer, err = %error, "Chunk request failed");
             }
+            _ => {}
         }
     }
 
+    /// Ask sources for the sequenced messages this node is missing, and
+    /// report those given up on.
+    fn request_missing(&mut self, mycelium: &mut Mycelium) {
+        let sweep = self.gaps.sweep(&self.sequencing, std::time::Instant::now());
+        for (source, request) in sweep.requests {
+            match source.parse::<PeerId>() {
+                Ok(peer) => mycelium.request_resend(&peer, request),
+                Err(_) => self.gaps.forget(&source),
+            }
+        }
+        for (source, count) in sweep.lost {
+            tracing::debug!(%source, count, "Sequenced messages lost");
+            let _ = self.events.send(NodeEvent::MessagesLost { source, count });
+        }
+    }
+
+    fn handle_sequence_event(
+        &mut self,
+        mycelium: &mut Mycelium,
+        event: request_response::Event<ResendRequest, ResendResponse>,
+    ) {
+        match event {
+            request_response::Event::Message {
+                message:
+                    request_response::Message::Request {
+                        request, channel, ..
+                    },
+                ..
+            } => {
+                let response = self.sequencer.lock().unwrap().resend(&request);
+                let sequence = &mut mycelium.swarm.behaviour_mut().sequence;
+                let _ = sequence.send_response(channel, response);
+            }
+            request_response::Event::Message {
+                peer,
+                message: request_response::Message::Response { response, .. },
+                ..
+            } => {
+                let source = peer.to_string();
+                let resent = response.messages.len();
+                for message in response.messages {
+                    self.replay_resent(&source, message);
+                }
+                let lost = self.gaps.give_up_below(&source, response.oldest);
+                tracing::debug!(%peer, resent, lost, "Received resent messages");
+            }
+            request_response::Event::OutboundFailure { peer, error, .. } => {
+                tracing::debug!(%peer, err = %error, "Resend request failed");
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle a message `source` resent, if it fills a gap.
+    fn replay_resent(&mut self, source: &str, message: Resent) {
+        let stamped = wire::peek_sequence(&message.envelope)
+            .filter(|seq| seq.source == source && seq.n == message.n);
+        let Some(seq) = stamped else {
+            tracing::debug!(%source, n = message.n, "Ignoring resent message with a foreign stamp");
+            return;
+        };
+        if !self.sequencing.sequences(message.topic)
+            || !self
+                .topic_acl
+                .permits(source, Some(message.topic), unix_now())
+        {
+            return;
+        }
+        let now = std::time::Instant::now();
+        if self.gaps.observe(&self.sequencing, &seq, now) != Arrival::Recovered {
+            return;
+        }
+        match message.topic {
+            TopicKind::Task => match topic::TASKS.decode(&message.envelope) {
+                Ok(envelope) => {
+                    self.mailbox.seen(&envelope.body.id);
+                    let _ = self.events.send(NodeEvent::Task(envelope.body));
+                }
+                Err(e) => tracing::debug!(%source, err = %e, "Ignoring malformed resent Task"),
+            },
+            TopicKind::Sensor => match topic::SENSOR.decode(&message.envelope) {
+                Ok(envelope) => {
+                    let _ = self.events.send(NodeEvent::Reading(envelope.body));
+                }
+                Err(e) => {
+                    tracing::debug!(%source, err = %e, "Ignoring malformed resent SensorReading")
+                }
+            },
+            _ => {}
+        }
+    }
+
+    /// Ignore a peer that failed admission and tell subscribers why. Callers
+    /// disconnect it once any reply is out.
+    fn refuse_peer(&self, mycelium: &mut Mycelium, peer: PeerId, reason: &AdmissionError) {
+        tracing::warn!(%peer, %reason, "Peer refused admission");
+        mycelium
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .blacklist_peer(&peer);
+        let _ = self.events.send(NodeEvent::PeerRefused {
+            peer: peer.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Gossip queued awards and due renewals, and publish again the tasks
+    /// whose award lapsed.
+    fn publish_leases(
+        &self,
+        mycelium: &mut Mycelium,
+        mode: &PowerMode,
+    ) -> Result<(), Box<dyn Error>> {
+        let now = std::time::Instant::now();
+        let in_flight: HashSet<String> = self.in_flight.lock().unwrap().keys().cloned().collect();
+        let (messages, lapsed) = {
+            let mut leases = self.leases.lock().unwrap();
+            let mut messages = leases.take_outgoing();
+            messages.extend(leases.due_renewals(&self.peer_id.to_string(), &in_flight, now));
+            (messages, leases.lapsed(now))
+        };
+        for message in messages {
+            mycelium.publish(topic::LEASES, Priority::High, &message, mode)?;
+        }
+        for (task, winner) in lapsed {
+            tracing::warn!(task_id = %task.id, %winner, "Award lease lapsed; re-opening auction");
+            mycelium.publish_to_shard(
+                topic::TASKS,
+                self.task_shard(&task),
+                Priority::High,
+                &task,
+                mode,
+            )?;
+            let _ = self.events.send(NodeEvent::LeaseLapsed {
+                task_id: task.id,
+                winner,
+            });
+        }
+        Ok(())
+    }
+
+    /// Awards count only from the task's publisher and renewals only from
+    /// the winner, as vouched for by the gossipsub message signature.
+    fn handle_lease(&mut self, message: LeaseMessage, origin: Option<PeerId>, source: &PeerId) {
+        let origin = origin.map(|o| o.to_string());
+        match message {
+            LeaseMessage::Award { task, winner, .. }
+                if origin.as_ref() == Some(&task.source_id) =>
+            {
+                if winner == self.peer_id.to_string() {
+                    // Other awards may have arrived since the bid.
+                    if let Err(e) = self.admit(&task) {
+                        info!(task_id = %task.id, reason = %e, "Declining task award");
+                        self.leases
+                            .lock()
+                            .unwrap()
+                            .decline(&task.id, &winner, e.to_string());
+                        let _ = self.events.send(NodeEvent::AwardDeclined {
+                            task_id: task.id,
+                            winner,
+                            reason: e.to_string(),
+                        });
+                        return;
+                    }
+                    info!(task_id = %task.id, "Won task award");
+                    self.leases
+                        .lock()
+                        .unwrap()
+                        .won(&task.id, std::time::Instant::now());
+                    self.in_flight
+                        .lock()
+                        .unwrap()
+                        .insert(task.id.clone(), task.clone());
+                }
+                let _ = self.events.send(NodeEvent::Awarded { task, winner });
+            }
+            LeaseMessage::Renew { task_id, winner } if origin.as_ref() == Some(&winner) => {
+                self.leases
+                    .lock()
+                    .unwrap()
+                    .renewed(&task_id, &winner, std::time::Instant::now());
+            }
+            LeaseMessage::Decline {
+                task_id,
+                winner,
+                reason,
+            } if origin.as_ref() == Some(&winner) => {
+                let reopened = self.leases.lock().unwrap().declined(&task_id, &winner);
+                if let Some(task) = reopened {
+                    tracing::warn!(%task_id, %winner, %reason, "Award declined; re-opening auction");
+                    self.outgoing_tasks.lock().unwrap().push(task);
+                    let _ = self.events.send(NodeEvent::AwardDeclined {
+                        task_id,
+                        winner,
+                        reason,
+                    });
+                }
+            }
+            _ => {
+                tracing::warn!(
+                    peer_id = %source,
+                    "Ignoring lease message sent on behalf of another peer"
+                );
+                self.anomaly.record_malformed(&source.to_string());
+            }
+        }
+    }
+
+    /// Ask peers for updates missing from the node's doc and every tenant's.
+    fn publish_sync_requests(
+        &self,
+        mycelium: &mut Mycelium,
+        mode: &PowerMode,
+    ) -> Result<(), Box<dyn Error>> {
+        let sync_msg = self.shared_state.lock().unwrap().create_sync_step_1();
+        mycelium.publish(topic::SHARED_STATE, Priority::Normal, &sync_msg, mode)?;
+        for (id, tenant) in &self.tenants {
+            let message = tenant.shared_state.lock().unwrap().create_sync_step_1();
+            let sync_msg = TenantSync {
+                tenant: id.clone(),
+                message,
+            };
+            mycelium.publish(topic::TENANT_SYNC, Priority::Normal, &sync_msg, mode)?;
+        }
+        Ok(())
+    }
+
+    /// Hand a sync message for `tenant`'s doc to `offload`. Messages for
+    /// tenants this node does not host are ignored.
+    fn handle_sync(
+        &self,
+        offload: &Offload<Offloaded>,
+        tenant: Option<String>,
+        message: SyncMessage,
+        source: &PeerId,
+    ) {
+        let Some(state) = self.shared_state_of(tenant.as_deref()) else {
+            return;
+        };
+        let source = source.to_string();
+        offload.submit(move || apply_sync(&state, tenant, message, source));
+    }
+
+    /// Act on the result of offloaded work: publish a sync reply, or note a
+    /// merged update.
+    fn complete(
+        &mut self,
+        mycelium: &mut Mycelium,
+        done: Offloaded,
+        mode: &PowerMode,
+    ) -> Result<(), Box<dyn Error>> {
+        match done {
+            Offloaded::Answered {
+                tenant,
+                reply,
+                diverged,
+            } => {
+                if diverged {
+                    self.anti_entropy.record_divergence();
+                }
+                let Some(message) = reply else {
+                    return Ok(());
+                };
+                match tenant {
+                    None => {
+                        mycelium.publish(topic::SHARED_STATE, Priority::Normal, &message, mode)?
+                    }
+                    Some(tenant) => mycelium.publish(
+                        topic::TENANT_SYNC,
+                        Priority::Normal,
+                        &TenantSync { tenant, message },
+                        mode,
+                    )?,
+                };
+            }
+            Offloaded::Merged {
+                tenant,
+                source,
+                changed,
+            } => {
+                if changed {
+                    self.anti_entropy.record_divergence();
+                    if let Some(id) = &tenant {
+                        self.metrics.lock().unwrap().tenant_mut(id).state_updates += 1;
+                    }
+                }
+                let _ = self.events.send(NodeEvent::StateUpdated { source, tenant });
+            }
+        }
+        Ok(())
+    }
+
     /// Default run loop: listen + run forever.
-    pub async fn start(&mut self) -> Result<(), Box<dyn Error>> {
+    pub async fn start(self) -> Result<(), Box<dyn Error>> {
         let mut mycelium = self.build_mycelium()?;
         // Default: listen on an ephemeral local port.
         mycelium.listen_on("/ip4/0.0.0.0/tcp/0".parse()?)?;
+        let heartbeat_every = self.heartbeat_interval();
         let _ = self
-            .run_for(
+            .run_supervised(
                 mycelium,
                 Duration::from_secs(u64::MAX / 4),
-                self.heartbeat_interval(),
+                heartbeat_every,
                 0.05,
                 true,
                 None,
@@ -614,6 +3833,157 @@ impl SporeNode {
     }
 }
 
+/// Cancels a supervised run loop when the supervisor stops waiting for it,
+/// including when the `run_supervised` future is dropped. The loop's thread
+/// then holds the node until it exits; waiting for that, up to `grace`, is
+/// left to the blocking pool so that no runtime worker is held up and a
+/// runtime being dropped still lets the thread finish first.
+struct LoopStop {
+    cancel: Option<tokio::sync::oneshot::Sender<()>>,
+    exited: Option<std::sync::mpsc::Receiver<()>>,
+    grace: Duration,
+}
+
+impl LoopStop {
+    fn cancel(&mut self) {
+        if let Some(cancel) = self.cancel.take() {
+            let _ = cancel.send(());
+        }
+    }
+}
+
+impl Drop for LoopStop {
+    fn drop(&mut self) {
+        self.cancel();
+        let Some(exited) = self.exited.take() else {
+            return;
+        };
+        let grace = self.grace;
+        let wait = move || {
+            let _ = exited.recv_timeout(grace);
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn_blocking(wait);
+            }
+            Err(_) => {
+                std::thread::spawn(wait);
+            }
+        }
+    }
+}
+
+/// Results of work the run loop handed to its `Offload` worker.
+enum Offloaded {
+    /// A peer's state vector was checked; `reply` carries what it lacks.
+    Answered {
+        tenant: Option<String>,
+        reply: Option<SyncMessage>,
+        diverged: bool,
+    },
+    /// A peer's update was merged into `tenant`'s doc.
+    Merged {
+        tenant: Option<String>,
+        source: String,
+        changed: bool,
+    },
+}
+
+/// Apply a sync message from `source` to `state`. Runs on the offload
+/// worker, so failures are only logged.
+fn apply_sync(
+    state: &Mutex<SharedState>,
+    tenant: Option<String>,
+    message: SyncMessage,
+    source: String,
+) -> Option<Offloaded> {
+    let state = state.lock().unwrap();
+    let changed = match message {
+        SyncMessage::Update(bytes) => match state.apply_update(&bytes) {
+            Err(e) => {
+                tracing::warn!("Failed to apply CRDT update: {}", e);
+                return None;
+            }
+            Ok(changed) => {
+                tracing::info!("Applied CRDT update from {}", source);
+                changed
+            }
+        },
+        SyncMessage::SyncStep1(sv_bytes) => {
+            let diverged = state.diverges_from(&sv_bytes).unwrap_or(false);
+            let reply = state.handle_sync_step_1(&sv_bytes).ok();
+            return Some(Offloaded::Answered {
+                tenant,
+                reply,
+                diverged,
+            });
+        }
+        SyncMessage::SyncStep2(update_bytes) => match state.handle_sync_step_2(&update_bytes) {
+            Err(e) => {
+                tracing::warn!("Failed to apply sync step 2: {}", e);
+                return None;
+            }
+            Ok(changed) => changed,
+        },
+    };
+    Some(Offloaded::Merged {
+        tenant,
+        source,
+        changed,
+    })
+}
+
+/// The IP address in `addr`, if it has one. For a relayed connection that
+/// is the relay's.
+fn remote_ip(addr: &Multiaddr) -> Option<std::net::IpAddr> {
+    addr.iter().find_map(|protocol| match protocol {
+        Protocol::Ip4(ip) => Some(ip.into()),
+        Protocol::Ip6(ip) => Some(ip.into()),
+        _ => None,
+    })
+}
+
+/// The next captured message waiting to be replayed, else the next swarm
+/// event.
+async fn next_event(
+    swarm: &mut libp2p::Swarm<MyceliumBehaviour>,
+    replaying: &mut VecDeque<gossipsub::Event>,
+) -> SwarmEvent<MyceliumEvent> {
+    match replaying.pop_front() {
+        Some(event) => SwarmEvent::Behaviour(MyceliumEvent::Gossipsub(event)),
+        None => swarm.select_next_some().await,
+    }
+}
+
+/// Short label for a swarm event, kept in the watchdog's history.
+fn swarm_event_kind(event: &SwarmEvent<MyceliumEvent>) -> &'static str {
+    match event {
+        SwarmEvent::Behaviour(MyceliumEvent::Gossipsub(_)) => "swarm:gossipsub",
+        SwarmEvent::Behaviour(MyceliumEvent::Identify(_)) => "swarm:identify",
+        SwarmEvent::Behaviour(MyceliumEvent::RelayClient(_)) => "swarm:relay_client",
+        SwarmEvent::Behaviour(MyceliumEvent::Dcutr(_)) => "swarm:dcutr",
+        SwarmEvent::Behaviour(MyceliumEvent::Rendezvous(_)) => "swarm:rendezvous",
+        SwarmEvent::Behaviour(MyceliumEvent::RendezvousServer(_)) => "swarm:rendezvous_server",
+        SwarmEvent::Behaviour(MyceliumEvent::Join(_)) => "swarm:join",
+        SwarmEvent::Behaviour(MyceliumEvent::Catalog(_)) => "swarm:catalog",
+        SwarmEvent::Behaviour(MyceliumEvent::Mailbox(_)) => "swarm:mailbox",
+        SwarmEvent::Behaviour(MyceliumEvent::Sequence(_)) => "swarm:sequence",
+        SwarmEvent::Behaviour(MyceliumEvent::Chunks(_)) => "swarm:chunks",
+        SwarmEvent::Behaviour(MyceliumEvent::Introduce(_)) => "swarm:introduce",
+        SwarmEvent::ConnectionEstablished { .. } => "swarm:connection_established",
+        SwarmEvent::ConnectionClosed { .. } => "swarm:connection_closed",
+        SwarmEvent::IncomingConnection { .. } | SwarmEvent::IncomingConnectionError { .. } => {
+            "swarm:incoming"
+        }
+        SwarmEvent::OutgoingConnectionError { .. } | SwarmEvent::Dialing { .. } => "swarm:dial",
+        SwarmEvent::NewListenAddr { .. }
+        | SwarmEvent::ExpiredListenAddr { .. }
+        | SwarmEvent::ListenerClosed { .. }
+        | SwarmEvent::ListenerError { .. } => "swarm:listener",
+        _ => "swarm:other",
+    }
+}
+
 #[cfg(test)]
 mod eval_suite {
     use super::*;
@@ -634,6 +4004,12 @@ mod eval_suite {
             reach_intensity: 1.0,
             source_id: "test-source".to_string(),
             auth_token: None,
+            public_result: false,
+            tenant: None,
+            memo_key: None,
+            geohash: None,
+            target: None,
+            expected_output_bytes: None,
         };
 
         // 1. No other bidders -> Spore bids (energy 1.0)
@@ -648,4 +4024,226 @@ mod eval_suite {
             "Should stay silent due to quorum"
         );
     }
+
+    #[test]
+    fn bids_are_priced_from_execution_telemetry() {
+        let tmp = tempdir().unwrap();
+        let metabolism = Arc::new(Mutex::new(MockMetabolism::new(1.0, false)));
+        let mut node = SporeNode::new_with_metabolism(tmp.path(), metabolism).unwrap();
+        node.add_capability(Capability::Compute(10));
+        let task = Task::new("t".into(), Capability::Compute(5), 1, "p".into());
+        assert_eq!(node.evaluate_task(&task, 0).unwrap().cost_mah, 50.0);
+
+        for _ in 0..4 {
+            node.record_execution(ExecutionReport {
+                wall_time: Duration::from_millis(20),
+                fuel_used: 300_000,
+                mah_delta: None,
+            });
+        }
+        assert_eq!(node.evaluate_task(&task, 0).unwrap().cost_mah, 3.0);
+    }
+
+    #[test]
+    fn cached_results_are_bid_at_near_zero_cost() {
+        let tmp = tempdir().unwrap();
+        let metabolism = Arc::new(Mutex::new(MockMetabolism::new(1.0, false)));
+        let mut node = SporeNode::new_with_metabolism(tmp.path(), metabolism).unwrap();
+        node.add_capability(Capability::Compute(10));
+        let key = memo_key(b"\0asm", b"input");
+        let task =
+            Task::new("t".into(), Capability::Compute(5), 1, "p".into()).with_memo_key(key.clone());
+        assert_eq!(node.evaluate_task(&task, 0).unwrap().cost_mah, 50.0);
+
+        node.result_cache
+            .insert(&key, &Capability::Compute(5), b"42".to_vec(), unix_now())
+            .unwrap();
+        assert_eq!(
+            node.evaluate_task(&task, 0).unwrap().cost_mah,
+            CACHED_COST_MAH
+        );
+    }
+
+    #[test]
+    fn only_nodes_inside_a_tasks_area_bid() {
+        let tmp = tempdir().unwrap();
+        let metabolism = Arc::new(Mutex::new(MockMetabolism::new(1.0, false)));
+        let mut node = SporeNode::new_with_metabolism(tmp.path(), metabolism).unwrap();
+        node.add_capability(Capability::Compute(10));
+        let task = Task::new("t".into(), Capability::Compute(5), 1, "p".into());
+        let here = task.clone().with_geohash("u4pru");
+        let elsewhere = task.clone().with_geohash("ezs42");
+
+        assert!(node.evaluate_task(&task, 0).is_some());
+        assert!(node.evaluate_task(&here, 0).is_none());
+
+        node.set_location_sensor(Box::new(FixedLocation {
+            lat: 57.64911,
+            lon: 10.40744,
+        }));
+        assert!(node.evaluate_task(&here, 0).is_some());
+        assert!(node.evaluate_task(&elsewhere, 0).is_none());
+        assert!(node
+            .catalog_entries()
+            .contains(&CatalogEntry::Location("u4pruy".to_string())));
+    }
+
+    #[test]
+    fn capacity_limits_stop_bids_and_decline_awards() {
+        use crate::capacity::{CapabilityKind, CapacityLimit};
+
+        let tmp = tempdir().unwrap();
+        let metabolism = Arc::new(Mutex::new(MockMetabolism::new(1.0, false)));
+        let mut node = SporeNode::new_with_metabolism(tmp.path(), metabolism).unwrap();
+        node.add_capability(Capability::Compute(100));
+        node.capacity.set(
+            CapabilityKind::Compute,
+            CapacityLimit {
+                max_tasks: Some(2),
+                max_units: Some(100),
+            },
+        );
+        let task =
+            |id: &str, units| Task::new(id.into(), Capability::Compute(units), 1, "p".into());
+        node.accept_task(task("a", 60));
+        assert!(node.evaluate_task(&task("b", 40), 0).is_some());
+        assert!(node.evaluate_task(&task("b", 41), 0).is_none());
+
+        // An award won in a parallel auction fills the node up; the next
+        // one is declined rather than taken on.
+        let publisher = PeerId::random();
+        let award = |task: Task| LeaseMessage::Award {
+            task: Task {
+                source_id: publisher.to_string(),
+                ..task
+            },
+            winner: node.peer_id.to_string(),
+            lease_secs: 30,
+        };
+        let (first, second) = (award(task("b", 40)), award(task("c", 1)));
+        node.handle_lease(first, Some(publisher), &publisher);
+        node.handle_lease(second, Some(publisher), &publisher);
+        assert!(node.in_flight.lock().unwrap().contains_key("b"));
+        assert!(!node.in_flight.lock().unwrap().contains_key("c"));
+        assert!(matches!(
+            node.leases.lock().unwrap().take_outgoing().as_slice(),
+            [LeaseMessage::Decline { task_id, .. }] if task_id == "c"
+        ));
+    }
+
+    #[test]
+    fn storage_quota_stops_bids_and_declines_awards() {
+        let tmp = tempdir().unwrap();
+        let metabolism = Arc::new(Mutex::new(MockMetabolism::new(1.0, false)));
+        let mut node = SporeNode::new_with_metabolism(tmp.path(), metabolism).unwrap();
+        node.add_capability(Capability::Compute(100));
+        let quota = node.messages.measure().unwrap() + 10_000;
+        node.messages.set_quota(Some(quota)).unwrap();
+        let task = |id: &str, bytes| {
+            Task::new(id.into(), Capability::Compute(10), 1, "p".into())
+                .with_expected_output_bytes(bytes)
+        };
+        assert!(node.evaluate_task(&task("big", 20_000), 0).is_none());
+        assert!(node.evaluate_task(&task("a", 8_000), 0).is_some());
+
+        // Writes since the bid used the headroom up; the award is declined.
+        node.simulate_receive("bulk", &[0u8; 4_000]).unwrap();
+        let publisher = PeerId::random();
+        let award = LeaseMessage::Award {
+            task: Task {
+                source_id: publisher.to_string(),
+                ..task("a", 8_000)
+            },
+            winner: node.peer_id.to_string(),
+            lease_secs: 30,
+        };
+        node.handle_lease(award, Some(publisher), &publisher);
+        assert!(!node.in_flight.lock().unwrap().contains_key("a"));
+        assert!(matches!(
+            node.leases.lock().unwrap().take_outgoing().as_slice(),
+            [LeaseMessage::Decline { task_id, .. }] if task_id == "a"
+        ));
+    }
+
+    #[test]
+    fn addressed_tasks_skip_the_auction_but_not_the_checks() {
+        let tmp = tempdir().unwrap();
+        let metabolism = Arc::new(Mutex::new(MockMetabolism::new(1.0, false)));
+        let mut node = SporeNode::new_with_metabolism(tmp.path(), metabolism).unwrap();
+        node.add_capability(Capability::Compute(10));
+        let me = node.peer_id.to_string();
+        let token = node
+            .delegate(
+                &node.peer_id,
+                Capability::Compute(10),
+                Duration::from_secs(60),
+                None,
+            )
+            .unwrap()
+            .encode();
+        let task = |id: &str, units| {
+            Task::new(id.into(), Capability::Compute(units), 1, "p".into()).with_target(&me)
+        };
+
+        assert!(node.evaluate_task(&task("a", 5), 0).is_none());
+        assert_eq!(
+            node.take_assignment(&task("a", 5)),
+            Err(AssignmentError::MissingToken)
+        );
+        assert_eq!(
+            node.take_assignment(&task("a", 5).with_auth("bogus".into())),
+            Err(AssignmentError::Unauthorized)
+        );
+        let elsewhere = Task {
+            target: Some(PeerId::random().to_string()),
+            ..task("a", 5).with_auth(token.clone())
+        };
+        assert_eq!(
+            node.take_assignment(&elsewhere),
+            Err(AssignmentError::NotAddressed)
+        );
+        assert_eq!(node.take_assignment(&task("a", 5).with_auth(token)), Ok(()));
+        assert!(node.in_flight.lock().unwrap().contains_key("a"));
+    }
+
+    #[test]
+    fn harvest_surplus_discounts_bids_and_wins_auctions() {
+        let tmp = tempdir().unwrap();
+        let metabolism = Arc::new(Mutex::new(HarvestingMetabolism {
+            battery: BatteryMetabolism {
+                voltage: 3.9,
+                mah_remaining: 1500.0,
+                ..BatteryMetabolism::default()
+            },
+            harvest_ma: 400.0,
+            load_ma: 400.0,
+            max_discount: 0.5,
+        }));
+        let mut node = SporeNode::new_with_metabolism(tmp.path(), metabolism.clone()).unwrap();
+        node.add_capability(Capability::Compute(10));
+        let task = Task::new("t".into(), Capability::Compute(5), 1, "p".into());
+
+        // Breaking even: priced like any battery node.
+        let bid = node.evaluate_task(&task, 0).unwrap();
+        assert_eq!((bid.cost_mah, bid.price), (50.0, None));
+
+        // Midday: the whole harvest is surplus.
+        metabolism.lock().unwrap().load_ma = 0.0;
+        let bid = node.evaluate_task(&task, 0).unwrap();
+        assert_eq!(bid.cost_mah, 25.0);
+        assert_eq!(bid.price.as_ref().unwrap().base_mah, 50.0);
+
+        // It outbids a node with a fuller battery but no surplus.
+        let mut known_bids = vec![Bid {
+            task_id: "t".to_string(),
+            bidder_id: "big-battery".to_string(),
+            energy_score: 0.9,
+            cost_mah: 50.0,
+            price: None,
+        }];
+        assert!(bid.energy_score < 0.9);
+        assert!(node
+            .process_task_bundle_best_bid(&task, &mut known_bids)
+            .is_some());
+    }
 }
@@ -0,0 +1,154 @@
+//! Node lifecycle.
+//!
+//! A node is always in exactly one [`NodeState`], derived on each heartbeat
+//! from [`LifecycleSignals`]:
+//!
+//! | State          | Entered when                                          |
+//! |----------------|-------------------------------------------------------|
+//! | `Departed`     | the node has announced its departure                  |
+//! | `Hibernating`  | the energy score falls to the hibernate rung          |
+//! | `Bootstrapping`| no peer is connected                                  |
+//! | `Joining`      | peers are connected but none is in the mesh yet       |
+//! | `Degraded`     | in the mesh, on the status-only or spike-only rung    |
+//! | `Active`       | in the mesh, on the full or no-relay rung             |
+//!
+//! Earlier rows win. A node leaves `Departed` once the departure monitor
+//! re-arms, e.g. on mains power. The run loop consults the state's gates
+//! before relaying, advertising its status and publishing queued work, and
+//! every transition is broadcast as `NodeEvent::StateChanged` and to
+//! `NodeLink::state`.
+
+use crate::core::NodeState;
+use crate::degradation::Rung;
+use std::time::Instant;
+use tokio::sync::watch;
+
+/// What the run loop knows on a heartbeat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LifecycleSignals {
+    pub connected_peers: usize,
+    pub mesh_peers: usize,
+    pub rung: Rung,
+    pub departing: bool,
+}
+
+impl LifecycleSignals {
+    pub fn state(&self) -> NodeState {
+        if self.departing {
+            NodeState::Departed
+        } else if self.rung == Rung::Hibernate {
+            NodeState::Hibernating
+        } else if self.connected_peers == 0 {
+            NodeState::Bootstrapping
+        } else if self.mesh_peers == 0 {
+            NodeState::Joining
+        } else if self.rung <= Rung::NoRelay {
+            NodeState::Active
+        } else {
+            NodeState::Degraded
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeLifecycle {
+    state: watch::Sender<NodeState>,
+    since: Instant,
+}
+
+impl Default for NodeLifecycle {
+    fn default() -> Self {
+        Self {
+            state: watch::channel(NodeState::default()).0,
+            since: Instant::now(),
+        }
+    }
+}
+
+impl NodeLifecycle {
+    pub fn state(&self) -> NodeState {
+        *self.state.borrow()
+    }
+
+    /// When the current state was entered.
+    pub fn since(&self) -> Instant {
+        self.since
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<NodeState> {
+        self.state.subscribe()
+    }
+
+    /// Move to the state `signals` call for. Returns the transition, if any.
+    pub fn update(
+        &mut self,
+        signals: &LifecycleSignals,
+        now: Instant,
+    ) -> Option<(NodeState, NodeState)> {
+        let next = signals.state();
+        let previous = self.state.send_replace(next);
+        if previous == next {
+            return None;
+        }
+        self.since = now;
+        Some((previous, next))
+    }
+
+    /// Whether the node forwards others' messages. The energy rung and relay
+    /// policy still apply on top.
+    pub fn relays(&self) -> bool {
+        matches!(self.state(), NodeState::Active | NodeState::Degraded)
+    }
+
+    /// Whether the node publishes its status adverts.
+    pub fn advertises(&self) -> bool {
+        !matches!(self.state(), NodeState::Bootstrapping | NodeState::Departed)
+    }
+
+    /// Whether queued tasks go out; they wait otherwise.
+    pub fn publishes_work(&self) -> bool {
+        matches!(self.state(), NodeState::Active | NodeState::Degraded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn states_follow_connections_mesh_energy_and_departure() {
+        let mut lifecycle = NodeLifecycle::default();
+        let watcher = lifecycle.subscribe();
+        let t0 = Instant::now();
+        let mut signals = LifecycleSignals {
+            connected_peers: 0,
+            mesh_peers: 0,
+            rung: Rung::Full,
+            departing: false,
+        };
+        assert_eq!(lifecycle.update(&signals, t0), None);
+        assert!(!lifecycle.advertises());
+
+        signals.connected_peers = 3;
+        assert_eq!(
+            lifecycle.update(&signals, t0),
+            Some((NodeState::Bootstrapping, NodeState::Joining))
+        );
+        signals.mesh_peers = 2;
+        lifecycle.update(&signals, t0);
+        assert!(lifecycle.relays() && lifecycle.publishes_work());
+        assert_eq!(*watcher.borrow(), NodeState::Active);
+
+        signals.rung = Rung::SpikeOnly;
+        lifecycle.update(&signals, t0);
+        assert_eq!(lifecycle.state(), NodeState::Degraded);
+        signals.rung = Rung::Hibernate;
+        lifecycle.update(&signals, t0);
+        assert!(!lifecycle.relays() && lifecycle.advertises());
+
+        signals.departing = true;
+        lifecycle.update(&signals, t0);
+        assert_eq!(lifecycle.state(), NodeState::Departed);
+        assert!(!lifecycle.advertises() && !lifecycle.publishes_work());
+    }
+}
@@ -0,0 +1,353 @@
+//! Mailboxes for intermittently connected peers.
+//!
+//! A sensor node that sleeps through a task announcement never sees it:
+//! gossip is not replayed. With a [`Mailbox`] enabled, a node with energy to
+//! spare keeps the tasks it hears for known peers that are currently
+//! disconnected, and when one of them reconnects it hands over what is still
+//! fresh as a [`MailboxDelivery`] over the [`MAILBOX_PROTOCOL`]
+//! request-response protocol.
+//!
+//! A peer is known once it has connected; it is forgotten after
+//! `forget_after` away. Tasks are held only for peers whose catalog offers
+//! the required capability, when their catalog is known, and expire `ttl`
+//! after they were first held. Each peer's box keeps its newest `per_peer`
+//! tasks. A delivery that fails goes back in the box.
+//!
+//! Delivered tasks carry no gossipsub signature from their publisher; they
+//! face the same token and bid checks as gossiped ones.
+//!
+//! The same delivery carries tasks addressed to one peer (`Task::target`),
+//! such as a firmware update for a given device. Their publisher keeps them
+//! with [`Mailbox::address`], whether or not the mailbox is enabled, and
+//! hands them over as soon as the target is connected. They skip the
+//! auction, not the checks: the target takes an addressed task only with a
+//! token granting it, the capability and room under its capacity limits,
+//! and tells the publisher why it refused one in its [`MailboxAck`].
+
+use crate::capacity::CapacityError;
+use crate::core::Task;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+pub const MAILBOX_PROTOCOL: &str = "/hypha/mailbox/1.0.0";
+
+/// Task ids remembered to drop deliveries of tasks already seen.
+const SEEN_CAPACITY: usize = 256;
+
+/// Tasks held for the receiving node while it was away.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MailboxDelivery {
+    pub tasks: Vec<Task>,
+}
+
+/// How many delivered tasks the recipient took, and which addressed ones
+/// it refused.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MailboxAck {
+    pub accepted: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub refused: Vec<Refusal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Refusal {
+    pub task_id: String,
+    pub reason: String,
+}
+
+/// Why a node refused a task addressed to it.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum AssignmentError {
+    #[error("task is not addressed to this node")]
+    NotAddressed,
+    #[error("addressed tasks need a token")]
+    MissingToken,
+    #[error("token does not grant the task")]
+    Unauthorized,
+    #[error("node lacks the required capability")]
+    Incapable,
+    #[error(transparent)]
+    Capacity(#[from] CapacityError),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MailboxConfig {
+    /// Off by default: nothing is held for absent peers.
+    pub enabled: bool,
+    /// Lowest energy score at which the node holds tasks for others.
+    pub min_energy: f32,
+    /// Most tasks held for one peer; the oldest go first.
+    pub per_peer: usize,
+    /// How long a held task stays deliverable.
+    pub ttl: Duration,
+    /// How long a peer may stay away before it is no longer known.
+    pub forget_after: Duration,
+}
+
+impl Default for MailboxConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            min_energy: 0.7,
+            per_peer: 16,
+            ttl: Duration::from_secs(600),
+            forget_after: Duration::from_secs(24 * 3600),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Mailbox {
+    pub config: MailboxConfig,
+    /// Known peers, with when they left; `None` while connected.
+    peers: HashMap<String, Option<Instant>>,
+    boxes: HashMap<String, VecDeque<(Task, Instant)>>,
+    /// Deliveries awaiting the recipient's ack.
+    sending: HashMap<String, Vec<(Task, Instant)>>,
+    seen: VecDeque<String>,
+    seen_ids: HashSet<String>,
+}
+
+impl Mailbox {
+    pub fn new(config: MailboxConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Whether the node holds tasks for others at energy score `energy`.
+    pub fn holds(&self, energy: f32) -> bool {
+        self.config.enabled && energy >= self.config.min_energy
+    }
+
+    /// `peer` connected. Returns the tasks waiting for it, which stay in
+    /// transit until [`Mailbox::delivered`] or [`Mailbox::failed`].
+    pub fn connected(&mut self, peer: &str, now: Instant) -> Vec<Task> {
+        self.peers.insert(peer.to_string(), None);
+        self.due(peer, now)
+    }
+
+    /// The tasks waiting for `peer`, which is connected, unless a delivery
+    /// to it is still unacknowledged. They stay in transit as on
+    /// [`Mailbox::connected`].
+    pub fn due(&mut self, peer: &str, now: Instant) -> Vec<Task> {
+        if self.sending.contains_key(peer) {
+            return Vec::new();
+        }
+        let Some(held) = self.boxes.remove(peer) else {
+            return Vec::new();
+        };
+        let held: Vec<_> = held
+            .into_iter()
+            .filter(|(_, at)| now.duration_since(*at) < self.config.ttl)
+            .collect();
+        let tasks = held.iter().map(|(task, _)| task.clone()).collect();
+        if !held.is_empty() {
+            self.sending.insert(peer.to_string(), held);
+        }
+        tasks
+    }
+
+    pub fn disconnected(&mut self, peer: &str, now: Instant) {
+        self.peers.insert(peer.to_string(), Some(now));
+    }
+
+    /// Hold `task` for every known peer that is away, forgetting peers gone
+    /// longer than `forget_after`. `wants` says whether a peer could take
+    /// the task. Returns how many boxes it went into.
+    pub fn hold(
+        &mut self,
+        task: &Task,
+        now: Instant,
+        wants: impl Fn(&str, &Task) -> bool,
+    ) -> usize {
+        let forget_after = self.config.forget_after;
+        let gone: Vec<String> = self
+            .peers
+            .iter()
+            .filter(|(_, left)| left.is_some_and(|at| now.duration_since(at) >= forget_after))
+            .map(|(peer, _)| peer.clone())
+            .collect();
+        for peer in gone {
+            self.forget(&peer);
+        }
+
+        let mut held = 0;
+        for (peer, left) in &self.peers {
+            if left.is_none() || *peer == task.source_id || !wants(peer, task) {
+                continue;
+            }
+            let queue = self.boxes.entry(peer.clone()).or_default();
+            if queue.iter().any(|(t, _)| t.id == task.id) {
+                continue;
+            }
+            queue.push_back((task.clone(), now));
+            while queue.len() > self.config.per_peer {
+                queue.pop_front();
+            }
+            held += 1;
+        }
+        held
+    }
+
+    /// Keep `task`, which this node publishes, for `peer` alone until it
+    /// is [`due`](Mailbox::due). Expired tasks in the box make room first.
+    pub fn address(&mut self, peer: &str, task: Task, now: Instant) {
+        let ttl = self.config.ttl;
+        let queue = self.boxes.entry(peer.to_string()).or_default();
+        queue.retain(|(held, at)| held.id != task.id && now.duration_since(*at) < ttl);
+        queue.push_back((task, now));
+        while queue.len() > self.config.per_peer {
+            queue.pop_front();
+        }
+    }
+
+    /// The delivery to `peer` was acknowledged.
+    pub fn delivered(&mut self, peer: &str) {
+        self.sending.remove(peer);
+    }
+
+    /// The delivery to `peer` failed; its tasks go back in the box.
+    pub fn failed(&mut self, peer: &str) {
+        let Some(tasks) = self.sending.remove(peer) else {
+            return;
+        };
+        let queue = self.boxes.entry(peer.to_string()).or_default();
+        for (task, at) in tasks.into_iter().rev() {
+            queue.push_front((task, at));
+        }
+        while queue.len() > self.config.per_peer {
+            queue.pop_front();
+        }
+    }
+
+    /// Record that the task `id` reached this node.
+    pub fn seen(&mut self, id: &str) {
+        if !self.seen_ids.insert(id.to_string()) {
+            return;
+        }
+        self.seen.push_back(id.to_string());
+        if self.seen.len() > SEEN_CAPACITY {
+            if let Some(oldest) = self.seen.pop_front() {
+                self.seen_ids.remove(&oldest);
+            }
+        }
+    }
+
+    /// The tasks in a delivery this node has not seen yet.
+    pub fn accept(&mut self, delivery: MailboxDelivery) -> Vec<Task> {
+        let mut fresh = Vec::new();
+        for task in delivery.tasks {
+            if self.seen_ids.contains(&task.id) {
+                continue;
+            }
+            self.seen(&task.id);
+            fresh.push(task);
+        }
+        fresh
+    }
+
+    /// Tasks waiting for `peer`.
+    pub fn held_for(&self, peer: &str) -> usize {
+        self.boxes.get(peer).map_or(0, VecDeque::len)
+    }
+
+    pub fn forget(&mut self, peer: &str) {
+        self.peers.remove(peer);
+        self.boxes.remove(peer);
+        self.sending.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Capability;
+
+    fn task(id: &str) -> Task {
+        Task::new(
+            id.to_string(),
+            Capability::Compute(1),
+            1,
+            "origin".to_string(),
+        )
+    }
+
+    #[test]
+    fn tasks_wait_for_absent_peers_until_they_reconnect() {
+        let mut mailbox = Mailbox::new(MailboxConfig {
+            enabled: true,
+            per_peer: 2,
+            ..MailboxConfig::default()
+        });
+        let t0 = Instant::now();
+        assert!(mailbox.holds(0.9) && !mailbox.holds(0.2));
+
+        // Unknown peers get nothing; connected ones hear gossip themselves.
+        assert_eq!(mailbox.hold(&task("a"), t0, |_, _| true), 0);
+        assert!(mailbox.connected("sensor", t0).is_empty());
+        mailbox.connected("busy", t0);
+        mailbox.disconnected("sensor", t0);
+        mailbox.disconnected("busy", t0);
+
+        assert_eq!(mailbox.hold(&task("b"), t0, |peer, _| peer == "sensor"), 1);
+        mailbox.hold(&task("c"), t0, |peer, _| peer == "sensor");
+        mailbox.hold(&task("c"), t0, |peer, _| peer == "sensor");
+        mailbox.hold(&task("d"), t0, |peer, _| peer == "sensor");
+        assert_eq!(mailbox.held_for("sensor"), 2);
+        assert_eq!(mailbox.held_for("busy"), 0);
+
+        // A failed delivery is retried on the next connection.
+        let later = t0 + Duration::from_secs(60);
+        let ids = |tasks: Vec<Task>| tasks.into_iter().map(|t| t.id).collect::<Vec<_>>();
+        assert_eq!(ids(mailbox.connected("sensor", later)), ["c", "d"]);
+        mailbox.failed("sensor");
+        mailbox.disconnected("sensor", later);
+        assert_eq!(ids(mailbox.connected("sensor", later)), ["c", "d"]);
+        mailbox.delivered("sensor");
+        assert_eq!(mailbox.held_for("sensor"), 0);
+
+        // Expired tasks are not delivered, and long-gone peers are forgotten.
+        mailbox.disconnected("sensor", later);
+        mailbox.hold(&task("e"), later, |_, _| true);
+        let expired = later + mailbox.config.ttl;
+        assert!(mailbox.connected("sensor", expired).is_empty());
+        let gone = t0 + mailbox.config.forget_after;
+        assert_eq!(mailbox.hold(&task("f"), gone, |peer, _| peer == "busy"), 0);
+    }
+
+    #[test]
+    fn addressed_tasks_wait_for_their_target_alone() {
+        // Disabled, as by default: nothing else is held.
+        let mut mailbox = Mailbox::default();
+        let t0 = Instant::now();
+        mailbox.address("device", task("update").with_target("device"), t0);
+        mailbox.address("device", task("update").with_target("device"), t0);
+        assert_eq!(mailbox.held_for("device"), 1);
+        assert!(mailbox.due("other", t0).is_empty());
+
+        let sent = mailbox.due("device", t0);
+        assert_eq!(sent.len(), 1);
+        // Nothing more goes out until the first delivery is acknowledged.
+        mailbox.address("device", task("reboot").with_target("device"), t0);
+        assert!(mailbox.due("device", t0).is_empty());
+        mailbox.failed("device");
+        assert_eq!(mailbox.held_for("device"), 2);
+        assert_eq!(mailbox.due("device", t0).len(), 2);
+        mailbox.delivered("device");
+        assert_eq!(mailbox.held_for("device"), 0);
+    }
+
+    #[test]
+    fn deliveries_skip_tasks_already_seen() {
+        let mut mailbox = Mailbox::default();
+        mailbox.seen("a");
+        let fresh = mailbox.accept(MailboxDelivery {
+            tasks: vec![task("a"), task("b"), task("b")],
+        });
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].id, "b");
+    }
+}
@@ -4,15 +4,28 @@
 //! Key concepts:
 //!
 //! - **D parameters**: Target mesh degree (D=6), bounds (D_low=4, D_high=12)
-//! - **Peer scoring**: Energy scores influence mesh membership
+//! - **Peer scoring**: Energy scores influence mesh membership; weights and
+//!   the scoring function itself are pluggable
 //! - **Opportunistic grafting**: Recover from degraded mesh states
 //! - **Flood publishing**: Own messages can bypass mesh for broad fanout
+//! - **Diverse first hops**: Own critical messages go first to peers that
+//!   differ in introducer, address prefix and zone, rotating among them
+//! - **Forward ranking**: With RTT data, latency-sensitive topics forward to
+//!   fast, unloaded peers first and bulk topics to well-used paths
+//! - **Lazy push**: IHAVE gossip shrinks with bandwidth and energy headroom,
+//!   under a per-heartbeat byte cap, announcing urgent messages first
+//! - **Delivery windows**: first deliveries, duplicates and IHAVE outcomes
+//!   are counted over the last minute, giving a gossip efficiency to trend
 //!
 //! This module provides a simulation-friendly mesh layer that can be evaluated
 //! without running a full libp2p swarm.
 
 pub use crate::core::mesh::{
-    MeshConfig, MeshControl, MeshPeer, MeshStats, TopicMesh, PRESSURE_SPIKE_THRESHOLD,
+    ip_prefix, DeliveryCounts, DeliveryWindow, ForwardWeights, MeshConfig, MeshControl,
+    MeshDiagnostics, MeshPeer, MeshStats, OriginFact, PeerDiagnostics, PeerOrigin, PeerScorer,
+    PersistedMesh, PersistedPeer, ScoreWeights, TopicMesh, TrafficClass, WeightedScore,
+    DELIVERY_WINDOW, DISCONNECT_BACKOFF, MAX_WARM_START_AGE, PRESSURE_SPIKE_THRESHOLD, SLOW_RTT,
+    UNKNOWN_ENERGY_SCORE, WARM_START_GRACE,
 };
 
 #[cfg(test)]
@@ -162,4 +175,345 @@ mod tests {
             "spike handling should not create peers implicitly"
         );
     }
+
+    #[test]
+    fn connect_tracks_unknown_peer_and_disconnect_prunes_with_backoff() {
+        let mut mesh = TopicMesh::new("test".to_string(), MeshConfig::default());
+
+        mesh.peer_connected("peer-a");
+        let peer = mesh.known_peers.get("peer-a").unwrap();
+        assert!(peer.connected);
+        assert_eq!(peer.energy_score, UNKNOWN_ENERGY_SCORE);
+
+        mesh.mesh_peers.insert("peer-a".to_string());
+        mesh.peer_disconnected("peer-a");
+
+        assert!(!mesh.mesh_peers.contains("peer-a"));
+        assert!(mesh.backoff.contains_key("peer-a"));
+        assert!(!mesh.known_peers.get("peer-a").unwrap().connected);
+        assert!(
+            !mesh.handle_graft("peer-a"),
+            "disconnected peer stays backed off"
+        );
+    }
+
+    #[test]
+    fn heartbeat_keeps_an_outbound_quota_in_the_mesh() {
+        let mut mesh = TopicMesh::new("test".to_string(), MeshConfig::default());
+        // Strong inbound peers would fill the mesh on score alone.
+        for i in 0..14 {
+            let id = format!("in-{i}");
+            mesh.peer_connected(&id);
+            mesh.update_peer_score(&id, 1.0);
+            mesh.mesh_peers.insert(id);
+        }
+        for i in 0..3 {
+            let id = format!("out-{i}");
+            mesh.peer_dialed(&id);
+            mesh.update_peer_score(&id, 0.3);
+        }
+        mesh.mesh_peers.insert("out-0".to_string());
+
+        let _ = mesh.heartbeat();
+        let stats = mesh.stats();
+        assert_eq!(stats.outbound_peers, 2, "0.3 of d = 6, rounded up");
+        assert!(
+            mesh.mesh_peers.contains("out-0"),
+            "pruning spares the quota"
+        );
+        assert_eq!(stats.inbound_peers, mesh.config.d_high - 1);
+
+        mesh.peer_disconnected("out-0");
+        assert!(!mesh.known_peers["out-0"].outbound);
+    }
+
+    #[test]
+    fn forward_targets_follow_rtt_or_conductivity_by_class() {
+        use std::time::Duration;
+
+        let mut mesh = TopicMesh::new("test".to_string(), MeshConfig::default());
+        for i in 0..8 {
+            let id = format!("peer-{i}");
+            mesh.add_peer(id.clone(), 0.8);
+            mesh.mesh_peers.insert(id);
+        }
+        // Unmeasured meshes forward as before.
+        assert_eq!(mesh.forward_targets(false, TrafficClass::Latency).len(), 8);
+
+        mesh.record_rtt("peer-3", Duration::from_millis(20));
+        mesh.record_rtt("peer-5", Duration::from_millis(900));
+        mesh.known_peers.get_mut("peer-5").unwrap().conductivity = 5.0;
+
+        let fast = mesh.forward_targets(false, TrafficClass::Latency);
+        assert_eq!(fast.len(), mesh.config.d);
+        assert_eq!(fast[0], "peer-3");
+        assert!(!fast.contains(&"peer-5".to_string()));
+
+        let bulk = mesh.forward_targets(false, TrafficClass::Bulk);
+        assert_eq!(bulk[0], "peer-5");
+        assert_eq!(mesh.forward_targets(true, TrafficClass::Latency).len(), 8);
+    }
+
+    #[test]
+    fn first_hops_span_origins_and_rotate() {
+        let mut mesh = TopicMesh::new(
+            "test".to_string(),
+            MeshConfig {
+                diverse_first_hops: 3,
+                ..MeshConfig::default()
+            },
+        );
+        let peers = [
+            ("sybil-0", 0.95, "evil", [10, 0, 0, 1], Some("north")),
+            ("sybil-1", 0.95, "evil", [10, 0, 0, 2], Some("north")),
+            ("sybil-2", 0.95, "evil", [10, 0, 0, 3], Some("north")),
+            ("honest-a", 0.4, "rv-1", [192, 168, 5, 9], Some("south")),
+            ("honest-b", 0.05, "rv-2", [172, 16, 0, 7], None),
+        ];
+        for (id, score, introducer, ip, zone) in peers {
+            mesh.update_peer_score(id, score);
+            mesh.peer_connected(id);
+            mesh.record_origin(id, OriginFact::Introducer(introducer.to_string()));
+            mesh.record_origin(id, OriginFact::Address(ip.into()));
+            mesh.record_origin(id, OriginFact::Zone(zone.map(str::to_string)));
+        }
+        mesh.add_peer("offline".to_string(), 1.0);
+        assert_eq!(
+            mesh.known_peers["sybil-1"].origin.ip_prefix.as_deref(),
+            Some("10.0.0.0/24")
+        );
+
+        let first = mesh.select_first_hops();
+        assert_eq!(first, ["sybil-0", "honest-a", "honest-b"]);
+        let second = mesh.critical_targets(TrafficClass::Latency);
+        assert_eq!(second[..3], ["sybil-1", "honest-a", "honest-b"]);
+        assert!(second.contains(&"sybil-2".to_string()));
+        assert_eq!(mesh.stats().first_hops, second[..3]);
+
+        assert_eq!(
+            ip_prefix("2001:db8:abcd:12::1".parse().unwrap()),
+            "2001:db8:abcd::/48"
+        );
+        mesh.config.diverse_first_hops = 0;
+        assert!(mesh.select_first_hops().is_empty());
+    }
+
+    #[test]
+    fn reconnect_keeps_score_history() {
+        let mut mesh = TopicMesh::new("test".to_string(), MeshConfig::default());
+        mesh.update_peer_score("peer-a", 0.9);
+
+        mesh.peer_connected("peer-a");
+
+        assert_eq!(mesh.known_peers.get("peer-a").unwrap().energy_score, 0.9);
+    }
+
+    #[test]
+    fn penalty_lowers_score_until_it_expires() {
+        let mut mesh = TopicMesh::new("test".to_string(), MeshConfig::default());
+        mesh.add_peer("peer-a".to_string(), 0.9);
+        let before = mesh.known_peers.get("peer-a").unwrap().score();
+
+        mesh.penalize_peer("peer-a", 0.5, std::time::Duration::from_secs(60));
+        let penalized = mesh.known_peers.get("peer-a").unwrap().score();
+        assert!((before - penalized - 0.5).abs() < 1e-6);
+
+        // A shorter repeat penalty does not cut the active one short.
+        mesh.penalize_peer("peer-a", 0.1, std::time::Duration::ZERO);
+        assert_eq!(mesh.known_peers.get("peer-a").unwrap().score(), penalized);
+
+        mesh.known_peers.get_mut("peer-a").unwrap().penalty_until = None;
+        assert_eq!(mesh.known_peers.get("peer-a").unwrap().score(), before);
+    }
+
+    #[test]
+    fn restored_mesh_rejoins_on_first_heartbeat() {
+        use std::time::Duration;
+
+        let mut before = TopicMesh::new("test".to_string(), MeshConfig::default());
+        for i in 0..6 {
+            let id = format!("peer-{}", i);
+            before.peer_connected(&id);
+            before.update_peer_score(&id, 0.9);
+        }
+        let _ = before.heartbeat();
+        before.handle_prune("peer-5", Duration::from_secs(120));
+        before.penalize_peer("peer-0", 0.2, Duration::from_secs(30));
+        let saved = before.persist();
+        let members = saved.mesh_peers.clone();
+        assert!(!members.is_empty());
+
+        let mut after = TopicMesh::new("test".to_string(), MeshConfig::default());
+        after.restore(saved.clone(), Duration::from_secs(45));
+        assert_eq!(after.mesh_size(), members.len());
+        assert!(after.backoff.contains_key("peer-5"));
+        assert!(after.known_peers["peer-0"].penalty_until.is_none());
+
+        let grafts: Vec<String> = after
+            .heartbeat()
+            .into_iter()
+            .filter(|(_, c)| matches!(c, MeshControl::Graft { .. }))
+            .map(|(id, _)| id)
+            .collect();
+        for id in &members {
+            assert!(grafts.contains(id), "{id} not re-grafted");
+        }
+
+        // After a long outage only the scores carry over.
+        let mut cold = TopicMesh::new("test".to_string(), MeshConfig::default());
+        cold.restore(saved, MAX_WARM_START_AGE * 2);
+        assert_eq!(cold.mesh_size(), 0);
+        assert!(cold.backoff.is_empty());
+        assert_eq!(cold.known_peers.len(), 6);
+    }
+
+    #[test]
+    fn ihave_budget_shrinks_with_headroom_and_leads_with_urgent() {
+        let ihaves = |mesh: &mut TopicMesh| -> Vec<Vec<String>> {
+            mesh.heartbeat()
+                .into_iter()
+                .filter_map(|(_, c)| match c {
+                    MeshControl::IHave { message_ids, .. } => Some(message_ids),
+                    _ => None,
+                })
+                .collect()
+        };
+        let mesh_with = |config: MeshConfig| {
+            let mut mesh = TopicMesh::new("test".to_string(), config);
+            for i in 0..20 {
+                mesh.add_peer(format!("peer-{i}"), 0.9);
+            }
+            for i in 0..30 {
+                mesh.record_message("peer-0", &format!("msg-{i:02}"));
+            }
+            mesh
+        };
+
+        let mut full = mesh_with(MeshConfig::default());
+        let sent = ihaves(&mut full);
+        assert_eq!(sent.len(), 6);
+        assert!(sent.iter().all(|ids| ids.len() == 10));
+
+        let mut congested = mesh_with(MeshConfig::default().scale_gossip(0.3));
+        congested.mark_urgent("msg-17");
+        congested.mark_urgent("not-cached");
+        let sent = ihaves(&mut congested);
+        assert_eq!(sent.len(), 2);
+        assert!(sent.iter().all(|ids| ids.len() == 3 && ids[0] == "msg-17"));
+
+        // The byte cap trims the last IHAVE and stops the rest.
+        let tight = MeshConfig {
+            max_ihave_bytes: 200,
+            ..MeshConfig::default()
+        };
+        let sent = ihaves(&mut mesh_with(tight));
+        let bytes: usize = sent
+            .iter()
+            .map(|ids| 32 + 4 + ids.iter().map(|id| id.len() + 3).sum::<usize>())
+            .sum();
+        assert!(sent.len() < 6 && bytes <= 200, "{sent:?}");
+        assert!(ihaves(&mut mesh_with(MeshConfig::default().scale_gossip(0.0))).is_empty());
+    }
+
+    #[test]
+    fn custom_weights_and_scorers_steer_grafting() {
+        use std::sync::Arc;
+
+        // Prefers peers in the local zone, whatever their energy.
+        #[derive(Debug)]
+        struct ZoneAffinity;
+        impl PeerScorer for ZoneAffinity {
+            fn score(&self, peer: &MeshPeer, weights: &ScoreWeights) -> f32 {
+                let affinity = if peer.id.starts_with("near") {
+                    1.0
+                } else {
+                    0.0
+                };
+                peer.weighted_score(weights) * 0.1 + affinity
+            }
+        }
+
+        let peer = MeshPeer::new("p".to_string(), 1.0);
+        let energy_only = ScoreWeights {
+            energy: 1.0,
+            activity: 0.0,
+            conductivity: 0.0,
+            pressure: 0.0,
+            role: 0.0,
+        };
+        assert_eq!(peer.weighted_score(&energy_only), 1.0);
+        assert_eq!(peer.score(), peer.weighted_score(&ScoreWeights::default()));
+
+        let mut mesh = TopicMesh::new("test".to_string(), MeshConfig::default());
+        for i in 0..4 {
+            mesh.add_peer(format!("near-{i}"), 0.2);
+            mesh.add_peer(format!("far-{i}"), 1.0);
+        }
+        mesh.set_scorer(Arc::new(ZoneAffinity));
+        let _ = mesh.heartbeat();
+        assert!(mesh.mesh_peers.iter().all(|id| id.starts_with("near")));
+    }
+
+    #[test]
+    fn diagnostics_dump_sorted_peers_and_running_backoffs() {
+        let mut mesh = TopicMesh::new("test".to_string(), MeshConfig::default());
+        for id in ["c", "a", "b"] {
+            mesh.add_peer(id.to_string(), 0.8);
+        }
+        let _ = mesh.heartbeat();
+        mesh.peer_disconnected("b");
+
+        let dump = mesh.diagnostics();
+        let ids: Vec<&str> = dump.peers.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "c"]);
+        assert_eq!(dump.stats, mesh.stats());
+        assert_eq!(dump.mesh_peers, ["a", "c"]);
+        assert_eq!(dump.backoff.len(), 1);
+        assert_eq!(dump.backoff[0].0, "b");
+
+        let json = serde_json::to_string(&dump).unwrap();
+        assert_eq!(
+            serde_json::from_str::<MeshDiagnostics>(&json).unwrap(),
+            dump
+        );
+    }
+
+    #[test]
+    fn deliveries_are_counted_over_a_sliding_window() {
+        use std::time::{Duration, Instant};
+
+        let mut mesh = TopicMesh::new("test".to_string(), MeshConfig::default());
+        assert_eq!(mesh.stats().gossip_efficiency, None);
+        mesh.add_peer("a".to_string(), 0.8);
+        for id in ["m1", "m2", "m1", "m3"] {
+            mesh.record_message("a", id);
+        }
+        let ihave = MeshControl::IHave {
+            topic: "test".to_string(),
+            message_ids: vec!["m1".to_string(), "m9".to_string()],
+        };
+        assert!(mesh.handle_control("a", ihave).is_some());
+
+        let stats = mesh.stats();
+        assert_eq!(
+            stats.deliveries,
+            DeliveryCounts {
+                first_deliveries: 3,
+                duplicates: 1,
+                ihave_hits: 1,
+                ihave_misses: 1,
+            }
+        );
+        assert_eq!(stats.gossip_efficiency, Some(0.75));
+        assert_eq!(stats.duplicate_count, 1);
+
+        let t0 = Instant::now();
+        let mut window = DeliveryWindow::default();
+        window.at(t0).duplicates += 2;
+        window.at(t0 + Duration::from_secs(30)).first_deliveries += 1;
+        assert_eq!(window.counts(t0 + Duration::from_secs(45)).duplicates, 2);
+        let later = window.counts(t0 + DELIVERY_WINDOW);
+        assert_eq!((later.duplicates, later.first_deliveries), (0, 1));
+        assert_eq!(later.efficiency(), Some(1.0));
+    }
 }
@@ -0,0 +1,332 @@
+//! Mesh state behind a single owner task.
+//!
+//! While `SporeNode::run_for` is running, every mesh mutation goes through a
+//! [`MeshHandle`] as a [`MeshCommand`] on an unbounded channel. The actor task
+//! drains whatever is queued, applies the batch under one lock of the node's
+//! `TopicMesh`, and publishes a [`MeshSnapshot`] on a watch channel. The run
+//! loop therefore never blocks on the mesh, and readers such as bridges and
+//! metrics poll the snapshot instead of taking the lock.
+//!
+//! The `Arc<Mutex<TopicMesh>>` stays the storage, so callers that own the
+//! node outside `run_for` (tests, simulations) can still inspect it directly.
+
+use crate::core::NodeRole;
+use crate::mesh::{MeshConfig, MeshControl, MeshStats, OriginFact, TopicMesh};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+
+/// Cheap view of the mesh, refreshed after every applied batch. `stats` and
+/// `ranked` are recomputed on heartbeats only.
+#[derive(Debug, Clone, Default)]
+pub struct MeshSnapshot {
+    pub local_pressure: f32,
+    pub pulse_phase: f32,
+    pub stats: MeshStats,
+    /// The best-scored known peers, best first, at most `RANKED_PEERS`.
+    pub ranked: Vec<(String, f32)>,
+}
+
+/// Peers kept in `MeshSnapshot::ranked`.
+pub const RANKED_PEERS: usize = 64;
+
+#[derive(Debug)]
+pub enum MeshCommand {
+    PeerConnected(String),
+    PeerDialed(String),
+    PeerDisconnected(String),
+    MarkSeen(String),
+    UpdateScore {
+        peer: String,
+        energy_score: f32,
+    },
+    UpdateRole {
+        peer: String,
+        role: Option<NodeRole>,
+    },
+    RecordMessage {
+        peer: String,
+        msg_id: String,
+    },
+    MarkUrgent(String),
+    RecordOrigin {
+        peer: String,
+        fact: OriginFact,
+    },
+    Spike {
+        source: String,
+        intensity: u8,
+    },
+    Penalize {
+        peer: String,
+        penalty: f32,
+        duration: Duration,
+    },
+    SetReputation {
+        peer: String,
+        deduction: f32,
+    },
+    /// Advance the pulse and reply with the new phase.
+    TickPulse {
+        delta: f32,
+        reply: oneshot::Sender<f32>,
+    },
+    /// Run a mesh heartbeat under `config` and reply with the controls to send.
+    Heartbeat {
+        config: MeshConfig,
+        reply: oneshot::Sender<Vec<(String, MeshControl)>>,
+    },
+    /// Recompute local pressure from the message backlog.
+    RefreshPressure,
+    Control {
+        peer: String,
+        control: MeshControl,
+        reply: oneshot::Sender<Option<MeshControl>>,
+    },
+}
+
+/// Sending half of the mesh actor. Dropping every handle stops the actor
+/// once its queue is drained.
+#[derive(Debug, Clone)]
+pub struct MeshHandle {
+    commands: mpsc::UnboundedSender<MeshCommand>,
+    snapshot: watch::Receiver<MeshSnapshot>,
+}
+
+impl MeshHandle {
+    /// Start the actor on the current tokio runtime. Snapshots are published
+    /// on `snapshots`.
+    pub fn spawn(
+        mesh: Arc<Mutex<TopicMesh>>,
+        snapshots: watch::Sender<MeshSnapshot>,
+    ) -> (Self, JoinHandle<()>) {
+        let (commands, rx) = mpsc::unbounded_channel();
+        let snapshot = snapshots.subscribe();
+        let task = tokio::spawn(run(mesh, rx, snapshots));
+        (Self { commands, snapshot }, task)
+    }
+
+    fn send(&self, command: MeshCommand) {
+        // The actor only exits once every handle is gone.
+        let _ = self.commands.send(command);
+    }
+
+    pub fn peer_connected(&self, peer: &str) {
+        self.send(MeshCommand::PeerConnected(peer.to_string()));
+    }
+
+    pub fn peer_dialed(&self, peer: &str) {
+        self.send(MeshCommand::PeerDialed(peer.to_string()));
+    }
+
+    pub fn peer_disconnected(&self, peer: &str) {
+        self.send(MeshCommand::PeerDisconnected(peer.to_string()));
+    }
+
+    pub fn mark_seen(&self, peer: &str) {
+        self.send(MeshCommand::MarkSeen(peer.to_string()));
+    }
+
+    pub fn update_peer_score(&self, peer: &str, energy_score: f32) {
+        self.send(MeshCommand::UpdateScore {
+            peer: peer.to_string(),
+            energy_score,
+        });
+    }
+
+    pub fn update_peer_role(&self, peer: &str, role: Option<NodeRole>) {
+        self.send(MeshCommand::UpdateRole {
+            peer: peer.to_string(),
+            role,
+        });
+    }
+
+    pub fn record_message(&self, peer: &str, msg_id: &str) {
+        self.send(MeshCommand::RecordMessage {
+            peer: peer.to_string(),
+            msg_id: msg_id.to_string(),
+        });
+    }
+
+    pub fn mark_urgent(&self, msg_id: &str) {
+        self.send(MeshCommand::MarkUrgent(msg_id.to_string()));
+    }
+
+    pub fn record_origin(&self, peer: &str, fact: OriginFact) {
+        self.send(MeshCommand::RecordOrigin {
+            peer: peer.to_string(),
+            fact,
+        });
+    }
+
+    pub fn handle_spike(&self, source: &str, intensity: u8) {
+        self.send(MeshCommand::Spike {
+            source: source.to_string(),
+            intensity,
+        });
+    }
+
+    pub fn penalize_peer(&self, peer: &str, penalty: f32, duration: Duration) {
+        self.send(MeshCommand::Penalize {
+            peer: peer.to_string(),
+            penalty,
+            duration,
+        });
+    }
+
+    pub fn set_peer_reputation(&self, peer: &str, deduction: f32) {
+        self.send(MeshCommand::SetReputation {
+            peer: peer.to_string(),
+            deduction,
+        });
+    }
+
+    pub fn refresh_pressure(&self) {
+        self.send(MeshCommand::RefreshPressure);
+    }
+
+    /// Phase after advancing the pulse, or `None` if the actor has stopped.
+    pub async fn tick_pulse(&self, delta: f32) -> Option<f32> {
+        let (reply, rx) = oneshot::channel();
+        self.send(MeshCommand::TickPulse { delta, reply });
+        rx.await.ok()
+    }
+
+    pub async fn heartbeat(&self, config: MeshConfig) -> Vec<(String, MeshControl)> {
+        let (reply, rx) = oneshot::channel();
+        self.send(MeshCommand::Heartbeat { config, reply });
+        rx.await.unwrap_or_default()
+    }
+
+    pub async fn handle_control(&self, peer: &str, control: MeshControl) -> Option<MeshControl> {
+        let (reply, rx) = oneshot::channel();
+        self.send(MeshCommand::Control {
+            peer: peer.to_string(),
+            control,
+            reply,
+        });
+        rx.await.ok().flatten()
+    }
+
+    /// The state as of the last applied batch.
+    pub fn snapshot(&self) -> MeshSnapshot {
+        self.snapshot.borrow().clone()
+    }
+}
+
+async fn run(
+    mesh: Arc<Mutex<TopicMesh>>,
+    mut rx: mpsc::UnboundedReceiver<MeshCommand>,
+    snapshots: watch::Sender<MeshSnapshot>,
+) {
+    let mut batch = Vec::new();
+    while rx.recv_many(&mut batch, 256).await > 0 {
+        apply_batch(&mesh, &mut batch, &snapshots);
+    }
+}
+
+fn apply_batch(
+    mesh: &Mutex<TopicMesh>,
+    batch: &mut Vec<MeshCommand>,
+    snapshots: &watch::Sender<MeshSnapshot>,
+) {
+    let mut mesh = mesh.lock().unwrap();
+    let mut stats = None;
+    for command in batch.drain(..) {
+        apply(&mut mesh, command, &mut stats);
+    }
+    let (stats, ranked) = match stats {
+        Some(stats) => (stats, mesh.ranked_peers(RANKED_PEERS)),
+        None => {
+            let previous = snapshots.borrow();
+            (previous.stats.clone(), previous.ranked.clone())
+        }
+    };
+    let snapshot = MeshSnapshot {
+        local_pressure: mesh.local_pressure,
+        pulse_phase: mesh.pulse_phase,
+        stats,
+        ranked,
+    };
+    drop(mesh);
+    snapshots.send_replace(snapshot);
+}
+
+fn apply(mesh: &mut TopicMesh, command: MeshCommand, stats: &mut Option<MeshStats>) {
+    match command {
+        MeshCommand::PeerConnected(peer) => mesh.peer_connected(&peer),
+        MeshCommand::PeerDialed(peer) => mesh.peer_dialed(&peer),
+        MeshCommand::PeerDisconnected(peer) => mesh.peer_disconnected(&peer),
+        MeshCommand::MarkSeen(peer) => mesh.mark_seen(&peer),
+        MeshCommand::UpdateScore { peer, energy_score } => {
+            mesh.update_peer_score(&peer, energy_score)
+        }
+        MeshCommand::UpdateRole { peer, role } => mesh.update_peer_role(&peer, role),
+        MeshCommand::RecordMessage { peer, msg_id } => mesh.record_message(&peer, &msg_id),
+        MeshCommand::MarkUrgent(msg_id) => mesh.mark_urgent(&msg_id),
+        MeshCommand::RecordOrigin { peer, fact } => mesh.record_origin(&peer, fact),
+        MeshCommand::Spike { source, intensity } => mesh.handle_spike(&source, intensity),
+        MeshCommand::Penalize {
+            peer,
+            penalty,
+            duration,
+        } => mesh.penalize_peer(&peer, penalty, duration),
+        MeshCommand::SetReputation { peer, deduction } => {
+            mesh.set_peer_reputation(&peer, deduction)
+        }
+        MeshCommand::TickPulse { delta, reply } => {
+            mesh.tick_pulse(delta);
+            let _ = reply.send(mesh.pulse_phase);
+        }
+        MeshCommand::Heartbeat { config, reply } => {
+            mesh.config = config;
+            let _ = reply.send(mesh.heartbeat());
+            *stats = Some(mesh.stats());
+        }
+        MeshCommand::RefreshPressure => {
+            // Backlog of cached message ids is the pressure proxy.
+            let backlog = mesh.message_cache.len() as f32;
+            mesh.set_pressure(backlog * 0.1);
+        }
+        MeshCommand::Control {
+            peer,
+            control,
+            reply,
+        } => {
+            let _ = reply.send(mesh.handle_control(&peer, control));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn commands_apply_in_order_and_drain_on_shutdown() {
+        let mesh = Arc::new(Mutex::new(TopicMesh::new(
+            "t".to_string(),
+            MeshConfig::default(),
+        )));
+        let (snapshots, _) = watch::channel(MeshSnapshot::default());
+        let (handle, task) = MeshHandle::spawn(mesh.clone(), snapshots);
+
+        for i in 0..8 {
+            handle.update_peer_score(&format!("peer-{i}"), 0.9);
+        }
+        let controls = handle.heartbeat(MeshConfig::default()).await;
+        assert!(!controls.is_empty());
+        assert_eq!(handle.snapshot().stats.known_peers, 8);
+        assert_eq!(handle.snapshot().ranked.len(), 8);
+
+        handle.record_message("peer-0", "m1");
+        handle.refresh_pressure();
+        drop(handle);
+        task.await.unwrap();
+
+        let mesh = mesh.lock().unwrap();
+        assert!(mesh.message_cache.contains("m1"));
+        assert!((mesh.local_pressure - 0.1).abs() < 1e-6);
+    }
+}
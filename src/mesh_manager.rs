@@ -0,0 +1,212 @@
+//! One [`TopicMesh`] per topic, with heartbeats run side by side.
+//!
+//! Each topic's heartbeat runs on the blocking pool after its own random
+//! offset within `jitter`, so a node with many topics neither stalls the
+//! runtime nor emits every topic's control burst in the same instant. The
+//! controls gathered in one tick are capped: GRAFT and PRUNE are always kept,
+//! since the local mesh has already applied them, and IHAVE/IWANT gossip is
+//! shared round-robin across topics in whatever budget remains.
+
+use crate::mesh::{MeshConfig, MeshControl, TopicMesh};
+use rand::Rng;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshManagerConfig {
+    /// Upper bound on controls emitted per tick across all topics.
+    pub max_controls_per_tick: usize,
+    /// Each topic's heartbeat starts at a random offset in `0..jitter`.
+    pub jitter: Duration,
+}
+
+impl Default for MeshManagerConfig {
+    fn default() -> Self {
+        Self {
+            max_controls_per_tick: 64,
+            jitter: Duration::from_millis(200),
+        }
+    }
+}
+
+/// A control addressed to `peer` for `topic`.
+#[derive(Debug, Clone)]
+pub struct TopicControl {
+    pub topic: String,
+    pub peer: String,
+    pub control: MeshControl,
+}
+
+#[derive(Debug, Default)]
+pub struct MeshManager {
+    pub config: MeshManagerConfig,
+    meshes: BTreeMap<String, Arc<Mutex<TopicMesh>>>,
+}
+
+impl MeshManager {
+    pub fn new(config: MeshManagerConfig) -> Self {
+        Self {
+            config,
+            meshes: BTreeMap::new(),
+        }
+    }
+
+    /// The mesh for `topic`, created with `config` if it does not exist yet.
+    pub fn join(&mut self, topic: &str, config: MeshConfig) -> Arc<Mutex<TopicMesh>> {
+        self.meshes
+            .entry(topic.to_string())
+            .or_insert_with(|| Arc::new(Mutex::new(TopicMesh::new(topic.to_string(), config))))
+            .clone()
+    }
+
+    pub fn leave(&mut self, topic: &str) -> Option<Arc<Mutex<TopicMesh>>> {
+        self.meshes.remove(topic)
+    }
+
+    pub fn get(&self, topic: &str) -> Option<&Arc<Mutex<TopicMesh>>> {
+        self.meshes.get(topic)
+    }
+
+    pub fn topics(&self) -> impl Iterator<Item = &str> {
+        self.meshes.keys().map(String::as_str)
+    }
+
+    /// Run one heartbeat for every topic concurrently and return the capped
+    /// set of controls to send.
+    pub async fn heartbeat(&self) -> Vec<TopicControl> {
+        let mut rng = rand::rng();
+        let mut tasks = JoinSet::new();
+        for (topic, mesh) in &self.meshes {
+            let offset = if self.config.jitter.is_zero() {
+                Duration::ZERO
+            } else {
+                self.config.jitter.mul_f64(rng.random::<f64>())
+            };
+            let (topic, mesh) = (topic.clone(), mesh.clone());
+            tasks.spawn(async move {
+                tokio::time::sleep(offset).await;
+                let controls =
+                    tokio::task::spawn_blocking(move || mesh.lock().unwrap().heartbeat()).await;
+                (topic, controls.unwrap_or_default())
+            });
+        }
+
+        let mut per_topic = BTreeMap::new();
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok((topic, controls)) = joined {
+                per_topic.insert(topic, controls);
+            }
+        }
+        cap_controls(per_topic, self.config.max_controls_per_tick)
+    }
+}
+
+fn is_membership(control: &MeshControl) -> bool {
+    matches!(
+        control,
+        MeshControl::Graft { .. } | MeshControl::Prune { .. }
+    )
+}
+
+fn cap_controls(
+    per_topic: BTreeMap<String, Vec<(String, MeshControl)>>,
+    max: usize,
+) -> Vec<TopicControl> {
+    let mut kept = Vec::new();
+    let mut gossip: Vec<VecDeque<TopicControl>> = Vec::new();
+    for (topic, controls) in per_topic {
+        let mut queue = VecDeque::new();
+        for (peer, control) in controls {
+            let control = TopicControl {
+                topic: topic.clone(),
+                peer,
+                control,
+            };
+            if is_membership(&control.control) {
+                kept.push(control);
+            } else {
+                queue.push_back(control);
+            }
+        }
+        gossip.push(queue);
+    }
+
+    let mut budget = max.saturating_sub(kept.len());
+    while budget > 0 && gossip.iter().any(|q| !q.is_empty()) {
+        for queue in &mut gossip {
+            if budget == 0 {
+                break;
+            }
+            if let Some(control) = queue.pop_front() {
+                kept.push(control);
+                budget -= 1;
+            }
+        }
+    }
+    let dropped: usize = gossip.iter().map(VecDeque::len).sum();
+    if dropped > 0 {
+        tracing::debug!(dropped, "Control cap reached; gossip trimmed");
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn three_topics(cap: usize) -> MeshManager {
+        let mut manager = MeshManager::new(MeshManagerConfig {
+            max_controls_per_tick: cap,
+            jitter: Duration::from_millis(5),
+        });
+        for topic in ["a", "b", "c"] {
+            let mesh = manager.join(topic, MeshConfig::default());
+            let mut mesh = mesh.lock().unwrap();
+            for i in 0..20 {
+                mesh.add_peer(format!("{topic}-peer-{i}"), 0.9);
+            }
+            mesh.record_message("origin", "m1");
+        }
+        manager
+    }
+
+    #[tokio::test]
+    async fn every_topic_beats_and_membership_survives_the_cap() {
+        let manager = three_topics(0);
+        let controls = manager.heartbeat().await;
+
+        let mesh_sizes: usize = manager
+            .topics()
+            .map(|t| manager.get(t).unwrap().lock().unwrap().mesh_size())
+            .sum();
+        assert!(mesh_sizes > 0);
+        assert_eq!(controls.len(), mesh_sizes, "only grafts under a zero cap");
+        assert!(controls.iter().all(|c| is_membership(&c.control)));
+        for topic in ["a", "b", "c"] {
+            assert!(controls.iter().any(|c| c.topic == topic));
+        }
+    }
+
+    #[tokio::test]
+    async fn gossip_budget_is_shared_across_topics() {
+        let manager = three_topics(usize::MAX);
+        let uncapped = manager.heartbeat().await;
+        let grafts = uncapped
+            .iter()
+            .filter(|c| is_membership(&c.control))
+            .count();
+        assert!(uncapped.len() > grafts + 3, "expected IHAVE gossip");
+
+        let manager = three_topics(grafts + 3);
+        let capped = manager.heartbeat().await;
+        let gossip: Vec<_> = capped
+            .iter()
+            .filter(|c| !is_membership(&c.control))
+            .map(|c| c.topic.as_str())
+            .collect();
+        assert_eq!(capped.len(), grafts + 3);
+        assert_eq!(gossip, ["a", "b", "c"]);
+    }
+}
@@ -3,11 +3,66 @@
 //! Separates the network behavior (GossipSub, bio-inspired mesh) from the
 //! agentic Spore logic.
 
+use crate::admission::{Credential, JoinRequest, JoinResponse, JOIN_PROTOCOL};
+use crate::catalog::{CatalogRequest, CatalogSnapshot, CATALOG_PROTOCOL};
+use crate::core::{PowerMode, Task};
+use crate::did;
 use crate::eval::MetricsCollector;
-use crate::mesh::{TopicMesh, PRESSURE_SPIKE_THRESHOLD};
-use libp2p::{gossipsub, identity, noise, swarm::NetworkBehaviour, tcp, yamux, Multiaddr, Swarm};
+use crate::introduction::{Introduction, IntroductionRequest, SignedPeer, INTRODUCTION_PROTOCOL};
+use crate::mailbox::{MailboxAck, MailboxDelivery, MAILBOX_PROTOCOL};
+use crate::mesh::{TopicMesh, TrafficClass};
+use crate::ota::{ChunkRequest, ChunkResponse, CHUNK_PROTOCOL};
+use crate::sequence::{ResendRequest, ResendResponse, Sequencer, SEQUENCE_PROTOCOL};
+use crate::topic::{Received, Subscription, Topic};
+use crate::trace::{self, Trace};
+use crate::util::RetryPolicy;
+use crate::wire::{
+    Batcher, CompressionPolicy, Envelope, Outbox, OutboxEntry, Priority, SendDecision, SendPolicy,
+};
+use libp2p::{
+    futures::{stream, Stream, StreamExt},
+    gossipsub, identity,
+    multiaddr::Protocol,
+    noise, rendezvous, request_response,
+    swarm::{
+        behaviour::toggle::Toggle,
+        dial_opts::{DialOpts, PeerCondition},
+        NetworkBehaviour,
+    },
+    tcp, yamux, Multiaddr, PeerId, StreamProtocol, Swarm,
+};
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub use crate::spike::Spike;
+
+/// How often a long-running node re-dials its bootstrap entries.
+///
+/// `/dns4`, `/dns6`, and `/dnsaddr` components are resolved at dial time, so
+/// re-dialing also picks up bootstrap hosts whose IPs have changed.
+pub const BOOTSTRAP_REDIAL_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Rendezvous namespace nodes register under unless `set_namespace` moves them.
+pub const DEFAULT_RENDEZVOUS_NAMESPACE: &str = "hypha";
+
+/// Cap on publishes waiting for a retry; the oldest are dropped first.
+const MAX_PENDING_RETRIES: usize = 64;
+
+/// Cap on discovered peers remembered with their rendezvous point.
+const MAX_PENDING_INTRODUCTIONS: usize = 1024;
+
+/// A publish gossipsub refused, waiting for its next attempt.
+#[derive(Debug)]
+struct PendingRetry {
+    topic: gossipsub::TopicHash,
+    bytes: Vec<u8>,
+    attempts: u32,
+    due: Instant,
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum NetProfile {
@@ -20,6 +75,50 @@ pub enum NetProfile {
     Mobile,
 }
 
+/// Swarm construction options beyond the transport profile.
+#[derive(Debug, Clone, Default)]
+pub struct NetOptions {
+    pub profile: NetProfile,
+    /// Also act as a rendezvous point that other nodes register with and
+    /// query, e.g. on a publicly reachable seed node.
+    pub rendezvous_server: bool,
+    /// Topics whose gossipsub message ids hash the content; see
+    /// [`message_id`]. Every node of a network must agree on the set.
+    pub content_ids: HashSet<TopicKind>,
+}
+
+/// Gossipsub message id of `message`.
+///
+/// Gossipsub's default id is the source peer and sequence number, so the
+/// same payload published twice counts as two messages. On the
+/// `content_ids` topics the id is a SHA-256 of the topic and payload
+/// instead, and identical content collapses into one message at the
+/// protocol layer, whoever sends it, for as long as gossipsub remembers
+/// seen ids. A payload legitimately repeated within that window is dropped
+/// too, so only topics whose payloads differ per publish should hash.
+pub fn message_id(
+    content_ids: &HashSet<TopicKind>,
+    message: &gossipsub::Message,
+) -> gossipsub::MessageId {
+    let kind = TopicKind::from_topic_name(message.topic.as_str());
+    if kind.is_some_and(|kind| content_ids.contains(&kind)) {
+        let digest = Sha256::new()
+            .chain_update(message.topic.as_str())
+            .chain_update(&message.data)
+            .finalize();
+        return gossipsub::MessageId::new(&digest);
+    }
+    // Gossipsub's default, spelled out.
+    let mut id = match message.source {
+        Some(source) => source.to_base58(),
+        None => PeerId::from_bytes(&[0, 1, 0])
+            .expect("valid peer id")
+            .to_base58(),
+    };
+    id.push_str(&message.sequence_number.unwrap_or_default().to_string());
+    gossipsub::MessageId::from(id)
+}
+
 #[derive(NetworkBehaviour)]
 #[behaviour(to_swarm = "MyceliumEvent")]
 pub struct MyceliumBehaviour {
@@ -27,6 +126,20 @@ pub struct MyceliumBehaviour {
     pub identify: libp2p::identify::Behaviour,
     pub relay_client: libp2p::relay::client::Behaviour,
     pub dcutr: libp2p::dcutr::Behaviour,
+    pub rendezvous: rendezvous::client::Behaviour,
+    pub rendezvous_server: Toggle<rendezvous::server::Behaviour>,
+    /// Admission handshake; see `crate::admission`.
+    pub join: request_response::json::Behaviour<JoinRequest, JoinResponse>,
+    /// Catalog snapshots for peers that missed a delta; see `crate::catalog`.
+    pub catalog: request_response::json::Behaviour<CatalogRequest, CatalogSnapshot>,
+    /// Tasks held for peers while they were away; see `crate::mailbox`.
+    pub mailbox: request_response::json::Behaviour<MailboxDelivery, MailboxAck>,
+    /// Resends of sequenced messages a peer missed; see `crate::sequence`.
+    pub sequence: request_response::json::Behaviour<ResendRequest, ResendResponse>,
+    /// Firmware chunks pulled from peers; see `crate::ota`.
+    pub chunks: request_response::json::Behaviour<ChunkRequest, ChunkResponse>,
+    /// Samples of known peers for sparse boots; see `crate::introduction`.
+    pub introduce: request_response::json::Behaviour<IntroductionRequest, Introduction>,
 }
 
 #[derive(Debug)]
@@ -35,6 +148,14 @@ pub enum MyceliumEvent {
     Identify(Box<libp2p::identify::Event>),
     RelayClient(libp2p::relay::client::Event),
     Dcutr(libp2p::dcutr::Event),
+    Rendezvous(rendezvous::client::Event),
+    RendezvousServer(Box<rendezvous::server::Event>),
+    Join(request_response::Event<JoinRequest, JoinResponse>),
+    Catalog(request_response::Event<CatalogRequest, CatalogSnapshot>),
+    Mailbox(request_response::Event<MailboxDelivery, MailboxAck>),
+    Sequence(request_response::Event<ResendRequest, ResendResponse>),
+    Chunks(request_response::Event<ChunkRequest, ChunkResponse>),
+    Introduce(request_response::Event<IntroductionRequest, Introduction>),
 }
 
 impl From<gossipsub::Event> for MyceliumEvent {
@@ -61,25 +182,264 @@ impl From<libp2p::dcutr::Event> for MyceliumEvent {
     }
 }
 
-/// Prototype pressure spike telemetry.
+impl From<rendezvous::client::Event> for MyceliumEvent {
+    fn from(event: rendezvous::client::Event) -> Self {
+        MyceliumEvent::Rendezvous(event)
+    }
+}
+
+impl From<rendezvous::server::Event> for MyceliumEvent {
+    fn from(event: rendezvous::server::Event) -> Self {
+        MyceliumEvent::RendezvousServer(Box::new(event))
+    }
+}
+
+impl From<request_response::Event<JoinRequest, JoinResponse>> for MyceliumEvent {
+    fn from(event: request_response::Event<JoinRequest, JoinResponse>) -> Self {
+        MyceliumEvent::Join(event)
+    }
+}
+
+impl From<request_response::Event<CatalogRequest, CatalogSnapshot>> for MyceliumEvent {
+    fn from(event: request_response::Event<CatalogRequest, CatalogSnapshot>) -> Self {
+        MyceliumEvent::Catalog(event)
+    }
+}
+
+impl From<request_response::Event<MailboxDelivery, MailboxAck>> for MyceliumEvent {
+    fn from(event: request_response::Event<MailboxDelivery, MailboxAck>) -> Self {
+        MyceliumEvent::Mailbox(event)
+    }
+}
+
+impl From<request_response::Event<ResendRequest, ResendResponse>> for MyceliumEvent {
+    fn from(event: request_response::Event<ResendRequest, ResendResponse>) -> Self {
+        MyceliumEvent::Sequence(event)
+    }
+}
+
+impl From<request_response::Event<ChunkRequest, ChunkResponse>> for MyceliumEvent {
+    fn from(event: request_response::Event<ChunkRequest, ChunkResponse>) -> Self {
+        MyceliumEvent::Chunks(event)
+    }
+}
+
+impl From<request_response::Event<IntroductionRequest, Introduction>> for MyceliumEvent {
+    fn from(event: request_response::Event<IntroductionRequest, Introduction>) -> Self {
+        MyceliumEvent::Introduce(event)
+    }
+}
+
+impl MyceliumBehaviour {
+    fn new(
+        key: &identity::Keypair,
+        relay_client: libp2p::relay::client::Behaviour,
+        options: &NetOptions,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let content_ids = options.content_ids.clone();
+        let gossipsub_config = gossipsub::ConfigBuilder::default()
+            .validation_mode(gossipsub::ValidationMode::Strict)
+            .message_id_fn(move |message| message_id(&content_ids, message))
+            .build()?;
+        let mut identify_config =
+            libp2p::identify::Config::new("/hypha/1.0.0".to_string(), key.public());
+        // Advertise the node's did:key so peers can map it to this peer id.
+        if let Some(did) = did::did_of_peer(&key.public().to_peer_id()) {
+            identify_config = identify_config.with_agent_version(did::agent_version(&did));
+        }
+
+        Ok(Self {
+            gossipsub: gossipsub::Behaviour::new(
+                gossipsub::MessageAuthenticity::Signed(key.clone()),
+                gossipsub_config,
+            )?,
+            identify: libp2p::identify::Behaviour::new(identify_config),
+            relay_client,
+            dcutr: libp2p::dcutr::Behaviour::new(key.public().to_peer_id()),
+            rendezvous: rendezvous::client::Behaviour::new(key.clone()),
+            rendezvous_server: options
+                .rendezvous_server
+                .then(|| rendezvous::server::Behaviour::new(rendezvous::server::Config::default()))
+                .into(),
+            join: request_response::json::Behaviour::new(
+                [(
+                    StreamProtocol::new(JOIN_PROTOCOL),
+                    request_response::ProtocolSupport::Full,
+                )],
+                request_response::Config::default(),
+            ),
+            catalog: request_response::json::Behaviour::new(
+                [(
+                    StreamProtocol::new(CATALOG_PROTOCOL),
+                    request_response::ProtocolSupport::Full,
+                )],
+                request_response::Config::default(),
+            ),
+            mailbox: request_response::json::Behaviour::new(
+                [(
+                    StreamProtocol::new(MAILBOX_PROTOCOL),
+                    request_response::ProtocolSupport::Full,
+                )],
+                request_response::Config::default(),
+            ),
+            sequence: request_response::json::Behaviour::new(
+                [(
+                    StreamProtocol::new(SEQUENCE_PROTOCOL),
+                    request_response::ProtocolSupport::Full,
+                )],
+                request_response::Config::default(),
+            ),
+            chunks: request_response::json::Behaviour::new(
+                [(
+                    StreamProtocol::new(CHUNK_PROTOCOL),
+                    request_response::ProtocolSupport::Full,
+                )],
+                request_response::Config::default(),
+            ),
+            introduce: request_response::json::Behaviour::new(
+                [(
+                    StreamProtocol::new(INTRODUCTION_PROTOCOL),
+                    request_response::ProtocolSupport::Full,
+                )],
+                request_response::Config::default(),
+            ),
+        })
+    }
+}
+
+/// The gossip topics a node can join.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum TopicKind {
+    Status,
+    Control,
+    Task,
+    Spike,
+    SharedState,
+    Sensor,
+    Quorum,
+    Departure,
+}
+
+impl TopicKind {
+    pub const ALL: [TopicKind; 8] = [
+        TopicKind::Status,
+        TopicKind::Control,
+        TopicKind::Task,
+        TopicKind::Spike,
+        TopicKind::SharedState,
+        TopicKind::Sensor,
+        TopicKind::Quorum,
+        TopicKind::Departure,
+    ];
+
+    /// Gossip topic name used when no namespace is set.
+    pub fn base_name(self) -> &'static str {
+        match self {
+            TopicKind::Status => "hypha_energy_status",
+            TopicKind::Control => "hypha_mesh_control",
+            TopicKind::Task => "hypha_task_stream",
+            TopicKind::Spike => "hypha_spikes",
+            TopicKind::SharedState => "hypha_global_state",
+            TopicKind::Sensor => "hypha_sensor_readings",
+            TopicKind::Quorum => "hypha_quorum",
+            TopicKind::Departure => "hypha_departures",
+        }
+    }
+
+    /// The kind a gossip topic name refers to, bare or inside a namespace.
+    /// Task shards, `<task topic>.<shard>`, count as the task topic.
+    pub fn from_topic_name(name: &str) -> Option<TopicKind> {
+        let base = name.rsplit('/').next()?;
+        let unsharded = match base.rsplit_once('.') {
+            Some((task, shard))
+                if task == TopicKind::Task.base_name() && shard.parse::<u32>().is_ok() =>
+            {
+                task
+            }
+            _ => base,
+        };
+        TopicKind::ALL
+            .into_iter()
+            .find(|kind| kind.base_name() == unsharded)
+    }
+
+    /// Gossip topic name inside `namespace`, e.g. `cluster-a/hypha_spikes`.
+    pub fn namespaced_name(self, namespace: &str) -> String {
+        format!("{namespace}/{}", self.base_name())
+    }
+
+    /// How forward targets are ranked for this topic.
+    pub fn traffic_class(self) -> TrafficClass {
+        match self {
+            TopicKind::Control
+            | TopicKind::Task
+            | TopicKind::Spike
+            | TopicKind::Quorum
+            | TopicKind::Departure => TrafficClass::Latency,
+            TopicKind::Status | TopicKind::SharedState | TopicKind::Sensor => TrafficClass::Bulk,
+        }
+    }
+}
+
+/// Maps a `PowerMode` to the set of gossip topics a node joins.
 ///
-/// This is not a typed, authenticated alert vocabulary. ADR-0006 keeps
-/// action-triggering alerts out of this primitive channel.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
-pub struct Spike {
-    pub source: String,
-    pub intensity: u8,  // 0-255
-    pub pattern_id: u8, // reserved prototype pattern slot
+/// Spikes are always joined, whatever the configured sets say, so that even a
+/// critical node still hears pressure telemetry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscriptionPolicy {
+    pub normal: Vec<TopicKind>,
+    pub low_battery: Vec<TopicKind>,
+    pub critical: Vec<TopicKind>,
+}
+
+impl Default for SubscriptionPolicy {
+    fn default() -> Self {
+        Self {
+            normal: TopicKind::ALL.to_vec(),
+            // Shared-state sync is the bulkiest stream; drop it first.
+            low_battery: vec![
+                TopicKind::Status,
+                TopicKind::Control,
+                TopicKind::Task,
+                TopicKind::Spike,
+                TopicKind::Sensor,
+                TopicKind::Quorum,
+                TopicKind::Departure,
+            ],
+            // Quorum votes are small and may carry the decision to shut down;
+            // departures are rare and let us prune a dying peer early.
+            critical: vec![
+                TopicKind::Status,
+                TopicKind::Control,
+                TopicKind::Spike,
+                TopicKind::Quorum,
+                TopicKind::Departure,
+            ],
+        }
+    }
 }
 
-impl Spike {
-    pub fn affects_mesh_pressure(&self) -> bool {
-        self.intensity > PRESSURE_SPIKE_THRESHOLD
+impl SubscriptionPolicy {
+    /// Topics to join in `mode`, in `TopicKind::ALL` order.
+    pub fn topics_for(&self, mode: &PowerMode) -> Vec<TopicKind> {
+        let configured = match mode {
+            PowerMode::Normal => &self.normal,
+            PowerMode::LowBattery => &self.low_battery,
+            PowerMode::Critical => &self.critical,
+        };
+        TopicKind::ALL
+            .into_iter()
+            .filter(|kind| *kind == TopicKind::Spike || configured.contains(kind))
+            .collect()
     }
 }
 
 pub struct Mycelium {
     pub swarm: Swarm<MyceliumBehaviour>,
+    /// Options the swarm was built with, reused when it is rebuilt.
+    pub options: NetOptions,
+    /// Addresses passed to `listen_on`, in order.
+    pub listen_addrs: Vec<Multiaddr>,
     pub mesh: Arc<Mutex<TopicMesh>>,
     pub metrics: Arc<Mutex<MetricsCollector>>,
     pub status_topic: gossipsub::IdentTopic,
@@ -87,6 +447,44 @@ pub struct Mycelium {
     pub task_topic: gossipsub::IdentTopic,
     pub spike_topic: gossipsub::IdentTopic,
     pub shared_state_topic: gossipsub::IdentTopic,
+    pub sensor_topic: gossipsub::IdentTopic,
+    pub quorum_topic: gossipsub::IdentTopic,
+    pub departure_topic: gossipsub::IdentTopic,
+    /// Namespace the topics live in, once `set_namespace` has run.
+    pub namespace: Option<String>,
+    /// Bootstrap addresses, possibly DNS-based, re-dialed by `redial_bootstrap`.
+    pub bootstrap: Vec<Multiaddr>,
+    /// Rendezvous points (each with a `/p2p` suffix) this node registers with
+    /// and discovers peers through.
+    pub rendezvous_points: Vec<Multiaddr>,
+    pub rendezvous_namespace: rendezvous::Namespace,
+    /// Where the last discovery at each rendezvous point left off.
+    rendezvous_cookies: HashMap<PeerId, rendezvous::Cookie>,
+    /// The rendezvous point or peer that led this node to each discovered
+    /// peer, until it connects; see `take_introducer`.
+    introducers: HashMap<PeerId, PeerId>,
+    /// Topics currently joined through `subscribe_all` or a subscription policy.
+    pub subscribed: HashSet<TopicKind>,
+    /// Task shards joined through `join_task_shards`.
+    pub task_shards: BTreeMap<u32, gossipsub::IdentTopic>,
+    pub send_policy: SendPolicy,
+    /// Publishes held back by `send_policy` until the power mode allows them.
+    pub outbox: Outbox,
+    /// Backoff for publishes gossipsub refuses, e.g. before any peer has
+    /// joined the topic.
+    pub retry_policy: RetryPolicy,
+    retries: VecDeque<PendingRetry>,
+    /// Topics whose publishes always carry a hop trace.
+    pub traced: HashSet<TopicKind>,
+    pub compression: CompressionPolicy,
+    /// Small publishes held for `flush_batches`.
+    pub batcher: Batcher,
+    /// Topics a topic ACL does not let this node publish on; publishes to
+    /// them are dropped.
+    pub forbidden: HashSet<TopicKind>,
+    /// Stamps publishes on sequenced topics and keeps them for resending.
+    /// Set by `SporeNode::run_for`.
+    pub sequencer: Option<Arc<Mutex<Sequencer>>>,
 }
 
 impl Mycelium {
@@ -104,7 +502,20 @@ impl Mycelium {
         metrics: Arc<Mutex<MetricsCollector>>,
         profile: NetProfile,
     ) -> Result<Self, Box<dyn Error>> {
-        let swarm = match profile {
+        let options = NetOptions {
+            profile,
+            ..NetOptions::default()
+        };
+        Self::new_with_options(keypair, mesh, metrics, options)
+    }
+
+    pub fn new_with_options(
+        keypair: identity::Keypair,
+        mesh: Arc<Mutex<TopicMesh>>,
+        metrics: Arc<Mutex<MetricsCollector>>,
+        options: NetOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        let swarm = match options.profile {
             NetProfile::Tcp => libp2p::SwarmBuilder::with_existing_identity(keypair.clone())
                 .with_tokio()
                 .with_tcp(
@@ -112,26 +523,12 @@ impl Mycelium {
                     noise::Config::new,
                     yamux::Config::default,
                 )?
+                .with_dns()?
                 // Use SwarmBuilder's relay-client wiring (transport + behaviour) to
                 // ensure `/p2p-circuit` addresses actually work and reservations are made.
                 .with_relay_client(noise::Config::new, yamux::Config::default)?
                 .with_behaviour(|key, relay_client| {
-                    let gossipsub_config = gossipsub::ConfigBuilder::default()
-                        .validation_mode(gossipsub::ValidationMode::Strict)
-                        .build()?;
-
-                    Ok(MyceliumBehaviour {
-                        gossipsub: gossipsub::Behaviour::new(
-                            gossipsub::MessageAuthenticity::Signed(key.clone()),
-                            gossipsub_config,
-                        )?,
-                        identify: libp2p::identify::Behaviour::new(libp2p::identify::Config::new(
-                            "/hypha/1.0.0".to_string(),
-                            key.public(),
-                        )),
-                        relay_client,
-                        dcutr: libp2p::dcutr::Behaviour::new(key.public().to_peer_id()),
-                    })
+                    MyceliumBehaviour::new(key, relay_client, &options)
                 })?
                 .build(),
             NetProfile::TcpQuic | NetProfile::Mobile => {
@@ -143,39 +540,28 @@ impl Mycelium {
                         yamux::Config::default,
                     )?
                     .with_quic()
+                    .with_dns()?
                     .with_relay_client(noise::Config::new, yamux::Config::default)?
                     .with_behaviour(|key, relay_client| {
-                        let gossipsub_config = gossipsub::ConfigBuilder::default()
-                            .validation_mode(gossipsub::ValidationMode::Strict)
-                            .build()?;
-
-                        Ok(MyceliumBehaviour {
-                            gossipsub: gossipsub::Behaviour::new(
-                                gossipsub::MessageAuthenticity::Signed(key.clone()),
-                                gossipsub_config,
-                            )?,
-                            identify: libp2p::identify::Behaviour::new(
-                                libp2p::identify::Config::new(
-                                    "/hypha/1.0.0".to_string(),
-                                    key.public(),
-                                ),
-                            ),
-                            relay_client,
-                            dcutr: libp2p::dcutr::Behaviour::new(key.public().to_peer_id()),
-                        })
+                        MyceliumBehaviour::new(key, relay_client, &options)
                     })?
                     .build()
             }
         };
 
-        let status_topic = gossipsub::IdentTopic::new("hypha_energy_status");
-        let control_topic = gossipsub::IdentTopic::new("hypha_mesh_control");
-        let task_topic = gossipsub::IdentTopic::new("hypha_task_stream");
-        let spike_topic = gossipsub::IdentTopic::new("hypha_spikes");
-        let shared_state_topic = gossipsub::IdentTopic::new("hypha_global_state");
+        let status_topic = gossipsub::IdentTopic::new(TopicKind::Status.base_name());
+        let control_topic = gossipsub::IdentTopic::new(TopicKind::Control.base_name());
+        let task_topic = gossipsub::IdentTopic::new(TopicKind::Task.base_name());
+        let spike_topic = gossipsub::IdentTopic::new(TopicKind::Spike.base_name());
+        let shared_state_topic = gossipsub::IdentTopic::new(TopicKind::SharedState.base_name());
+        let sensor_topic = gossipsub::IdentTopic::new(TopicKind::Sensor.base_name());
+        let quorum_topic = gossipsub::IdentTopic::new(TopicKind::Quorum.base_name());
+        let departure_topic = gossipsub::IdentTopic::new(TopicKind::Departure.base_name());
 
         Ok(Self {
             swarm,
+            options,
+            listen_addrs: Vec::new(),
             mesh,
             metrics,
             status_topic,
@@ -183,40 +569,841 @@ impl Mycelium {
             task_topic,
             spike_topic,
             shared_state_topic,
+            sensor_topic,
+            quorum_topic,
+            departure_topic,
+            namespace: None,
+            bootstrap: Vec::new(),
+            rendezvous_points: Vec::new(),
+            rendezvous_namespace: rendezvous::Namespace::from_static(DEFAULT_RENDEZVOUS_NAMESPACE),
+            rendezvous_cookies: HashMap::new(),
+            introducers: HashMap::new(),
+            subscribed: HashSet::new(),
+            task_shards: BTreeMap::new(),
+            send_policy: SendPolicy::default(),
+            outbox: Outbox::default(),
+            retry_policy: RetryPolicy::default(),
+            retries: VecDeque::new(),
+            traced: HashSet::new(),
+            compression: CompressionPolicy::default(),
+            batcher: Batcher::default(),
+            forbidden: HashSet::new(),
+            sequencer: None,
         })
     }
 
+    pub fn topic(&self, kind: TopicKind) -> &gossipsub::IdentTopic {
+        match kind {
+            TopicKind::Status => &self.status_topic,
+            TopicKind::Control => &self.control_topic,
+            TopicKind::Task => &self.task_topic,
+            TopicKind::Spike => &self.spike_topic,
+            TopicKind::SharedState => &self.shared_state_topic,
+            TopicKind::Sensor => &self.sensor_topic,
+            TopicKind::Quorum => &self.quorum_topic,
+            TopicKind::Departure => &self.departure_topic,
+        }
+    }
+
+    fn topic_mut(&mut self, kind: TopicKind) -> &mut gossipsub::IdentTopic {
+        match kind {
+            TopicKind::Status => &mut self.status_topic,
+            TopicKind::Control => &mut self.control_topic,
+            TopicKind::Task => &mut self.task_topic,
+            TopicKind::Spike => &mut self.spike_topic,
+            TopicKind::SharedState => &mut self.shared_state_topic,
+            TopicKind::Sensor => &mut self.sensor_topic,
+            TopicKind::Quorum => &mut self.quorum_topic,
+            TopicKind::Departure => &mut self.departure_topic,
+        }
+    }
+
+    /// Which of our topics `hash` names, if any. A joined task shard counts
+    /// as the task topic.
+    pub fn topic_kind(&self, hash: &gossipsub::TopicHash) -> Option<TopicKind> {
+        TopicKind::ALL
+            .into_iter()
+            .find(|kind| self.topic(*kind).hash() == *hash)
+            .or_else(|| {
+                self.task_shards
+                    .values()
+                    .any(|topic| topic.hash() == *hash)
+                    .then_some(TopicKind::Task)
+            })
+    }
+
+    /// The gossip topic of task shard `shard`, e.g. `hypha_task_stream.3`.
+    pub fn task_shard_topic(&self, shard: u32) -> gossipsub::IdentTopic {
+        gossipsub::IdentTopic::new(format!("{}.{shard}", self.task_topic))
+    }
+
+    /// Join exactly the task shards in `wanted` and leave the rest. While
+    /// the task topic itself is not joined, no shard is either. Returns
+    /// whether anything changed.
+    pub fn join_task_shards(&mut self, wanted: &BTreeSet<u32>) -> Result<bool, Box<dyn Error>> {
+        let empty = BTreeSet::new();
+        let wanted = if self.subscribed.contains(&TopicKind::Task) {
+            wanted
+        } else {
+            &empty
+        };
+        let mut changed = false;
+        let leaving: Vec<u32> = self
+            .task_shards
+            .keys()
+            .filter(|shard| !wanted.contains(shard))
+            .copied()
+            .collect();
+        for shard in leaving {
+            if let Some(topic) = self.task_shards.remove(&shard) {
+                let _ = self.swarm.behaviour_mut().gossipsub.unsubscribe(&topic);
+                changed = true;
+            }
+        }
+        for shard in wanted {
+            if !self.task_shards.contains_key(shard) {
+                let topic = self.task_shard_topic(*shard);
+                self.swarm.behaviour_mut().gossipsub.subscribe(&topic)?;
+                self.task_shards.insert(*shard, topic);
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
+
     pub fn subscribe_all(&mut self) -> Result<(), Box<dyn Error>> {
+        for kind in TopicKind::ALL {
+            let topic = self.topic(kind).clone();
+            self.swarm.behaviour_mut().gossipsub.subscribe(&topic)?;
+            self.subscribed.insert(kind);
+        }
+        Ok(())
+    }
+
+    /// Join `topic` and read its messages as `T`.
+    pub fn subscribe<T: DeserializeOwned>(
+        &mut self,
+        topic: Topic<T>,
+    ) -> Result<Subscription<T>, Box<dyn Error>> {
+        let gossip_topic = self.topic(topic.kind()).clone();
         self.swarm
             .behaviour_mut()
             .gossipsub
-            .subscribe(&self.status_topic)?;
-        self.swarm
+            .subscribe(&gossip_topic)?;
+        self.subscribed.insert(topic.kind());
+        Ok(Subscription::new(topic, gossip_topic.hash()))
+    }
+
+    /// Whether `hash` names the gossip topic `topic` is published on, or
+    /// one of its joined shards.
+    pub fn carries<T>(&self, topic: Topic<T>, hash: &gossipsub::TopicHash) -> bool {
+        self.topic_kind(hash) == Some(topic.kind())
+    }
+
+    /// The messages of `subscription`, driving the swarm to receive them.
+    /// Every other swarm event is dropped, so this suits tests and tools
+    /// that do nothing else with the swarm; the run loop matches events
+    /// itself.
+    pub fn messages<'a, T: DeserializeOwned + 'a>(
+        &'a mut self,
+        subscription: &'a Subscription<T>,
+    ) -> impl Stream<Item = Received<T>> + 'a {
+        (&mut self.swarm).flat_map(move |event| stream::iter(subscription.accept(&event)))
+    }
+
+    /// Join and leave topics so the subscription set matches `policy` for
+    /// `mode`. Returns true if anything changed.
+    pub fn apply_subscription_policy(
+        &mut self,
+        policy: &SubscriptionPolicy,
+        mode: &PowerMode,
+    ) -> Result<bool, Box<dyn Error>> {
+        self.apply_topics(&policy.topics_for(mode))
+    }
+
+    /// Join exactly the topics in `wanted` and leave the rest. Returns whether
+    /// anything changed.
+    pub fn apply_topics(&mut self, wanted: &[TopicKind]) -> Result<bool, Box<dyn Error>> {
+        let mut changed = false;
+        for kind in TopicKind::ALL {
+            let topic = self.topic(kind).clone();
+            if wanted.contains(&kind) {
+                if !self.subscribed.contains(&kind) {
+                    self.swarm.behaviour_mut().gossipsub.subscribe(&topic)?;
+                    self.subscribed.insert(kind);
+                    changed = true;
+                }
+            } else if self.subscribed.remove(&kind) {
+                let _ = self.swarm.behaviour_mut().gossipsub.unsubscribe(&topic);
+                changed = true;
+            }
+        }
+        if !self.subscribed.contains(&TopicKind::Task) {
+            changed |= self.join_task_shards(&BTreeSet::new())?;
+        }
+        Ok(changed)
+    }
+
+    /// Publish `body` on `topic`; see `publish_with_priority`.
+    pub fn publish<T: serde::Serialize>(
+        &mut self,
+        topic: Topic<T>,
+        priority: Priority,
+        body: &T,
+        mode: &PowerMode,
+    ) -> Result<SendDecision, Box<dyn Error>> {
+        self.publish_with_priority(topic.kind(), priority, body, mode)
+    }
+
+    /// Publish `body` on task shard `shard` of `topic`, or on `topic` itself
+    /// with `None`. Shard publishes are never batched, since a batch goes
+    /// out on one topic.
+    pub fn publish_to_shard<T: serde::Serialize>(
+        &mut self,
+        topic: Topic<T>,
+        shard: Option<u32>,
+        priority: Priority,
+        body: &T,
+        mode: &PowerMode,
+    ) -> Result<SendDecision, Box<dyn Error>> {
+        let kind = topic.kind();
+        let traced = self.traced.contains(&kind);
+        self.publish_envelope(kind, shard, priority, body, traced, mode)
+    }
+
+    /// Publish `body` on `kind` in a versioned envelope, subject to the send policy.
+    ///
+    /// Held-back and dropped publishes are counted in the metrics collector.
+    pub fn publish_with_priority<T: serde::Serialize>(
+        &mut self,
+        kind: TopicKind,
+        priority: Priority,
+        body: &T,
+        mode: &PowerMode,
+    ) -> Result<SendDecision, Box<dyn Error>> {
+        let traced = self.traced.contains(&kind);
+        self.publish_envelope(kind, None, priority, body, traced, mode)
+    }
+
+    /// Like `publish_with_priority`, but the message carries a hop trace
+    /// whether or not `kind` is traced.
+    pub fn publish_traced<T: serde::Serialize>(
+        &mut self,
+        kind: TopicKind,
+        priority: Priority,
+        body: &T,
+        mode: &PowerMode,
+    ) -> Result<SendDecision, Box<dyn Error>> {
+        self.publish_envelope(kind, None, priority, body, true, mode)
+    }
+
+    fn publish_envelope<T: serde::Serialize>(
+        &mut self,
+        kind: TopicKind,
+        shard: Option<u32>,
+        priority: Priority,
+        body: &T,
+        traced: bool,
+        mode: &PowerMode,
+    ) -> Result<SendDecision, Box<dyn Error>> {
+        if self.forbidden.contains(&kind) {
+            tracing::debug!(?kind, "Not publishing on a topic the ACL reserves");
+            self.metrics.lock().unwrap().record_publish_dropped();
+            return Ok(SendDecision::Drop);
+        }
+        let mut envelope = Envelope::new(priority, body);
+        if traced {
+            let origin = self.swarm.local_peer_id().to_string();
+            envelope.trace = Some(Trace::new(&origin, trace::now_ms()));
+        }
+        envelope.seq = self
+            .sequencer
+            .as_ref()
+            .and_then(|sequencer| sequencer.lock().unwrap().stamp(kind));
+        let encoded = envelope.encode()?;
+        if let (Some(seq), Some(sequencer)) = (&envelope.seq, &self.sequencer) {
+            sequencer
+                .lock()
+                .unwrap()
+                .retain(seq.n, kind, encoded.clone());
+        }
+        let mut decision = self.send_policy.decide(mode, priority, self.outbox.len());
+        if decision == SendDecision::Send
+            && !traced
+            && shard.is_none()
+            && self.batcher.accepts(kind, priority, encoded.len())
+        {
+            decision = SendDecision::Batched;
+        }
+        let raw_len = encoded.len();
+        let bytes = match decision {
+            // Batched envelopes are small; compressing them would not pay.
+            SendDecision::Batched => encoded,
+            _ => self.compression.apply(kind, encoded),
+        };
+        self.metrics
+            .lock()
+            .unwrap()
+            .record_publish_bytes(raw_len, bytes.len());
+        match decision {
+            SendDecision::Send => {
+                let topic = match shard {
+                    Some(shard) => self.task_shard_topic(shard).hash(),
+                    None => self.topic(kind).hash(),
+                };
+                self.publish_or_retry(topic, bytes, 0);
+            }
+            SendDecision::Batched => {
+                if let Some(frame) = self.batcher.push(kind, bytes) {
+                    let topic = self.topic(kind).hash();
+                    self.publish_or_retry(topic, frame, 0);
+                }
+            }
+            SendDecision::Queue => {
+                let topic = match shard {
+                    Some(shard) => self.task_shard_topic(shard).to_string(),
+                    None => self.topic(kind).to_string(),
+                };
+                self.outbox.push(OutboxEntry {
+                    topic,
+                    priority,
+                    bytes,
+                });
+                self.metrics.lock().unwrap().record_publish_queued();
+            }
+            SendDecision::Drop => self.metrics.lock().unwrap().record_publish_dropped(),
+        }
+        Ok(decision)
+    }
+
+    /// Publish any held-back messages that `mode` now allows. Returns how many
+    /// were sent.
+    pub fn flush_outbox(&mut self, mode: &PowerMode) -> usize {
+        let ready = self.outbox.drain_allowed(&self.send_policy, mode);
+        let sent = ready.len();
+        for entry in ready {
+            self.publish_or_retry(gossipsub::TopicHash::from_raw(entry.topic), entry.bytes, 0);
+        }
+        sent
+    }
+
+    /// Share of the send queue still free: 1.0 with nothing held back, 0.0
+    /// once the outbox is full and the link is evidently saturated.
+    pub fn link_headroom(&self) -> f32 {
+        let capacity = self.send_policy.queue_capacity.max(1);
+        1.0 - self.outbox.len().min(capacity) as f32 / capacity as f32
+    }
+
+    /// Send the publishes `batcher` holds, one message per topic. Called at
+    /// the pulse peak. Returns how many messages went out.
+    pub fn flush_batches(&mut self) -> usize {
+        let ready = self.batcher.drain();
+        let sent = ready.len();
+        for (kind, bytes) in ready {
+            let topic = self.topic(kind).hash();
+            self.publish_or_retry(topic, bytes, 0);
+        }
+        sent
+    }
+
+    /// Re-attempt refused publishes whose backoff has elapsed. Nothing is
+    /// retried while `energy` is below the policy's floor; entries that run
+    /// out of attempts are dropped. Returns how many were published.
+    pub fn retry_publishes(&mut self, energy: f32) -> usize {
+        let now = Instant::now();
+        let (due, waiting): (Vec<_>, Vec<_>) =
+            self.retries.drain(..).partition(|retry| retry.due <= now);
+        self.retries = waiting.into();
+
+        let mut sent = 0;
+        for retry in due {
+            if !self.retry_policy.allows_retry(retry.attempts, energy) {
+                self.metrics.lock().unwrap().record_publish_dropped();
+                continue;
+            }
+            if self.publish_or_retry(retry.topic, retry.bytes, retry.attempts) {
+                sent += 1;
+            }
+        }
+        sent
+    }
+
+    /// Publish `bytes`, scheduling a retry if gossipsub refuses for a reason
+    /// that may clear up. `attempts` counts earlier failures.
+    fn publish_or_retry(
+        &mut self,
+        topic: gossipsub::TopicHash,
+        bytes: Vec<u8>,
+        attempts: u32,
+    ) -> bool {
+        let err = match self
+            .swarm
             .behaviour_mut()
             .gossipsub
-            .subscribe(&self.control_topic)?;
+            .publish(topic.clone(), bytes.clone())
+        {
+            Ok(_) => return true,
+            Err(err) => err,
+        };
+        let attempts = attempts + 1;
+        let permanent = matches!(
+            err,
+            gossipsub::PublishError::Duplicate | gossipsub::PublishError::MessageTooLarge
+        );
+        if permanent || attempts >= self.retry_policy.max_attempts {
+            tracing::debug!(%topic, attempts, ?err, "Publish failed");
+            self.metrics.lock().unwrap().record_publish_dropped();
+            return false;
+        }
+        if self.retries.len() >= MAX_PENDING_RETRIES {
+            self.retries.pop_front();
+            self.metrics.lock().unwrap().record_publish_dropped();
+        }
+        self.retries.push_back(PendingRetry {
+            topic,
+            bytes,
+            attempts,
+            due: Instant::now() + self.retry_policy.delay(attempts, &mut rand::rng()),
+        });
+        false
+    }
+
+    /// Move every topic into `namespace` so that several logical nodes, or
+    /// several swarms, can share a network without hearing each other.
+    ///
+    /// Call before subscribing; existing subscriptions are left on the old
+    /// topic names.
+    pub fn set_namespace(&mut self, namespace: &str) {
+        self.namespace = Some(namespace.to_string());
+        self.status_topic =
+            gossipsub::IdentTopic::new(TopicKind::Status.namespaced_name(namespace));
+        self.control_topic =
+            gossipsub::IdentTopic::new(TopicKind::Control.namespaced_name(namespace));
+        self.task_topic = gossipsub::IdentTopic::new(TopicKind::Task.namespaced_name(namespace));
+        self.spike_topic = gossipsub::IdentTopic::new(TopicKind::Spike.namespaced_name(namespace));
+        self.shared_state_topic =
+            gossipsub::IdentTopic::new(TopicKind::SharedState.namespaced_name(namespace));
+        self.sensor_topic =
+            gossipsub::IdentTopic::new(TopicKind::Sensor.namespaced_name(namespace));
+        self.quorum_topic =
+            gossipsub::IdentTopic::new(TopicKind::Quorum.namespaced_name(namespace));
+        self.departure_topic =
+            gossipsub::IdentTopic::new(TopicKind::Departure.namespaced_name(namespace));
+        match rendezvous::Namespace::new(format!("{DEFAULT_RENDEZVOUS_NAMESPACE}/{namespace}")) {
+            Ok(ns) => self.rendezvous_namespace = ns,
+            Err(e) => tracing::warn!(namespace, err = %e, "Rendezvous namespace too long"),
+        }
+        self.rendezvous_cookies.clear();
+    }
+
+    /// Leave every joined topic and switch to `namespace`, or back to the
+    /// bare names with `None`. Rejoin with `apply_topics` or a subscription
+    /// policy afterwards.
+    pub fn move_to_namespace(&mut self, namespace: Option<&str>) {
+        for kind in std::mem::take(&mut self.subscribed) {
+            let topic = self.topic(kind).clone();
+            let _ = self.swarm.behaviour_mut().gossipsub.unsubscribe(&topic);
+        }
+        for topic in std::mem::take(&mut self.task_shards).into_values() {
+            let _ = self.swarm.behaviour_mut().gossipsub.unsubscribe(&topic);
+        }
+        match namespace {
+            Some(namespace) => self.set_namespace(namespace),
+            None => {
+                for kind in TopicKind::ALL {
+                    *self.topic_mut(kind) = gossipsub::IdentTopic::new(kind.base_name());
+                }
+                self.namespace = None;
+                self.rendezvous_namespace =
+                    rendezvous::Namespace::from_static(DEFAULT_RENDEZVOUS_NAMESPACE);
+                self.rendezvous_cookies.clear();
+            }
+        }
+    }
+
+    /// Names of the joined topics, task shards last, for status adverts.
+    pub fn subscribed_topic_names(&self) -> Vec<String> {
+        TopicKind::ALL
+            .into_iter()
+            .filter(|kind| self.subscribed.contains(kind))
+            .map(|kind| self.topic(kind).to_string())
+            .chain(self.task_shards.values().map(|topic| topic.to_string()))
+            .collect()
+    }
+
+    pub fn listen_on(&mut self, addr: Multiaddr) -> Result<(), Box<dyn Error>> {
+        self.swarm.listen_on(addr.clone())?;
+        self.listen_addrs.push(addr);
+        Ok(())
+    }
+
+    /// Present `credential` to `peer` for admission.
+    pub fn request_join(&mut self, peer: &PeerId, credential: Option<Credential>) {
         self.swarm
             .behaviour_mut()
-            .gossipsub
-            .subscribe(&self.task_topic)?;
+            .join
+            .send_request(peer, JoinRequest { credential });
+    }
+
+    /// Ask `peer` for a full snapshot of its catalog.
+    pub fn request_catalog(&mut self, peer: &PeerId) {
         self.swarm
             .behaviour_mut()
-            .gossipsub
-            .subscribe(&self.spike_topic)?;
+            .catalog
+            .send_request(peer, CatalogRequest::default());
+    }
+
+    /// Hand `peer` the tasks held for it while it was away.
+    pub fn deliver_mail(&mut self, peer: &PeerId, tasks: Vec<Task>) {
         self.swarm
             .behaviour_mut()
-            .gossipsub
-            .subscribe(&self.shared_state_topic)?;
-        Ok(())
+            .mailbox
+            .send_request(peer, MailboxDelivery { tasks });
     }
 
-    pub fn listen_on(&mut self, addr: Multiaddr) -> Result<(), Box<dyn Error>> {
-        self.swarm.listen_on(addr)?;
-        Ok(())
+    /// Ask `peer` for the chunk named `hash`.
+    pub fn request_chunk(&mut self, peer: &PeerId, hash: String) {
+        self.swarm
+            .behaviour_mut()
+            .chunks
+            .send_request(peer, ChunkRequest { hash });
+    }
+
+    /// Ask `peer` who it knows, handing it this node's own `record`.
+    pub fn request_introduction(&mut self, peer: &PeerId, record: Option<SignedPeer>) {
+        self.swarm
+            .behaviour_mut()
+            .introduce
+            .send_request(peer, IntroductionRequest { record });
+    }
+
+    /// Ask `peer` to resend sequenced messages this node missed.
+    pub fn request_resend(&mut self, peer: &PeerId, request: ResendRequest) {
+        self.swarm
+            .behaviour_mut()
+            .sequence
+            .send_request(peer, request);
     }
 
     pub fn dial(&mut self, addr: Multiaddr) -> Result<(), Box<dyn Error>> {
         self.swarm.dial(addr)?;
         Ok(())
     }
+
+    /// Dial `peer` at `addr`, unless it is already connected.
+    pub fn dial_peer(&mut self, peer: PeerId, addr: Multiaddr) -> Result<(), Box<dyn Error>> {
+        let opts = DialOpts::peer_id(peer)
+            .condition(PeerCondition::DisconnectedAndNotDialing)
+            .addresses(vec![addr])
+            .build();
+        self.swarm.dial(opts)?;
+        Ok(())
+    }
+
+    /// Register a bootstrap address such as `/dnsaddr/boot.example.org`.
+    ///
+    /// Duplicate entries are ignored. Nothing is dialed until
+    /// `redial_bootstrap` runs.
+    pub fn add_bootstrap(&mut self, addr: Multiaddr) {
+        if !self.bootstrap.contains(&addr) {
+            self.bootstrap.push(addr);
+        }
+    }
+
+    /// Dial every bootstrap entry that is not already connected.
+    ///
+    /// Each dial re-resolves DNS components, so calling this periodically keeps
+    /// long-running nodes reachable after bootstrap hosts change IPs. Returns
+    /// the number of dials started.
+    pub fn redial_bootstrap(&mut self) -> usize {
+        let mut dialed = 0;
+        for addr in self.bootstrap.clone() {
+            if let Some(peer) = bootstrap_peer_id(&addr) {
+                if self.swarm.is_connected(&peer) {
+                    continue;
+                }
+            }
+            match self.swarm.dial(addr.clone()) {
+                Ok(()) => dialed += 1,
+                Err(e) => tracing::warn!(%addr, err = %e, "Bootstrap dial failed"),
+            }
+        }
+        dialed
+    }
+
+    /// Register a rendezvous point. The address must end in `/p2p/<peer>`
+    /// since registrations are addressed to the point's peer id; returns
+    /// `false` otherwise. Nothing is dialed until `refresh_rendezvous` runs.
+    pub fn add_rendezvous_point(&mut self, addr: Multiaddr) -> bool {
+        if bootstrap_peer_id(&addr).is_none() {
+            return false;
+        }
+        if !self.rendezvous_points.contains(&addr) {
+            self.rendezvous_points.push(addr);
+        }
+        true
+    }
+
+    /// Re-register with every connected rendezvous point and ask it for new
+    /// peers in our namespace; dial the ones that are not connected.
+    /// Registrations expire on the server, so call this periodically, well
+    /// within the default TTL. Returns the number of dials started.
+    pub fn refresh_rendezvous(&mut self) -> usize {
+        let mut dialed = 0;
+        for addr in self.rendezvous_points.clone() {
+            let Some(point) = bootstrap_peer_id(&addr) else {
+                continue;
+            };
+            if self.swarm.is_connected(&point) {
+                self.register_and_discover(point);
+                continue;
+            }
+            match self.swarm.dial(addr.clone()) {
+                Ok(()) => dialed += 1,
+                Err(e) => tracing::warn!(%addr, err = %e, "Rendezvous dial failed"),
+            }
+        }
+        dialed
+    }
+
+    /// Register and discover as soon as a connection to a rendezvous point
+    /// comes up, rather than waiting for the next refresh.
+    pub fn rendezvous_connected(&mut self, peer: &PeerId) {
+        let is_point = self
+            .rendezvous_points
+            .iter()
+            .any(|addr| bootstrap_peer_id(addr) == Some(*peer));
+        if is_point {
+            self.register_and_discover(*peer);
+        }
+    }
+
+    /// The rendezvous point or peer that led this node to `peer`, if it was
+    /// discovered and has not connected since.
+    pub fn take_introducer(&mut self, peer: &PeerId) -> Option<PeerId> {
+        self.introducers.remove(peer)
+    }
+
+    /// Dial the peer `record` names at the addresses it signed, noting
+    /// `introducer` as the peer that led here. Returns the number of dials
+    /// started.
+    pub fn dial_introduced(&mut self, introducer: PeerId, record: &SignedPeer) -> usize {
+        let Ok(peer) = record.peer.parse::<PeerId>() else {
+            return 0;
+        };
+        if peer == *self.swarm.local_peer_id() || self.swarm.is_connected(&peer) {
+            return 0;
+        }
+        let addrs: Vec<Multiaddr> = record
+            .addrs
+            .iter()
+            .filter_map(|addr| addr.parse().ok())
+            .collect();
+        if addrs.is_empty() {
+            return 0;
+        }
+        if self.introducers.len() < MAX_PENDING_INTRODUCTIONS {
+            self.introducers.insert(peer, introducer);
+        }
+        let opts = DialOpts::peer_id(peer)
+            .condition(PeerCondition::DisconnectedAndNotDialing)
+            .addresses(addrs)
+            .build();
+        match self.swarm.dial(opts) {
+            Ok(()) => 1,
+            Err(e) => {
+                tracing::debug!(%peer, err = %e, "Introduced peer dial failed");
+                0
+            }
+        }
+    }
+
+    fn register_and_discover(&mut self, point: PeerId) {
+        let namespace = self.rendezvous_namespace.clone();
+        // Fails until the swarm knows an external address; discovery still
+        // works, and the next refresh retries the registration.
+        if let Err(e) =
+            self.swarm
+                .behaviour_mut()
+                .rendezvous
+                .register(namespace.clone(), point, None)
+        {
+            tracing::debug!(%point, err = %e, "Rendezvous registration deferred");
+        }
+        let cookie = self.rendezvous_cookies.get(&point).cloned();
+        self.swarm
+            .behaviour_mut()
+            .rendezvous
+            .discover(Some(namespace), cookie, None, point);
+    }
+
+    /// Act on a rendezvous client event: dial discovered peers and remember
+    /// the discovery cookie so the next query only returns newcomers.
+    /// Returns the number of dials started.
+    pub fn handle_rendezvous_event(&mut self, event: &rendezvous::client::Event) -> usize {
+        match event {
+            rendezvous::client::Event::Discovered {
+                rendezvous_node,
+                registrations,
+                cookie,
+            } => {
+                self.rendezvous_cookies
+                    .insert(*rendezvous_node, cookie.clone());
+                let local = *self.swarm.local_peer_id();
+                let mut dialed = 0;
+                for registration in registrations {
+                    let peer = registration.record.peer_id();
+                    if peer == local || self.swarm.is_connected(&peer) {
+                        continue;
+                    }
+                    if self.introducers.len() < MAX_PENDING_INTRODUCTIONS {
+                        self.introducers.insert(peer, *rendezvous_node);
+                    }
+                    for addr in registration.record.addresses() {
+                        let addr = addr.clone().with_p2p(peer).unwrap_or_else(|addr| addr);
+                        match self.swarm.dial(addr.clone()) {
+                            Ok(()) => dialed += 1,
+                            Err(e) => {
+                                tracing::debug!(%addr, err = %e, "Discovered peer dial failed")
+                            }
+                        }
+                    }
+                }
+                dialed
+            }
+            rendezvous::client::Event::RegisterFailed {
+                rendezvous_node,
+                error,
+                ..
+            } => {
+                tracing::warn!(point = %rendezvous_node, ?error, "Rendezvous registration failed");
+                0
+            }
+            rendezvous::client::Event::DiscoverFailed {
+                rendezvous_node,
+                error,
+                ..
+            } => {
+                tracing::warn!(point = %rendezvous_node, ?error, "Rendezvous discovery failed");
+                0
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// The `/p2p/<peer>` suffix of a bootstrap address, if present.
+fn bootstrap_peer_id(addr: &Multiaddr) -> Option<PeerId> {
+    addr.iter().find_map(|p| match p {
+        Protocol::P2p(peer) => Some(peer),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn rendezvous_points_need_a_peer_id() {
+        let keypair = identity::Keypair::generate_ed25519();
+        let mesh = Arc::new(Mutex::new(TopicMesh::new(
+            keypair.public().to_peer_id().to_string(),
+            crate::core::MeshConfig::default(),
+        )));
+        let metrics = Arc::new(Mutex::new(MetricsCollector::new()));
+        let mut mycelium = Mycelium::new(keypair, mesh, metrics).unwrap();
+
+        assert!(!mycelium.add_rendezvous_point("/ip4/10.0.0.1/tcp/4001".parse().unwrap()));
+        let point: Multiaddr = format!("/ip4/10.0.0.1/tcp/4001/p2p/{}", PeerId::random())
+            .parse()
+            .unwrap();
+        assert!(mycelium.add_rendezvous_point(point.clone()));
+        assert!(mycelium.add_rendezvous_point(point));
+        assert_eq!(mycelium.rendezvous_points.len(), 1);
+
+        mycelium.set_namespace("lab");
+        assert_eq!(mycelium.rendezvous_namespace.to_string(), "hypha/lab");
+    }
+
+    #[test]
+    fn critical_policy_drops_task_and_state_topics() {
+        let topics = SubscriptionPolicy::default().topics_for(&PowerMode::Critical);
+
+        assert!(!topics.contains(&TopicKind::Task));
+        assert!(!topics.contains(&TopicKind::SharedState));
+        assert!(topics.contains(&TopicKind::Spike));
+    }
+
+    #[test]
+    fn spikes_are_joined_even_when_policy_omits_them() {
+        let policy = SubscriptionPolicy {
+            normal: vec![],
+            low_battery: vec![],
+            critical: vec![],
+        };
+
+        for mode in [
+            PowerMode::Normal,
+            PowerMode::LowBattery,
+            PowerMode::Critical,
+        ] {
+            assert_eq!(policy.topics_for(&mode), vec![TopicKind::Spike]);
+        }
+    }
+
+    #[test]
+    fn content_ids_collapse_republished_payloads() {
+        let message = |source: PeerId, seq: u64, topic: &str, data: &[u8]| gossipsub::Message {
+            source: Some(source),
+            data: data.to_vec(),
+            sequence_number: Some(seq),
+            topic: gossipsub::TopicHash::from_raw(topic),
+        };
+        let (a, b) = (PeerId::random(), PeerId::random());
+        let task = TopicKind::Task.namespaced_name("lab");
+        let content_ids = HashSet::from([TopicKind::Task]);
+
+        assert_eq!(
+            message_id(&content_ids, &message(a, 1, &task, b"job")),
+            message_id(&content_ids, &message(b, 2, &task, b"job"))
+        );
+        assert_ne!(
+            message_id(&content_ids, &message(a, 1, &task, b"job")),
+            message_id(&content_ids, &message(a, 1, "hypha_task_stream", b"job"))
+        );
+        // Other topics keep gossipsub's source-and-sequence ids.
+        let status = TopicKind::Status.base_name();
+        assert_ne!(
+            message_id(&content_ids, &message(a, 1, status, b"up")),
+            message_id(&content_ids, &message(a, 2, status, b"up"))
+        );
+        assert_eq!(
+            message_id(&content_ids, &message(a, 7, status, b"up")),
+            gossipsub::MessageId::from(format!("{}7", a.to_base58()))
+        );
+    }
+
+    #[test]
+    fn namespaced_topics_keep_base_name_suffix() {
+        assert_eq!(
+            TopicKind::Spike.namespaced_name("gateway-a"),
+            "gateway-a/hypha_spikes"
+        );
+        assert_ne!(
+            TopicKind::Task.namespaced_name("a"),
+            TopicKind::Task.namespaced_name("b")
+        );
+        assert_eq!(
+            TopicKind::from_topic_name("lab/hypha_task_stream.12"),
+            Some(TopicKind::Task)
+        );
+        assert_eq!(TopicKind::from_topic_name("hypha_spikes.1"), None);
+    }
+
+    #[test]
+    fn normal_policy_joins_everything() {
+        let topics = SubscriptionPolicy::default().topics_for(&PowerMode::Normal);
+        assert_eq!(topics, TopicKind::ALL.to_vec());
+    }
 }
@@ -0,0 +1,139 @@
+//! Storage and CRDT work kept off the run loop.
+//!
+//! fjall writes and yrs merges are synchronous. Run inline, a burst of them
+//! holds up the swarm poll that shares their task, so pings, bids and mesh
+//! control all wait behind the disk. While `SporeNode::run_for` is running,
+//! the loop hands such work to an [`Offload`] worker instead: a dedicated
+//! thread runs jobs one at a time in submission order, so writes land in the
+//! order they would have inline, and a job with a result for the loop (a
+//! sync reply to publish, an event to emit) returns it on a completion
+//! channel the loop selects on.
+//!
+//! The job queue is bounded. When it is full the loop waits for room rather
+//! than run the job itself, which would land it ahead of older queued jobs;
+//! a burst past the queue's depth slows the loop down as it did before the
+//! worker existed, and nothing is dropped or reordered.
+//! [`Offload::finish`] waits for everything queued, which `run_for` does
+//! before its final saves.
+
+use std::sync::mpsc::{self as std_mpsc, SendError, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use tokio::sync::mpsc;
+
+/// Jobs queued before the loop waits for room.
+pub const DEFAULT_QUEUE: usize = 256;
+
+type Job<T> = Box<dyn FnOnce() -> Option<T> + Send>;
+
+/// Handle to the worker thread. Completions of type `T` arrive on the
+/// receiver returned by [`Offload::spawn`].
+pub struct Offload<T> {
+    jobs: SyncSender<Job<T>>,
+    done: mpsc::UnboundedSender<T>,
+    worker: JoinHandle<()>,
+}
+
+impl<T: Send + 'static> Offload<T> {
+    /// Start the worker with room for `queue` pending jobs.
+    pub fn spawn(queue: usize) -> std::io::Result<(Self, mpsc::UnboundedReceiver<T>)> {
+        let (jobs, pending) = std_mpsc::sync_channel::<Job<T>>(queue);
+        let (done, completions) = mpsc::unbounded_channel();
+        let worker_done = done.clone();
+        let worker = std::thread::Builder::new()
+            .name("hypha-offload".into())
+            .spawn(move || {
+                for job in pending {
+                    if let Some(completion) = job() {
+                        let _ = worker_done.send(completion);
+                    }
+                }
+            })?;
+        Ok((Self { jobs, done, worker }, completions))
+    }
+
+    /// Queue `job`, waiting for room if the queue is full. Returns whether
+    /// it was queued without waiting. Should the worker have died, the job
+    /// runs here instead, as nothing is left queued ahead of it.
+    pub fn submit(&self, job: impl FnOnce() -> Option<T> + Send + 'static) -> bool {
+        let job = match self.jobs.try_send(Box::new(job)) {
+            Ok(()) => return true,
+            Err(TrySendError::Full(job)) => match self.jobs.send(job) {
+                Ok(()) => return false,
+                Err(SendError(job)) => job,
+            },
+            Err(TrySendError::Disconnected(job)) => job,
+        };
+        if let Some(completion) = job() {
+            let _ = self.done.send(completion);
+        }
+        false
+    }
+
+    /// Queue a job with nothing to report back, such as a write.
+    pub fn write(&self, job: impl FnOnce() + Send + 'static) -> bool {
+        self.submit(move || {
+            job();
+            None
+        })
+    }
+
+    /// Close the queue and wait for the jobs already in it. Blocks, so async
+    /// callers run it on a blocking thread. Merely dropping the handle also
+    /// closes the queue, but leaves the worker to finish on its own.
+    pub fn finish(self) {
+        drop(self.jobs);
+        let _ = self.worker.join();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Barrier, Mutex};
+
+    #[test]
+    fn jobs_run_in_order_and_report_back() {
+        let (offload, mut completions) = Offload::spawn(8).unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        for n in 0..5 {
+            let log = log.clone();
+            assert!(offload.submit(move || {
+                log.lock().unwrap().push(n);
+                (n % 2 == 0).then_some(n)
+            }));
+        }
+        offload.finish();
+        assert_eq!(*log.lock().unwrap(), vec![0, 1, 2, 3, 4]);
+        let mut reported = Vec::new();
+        while let Ok(n) = completions.try_recv() {
+            reported.push(n);
+        }
+        assert_eq!(reported, vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn a_full_queue_waits_and_keeps_the_order() {
+        let (offload, mut completions) = Offload::spawn(1).unwrap();
+        // Hold the worker inside its first job so the queue fills up.
+        let started = Arc::new(Barrier::new(2));
+        let release = Arc::new(Barrier::new(2));
+        let (s, r) = (started.clone(), release.clone());
+        assert!(offload.write(move || {
+            s.wait();
+            r.wait();
+        }));
+        started.wait();
+        assert!(offload.submit(|| Some("older")));
+
+        // The next submission blocks until the worker makes room.
+        let releaser = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            release.wait();
+        });
+        assert!(!offload.submit(|| Some("newer")));
+        releaser.join().unwrap();
+        offload.finish();
+        assert_eq!(completions.try_recv().unwrap(), "older");
+        assert_eq!(completions.try_recv().unwrap(), "newer");
+    }
+}
@@ -0,0 +1,468 @@
+//! Firmware updates over the mesh.
+//!
+//! An operator releases firmware for one device class by signing the image
+//! with the firmware key its devices embed, which yields the `hypha_ota`
+//! manifest the devices verify themselves, and approving the image's hash
+//! in the artifact registry. `SporeNode::release_firmware` then splits the
+//! image into the node's [`ChunkStore`](crate::chunks::ChunkStore) and
+//! writes a [`FirmwareRelease`] under [`release_key`] in the shared-state
+//! document, which carries it to every node.
+//!
+//! A node with [`OtaConfig::enabled`] watches the release for its own
+//! device class. It takes a release only if the manifest verifies against
+//! `firmware_key`, the image is approved by one of the artifact operators
+//! and the version is not the one it runs. It then pulls the chunks it
+//! lacks from connected peers over the [`CHUNK_PROTOCOL`] request-response
+//! protocol, a few at a time and only while on mains or at `min_energy` or
+//! above, and serves the chunks it holds to others, so a release spreads
+//! through the fleet rather than out of one node. Chunks are checked
+//! against their hashes as they arrive and the assembled image against the
+//! manifest before an [`Installer`] gets it.
+
+use crate::audit::{from_hex, to_hex};
+use crate::chunks::chunk_hash;
+use crate::compute::registry::{ArtifactError, ArtifactPolicy};
+use hypha_ota::protocol;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+pub const CHUNK_PROTOCOL: &str = "/hypha/chunks/1.0.0";
+
+/// Prefix of release keys in the shared key/value map.
+pub const RELEASE_PREFIX: &str = "ota/";
+
+/// Size of the chunks images travel the mesh in. Devices behind a bridge
+/// fetch in `hypha_ota`'s much smaller chunks from the bridge.
+pub const DEFAULT_CHUNK_SIZE: usize = 16 * 1024;
+
+/// Key of the current release for `device_class`.
+pub fn release_key(device_class: &str) -> String {
+    format!("{RELEASE_PREFIX}{device_class}")
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChunkRequest {
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChunkResponse {
+    pub hash: String,
+    /// The chunk in hex, or `None` when the peer does not hold it.
+    #[serde(default)]
+    pub data: Option<String>,
+}
+
+impl ChunkResponse {
+    pub fn new(hash: &str, data: Option<&[u8]>) -> Self {
+        Self {
+            hash: hash.to_string(),
+            data: data.map(to_hex),
+        }
+    }
+
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        from_hex(self.data.as_deref()?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum OtaError {
+    #[error("malformed firmware release: {0}")]
+    Malformed(String),
+    #[error("no firmware key is configured")]
+    NoFirmwareKey,
+    #[error("firmware manifest does not verify against the firmware key")]
+    BadManifest,
+    #[error("firmware release disagrees with its manifest on the {0}")]
+    Mismatch(&'static str),
+    #[error(transparent)]
+    NotApproved(#[from] ArtifactError),
+    #[error("chunk {0} is missing")]
+    MissingChunk(String),
+    #[error("assembled image does not hash to {0}")]
+    BadImage(String),
+    #[error("chunk storage error: {0}")]
+    Storage(String),
+    #[error("no firmware installer is set")]
+    NoInstaller,
+    #[error("firmware install failed: {0}")]
+    Install(String),
+}
+
+/// A firmware image released for one device class.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirmwareRelease {
+    pub device_class: String,
+    pub version: String,
+    /// Hex SHA-256 of the whole image.
+    pub hash: String,
+    pub size: u64,
+    /// Hashes of the image's chunks, in order.
+    pub chunks: Vec<String>,
+    /// The `hypha_ota` manifest JSON, signed with the firmware key.
+    pub manifest: String,
+}
+
+impl FirmwareRelease {
+    /// Describe `image`, signed by `manifest`, split into chunks of
+    /// `chunk_size` bytes. The signature is checked by [`verify`](Self::verify).
+    pub fn new(
+        device_class: &str,
+        manifest: &str,
+        image: &[u8],
+        chunk_size: usize,
+    ) -> Result<Self, OtaError> {
+        let fields: serde_json::Value =
+            serde_json::from_str(manifest).map_err(|e| OtaError::Malformed(e.to_string()))?;
+        let field = |name: &str| {
+            fields
+                .get(name)
+                .and_then(|value| value.as_str())
+                .map(str::to_string)
+                .ok_or_else(|| OtaError::Malformed(format!("manifest has no `{name}`")))
+        };
+        let (version, signed_hash) = (field("v")?, field("h")?);
+        let hash = to_hex(&Sha256::digest(image));
+        if !signed_hash.eq_ignore_ascii_case(&hash) {
+            return Err(OtaError::Mismatch("hash"));
+        }
+        Ok(Self {
+            device_class: device_class.to_string(),
+            version,
+            hash,
+            size: image.len() as u64,
+            chunks: image.chunks(chunk_size.max(1)).map(chunk_hash).collect(),
+            manifest: manifest.to_string(),
+        })
+    }
+
+    /// The document stored under [`release_key`].
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("release serializes")
+    }
+
+    pub fn decode(document: &str) -> Result<Self, OtaError> {
+        serde_json::from_str(document).map_err(|e| OtaError::Malformed(e.to_string()))
+    }
+
+    /// Check the manifest's signature and that the release describes the
+    /// image the manifest signs.
+    pub fn verify(&self, firmware_key: &[u8; 32]) -> Result<(), OtaError> {
+        let (version, chunks, hash, _) =
+            protocol::verify_manifest_json_full(self.manifest.as_bytes(), firmware_key)
+                .ok_or(OtaError::BadManifest)?;
+        if version != self.version {
+            return Err(OtaError::Mismatch("version"));
+        }
+        if !hash.eq_ignore_ascii_case(&self.hash) {
+            return Err(OtaError::Mismatch("hash"));
+        }
+        let size = usize::try_from(self.size).map_err(|_| OtaError::Mismatch("size"))?;
+        if chunks != protocol::n_chunks_for_len(size) || self.chunks.is_empty() {
+            return Err(OtaError::Mismatch("size"));
+        }
+        Ok(())
+    }
+
+    /// Put the image together from its chunks, read through `get`, and
+    /// check it against the release.
+    pub fn assemble(&self, get: impl Fn(&str) -> Option<Vec<u8>>) -> Result<Vec<u8>, OtaError> {
+        let mut image = Vec::new();
+        for hash in &self.chunks {
+            let chunk = get(hash).ok_or_else(|| OtaError::MissingChunk(hash.clone()))?;
+            image.extend_from_slice(&chunk);
+        }
+        if image.len() as u64 != self.size || to_hex(&Sha256::digest(&image)) != self.hash {
+            return Err(OtaError::BadImage(self.hash.clone()));
+        }
+        Ok(image)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtaConfig {
+    /// Off by default: releases are neither fetched nor installed.
+    pub enabled: bool,
+    /// Ed25519 public key manifests must be signed with, as devices embed it.
+    pub firmware_key: Option<[u8; 32]>,
+    /// Version running now, whose release is not fetched.
+    pub current_version: Option<String>,
+    /// Lowest energy score at which a battery-powered node pulls chunks.
+    pub min_energy: f32,
+    /// Most chunk requests in flight.
+    pub parallel: usize,
+    /// How long a chunk request may go unanswered before it is sent again,
+    /// to the next peer.
+    pub request_timeout: Duration,
+}
+
+impl Default for OtaConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            firmware_key: None,
+            current_version: None,
+            min_energy: 0.8,
+            parallel: 4,
+            request_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl OtaConfig {
+    /// Whether `release` is signed with the firmware key and approved by one
+    /// of `policy`'s operators, reading approvals through `lookup`.
+    pub fn admit(
+        &self,
+        release: &FirmwareRelease,
+        policy: &ArtifactPolicy,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), OtaError> {
+        let key = self.firmware_key.as_ref().ok_or(OtaError::NoFirmwareKey)?;
+        release.verify(key)?;
+        policy.approved(&release.hash, lookup)?;
+        Ok(())
+    }
+
+    /// Whether chunks may be pulled now.
+    pub fn may_fetch(&self, energy: f32, is_mains: bool) -> bool {
+        is_mains || energy >= self.min_energy
+    }
+}
+
+/// Platform hook that installs a verified image, e.g. by writing it to the
+/// inactive flash slot or forwarding it to a device.
+pub trait Installer: Send + Sync {
+    fn install(
+        &self,
+        release: &FirmwareRelease,
+        image: &[u8],
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// The chunks of a release a node still lacks.
+#[derive(Debug, Clone)]
+pub struct Download {
+    pub release: FirmwareRelease,
+    /// Missing chunks by index, with when each was last requested.
+    missing: BTreeMap<usize, Option<Instant>>,
+    /// Next peer to ask.
+    cursor: usize,
+}
+
+impl Download {
+    /// Start on `release`, skipping the chunks `has` says are stored.
+    pub fn new(release: FirmwareRelease, has: impl Fn(&str) -> bool) -> Self {
+        let missing = release
+            .chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, hash)| !has(hash))
+            .map(|(index, _)| (index, None))
+            .collect();
+        Self {
+            release,
+            missing,
+            cursor: 0,
+        }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.missing.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    /// Chunk requests to send now, each to the next of `peers` in turn:
+    /// those not yet requested and those unanswered for `timeout`, up to
+    /// `parallel` in flight.
+    pub fn requests<P: Clone>(
+        &mut self,
+        peers: &[P],
+        parallel: usize,
+        timeout: Duration,
+        now: Instant,
+    ) -> Vec<(P, String)> {
+        if peers.is_empty() {
+            return Vec::new();
+        }
+        let mut in_flight = self
+            .missing
+            .values()
+            .filter(|sent| sent.is_some_and(|at| now.duration_since(at) < timeout))
+            .count();
+        let mut requests: Vec<(P, String)> = Vec::new();
+        for (index, sent) in self.missing.iter_mut() {
+            if in_flight >= parallel {
+                break;
+            }
+            if sent.is_some_and(|at| now.duration_since(at) < timeout) {
+                continue;
+            }
+            let hash = &self.release.chunks[*index];
+            *sent = Some(now);
+            // Repeated chunks, such as runs of blank flash, are asked once.
+            if requests.iter().any(|(_, requested)| requested == hash) {
+                continue;
+            }
+            requests.push((peers[self.cursor % peers.len()].clone(), hash.clone()));
+            self.cursor = self.cursor.wrapping_add(1);
+            in_flight += 1;
+        }
+        requests
+    }
+
+    /// The chunk `hash` is stored. Returns whether this download lacked it.
+    pub fn received(&mut self, hash: &str) -> bool {
+        let chunks = &self.release.chunks;
+        let before = self.missing.len();
+        self.missing.retain(|index, _| chunks[*index] != hash);
+        self.missing.len() < before
+    }
+
+    /// The request for `hash` came back empty or bad; ask again next round.
+    pub fn failed(&mut self, hash: &str) {
+        for (index, sent) in self.missing.iter_mut() {
+            if self.release.chunks[*index] == hash {
+                *sent = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::peer_id_of;
+    use crate::compute::registry::{artifact_key, ArtifactApproval};
+    use base64::Engine;
+    use ed25519_dalek::{Signer, SigningKey};
+    use std::collections::HashMap;
+
+    fn manifest(key: &SigningKey, version: &str, image: &[u8]) -> String {
+        let hash = Sha256::digest(image);
+        let chunks = protocol::n_chunks_for_len(image.len());
+        let payload = protocol::build_signing_payload(version, &hash, chunks);
+        let signature =
+            base64::engine::general_purpose::STANDARD.encode(key.sign(&payload).to_bytes());
+        protocol::build_manifest_json(version, &to_hex(&hash), chunks, &signature)
+    }
+
+    #[test]
+    fn releases_need_the_firmware_key_and_an_approval() {
+        let firmware = SigningKey::from_bytes(&[3; 32]);
+        let operator = SigningKey::from_bytes(&[4; 32]);
+        let image = vec![0xa5; 1000];
+        let release = FirmwareRelease::new(
+            "esp32c6",
+            &manifest(&firmware, "1.2.0", &image),
+            &image,
+            256,
+        )
+        .unwrap();
+        assert_eq!(release.chunks.len(), 4);
+        assert_eq!(
+            FirmwareRelease::new("esp32c6", &release.manifest, b"other", 256),
+            Err(OtaError::Mismatch("hash"))
+        );
+
+        let mut config = OtaConfig::default();
+        let policy = ArtifactPolicy {
+            enforce: false,
+            operators: vec![peer_id_of(&operator).to_string()],
+        };
+        let mut registry = HashMap::new();
+        let admit =
+            |config: &OtaConfig, release: &FirmwareRelease, registry: &HashMap<String, String>| {
+                config.admit(release, &policy, |key| registry.get(key).cloned())
+            };
+        assert_eq!(
+            admit(&config, &release, &registry),
+            Err(OtaError::NoFirmwareKey)
+        );
+        config.firmware_key = Some(operator.verifying_key().to_bytes());
+        assert_eq!(
+            admit(&config, &release, &registry),
+            Err(OtaError::BadManifest)
+        );
+        config.firmware_key = Some(firmware.verifying_key().to_bytes());
+        let relabelled = FirmwareRelease {
+            version: "9.9.9".into(),
+            ..release.clone()
+        };
+        assert_eq!(
+            admit(&config, &relabelled, &registry),
+            Err(OtaError::Mismatch("version"))
+        );
+        assert!(matches!(
+            admit(&config, &release, &registry),
+            Err(OtaError::NotApproved(ArtifactError::NotApproved(_)))
+        ));
+
+        let approval = ArtifactApproval::sign(&operator, &release.hash, "esp32c6-1.2.0", true, 1);
+        registry.insert(artifact_key(&release.hash), approval.encode());
+        assert_eq!(admit(&config, &release, &registry), Ok(()));
+        let decoded = FirmwareRelease::decode(&release.encode()).unwrap();
+        assert_eq!(decoded, release);
+    }
+
+    #[test]
+    fn downloads_spread_requests_and_retry_after_a_timeout() {
+        let firmware = SigningKey::from_bytes(&[3; 32]);
+        let mut image = vec![0u8; 8];
+        image.extend_from_slice(b"bootcode");
+        image.extend_from_slice(b"app");
+        let release =
+            FirmwareRelease::new("esp32c6", &manifest(&firmware, "2.0.0", &image), &image, 4)
+                .unwrap();
+        // Two blank chunks, two of boot code, one short tail.
+        assert_eq!(release.chunks.len(), 5);
+        let stored: HashMap<String, Vec<u8>> = image
+            .chunks(4)
+            .map(|c| (chunk_hash(c), c.to_vec()))
+            .collect();
+        let tail = chunk_hash(b"app");
+
+        let mut download = Download::new(release.clone(), |hash| *hash == tail);
+        assert_eq!(download.remaining(), 4);
+        let t0 = Instant::now();
+        let timeout = Duration::from_secs(30);
+        let sent = download.requests(&["a", "b"], 2, timeout, t0);
+        assert_eq!(
+            sent,
+            [("a", chunk_hash(&[0; 4])), ("b", chunk_hash(b"boot"))]
+        );
+        assert!(download.requests(&["a", "b"], 2, timeout, t0).is_empty());
+
+        assert!(download.received(&chunk_hash(&[0; 4])));
+        assert_eq!(download.remaining(), 2);
+        download.failed(&chunk_hash(b"boot"));
+        let sent = download.requests(&["a", "b"], 2, timeout, t0);
+        assert_eq!(
+            sent,
+            [("a", chunk_hash(b"boot")), ("b", chunk_hash(b"code"))]
+        );
+        // Unanswered requests go out again.
+        let later = t0 + timeout;
+        let sent = download.requests(&["a", "b"], 2, timeout, later);
+        let hashes: Vec<_> = sent.into_iter().map(|(_, hash)| hash).collect();
+        assert_eq!(hashes, [chunk_hash(b"boot"), chunk_hash(b"code")]);
+
+        download.received(&chunk_hash(b"boot"));
+        download.received(&chunk_hash(b"code"));
+        assert!(download.is_complete());
+        assert_eq!(
+            release.assemble(|hash| stored.get(hash).cloned()),
+            Ok(image)
+        );
+        assert_eq!(
+            release.assemble(|_| None),
+            Err(OtaError::MissingChunk(release.chunks[0].clone()))
+        );
+    }
+}
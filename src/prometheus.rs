@@ -0,0 +1,142 @@
+//! Prometheus text exposition of the node's mesh metrics.
+//!
+//! [`render_mesh`] writes a [`MeshStats`] in the text format Prometheus
+//! scrapes. Running totals are counters; the windowed delivery counts cover
+//! the last minute and are gauges, so a dashboard can plot them as rates
+//! without differencing. The gateway serves the page at `GET /metrics`.
+
+use crate::mesh::MeshStats;
+use std::fmt::Write;
+
+/// Content type of the exposition format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Counter,
+    Gauge,
+}
+
+#[derive(Default)]
+struct Exposition(String);
+
+impl Exposition {
+    fn metric(&mut self, name: &str, kind: Kind, help: &str, value: f64) {
+        let kind = match kind {
+            Kind::Counter => "counter",
+            Kind::Gauge => "gauge",
+        };
+        let _ = writeln!(self.0, "# HELP {name} {help}");
+        let _ = writeln!(self.0, "# TYPE {name} {kind}");
+        let _ = writeln!(self.0, "{name} {value}");
+    }
+}
+
+/// `stats` as a Prometheus scrape page.
+pub fn render_mesh(stats: &MeshStats) -> String {
+    use Kind::{Counter, Gauge};
+
+    let window = &stats.deliveries;
+    let mut page = Exposition::default();
+    page.metric(
+        "hypha_mesh_peers",
+        Gauge,
+        "Peers in the gossip mesh.",
+        stats.mesh_size as f64,
+    );
+    page.metric(
+        "hypha_mesh_known_peers",
+        Gauge,
+        "Peers the mesh knows of.",
+        stats.known_peers as f64,
+    );
+    page.metric(
+        "hypha_mesh_median_score",
+        Gauge,
+        "Median score of the mesh peers.",
+        stats.median_score as f64,
+    );
+    page.metric(
+        "hypha_mesh_messages_cached",
+        Gauge,
+        "Message ids in the mesh's message cache.",
+        stats.messages_cached as f64,
+    );
+    page.metric(
+        "hypha_mesh_duplicates_total",
+        Counter,
+        "Duplicate deliveries since the node started.",
+        stats.duplicate_count as f64,
+    );
+    page.metric(
+        "hypha_mesh_evicted_peers_total",
+        Counter,
+        "Peers evicted to stay within the known-peer cap.",
+        stats.evicted_count as f64,
+    );
+    page.metric(
+        "hypha_gossip_first_deliveries_per_minute",
+        Gauge,
+        "Messages first seen in the last minute.",
+        window.first_deliveries as f64,
+    );
+    page.metric(
+        "hypha_gossip_duplicates_per_minute",
+        Gauge,
+        "Duplicate deliveries in the last minute.",
+        window.duplicates as f64,
+    );
+    page.metric(
+        "hypha_gossip_ihave_hits_per_minute",
+        Gauge,
+        "IHAVE ids already cached, in the last minute.",
+        window.ihave_hits as f64,
+    );
+    page.metric(
+        "hypha_gossip_ihave_misses_per_minute",
+        Gauge,
+        "IHAVE ids missing and asked for, in the last minute.",
+        window.ihave_misses as f64,
+    );
+    if let Some(efficiency) = stats.gossip_efficiency {
+        page.metric(
+            "hypha_gossip_efficiency",
+            Gauge,
+            "First deliveries over all deliveries in the last minute.",
+            efficiency as f64,
+        );
+    }
+    page.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::DeliveryCounts;
+
+    #[test]
+    fn mesh_stats_render_as_typed_samples() {
+        let stats = MeshStats {
+            mesh_size: 6,
+            duplicate_count: 40,
+            deliveries: DeliveryCounts {
+                first_deliveries: 30,
+                duplicates: 10,
+                ihave_hits: 2,
+                ihave_misses: 1,
+            },
+            gossip_efficiency: Some(0.75),
+            ..MeshStats::default()
+        };
+        let page = render_mesh(&stats);
+        assert!(page.contains(
+            "# TYPE hypha_mesh_duplicates_total counter\nhypha_mesh_duplicates_total 40\n"
+        ));
+        assert!(
+            page.contains("# TYPE hypha_gossip_efficiency gauge\nhypha_gossip_efficiency 0.75\n")
+        );
+        assert!(page.contains("hypha_gossip_duplicates_per_minute 10\n"));
+        assert!(page.contains("hypha_mesh_peers 6\n"));
+        assert!(!render_mesh(&MeshStats::default()).contains("hypha_gossip_efficiency"));
+    }
+}
@@ -0,0 +1,211 @@
+//! Where stored messages came from.
+//!
+//! Gossip a node keeps is stored as an opaque `msg_<id>` blob. Alongside
+//! each, the node writes a [`Provenance`] record to its own fjall keyspace:
+//! the topic, the peer it arrived from and its author, when it arrived, its
+//! size and whether this node relayed it. Records are keyed by arrival time,
+//! so [`ProvenanceLog::query`] returns them oldest first and stops reading
+//! once it is past the end of the requested window.
+
+use fjall::{Database, Keyspace, KeyspaceCreateOptions};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const KEY_PREFIX: &str = "prov_";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProvenanceError {
+    #[error("provenance storage error: {0}")]
+    Storage(#[from] fjall::Error),
+    #[error("provenance record {key} is unreadable: {source}")]
+    Decode {
+        key: String,
+        source: serde_json::Error,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Id of the stored message; its payload is under `msg_<id>`.
+    pub id: String,
+    pub topic: String,
+    /// Peer the message was received from.
+    pub from: String,
+    /// Peer that signed the message, when it differs from `from`.
+    pub author: Option<String>,
+    /// Unix time in milliseconds.
+    pub received_at: u64,
+    pub size: usize,
+    /// Whether this node forwarded it.
+    pub relayed: bool,
+}
+
+/// Filter for [`ProvenanceLog::query`]. Unset fields match everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProvenanceQuery {
+    pub topic: Option<String>,
+    /// Matches either the peer a message came from or its author.
+    pub peer: Option<String>,
+    /// Inclusive lower bound on `received_at`.
+    pub since: Option<u64>,
+    /// Exclusive upper bound on `received_at`.
+    pub until: Option<u64>,
+    pub limit: Option<usize>,
+}
+
+impl ProvenanceQuery {
+    pub fn topic(mut self, topic: impl Into<String>) -> Self {
+        self.topic = Some(topic.into());
+        self
+    }
+
+    pub fn peer(mut self, peer: impl Into<String>) -> Self {
+        self.peer = Some(peer.into());
+        self
+    }
+
+    /// Records received in `since..until`, in Unix milliseconds.
+    pub fn between(mut self, since: u64, until: u64) -> Self {
+        self.since = Some(since);
+        self.until = Some(until);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn matches(&self, record: &Provenance) -> bool {
+        self.topic
+            .as_ref()
+            .is_none_or(|topic| record.topic == *topic)
+            && self
+                .peer
+                .as_ref()
+                .is_none_or(|peer| record.from == *peer || record.author.as_ref() == Some(peer))
+            && self.since.is_none_or(|since| record.received_at >= since)
+            && self.until.is_none_or(|until| record.received_at < until)
+    }
+}
+
+#[derive(Clone)]
+pub struct ProvenanceLog {
+    keyspace: Keyspace,
+}
+
+impl ProvenanceLog {
+    pub fn open(storage: &Database) -> Result<Self, ProvenanceError> {
+        let keyspace = storage.keyspace("hypha_provenance", KeyspaceCreateOptions::default)?;
+        Ok(Self { keyspace })
+    }
+
+    pub fn record(&self, record: &Provenance) -> Result<(), ProvenanceError> {
+        let value = serde_json::to_vec(record).expect("provenance serializes");
+        self.keyspace
+            .insert(record_key(record.received_at, &record.id), value)?;
+        Ok(())
+    }
+
+    /// Matching records, oldest first.
+    pub fn query(&self, query: &ProvenanceQuery) -> Result<Vec<Provenance>, ProvenanceError> {
+        let keys: Vec<_> = self
+            .keyspace
+            .prefix(KEY_PREFIX)
+            .map(|item| item.key())
+            .collect::<Result<_, _>>()?;
+        let mut records = Vec::new();
+        for key in keys {
+            if query.limit.is_some_and(|limit| records.len() >= limit) {
+                break;
+            }
+            let Some(value) = self.keyspace.get(&key)? else {
+                continue;
+            };
+            let record: Provenance =
+                serde_json::from_slice(&value).map_err(|source| ProvenanceError::Decode {
+                    key: String::from_utf8_lossy(&key).into_owned(),
+                    source,
+                })?;
+            if query.until.is_some_and(|until| record.received_at >= until) {
+                break;
+            }
+            if query.matches(&record) {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    pub fn by_topic(&self, topic: &str) -> Result<Vec<Provenance>, ProvenanceError> {
+        self.query(&ProvenanceQuery::default().topic(topic))
+    }
+
+    pub fn by_peer(&self, peer: &str) -> Result<Vec<Provenance>, ProvenanceError> {
+        self.query(&ProvenanceQuery::default().peer(peer))
+    }
+
+    pub fn between(&self, since: u64, until: u64) -> Result<Vec<Provenance>, ProvenanceError> {
+        self.query(&ProvenanceQuery::default().between(since, until))
+    }
+}
+
+/// Zero-padded so the keyspace's byte order is arrival order.
+fn record_key(received_at: u64, id: &str) -> String {
+    format!("{KEY_PREFIX}{received_at:020}_{id}")
+}
+
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, topic: &str, from: &str, received_at: u64) -> Provenance {
+        Provenance {
+            id: id.to_string(),
+            topic: topic.to_string(),
+            from: from.to_string(),
+            author: None,
+            received_at,
+            size: 64,
+            relayed: false,
+        }
+    }
+
+    #[test]
+    fn queries_filter_by_topic_peer_and_time() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let storage = Database::builder(dir.path()).open().unwrap();
+            let log = ProvenanceLog::open(&storage).unwrap();
+            log.record(&record("m3", "hypha-sensor", "peer-b", 3_000))
+                .unwrap();
+            log.record(&record("m1", "hypha-sensor", "peer-a", 1_000))
+                .unwrap();
+            log.record(&Provenance {
+                author: Some("peer-c".to_string()),
+                relayed: true,
+                ..record("m2", "hypha-task", "peer-a", 2_000)
+            })
+            .unwrap();
+        }
+
+        let storage = Database::builder(dir.path()).open().unwrap();
+        let log = ProvenanceLog::open(&storage).unwrap();
+        let ids = |records: Vec<Provenance>| -> Vec<String> {
+            records.into_iter().map(|r| r.id).collect()
+        };
+        assert_eq!(ids(log.by_topic("hypha-sensor").unwrap()), ["m1", "m3"]);
+        assert_eq!(ids(log.by_peer("peer-a").unwrap()), ["m1", "m2"]);
+        assert_eq!(ids(log.by_peer("peer-c").unwrap()), ["m2"]);
+        assert_eq!(ids(log.between(1_500, 3_000).unwrap()), ["m2"]);
+        let query = ProvenanceQuery::default().peer("peer-a").limit(1);
+        assert_eq!(ids(log.query(&query).unwrap()), ["m1"]);
+        assert!(log.query(&ProvenanceQuery::default()).unwrap()[1].relayed);
+    }
+}
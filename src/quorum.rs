@@ -0,0 +1,348 @@
+//! Quorum certificates for critical swarm decisions.
+//!
+//! Spikes are prototype pressure telemetry and must never drive an
+//! actuator on their own. For actions such as "shut down zone 3", designated
+//! super-peers each sign the same [`QuorumAction`]; their approvals travel on
+//! the quorum topic, and a [`QuorumCollector`] turns them into a
+//! [`QuorumCert`] once `threshold` distinct members of the [`SignerSet`]
+//! agree. Applications gate actuators on [`QuorumCollector::certificate`].
+
+use crate::audit::to_hex;
+use crate::auth::{peer_id_of, public_key_of};
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+const DOMAIN: &[u8] = b"hypha-quorum-v1:";
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum QuorumError {
+    #[error("threshold {threshold} is not within 1..={signers}")]
+    InvalidThreshold { threshold: usize, signers: usize },
+    #[error("`{0}` is not in the signer set")]
+    UnknownSigner(String),
+    #[error("signature by `{0}` does not verify")]
+    BadSignature(String),
+    #[error("action `{0}` is outside its validity window")]
+    Expired(String),
+    #[error("{have} of {need} required signatures")]
+    BelowThreshold { have: usize, need: usize },
+}
+
+/// The decision being voted on. Times are Unix seconds.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QuorumAction {
+    /// Application-defined verb, e.g. `shutdown`.
+    pub action: String,
+    /// What the action applies to, e.g. `zone-3`.
+    pub scope: String,
+    pub issued_at: u64,
+    pub expires_at: u64,
+}
+
+impl QuorumAction {
+    fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = DOMAIN.to_vec();
+        bytes.extend(serde_json::to_vec(self).expect("quorum action serializes"));
+        bytes
+    }
+
+    /// Stable identifier shared by all approvals of this exact action.
+    pub fn id(&self) -> String {
+        to_hex(&Sha256::digest(self.signing_bytes()))
+    }
+
+    pub fn is_live(&self, now: u64) -> bool {
+        self.issued_at <= now && now < self.expires_at
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuorumSignature {
+    pub signer: String,
+    pub signature: Vec<u8>,
+}
+
+impl QuorumSignature {
+    fn verifies(&self, action: &QuorumAction) -> bool {
+        public_key_of(&self.signer)
+            .is_some_and(|key| key.verify(&action.signing_bytes(), &self.signature))
+    }
+}
+
+/// One super-peer's vote for an action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Approval {
+    pub action: QuorumAction,
+    pub signature: QuorumSignature,
+}
+
+impl Approval {
+    pub fn sign(signing_key: &SigningKey, action: QuorumAction) -> Self {
+        let signature = QuorumSignature {
+            signer: peer_id_of(signing_key).to_string(),
+            signature: signing_key
+                .sign(&action.signing_bytes())
+                .to_bytes()
+                .to_vec(),
+        };
+        Self { action, signature }
+    }
+}
+
+/// Enough approvals for one action.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuorumCert {
+    pub action: QuorumAction,
+    pub signatures: Vec<QuorumSignature>,
+}
+
+impl QuorumCert {
+    /// Check the certificate against `set` at `now`. Signatures from outside
+    /// the set, invalid ones and duplicates do not count.
+    pub fn verify(&self, set: &SignerSet, now: u64) -> Result<(), QuorumError> {
+        if !self.action.is_live(now) {
+            return Err(QuorumError::Expired(self.action.id()));
+        }
+        let mut counted: Vec<&str> = Vec::new();
+        for sig in &self.signatures {
+            if set.contains(&sig.signer)
+                && !counted.contains(&sig.signer.as_str())
+                && sig.verifies(&self.action)
+            {
+                counted.push(&sig.signer);
+            }
+        }
+        if counted.len() < set.threshold {
+            return Err(QuorumError::BelowThreshold {
+                have: counted.len(),
+                need: set.threshold,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Gossiped on the quorum topic.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum QuorumMessage {
+    Approval(Approval),
+    /// A complete certificate, so late joiners need not see every vote.
+    Cert(QuorumCert),
+}
+
+/// Super-peers allowed to approve, and how many must agree.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SignerSet {
+    signers: Vec<String>,
+    threshold: usize,
+}
+
+impl SignerSet {
+    pub fn new(signers: Vec<String>, threshold: usize) -> Result<Self, QuorumError> {
+        if threshold == 0 || threshold > signers.len() {
+            return Err(QuorumError::InvalidThreshold {
+                threshold,
+                signers: signers.len(),
+            });
+        }
+        Ok(Self { signers, threshold })
+    }
+
+    pub fn contains(&self, peer_id: &str) -> bool {
+        self.signers.iter().any(|s| s == peer_id)
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+}
+
+/// Gathers approvals into certificates.
+///
+/// With the default (empty) signer set nothing is ever certified.
+#[derive(Debug, Default)]
+pub struct QuorumCollector {
+    set: SignerSet,
+    pending: HashMap<String, QuorumCert>,
+    certified: HashMap<String, QuorumCert>,
+}
+
+impl QuorumCollector {
+    pub fn new(set: SignerSet) -> Self {
+        Self {
+            set,
+            ..Self::default()
+        }
+    }
+
+    pub fn signer_set(&self) -> &SignerSet {
+        &self.set
+    }
+
+    /// Count one approval. Returns the certificate when this approval is the
+    /// one that reaches the threshold.
+    pub fn add_approval(
+        &mut self,
+        approval: Approval,
+        now: u64,
+    ) -> Result<Option<QuorumCert>, QuorumError> {
+        let Approval { action, signature } = approval;
+        let id = action.id();
+        if !self.set.contains(&signature.signer) {
+            return Err(QuorumError::UnknownSigner(signature.signer));
+        }
+        if !action.is_live(now) {
+            return Err(QuorumError::Expired(id));
+        }
+        if !signature.verifies(&action) {
+            return Err(QuorumError::BadSignature(signature.signer));
+        }
+        if self.certified.contains_key(&id) {
+            return Ok(None);
+        }
+
+        let cert = self
+            .pending
+            .entry(id.clone())
+            .or_insert_with(|| QuorumCert {
+                action,
+                signatures: Vec::new(),
+            });
+        if !cert.signatures.iter().any(|s| s.signer == signature.signer) {
+            cert.signatures.push(signature);
+        }
+        if cert.signatures.len() < self.set.threshold {
+            return Ok(None);
+        }
+        let cert = self.pending.remove(&id).expect("pending entry exists");
+        self.certified.insert(id, cert.clone());
+        Ok(Some(cert))
+    }
+
+    /// Accept a certificate assembled elsewhere. Returns it if it was new.
+    pub fn add_cert(
+        &mut self,
+        cert: QuorumCert,
+        now: u64,
+    ) -> Result<Option<QuorumCert>, QuorumError> {
+        cert.verify(&self.set, now)?;
+        let id = cert.action.id();
+        if self.certified.contains_key(&id) {
+            return Ok(None);
+        }
+        self.pending.remove(&id);
+        self.certified.insert(id, cert.clone());
+        Ok(Some(cert))
+    }
+
+    pub fn handle(
+        &mut self,
+        message: QuorumMessage,
+        now: u64,
+    ) -> Result<Option<QuorumCert>, QuorumError> {
+        match message {
+            QuorumMessage::Approval(approval) => self.add_approval(approval, now),
+            QuorumMessage::Cert(cert) => self.add_cert(cert, now),
+        }
+    }
+
+    /// A live certificate for `action` on `scope`, if one has been collected.
+    pub fn certificate(&self, action: &str, scope: &str, now: u64) -> Option<&QuorumCert> {
+        self.certified.values().find(|cert| {
+            cert.action.action == action && cert.action.scope == scope && cert.action.is_live(now)
+        })
+    }
+
+    /// Drop expired votes and certificates.
+    pub fn prune(&mut self, now: u64) {
+        self.pending.retain(|_, cert| now < cert.action.expires_at);
+        self.certified
+            .retain(|_, cert| now < cert.action.expires_at);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn shutdown() -> QuorumAction {
+        QuorumAction {
+            action: "shutdown".to_string(),
+            scope: "zone-3".to_string(),
+            issued_at: NOW,
+            expires_at: NOW + 60,
+        }
+    }
+
+    fn keys() -> Vec<SigningKey> {
+        (1..=3)
+            .map(|seed| SigningKey::from_bytes(&[seed; 32]))
+            .collect()
+    }
+
+    fn two_of_three(keys: &[SigningKey]) -> SignerSet {
+        let signers = keys.iter().map(|k| peer_id_of(k).to_string()).collect();
+        SignerSet::new(signers, 2).unwrap()
+    }
+
+    #[test]
+    fn two_of_three_approvals_certify_the_action() {
+        let keys = keys();
+        let mut collector = QuorumCollector::new(two_of_three(&keys));
+
+        let first = Approval::sign(&keys[0], shutdown());
+        assert_eq!(collector.add_approval(first.clone(), NOW), Ok(None));
+        assert_eq!(collector.add_approval(first, NOW), Ok(None));
+        assert!(collector.certificate("shutdown", "zone-3", NOW).is_none());
+
+        let cert = collector
+            .add_approval(Approval::sign(&keys[1], shutdown()), NOW)
+            .unwrap()
+            .expect("second signer completes the quorum");
+        assert_eq!(cert.verify(collector.signer_set(), NOW), Ok(()));
+        assert!(collector.certificate("shutdown", "zone-3", NOW).is_some());
+        assert!(collector
+            .certificate("shutdown", "zone-3", NOW + 60)
+            .is_none());
+
+        let mut late = QuorumCollector::new(two_of_three(&keys));
+        assert!(late
+            .handle(QuorumMessage::Cert(cert), NOW + 1)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn outsiders_and_tampered_votes_do_not_count() {
+        let keys = keys();
+        let set = two_of_three(&keys);
+        let outsider = SigningKey::from_bytes(&[9; 32]);
+        let mut collector = QuorumCollector::new(set.clone());
+
+        assert!(matches!(
+            collector.add_approval(Approval::sign(&outsider, shutdown()), NOW),
+            Err(QuorumError::UnknownSigner(_))
+        ));
+
+        let mut tampered = Approval::sign(&keys[0], shutdown());
+        tampered.action.scope = "zone-4".to_string();
+        assert!(matches!(
+            collector.add_approval(tampered, NOW),
+            Err(QuorumError::BadSignature(_))
+        ));
+
+        let approval = Approval::sign(&keys[0], shutdown());
+        let forged = QuorumCert {
+            action: shutdown(),
+            signatures: vec![approval.signature.clone(), approval.signature],
+        };
+        assert_eq!(
+            forged.verify(&set, NOW),
+            Err(QuorumError::BelowThreshold { have: 1, need: 2 })
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Long-term peer reputation.
+//!
+//! The anomaly detector judges a peer against its own recent baseline and
+//! forgets everything on restart. [`Reputation`] keeps the slower record: per
+//! peer, how many duplicate, malformed and unsolicited messages it has sent,
+//! each count halving every `half_life` (days by default). The node saves the
+//! record to its store and loads it on start, so a chronically misbehaving
+//! peer begins every session with a lowered mesh score and, once its standing
+//! passes `shun_above`, is refused as soon as it connects.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Records whose standing decays below this are dropped by `prune`.
+const FORGET_BELOW: f64 = 0.01;
+
+/// Misbehavior counts, fractional once decayed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Offenses {
+    pub duplicates: f64,
+    pub malformed: f64,
+    /// Messages the peer had no business sending, e.g. before admission.
+    pub unsolicited: f64,
+}
+
+impl Offenses {
+    pub fn is_empty(&self) -> bool {
+        self.duplicates == 0.0 && self.malformed == 0.0 && self.unsolicited == 0.0
+    }
+
+    fn scaled(self, factor: f64) -> Self {
+        Self {
+            duplicates: self.duplicates * factor,
+            malformed: self.malformed * factor,
+            unsolicited: self.unsolicited * factor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReputationConfig {
+    pub half_life: Duration,
+    pub duplicate_weight: f64,
+    pub malformed_weight: f64,
+    pub unsolicited_weight: f64,
+    /// Mesh score deduction per point of standing, up to `max_penalty`.
+    pub penalty_per_point: f32,
+    pub max_penalty: f32,
+    /// Standing at which the peer is refused on connection. `None` never
+    /// refuses.
+    pub shun_above: Option<f64>,
+}
+
+impl Default for ReputationConfig {
+    fn default() -> Self {
+        Self {
+            half_life: Duration::from_secs(3 * 24 * 3600),
+            // Gossip duplicates also arise from honest relaying.
+            duplicate_weight: 0.1,
+            malformed_weight: 1.0,
+            unsolicited_weight: 0.5,
+            penalty_per_point: 0.01,
+            max_penalty: 0.5,
+            shun_above: Some(100.0),
+        }
+    }
+}
+
+/// A peer's offenses as of `updated_at`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct PeerRecord {
+    pub offenses: Offenses,
+    /// Unix time in seconds.
+    pub updated_at: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Reputation {
+    pub config: ReputationConfig,
+    records: HashMap<String, PeerRecord>,
+}
+
+impl Reputation {
+    pub fn new(config: ReputationConfig) -> Self {
+        Self {
+            config,
+            records: HashMap::new(),
+        }
+    }
+
+    /// Add `offenses` to `peer`'s record at Unix time `now`.
+    pub fn record(&mut self, peer: &str, offenses: Offenses, now: u64) {
+        let current = self.offenses(peer, now);
+        self.records.insert(
+            peer.to_string(),
+            PeerRecord {
+                offenses: Offenses {
+                    duplicates: current.duplicates + offenses.duplicates,
+                    malformed: current.malformed + offenses.malformed,
+                    unsolicited: current.unsolicited + offenses.unsolicited,
+                },
+                updated_at: now,
+            },
+        );
+    }
+
+    /// `peer`'s offenses decayed to `now`.
+    pub fn offenses(&self, peer: &str, now: u64) -> Offenses {
+        self.records
+            .get(peer)
+            .map_or_else(Offenses::default, |record| {
+                let elapsed = now.saturating_sub(record.updated_at) as f64;
+                let half_life = self.config.half_life.as_secs_f64().max(1.0);
+                record.offenses.scaled(0.5f64.powf(elapsed / half_life))
+            })
+    }
+
+    /// Weighted sum of `peer`'s decayed offenses; zero for a clean record.
+    pub fn standing(&self, peer: &str, now: u64) -> f64 {
+        let offenses = self.offenses(peer, now);
+        offenses.duplicates * self.config.duplicate_weight
+            + offenses.malformed * self.config.malformed_weight
+            + offenses.unsolicited * self.config.unsolicited_weight
+    }
+
+    /// Lasting mesh score deduction for `peer`.
+    pub fn penalty(&self, peer: &str, now: u64) -> f32 {
+        (self.standing(peer, now) as f32 * self.config.penalty_per_point)
+            .min(self.config.max_penalty)
+    }
+
+    pub fn shuns(&self, peer: &str, now: u64) -> bool {
+        self.config
+            .shun_above
+            .is_some_and(|limit| self.standing(peer, now) >= limit)
+    }
+
+    /// Drop records that have all but decayed away.
+    pub fn prune(&mut self, now: u64) {
+        let stale: Vec<String> = self
+            .records
+            .keys()
+            .filter(|peer| self.standing(peer, now) < FORGET_BELOW)
+            .cloned()
+            .collect();
+        for peer in stale {
+            self.records.remove(&peer);
+        }
+    }
+
+    /// The records to persist.
+    pub fn records(&self) -> &HashMap<String, PeerRecord> {
+        &self.records
+    }
+
+    /// Load persisted records, keeping any already held for the same peer.
+    pub fn restore(&mut self, records: HashMap<String, PeerRecord>) {
+        for (peer, record) in records {
+            self.records.entry(peer).or_insert(record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 3600;
+
+    #[test]
+    fn offenses_decay_over_days_and_survive_a_reload() {
+        let mut reputation = Reputation::default();
+        let now = 1_700_000_000;
+        let spam = Offenses {
+            malformed: 150.0,
+            ..Offenses::default()
+        };
+        reputation.record("spammer", spam, now);
+        reputation.record(
+            "chatty",
+            Offenses {
+                duplicates: 50.0,
+                ..Offenses::default()
+            },
+            now,
+        );
+        assert!(reputation.shuns("spammer", now));
+        assert!(!reputation.shuns("chatty", now));
+        assert_eq!(reputation.penalty("spammer", now), 0.5);
+        assert!((reputation.penalty("chatty", now) - 0.05).abs() < 1e-6);
+        assert_eq!(reputation.standing("stranger", now), 0.0);
+
+        // Reload into a fresh node three days later.
+        let saved = serde_json::to_vec(reputation.records()).unwrap();
+        let mut restarted = Reputation::default();
+        restarted.restore(serde_json::from_slice(&saved).unwrap());
+        let later = now + 3 * DAY;
+        assert!((restarted.standing("spammer", later) - 75.0).abs() < 1e-9);
+        assert!(!restarted.shuns("spammer", later));
+
+        restarted.record("spammer", spam, later);
+        assert!(restarted.shuns("spammer", later));
+
+        restarted.prune(later + 20 * DAY);
+        assert!(restarted.records().contains_key("spammer"));
+        restarted.prune(later + 365 * DAY);
+        assert!(restarted.records().is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+//! Task results sealed to the publisher.
+//!
+//! A worker encrypts its output to the ed25519 key inlined in the task's
+//! `source_id`, converted to X25519, using a libsodium-compatible sealed box.
+//! Only the publisher's signing key can open it. Tasks published with
+//! `Task::public_result` skip encryption.
+
+use crate::auth::public_key_of;
+use crate::core::{ResultPayload, Task, TaskResult};
+use ed25519_dalek::{SigningKey, VerifyingKey};
+use rand_core::OsRng;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ResultError {
+    #[error("publisher `{0}` has no recoverable ed25519 key")]
+    UnknownPublisher(String),
+    #[error("failed to seal result")]
+    Seal,
+    #[error("result is not sealed to this key")]
+    Open,
+}
+
+/// Wrap `output` for `task`'s publisher.
+pub fn seal_result(
+    task: &Task,
+    worker_id: &str,
+    output: Vec<u8>,
+) -> Result<TaskResult, ResultError> {
+    let payload = if task.public_result {
+        ResultPayload::Plain(output)
+    } else {
+        let recipient = publisher_box_key(&task.source_id)?;
+        let sealed = recipient
+            .seal(&mut OsRng, &output)
+            .map_err(|_| ResultError::Seal)?;
+        ResultPayload::Sealed(sealed)
+    };
+    Ok(TaskResult {
+        task_id: task.id.clone(),
+        worker_id: worker_id.to_string(),
+        payload,
+    })
+}
+
+/// Read a result's output with the publisher's signing key.
+pub fn open_result(signing_key: &SigningKey, result: &TaskResult) -> Result<Vec<u8>, ResultError> {
+    match &result.payload {
+        ResultPayload::Plain(output) => Ok(output.clone()),
+        ResultPayload::Sealed(sealed) => {
+            let secret = crypto_box::SecretKey::from(signing_key.to_scalar_bytes());
+            secret.unseal(sealed).map_err(|_| ResultError::Open)
+        }
+    }
+}
+
+fn publisher_box_key(source_id: &str) -> Result<crypto_box::PublicKey, ResultError> {
+    let unknown = || ResultError::UnknownPublisher(source_id.to_string());
+    let ed25519 = public_key_of(source_id)
+        .and_then(|key| key.try_into_ed25519().ok())
+        .ok_or_else(unknown)?;
+    let verifying = VerifyingKey::from_bytes(&ed25519.to_bytes()).map_err(|_| unknown())?;
+    Ok(crypto_box::PublicKey::from(
+        verifying.to_montgomery().to_bytes(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::peer_id_of;
+    use crate::core::Capability;
+
+    fn task_from(publisher: &SigningKey) -> Task {
+        Task::new(
+            "t1".to_string(),
+            Capability::Compute(1),
+            1,
+            peer_id_of(publisher).to_string(),
+        )
+    }
+
+    #[test]
+    fn only_the_publisher_opens_sealed_results() {
+        let publisher = SigningKey::from_bytes(&[7; 32]);
+        let other = SigningKey::from_bytes(&[8; 32]);
+        let result = seal_result(&task_from(&publisher), "worker", b"42".to_vec()).unwrap();
+
+        assert!(matches!(result.payload, ResultPayload::Sealed(_)));
+        assert_eq!(open_result(&publisher, &result).unwrap(), b"42");
+        assert_eq!(open_result(&other, &result), Err(ResultError::Open));
+    }
+
+    #[test]
+    fn public_results_and_unknown_publishers() {
+        let publisher = SigningKey::from_bytes(&[7; 32]);
+        let task = task_from(&publisher).with_public_result();
+        let result = seal_result(&task, "worker", b"42".to_vec()).unwrap();
+        assert_eq!(result.payload, ResultPayload::Plain(b"42".to_vec()));
+
+        let anonymous = Task::new("t2".into(), Capability::Compute(1), 1, "gateway".into());
+        assert_eq!(
+            seal_result(&anonymous, "worker", Vec::new()),
+            Err(ResultError::UnknownPublisher("gateway".to_string()))
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Role-specific defaults.
+//!
+//! A node started with a [`NodeRole`] takes that role's [`RoleDefaults`] in
+//! place of the all-round ones: which topics it joins, how wide its mesh is,
+//! how eagerly it relays and whether it bids for tasks. The role is also
+//! advertised in the node's status, where it biases peers' mesh scores and
+//! is kept for task routing (`SporeNode::peer_role`). Everything a role sets
+//! can still be changed afterwards through `HyphaConfig`. With role
+//! inference on, the role also moves with the node's relay and energy
+//! history; see `crate::differentiation`.
+
+use crate::config::HyphaConfig;
+use crate::core::{NodeRole, PowerMode};
+use crate::degradation::DegradationLadder;
+use crate::mesh::MeshConfig;
+use crate::mycelium::{SubscriptionPolicy, TopicKind};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleDefaults {
+    pub subscriptions: SubscriptionPolicy,
+    /// Mesh parameters at full energy.
+    pub mesh: MeshConfig,
+    pub relay_all_above: f32,
+    pub relay_max_pressure: f32,
+    pub relay_min_phase: f32,
+    /// Whether the node bids for tasks.
+    pub compute: bool,
+}
+
+impl RoleDefaults {
+    pub fn of(role: NodeRole) -> Self {
+        let ladder = DegradationLadder::default();
+        let all_round = Self {
+            subscriptions: SubscriptionPolicy::default(),
+            mesh: ladder.mesh,
+            relay_all_above: ladder.relay_all_above,
+            relay_max_pressure: ladder.relay_max_pressure,
+            relay_min_phase: ladder.relay_min_phase,
+            compute: false,
+        };
+        match role {
+            // Joins only what it reports on or must hear, keeps a small mesh
+            // and relays only late in the pulse at low pressure.
+            NodeRole::Sensor => {
+                let reporting = vec![
+                    TopicKind::Status,
+                    TopicKind::Control,
+                    TopicKind::Spike,
+                    TopicKind::Sensor,
+                    TopicKind::Quorum,
+                    TopicKind::Departure,
+                ];
+                Self {
+                    subscriptions: SubscriptionPolicy {
+                        normal: reporting.clone(),
+                        low_battery: reporting,
+                        ..SubscriptionPolicy::default()
+                    },
+                    mesh: MeshConfig::for_mode(&PowerMode::LowBattery),
+                    relay_all_above: 1.0,
+                    relay_max_pressure: 3.0,
+                    relay_min_phase: 0.9,
+                    ..all_round
+                }
+            }
+            // Wide mesh, relays at any phase and under more pressure.
+            NodeRole::Relay => Self {
+                mesh: MeshConfig {
+                    d: 8,
+                    d_low: 6,
+                    d_high: 16,
+                    d_lazy: 8,
+                    ..MeshConfig::default()
+                },
+                relay_all_above: 0.5,
+                relay_max_pressure: 10.0,
+                relay_min_phase: 0.0,
+                ..all_round
+            },
+            NodeRole::Compute => Self {
+                compute: true,
+                ..all_round
+            },
+            // Keeps shared state even on low battery, to follow the swarm.
+            NodeRole::Coordinator => Self {
+                subscriptions: SubscriptionPolicy {
+                    low_battery: TopicKind::ALL.to_vec(),
+                    ..SubscriptionPolicy::default()
+                },
+                ..all_round
+            },
+        }
+    }
+
+    pub fn apply_to(&self, config: &mut HyphaConfig) {
+        config.subscriptions = self.subscriptions.clone();
+        config.degradation.mesh = self.mesh.clone();
+        config.degradation.relay_all_above = self.relay_all_above;
+        config.degradation.relay_max_pressure = self.relay_max_pressure;
+        config.degradation.relay_min_phase = self.relay_min_phase;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_shape_topics_mesh_relay_and_compute() {
+        let sensor = RoleDefaults::of(NodeRole::Sensor);
+        let relay = RoleDefaults::of(NodeRole::Relay);
+        let compute = RoleDefaults::of(NodeRole::Compute);
+        assert!(!sensor
+            .subscriptions
+            .topics_for(&PowerMode::Normal)
+            .contains(&TopicKind::Task));
+        assert!(sensor.mesh.d < relay.mesh.d);
+        assert!(compute.compute && !sensor.compute && !relay.compute);
+
+        let mut config = HyphaConfig::default();
+        relay.apply_to(&mut config);
+        let ladder = &config.degradation;
+        // A half-charged relay forwards whatever the pulse phase.
+        assert!(ladder.should_relay(0.55, 8.0, 0.1));
+        assert!(!DegradationLadder::default().should_relay(0.55, 8.0, 0.1));
+
+        sensor.apply_to(&mut config);
+        assert!(!config.degradation.should_relay(1.0, 0.0, 0.8));
+    }
+}
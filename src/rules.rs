@@ -0,0 +1,250 @@
+//! Local rules mapping sensor samples to tasks and spikes.
+//!
+//! A [`Rule`] watches one sensor against a threshold: once the condition has
+//! held for `hold_ms`, it fires its [`RuleAction`] and stays quiet until the
+//! value crosses back past `clear_at` (hysteresis). `SporeNode::run_for`
+//! samples the node's sensors every heartbeat and acts on what fires, so an
+//! edge node can react without an external controller.
+
+use crate::core::Capability;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RuleError {
+    #[error("rule `{rule}`: {reason}")]
+    Invalid { rule: String, reason: &'static str },
+    #[error("rules are not valid JSON: {0}")]
+    Parse(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Above,
+    Below,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RuleAction {
+    /// Publish a task. Its id is `id` suffixed with the firing time.
+    Task {
+        id: String,
+        capability: Capability,
+        #[serde(default)]
+        priority: u8,
+    },
+    /// Raise a mesh pressure spike.
+    Spike {
+        intensity: u8,
+        #[serde(default)]
+        pattern_id: u8,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Rule {
+    pub name: String,
+    pub sensor: String,
+    pub comparison: Comparison,
+    pub threshold: f32,
+    /// Value the sensor must cross back past before the rule can fire again.
+    /// Defaults to `threshold`.
+    #[serde(default)]
+    pub clear_at: Option<f32>,
+    /// How long the condition must hold before firing.
+    #[serde(default)]
+    pub hold_ms: u64,
+    pub action: RuleAction,
+}
+
+impl Rule {
+    pub fn validate(&self) -> Result<(), RuleError> {
+        let invalid = |reason| RuleError::Invalid {
+            rule: self.name.clone(),
+            reason,
+        };
+        if !self.threshold.is_finite() {
+            return Err(invalid("threshold must be finite"));
+        }
+        let clear_at = self.clear_at();
+        let on_wrong_side = match self.comparison {
+            Comparison::Above => clear_at > self.threshold,
+            Comparison::Below => clear_at < self.threshold,
+        };
+        if !clear_at.is_finite() || on_wrong_side {
+            return Err(invalid(
+                "clear_at must be on the untriggered side of threshold",
+            ));
+        }
+        Ok(())
+    }
+
+    fn clear_at(&self) -> f32 {
+        self.clear_at.unwrap_or(self.threshold)
+    }
+
+    fn triggered(&self, value: f32) -> bool {
+        match self.comparison {
+            Comparison::Above => value > self.threshold,
+            Comparison::Below => value < self.threshold,
+        }
+    }
+
+    fn cleared(&self, value: f32) -> bool {
+        match self.comparison {
+            Comparison::Above => value <= self.clear_at(),
+            Comparison::Below => value >= self.clear_at(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RuleState {
+    Idle,
+    Holding { since: Instant },
+    Fired,
+}
+
+/// A rule that fired on the latest sample.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Firing {
+    pub rule: String,
+    pub value: f32,
+    pub action: RuleAction,
+}
+
+#[derive(Debug, Default)]
+pub struct RuleEngine {
+    rules: Vec<(Rule, RuleState)>,
+}
+
+impl RuleEngine {
+    pub fn new(rules: Vec<Rule>) -> Result<Self, RuleError> {
+        for rule in &rules {
+            rule.validate()?;
+        }
+        Ok(Self {
+            rules: rules.into_iter().map(|r| (r, RuleState::Idle)).collect(),
+        })
+    }
+
+    /// Rules from a JSON array.
+    pub fn from_json(json: &str) -> Result<Self, RuleError> {
+        Self::new(serde_json::from_str(json)?)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &Rule> {
+        self.rules.iter().map(|(rule, _)| rule)
+    }
+
+    /// The same rules, none of them holding or fired.
+    pub fn reset(&self) -> Self {
+        Self {
+            rules: self
+                .rules
+                .iter()
+                .map(|(rule, _)| (rule.clone(), RuleState::Idle))
+                .collect(),
+        }
+    }
+
+    /// Feed one sample and return the rules it fires.
+    pub fn observe(&mut self, sensor: &str, value: f32, now: Instant) -> Vec<Firing> {
+        let mut fired = Vec::new();
+        if !value.is_finite() {
+            return fired;
+        }
+        for (rule, state) in self.rules.iter_mut().filter(|(r, _)| r.sensor == sensor) {
+            *state = match *state {
+                RuleState::Fired if rule.cleared(value) => RuleState::Idle,
+                RuleState::Fired => RuleState::Fired,
+                _ if !rule.triggered(value) => RuleState::Idle,
+                RuleState::Idle => RuleState::Holding { since: now },
+                holding => holding,
+            };
+            if let RuleState::Holding { since } = *state {
+                if now.saturating_duration_since(since) >= Duration::from_millis(rule.hold_ms) {
+                    *state = RuleState::Fired;
+                    fired.push(Firing {
+                        rule: rule.name.clone(),
+                        value,
+                        action: rule.action.clone(),
+                    });
+                }
+            }
+        }
+        fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overheat() -> Rule {
+        Rule {
+            name: "overheat".to_string(),
+            sensor: "temp".to_string(),
+            comparison: Comparison::Above,
+            threshold: 80.0,
+            clear_at: Some(75.0),
+            hold_ms: 30_000,
+            action: RuleAction::Spike {
+                intensity: 255,
+                pattern_id: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn fires_after_hold_and_rearms_below_clear_point() {
+        let mut engine = RuleEngine::new(vec![overheat()]).unwrap();
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+
+        assert!(engine.observe("temp", 85.0, at(0)).is_empty());
+        assert!(engine.observe("temp", 85.0, at(20)).is_empty());
+        assert_eq!(engine.observe("temp", 86.0, at(30)).len(), 1);
+        // Still hot, or cooled only into the hysteresis band: no refire.
+        assert!(engine.observe("temp", 90.0, at(90)).is_empty());
+        assert!(engine.observe("temp", 78.0, at(100)).is_empty());
+        assert!(engine.observe("temp", 85.0, at(200)).is_empty());
+        // Cooled past clear_at, then hot again for the full hold.
+        assert!(engine.observe("temp", 70.0, at(210)).is_empty());
+        assert!(engine.observe("temp", 85.0, at(220)).is_empty());
+        assert_eq!(engine.observe("temp", 85.0, at(250)).len(), 1);
+    }
+
+    #[test]
+    fn dip_during_hold_restarts_the_timer() {
+        let mut engine = RuleEngine::new(vec![overheat()]).unwrap();
+        let t0 = Instant::now();
+        let at = |secs| t0 + Duration::from_secs(secs);
+
+        engine.observe("temp", 85.0, at(0));
+        engine.observe("temp", 79.0, at(20));
+        assert!(engine.observe("temp", 85.0, at(30)).is_empty());
+        assert!(engine.observe("humidity", 85.0, at(60)).is_empty());
+        assert_eq!(engine.observe("temp", 85.0, at(60)).len(), 1);
+    }
+
+    #[test]
+    fn rules_load_from_json_and_reject_inverted_hysteresis() {
+        let engine = RuleEngine::from_json(
+            r#"[{"name": "cold", "sensor": "temp", "comparison": "below", "threshold": 2.0,
+                 "action": {"kind": "task", "id": "heat", "capability": {"Compute": 1}}}]"#,
+        )
+        .unwrap();
+        assert_eq!(engine.rules().count(), 1);
+
+        let mut inverted = overheat();
+        inverted.clear_at = Some(90.0);
+        assert!(RuleEngine::new(vec![inverted]).is_err());
+    }
+}
@@ -0,0 +1,269 @@
+//! Registry of message schemas.
+//!
+//! [`ENVELOPE_VERSION`](crate::wire::ENVELOPE_VERSION) versions the frame
+//! every message travels in; the bodies inside evolve on their own. Each
+//! gossiped body type has a [`Schema`] here giving its version and the
+//! version that introduced each of its top-level fields (or, for enums, its
+//! variants), so the history of the wire format is readable in one place.
+//!
+//! Peers of different versions share a mesh, so a body type only ever
+//! changes in ways older readers tolerate:
+//!
+//! - A new field is `#[serde(default)]`, is skipped when empty where it can
+//!   be, and gets the next version here. Older readers ignore it, and relays
+//!   pass it on untouched, since they forward the bytes or re-encode the
+//!   body as raw JSON.
+//! - Renaming, retyping or removing a field, or giving an enum a variant
+//!   older readers must understand, is not a version bump: it needs a new
+//!   type on a new topic.
+//! - A change to an encoding also needs new wire fixtures; see
+//!   [`crate::fixtures`].
+
+use crate::mycelium::TopicKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schema {
+    /// Matches the type's fixture name where it has one.
+    pub name: &'static str,
+    /// The topic carrying it; `None` for types only applications send.
+    pub topic: Option<TopicKind>,
+    pub version: u16,
+    /// Top-level JSON keys and the version that added each.
+    pub fields: &'static [(&'static str, u16)],
+}
+
+impl Schema {
+    pub fn knows(&self, field: &str) -> bool {
+        self.fields.iter().any(|(name, _)| *name == field)
+    }
+
+    /// Top-level keys of an encoded body that this version does not know,
+    /// i.e. fields a newer peer added.
+    pub fn unknown_fields<'a>(&self, body: &'a serde_json::Value) -> Vec<&'a str> {
+        body.as_object()
+            .into_iter()
+            .flat_map(|object| object.keys())
+            .map(String::as_str)
+            .filter(|key| !self.knows(key))
+            .collect()
+    }
+}
+
+pub const TASK: Schema = Schema {
+    name: "task",
+    topic: Some(TopicKind::Task),
+    version: 7,
+    fields: &[
+        ("id", 1),
+        ("required_capability", 1),
+        ("priority", 1),
+        ("reach_intensity", 1),
+        ("source_id", 1),
+        ("auth_token", 1),
+        ("public_result", 2),
+        ("tenant", 3),
+        ("memo_key", 4),
+        ("geohash", 5),
+        ("target", 6),
+        ("expected_output_bytes", 7),
+    ],
+};
+
+pub const BID: Schema = Schema {
+    name: "bid",
+    topic: None,
+    version: 2,
+    fields: &[
+        ("task_id", 1),
+        ("bidder_id", 1),
+        ("energy_score", 1),
+        ("cost_mah", 1),
+        ("price", 2),
+    ],
+};
+
+pub const ENERGY_STATUS: Schema = Schema {
+    name: "energy_status",
+    topic: Some(TopicKind::Status),
+    version: 10,
+    fields: &[
+        ("source_id", 1),
+        ("energy_score", 1),
+        ("facts", 2),
+        ("topics", 3),
+        ("grants", 4),
+        ("role", 5),
+        ("attestation", 6),
+        ("zone", 7),
+        ("state", 8),
+        ("catalog_version", 9),
+        ("health", 10),
+    ],
+};
+
+pub const CATALOG_DELTA: Schema = Schema {
+    name: "catalog_delta",
+    topic: Some(TopicKind::Status),
+    version: 1,
+    fields: &[
+        ("owner", 1),
+        ("from", 1),
+        ("to", 1),
+        ("added", 1),
+        ("removed", 1),
+    ],
+};
+
+pub const MESH_CONTROL: Schema = Schema {
+    name: "mesh_control",
+    topic: Some(TopicKind::Control),
+    version: 1,
+    fields: &[
+        ("sender", 1),
+        ("target", 1),
+        ("control", 1),
+        ("signature", 1),
+    ],
+};
+
+pub const LEASE_MESSAGE: Schema = Schema {
+    name: "lease_message",
+    topic: Some(TopicKind::Task),
+    version: 2,
+    fields: &[
+        ("kind", 1),
+        ("task", 1),
+        ("winner", 1),
+        ("lease_secs", 1),
+        ("task_id", 1),
+        ("reason", 2),
+    ],
+};
+
+pub const SPIKE: Schema = Schema {
+    name: "spike",
+    topic: Some(TopicKind::Spike),
+    version: 4,
+    fields: &[
+        ("source", 1),
+        ("intensity", 1),
+        ("pattern_id", 1),
+        ("proof", 2),
+        ("signature", 3),
+        ("issued_at", 4),
+        ("seq", 4),
+    ],
+};
+
+pub const SYNC_MESSAGE: Schema = Schema {
+    name: "sync_message",
+    topic: Some(TopicKind::SharedState),
+    version: 1,
+    fields: &[("Update", 1), ("SyncStep1", 1), ("SyncStep2", 1)],
+};
+
+pub const TENANT_SYNC: Schema = Schema {
+    name: "tenant_sync",
+    topic: Some(TopicKind::SharedState),
+    version: 1,
+    fields: &[("tenant", 1), ("message", 1)],
+};
+
+pub const SENSOR_READING: Schema = Schema {
+    name: "sensor_reading",
+    topic: Some(TopicKind::Sensor),
+    version: 4,
+    fields: &[
+        ("source_id", 1),
+        ("sensor", 1),
+        ("value", 1),
+        ("unit", 2),
+        ("timestamp_ms", 3),
+        ("summary", 4),
+    ],
+};
+
+pub const QUORUM_MESSAGE: Schema = Schema {
+    name: "quorum_message",
+    topic: Some(TopicKind::Quorum),
+    version: 1,
+    fields: &[("Approval", 1), ("Cert", 1)],
+};
+
+pub const DEPARTING: Schema = Schema {
+    name: "departing",
+    topic: Some(TopicKind::Departure),
+    version: 1,
+    fields: &[("peer", 1), ("eta_secs", 1), ("in_flight", 1)],
+};
+
+/// Every registered schema.
+pub const REGISTRY: &[Schema] = &[
+    TASK,
+    BID,
+    ENERGY_STATUS,
+    CATALOG_DELTA,
+    MESH_CONTROL,
+    LEASE_MESSAGE,
+    SPIKE,
+    SYNC_MESSAGE,
+    TENANT_SYNC,
+    SENSOR_READING,
+    QUORUM_MESSAGE,
+    DEPARTING,
+];
+
+pub fn lookup(name: &str) -> Option<&'static Schema> {
+    REGISTRY.iter().find(|schema| schema.name == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Capability, SensorReading, Task};
+    use crate::fixtures;
+    use crate::wire;
+    use std::collections::HashSet;
+
+    #[test]
+    fn versions_match_the_field_history() {
+        let mut names = HashSet::new();
+        for schema in REGISTRY {
+            assert!(
+                names.insert(schema.name),
+                "{} registered twice",
+                schema.name
+            );
+            let newest = schema.fields.iter().map(|(_, since)| *since).max();
+            assert_eq!(newest, Some(schema.version), "{}", schema.name);
+        }
+    }
+
+    #[test]
+    fn registry_covers_every_field_on_the_wire() {
+        for fixture in fixtures::all() {
+            let schema = lookup(fixture.name).unwrap();
+            let envelope = wire::decode::<serde_json::Value>(&fixture.encoded).unwrap();
+            assert!(
+                schema.unknown_fields(&envelope.body).is_empty(),
+                "{}",
+                schema.name
+            );
+        }
+        let task = Task::new("t".into(), Capability::Compute(1), 1, "p".into())
+            .with_public_result()
+            .with_tenant("farm")
+            .with_memo_key("k".into())
+            .with_geohash("u4pr")
+            .with_target("q")
+            .with_expected_output_bytes(1 << 20);
+        let task = serde_json::to_value(task).unwrap();
+        assert!(TASK.unknown_fields(&task).is_empty());
+        assert_eq!(task.as_object().unwrap().len(), TASK.fields.len());
+
+        let mut reading =
+            serde_json::to_value(SensorReading::new("n".into(), "t".into(), 1.0)).unwrap();
+        reading["calibrated"] = serde_json::json!(true);
+        assert_eq!(SENSOR_READING.unknown_fields(&reading), vec!["calibrated"]);
+    }
+}
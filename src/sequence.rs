@@ -0,0 +1,455 @@
+//! Per-source sequence numbers for gap detection.
+//!
+//! Gossip gives no delivery guarantee: a message that misses every mesh link
+//! to a node is simply gone. With sequencing enabled, a node stamps each
+//! envelope it publishes on the [`SequenceConfig::topics`] with a
+//! [`Sequence`]: its peer id and a number one higher than the last. A
+//! [`Sequencer`] keeps the newest `retain` stamped envelopes for resending,
+//! and reserves numbers in blocks of `reserve` so that they stay increasing
+//! across restarts; only the reservation is persisted.
+//!
+//! Receivers track the highest number seen from each source in a
+//! [`GapTracker`]. A jump past it marks the numbers in between missing;
+//! after `request_after` the receiver asks the source for them over the
+//! [`SEQUENCE_PROTOCOL`] request-response protocol, up to `max_requests`
+//! times, and then counts them lost. A source answers with what it still
+//! retains and the oldest number it could resend, so receivers give up on
+//! older ones at once. Arrivals already seen are duplicates and dropped.
+//!
+//! Resent messages come straight from their source over an authenticated
+//! connection rather than with a gossipsub signature. Only tasks and sensor
+//! readings are replayed when recovered; gaps on other topics are detected
+//! and reported only.
+
+use crate::mycelium::TopicKind;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+pub const SEQUENCE_PROTOCOL: &str = "/hypha/seq/1.0.0";
+
+/// Most envelopes one resend response carries.
+pub const MAX_RESEND: usize = 64;
+
+/// A message's place in its source's stream.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sequence {
+    /// Peer id of the publishing node.
+    pub source: String,
+    pub n: u64,
+}
+
+/// Numbers a receiver missed, as inclusive ranges.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResendRequest {
+    pub ranges: Vec<(u64, u64)>,
+}
+
+/// A retained envelope, as published.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Resent {
+    pub n: u64,
+    pub topic: TopicKind,
+    pub envelope: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResendResponse {
+    pub messages: Vec<Resent>,
+    /// Lowest number the source can still resend; anything below is gone.
+    pub oldest: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequenceConfig {
+    /// Off by default: nothing is stamped and gaps are not tracked.
+    pub enabled: bool,
+    /// Topics whose publishes are stamped and whose gaps are tracked.
+    pub topics: Vec<TopicKind>,
+    /// Stamped envelopes kept for resending.
+    pub retain: usize,
+    /// Numbers reserved, and persisted, at a time.
+    pub reserve: u64,
+    /// How long a gap may stay open before it is requested, and between
+    /// requests.
+    pub request_after: Duration,
+    /// Requests for a gap before its messages count as lost.
+    pub max_requests: u32,
+    /// Most missing numbers tracked per source; a bigger jump counts the
+    /// oldest ones lost at once, and an arrival this far behind the highest
+    /// number means the source started over.
+    pub max_gap: u64,
+}
+
+impl Default for SequenceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            topics: vec![TopicKind::Task, TopicKind::Sensor],
+            retain: 256,
+            reserve: 64,
+            request_after: Duration::from_secs(2),
+            max_requests: 3,
+            max_gap: 128,
+        }
+    }
+}
+
+impl SequenceConfig {
+    pub fn sequences(&self, topic: TopicKind) -> bool {
+        self.enabled && self.topics.contains(&topic)
+    }
+}
+
+/// Stamps this node's publishes and keeps them for resending.
+pub struct Sequencer {
+    pub config: SequenceConfig,
+    source: String,
+    next: u64,
+    reserved: u64,
+    retained: VecDeque<Resent>,
+    /// Stores a new reservation before numbers from it go out.
+    persist: Option<Box<dyn Fn(u64) + Send>>,
+}
+
+impl std::fmt::Debug for Sequencer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sequencer")
+            .field("source", &self.source)
+            .field("next", &self.next)
+            .field("reserved", &self.reserved)
+            .field("retained", &self.retained.len())
+            .finish()
+    }
+}
+
+impl Sequencer {
+    /// Continue the stream of `source` from `resume`, the number persisted
+    /// last.
+    pub fn new(source: &str, resume: u64) -> Self {
+        Self {
+            config: SequenceConfig::default(),
+            source: source.to_string(),
+            next: resume,
+            reserved: resume,
+            retained: VecDeque::new(),
+            persist: None,
+        }
+    }
+
+    /// Store reservations through `persist`.
+    pub fn with_persist(mut self, persist: impl Fn(u64) + Send + 'static) -> Self {
+        self.persist = Some(Box::new(persist));
+        self
+    }
+
+    /// Where the stream continues after a clean shutdown.
+    pub fn next(&self) -> u64 {
+        self.next
+    }
+
+    /// The stamp for the next publish on `topic`, or `None` if `topic` is
+    /// not sequenced.
+    pub fn stamp(&mut self, topic: TopicKind) -> Option<Sequence> {
+        if !self.config.sequences(topic) {
+            return None;
+        }
+        let n = self.next;
+        if n >= self.reserved {
+            self.reserved = n + self.config.reserve.max(1);
+            if let Some(persist) = &self.persist {
+                persist(self.reserved);
+            }
+        }
+        self.next += 1;
+        Some(Sequence {
+            source: self.source.clone(),
+            n,
+        })
+    }
+
+    /// Keep the envelope published as `n` for resending.
+    pub fn retain(&mut self, n: u64, topic: TopicKind, envelope: Vec<u8>) {
+        self.retained.push_back(Resent { n, topic, envelope });
+        while self.retained.len() > self.config.retain {
+            self.retained.pop_front();
+        }
+    }
+
+    /// Answer a receiver's request from what is still retained.
+    pub fn resend(&self, request: &ResendRequest) -> ResendResponse {
+        let messages = self
+            .retained
+            .iter()
+            .filter(|resent| {
+                request
+                    .ranges
+                    .iter()
+                    .any(|&(from, to)| (from..=to).contains(&resent.n))
+            })
+            .take(MAX_RESEND)
+            .cloned()
+            .collect();
+        ResendResponse {
+            messages,
+            oldest: self.retained.front().map_or(self.next, |resent| resent.n),
+        }
+    }
+}
+
+/// What an arriving stamp means to the receiver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arrival {
+    /// Next in line, or ahead of it.
+    New,
+    /// Fills a gap.
+    Recovered,
+    /// Seen before.
+    Duplicate,
+}
+
+#[derive(Debug)]
+struct Missing {
+    due: Instant,
+    requests: u32,
+}
+
+#[derive(Debug, Default)]
+struct SourceState {
+    highest: u64,
+    missing: BTreeMap<u64, Missing>,
+}
+
+/// Requests to send and losses found by [`GapTracker::sweep`].
+#[derive(Debug, Default, PartialEq)]
+pub struct GapSweep {
+    pub requests: Vec<(String, ResendRequest)>,
+    /// Sources and how many of their messages were given up on.
+    pub lost: Vec<(String, u64)>,
+}
+
+/// Highest number seen and open gaps for each source.
+#[derive(Debug, Default)]
+pub struct GapTracker {
+    sources: HashMap<String, SourceState>,
+    /// Losses counted outside a sweep, reported by the next one.
+    lost: HashMap<String, u64>,
+}
+
+impl GapTracker {
+    pub fn observe(&mut self, config: &SequenceConfig, seq: &Sequence, now: Instant) -> Arrival {
+        let Some(state) = self.sources.get_mut(&seq.source) else {
+            self.sources.insert(
+                seq.source.clone(),
+                SourceState {
+                    highest: seq.n,
+                    missing: BTreeMap::new(),
+                },
+            );
+            return Arrival::New;
+        };
+        if seq.n > state.highest {
+            let first = (state.highest + 1).max(seq.n.saturating_sub(config.max_gap));
+            let skipped = first - (state.highest + 1);
+            if skipped > 0 {
+                *self.lost.entry(seq.source.clone()).or_default() += skipped;
+            }
+            for n in first..seq.n {
+                state.missing.insert(
+                    n,
+                    Missing {
+                        due: now + config.request_after,
+                        requests: 0,
+                    },
+                );
+            }
+            state.highest = seq.n;
+            while state.missing.len() as u64 > config.max_gap {
+                state.missing.pop_first();
+                *self.lost.entry(seq.source.clone()).or_default() += 1;
+            }
+            return Arrival::New;
+        }
+        if state.missing.remove(&seq.n).is_some() {
+            return Arrival::Recovered;
+        }
+        if state.highest - seq.n > config.max_gap {
+            // The source lost its reservation and started over.
+            state.highest = seq.n;
+            state.missing.clear();
+            return Arrival::New;
+        }
+        Arrival::Duplicate
+    }
+
+    /// Numbers missing from `source`.
+    pub fn missing(&self, source: &str) -> usize {
+        self.sources
+            .get(source)
+            .map_or(0, |state| state.missing.len())
+    }
+
+    /// The source cannot resend anything below `oldest`. Returns how many
+    /// open gaps that closes.
+    pub fn give_up_below(&mut self, source: &str, oldest: u64) -> u64 {
+        let Some(state) = self.sources.get_mut(source) else {
+            return 0;
+        };
+        let kept = state.missing.split_off(&oldest);
+        let lost = std::mem::replace(&mut state.missing, kept).len() as u64;
+        if lost > 0 {
+            *self.lost.entry(source.to_string()).or_default() += lost;
+        }
+        lost
+    }
+
+    /// Collect the gaps due for a request, and give up on those requested
+    /// `max_requests` times.
+    pub fn sweep(&mut self, config: &SequenceConfig, now: Instant) -> GapSweep {
+        let mut sweep = GapSweep::default();
+        for (source, state) in &mut self.sources {
+            let mut ranges: Vec<(u64, u64)> = Vec::new();
+            let mut given_up = Vec::new();
+            for (&n, missing) in &mut state.missing {
+                if missing.due > now {
+                    continue;
+                }
+                if missing.requests >= config.max_requests {
+                    given_up.push(n);
+                    continue;
+                }
+                missing.requests += 1;
+                missing.due = now + config.request_after;
+                match ranges.last_mut() {
+                    Some((_, to)) if *to + 1 == n => *to = n,
+                    _ => ranges.push((n, n)),
+                }
+            }
+            for n in &given_up {
+                state.missing.remove(n);
+            }
+            if !given_up.is_empty() {
+                *self.lost.entry(source.clone()).or_default() += given_up.len() as u64;
+            }
+            if !ranges.is_empty() {
+                sweep
+                    .requests
+                    .push((source.clone(), ResendRequest { ranges }));
+            }
+        }
+        sweep.lost = self.lost.drain().collect();
+        sweep.requests.sort_by(|a, b| a.0.cmp(&b.0));
+        sweep.lost.sort();
+        sweep
+    }
+
+    pub fn forget(&mut self, source: &str) {
+        self.sources.remove(source);
+        self.lost.remove(source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn config() -> SequenceConfig {
+        SequenceConfig {
+            enabled: true,
+            retain: 3,
+            reserve: 4,
+            max_requests: 2,
+            max_gap: 8,
+            ..SequenceConfig::default()
+        }
+    }
+
+    fn seq(n: u64) -> Sequence {
+        Sequence {
+            source: "a".to_string(),
+            n,
+        }
+    }
+
+    #[test]
+    fn stamps_increase_across_restarts_and_are_retained() {
+        let stored = Arc::new(Mutex::new(Vec::new()));
+        let log = stored.clone();
+        let mut sequencer =
+            Sequencer::new("a", 0).with_persist(move |n| log.lock().unwrap().push(n));
+        sequencer.config = config();
+        assert!(sequencer.stamp(TopicKind::Status).is_none());
+        for n in 0..5 {
+            let stamp = sequencer.stamp(TopicKind::Task).unwrap();
+            assert_eq!(stamp, seq(n));
+            sequencer.retain(n, TopicKind::Task, vec![n as u8]);
+        }
+        assert_eq!(*stored.lock().unwrap(), [4, 8]);
+
+        let response = sequencer.resend(&ResendRequest {
+            ranges: vec![(0, 1), (3, 3)],
+        });
+        assert_eq!(response.oldest, 2);
+        assert_eq!(response.messages.len(), 1);
+        assert_eq!(response.messages[0].n, 3);
+
+        // After a crash the stream picks up past the last reservation.
+        let mut restarted = Sequencer::new("a", *stored.lock().unwrap().last().unwrap());
+        restarted.config = config();
+        assert_eq!(restarted.stamp(TopicKind::Sensor).unwrap().n, 8);
+    }
+
+    #[test]
+    fn gaps_are_requested_then_recovered_or_given_up() {
+        let config = config();
+        let mut tracker = GapTracker::default();
+        let t0 = Instant::now();
+
+        assert_eq!(tracker.observe(&config, &seq(10), t0), Arrival::New);
+        assert_eq!(tracker.observe(&config, &seq(14), t0), Arrival::New);
+        assert_eq!(tracker.missing("a"), 3);
+        assert_eq!(tracker.observe(&config, &seq(14), t0), Arrival::Duplicate);
+        assert_eq!(tracker.sweep(&config, t0), GapSweep::default());
+
+        let due = t0 + config.request_after;
+        let sweep = tracker.sweep(&config, due);
+        assert_eq!(
+            sweep.requests,
+            [(
+                "a".to_string(),
+                ResendRequest {
+                    ranges: vec![(11, 13)]
+                }
+            )]
+        );
+        assert_eq!(tracker.observe(&config, &seq(12), due), Arrival::Recovered);
+        assert_eq!(tracker.observe(&config, &seq(12), due), Arrival::Duplicate);
+        assert_eq!(tracker.give_up_below("a", 12), 1);
+
+        let later = due + config.request_after;
+        let sweep = tracker.sweep(&config, later);
+        assert_eq!(sweep.requests[0].1.ranges, [(13, 13)]);
+        assert_eq!(sweep.lost, [("a".to_string(), 1)]);
+        let sweep = tracker.sweep(&config, later + config.request_after);
+        assert!(sweep.requests.is_empty());
+        assert_eq!(sweep.lost, [("a".to_string(), 1)]);
+        assert_eq!(tracker.missing("a"), 0);
+    }
+
+    #[test]
+    fn large_jumps_and_restarts_stay_bounded() {
+        let config = config();
+        let mut tracker = GapTracker::default();
+        let t0 = Instant::now();
+        tracker.observe(&config, &seq(0), t0);
+        tracker.observe(&config, &seq(100), t0);
+        assert_eq!(tracker.missing("a"), 8);
+        assert_eq!(tracker.sweep(&config, t0).lost, [("a".to_string(), 91)]);
+
+        // Far behind the highest number: the source started over.
+        assert_eq!(tracker.observe(&config, &seq(2), t0), Arrival::New);
+        assert_eq!(tracker.missing("a"), 0);
+        assert_eq!(tracker.observe(&config, &seq(3), t0), Arrival::New);
+    }
+}
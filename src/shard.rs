@@ -0,0 +1,228 @@
+//! Sharding the task topic.
+//!
+//! In a large swarm every node hearing every task turns the task topic into
+//! a firehose. A [`ShardPlan`] splits it into `shards` sub-topics, named
+//! `<task topic>.<shard>`, and places each task on one by hashing its
+//! [`ShardKey`]: the class of capability it needs, its tenant, or the zone
+//! of the node publishing it. A node joins only the shards its capabilities,
+//! hosted tenants or zone hash to. Tasks without a value for the key, such
+//! as untenanted tasks under [`ShardKey::Tenant`], stay on the task topic
+//! itself, which every node taking tasks keeps joined, as do lease messages.
+//!
+//! An operator reshards the swarm by rolling out a config epoch carrying a
+//! new plan. Epochs reach nodes at jittered moments, so for a while some
+//! publishers still place tasks by the old plan; [`TaskShards`] keeps
+//! listening on the old plan's shards for `drain` after switching.
+
+use crate::core::{Capability, Task};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+/// Most shards a plan may split the task topic into.
+pub const MAX_SHARDS: u32 = 256;
+
+/// What places a task on a shard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShardKey {
+    /// The class of the required capability: `compute`, `sensing:<name>`, ...
+    #[default]
+    Capability,
+    Tenant,
+    /// The zone of the publishing node.
+    Zone,
+}
+
+/// How the task topic is split. With fewer than two shards, the default,
+/// every task goes on the task topic itself.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ShardPlan {
+    pub shards: u32,
+    pub key: ShardKey,
+}
+
+impl ShardPlan {
+    pub fn new(shards: u32, key: ShardKey) -> Self {
+        Self { shards, key }
+    }
+
+    pub fn is_sharded(&self) -> bool {
+        self.shards > 1
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.shards > MAX_SHARDS {
+            return Err(format!(
+                "{} task shards exceed the limit of {MAX_SHARDS}",
+                self.shards
+            ));
+        }
+        Ok(())
+    }
+
+    /// The shard `task`, published from `zone`, goes on; `None` for the
+    /// task topic itself.
+    pub fn shard_for(&self, task: &Task, zone: Option<&str>) -> Option<u32> {
+        if !self.is_sharded() {
+            return None;
+        }
+        let value = match self.key {
+            ShardKey::Capability => capability_class(&task.required_capability),
+            ShardKey::Tenant => task.tenant.clone()?,
+            ShardKey::Zone => zone?.to_string(),
+        };
+        Some(shard_of(&value, self.shards))
+    }
+
+    /// The shards a node holding `capabilities`, hosting `tenants` and
+    /// sitting in `zone` hears tasks for.
+    pub fn shards_for<'a>(
+        &self,
+        capabilities: impl IntoIterator<Item = &'a Capability>,
+        tenants: impl IntoIterator<Item = &'a str>,
+        zone: Option<&str>,
+    ) -> BTreeSet<u32> {
+        if !self.is_sharded() {
+            return BTreeSet::new();
+        }
+        let values: Vec<String> = match self.key {
+            ShardKey::Capability => capabilities.into_iter().map(capability_class).collect(),
+            ShardKey::Tenant => tenants.into_iter().map(str::to_string).collect(),
+            ShardKey::Zone => zone.map(str::to_string).into_iter().collect(),
+        };
+        values
+            .iter()
+            .map(|value| shard_of(value, self.shards))
+            .collect()
+    }
+}
+
+/// The part of a capability tasks are matched on by name; levels such as a
+/// compute budget are left out so every holder of the class hashes alike.
+pub fn capability_class(capability: &Capability) -> String {
+    match capability {
+        Capability::Compute(_) => "compute".to_string(),
+        Capability::Storage(_) => "storage".to_string(),
+        Capability::Sensing(name) => format!("sensing:{name}"),
+        Capability::Join(network) => format!("join:{network}"),
+        Capability::Alert(_) => "alert".to_string(),
+        Capability::Coordinator => "coordinator".to_string(),
+    }
+}
+
+/// Shard of `value` among `shards`, the same on every node.
+pub fn shard_of(value: &str, shards: u32) -> u32 {
+    let digest = Sha256::digest(value.as_bytes());
+    let prefix = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+    prefix % shards.max(1)
+}
+
+/// The plan a node publishes by, and the one it replaced while that drains.
+#[derive(Debug, Clone)]
+pub struct TaskShards {
+    pub plan: ShardPlan,
+    /// How long the shards of a replaced plan stay joined.
+    pub drain: Duration,
+    previous: Option<(ShardPlan, Instant)>,
+}
+
+impl Default for TaskShards {
+    fn default() -> Self {
+        Self {
+            plan: ShardPlan::default(),
+            drain: Duration::from_secs(120),
+            previous: None,
+        }
+    }
+}
+
+impl TaskShards {
+    /// Switch to `plan`, keeping the current one joined until `drain` has
+    /// passed. Returns whether anything changed.
+    pub fn reshard(&mut self, plan: ShardPlan, now: Instant) -> bool {
+        if plan == self.plan {
+            return false;
+        }
+        let replaced = std::mem::replace(&mut self.plan, plan);
+        self.previous = Some((replaced, now + self.drain));
+        true
+    }
+
+    /// The replaced plan, while it is still draining.
+    pub fn draining(&self, now: Instant) -> Option<ShardPlan> {
+        self.previous
+            .filter(|(_, until)| now < *until)
+            .map(|(plan, _)| plan)
+    }
+
+    /// Shards to join now: the current plan's and, while it drains, the
+    /// replaced plan's.
+    pub fn wanted<'a>(
+        &self,
+        capabilities: &[&'a Capability],
+        tenants: &[&'a str],
+        zone: Option<&str>,
+        now: Instant,
+    ) -> BTreeSet<u32> {
+        let mut wanted =
+            self.plan
+                .shards_for(capabilities.iter().copied(), tenants.iter().copied(), zone);
+        if let Some(previous) = self.draining(now) {
+            wanted.extend(previous.shards_for(
+                capabilities.iter().copied(),
+                tenants.iter().copied(),
+                zone,
+            ));
+        }
+        wanted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tasks_land_on_the_shards_their_holders_join() {
+        let plan = ShardPlan::new(16, ShardKey::Capability);
+        let task = Task::new("t".into(), Capability::Compute(50), 1, "publisher".into());
+        let shard = plan.shard_for(&task, None).unwrap();
+        assert!(shard < 16);
+
+        let strong = Capability::Compute(500);
+        let camera = Capability::Sensing("camera".into());
+        assert!(plan.shards_for([&strong], [], None).contains(&shard));
+        assert_eq!(
+            plan.shards_for([&camera], [], None),
+            BTreeSet::from([shard_of("sensing:camera", 16)])
+        );
+
+        let by_tenant = ShardPlan::new(16, ShardKey::Tenant);
+        assert_eq!(by_tenant.shard_for(&task, None), None);
+        assert_eq!(ShardPlan::default().shard_for(&task, Some("north")), None);
+        assert!(ShardPlan::new(MAX_SHARDS + 1, ShardKey::Zone)
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn resharding_keeps_the_old_shards_until_they_drain() {
+        let t0 = Instant::now();
+        let mut shards = TaskShards::default();
+        let old = ShardPlan::new(4, ShardKey::Zone);
+        let new = ShardPlan::new(64, ShardKey::Zone);
+        assert!(shards.reshard(old, t0));
+        assert!(shards.reshard(new, t0));
+        assert!(!shards.reshard(new, t0));
+
+        let during = shards.wanted(&[], &[], Some("north"), t0);
+        assert_eq!(
+            during,
+            BTreeSet::from([shard_of("north", 4), shard_of("north", 64)])
+        );
+        let after = shards.wanted(&[], &[], Some("north"), t0 + shards.drain);
+        assert_eq!(after, BTreeSet::from([shard_of("north", 64)]));
+    }
+}
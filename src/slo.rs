@@ -0,0 +1,225 @@
+//! Per-topic delivery SLOs.
+//!
+//! An [`Slo`] asks that a fraction of deliveries on a topic land within a
+//! deadline, e.g. spikes reaching 90% of peers within 2s. The monitor counts
+//! deliveries in a sliding window: each receipt is on time or late, and
+//! deliveries that were expected but never arrived count as late. A live node
+//! feeds it the origin-to-receipt latency of traced messages (so it depends
+//! on roughly synchronized clocks); simulations can also declare how many
+//! receipts each publish should produce.
+//!
+//! After `sustain` consecutive failing evaluations the SLO is reported once as
+//! an [`SloViolation`]; the run loop emits an event and a diagnostic spike
+//! with [`SLO_ALERT_PATTERN`], so degraded service shows up in the mesh
+//! itself. It is re-armed when compliance recovers.
+
+use crate::mycelium::TopicKind;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Spike pattern marking an SLO violation.
+pub const SLO_ALERT_PATTERN: u8 = 0xA1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slo {
+    pub name: String,
+    pub topic: TopicKind,
+    /// Deadline for a delivery to count as on time.
+    pub within: Duration,
+    /// Fraction of deliveries that must be on time, in [0, 1].
+    pub target: f64,
+    pub window: Duration,
+    /// Consecutive failing evaluations before the SLO is reported.
+    pub sustain: u32,
+    /// Deliveries needed in the window before the SLO is judged at all.
+    pub min_samples: usize,
+}
+
+impl Slo {
+    pub fn delivery(name: &str, topic: TopicKind, target: f64, within: Duration) -> Self {
+        Self {
+            name: name.to_string(),
+            topic,
+            within,
+            target,
+            window: Duration::from_secs(60),
+            sustain: 3,
+            min_samples: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SloViolation {
+    pub name: String,
+    pub topic: TopicKind,
+    /// On-time fraction over the window.
+    pub compliance: f64,
+    pub target: f64,
+    pub deliveries: usize,
+}
+
+#[derive(Debug)]
+struct SloState {
+    slo: Slo,
+    /// (received at, on time)
+    deliveries: VecDeque<(Instant, bool)>,
+    /// (published at, receipts expected)
+    expected: VecDeque<(Instant, usize)>,
+    failing: u32,
+    alerting: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct SloMonitor {
+    states: Vec<SloState>,
+}
+
+impl SloMonitor {
+    pub fn add(&mut self, slo: Slo) {
+        self.states.push(SloState {
+            slo,
+            deliveries: VecDeque::new(),
+            expected: VecDeque::new(),
+            failing: 0,
+            alerting: false,
+        });
+    }
+
+    pub fn slos(&self) -> impl Iterator<Item = &Slo> {
+        self.states.iter().map(|s| &s.slo)
+    }
+
+    /// Topics that have at least one SLO.
+    pub fn topics(&self) -> impl Iterator<Item = TopicKind> + '_ {
+        self.slos().map(|slo| slo.topic)
+    }
+
+    /// A message on `topic` arrived `latency` after it was published.
+    pub fn record_delivery(&mut self, topic: TopicKind, latency: Duration, now: Instant) {
+        for state in self.states.iter_mut().filter(|s| s.slo.topic == topic) {
+            let on_time = latency <= state.slo.within;
+            state.deliveries.push_back((now, on_time));
+        }
+    }
+
+    /// A publish on `topic` should produce `receivers` deliveries.
+    pub fn record_expected(&mut self, topic: TopicKind, receivers: usize, now: Instant) {
+        for state in self.states.iter_mut().filter(|s| s.slo.topic == topic) {
+            state.expected.push_back((now, receivers));
+        }
+    }
+
+    /// Judge every SLO over its window and return the ones that just became
+    /// violated.
+    pub fn evaluate(&mut self, now: Instant) -> Vec<SloViolation> {
+        let mut violations = Vec::new();
+        for state in &mut self.states {
+            let window = state.slo.window;
+            let expired = |at: &Instant| now.saturating_duration_since(*at) > window;
+            while state.deliveries.front().is_some_and(|(at, _)| expired(at)) {
+                state.deliveries.pop_front();
+            }
+            while state.expected.front().is_some_and(|(at, _)| expired(at)) {
+                state.expected.pop_front();
+            }
+
+            let received = state.deliveries.len();
+            let expected: usize = state.expected.iter().map(|(_, n)| n).sum();
+            let total = received.max(expected);
+            if total < state.slo.min_samples {
+                state.failing = 0;
+                continue;
+            }
+            let on_time = state.deliveries.iter().filter(|(_, ok)| *ok).count();
+            let compliance = on_time as f64 / total as f64;
+            if compliance >= state.slo.target {
+                state.failing = 0;
+                state.alerting = false;
+                continue;
+            }
+            state.failing = state.failing.saturating_add(1);
+            if state.failing >= state.slo.sustain && !state.alerting {
+                state.alerting = true;
+                violations.push(SloViolation {
+                    name: state.slo.name.clone(),
+                    topic: state.slo.topic,
+                    compliance,
+                    target: state.slo.target,
+                    deliveries: total,
+                });
+            }
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn spikes_slo() -> SloMonitor {
+        let mut monitor = SloMonitor::default();
+        monitor.add(Slo::delivery(
+            "spikes-2s",
+            TopicKind::Spike,
+            0.9,
+            Duration::from_secs(2),
+        ));
+        monitor
+    }
+
+    #[test]
+    fn sustained_lateness_is_reported_once_and_rearms() {
+        let mut monitor = spikes_slo();
+        let t0 = Instant::now();
+        let mut reported = Vec::new();
+        for i in 0..6 {
+            let now = t0 + SECOND * i;
+            for late in [false, false, true, true] {
+                let latency = if late { SECOND * 5 } else { SECOND / 2 };
+                monitor.record_delivery(TopicKind::Spike, latency, now);
+            }
+            // Other topics do not count.
+            monitor.record_delivery(TopicKind::Status, SECOND * 9, now);
+            reported.extend(monitor.evaluate(now));
+        }
+        assert_eq!(reported.len(), 1);
+        assert!((reported[0].compliance - 0.5).abs() < 1e-9);
+
+        // Recovery: the window slides past the late deliveries.
+        let later = t0 + SECOND * 120;
+        for _ in 0..20 {
+            monitor.record_delivery(TopicKind::Spike, SECOND / 2, later);
+        }
+        assert!(monitor.evaluate(later).is_empty());
+        for i in 1..=3 {
+            for _ in 0..40 {
+                monitor.record_delivery(TopicKind::Spike, SECOND * 5, later + SECOND * i);
+            }
+            let found = monitor.evaluate(later + SECOND * i);
+            assert_eq!(found.len(), usize::from(i == 3));
+        }
+    }
+
+    #[test]
+    fn missing_receipts_count_against_expected() {
+        let mut monitor = spikes_slo();
+        let t0 = Instant::now();
+        let mut reported = Vec::new();
+        for i in 0..3 {
+            let now = t0 + SECOND * i;
+            // Ten zone peers expected, eight heard it in time.
+            monitor.record_expected(TopicKind::Spike, 10, now);
+            for _ in 0..8 {
+                monitor.record_delivery(TopicKind::Spike, SECOND, now);
+            }
+            reported.extend(monitor.evaluate(now));
+        }
+        assert_eq!(reported.len(), 1);
+        assert!((reported[0].compliance - 0.8).abs() < 1e-9);
+    }
+}
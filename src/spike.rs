@@ -0,0 +1,456 @@
+//! Authenticated pressure spikes and per-source quotas.
+//!
+//! Spikes remain prototype pressure telemetry (ADR-0006 keeps
+//! action-triggering alerts off this channel), but even telemetry is an
+//! attack surface: an unauthenticated intensity-255 spike pushes every
+//! receiver to maximum pressure. So every [`Spike`] is signed by its source,
+//! and a [`SpikeGuard`] accepts from each source at most `max_per_minute`
+//! spikes and `max_intensity_per_minute` cumulative intensity per minute.
+//! Spikes above `alert_above` must also carry a delegation of
+//! `Capability::Alert` for at least their intensity, rooted at an issuer the
+//! receiver trusts.
+//!
+//! A signature alone would let anyone capture a proven spike and replay it
+//! forever, spending its source's quota so that the source's genuine spikes
+//! are dropped. Each spike therefore signs when it was issued and a sequence
+//! number that rises with every spike from its source. The guard refuses
+//! spikes older than `max_age` and any whose sequence number does not exceed
+//! the last one it accepted from that source, before they count against the
+//! quota. A spike overtaken in transit by a later one from the same source
+//! is refused too; spikes are telemetry and the later one stands for it.
+
+use crate::auth::{peer_id_of, public_key_of, unix_now, AuthError, Delegation, DelegationLimits};
+use crate::core::Capability;
+use crate::mesh::PRESSURE_SPIKE_THRESHOLD;
+use ed25519_dalek::{Signer, SigningKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const DOMAIN: &[u8] = b"hypha-spike-v1:";
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Prototype pressure spike telemetry.
+///
+/// This is not a typed alert vocabulary. ADR-0006 keeps action-triggering
+/// alerts out of this primitive channel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spike {
+    pub source: String,
+    pub intensity: u8,  // 0-255
+    pub pattern_id: u8, // reserved prototype pattern slot
+    /// Encoded `Capability::Alert` delegation to `source`, required above the
+    /// receiver's `alert_above`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<String>,
+    /// Unix seconds at signing. Spikes without one are refused.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issued_at: Option<u64>,
+    /// Rises with every spike from `source`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    #[serde(default)]
+    pub signature: Vec<u8>,
+}
+
+impl Spike {
+    /// Sign a spike issued now as the `seq`th from this source.
+    pub fn sign(
+        signing_key: &SigningKey,
+        intensity: u8,
+        pattern_id: u8,
+        proof: Option<String>,
+        seq: u64,
+    ) -> Self {
+        Self::sign_at(signing_key, intensity, pattern_id, proof, seq, unix_now())
+    }
+
+    fn sign_at(
+        signing_key: &SigningKey,
+        intensity: u8,
+        pattern_id: u8,
+        proof: Option<String>,
+        seq: u64,
+        issued_at: u64,
+    ) -> Self {
+        let mut spike = Self {
+            source: peer_id_of(signing_key).to_string(),
+            intensity,
+            pattern_id,
+            proof,
+            issued_at: Some(issued_at),
+            seq: Some(seq),
+            signature: Vec::new(),
+        };
+        spike.signature = signing_key.sign(&spike.signing_bytes()).to_bytes().to_vec();
+        spike
+    }
+
+    pub fn affects_mesh_pressure(&self) -> bool {
+        self.intensity > PRESSURE_SPIKE_THRESHOLD
+    }
+
+    pub fn verify_signature(&self) -> Result<(), SpikeError> {
+        if public_key_of(&self.source)
+            .is_some_and(|key| key.verify(&self.signing_bytes(), &self.signature))
+        {
+            Ok(())
+        } else {
+            Err(SpikeError::BadSignature(self.source.clone()))
+        }
+    }
+
+    fn signing_bytes(&self) -> Vec<u8> {
+        let mut bytes = DOMAIN.to_vec();
+        bytes.extend(
+            serde_json::to_vec(&(
+                &self.source,
+                self.intensity,
+                self.pattern_id,
+                &self.proof,
+                self.issued_at,
+                self.seq,
+            ))
+            .expect("spike serializes"),
+        );
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum SpikeError {
+    #[error("spike signature by `{0}` does not verify")]
+    BadSignature(String),
+    #[error("`{0}` sent more spikes this minute than allowed")]
+    RateExceeded(String),
+    #[error("`{0}` exceeded its spike intensity budget this minute")]
+    IntensityExceeded(String),
+    #[error("intensity {0} spike carries no alert proof")]
+    MissingProof(u8),
+    #[error("alert proof rejected: {0}")]
+    Proof(#[from] AuthError),
+    #[error("spike from `{0}` is undated, stale or from the future")]
+    Stale(String),
+    #[error("spike {seq} from `{source_peer}` was already surpassed")]
+    Replayed { source_peer: String, seq: u64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpikeConfig {
+    pub max_per_minute: u32,
+    /// Sum of accepted intensities per source per minute.
+    pub max_intensity_per_minute: u32,
+    /// Spikes above this intensity need a `Capability::Alert` proof. The
+    /// default is the pressure threshold, so raising pressure needs one.
+    pub alert_above: u8,
+    /// Attached to this node's own spikes above `alert_above`.
+    pub proof: Option<String>,
+    /// Oldest spike accepted, and how far ahead of this node's clock one
+    /// may be dated.
+    pub max_age: Duration,
+}
+
+impl Default for SpikeConfig {
+    fn default() -> Self {
+        Self {
+            max_per_minute: 30,
+            max_intensity_per_minute: 2048,
+            alert_above: PRESSURE_SPIKE_THRESHOLD,
+            proof: None,
+            max_age: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    started: Instant,
+    count: u32,
+    intensity: u32,
+}
+
+/// Checks incoming spikes and tracks each source's quota and latest
+/// sequence number.
+#[derive(Debug)]
+pub struct SpikeGuard {
+    pub config: SpikeConfig,
+    windows: HashMap<String, Window>,
+    /// Last accepted sequence number and its issue time, per source.
+    latest: HashMap<String, (u64, u64)>,
+    /// Sequence number of this node's next spike. Starts at the Unix time in
+    /// milliseconds, so a restarted node continues above what receivers
+    /// remember.
+    next_seq: AtomicU64,
+}
+
+impl Default for SpikeGuard {
+    fn default() -> Self {
+        Self::new(SpikeConfig::default())
+    }
+}
+
+impl SpikeGuard {
+    pub fn new(config: SpikeConfig) -> Self {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64);
+        Self {
+            config,
+            windows: HashMap::new(),
+            latest: HashMap::new(),
+            next_seq: AtomicU64::new(millis),
+        }
+    }
+
+    /// Sign a spike from this node, attaching the configured proof when the
+    /// intensity needs one.
+    pub fn sign(&self, signing_key: &SigningKey, intensity: u8, pattern_id: u8) -> Spike {
+        let proof = (intensity > self.config.alert_above)
+            .then(|| self.config.proof.clone())
+            .flatten();
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        Spike::sign(signing_key, intensity, pattern_id, proof, seq)
+    }
+
+    /// Refuse `spike` unless it is fresh at Unix time `unix` and newer than
+    /// the last accepted from its source.
+    fn check_fresh(&mut self, spike: &Spike, unix: u64) -> Result<(), SpikeError> {
+        let max_age = self.config.max_age.as_secs();
+        let (Some(issued_at), Some(seq)) = (spike.issued_at, spike.seq) else {
+            return Err(SpikeError::Stale(spike.source.clone()));
+        };
+        if issued_at.saturating_add(max_age) < unix || issued_at > unix.saturating_add(max_age) {
+            return Err(SpikeError::Stale(spike.source.clone()));
+        }
+        // Entries older than `max_age` guard nothing staleness does not.
+        self.latest
+            .retain(|_, (_, at)| at.saturating_add(max_age) >= unix);
+        if self
+            .latest
+            .get(&spike.source)
+            .is_some_and(|(latest, _)| seq <= *latest)
+        {
+            return Err(SpikeError::Replayed {
+                source_peer: spike.source.clone(),
+                seq,
+            });
+        }
+        Ok(())
+    }
+
+    /// Accept `spike` if it is signed by its source, within the source's
+    /// quota, and proven when it is high-intensity. Only accepted spikes
+    /// count against the quota; `roots` may root the alert proof.
+    pub fn admit(
+        &mut self,
+        spike: &Spike,
+        roots: &[String],
+        limits: &DelegationLimits,
+        now: Instant,
+    ) -> Result<(), SpikeError> {
+        self.admit_at(spike, roots, limits, now, unix_now())
+    }
+
+    fn admit_at(
+        &mut self,
+        spike: &Spike,
+        roots: &[String],
+        limits: &DelegationLimits,
+        now: Instant,
+        unix: u64,
+    ) -> Result<(), SpikeError> {
+        spike.verify_signature()?;
+        self.check_fresh(spike, unix)?;
+
+        if spike.intensity > self.config.alert_above {
+            let proof = spike
+                .proof
+                .as_deref()
+                .ok_or(SpikeError::MissingProof(spike.intensity))?;
+            Delegation::decode(proof)?.verify(
+                &spike.source,
+                &Capability::Alert(spike.intensity),
+                roots,
+                limits,
+                unix,
+            )?;
+        }
+
+        if !self.windows.contains_key(&spike.source) {
+            self.windows
+                .retain(|_, window| now.saturating_duration_since(window.started) < WINDOW);
+        }
+        let window = self.windows.entry(spike.source.clone()).or_insert(Window {
+            started: now,
+            count: 0,
+            intensity: 0,
+        });
+        if now.saturating_duration_since(window.started) >= WINDOW {
+            *window = Window {
+                started: now,
+                count: 0,
+                intensity: 0,
+            };
+        }
+        if window.count >= self.config.max_per_minute {
+            return Err(SpikeError::RateExceeded(spike.source.clone()));
+        }
+        let intensity = window.intensity + u32::from(spike.intensity);
+        if intensity > self.config.max_intensity_per_minute {
+            return Err(SpikeError::IntensityExceeded(spike.source.clone()));
+        }
+        window.count += 1;
+        window.intensity = intensity;
+        if let (Some(seq), Some(issued_at)) = (spike.seq, spike.issued_at) {
+            self.latest.insert(spike.source.clone(), (seq, issued_at));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn forged_and_unsigned_spikes_are_refused() {
+        let mut guard = SpikeGuard::default();
+        let now = Instant::now();
+        let limits = DelegationLimits::default();
+
+        let mut forged = Spike::sign(&key(2), 50, 0, None, 1);
+        forged.source = peer_id_of(&key(1)).to_string();
+        assert!(matches!(
+            guard.admit(&forged, &[], &limits, now),
+            Err(SpikeError::BadSignature(_))
+        ));
+
+        let unsigned: Spike = serde_json::from_str(&format!(
+            r#"{{"source":"{}","intensity":50,"pattern_id":0}}"#,
+            peer_id_of(&key(1))
+        ))
+        .unwrap();
+        assert!(guard.admit(&unsigned, &[], &limits, now).is_err());
+        assert_eq!(
+            guard.admit(&Spike::sign(&key(1), 50, 0, None, 1), &[], &limits, now),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn quota_is_per_source_and_per_minute() {
+        let mut guard = SpikeGuard::new(SpikeConfig {
+            max_per_minute: 3,
+            max_intensity_per_minute: 400,
+            ..SpikeConfig::default()
+        });
+        let limits = DelegationLimits::default();
+        let start = Instant::now();
+        let noisy = |seq| Spike::sign(&key(1), 150, 0, None, seq);
+        let quiet = |seq| Spike::sign(&key(2), 10, 0, None, seq);
+
+        assert_eq!(guard.admit(&noisy(1), &[], &limits, start), Ok(()));
+        assert_eq!(guard.admit(&noisy(2), &[], &limits, start), Ok(()));
+        assert!(matches!(
+            guard.admit(&noisy(3), &[], &limits, start),
+            Err(SpikeError::IntensityExceeded(_))
+        ));
+        for seq in 1..=3 {
+            assert_eq!(guard.admit(&quiet(seq), &[], &limits, start), Ok(()));
+        }
+        assert!(matches!(
+            guard.admit(&quiet(4), &[], &limits, start),
+            Err(SpikeError::RateExceeded(_))
+        ));
+
+        let later = start + WINDOW;
+        assert_eq!(guard.admit(&noisy(3), &[], &limits, later), Ok(()));
+        assert_eq!(guard.admit(&quiet(4), &[], &limits, later), Ok(()));
+    }
+
+    #[test]
+    fn high_intensity_needs_an_alert_grant() {
+        let (root, sender) = (key(1), key(2));
+        let roots = vec![peer_id_of(&root).to_string()];
+        let limits = DelegationLimits::default();
+        let now = Instant::now();
+        let grant = |max: u8| {
+            Delegation::mint(
+                &root,
+                &peer_id_of(&sender),
+                Capability::Alert(max),
+                unix_now(),
+                Duration::from_secs(3600),
+                None,
+            )
+            .unwrap()
+            .encode()
+        };
+        let mut guard = SpikeGuard::default();
+
+        assert_eq!(
+            guard.admit(&Spike::sign(&sender, 255, 0, None, 1), &roots, &limits, now),
+            Err(SpikeError::MissingProof(255))
+        );
+        assert!(matches!(
+            guard.admit(
+                &Spike::sign(&sender, 255, 0, Some(grant(220)), 2),
+                &roots,
+                &limits,
+                now
+            ),
+            Err(SpikeError::Proof(_))
+        ));
+        assert!(matches!(
+            guard.admit(
+                &Spike::sign(&sender, 255, 0, Some(grant(255)), 3),
+                &[],
+                &limits,
+                now
+            ),
+            Err(SpikeError::Proof(AuthError::UntrustedRoot(_)))
+        ));
+
+        guard.config.proof = Some(grant(255));
+        let own = guard.sign(&sender, 255, 0);
+        assert_eq!(guard.admit(&own, &roots, &limits, now), Ok(()));
+        assert!(guard.sign(&sender, 100, 0).proof.is_none());
+    }
+
+    #[test]
+    fn replayed_and_stale_spikes_spend_no_quota() {
+        let mut guard = SpikeGuard::new(SpikeConfig {
+            max_per_minute: 2,
+            ..SpikeConfig::default()
+        });
+        let limits = DelegationLimits::default();
+        let (now, unix) = (Instant::now(), 1_700_000_000);
+        let captured = Spike::sign_at(&key(1), 50, 0, None, 7, unix);
+
+        assert_eq!(guard.admit_at(&captured, &[], &limits, now, unix), Ok(()));
+        for _ in 0..5 {
+            assert!(matches!(
+                guard.admit_at(&captured, &[], &limits, now, unix),
+                Err(SpikeError::Replayed { seq: 7, .. })
+            ));
+        }
+        let stale = Spike::sign_at(&key(1), 50, 0, None, 8, unix - 120);
+        assert!(matches!(
+            guard.admit_at(&stale, &[], &limits, now, unix),
+            Err(SpikeError::Stale(_))
+        ));
+        // The replays left the source's quota for its genuine spikes.
+        let genuine = Spike::sign_at(&key(1), 50, 0, None, 9, unix);
+        assert_eq!(guard.admit_at(&genuine, &[], &limits, now, unix), Ok(()));
+
+        let own = guard.sign(&key(1), 50, 0);
+        let next = guard.sign(&key(1), 50, 0);
+        assert!(next.seq > own.seq);
+    }
+}
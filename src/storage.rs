@@ -0,0 +1,284 @@
+//! Hot in-memory cache over a fjall keyspace.
+//!
+//! Every message lookup used to go to flash. [`StorageManager`] keeps the
+//! most recently used values in memory, up to `capacity` entries, and writes
+//! through: an insert or removal reaches the keyspace before the cache, so
+//! the cache never holds anything the store does not. Hit and miss counters
+//! show whether the capacity suits the workload.
+//!
+//! A manager may also carry a storage quota, in bytes, for the keyspace it
+//! writes to. Usage is measured when the quota is set and then kept up by
+//! the manager's own writes; an overwrite counts in full, so usage errs
+//! high until the next measurement. Bidders check [`StorageManager::headroom`]
+//! before taking on tasks expected to leave output behind.
+
+use fjall::Keyspace;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Entries a node's message cache holds unless configured otherwise.
+pub const DEFAULT_CACHE_CAPACITY: usize = 512;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+/// Least-recently-used map. Each access takes a fresh tick; the smallest
+/// tick is evicted first.
+#[derive(Debug, Default)]
+struct Lru {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<Vec<u8>, (Vec<u8>, u64)>,
+    order: BTreeMap<u64, Vec<u8>>,
+}
+
+impl Lru {
+    fn get(&mut self, key: &[u8]) -> Option<Vec<u8>> {
+        self.tick += 1;
+        let (value, tick) = self.entries.get_mut(key)?;
+        self.order.remove(tick);
+        *tick = self.tick;
+        self.order.insert(self.tick, key.to_vec());
+        Some(value.clone())
+    }
+
+    fn put(&mut self, key: &[u8], value: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((_, old)) = self.entries.insert(key.to_vec(), (value, self.tick)) {
+            self.order.remove(&old);
+        }
+        self.order.insert(self.tick, key.to_vec());
+        self.shrink();
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        if let Some((_, tick)) = self.entries.remove(key) {
+            self.order.remove(&tick);
+        }
+    }
+
+    fn shrink(&mut self) {
+        while self.entries.len() > self.capacity {
+            let Some((_, key)) = self.order.pop_first() else {
+                break;
+            };
+            self.entries.remove(&key);
+        }
+    }
+}
+
+pub struct StorageManager {
+    keyspace: Keyspace,
+    cache: Mutex<Lru>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    /// Bytes the keyspace may hold; `u64::MAX` for no quota.
+    quota: AtomicU64,
+    /// Bytes of keys and values held, as last measured plus writes since.
+    used: AtomicU64,
+}
+
+impl StorageManager {
+    pub fn new(keyspace: Keyspace, capacity: usize) -> Self {
+        Self {
+            keyspace,
+            cache: Mutex::new(Lru {
+                capacity,
+                ..Lru::default()
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            quota: AtomicU64::new(u64::MAX),
+            used: AtomicU64::new(0),
+        }
+    }
+
+    /// The keyspace behind the cache, for scans the cache cannot serve.
+    pub fn keyspace(&self) -> &Keyspace {
+        &self.keyspace
+    }
+
+    /// Resize the cache, evicting the least recently used entries if it
+    /// shrinks. Zero turns caching off.
+    pub fn set_capacity(&self, capacity: usize) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.capacity = capacity;
+        cache.shrink();
+    }
+
+    /// Cap the bytes the keyspace may hold, measuring what it holds now.
+    /// `None` lifts the quota.
+    pub fn set_quota(&self, quota: Option<u64>) -> Result<(), fjall::Error> {
+        if quota.is_some() {
+            self.measure()?;
+        }
+        self.quota
+            .store(quota.unwrap_or(u64::MAX), Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn quota(&self) -> Option<u64> {
+        Some(self.quota.load(Ordering::Relaxed)).filter(|quota| *quota != u64::MAX)
+    }
+
+    /// Bytes of keys and values the keyspace holds, by the last measurement
+    /// and the writes since.
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::Relaxed)
+    }
+
+    /// Bytes left under the quota, or `None` without one.
+    pub fn headroom(&self) -> Option<u64> {
+        self.quota().map(|quota| quota.saturating_sub(self.used()))
+    }
+
+    /// Recount the bytes the keyspace holds, including writes made around
+    /// the manager. Scans every key.
+    pub fn measure(&self) -> Result<u64, fjall::Error> {
+        let keys: Vec<_> = self
+            .keyspace
+            .prefix("")
+            .map(|item| item.key())
+            .collect::<Result<_, _>>()?;
+        let mut used = 0u64;
+        for key in keys {
+            if let Some(value) = self.keyspace.get(&key)? {
+                used = used.saturating_add((key.len() + value.len()) as u64);
+            }
+        }
+        self.used.store(used, Ordering::Relaxed);
+        Ok(used)
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>, fjall::Error> {
+        if let Some(value) = self.cache.lock().unwrap().get(key.as_bytes()) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(value));
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let Some(value) = self.keyspace.get(key)? else {
+            return Ok(None);
+        };
+        let value = value.to_vec();
+        self.cache
+            .lock()
+            .unwrap()
+            .put(key.as_bytes(), value.clone());
+        Ok(Some(value))
+    }
+
+    pub fn contains(&self, key: &str) -> Result<bool, fjall::Error> {
+        Ok(self.get(key)?.is_some())
+    }
+
+    /// Write `value` to the keyspace, then to the cache.
+    pub fn insert(&self, key: &str, value: &[u8]) -> Result<(), fjall::Error> {
+        self.keyspace.insert(key, value)?;
+        self.used
+            .fetch_add((key.len() + value.len()) as u64, Ordering::Relaxed);
+        self.cache
+            .lock()
+            .unwrap()
+            .put(key.as_bytes(), value.to_vec());
+        Ok(())
+    }
+
+    pub fn remove(&self, key: &str) -> Result<(), fjall::Error> {
+        let freed = match self.keyspace.get(key)? {
+            Some(value) => (key.len() + value.len()) as u64,
+            None => 0,
+        };
+        self.keyspace.remove(key)?;
+        let _ = self
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                Some(used.saturating_sub(freed))
+            });
+        self.cache.lock().unwrap().remove(key.as_bytes());
+        Ok(())
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let cache = self.cache.lock().unwrap();
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: cache.entries.len(),
+            capacity: cache.capacity,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fjall::{Database, KeyspaceCreateOptions};
+
+    #[test]
+    fn cache_writes_through_and_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Database::builder(dir.path()).open().unwrap();
+        let keyspace = storage
+            .keyspace("messages", KeyspaceCreateOptions::default)
+            .unwrap();
+        let store = StorageManager::new(keyspace.clone(), 2);
+
+        store.insert("msg_a", b"a").unwrap();
+        store.insert("msg_b", b"b").unwrap();
+        assert_eq!(store.get("msg_a").unwrap(), Some(b"a".to_vec()));
+        // `msg_b` is now the least recently used.
+        store.insert("msg_c", b"c").unwrap();
+        assert_eq!(store.stats().entries, 2);
+        assert_eq!(store.get("msg_b").unwrap(), Some(b"b".to_vec()));
+        assert_eq!(
+            store.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                entries: 2,
+                capacity: 2,
+            }
+        );
+
+        store.remove("msg_a").unwrap();
+        assert!(keyspace.get("msg_a").unwrap().is_none());
+        assert!(!store.contains("msg_a").unwrap());
+
+        store.set_capacity(0);
+        assert_eq!(store.stats().entries, 0);
+        assert_eq!(store.get("msg_c").unwrap(), Some(b"c".to_vec()));
+    }
+
+    #[test]
+    fn quota_headroom_follows_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Database::builder(dir.path()).open().unwrap();
+        let keyspace = storage
+            .keyspace("messages", KeyspaceCreateOptions::default)
+            .unwrap();
+        // Written around the manager, so only a measurement sees it.
+        keyspace.insert("msg_old", &[0u8; 93][..]).unwrap();
+        let store = StorageManager::new(keyspace, 8);
+        assert_eq!(store.headroom(), None);
+
+        store.set_quota(Some(1000)).unwrap();
+        assert_eq!(store.used(), 100);
+        store.insert("msg_new", &[0u8; 193]).unwrap();
+        assert_eq!(store.headroom(), Some(700));
+        store.remove("msg_old").unwrap();
+        assert_eq!(store.headroom(), Some(800));
+
+        store.set_quota(None).unwrap();
+        assert_eq!(store.headroom(), None);
+    }
+}
@@ -0,0 +1,47 @@
+//! Support bundles for bug reports.
+//!
+//! [`SupportBundle`] gathers what a maintainer asks for first when a node
+//! misbehaves: the crate and wire versions, the node's lifecycle state and
+//! live config, its network setup, a full [`MeshDiagnostics`] dump, the
+//! event kinds the run loop handled most recently, and message cache
+//! counters. `SporeNode::support_bundle` collects one; [`SupportBundle::write`]
+//! saves it as a single pretty-printed JSON file to attach to a report.
+//!
+//! Bundles hold peer ids and addresses but no keys, message bodies or
+//! delegation tokens.
+
+use crate::core::NodeState;
+use crate::mesh::MeshDiagnostics;
+use crate::storage::CacheStats;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SupportBundle {
+    /// `hypha` crate version.
+    pub version: String,
+    pub envelope_version: u8,
+    /// Unix seconds when the bundle was collected.
+    pub generated_at: u64,
+    pub peer_id: String,
+    pub state: NodeState,
+    /// The live `HyphaConfig`, rendered with `Debug`.
+    pub config: String,
+    pub listen_addrs: Vec<String>,
+    pub topics: Vec<String>,
+    pub mesh: MeshDiagnostics,
+    /// Event kinds the run loop handled last, oldest first.
+    pub recent_events: Vec<String>,
+    pub message_cache: CacheStats,
+}
+
+impl SupportBundle {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Save the bundle to `path` as JSON.
+    pub fn write(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_json()?)
+    }
+}
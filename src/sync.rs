@@ -1,13 +1,141 @@
+use libp2p::futures::Stream;
 use libp2p::gossipsub;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use yrs::types::EntryChange;
 use yrs::updates::decoder::Decode;
 use yrs::updates::encoder::Encode;
-use yrs::{Doc, Map, ReadTxn, StateVector, Transact, Update};
+use yrs::{Doc, Map, Observable, ReadTxn, StateVector, Transact, Update};
+
+/// Root map holding application key/value pairs.
+const KV_MAP: &str = "kv";
 
 /// Distributed State synchronization via CRDTs (Yrs) over Gossipsub.
 pub struct SharedState {
     pub doc: Doc,
     pub topic: gossipsub::IdentTopic,
+    /// Observer keys of live watches, with a sender to tell when the
+    /// watch was dropped.
+    watches: Mutex<Vec<(String, mpsc::UnboundedSender<KeyChange>)>>,
+    next_watch: Mutex<u64>,
+}
+
+/// A key of the shared key/value map changed, locally or by a peer's
+/// update. `old` is `None` for a new key and `new` is `None` for a removed
+/// one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChange {
+    pub key: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl KeyChange {
+    /// The new value parsed as `T`, e.g. a numeric threshold. `None` if the
+    /// key was removed or does not parse.
+    pub fn value<T: FromStr>(&self) -> Option<T> {
+        self.new.as_deref()?.parse().ok()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchOptions {
+    /// Hold changes until the watched keys have been quiet this long, then
+    /// deliver one change per key spanning the burst. Zero delivers each
+    /// change as it happens.
+    pub debounce: Duration,
+    /// Skip writes that leave a key's value as it was.
+    pub skip_unchanged: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::ZERO,
+            skip_unchanged: true,
+        }
+    }
+}
+
+/// Stream of [`KeyChange`]s from [`SharedState::watch`]. Dropping it ends
+/// the watch.
+pub struct KeyWatch {
+    rx: mpsc::UnboundedReceiver<KeyChange>,
+    debounce: Duration,
+    skip_unchanged: bool,
+    /// Changes held back by the debounce, one per key.
+    pending: Vec<KeyChange>,
+    timer: Option<Pin<Box<tokio::time::Sleep>>>,
+    ready: VecDeque<KeyChange>,
+}
+
+impl KeyWatch {
+    /// Fold `change` into the held change for its key, keeping the value
+    /// from before the burst.
+    fn hold(&mut self, change: KeyChange) {
+        match self.pending.iter_mut().find(|held| held.key == change.key) {
+            Some(held) => held.new = change.new,
+            None => self.pending.push(change),
+        }
+        self.timer = Some(Box::pin(tokio::time::sleep(self.debounce)));
+    }
+}
+
+impl Stream for KeyWatch {
+    type Item = KeyChange;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<KeyChange>> {
+        let this = &mut *self;
+        let mut closed = false;
+        while this.ready.is_empty() {
+            match this.rx.poll_recv(cx) {
+                Poll::Ready(Some(change)) if this.debounce.is_zero() => {
+                    this.ready.push_back(change)
+                }
+                Poll::Ready(Some(change)) => this.hold(change),
+                Poll::Ready(None) => {
+                    closed = true;
+                    break;
+                }
+                Poll::Pending => break,
+            }
+        }
+        let quiet = match &mut this.timer {
+            Some(timer) => closed || timer.as_mut().poll(cx).is_ready(),
+            None => false,
+        };
+        if quiet {
+            let skip_unchanged = this.skip_unchanged;
+            this.timer = None;
+            this.ready.extend(
+                this.pending
+                    .drain(..)
+                    .filter(|change| !skip_unchanged || change.old != change.new),
+            );
+        }
+        match this.ready.pop_front() {
+            Some(change) => Poll::Ready(Some(change)),
+            None if closed && this.pending.is_empty() => Poll::Ready(None),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// Whether `key` falls under the watched `path`: the key itself, or any key
+/// below it if `path` ends with `/`. An empty path matches every key.
+fn watches(path: &str, key: &str) -> bool {
+    if path.is_empty() || path.ends_with('/') {
+        key.starts_with(path)
+    } else {
+        key == path
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,15 +153,19 @@ impl SharedState {
         Self {
             doc: Doc::new(),
             topic: gossipsub::IdentTopic::new(topic_name),
+            watches: Mutex::new(Vec::new()),
+            next_watch: Mutex::new(0),
         }
     }
 
-    /// Apply an incoming update from the network
-    pub fn apply_update(&self, update: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    /// Apply an incoming update from the network. Returns whether it
+    /// advanced the local state vector, i.e. carried something new.
+    pub fn apply_update(&self, update: &[u8]) -> Result<bool, Box<dyn std::error::Error>> {
         let mut txn = self.doc.transact_mut();
         let update = Update::decode_v1(update)?;
+        let before = txn.state_vector();
         txn.apply_update(update)?;
-        Ok(())
+        Ok(txn.state_vector() != before)
     }
 
     /// Generate a local update to broadcast
@@ -43,6 +175,11 @@ impl SharedState {
         txn.encode_state_as_update_v1(sv)
     }
 
+    /// The whole document as one update, for `apply_update` to load.
+    pub fn encode_state(&self) -> Vec<u8> {
+        self.get_update_since(&StateVector::default())
+    }
+
     /// Create a message to start a sync with a peer (send our StateVector)
     pub fn create_sync_step_1(&self) -> SyncMessage {
         let txn = self.doc.transact();
@@ -60,11 +197,18 @@ impl SharedState {
         Ok(SyncMessage::SyncStep2(update))
     }
 
+    /// Whether a peer's encoded state vector differs from ours in either
+    /// direction.
+    pub fn diverges_from(&self, sv_bytes: &[u8]) -> Result<bool, Box<dyn std::error::Error>> {
+        let remote = StateVector::decode_v1(sv_bytes)?;
+        Ok(self.doc.transact().state_vector() != remote)
+    }
+
     /// Handle a sync step 2 message (apply updates)
     pub fn handle_sync_step_2(
         &self,
         update_bytes: &[u8],
-    ) -> Result<(), Box<dyn std::error::Error>> {
+    ) -> Result<bool, Box<dyn std::error::Error>> {
         self.apply_update(update_bytes)
     }
 
@@ -74,4 +218,127 @@ impl SharedState {
         let peers = self.doc.get_or_insert_map("peers");
         peers.insert(&mut txn, peer_id, status);
     }
+
+    /// Set `key` in the shared key/value map. Peers pick the change up
+    /// through anti-entropy.
+    pub fn set(&self, key: &str, value: &str) {
+        let kv = self.doc.get_or_insert_map(KV_MAP);
+        let mut txn = self.doc.transact_mut();
+        kv.insert(&mut txn, key, value);
+    }
+
+    /// Read `key` from the shared key/value map.
+    pub fn get(&self, key: &str) -> Option<String> {
+        let kv = self.doc.get_or_insert_map(KV_MAP);
+        let txn = self.doc.transact();
+        kv.get(&txn, key).map(|value| value.to_string(&txn))
+    }
+
+    /// Watch `path` in the shared key/value map: a single key such as
+    /// `limits/temp`, every key under a prefix such as `limits/`, or every
+    /// key with `""`. Changes arrive from local writes and from applied
+    /// peer updates alike. The stream needs a tokio runtime when
+    /// `options.debounce` is set.
+    pub fn watch(&self, path: &str, options: WatchOptions) -> KeyWatch {
+        let kv = self.doc.get_or_insert_map(KV_MAP);
+        let mut live = self.watches.lock().unwrap();
+        // Drop the observers of watches that have since been dropped.
+        live.retain(|(origin, tx)| {
+            let closed = tx.is_closed();
+            if closed {
+                kv.unobserve(origin.as_str());
+            }
+            !closed
+        });
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let origin = {
+            let mut next = self.next_watch.lock().unwrap();
+            *next += 1;
+            format!("hypha-watch-{next}")
+        };
+        let path = path.to_string();
+        let skip_unchanged = options.skip_unchanged;
+        let sender = tx.clone();
+        kv.observe_with(origin.as_str(), move |txn, event| {
+            for (key, change) in event.keys(txn) {
+                if !watches(&path, key) {
+                    continue;
+                }
+                let (old, new) = match change {
+                    EntryChange::Inserted(new) => (None, Some(new.clone().to_string(txn))),
+                    EntryChange::Updated(old, new) => (
+                        Some(old.clone().to_string(txn)),
+                        Some(new.clone().to_string(txn)),
+                    ),
+                    EntryChange::Removed(old) => (Some(old.clone().to_string(txn)), None),
+                };
+                if skip_unchanged && old == new {
+                    continue;
+                }
+                let _ = sender.send(KeyChange {
+                    key: key.to_string(),
+                    old,
+                    new,
+                });
+            }
+        });
+        live.push((origin, tx));
+
+        KeyWatch {
+            rx,
+            debounce: options.debounce,
+            skip_unchanged,
+            pending: Vec::new(),
+            timer: None,
+            ready: VecDeque::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::futures::StreamExt;
+
+    #[tokio::test]
+    async fn watches_report_local_and_remote_changes_under_a_path(
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let local = SharedState::new("hypha_global_state");
+        let remote = SharedState::new("hypha_global_state");
+        let mut limits = local.watch("limits/", WatchOptions::default());
+        let mut temp = local.watch(
+            "limits/temp",
+            WatchOptions {
+                debounce: Duration::from_millis(20),
+                ..WatchOptions::default()
+            },
+        );
+
+        local.set("limits/temp", "40");
+        local.set("mode", "eco");
+        local.set("limits/temp", "40");
+        local.set("limits/temp", "42");
+        remote.set("limits/humidity", "80");
+        local.apply_update(&remote.get_update_since(&StateVector::default()))?;
+
+        let change = |key: &str, old: Option<&str>, new: &str| KeyChange {
+            key: key.to_string(),
+            old: old.map(str::to_string),
+            new: Some(new.to_string()),
+        };
+        assert_eq!(limits.next().await, Some(change("limits/temp", None, "40")));
+        assert_eq!(
+            limits.next().await,
+            Some(change("limits/temp", Some("40"), "42"))
+        );
+        let humidity = limits.next().await.unwrap();
+        assert_eq!(humidity.value::<u32>(), Some(80));
+
+        // The burst on `limits/temp` arrives as one change once it settles.
+        assert_eq!(temp.next().await, Some(change("limits/temp", None, "42")));
+        drop(limits);
+        let _again = local.watch("limits/", WatchOptions::default());
+        Ok(())
+    }
 }
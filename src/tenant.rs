@@ -0,0 +1,73 @@
+//! Several applications sharing one node.
+//!
+//! A gateway may serve applications that must not see each other's work.
+//! Each hosted [`Tenant`] has its own capability set, its own trusted
+//! issuers and its own shared-state document. Tasks and delegations name
+//! their tenant: a task is bid on only with its tenant's capabilities, and
+//! only a token scoped to the same tenant, rooted at one of that tenant's
+//! issuers, authorizes it. Tasks for tenants the node does not host are
+//! ignored. Audit entries and metrics carry the tenant so each can be read
+//! back per tenant.
+//!
+//! Untenanted tasks, tokens and sync messages keep their existing formats
+//! and use the node-wide settings.
+
+use crate::core::Capability;
+use crate::sync::{SharedState, SyncMessage};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+pub struct Tenant {
+    pub capabilities: Vec<Capability>,
+    /// Issuers whose grants may root this tenant's delegation chains, besides
+    /// the node itself. The node-wide `trusted_issuers` do not apply.
+    pub trusted_issuers: Vec<String>,
+    pub shared_state: Arc<Mutex<SharedState>>,
+}
+
+impl Tenant {
+    pub fn new(id: &str, capabilities: Vec<Capability>) -> Self {
+        Self {
+            capabilities,
+            trusted_issuers: Vec::new(),
+            shared_state: Arc::new(Mutex::new(SharedState::new(&format!("hypha_state/{id}")))),
+        }
+    }
+
+    pub fn with_trusted_issuers(mut self, issuers: Vec<String>) -> Self {
+        self.trusted_issuers = issuers;
+        self
+    }
+
+    pub fn has_capability(&self, required: &Capability) -> bool {
+        self.capabilities
+            .iter()
+            .any(|capability| capability.satisfies(required))
+    }
+}
+
+/// Sync traffic for one tenant's document, sent on the shared-state topic.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TenantSync {
+    pub tenant: String,
+    pub message: SyncMessage,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tenant_and_bare_sync_messages_do_not_cross_decode() {
+        let bare = serde_json::to_vec(&SyncMessage::SyncStep1(vec![0])).unwrap();
+        let scoped = serde_json::to_vec(&TenantSync {
+            tenant: "farm".to_string(),
+            message: SyncMessage::SyncStep1(vec![0]),
+        })
+        .unwrap();
+        assert!(serde_json::from_slice::<TenantSync>(&bare).is_err());
+        assert!(serde_json::from_slice::<SyncMessage>(&scoped).is_err());
+        let decoded: TenantSync = serde_json::from_slice(&scoped).unwrap();
+        assert_eq!(decoded.tenant, "farm");
+    }
+}
@@ -0,0 +1,368 @@
+//! Harness for multi-node integration tests.
+//!
+//! [`TestNet::spawn`] starts N nodes on localhost, each with its own storage
+//! under one temporary directory, subscribes them to every topic and dials
+//! them into the requested [`Topology`]. Nothing runs on its own: the test
+//! drives the swarms with [`TestNet::drive_until`], which polls every live
+//! node and hands each event to a predicate.
+//!
+//! Two chaos knobs cover the usual failure tests. [`TestNet::kill`] drops a
+//! node's swarm, closing its connections as a crash would. [`TestNet::isolate`]
+//! cuts a node off from everyone and keeps it cut off, dropping any connection
+//! either side re-establishes, until [`TestNet::heal`] redials its links.
+//!
+//! ```no_run
+//! # async fn demo() -> Result<(), Box<dyn std::error::Error>> {
+//! use hypha::mycelium::TopicKind;
+//! use hypha::testing::{TestNet, Topology};
+//! use std::time::Duration;
+//!
+//! let mut net = TestNet::spawn(4, Topology::Full).await?;
+//! assert!(net.wait_for_mesh(Duration::from_secs(5)).await);
+//! net.kill(0)?;
+//! net.isolate(1);
+//! net.publish(2, TopicKind::Status, b"hello".to_vec())?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::mycelium::{Mycelium, MyceliumEvent, NetProfile, TopicKind};
+use crate::SporeNode;
+use libp2p::futures::future::select_all;
+use libp2p::futures::StreamExt;
+use libp2p::gossipsub::{MessageId, PublishError};
+use libp2p::swarm::dial_opts::DialOpts;
+use libp2p::swarm::SwarmEvent;
+use libp2p::{Multiaddr, PeerId};
+use std::error::Error;
+use std::time::Duration;
+use tempfile::TempDir;
+use tokio::time::Instant;
+
+/// How long `spawn` waits for listeners and for the topology to connect.
+const SETUP_TIMEOUT: Duration = Duration::from_secs(5);
+/// Step between mesh checks in `wait_for_mesh`.
+const MESH_POLL: Duration = Duration::from_millis(50);
+
+/// Who dials whom at spawn, and again when an isolated node heals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Topology {
+    /// Every node connects to every other.
+    #[default]
+    Full,
+    /// Node i connects to node i + 1.
+    Line,
+    /// Every node connects to node 0.
+    Star,
+}
+
+impl Topology {
+    /// The (dialer, listener) pairs for `n` nodes.
+    pub fn links(self, n: usize) -> Vec<(usize, usize)> {
+        match self {
+            Topology::Full => (0..n)
+                .flat_map(|a| (a + 1..n).map(move |b| (a, b)))
+                .collect(),
+            Topology::Line => (1..n).map(|b| (b - 1, b)).collect(),
+            Topology::Star => (1..n).map(|a| (a, 0)).collect(),
+        }
+    }
+}
+
+pub struct TestNode {
+    pub node: SporeNode,
+    pub mycelium: Mycelium,
+    pub peer_id: PeerId,
+    /// Where the node first listened.
+    pub addr: Multiaddr,
+    alive: bool,
+    isolated: bool,
+}
+
+impl TestNode {
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
+
+    pub fn is_isolated(&self) -> bool {
+        self.isolated
+    }
+
+    /// Alive and not isolated.
+    pub fn is_reachable(&self) -> bool {
+        self.alive && !self.isolated
+    }
+
+    /// Peers in this node's gossipsub mesh for `kind`.
+    pub fn mesh_peers(&self, kind: TopicKind) -> Vec<PeerId> {
+        let topic = self.mycelium.topic(kind).hash();
+        self.mycelium
+            .swarm
+            .behaviour()
+            .gossipsub
+            .mesh_peers(&topic)
+            .copied()
+            .collect()
+    }
+}
+
+pub struct TestNet {
+    pub nodes: Vec<TestNode>,
+    pub topology: Topology,
+    profile: NetProfile,
+    // Keeps every node's storage until the net is dropped.
+    _dir: TempDir,
+}
+
+impl TestNet {
+    /// `n` TCP nodes connected in `topology`.
+    pub async fn spawn(n: usize, topology: Topology) -> Result<Self, Box<dyn Error>> {
+        Self::spawn_with_profile(n, topology, NetProfile::Tcp).await
+    }
+
+    pub async fn spawn_with_profile(
+        n: usize,
+        topology: Topology,
+        profile: NetProfile,
+    ) -> Result<Self, Box<dyn Error>> {
+        let dir = tempfile::tempdir()?;
+        let listen: Multiaddr = match profile {
+            NetProfile::Tcp => "/ip4/127.0.0.1/tcp/0",
+            NetProfile::TcpQuic | NetProfile::Mobile => "/ip4/127.0.0.1/udp/0/quic-v1",
+        }
+        .parse()?;
+
+        let mut nodes = Vec::with_capacity(n);
+        for i in 0..n {
+            let path = dir.path().join(format!("n{i}"));
+            std::fs::create_dir_all(&path)?;
+            let node = SporeNode::new(&path)?;
+            let mut mycelium = node.build_mycelium_with_profile(profile)?;
+            mycelium.subscribe_all()?;
+            mycelium.listen_on(listen.clone())?;
+            let addr = capture_listen_addr(&mut mycelium, SETUP_TIMEOUT)
+                .await
+                .ok_or_else(|| format!("node {i} did not obtain a listen address"))?;
+            nodes.push(TestNode {
+                peer_id: node.peer_id,
+                node,
+                mycelium,
+                addr,
+                alive: true,
+                isolated: false,
+            });
+        }
+
+        let mut net = Self {
+            nodes,
+            topology,
+            profile,
+            _dir: dir,
+        };
+        let links = topology.links(n);
+        for &(a, b) in &links {
+            net.dial(a, b)?;
+        }
+        let connected = net
+            .drive_until(SETUP_TIMEOUT, |net, _, _| {
+                links.iter().all(|&(a, b)| net.connected(a, b))
+            })
+            .await;
+        if !connected && !links.is_empty() {
+            return Err(format!("{topology:?} topology of {n} nodes did not connect").into());
+        }
+        Ok(net)
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn node(&self, i: usize) -> &TestNode {
+        &self.nodes[i]
+    }
+
+    pub fn peer_id(&self, i: usize) -> PeerId {
+        self.nodes[i].peer_id
+    }
+
+    /// Whether node `a` holds a connection to node `b`.
+    pub fn connected(&self, a: usize, b: usize) -> bool {
+        self.nodes[a]
+            .mycelium
+            .swarm
+            .is_connected(&self.nodes[b].peer_id)
+    }
+
+    pub fn publish(
+        &mut self,
+        i: usize,
+        kind: TopicKind,
+        data: Vec<u8>,
+    ) -> Result<MessageId, PublishError> {
+        let mycelium = &mut self.nodes[i].mycelium;
+        let topic = mycelium.topic(kind).clone();
+        mycelium
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(topic, data)
+    }
+
+    /// Poll every live node until `done` returns true for an event, or
+    /// `timeout` passes. Returns whether `done` was satisfied.
+    pub async fn drive_until(
+        &mut self,
+        timeout: Duration,
+        mut done: impl FnMut(&mut Self, usize, &SwarmEvent<MyceliumEvent>) -> bool,
+    ) -> bool {
+        let deadline = Instant::now() + timeout;
+        while let Some((i, event)) = self.next_event(deadline).await {
+            self.enforce_isolation(i, &event);
+            if done(self, i, &event) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Keep every live node running for `duration`.
+    pub async fn drive_for(&mut self, duration: Duration) {
+        self.drive_until(duration, |_, _, _| false).await;
+    }
+
+    /// Drive until every reachable node has a status-topic mesh peer, which
+    /// is when gossip between them stops depending on heartbeat timing.
+    pub async fn wait_for_mesh(&mut self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while !self.meshed() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            self.drive_for(MESH_POLL).await;
+        }
+        true
+    }
+
+    fn meshed(&self) -> bool {
+        let reachable: Vec<&TestNode> = self.nodes.iter().filter(|n| n.is_reachable()).collect();
+        reachable.len() < 2
+            || reachable
+                .iter()
+                .all(|n| !n.mesh_peers(TopicKind::Status).is_empty())
+    }
+
+    /// Crash node `i`: its swarm is dropped, closing every connection, and
+    /// the node is no longer polled.
+    pub fn kill(&mut self, i: usize) -> Result<(), Box<dyn Error>> {
+        let node = &mut self.nodes[i];
+        // An unpolled replacement with no listeners keeps the field valid.
+        node.mycelium = node.node.build_mycelium_with_profile(self.profile)?;
+        node.alive = false;
+        Ok(())
+    }
+
+    /// Cut node `i` off from every other node until `heal`.
+    pub fn isolate(&mut self, i: usize) {
+        self.nodes[i].isolated = true;
+        let peer = self.nodes[i].peer_id;
+        for (j, other) in self.nodes.iter_mut().enumerate() {
+            if j == i {
+                let connected: Vec<PeerId> =
+                    other.mycelium.swarm.connected_peers().copied().collect();
+                for p in connected {
+                    let _ = other.mycelium.swarm.disconnect_peer_id(p);
+                }
+            } else if other.alive {
+                let _ = other.mycelium.swarm.disconnect_peer_id(peer);
+            }
+        }
+    }
+
+    /// Let node `i` back in and redial its topology links to live nodes.
+    pub fn heal(&mut self, i: usize) -> Result<(), Box<dyn Error>> {
+        self.nodes[i].isolated = false;
+        for (a, b) in self.topology.links(self.nodes.len()) {
+            if (a == i || b == i) && self.nodes[a].alive && self.nodes[b].alive {
+                self.dial(a, b)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn dial(&mut self, a: usize, b: usize) -> Result<(), Box<dyn Error>> {
+        let (peer, addr) = (self.nodes[b].peer_id, self.nodes[b].addr.clone());
+        self.nodes[a]
+            .mycelium
+            .swarm
+            .dial(DialOpts::peer_id(peer).addresses(vec![addr]).build())?;
+        Ok(())
+    }
+
+    /// The next event of any live node, or `None` at `deadline`.
+    async fn next_event(
+        &mut self,
+        deadline: Instant,
+    ) -> Option<(usize, SwarmEvent<MyceliumEvent>)> {
+        let polls: Vec<_> = self
+            .nodes
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, n)| n.alive)
+            .map(|(i, n)| Box::pin(async move { (i, n.mycelium.swarm.select_next_some().await) }))
+            .collect();
+        if polls.is_empty() {
+            tokio::time::sleep_until(deadline).await;
+            return None;
+        }
+        tokio::time::timeout_at(deadline, select_all(polls))
+            .await
+            .ok()
+            .map(|(event, _, _)| event)
+    }
+
+    /// Drop connections that reach across an isolation boundary; gossipsub
+    /// and redials would otherwise restore them.
+    fn enforce_isolation(&mut self, i: usize, event: &SwarmEvent<MyceliumEvent>) {
+        let SwarmEvent::ConnectionEstablished { peer_id, .. } = event else {
+            return;
+        };
+        let crosses = self.nodes[i].isolated
+            || self
+                .nodes
+                .iter()
+                .any(|n| n.isolated && n.peer_id == *peer_id);
+        if crosses {
+            let _ = self.nodes[i].mycelium.swarm.disconnect_peer_id(*peer_id);
+        }
+    }
+}
+
+/// Poll `mycelium` until it reports a listen address.
+pub async fn capture_listen_addr(mycelium: &mut Mycelium, timeout: Duration) -> Option<Multiaddr> {
+    let deadline = Instant::now() + timeout;
+    while let Ok(event) = tokio::time::timeout_at(deadline, mycelium.swarm.select_next_some()).await
+    {
+        if let SwarmEvent::NewListenAddr { address, .. } = event {
+            return Some(address);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topologies_link_every_node() {
+        assert_eq!(Topology::Full.links(3), [(0, 1), (0, 2), (1, 2)]);
+        assert_eq!(Topology::Line.links(3), [(0, 1), (1, 2)]);
+        assert_eq!(Topology::Star.links(3), [(1, 0), (2, 0)]);
+        assert!(Topology::Line.links(1).is_empty());
+    }
+}
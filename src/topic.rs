@@ -0,0 +1,162 @@
+//! Typed gossip topics.
+//!
+//! A [`Topic`] pairs a [`TopicKind`] with the message type published on it,
+//! so `Mycelium::publish` only accepts the right body for a topic and a
+//! [`Subscription`] hands back decoded envelopes instead of raw bytes. Some
+//! kinds carry more than one type: lease messages share the task topic,
+//! catalog deltas the status topic and tenant sync the shared-state topic.
+//! Each type has its own constant, and decoding as one type skips messages
+//! of the others.
+
+use crate::catalog::CatalogDelta;
+use crate::control::SignedControl;
+use crate::core::{EnergyStatus, SensorReading, Task};
+use crate::departure::Departing;
+use crate::lease::LeaseMessage;
+use crate::mycelium::{MyceliumEvent, TopicKind};
+use crate::quorum::QuorumMessage;
+use crate::spike::Spike;
+use crate::sync::SyncMessage;
+use crate::tenant::TenantSync;
+use crate::wire::{self, Envelope};
+use libp2p::{gossipsub, swarm::SwarmEvent, PeerId};
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::marker::PhantomData;
+
+pub const STATUS: Topic<EnergyStatus> = Topic::new(TopicKind::Status);
+pub const CATALOG: Topic<CatalogDelta> = Topic::new(TopicKind::Status);
+pub const CONTROL: Topic<SignedControl> = Topic::new(TopicKind::Control);
+pub const TASKS: Topic<Task> = Topic::new(TopicKind::Task);
+pub const LEASES: Topic<LeaseMessage> = Topic::new(TopicKind::Task);
+pub const SPIKES: Topic<Spike> = Topic::new(TopicKind::Spike);
+pub const SHARED_STATE: Topic<SyncMessage> = Topic::new(TopicKind::SharedState);
+pub const TENANT_SYNC: Topic<TenantSync> = Topic::new(TopicKind::SharedState);
+pub const SENSOR: Topic<SensorReading> = Topic::new(TopicKind::Sensor);
+pub const QUORUM: Topic<QuorumMessage> = Topic::new(TopicKind::Quorum);
+pub const DEPARTURES: Topic<Departing> = Topic::new(TopicKind::Departure);
+
+/// A gossip topic carrying `T` bodies.
+pub struct Topic<T> {
+    kind: TopicKind,
+    body: PhantomData<fn() -> T>,
+}
+
+impl<T> Topic<T> {
+    pub const fn new(kind: TopicKind) -> Self {
+        Self {
+            kind,
+            body: PhantomData,
+        }
+    }
+
+    pub fn kind(&self) -> TopicKind {
+        self.kind
+    }
+}
+
+impl<T: DeserializeOwned> Topic<T> {
+    /// Decode one envelope (not a batch frame) published on this topic.
+    pub fn decode(&self, bytes: &[u8]) -> serde_json::Result<Envelope<T>> {
+        wire::decode(bytes)
+    }
+}
+
+impl<T> Clone for Topic<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Topic<T> {}
+
+impl<T> fmt::Debug for Topic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Topic")
+            .field(&self.kind)
+            .field(&std::any::type_name::<T>())
+            .finish()
+    }
+}
+
+/// A message decoded from a subscribed topic.
+#[derive(Debug, Clone)]
+pub struct Received<T> {
+    /// The peer that forwarded the message to this node.
+    pub propagation_source: PeerId,
+    /// The peer that signed the gossip message, if any.
+    pub source: Option<PeerId>,
+    pub message_id: gossipsub::MessageId,
+    pub envelope: Envelope<T>,
+}
+
+/// The messages of one topic, as joined under the node's namespace. Built
+/// by `Mycelium::subscribe`.
+#[derive(Debug, Clone)]
+pub struct Subscription<T> {
+    topic: Topic<T>,
+    hash: gossipsub::TopicHash,
+}
+
+impl<T: DeserializeOwned> Subscription<T> {
+    pub(crate) fn new(topic: Topic<T>, hash: gossipsub::TopicHash) -> Self {
+        Self { topic, hash }
+    }
+
+    pub fn topic(&self) -> Topic<T> {
+        self.topic
+    }
+
+    pub fn hash(&self) -> &gossipsub::TopicHash {
+        &self.hash
+    }
+
+    /// The `T` messages `event` carries, one per envelope of a batch frame.
+    /// Other events, topics and message types give nothing.
+    pub fn accept(&self, event: &SwarmEvent<MyceliumEvent>) -> Vec<Received<T>> {
+        let SwarmEvent::Behaviour(MyceliumEvent::Gossipsub(gossipsub::Event::Message {
+            propagation_source,
+            message_id,
+            message,
+        })) = event
+        else {
+            return Vec::new();
+        };
+        if message.topic != self.hash {
+            return Vec::new();
+        }
+        let items = match wire::unbatch(&message.data) {
+            Ok(items) => items,
+            Err(e) => {
+                tracing::debug!(%message_id, err = %e, "Skipping malformed batch");
+                return Vec::new();
+            }
+        };
+        items
+            .into_iter()
+            .filter_map(|item| self.topic.decode(item).ok())
+            .map(|envelope| Received {
+                propagation_source: *propagation_source,
+                source: message.source,
+                message_id: message_id.clone(),
+                envelope,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::Priority;
+
+    #[test]
+    fn topics_decode_only_their_own_type() {
+        let reading = SensorReading::new("n1".into(), "temp".into(), 21.5);
+        let bytes = Envelope::new(Priority::Normal, &reading).encode().unwrap();
+        assert_eq!(SENSOR.decode(&bytes).unwrap().body, reading);
+        assert!(QUORUM.decode(&bytes).is_err());
+        assert_eq!(TASKS.kind(), LEASES.kind());
+        assert!(format!("{CATALOG:?}").contains("CatalogDelta"));
+    }
+}
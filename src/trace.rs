@@ -0,0 +1,225 @@
+//! End-to-end hop records for diagnosing propagation.
+//!
+//! A [`Trace`] rides in the wire envelope of messages that ask for one, either
+//! per message (`Mycelium::publish_traced`) or per topic (`Mycelium::traced`).
+//! It starts at the origin and gains a [`Hop`] each time a node handles the
+//! message: on receipt the run loop appends itself and emits
+//! `NodeEvent::Traced`, and an emergent relay republishes with its own hop
+//! included. Gossipsub forwards bytes unchanged, so the record lists the
+//! nodes that handled the message rather than every link it crossed.
+//!
+//! The hop list is capped at [`MAX_TRACE_HOPS`]; `hop_count` keeps counting
+//! past the cap.
+
+use crate::wire::{self, Envelope};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const MAX_TRACE_HOPS: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hop {
+    pub peer: String,
+    /// Unix time in milliseconds.
+    pub at_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trace {
+    pub origin: String,
+    /// The origin first, then each handling node in order.
+    pub hops: Vec<Hop>,
+    /// Hops after the origin, including any dropped at the cap.
+    pub hop_count: u32,
+}
+
+impl Trace {
+    pub fn new(origin: &str, at_ms: u64) -> Self {
+        Self {
+            origin: origin.to_string(),
+            hops: vec![Hop {
+                peer: origin.to_string(),
+                at_ms,
+            }],
+            hop_count: 0,
+        }
+    }
+
+    /// Append `peer` as the next hop. Returns `false` if `peer` had already
+    /// handled the message, i.e. it came back around a loop.
+    pub fn record_hop(&mut self, peer: &str, at_ms: u64) -> bool {
+        let fresh = !self.visited(peer);
+        self.hop_count = self.hop_count.saturating_add(1);
+        if self.hops.len() < MAX_TRACE_HOPS {
+            self.hops.push(Hop {
+                peer: peer.to_string(),
+                at_ms,
+            });
+        }
+        fresh
+    }
+
+    pub fn visited(&self, peer: &str) -> bool {
+        self.hops.iter().any(|hop| hop.peer == peer)
+    }
+
+    /// Whether hops were dropped at the cap.
+    pub fn truncated(&self) -> bool {
+        self.hops.len() <= self.hop_count as usize
+    }
+
+    /// Milliseconds from the origin to the last recorded hop.
+    pub fn elapsed_ms(&self) -> u64 {
+        match (self.hops.first(), self.hops.last()) {
+            (Some(first), Some(last)) => last.at_ms.saturating_sub(first.at_ms),
+            _ => 0,
+        }
+    }
+
+    fn has_loop(&self) -> bool {
+        self.hops
+            .iter()
+            .enumerate()
+            .any(|(i, hop)| self.hops[..i].iter().any(|h| h.peer == hop.peer))
+    }
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// The trace carried by an enveloped payload, if any.
+pub fn peek(bytes: &[u8]) -> Option<Trace> {
+    #[derive(Deserialize)]
+    struct Probe {
+        #[serde(default)]
+        trace: Option<Trace>,
+    }
+    let bytes = wire::inflate(bytes).ok()?;
+    serde_json::from_slice::<Probe>(&bytes).ok()?.trace
+}
+
+/// Re-encode an enveloped payload with `trace` in place of its current one,
+/// leaving the body untouched. `None` if `bytes` is not an envelope. The
+/// result is uncompressed.
+pub fn restamp(bytes: &[u8], trace: Trace) -> Option<Vec<u8>> {
+    let bytes = wire::inflate(bytes).ok()?;
+    let mut envelope: Envelope<serde_json::Value> = serde_json::from_slice(&bytes).ok()?;
+    envelope.trace = Some(trace);
+    envelope.encode().ok()
+}
+
+/// How one message spread, rebuilt from the traces its receivers reported.
+#[derive(Debug, Default)]
+pub struct PropagationTree {
+    pub origin: Option<String>,
+    parents: HashMap<String, String>,
+    depths: HashMap<String, u32>,
+    loops: usize,
+}
+
+impl PropagationTree {
+    pub fn from_traces<'a>(traces: impl IntoIterator<Item = &'a Trace>) -> Self {
+        let mut tree = Self::default();
+        for trace in traces {
+            tree.origin.get_or_insert_with(|| trace.origin.clone());
+            if trace.has_loop() {
+                tree.loops += 1;
+                continue;
+            }
+            for pair in trace.hops.windows(2) {
+                tree.parents
+                    .entry(pair[1].peer.clone())
+                    .or_insert_with(|| pair[0].peer.clone());
+            }
+            if let Some(last) = trace.hops.last().filter(|_| trace.hop_count > 0) {
+                let depth = tree.depths.entry(last.peer.clone()).or_insert(u32::MAX);
+                *depth = (*depth).min(trace.hop_count);
+            }
+        }
+        tree
+    }
+
+    /// The node `peer` first heard the message from.
+    pub fn parent(&self, peer: &str) -> Option<&str> {
+        self.parents.get(peer).map(String::as_str)
+    }
+
+    /// Fewest hops any trace ending at `peer` took.
+    pub fn depth(&self, peer: &str) -> Option<u32> {
+        self.depths.get(peer).copied()
+    }
+
+    pub fn max_depth(&self) -> u32 {
+        self.depths.values().copied().max().unwrap_or(0)
+    }
+
+    pub fn reached(&self) -> usize {
+        self.depths.len()
+    }
+
+    /// Traces that visited some node twice.
+    pub fn loops(&self) -> usize {
+        self.loops
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::{self, Priority};
+
+    #[test]
+    fn hops_are_capped_but_still_counted() {
+        let mut trace = Trace::new("a", 0);
+        for i in 0..MAX_TRACE_HOPS + 4 {
+            assert!(trace.record_hop(&format!("n{i}"), i as u64));
+        }
+        assert_eq!(trace.hops.len(), MAX_TRACE_HOPS);
+        assert_eq!(trace.hop_count as usize, MAX_TRACE_HOPS + 4);
+        assert!(trace.truncated());
+        assert!(!trace.record_hop("n3", 99), "n3 was already on the path");
+    }
+
+    #[test]
+    fn trace_survives_the_envelope_and_restamp() {
+        let mut envelope = Envelope::new(Priority::Normal, "payload".to_string());
+        envelope.trace = Some(Trace::new("a", 10));
+        let bytes = envelope.encode().unwrap();
+
+        let mut trace = peek(&bytes).unwrap();
+        trace.record_hop("b", 25);
+        let relayed = restamp(&bytes, trace).unwrap();
+
+        let decoded = wire::decode::<String>(&relayed).unwrap();
+        assert_eq!(decoded.body, "payload");
+        let trace = decoded.trace.unwrap();
+        assert_eq!((trace.hop_count, trace.elapsed_ms()), (1, 15));
+        assert!(peek(&Envelope::new(Priority::Low, 1).encode().unwrap()).is_none());
+    }
+
+    #[test]
+    fn tree_rebuilds_parents_depths_and_loops() {
+        let path = |peers: &[&str]| {
+            let mut trace = Trace::new(peers[0], 0);
+            for (i, peer) in peers[1..].iter().enumerate() {
+                trace.record_hop(peer, i as u64 + 1);
+            }
+            trace
+        };
+        let traces = [
+            path(&["a", "b"]),
+            path(&["a", "b", "c"]),
+            path(&["a", "d"]),
+            path(&["a", "b", "a"]),
+        ];
+        let tree = PropagationTree::from_traces(&traces);
+        assert_eq!(tree.origin.as_deref(), Some("a"));
+        assert_eq!(tree.parent("c"), Some("b"));
+        assert_eq!(tree.depth("c"), Some(2));
+        assert_eq!((tree.max_depth(), tree.reached(), tree.loops()), (2, 3, 1));
+    }
+}
@@ -0,0 +1,178 @@
+//! Small helpers shared by the run loop, the swarm wrapper and examples.
+
+use crate::core::Metabolism;
+use rand::Rng;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Bounded retries with exponential backoff and jitter.
+///
+/// The delay before retry `n` is `base_delay * 2^(n-1)`, capped at
+/// `max_delay`, with up to `jitter` of it taken off at random so that peers
+/// that failed together do not retry together. Retrying spends energy, so no
+/// retry is made while the node's energy score is below `min_energy`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Attempts in total, the first one included.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction of each delay that is randomized, in [0, 1].
+    pub jitter: f64,
+    pub min_energy: f32,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            jitter: 0.5,
+            // Critical power mode starts below 0.2.
+            min_energy: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry`, counting the first retry as 1.
+    pub fn delay(&self, retry: u32, rng: &mut impl Rng) -> Duration {
+        let doublings = retry.saturating_sub(1).min(31);
+        let full = self
+            .base_delay
+            .saturating_mul(1 << doublings)
+            .min(self.max_delay);
+        full.mul_f64(1.0 - self.jitter.clamp(0.0, 1.0) * rng.random::<f64>())
+    }
+
+    /// Whether another attempt may follow `attempts` failed ones at `energy`.
+    pub fn allows_retry(&self, attempts: u32, energy: f32) -> bool {
+        attempts < self.max_attempts && energy >= self.min_energy
+    }
+
+    pub fn backoff(&self) -> Backoff {
+        Backoff {
+            policy: self.clone(),
+            attempts: 0,
+        }
+    }
+}
+
+/// Attempt counter for one operation under a [`RetryPolicy`], for loops that
+/// cannot hand their operation to [`retry`], such as ones borrowing a swarm.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    policy: RetryPolicy,
+    attempts: u32,
+}
+
+impl Backoff {
+    /// Record a failed attempt. Returns how long to wait before the next one,
+    /// or `None` once the policy gives up.
+    pub fn next_delay(&mut self, energy: f32) -> Option<Duration> {
+        self.attempts += 1;
+        self.policy
+            .allows_retry(self.attempts, energy)
+            .then(|| self.policy.delay(self.attempts, &mut rand::rng()))
+    }
+
+    /// Failed attempts recorded so far.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
+
+/// Run `op` until it succeeds or `policy` gives up, sleeping between
+/// attempts. The energy score is read from `metabolism` before each retry.
+/// Returns the last error if every attempt failed.
+pub async fn retry<T, E, F, Fut>(
+    policy: &RetryPolicy,
+    metabolism: &Mutex<dyn Metabolism>,
+    mut op: F,
+) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut backoff = policy.backoff();
+    loop {
+        let err = match op().await {
+            Ok(value) => return Ok(value),
+            Err(err) => err,
+        };
+        let energy = metabolism.lock().unwrap().energy_score();
+        match backoff.next_delay(energy) {
+            Some(delay) => tokio::time::sleep(delay).await,
+            None => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::MockMetabolism;
+    use std::sync::Arc;
+
+    #[test]
+    fn delay_doubles_up_to_the_cap() {
+        let policy = RetryPolicy {
+            jitter: 0.0,
+            max_delay: Duration::from_millis(500),
+            ..RetryPolicy::default()
+        };
+        let mut rng = rand::rng();
+        let delays: Vec<_> = (1..=5).map(|n| policy.delay(n, &mut rng)).collect();
+        assert_eq!(delays, [100, 200, 400, 500, 500].map(Duration::from_millis));
+
+        let jittered = RetryPolicy::default();
+        for _ in 0..100 {
+            let d = jittered.delay(2, &mut rng);
+            assert!(d > Duration::from_millis(100) && d <= Duration::from_millis(200));
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_succeeds_within_budget_and_stops_on_low_energy() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        };
+        let healthy: Arc<Mutex<dyn Metabolism>> =
+            Arc::new(Mutex::new(MockMetabolism::new(0.9, false)));
+        let mut calls = 0;
+        let result: Result<u32, &str> = retry(&policy, &healthy, || {
+            calls += 1;
+            let n = calls;
+            async move {
+                if n < 3 {
+                    Err("busy")
+                } else {
+                    Ok(n)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result, Ok(3));
+
+        let mut calls = 0;
+        let result: Result<(), &str> = retry(&policy, &healthy, || {
+            calls += 1;
+            async { Err("down") }
+        })
+        .await;
+        assert_eq!((result, calls), (Err("down"), policy.max_attempts));
+
+        let drained: Arc<Mutex<dyn Metabolism>> =
+            Arc::new(Mutex::new(MockMetabolism::new(0.1, false)));
+        let mut calls = 0;
+        let _: Result<(), &str> = retry(&policy, &drained, || {
+            calls += 1;
+            async { Err("down") }
+        })
+        .await;
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,153 @@
+//! Run-loop liveness watchdog.
+//!
+//! The run loop stamps a [`Watermark`] on every pass through its select:
+//! a dedicated tick proves it is still polling, and each handled event adds
+//! its kind to a short history. [`monitor`] runs as its own task and, once
+//! the watermark is older than `stall_after`, logs the history and wakes the
+//! supervisor in `SporeNode::run_supervised`, which stops the stalled loop,
+//! rebuilds the swarm and carries on with the same node state.
+//!
+//! A loop stuck awaiting something is cancelled cleanly. The loop runs on
+//! its own thread, so one blocked inside a synchronous call (say, a mutex
+//! deadlock) cannot block the supervisor: after another `stall_after` it is
+//! abandoned and its node retired, and a successor node sharing its handles
+//! takes over.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Event kinds kept for stall diagnostics.
+const RECENT_EVENTS: usize = 32;
+
+/// How often the run loop stamps the watermark when nothing else happens.
+pub const LIVENESS_TICK: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchdogConfig {
+    /// A watermark this old means the loop has stalled.
+    pub stall_after: Duration,
+    pub check_every: Duration,
+    /// Restarts allowed within one `run_supervised` call before giving up.
+    pub max_restarts: u32,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            stall_after: Duration::from_secs(30),
+            check_every: Duration::from_secs(5),
+            max_restarts: 5,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Watermark {
+    epoch: Instant,
+    /// Milliseconds since `epoch` at the last stamp.
+    last_ms: AtomicU64,
+    recent: Mutex<VecDeque<&'static str>>,
+}
+
+impl Default for Watermark {
+    fn default() -> Self {
+        Self {
+            epoch: Instant::now(),
+            last_ms: AtomicU64::new(0),
+            recent: Mutex::new(VecDeque::with_capacity(RECENT_EVENTS)),
+        }
+    }
+}
+
+impl Watermark {
+    /// Record that the loop is alive and just handled `what`.
+    pub fn beat(&self, what: &'static str) {
+        let now_ms = self.epoch.elapsed().as_millis() as u64;
+        self.last_ms.fetch_max(now_ms, Ordering::Relaxed);
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() == RECENT_EVENTS {
+            recent.pop_front();
+        }
+        recent.push_back(what);
+    }
+
+    pub fn since_last(&self) -> Duration {
+        let last = Duration::from_millis(self.last_ms.load(Ordering::Relaxed));
+        self.epoch.elapsed().saturating_sub(last)
+    }
+
+    /// Recently handled event kinds, oldest first.
+    pub fn recent(&self) -> Vec<&'static str> {
+        self.recent.lock().unwrap().iter().copied().collect()
+    }
+}
+
+/// Watch `watermark` until it goes stale, then log the recent event history,
+/// notify `stalled` and return how long the loop had been silent.
+pub async fn monitor(
+    watermark: Arc<Watermark>,
+    config: WatchdogConfig,
+    stalled: Arc<Notify>,
+) -> Duration {
+    let mut check = tokio::time::interval(config.check_every);
+    loop {
+        check.tick().await;
+        let idle = watermark.since_last();
+        if idle >= config.stall_after {
+            tracing::error!(
+                idle_ms = idle.as_millis() as u64,
+                recent = ?watermark.recent(),
+                "Run loop stalled"
+            );
+            stalled.notify_one();
+            return idle;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watermark_keeps_a_bounded_history() {
+        let watermark = Watermark::default();
+        for _ in 0..RECENT_EVENTS {
+            watermark.beat("swarm:gossipsub");
+        }
+        watermark.beat("heartbeat");
+        let recent = watermark.recent();
+        assert_eq!(recent.len(), RECENT_EVENTS);
+        assert_eq!(recent.last(), Some(&"heartbeat"));
+        assert!(watermark.since_last() < Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn monitor_fires_only_once_beats_stop() {
+        let watermark = Arc::new(Watermark::default());
+        let stalled = Arc::new(Notify::new());
+        let config = WatchdogConfig {
+            stall_after: Duration::from_millis(60),
+            check_every: Duration::from_millis(10),
+            max_restarts: 1,
+        };
+        let task = tokio::spawn(monitor(watermark.clone(), config, stalled.clone()));
+        for _ in 0..10 {
+            watermark.beat("tick");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!task.is_finished());
+
+        let idle = tokio::time::timeout(Duration::from_secs(2), task)
+            .await
+            .expect("monitor reports the stall")
+            .unwrap();
+        assert!(idle >= Duration::from_millis(60));
+        tokio::time::timeout(Duration::from_secs(1), stalled.notified())
+            .await
+            .expect("supervisor is woken");
+    }
+}
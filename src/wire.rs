@@ -0,0 +1,697 @@
+//! Versioned wire envelope and energy-aware send policy.
+//!
+//! Payloads published by `SporeNode::run_for` travel inside an [`Envelope`]
+//! carrying a format version and a [`Priority`]. Receivers also accept bare
+//! legacy payloads, so older publishers and hand-rolled test swarms keep
+//! working.
+//!
+//! Decoding is tolerant. Fields a newer peer added are ignored by today's
+//! types, and unknown envelope fields are kept in [`Envelope::extra`] so
+//! that a relay re-encoding the envelope (to stamp a trace hop or a zone
+//! bridge tag) passes them on. Newer envelope versions are read with
+//! today's layout. [`crate::schema`] lists each body type's version and the
+//! rules for changing one.
+//!
+//! Large envelopes on topics chosen by a [`CompressionPolicy`] are sent as a
+//! compressed frame instead: a zero byte (which no JSON text starts with),
+//! [`COMPRESSED_ENVELOPE_VERSION`], a [`Codec`] id, then the compressed JSON
+//! envelope. [`decode`] inflates such frames transparently.
+//!
+//! Small low-priority publishes on topics chosen by a [`BatchPolicy`] wait
+//! for the next pulse peak and go out together as one batch frame: a zero
+//! byte, [`BATCH_FRAME_VERSION`], then each encoded envelope behind its
+//! little-endian `u32` length. One gossip message per topic and pulse saves
+//! the per-message radio overhead that dominates tiny payloads. Receivers
+//! split frames with [`unbatch`].
+
+use crate::bridge::federation::FederationMark;
+use crate::core::PowerMode;
+use crate::mycelium::TopicKind;
+use crate::sequence::Sequence;
+use crate::trace::Trace;
+use crate::zone::BridgeTag;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Current envelope format version. Bare legacy payloads decode as version 0.
+pub const ENVELOPE_VERSION: u8 = 1;
+
+/// Format version of compressed frames.
+pub const COMPRESSED_ENVELOPE_VERSION: u8 = 2;
+
+/// Leading byte of a compressed frame.
+const FRAME_MARKER: u8 = 0;
+
+/// Format version of batch frames.
+pub const BATCH_FRAME_VERSION: u8 = 3;
+
+/// Most envelopes one batch frame may carry.
+pub const MAX_BATCH_ITEMS: usize = 64;
+
+/// Refuse frames that claim to inflate past this, so a small message cannot
+/// make a receiver allocate without bound.
+pub const MAX_INFLATED_BYTES: usize = 1 << 20;
+
+/// Send priority. Ordering is significant: `Low < Normal < High < Critical`.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Priority {
+    /// Periodic chatter such as status adverts.
+    Low,
+    #[default]
+    Normal,
+    /// Mesh control and task awards.
+    High,
+    /// Spikes. Never held back by the send policy.
+    Critical,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub v: u8,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Hop record, present only on traced messages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace: Option<Trace>,
+    /// Present only on messages a zone bridge carried across zones.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bridge: Option<BridgeTag>,
+    /// Present only on messages a federation bridge carried between swarms.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub federation: Option<FederationMark>,
+    /// Present only on messages on topics the publisher sequences.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seq: Option<Sequence>,
+    pub body: T,
+    /// Fields from newer versions that this one does not know, re-emitted
+    /// as they came.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl<T> Envelope<T> {
+    pub fn new(priority: Priority, body: T) -> Self {
+        Self {
+            v: ENVELOPE_VERSION,
+            priority,
+            trace: None,
+            bridge: None,
+            federation: None,
+            seq: None,
+            body,
+            extra: serde_json::Map::new(),
+        }
+    }
+}
+
+impl<T: Serialize> Envelope<T> {
+    pub fn encode(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec(self)
+    }
+}
+
+/// Decode an enveloped payload, falling back to a bare legacy body.
+///
+/// A bare body decodes as a version-0 envelope with `Priority::Normal`. The
+/// returned error is the one from the bare decode, which is the more useful
+/// message for malformed legacy input. Envelopes newer than
+/// [`ENVELOPE_VERSION`] are read with today's layout, keeping the fields it
+/// lacks in `extra`.
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> serde_json::Result<Envelope<T>> {
+    let bytes = inflate(bytes).map_err(serde::de::Error::custom)?;
+    let bytes = bytes.as_ref();
+    if let Ok(envelope) = serde_json::from_slice::<Envelope<T>>(bytes) {
+        return Ok(envelope);
+    }
+    serde_json::from_slice::<T>(bytes).map(|body| Envelope {
+        v: 0,
+        priority: Priority::Normal,
+        trace: None,
+        bridge: None,
+        federation: None,
+        seq: None,
+        body,
+        extra: serde_json::Map::new(),
+    })
+}
+
+/// The priority of an enveloped payload, without decoding its body. `None`
+/// for bare legacy bodies and anything else that is not an envelope.
+pub fn peek_priority(bytes: &[u8]) -> Option<Priority> {
+    #[derive(Deserialize)]
+    struct Probe {
+        // Required, so bare bodies do not pass for envelopes.
+        #[serde(rename = "v")]
+        _version: u8,
+        #[serde(default)]
+        priority: Priority,
+    }
+    let bytes = inflate(bytes).ok()?;
+    serde_json::from_slice::<Probe>(&bytes)
+        .ok()
+        .map(|p| p.priority)
+}
+
+/// The sequence stamp of an enveloped payload, without decoding its body.
+pub fn peek_sequence(bytes: &[u8]) -> Option<Sequence> {
+    #[derive(Deserialize)]
+    struct Probe {
+        #[serde(rename = "v")]
+        _version: u8,
+        seq: Option<Sequence>,
+    }
+    let bytes = inflate(bytes).ok()?;
+    serde_json::from_slice::<Probe>(&bytes).ok()?.seq
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum Codec {
+    /// LZ4 block format with the inflated size prepended.
+    Lz4 = 1,
+}
+
+impl Codec {
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            1 => Some(Codec::Lz4),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum FrameError {
+    #[error("compressed frame version {0} is not supported")]
+    Version(u8),
+    #[error("unknown codec {0}")]
+    Codec(u8),
+    #[error("frame inflates to {0} bytes, over the limit")]
+    TooLarge(usize),
+    #[error("truncated or corrupt compressed frame")]
+    Corrupt,
+    #[error("batch frame carries more than {MAX_BATCH_ITEMS} envelopes")]
+    TooManyItems,
+    #[error("truncated or corrupt batch frame")]
+    CorruptBatch,
+}
+
+/// Which topics get compressed, and from what size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompressionPolicy {
+    pub codec: Codec,
+    /// Encoded envelopes smaller than this are sent as they are.
+    pub threshold: usize,
+    pub topics: HashSet<TopicKind>,
+}
+
+impl Default for CompressionPolicy {
+    fn default() -> Self {
+        Self {
+            codec: Codec::Lz4,
+            threshold: 512,
+            // CRDT updates and task payloads are the large, repetitive ones.
+            topics: HashSet::from([TopicKind::SharedState, TopicKind::Task]),
+        }
+    }
+}
+
+impl CompressionPolicy {
+    /// Compress an encoded envelope for `kind` if the policy covers it and
+    /// the frame comes out smaller. Otherwise `encoded` is returned as is.
+    pub fn apply(&self, kind: TopicKind, encoded: Vec<u8>) -> Vec<u8> {
+        if encoded.len() < self.threshold || !self.topics.contains(&kind) {
+            return encoded;
+        }
+        let framed = compress(self.codec, &encoded);
+        if framed.len() < encoded.len() {
+            framed
+        } else {
+            encoded
+        }
+    }
+}
+
+/// Wrap `bytes` in a compressed frame.
+pub fn compress(codec: Codec, bytes: &[u8]) -> Vec<u8> {
+    let body = match codec {
+        Codec::Lz4 => lz4_flex::compress_prepend_size(bytes),
+    };
+    let mut framed = Vec::with_capacity(body.len() + 3);
+    framed.extend_from_slice(&[FRAME_MARKER, COMPRESSED_ENVELOPE_VERSION, codec as u8]);
+    framed.extend_from_slice(&body);
+    framed
+}
+
+/// The JSON carried by `bytes`, inflating a compressed frame if it is one.
+pub fn inflate(bytes: &[u8]) -> Result<Cow<'_, [u8]>, FrameError> {
+    let [FRAME_MARKER, version, codec, body @ ..] = bytes else {
+        return Ok(Cow::Borrowed(bytes));
+    };
+    if *version != COMPRESSED_ENVELOPE_VERSION {
+        return Err(FrameError::Version(*version));
+    }
+    match Codec::from_id(*codec).ok_or(FrameError::Codec(*codec))? {
+        Codec::Lz4 => {
+            let size: [u8; 4] = body
+                .get(..4)
+                .and_then(|b| b.try_into().ok())
+                .ok_or(FrameError::Corrupt)?;
+            let size = u32::from_le_bytes(size) as usize;
+            if size > MAX_INFLATED_BYTES {
+                return Err(FrameError::TooLarge(size));
+            }
+            lz4_flex::decompress_size_prepended(body)
+                .map(Cow::Owned)
+                .map_err(|_| FrameError::Corrupt)
+        }
+    }
+}
+
+/// Pack encoded envelopes into one batch frame.
+pub fn batch(items: &[Vec<u8>]) -> Vec<u8> {
+    let len = items.iter().map(|item| item.len() + 4).sum::<usize>();
+    let mut framed = Vec::with_capacity(len + 2);
+    framed.extend_from_slice(&[FRAME_MARKER, BATCH_FRAME_VERSION]);
+    for item in items {
+        framed.extend_from_slice(&(item.len() as u32).to_le_bytes());
+        framed.extend_from_slice(item);
+    }
+    framed
+}
+
+/// The envelopes in `bytes`: each one of a batch frame, or `bytes` itself
+/// if it is not one.
+pub fn unbatch(bytes: &[u8]) -> Result<Vec<&[u8]>, FrameError> {
+    let [FRAME_MARKER, BATCH_FRAME_VERSION, body @ ..] = bytes else {
+        return Ok(vec![bytes]);
+    };
+    let mut rest = body;
+    let mut items = Vec::new();
+    while !rest.is_empty() {
+        if items.len() == MAX_BATCH_ITEMS {
+            return Err(FrameError::TooManyItems);
+        }
+        let (len, tail) = rest
+            .split_first_chunk::<4>()
+            .ok_or(FrameError::CorruptBatch)?;
+        let len = u32::from_le_bytes(*len) as usize;
+        if tail.len() < len {
+            return Err(FrameError::CorruptBatch);
+        }
+        let (item, tail) = tail.split_at(len);
+        items.push(item);
+        rest = tail;
+    }
+    Ok(items)
+}
+
+/// Which publishes wait for the pulse peak to go out in a batch frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchPolicy {
+    pub topics: HashSet<TopicKind>,
+    /// Publishes above this priority go out at once.
+    pub max_priority: Priority,
+    /// Encoded envelopes larger than this go out at once.
+    pub max_item_bytes: usize,
+    /// A topic's batch is sent early once it would grow past this.
+    pub max_batch_bytes: usize,
+}
+
+impl Default for BatchPolicy {
+    fn default() -> Self {
+        Self {
+            // Periodic adverts and readings; nothing that steers the mesh.
+            topics: HashSet::from([TopicKind::Status, TopicKind::Sensor]),
+            max_priority: Priority::Normal,
+            max_item_bytes: 256,
+            max_batch_bytes: 4096,
+        }
+    }
+}
+
+/// Small publishes held until the next pulse peak, by topic.
+#[derive(Debug, Default)]
+pub struct Batcher {
+    pub policy: BatchPolicy,
+    pending: HashMap<TopicKind, Vec<Vec<u8>>>,
+}
+
+impl Batcher {
+    pub fn accepts(&self, kind: TopicKind, priority: Priority, len: usize) -> bool {
+        self.policy.topics.contains(&kind)
+            && priority <= self.policy.max_priority
+            && len <= self.policy.max_item_bytes
+    }
+
+    /// Hold `encoded` for `kind`. If that would overflow the topic's batch,
+    /// the held envelopes come back as a frame to send now.
+    pub fn push(&mut self, kind: TopicKind, encoded: Vec<u8>) -> Option<Vec<u8>> {
+        let pending = self.pending.entry(kind).or_default();
+        let size = |items: &[Vec<u8>]| 2 + items.iter().map(|item| item.len() + 4).sum::<usize>();
+        let full = !pending.is_empty()
+            && (pending.len() == MAX_BATCH_ITEMS
+                || size(pending) + encoded.len() + 4 > self.policy.max_batch_bytes);
+        let flushed = full.then(|| batch(&std::mem::take(pending)));
+        pending.push(encoded);
+        flushed
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.values().all(Vec::is_empty)
+    }
+
+    /// One message per topic with anything held: the envelope itself when
+    /// there is only one, otherwise a batch frame.
+    pub fn drain(&mut self) -> Vec<(TopicKind, Vec<u8>)> {
+        self.pending
+            .drain()
+            .filter_map(|(kind, mut items)| match items.len() {
+                0 => None,
+                1 => items.pop().map(|item| (kind, item)),
+                _ => Some((kind, batch(&items))),
+            })
+            .collect()
+    }
+}
+
+/// What the send policy decided for one publish.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SendDecision {
+    Send,
+    /// Held for the next pulse peak, to go out with other small publishes
+    /// on the same topic.
+    Batched,
+    Queue,
+    Drop,
+}
+
+/// Sender-side policy: in low-power modes, hold back low-priority publishes.
+///
+/// Messages below the mode's minimum priority are queued in a bounded outbox
+/// and dropped once it is full. `Priority::Critical` is always sent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendPolicy {
+    pub low_battery_min: Priority,
+    pub critical_min: Priority,
+    pub queue_capacity: usize,
+}
+
+impl Default for SendPolicy {
+    fn default() -> Self {
+        Self {
+            low_battery_min: Priority::Normal,
+            critical_min: Priority::High,
+            queue_capacity: 32,
+        }
+    }
+}
+
+impl SendPolicy {
+    /// Whether `priority` may go out immediately in `mode`.
+    pub fn allows(&self, mode: &PowerMode, priority: Priority) -> bool {
+        let min = match mode {
+            PowerMode::Normal => Priority::Low,
+            PowerMode::LowBattery => self.low_battery_min,
+            PowerMode::Critical => self.critical_min,
+        };
+        priority == Priority::Critical || priority >= min
+    }
+
+    pub fn decide(&self, mode: &PowerMode, priority: Priority, queued: usize) -> SendDecision {
+        if self.allows(mode, priority) {
+            SendDecision::Send
+        } else if queued < self.queue_capacity {
+            SendDecision::Queue
+        } else {
+            SendDecision::Drop
+        }
+    }
+}
+
+/// A publish held back by the send policy.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub topic: String,
+    pub priority: Priority,
+    pub bytes: Vec<u8>,
+}
+
+/// Bounded FIFO of held-back publishes.
+#[derive(Debug, Default)]
+pub struct Outbox {
+    entries: VecDeque<OutboxEntry>,
+}
+
+impl Outbox {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn push(&mut self, entry: OutboxEntry) {
+        self.entries.push_back(entry);
+    }
+
+    /// Remove and return every entry, oldest first.
+    pub fn take_all(&mut self) -> Vec<OutboxEntry> {
+        self.entries.drain(..).collect()
+    }
+
+    /// Remove and return the entries `policy` now allows in `mode`, oldest first.
+    pub fn drain_allowed(&mut self, policy: &SendPolicy, mode: &PowerMode) -> Vec<OutboxEntry> {
+        let (ready, held): (Vec<_>, Vec<_>) = self
+            .entries
+            .drain(..)
+            .partition(|entry| policy.allows(mode, entry.priority));
+        self.entries = held.into();
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::EnergyStatus;
+
+    #[test]
+    fn legacy_bare_payload_decodes_as_version_zero() {
+        let bytes = serde_json::to_vec(&EnergyStatus::new("n".to_string(), 0.5)).unwrap();
+
+        let envelope = decode::<EnergyStatus>(&bytes).unwrap();
+
+        assert_eq!(envelope.v, 0);
+        assert_eq!(envelope.priority, Priority::Normal);
+        assert_eq!(envelope.body.source_id, "n");
+    }
+
+    #[test]
+    fn envelope_roundtrip_keeps_priority() {
+        let bytes = Envelope::new(Priority::Low, EnergyStatus::new("n".to_string(), 0.5))
+            .encode()
+            .unwrap();
+
+        let envelope = decode::<EnergyStatus>(&bytes).unwrap();
+
+        assert_eq!(envelope.v, ENVELOPE_VERSION);
+        assert_eq!(envelope.priority, Priority::Low);
+        assert_eq!(peek_priority(&bytes), Some(Priority::Low));
+        let bare = serde_json::to_vec(&EnergyStatus::new("n".to_string(), 0.5)).unwrap();
+        assert_eq!(peek_priority(&bare), None);
+    }
+
+    #[test]
+    fn sequence_stamps_can_be_read_without_the_body() {
+        let mut envelope = Envelope::new(Priority::Normal, EnergyStatus::new("n".to_string(), 0.5));
+        assert_eq!(peek_sequence(&envelope.encode().unwrap()), None);
+        let stamp = Sequence {
+            source: "n".to_string(),
+            n: 7,
+        };
+        envelope.seq = Some(stamp.clone());
+        let bytes = envelope.encode().unwrap();
+        assert_eq!(peek_sequence(&bytes), Some(stamp.clone()));
+        assert_eq!(decode::<EnergyStatus>(&bytes).unwrap().seq, Some(stamp));
+    }
+
+    #[test]
+    fn envelopes_from_a_newer_version_keep_what_they_add() {
+        let mut future = Envelope::new(Priority::Low, EnergyStatus::new("n".to_string(), 0.5));
+        future.v = ENVELOPE_VERSION + 1;
+        future
+            .extra
+            .insert("deadline_ms".to_string(), serde_json::json!(1_700_000_000));
+        let mut json: serde_json::Value =
+            serde_json::from_slice(&future.encode().unwrap()).unwrap();
+        json["body"]["mood"] = serde_json::json!("sunny");
+        let bytes = serde_json::to_vec(&json).unwrap();
+
+        let envelope = decode::<EnergyStatus>(&bytes).unwrap();
+        assert_eq!(envelope.v, ENVELOPE_VERSION + 1);
+        assert_eq!(envelope.body.source_id, "n");
+        assert_eq!(envelope.extra, future.extra);
+
+        // Read as a relay reads it, the body's unknown fields survive too.
+        let relayed = decode::<serde_json::Value>(&bytes)
+            .unwrap()
+            .encode()
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&relayed).unwrap(),
+            json
+        );
+    }
+
+    #[test]
+    fn low_battery_queues_chatter_but_sends_spikes() {
+        let policy = SendPolicy::default();
+
+        assert_eq!(
+            policy.decide(&PowerMode::LowBattery, Priority::Low, 0),
+            SendDecision::Queue
+        );
+        assert_eq!(
+            policy.decide(&PowerMode::LowBattery, Priority::Critical, 0),
+            SendDecision::Send
+        );
+        assert_eq!(
+            policy.decide(&PowerMode::Normal, Priority::Low, 0),
+            SendDecision::Send
+        );
+    }
+
+    #[test]
+    fn full_outbox_drops() {
+        let policy = SendPolicy {
+            queue_capacity: 1,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy.decide(&PowerMode::Critical, Priority::Normal, 1),
+            SendDecision::Drop
+        );
+        assert_eq!(
+            policy.decide(&PowerMode::Critical, Priority::High, 1),
+            SendDecision::Send
+        );
+    }
+
+    #[test]
+    fn outbox_releases_entries_when_mode_improves() {
+        let policy = SendPolicy::default();
+        let mut outbox = Outbox::default();
+        for priority in [Priority::Low, Priority::Normal] {
+            outbox.push(OutboxEntry {
+                topic: "t".to_string(),
+                priority,
+                bytes: vec![],
+            });
+        }
+
+        assert!(outbox
+            .drain_allowed(&policy, &PowerMode::Critical)
+            .is_empty());
+        assert_eq!(
+            outbox.drain_allowed(&policy, &PowerMode::LowBattery).len(),
+            1
+        );
+        assert_eq!(outbox.drain_allowed(&policy, &PowerMode::Normal).len(), 1);
+        assert!(outbox.is_empty());
+    }
+
+    #[test]
+    fn large_envelopes_on_policy_topics_are_compressed() {
+        let policy = CompressionPolicy::default();
+        let body = "sensor=42;".repeat(200);
+        let encoded = Envelope::new(Priority::Normal, body.clone())
+            .encode()
+            .unwrap();
+
+        let framed = policy.apply(TopicKind::SharedState, encoded.clone());
+        assert!(framed.len() < encoded.len() / 4);
+        assert_eq!(decode::<String>(&framed).unwrap().body, body);
+
+        // Other topics and small envelopes go out untouched.
+        assert_eq!(policy.apply(TopicKind::Status, encoded.clone()), encoded);
+        let small = Envelope::new(Priority::Normal, "x").encode().unwrap();
+        assert_eq!(policy.apply(TopicKind::Task, small.clone()), small);
+    }
+
+    #[test]
+    fn oversized_or_unknown_frames_are_rejected() {
+        let mut bomb = vec![FRAME_MARKER, COMPRESSED_ENVELOPE_VERSION, Codec::Lz4 as u8];
+        bomb.extend_from_slice(&(u32::MAX).to_le_bytes());
+        assert_eq!(
+            inflate(&bomb).unwrap_err(),
+            FrameError::TooLarge(u32::MAX as usize)
+        );
+        assert_eq!(
+            inflate(&[FRAME_MARKER, COMPRESSED_ENVELOPE_VERSION, 9]).unwrap_err(),
+            FrameError::Codec(9)
+        );
+        assert!(decode::<String>(&bomb).is_err());
+    }
+
+    #[test]
+    fn small_chatter_is_batched_per_topic() {
+        let mut batcher = Batcher::default();
+        let status = |n: usize| {
+            Envelope::new(Priority::Low, EnergyStatus::new(format!("n{n}"), 0.5))
+                .encode()
+                .unwrap()
+        };
+        assert!(batcher.accepts(TopicKind::Status, Priority::Low, 100));
+        assert!(!batcher.accepts(TopicKind::Status, Priority::High, 100));
+        assert!(!batcher.accepts(TopicKind::Control, Priority::Low, 100));
+        assert!(!batcher.accepts(TopicKind::Status, Priority::Low, 1000));
+
+        for n in 0..3 {
+            assert_eq!(batcher.push(TopicKind::Status, status(n)), None);
+        }
+        assert_eq!(batcher.push(TopicKind::Sensor, status(9)), None);
+        assert_eq!(batcher.len(), 4);
+
+        let mut sent = batcher.drain();
+        sent.sort_by_key(|(kind, _)| *kind as u8);
+        assert_eq!(sent.len(), 2);
+        assert!(batcher.is_empty());
+
+        let items = unbatch(&sent[0].1).unwrap();
+        let sources: Vec<String> = items
+            .iter()
+            .map(|item| decode::<EnergyStatus>(item).unwrap().body.source_id)
+            .collect();
+        assert_eq!(sources, ["n0", "n1", "n2"]);
+        // A lone envelope goes out as it is.
+        assert_eq!(sent[1].1, status(9));
+        assert_eq!(unbatch(&sent[1].1).unwrap(), [sent[1].1.as_slice()]);
+    }
+
+    #[test]
+    fn batches_are_bounded_and_checked() {
+        let mut batcher = Batcher::default();
+        batcher.policy.max_batch_bytes = 40;
+        assert_eq!(batcher.push(TopicKind::Status, vec![b'a'; 16]), None);
+        let early = batcher.push(TopicKind::Status, vec![b'b'; 16]).unwrap();
+        assert_eq!(unbatch(&early).unwrap(), [&[b'a'; 16][..]]);
+        assert_eq!(batcher.len(), 1);
+
+        let mut truncated = batch(&[vec![b'x'; 8]]);
+        truncated.pop();
+        assert_eq!(unbatch(&truncated), Err(FrameError::CorruptBatch));
+        let crowded = batch(&vec![vec![b'x'; 1]; MAX_BATCH_ITEMS + 1]);
+        assert_eq!(unbatch(&crowded), Err(FrameError::TooManyItems));
+    }
+}
@@ -0,0 +1,293 @@
+//! Bridging gossip between zones.
+//!
+//! In a zone-aware mesh each node advertises its zone in its status, and
+//! cross-zone traffic should funnel through one elected bridge per zone
+//! rather than every node relaying whatever reaches it. Every node of a zone
+//! runs the same [`ZoneRouter`] election over the adverts it hears: the
+//! highest-energy peer of the zone (ties going to the lowest peer id) holds
+//! the bridge for a `lease`, and is only replaced once the lease runs out or
+//! it falls silent, so fluctuating scores do not make the bridge flap.
+//!
+//! A message is cross-zone when its author advertised another zone, or when
+//! it already carries a [`BridgeTag`]. Only the bridge relays those, stamping
+//! the tag with its zone. The tag names the original message and every zone
+//! it was bridged into, so a bridge never carries a message back into a zone
+//! it already reached, nor bridges the same message twice. A node without a
+//! zone relays as before.
+
+use crate::wire::{self, Envelope};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+
+/// How many bridged message ids a router remembers.
+const BRIDGED_IDS: usize = 1024;
+
+/// Duplicate-suppression tag carried in the envelope of bridged messages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BridgeTag {
+    /// Gossip id of the message as first published.
+    pub origin_id: String,
+    /// The origin's zone first, then each zone it was bridged into.
+    pub zones: Vec<String>,
+}
+
+/// What the relay step should do with a received message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Forward {
+    /// Within the zone: the usual relay policy applies.
+    Relay,
+    /// Republish with this tag; the local node is the zone's bridge.
+    Bridge(BridgeTag),
+    /// Cross-zone and not ours to carry.
+    Hold,
+}
+
+#[derive(Debug, Clone)]
+struct ZonePeer {
+    zone: String,
+    energy: f32,
+    heard: Instant,
+}
+
+#[derive(Debug, Clone)]
+struct BridgeLease {
+    peer: String,
+    until: Instant,
+}
+
+#[derive(Debug)]
+pub struct ZoneRouter {
+    /// This node's zone. `None` leaves relaying zone-unaware.
+    pub zone: Option<String>,
+    /// How long an elected bridge holds the role.
+    pub lease: Duration,
+    peers: HashMap<String, ZonePeer>,
+    bridge: Option<BridgeLease>,
+    bridged_ids: HashSet<String>,
+    bridged_order: VecDeque<String>,
+}
+
+impl Default for ZoneRouter {
+    fn default() -> Self {
+        Self {
+            zone: None,
+            lease: Duration::from_secs(60),
+            peers: HashMap::new(),
+            bridge: None,
+            bridged_ids: HashSet::new(),
+            bridged_order: VecDeque::new(),
+        }
+    }
+}
+
+impl ZoneRouter {
+    pub fn new(zone: String) -> Self {
+        Self {
+            zone: Some(zone),
+            ..Self::default()
+        }
+    }
+
+    /// Note `peer`'s advertised zone and energy. The local node observes
+    /// itself too, so that it stands in its own zone's election.
+    pub fn observe(&mut self, peer: &str, zone: Option<&str>, energy: f32, now: Instant) {
+        match zone {
+            Some(zone) => {
+                self.peers.insert(
+                    peer.to_string(),
+                    ZonePeer {
+                        zone: zone.to_string(),
+                        energy,
+                        heard: now,
+                    },
+                );
+            }
+            None => {
+                self.peers.remove(peer);
+            }
+        }
+    }
+
+    pub fn zone_of(&self, peer: &str) -> Option<&str> {
+        self.peers.get(peer).map(|p| p.zone.as_str())
+    }
+
+    /// The local zone's bridge at `now`, electing a new one if the lease ran
+    /// out or the holder fell silent or left the zone.
+    pub fn bridge(&mut self, now: Instant) -> Option<&str> {
+        let zone = self.zone.as_deref()?;
+        let live =
+            |peer: &ZonePeer| peer.zone == zone && now.duration_since(peer.heard) < self.lease;
+        let holds = self.bridge.as_ref().is_some_and(|lease| {
+            now < lease.until && self.peers.get(&lease.peer).is_some_and(live)
+        });
+        if !holds {
+            self.bridge = self
+                .peers
+                .iter()
+                .filter(|(_, peer)| live(peer))
+                .max_by(|(a_id, a), (b_id, b)| {
+                    a.energy.total_cmp(&b.energy).then_with(|| b_id.cmp(a_id))
+                })
+                .map(|(peer, _)| BridgeLease {
+                    peer: peer.clone(),
+                    until: now + self.lease,
+                });
+        }
+        self.bridge.as_ref().map(|lease| lease.peer.as_str())
+    }
+
+    pub fn is_bridge(&mut self, local: &str, now: Instant) -> bool {
+        self.bridge(now) == Some(local)
+    }
+
+    /// Decide how the local node treats message `id` from `author`, carrying
+    /// `tag` if it was bridged before.
+    pub fn route(
+        &mut self,
+        local: &str,
+        id: &str,
+        author: &str,
+        tag: Option<&BridgeTag>,
+        now: Instant,
+    ) -> Forward {
+        let Some(zone) = self.zone.clone() else {
+            return Forward::Relay;
+        };
+        let origin_zone = match tag {
+            Some(tag) => tag.zones.first().cloned(),
+            None => self.zone_of(author).map(str::to_string),
+        };
+        let Some(origin_zone) = origin_zone else {
+            // An author that never advertised a zone counts as local.
+            return Forward::Relay;
+        };
+        if tag.is_none() && origin_zone == zone {
+            return Forward::Relay;
+        }
+        let mut tag = tag.cloned().unwrap_or_else(|| BridgeTag {
+            origin_id: id.to_string(),
+            zones: vec![origin_zone],
+        });
+        if tag.zones.contains(&zone)
+            || self.bridged_ids.contains(&tag.origin_id)
+            || !self.is_bridge(local, now)
+        {
+            return Forward::Hold;
+        }
+        self.remember(&tag.origin_id);
+        tag.zones.push(zone);
+        Forward::Bridge(tag)
+    }
+
+    pub fn forget(&mut self, peer: &str) {
+        self.peers.remove(peer);
+        if self.bridge.as_ref().is_some_and(|lease| lease.peer == peer) {
+            self.bridge = None;
+        }
+    }
+
+    fn remember(&mut self, origin_id: &str) {
+        if self.bridged_ids.insert(origin_id.to_string()) {
+            self.bridged_order.push_back(origin_id.to_string());
+        }
+        if self.bridged_order.len() > BRIDGED_IDS {
+            if let Some(old) = self.bridged_order.pop_front() {
+                self.bridged_ids.remove(&old);
+            }
+        }
+    }
+}
+
+/// The bridge tag carried by an enveloped payload, if any.
+pub fn peek(bytes: &[u8]) -> Option<BridgeTag> {
+    #[derive(Deserialize)]
+    struct Probe {
+        #[serde(default)]
+        bridge: Option<BridgeTag>,
+    }
+    let bytes = wire::inflate(bytes).ok()?;
+    serde_json::from_slice::<Probe>(&bytes).ok()?.bridge
+}
+
+/// Re-encode an enveloped payload carrying `tag`, leaving the rest of it
+/// untouched. `None` if `bytes` is not an envelope. The result is
+/// uncompressed.
+pub fn restamp(bytes: &[u8], tag: BridgeTag) -> Option<Vec<u8>> {
+    let bytes = wire::inflate(bytes).ok()?;
+    let mut envelope: Envelope<serde_json::Value> = serde_json::from_slice(&bytes).ok()?;
+    envelope.bridge = Some(tag);
+    envelope.encode().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wire::Priority;
+
+    #[test]
+    fn bridge_is_leased_to_the_strongest_zone_peer() {
+        let t0 = Instant::now();
+        let mut router = ZoneRouter::new("north".to_string());
+        router.observe("a", Some("north"), 0.6, t0);
+        router.observe("b", Some("north"), 0.9, t0);
+        router.observe("c", Some("south"), 1.0, t0);
+        assert_eq!(router.bridge(t0), Some("b"));
+
+        // A stronger peer waits for the lease to run out.
+        let later = t0 + Duration::from_secs(30);
+        router.observe("a", Some("north"), 1.0, later);
+        router.observe("b", Some("north"), 0.5, later);
+        assert_eq!(router.bridge(later), Some("b"));
+        assert_eq!(router.bridge(t0 + router.lease), Some("a"));
+
+        // A departed bridge is replaced at once.
+        router.forget("a");
+        assert_eq!(router.bridge(t0 + router.lease), Some("b"));
+    }
+
+    #[test]
+    fn only_the_bridge_forwards_across_zones_and_never_back() {
+        let now = Instant::now();
+        let mut router = ZoneRouter::new("north".to_string());
+        router.observe("me", Some("north"), 0.9, now);
+        router.observe("peer", Some("north"), 0.4, now);
+        router.observe("far", Some("south"), 0.8, now);
+
+        assert_eq!(router.route("me", "m1", "peer", None, now), Forward::Relay);
+        let bridged = BridgeTag {
+            origin_id: "m2".to_string(),
+            zones: vec!["south".to_string(), "north".to_string()],
+        };
+        assert_eq!(
+            router.route("me", "m2", "far", None, now),
+            Forward::Bridge(bridged.clone())
+        );
+        // The same message again, or tagged as already in this zone.
+        assert_eq!(router.route("me", "m2", "far", None, now), Forward::Hold);
+        assert_eq!(
+            router.route("me", "m3", "far", Some(&bridged), now),
+            Forward::Hold
+        );
+        assert_eq!(router.route("peer", "m4", "far", None, now), Forward::Hold);
+
+        let mut unzoned = ZoneRouter::default();
+        assert_eq!(unzoned.route("me", "m5", "far", None, now), Forward::Relay);
+    }
+
+    #[test]
+    fn tag_survives_the_envelope_and_restamp() {
+        let bytes = Envelope::new(Priority::Normal, "payload".to_string())
+            .encode()
+            .unwrap();
+        assert!(peek(&bytes).is_none());
+        let tag = BridgeTag {
+            origin_id: "m1".to_string(),
+            zones: vec!["south".to_string(), "north".to_string()],
+        };
+        let bridged = restamp(&bytes, tag.clone()).unwrap();
+        assert_eq!(peek(&bridged), Some(tag));
+        assert_eq!(wire::decode::<String>(&bridged).unwrap().body, "payload");
+    }
+}
@@ -1,6 +1,12 @@
+use hypha::admission::{Admission, AdmissionConfig, AdmissionPolicy};
+use hypha::events::NodeEvent;
+use hypha::mailbox::MailboxAck;
+use hypha::mycelium::MyceliumEvent;
 use hypha::{Capability, SporeNode, Task};
 use libp2p::futures::StreamExt;
-use libp2p::{gossipsub, swarm::dial_opts::DialOpts, swarm::SwarmEvent, Multiaddr, PeerId};
+use libp2p::{
+    gossipsub, request_response, swarm::dial_opts::DialOpts, swarm::SwarmEvent, Multiaddr, PeerId,
+};
 use rand::{rngs::StdRng, RngCore, SeedableRng};
 use tempfile::tempdir;
 
@@ -24,12 +30,14 @@ fn test_process_task_bundle_does_not_panic_on_nan_bids() {
             bidder_id: "a".to_string(),
             energy_score: f32::NAN,
             cost_mah: 1.0,
+            price: None,
         },
         hypha::Bid {
             task_id: "t".to_string(),
             bidder_id: "b".to_string(),
             energy_score: 0.5,
             cost_mah: 1.0,
+            price: None,
         },
     ];
 
@@ -132,6 +140,14 @@ async fn test_malformed_status_json_does_not_crash_run_for(
         source_id: "pub".to_string(),
         energy_score: 0.9,
         facts: None,
+        topics: Vec::new(),
+        grants: Vec::new(),
+        role: None,
+        attestation: None,
+        zone: None,
+        state: None,
+        catalog_version: None,
+        health: None,
     })?;
     let pub_res = pub_my
         .swarm
@@ -355,6 +371,14 @@ fn test_replay_attack_duplicate_detection() {
             source_id: "pub_replay".to_string(),
             energy_score: 0.99,
             facts: None,
+            topics: Vec::new(),
+            grants: Vec::new(),
+            role: None,
+            attestation: None,
+            zone: None,
+            state: None,
+            catalog_version: None,
+            health: None,
         };
         let bytes = serde_json::to_vec(&status).unwrap();
 
@@ -434,6 +458,103 @@ fn test_replay_attack_duplicate_detection() {
     });
 }
 
+/// Hand `victim` one held task from a fresh peer over the mailbox protocol.
+/// Returns the peer's ack, if the victim sent one, and whether the task came
+/// out of the victim as a `NodeEvent::Task`.
+async fn deliver_held_task(
+    mut victim: SporeNode,
+    sender: SporeNode,
+) -> Result<(Option<MailboxAck>, bool), Box<dyn std::error::Error>> {
+    let victim_peer = victim.peer_id;
+    let mut events = victim.link().subscribe();
+    let mut victim_my = victim.build_mycelium_with_profile(hypha::mycelium::NetProfile::Tcp)?;
+    victim_my.listen_on("/ip4/127.0.0.1/tcp/0".parse::<Multiaddr>()?)?;
+    let (listen_tx, listen_rx) = tokio::sync::oneshot::channel();
+    let running = victim.run_for(
+        victim_my,
+        std::time::Duration::from_secs(4),
+        std::time::Duration::from_millis(100),
+        0.1,
+        false,
+        Some(listen_tx),
+    );
+
+    let mut sender_my = sender.build_mycelium_with_profile(hypha::mycelium::NetProfile::Tcp)?;
+    let task = Task::new(
+        "held".to_string(),
+        Capability::Compute(1),
+        1,
+        sender.peer_id.to_string(),
+    );
+    let deliver = async {
+        let victim_addr = listen_rx.await?;
+        sender_my.swarm.dial(
+            DialOpts::peer_id(victim_peer)
+                .addresses(vec![victim_addr])
+                .build(),
+        )?;
+        let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(3);
+        while tokio::time::Instant::now() < deadline {
+            tokio::select! {
+                ev = sender_my.swarm.select_next_some() => match ev {
+                    SwarmEvent::ConnectionEstablished { peer_id, .. } if peer_id == victim_peer => {
+                        sender_my.deliver_mail(&victim_peer, vec![task.clone()]);
+                    }
+                    SwarmEvent::Behaviour(MyceliumEvent::Mailbox(request_response::Event::Message {
+                        message: request_response::Message::Response { response, .. },
+                        ..
+                    })) => return Ok(Some(response)),
+                    SwarmEvent::Behaviour(MyceliumEvent::Mailbox(
+                        request_response::Event::OutboundFailure { .. },
+                    )) => return Ok(None),
+                    _ => {}
+                },
+                _ = tokio::time::sleep(std::time::Duration::from_millis(10)) => {}
+            }
+        }
+        Ok::<_, Box<dyn std::error::Error>>(None)
+    };
+    let (ran, ack) = tokio::join!(running, deliver);
+    ran?;
+    let ack = ack?;
+
+    let mut surfaced = false;
+    while let Ok(event) = events.try_recv() {
+        surfaced |= matches!(event, NodeEvent::Task(t) if t.id == task.id);
+    }
+    Ok((ack, surfaced))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_held_tasks_from_unadmitted_peer_are_dropped() -> Result<(), Box<dyn std::error::Error>>
+{
+    let tmp = tempdir()?;
+    let node = |name: &str| -> Result<SporeNode, Box<dyn std::error::Error>> {
+        let path = tmp.path().join(name);
+        std::fs::create_dir_all(&path)?;
+        SporeNode::new(&path)
+    };
+
+    // An open node takes the held task, so the delivery itself works.
+    let (ack, surfaced) = deliver_held_task(node("open")?, node("sender")?).await?;
+    assert_eq!(ack.map(|ack| ack.accepted), Some(1));
+    assert!(surfaced, "open node did not surface the held task");
+
+    // An invitation-only node does not take it from a peer it never
+    // admitted; the sender is left holding it.
+    let mut closed = node("closed")?;
+    closed.admission = Admission::new(AdmissionConfig {
+        policy: AdmissionPolicy::Invitation {
+            issuers: Vec::new(),
+        },
+        ..AdmissionConfig::default()
+    });
+    let (ack, surfaced) = deliver_held_task(closed, node("intruder")?).await?;
+    assert!(ack.is_none(), "unadmitted peer got an ack: {ack:?}");
+    assert!(!surfaced, "held task from an unadmitted peer surfaced");
+    Ok(())
+}
+
 #[test]
 fn test_random_task_json_deserialize_never_panics() {
     // This is a cheap, deterministic "adversarial input" test. It doesn't prove
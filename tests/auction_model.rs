@@ -0,0 +1,74 @@
+use hypha::auction::{permits, AuctionEvent, AuctionStateMachine, Effect, Phase};
+use proptest::prelude::*;
+
+fn worker() -> impl Strategy<Value = String> {
+    "[a-d]".prop_map(String::from)
+}
+
+fn event() -> impl Strategy<Value = AuctionEvent> {
+    prop_oneof![
+        4 => (worker(), prop_oneof![9 => 0.0f32..1.0, 1 => Just(f32::NAN)])
+            .prop_map(|(bidder, score)| AuctionEvent::Bid { bidder, score }),
+        2 => Just(AuctionEvent::Close),
+        3 => worker().prop_map(|worker| AuctionEvent::Accept { worker }),
+        1 => worker().prop_map(|worker| AuctionEvent::Decline { worker }),
+        1 => Just(AuctionEvent::Lapse),
+        2 => worker().prop_map(|worker| AuctionEvent::Result { worker }),
+        1 => Just(AuctionEvent::Cancel),
+        1 => Just(AuctionEvent::Deadline),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn auctions_never_have_two_winners_or_run_after_cancel(
+        max_reopens in 0u32..3,
+        events in prop::collection::vec(event(), 0..60),
+    ) {
+        let mut machine = AuctionStateMachine::new(max_reopens);
+        // Award holder as the auctioneer's announcements tell it.
+        let mut holder: Option<String> = None;
+        let mut settled = 0;
+        let mut cancelled = false;
+
+        for event in &events {
+            let Ok((next, effect)) = machine.step(event) else {
+                continue;
+            };
+            prop_assert!(!cancelled, "{event:?} accepted after a cancel");
+            prop_assert!(permits(machine.phase, event.kind(), next.phase));
+            match &effect {
+                Some(Effect::Award { winner }) => {
+                    prop_assert!(holder.is_none(), "awarded {winner} while {holder:?} holds it");
+                    prop_assert_eq!(machine.leader().map(|(best, _)| best), Some(winner.as_str()));
+                    holder = Some(winner.clone());
+                }
+                Some(Effect::Settle { worker }) => {
+                    prop_assert!(next.awarded.contains(worker));
+                    settled += 1;
+                    holder = None;
+                }
+                Some(Effect::Reopen) | Some(Effect::Abandon) => holder = None,
+                None => {}
+            }
+            if matches!(event, AuctionEvent::Cancel) {
+                cancelled = true;
+            }
+            prop_assert_eq!(&next.winner, &holder);
+            prop_assert_eq!(
+                next.winner.is_some(),
+                matches!(next.phase, Phase::Awarded | Phase::Executing)
+            );
+            prop_assert!(next.reopens <= max_reopens);
+            machine = next;
+        }
+
+        prop_assert!(settled <= 1);
+        prop_assert_eq!(settled == 1, machine.phase == Phase::Completed);
+        if machine.phase.is_terminal() {
+            for event in &events {
+                prop_assert!(machine.step(event).is_err());
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use hypha::{Bid, Capability, MockMetabolism, SporeNode, Task};
 use proptest::prelude::*;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tempfile::{tempdir, TempDir};
 
 fn compute_node(available: u32, energy: f32) -> (TempDir, SporeNode) {
@@ -19,6 +20,12 @@ fn compute_task(required: u32) -> Task {
         reach_intensity: 1.0,
         source_id: "test-source".to_string(),
         auth_token: None,
+        public_result: false,
+        tenant: None,
+        memo_key: None,
+        geohash: None,
+        target: None,
+        expected_output_bytes: None,
     }
 }
 
@@ -82,6 +89,7 @@ fn process_task_bundle_still_respects_better_known_bid() {
         bidder_id: "peer-a".to_string(),
         energy_score: 0.9,
         cost_mah: 1.0,
+        price: None,
     }];
 
     assert!(node
@@ -99,6 +107,7 @@ fn process_task_bundle_compares_the_reach_adjusted_bid_score() {
         bidder_id: "peer-a".to_string(),
         energy_score: 0.9,
         cost_mah: 1.0,
+        price: None,
     }];
 
     assert!(node
@@ -116,6 +125,7 @@ fn process_task_bundle_ignores_non_finite_known_bid_scores() {
         bidder_id: "peer-a".to_string(),
         energy_score: f32::NAN,
         cost_mah: 1.0,
+        price: None,
     }];
 
     let bid = node.process_task_bundle_best_bid(&task, &mut bids).unwrap();
@@ -187,6 +197,32 @@ fn compatibility_bidding_names_delegate_to_named_heuristics() {
     assert_eq!(old_bids.len(), named_bids.len());
 }
 
+#[test]
+fn process_task_bundle_requires_a_token_when_told_to() {
+    let (_tmp, mut node) = compute_node(100, 1.0);
+    let token = node
+        .delegate(
+            &node.peer_id,
+            Capability::Compute(100),
+            Duration::from_secs(60),
+            None,
+        )
+        .unwrap()
+        .encode();
+    node.require_token = true;
+
+    assert!(node
+        .process_task_bundle_best_bid(&compute_task(50), &mut Vec::new())
+        .is_none());
+    let authorized = Task {
+        auth_token: Some(token),
+        ..compute_task(50)
+    };
+    assert!(node
+        .process_task_bundle_best_bid(&authorized, &mut Vec::new())
+        .is_some());
+}
+
 proptest! {
     #[test]
     fn compute_satisfaction_matches_capacity_order(available in any::<u32>(), required in any::<u32>()) {
@@ -0,0 +1,65 @@
+use hypha::mycelium::{MyceliumEvent, TopicKind};
+use hypha::testing::{TestNet, Topology};
+use libp2p::gossipsub;
+use libp2p::swarm::SwarmEvent;
+use std::time::Duration;
+
+fn status_from(net: &TestNet, i: usize, event: &SwarmEvent<MyceliumEvent>) -> Option<Vec<u8>> {
+    match event {
+        SwarmEvent::Behaviour(MyceliumEvent::Gossipsub(gossipsub::Event::Message {
+            message,
+            ..
+        })) if message.topic == net.node(i).mycelium.topic(TopicKind::Status).hash() => {
+            Some(message.data.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Kill one node and isolate another: the rest keep gossiping, the isolated
+/// node hears nothing until it heals.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn gossip_survives_kill_and_isolation() -> Result<(), Box<dyn std::error::Error>> {
+    let mut net = TestNet::spawn(4, Topology::Full).await?;
+    assert!(
+        net.wait_for_mesh(Duration::from_secs(5)).await,
+        "mesh did not form"
+    );
+
+    net.kill(0)?;
+    net.isolate(1);
+    assert!(!net.node(0).is_alive());
+    let cut = net
+        .drive_until(Duration::from_secs(3), |net, _, _| {
+            !net.connected(2, 0) && !net.connected(2, 1) && !net.connected(1, 3)
+        })
+        .await;
+    assert!(cut, "killed or isolated nodes are still connected");
+    assert!(net.wait_for_mesh(Duration::from_secs(5)).await);
+
+    net.publish(2, TopicKind::Status, b"after the storm".to_vec())?;
+    let mut heard_by_isolated = false;
+    let delivered = net
+        .drive_until(Duration::from_secs(3), |net, i, event| {
+            let data = status_from(net, i, event);
+            heard_by_isolated |= i == 1 && data.is_some();
+            i == 3 && data.is_some_and(|d| d == b"after the storm")
+        })
+        .await;
+    assert!(delivered, "surviving node did not receive status");
+    assert!(!heard_by_isolated, "isolated node received gossip");
+
+    net.heal(1)?;
+    assert!(
+        net.wait_for_mesh(Duration::from_secs(5)).await,
+        "healed node did not rejoin"
+    );
+    net.publish(3, TopicKind::Status, b"welcome back".to_vec())?;
+    let rejoined = net
+        .drive_until(Duration::from_secs(3), |net, i, event| {
+            i == 1 && status_from(net, i, event).is_some_and(|d| d == b"welcome back")
+        })
+        .await;
+    assert!(rejoined, "healed node did not receive status");
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use hypha::config::{ConfigSection, HyphaConfig};
+use hypha::events::NodeEvent;
+use hypha::mycelium::TopicKind;
+use hypha::SporeNode;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn apply_config_changes_only_what_differs() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp = tempdir()?;
+    let mut node = SporeNode::new(tmp.path())?;
+    let mut mycelium = node.build_mycelium()?;
+    mycelium.subscribe_all()?;
+    let mut events = node.subscribe_events();
+
+    let unchanged = node.config(&mycelium);
+    assert!(node
+        .apply_config(&mut mycelium, unchanged.clone())?
+        .is_empty());
+
+    let mut new: HyphaConfig = unchanged;
+    new.namespace = Some("lab".to_string());
+    new.degradation.mesh.d = 8;
+    new.trusted_issuers.push(node.did());
+    let changed = node.apply_config(&mut mycelium, new.clone())?;
+    assert_eq!(
+        changed,
+        vec![
+            ConfigSection::Mesh,
+            ConfigSection::Namespace,
+            ConfigSection::TrustedIssuers
+        ]
+    );
+
+    // The namespace change moved every subscription to the new names.
+    let names = mycelium.subscribed_topic_names();
+    assert!(!names.is_empty());
+    assert!(
+        names.iter().all(|name| name.starts_with("lab/")),
+        "{names:?}"
+    );
+    assert!(mycelium.subscribed.contains(&TopicKind::Spike));
+    assert_eq!(node.degradation.mesh.d, 8);
+    assert_eq!(node.config(&mycelium), new);
+
+    match events.try_recv()? {
+        NodeEvent::ConfigApplied { changed: reported } => assert_eq!(reported, changed),
+        other => panic!("unexpected event {other:?}"),
+    }
+    Ok(())
+}
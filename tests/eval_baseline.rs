@@ -0,0 +1,84 @@
+//! Golden-baseline regression gate for the evaluation simulator.
+//!
+//! Every scenario of `EvalScenario::regression_suite` is re-run under each
+//! seed and compared with the summary committed in
+//! `tests/golden/eval_baseline.json`; a drop in delivery rate or a rise in
+//! p99 latency beyond `Tolerance::default()` fails the test. After an
+//! intended change, regenerate the file with
+//! `HYPHA_BLESS=1 cargo test --test eval_baseline`.
+
+use hypha::eval::{load_scenarios, simulate, Baseline, EvalScenario, EvalSummary, Tolerance};
+use std::path::Path;
+
+const SEEDS: [u64; 3] = [1, 2, 3];
+
+fn baseline_path() -> &'static Path {
+    Path::new(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/golden/eval_baseline.json"
+    ))
+}
+
+fn rerun(scenario: &EvalScenario, seed: u64) -> EvalSummary {
+    EvalSummary::from_runs(&[simulate(scenario, seed)]).expect("one run")
+}
+
+#[test]
+fn eval_metrics_do_not_regress() {
+    let suite = EvalScenario::regression_suite();
+    if std::env::var_os("HYPHA_BLESS").is_some() {
+        let mut baseline = Baseline::default();
+        for scenario in &suite {
+            for seed in SEEDS {
+                baseline.record(seed, rerun(scenario, seed));
+            }
+        }
+        baseline.save(baseline_path()).expect("write baseline");
+        return;
+    }
+
+    let baseline = Baseline::load(baseline_path()).expect("read baseline");
+    let tolerance = Tolerance::default();
+    let mut failures = Vec::new();
+    for scenario in &suite {
+        for seed in SEEDS {
+            let Some(expected) = baseline.get(&scenario.name, seed) else {
+                failures.push(format!("{} (seed {seed}): no baseline", scenario.name));
+                continue;
+            };
+            failures.extend(
+                tolerance
+                    .regressions(expected, &rerun(scenario, seed))
+                    .iter()
+                    .map(|regression| format!("{regression} (seed {seed})")),
+            );
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "evaluation regressed against {}:\n{}",
+        baseline_path().display(),
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn example_scenario_files_run() {
+    let dir = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/examples/scenarios"));
+    let scenarios = load_scenarios(dir).expect("example scenarios parse");
+    let names: Vec<&str> = scenarios.iter().map(|s| s.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "flaky_lora_field",
+            "orchard_grid",
+            "secure_tasks",
+            "split_campus"
+        ]
+    );
+    for scenario in &scenarios {
+        let run = simulate(scenario, 1);
+        assert_eq!(run.fault_events.len(), scenario.fault_schedule.len());
+        assert!(run.delivery.messages_delivered > 0, "{}", scenario.name);
+    }
+}
@@ -73,6 +73,14 @@ async fn test_gossip_storm_resilience() -> Result<(), Box<dyn std::error::Error>
             source_id: "attacker".to_string(),
             energy_score: 0.1,
             facts: None,
+            topics: Vec::new(),
+            grants: Vec::new(),
+            role: None,
+            attestation: None,
+            zone: None,
+            state: None,
+            catalog_version: None,
+            health: None,
         })
         .unwrap();
 
@@ -106,6 +114,14 @@ async fn test_gossip_storm_resilience() -> Result<(), Box<dyn std::error::Error>
             source_id: "observer".to_string(),
             energy_score: 0.9,
             facts: None,
+            topics: Vec::new(),
+            grants: Vec::new(),
+            role: None,
+            attestation: None,
+            zone: None,
+            state: None,
+            catalog_version: None,
+            health: None,
         })
         .unwrap();
 
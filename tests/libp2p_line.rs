@@ -122,6 +122,14 @@ async fn run_line(
         source_id: "n0".to_string(),
         energy_score: 0.9,
         facts: None,
+        topics: Vec::new(),
+        grants: Vec::new(),
+        role: None,
+        attestation: None,
+        zone: None,
+        state: None,
+        catalog_version: None,
+        health: None,
     };
     let bytes = serde_json::to_vec(&status)?;
     let pub_res = m0
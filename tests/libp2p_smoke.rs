@@ -1,4 +1,5 @@
-use hypha::{EnergyStatus, SporeNode};
+use hypha::wire::Priority;
+use hypha::{topic, EnergyStatus, PowerMode, SensorReading, SporeNode};
 use libp2p::futures::StreamExt;
 use libp2p::{
     gossipsub, multiaddr::Protocol, noise, relay, swarm::dial_opts::DialOpts, swarm::SwarmEvent,
@@ -155,6 +156,14 @@ async fn test_status_gossip_adds_peer() -> Result<(), Box<dyn std::error::Error>
         source_id: "node0".to_string(),
         energy_score: 0.9,
         facts: None,
+        topics: Vec::new(),
+        grants: Vec::new(),
+        role: None,
+        attestation: None,
+        zone: None,
+        state: None,
+        catalog_version: None,
+        health: None,
     };
     let bytes = serde_json::to_vec(&status)?;
     let pub_res = m0
@@ -284,6 +293,14 @@ async fn test_status_gossip_over_quic() -> Result<(), Box<dyn std::error::Error>
         source_id: "node0".to_string(),
         energy_score: 0.9,
         facts: None,
+        topics: Vec::new(),
+        grants: Vec::new(),
+        role: None,
+        attestation: None,
+        zone: None,
+        state: None,
+        catalog_version: None,
+        health: None,
     };
     let bytes = serde_json::to_vec(&status)?;
     let pub_res = m0
@@ -318,6 +335,77 @@ async fn test_status_gossip_over_quic() -> Result<(), Box<dyn std::error::Error>
     Ok(())
 }
 
+/// Typed topics: publish a `SensorReading` and read it back decoded, without
+/// touching topic hashes or serde on either side.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_typed_topic_round_trip() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp = tempdir()?;
+    let p0 = tmp.path().join("n0");
+    let p1 = tmp.path().join("n1");
+    std::fs::create_dir_all(&p0)?;
+    std::fs::create_dir_all(&p1)?;
+
+    let n0 = SporeNode::new(&p0)?;
+    let n1 = SporeNode::new(&p1)?;
+    let peer0 = n0.peer_id;
+    let peer1 = n1.peer_id;
+
+    let mut m0 = n0.build_mycelium()?;
+    let mut m1 = n1.build_mycelium()?;
+    m0.subscribe(topic::SENSOR)?;
+    let readings = m1.subscribe(topic::SENSOR)?;
+
+    m0.listen_on("/ip4/127.0.0.1/tcp/0".parse::<Multiaddr>()?)?;
+    m1.listen_on("/ip4/127.0.0.1/tcp/0".parse::<Multiaddr>()?)?;
+
+    let mut a1: Option<Multiaddr> = None;
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_millis(500);
+    while a1.is_none() && tokio::time::Instant::now() < deadline {
+        tokio::select! {
+            ev = m1.swarm.select_next_some() => {
+                if let SwarmEvent::NewListenAddr { address, .. } = ev {
+                    a1.get_or_insert(address);
+                }
+            }
+            ev = m0.swarm.select_next_some() => { let _ = ev; }
+            _ = tokio::time::sleep(std::time::Duration::from_millis(10)) => {}
+        }
+    }
+    let a1 = a1.ok_or("node1 did not obtain listen addr")?;
+    m0.swarm
+        .dial(DialOpts::peer_id(peer1).addresses(vec![a1]).build())?;
+    m0.swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer1);
+    m1.swarm.behaviour_mut().gossipsub.add_explicit_peer(&peer0);
+
+    // Publish a fresh reading every 100ms until node1 has learned node0's
+    // subscription and one arrives; each carries a new value, so none is
+    // dropped as a duplicate of an earlier publish.
+    let mut incoming = m1.messages(&readings);
+    let mut tick = tokio::time::interval(std::time::Duration::from_millis(100));
+    let deadline = tokio::time::sleep(std::time::Duration::from_secs(5));
+    tokio::pin!(deadline);
+    let mut sent = 0.0;
+    let received = loop {
+        tokio::select! {
+            Some(message) = incoming.next() => break Some(message),
+            ev = m0.swarm.select_next_some() => { let _ = ev; }
+            _ = tick.tick() => {
+                sent += 1.0;
+                let reading = SensorReading::new("node0".into(), "temp".into(), sent);
+                let _ = m0.publish(topic::SENSOR, Priority::Normal, &reading, &PowerMode::Normal);
+            }
+            _ = &mut deadline => break None,
+        }
+    };
+
+    let received = received.ok_or("node1 did not receive a typed reading")?;
+    assert_eq!(received.propagation_source, peer0);
+    assert_eq!(received.envelope.body.source_id, "node0");
+    assert_eq!(received.envelope.body.sensor, "temp");
+    assert!(received.envelope.body.value >= 1.0);
+    Ok(())
+}
+
 /// Coverage test: exercise circuit relay v2 reservation using Hypha's `Mycelium` wiring.
 ///
 /// This does NOT simulate NAT, but it does verify that:
@@ -521,3 +609,66 @@ async fn test_relay_reservation_minimal_tcp() -> Result<(), Box<dyn std::error::
     );
     Ok(())
 }
+
+/// Bootstrap entries may name hosts instead of IPs. `/dns4/localhost` must be
+/// resolved by the Mycelium transport stack, and connected entries are skipped
+/// on re-dial.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_dns_bootstrap_redial() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp = tempdir()?;
+    let p0 = tmp.path().join("n0");
+    let p1 = tmp.path().join("n1");
+    std::fs::create_dir_all(&p0)?;
+    std::fs::create_dir_all(&p1)?;
+
+    let n0 = SporeNode::new(&p0)?;
+    let n1 = SporeNode::new(&p1)?;
+    let peer1 = n1.peer_id;
+
+    let mut m0 = n0.build_mycelium_with_profile(hypha::mycelium::NetProfile::Tcp)?;
+    let mut m1 = n1.build_mycelium_with_profile(hypha::mycelium::NetProfile::Tcp)?;
+    m1.listen_on("/ip4/127.0.0.1/tcp/0".parse::<Multiaddr>()?)?;
+
+    let mut port: Option<u16> = None;
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(2);
+    while port.is_none() && tokio::time::Instant::now() < deadline {
+        if let SwarmEvent::NewListenAddr { address, .. } = m1.swarm.select_next_some().await {
+            port = address.iter().find_map(|p| match p {
+                Protocol::Tcp(port) => Some(port),
+                _ => None,
+            });
+        }
+    }
+    let port = port.ok_or("node1 did not obtain listen port")?;
+
+    let bootstrap: Multiaddr = format!("/dns4/localhost/tcp/{port}/p2p/{peer1}").parse()?;
+    m0.add_bootstrap(bootstrap.clone());
+    m0.add_bootstrap(bootstrap);
+    assert_eq!(
+        m0.bootstrap.len(),
+        1,
+        "duplicate bootstrap entries are ignored"
+    );
+    assert_eq!(m0.redial_bootstrap(), 1);
+
+    let mut connected = false;
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(3);
+    while !connected && tokio::time::Instant::now() < deadline {
+        tokio::select! {
+            ev = m0.swarm.select_next_some() => {
+                if let SwarmEvent::ConnectionEstablished { peer_id, .. } = ev {
+                    connected = peer_id == peer1;
+                }
+            }
+            ev = m1.swarm.select_next_some() => { let _ = ev; }
+            _ = tokio::time::sleep(std::time::Duration::from_millis(10)) => {}
+        }
+    }
+    assert!(connected, "dns bootstrap dial did not connect");
+    assert_eq!(
+        m0.redial_bootstrap(),
+        0,
+        "connected bootstrap peers are not re-dialed"
+    );
+    Ok(())
+}
@@ -10,6 +10,12 @@ fn test_task_diffusion_mechanics() {
         reach_intensity: 1.0,
         source_id: "source".to_string(),
         auth_token: None,
+        public_result: false,
+        tenant: None,
+        memo_key: None,
+        geohash: None,
+        target: None,
+        expected_output_bytes: None,
     };
 
     // Case 1: Healthy neighbor, low pressure
@@ -1,4 +1,4 @@
-use hypha::SporeNode;
+use hypha::{hibernate, SporeNode};
 use tempfile::tempdir;
 
 #[test]
@@ -32,5 +32,95 @@ fn test_identity_and_messages_persist_across_restart() -> Result<(), Box<dyn std
     let bytes = n1.db.get("msg_m1")?.ok_or("expected msg_m1 value")?;
     assert_eq!(bytes.as_ref(), b"hello");
 
+    // Reads go through the message cache: cold after the restart, then hot.
+    assert_eq!(n1.message("m1")?.as_deref(), Some(&b"hello"[..]));
+    n1.message("m1")?;
+    let stats = n1.messages.stats();
+    assert_eq!((stats.misses, stats.hits), (1, 1));
+
+    Ok(())
+}
+
+#[test]
+fn test_mesh_membership_warm_starts_after_restart() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp = tempdir()?;
+    let p = tmp.path().join("node");
+    std::fs::create_dir_all(&p)?;
+
+    let n0 = SporeNode::new(&p)?;
+    let members = {
+        let mut mesh = n0.mesh.lock().unwrap();
+        for i in 0..5 {
+            let id = format!("peer-{i}");
+            mesh.peer_connected(&id);
+            mesh.update_peer_score(&id, 0.9);
+        }
+        let _ = mesh.heartbeat();
+        let mut members: Vec<String> = mesh.mesh_peers.iter().cloned().collect();
+        members.sort();
+        members
+    };
+    assert!(!members.is_empty());
+    n0.save_mesh_state()?;
+    drop(n0);
+
+    let n1 = SporeNode::new(&p)?;
+    let mesh = n1.mesh.lock().unwrap();
+    let mut restored: Vec<String> = mesh.mesh_peers.iter().cloned().collect();
+    restored.sort();
+    assert_eq!(restored, members);
+    assert_eq!(mesh.known_peers.len(), 5);
+    assert!(mesh.known_peers.values().all(|peer| !peer.connected));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_hibernation_snapshot_restores_shared_state_once(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tmp = tempdir()?;
+    let p = tmp.path().join("node");
+    std::fs::create_dir_all(&p)?;
+
+    let mut n0 = SporeNode::new(&p)?;
+    {
+        let mut mesh = n0.mesh.lock().unwrap();
+        mesh.peer_connected("peer-a");
+        mesh.mesh_peers.insert("peer-a".to_string());
+    }
+    n0.shared_state.lock().unwrap().set("zone/roof", "ok");
+    let mut mycelium = n0.build_mycelium()?;
+    assert!(n0.hibernate(&mut mycelium)? > 0);
+    drop(mycelium);
+    drop(n0);
+
+    let n1 = SporeNode::new(&p)?;
+    assert_eq!(
+        n1.shared_state.lock().unwrap().get("zone/roof").as_deref(),
+        Some("ok")
+    );
+    assert!(n1.mesh.lock().unwrap().mesh_peers.contains("peer-a"));
+    assert!(n1.db.get(hibernate::HIBERNATION_KEY)?.is_none());
+    drop(n1);
+
+    // Read once: the next start finds no snapshot and no shared state.
+    let n2 = SporeNode::new(&p)?;
+    assert_eq!(n2.shared_state.lock().unwrap().get("zone/roof"), None);
+    Ok(())
+}
+
+#[test]
+fn test_unreadable_message_sequence_fails_open() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp = tempdir()?;
+    let p = tmp.path().join("node");
+    std::fs::create_dir_all(&p)?;
+
+    let n0 = SporeNode::new(&p)?;
+    n0.db.insert("sequence_next", b"not a number")?;
+    drop(n0);
+
+    let err = SporeNode::new(&p)
+        .err()
+        .ok_or("opened with an unreadable sequence")?;
+    assert!(err.to_string().contains("message sequence"), "{err}");
     Ok(())
 }
@@ -29,6 +29,12 @@ proptest! {
             reach_intensity,
             source_id,
             auth_token: token,
+            public_result: false,
+            tenant: None,
+            memo_key: None,
+            geohash: None,
+            target: None,
+            expected_output_bytes: None,
         };
 
         let mut known_bids = vec![
@@ -37,6 +43,7 @@ proptest! {
                 bidder_id: "other".to_string(),
                 energy_score,
                 cost_mah: cost,
+                price: None,
             }
         ];
 
@@ -58,6 +65,12 @@ proptest! {
             reach_intensity: reach,
             source_id: "s".into(),
             auth_token: None,
+            public_result: false,
+            tenant: None,
+            memo_key: None,
+            geohash: None,
+            target: None,
+            expected_output_bytes: None,
         };
 
         let _new_reach = task.diffuse(conductivity, neighbor_energy, neighbor_pressure);
@@ -0,0 +1,50 @@
+use hypha::config::ConfigSection;
+use hypha::core::Capability;
+use hypha::mycelium::TopicKind;
+use hypha::shard::{shard_of, ShardKey, ShardPlan};
+use hypha::{topic, SporeNode};
+use std::time::Instant;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn resharding_joins_the_new_shards_and_drains_the_old(
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tmp = tempdir()?;
+    let mut node = SporeNode::new(tmp.path())?;
+    node.add_capability(Capability::Compute(100));
+    let mut mycelium = node.build_mycelium()?;
+    mycelium.subscribe_all()?;
+
+    let mut config = node.config(&mycelium);
+    config.shards = ShardPlan::new(8, ShardKey::Capability);
+    assert_eq!(
+        node.apply_config(&mut mycelium, config.clone())?,
+        vec![ConfigSection::Shards]
+    );
+    config.shards = ShardPlan::new(32, ShardKey::Capability);
+    node.apply_config(&mut mycelium, config)?;
+
+    let compute = Capability::Compute(100);
+    let wanted = node
+        .task_shards
+        .wanted(&[&compute], &[], None, Instant::now());
+    let (old, new) = (shard_of("compute", 8), shard_of("compute", 32));
+    assert!(wanted.contains(&old) && wanted.contains(&new));
+    assert!(mycelium.join_task_shards(&wanted)?);
+
+    let shard = mycelium.task_shard_topic(new);
+    assert!(mycelium
+        .subscribed_topic_names()
+        .contains(&format!("hypha_task_stream.{new}")));
+    assert_eq!(mycelium.topic_kind(&shard.hash()), Some(TopicKind::Task));
+    assert!(mycelium.carries(topic::TASKS, &shard.hash()));
+
+    // Leaving the task topic leaves its shards too.
+    let without_tasks: Vec<TopicKind> = TopicKind::ALL
+        .into_iter()
+        .filter(|kind| *kind != TopicKind::Task)
+        .collect();
+    mycelium.apply_topics(&without_tasks)?;
+    assert!(mycelium.task_shards.is_empty());
+    Ok(())
+}
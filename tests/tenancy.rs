@@ -0,0 +1,97 @@
+use ed25519_dalek::SigningKey;
+use hypha::auth::{peer_id_of, Delegation};
+use hypha::tenant::Tenant;
+use hypha::{Capability, MockMetabolism, SporeNode, Task};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::{tempdir, TempDir};
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+fn gateway(farm_root: &SigningKey) -> (TempDir, SporeNode) {
+    let tmp = tempdir().unwrap();
+    let metabolism = Arc::new(Mutex::new(MockMetabolism::new(1.0, true)));
+    let mut node = SporeNode::new_with_metabolism(tmp.path(), metabolism).unwrap();
+    node.add_tenant(
+        "farm",
+        Tenant::new("farm", vec![Capability::Compute(10)])
+            .with_trusted_issuers(vec![peer_id_of(farm_root).to_string()]),
+    );
+    node.add_tenant("city", Tenant::new("city", vec![Capability::Compute(10)]));
+    (tmp, node)
+}
+
+fn task(id: &str, tenant: Option<&str>, token: &Delegation) -> Task {
+    let task = Task::new(
+        id.to_string(),
+        Capability::Compute(5),
+        1,
+        "publisher".into(),
+    )
+    .with_auth(token.encode());
+    match tenant {
+        Some(tenant) => task.with_tenant(tenant),
+        None => task,
+    }
+}
+
+#[test]
+fn tenant_grants_stay_within_their_tenant() {
+    let farm_root = SigningKey::from_bytes(&[9; 32]);
+    let (_tmp, node) = gateway(&farm_root);
+    let token = Delegation::mint_for_tenant(
+        &farm_root,
+        &node.peer_id,
+        Capability::Compute(10),
+        "farm",
+        hypha::auth::unix_now(),
+        HOUR,
+        None,
+    )
+    .unwrap();
+
+    let mut bids = Vec::new();
+    assert!(node
+        .process_task_bundle(&task("farm-1", Some("farm"), &token), &mut bids)
+        .is_some());
+    assert!(node
+        .process_task_bundle(&task("city-1", Some("city"), &token), &mut bids)
+        .is_none());
+    assert!(node
+        .process_task_bundle(&task("node-1", None, &token), &mut bids)
+        .is_none());
+    assert!(node
+        .process_task_bundle(&task("zoo-1", Some("zoo"), &token), &mut bids)
+        .is_none());
+
+    let farm = node.metrics.lock().unwrap().tenant("farm");
+    assert_eq!((farm.tasks_authorized, farm.tasks_rejected), (1, 0));
+    assert_eq!(
+        node.metrics.lock().unwrap().tenant("city").tasks_rejected,
+        1
+    );
+
+    let farm_entries = node.audit.entries_for_tenant(Some("farm")).unwrap();
+    assert_eq!(farm_entries.len(), 1);
+    assert_eq!(farm_entries[0].record.task_id.as_deref(), Some("farm-1"));
+    assert_eq!(node.audit.entries_for_tenant(None).unwrap().len(), 1);
+    assert_eq!(node.audit.verify().unwrap(), 4);
+}
+
+#[test]
+fn tenants_keep_separate_state_and_capabilities() {
+    let farm_root = SigningKey::from_bytes(&[9; 32]);
+    let (_tmp, node) = gateway(&farm_root);
+
+    let farm = node.shared_state_of(Some("farm")).unwrap();
+    let own = node.shared_state_of(None).unwrap();
+    assert!(!Arc::ptr_eq(&farm, &own));
+    assert!(node.shared_state_of(Some("zoo")).is_none());
+
+    // The node itself has no compute; only its tenants do.
+    let untenanted = Task::new("t".into(), Capability::Compute(5), 1, "p".into());
+    assert!(node.evaluate_task_with_quorum(&untenanted, 0).is_none());
+    assert!(node
+        .evaluate_task_with_quorum(&untenanted.clone().with_tenant("city"), 0)
+        .is_some());
+}
@@ -1,4 +1,5 @@
 use hypha::mesh::{MeshConfig, TopicMesh};
+use std::time::Duration;
 
 #[test]
 fn test_update_peer_score_inserts_peer() {
@@ -111,3 +112,57 @@ fn test_heartbeat_never_panics_on_nan_energy_scores() {
     let _ = mesh.heartbeat();
     assert!(mesh.mesh_peers.len() <= mesh.config.d_high);
 }
+
+#[test]
+fn test_heartbeat_grafts_best_of_large_peer_set() {
+    let mut mesh = TopicMesh::new("t".to_string(), MeshConfig::default());
+    for i in 0..5_000 {
+        mesh.add_peer(format!("peer-{i}"), (i % 1_000) as f32 / 1_000.0);
+    }
+
+    let _ = mesh.heartbeat();
+
+    let score = |id: &String| mesh.known_peers[id].score();
+    let weakest_in_mesh = mesh.mesh_peers.iter().map(score).fold(f32::MAX, f32::min);
+    let best_outside = mesh
+        .known_peers
+        .keys()
+        .filter(|id| !mesh.mesh_peers.contains(*id))
+        .map(score)
+        .fold(f32::MIN, f32::max);
+    assert!(mesh.mesh_peers.len() >= mesh.config.d_low);
+    assert!(weakest_in_mesh >= best_outside);
+}
+
+#[test]
+fn test_known_peers_cap_evicts_least_useful_outside_mesh() {
+    let config = MeshConfig {
+        max_known_peers: Some(3),
+        ..MeshConfig::default()
+    };
+    let mut mesh = TopicMesh::new("t".to_string(), config);
+    mesh.add_peer("member".to_string(), 0.0);
+    mesh.mesh_peers.insert("member".to_string());
+    mesh.add_peer("strong".to_string(), 0.9);
+    mesh.add_peer("weak".to_string(), 0.1);
+
+    // The lowest scorer outside the mesh makes room; the newcomer stays.
+    mesh.add_peer("newcomer".to_string(), 0.0);
+    assert!(!mesh.known_peers.contains_key("weak"));
+    assert!(mesh.known_peers.contains_key("member"));
+    assert!(mesh.known_peers.contains_key("newcomer"));
+
+    // On equal scores the longest unseen goes first.
+    mesh.update_peer_score("newcomer", 0.9);
+    mesh.known_peers.get_mut("strong").unwrap().last_seen -= Duration::from_secs(60);
+    mesh.peer_connected("late");
+    assert!(!mesh.known_peers.contains_key("strong"));
+    assert_eq!(mesh.known_peers.len(), 3);
+    assert_eq!(mesh.stats().evicted_count, 2);
+
+    // A smaller cap applies on the next heartbeat.
+    mesh.config.max_known_peers = Some(1);
+    let _ = mesh.heartbeat();
+    assert_eq!(mesh.known_peers.keys().collect::<Vec<_>>(), vec!["member"]);
+    assert_eq!(mesh.stats().evicted_count, 4);
+}
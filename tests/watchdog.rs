@@ -0,0 +1,80 @@
+use hypha::audit::{token_digest, AuditRecord, Decision};
+use hypha::core::{Capability, Metabolism, MockMetabolism};
+use hypha::events::NodeEvent;
+use hypha::watchdog::WatchdogConfig;
+use hypha::SporeNode;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+fn record(decision: Decision) -> AuditRecord {
+    AuditRecord {
+        timestamp: 1,
+        requester: Some("peer-a".to_string()),
+        task_id: Some("t1".to_string()),
+        tenant: None,
+        required: Capability::Compute(1),
+        token_sha256: token_digest("token"),
+        decision,
+        reason: None,
+    }
+}
+
+/// A loop deadlocked in a synchronous call does not take its supervisor
+/// down with it: the loop is abandoned, and once the successor deadlocks on
+/// the same lock the supervisor gives up. The audit chain the abandoned
+/// loops shared with their successors still verifies.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn a_deadlocked_run_loop_is_abandoned() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp = tempfile::tempdir()?;
+    let metabolism: Arc<Mutex<dyn Metabolism>> =
+        Arc::new(Mutex::new(MockMetabolism::new(0.9, true)));
+    let mut node = SporeNode::new_with_metabolism(tmp.path(), metabolism.clone())?;
+    node.watchdog = WatchdogConfig {
+        stall_after: Duration::from_millis(300),
+        check_every: Duration::from_millis(50),
+        max_restarts: 1,
+    };
+    let link = node.link();
+    let audit = node.audit.clone();
+    let mut events = link.subscribe();
+    audit.record(record(Decision::Accepted))?;
+    let mut mycelium = node.build_mycelium()?;
+    mycelium.listen_on("/ip4/127.0.0.1/tcp/0".parse()?)?;
+
+    // Every heartbeat reads the metabolism; hold it until the test ends.
+    let (locked_tx, locked_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let holder = std::thread::spawn(move || {
+        let _guard = metabolism.lock().unwrap();
+        locked_tx.send(()).unwrap();
+        let _ = release_rx.recv();
+    });
+    locked_rx.recv()?;
+
+    let supervised = tokio::time::timeout(
+        Duration::from_secs(10),
+        node.run_supervised(
+            mycelium,
+            Duration::from_secs(60),
+            Duration::from_millis(50),
+            0.05,
+            false,
+            None,
+        ),
+    )
+    .await;
+    release_tx.send(())?;
+    holder.join().unwrap();
+
+    let result = supervised.expect("supervisor stayed responsive");
+    assert!(result.is_err(), "supervisor gave up after max_restarts");
+    let mut restarts = 0;
+    while let Ok(event) = events.try_recv() {
+        restarts += matches!(event, NodeEvent::NodeRestarted { .. }) as u32;
+    }
+    assert_eq!(restarts, 1);
+
+    audit.record(record(Decision::Rejected))?;
+    assert_eq!(audit.verify()?, 2);
+    Ok(())
+}
@@ -0,0 +1,110 @@
+//! Wire-format compatibility against committed fixtures.
+//!
+//! Set `HYPHA_BLESS=1` to write missing fixtures for the current envelope
+//! version. Existing fixtures are never overwritten: an encoding change needs
+//! a new `ENVELOPE_VERSION`.
+
+use hypha::core::Task;
+use hypha::trace::{self, Trace};
+use hypha::wire::{self, ENVELOPE_VERSION};
+use hypha::zone::{self, BridgeTag};
+use hypha::{fixtures, schema};
+use std::path::{Path, PathBuf};
+
+fn version_dir(version: u8) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("tests/fixtures/wire/v{version}"))
+}
+
+#[test]
+fn current_encodings_match_their_fixtures() {
+    let bless = std::env::var_os("HYPHA_BLESS").is_some();
+    let dir = version_dir(ENVELOPE_VERSION);
+    for fixture in fixtures::all() {
+        let path = dir.join(format!("{}.json", fixture.name));
+        let committed = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(_) if bless => {
+                std::fs::create_dir_all(&dir).unwrap();
+                let mut bytes = fixture.encoded.clone();
+                bytes.push(b'\n');
+                std::fs::write(&path, bytes).unwrap();
+                continue;
+            }
+            Err(e) => panic!("missing fixture {}: {e}", path.display()),
+        };
+        assert_eq!(
+            String::from_utf8_lossy(committed.trim_ascii_end()),
+            String::from_utf8_lossy(&fixture.encoded),
+            "{} encodes differently than its v{ENVELOPE_VERSION} fixture; bump \
+             ENVELOPE_VERSION and bless new fixtures instead of editing old ones",
+            fixture.name
+        );
+    }
+}
+
+#[test]
+fn fixtures_of_every_version_still_decode() {
+    for version in 0..=ENVELOPE_VERSION {
+        let dir = version_dir(version);
+        for fixture in fixtures::all() {
+            let path = dir.join(format!("{}.json", fixture.name));
+            let Ok(bytes) = std::fs::read(&path) else {
+                // Types added after this version have no fixture for it.
+                continue;
+            };
+            let decoded = (fixture.decode)(&bytes)
+                .unwrap_or_else(|e| panic!("{} no longer decodes: {e}", path.display()));
+            assert_eq!(decoded, version, "{}", path.display());
+        }
+    }
+}
+
+#[test]
+fn every_wire_type_has_a_fixture() {
+    let names: Vec<&str> = fixtures::all().iter().map(|f| f.name).collect();
+    for name in [
+        "task",
+        "bid",
+        "energy_status",
+        "mesh_control",
+        "sync_message",
+        "spike",
+    ] {
+        assert!(names.contains(&name), "no fixture for {name}");
+    }
+}
+
+#[test]
+fn newer_messages_survive_an_older_relay_intact() {
+    let task = fixtures::all()
+        .into_iter()
+        .find(|f| f.name == "task")
+        .unwrap();
+    let mut future: serde_json::Value = serde_json::from_slice(&task.encoded).unwrap();
+    future["v"] = (ENVELOPE_VERSION + 1).into();
+    future["lineage"] = serde_json::json!({ "hops": 2 });
+    future["body"]["deadline_ms"] = 1_700_000_000u64.into();
+    let sent = serde_json::to_vec(&future).unwrap();
+
+    // A relay of today's version stamps its hop and bridges the message.
+    let traced = trace::restamp(&sent, Trace::new("relay", 1)).unwrap();
+    let tag = BridgeTag {
+        origin_id: "origin".to_string(),
+        zones: vec!["north".to_string(), "south".to_string()],
+    };
+    let relayed = zone::restamp(&traced, tag).unwrap();
+
+    let mut received: serde_json::Value = serde_json::from_slice(&relayed).unwrap();
+    let stamps = received.as_object_mut().unwrap();
+    assert!(stamps.remove("trace").is_some());
+    assert!(stamps.remove("bridge").is_some());
+    assert_eq!(received, future);
+
+    // The relay itself still reads what it knows.
+    let envelope = wire::decode::<Task>(&relayed).unwrap();
+    assert_eq!(envelope.body.id, "fixture-task");
+    assert_eq!(
+        schema::TASK.unknown_fields(&future["body"]),
+        vec!["deadline_ms"]
+    );
+}